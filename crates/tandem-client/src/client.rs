@@ -0,0 +1,459 @@
+//! The async [`TandemClient`].
+//!
+//! capnp-rpc objects are `!Send`, so the connection lives on a thread of its
+//! own (a current-thread runtime with a `LocalSet`) and the client hands it
+//! requests over a channel. Every method is therefore `Send` and callable
+//! from any tokio runtime, and calls from several tasks share the one
+//! connection and run concurrently.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use capnp::capability::Promise;
+use capnp_rpc::{rpc_twoparty_capnp, twoparty, RpcSystem};
+use tokio::sync::{mpsc, oneshot};
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+use crate::info::{RepoCapability, RepoInfo};
+use crate::limits;
+use crate::tandem_capnp::{head_watcher, store};
+use crate::transport::connect_stream;
+
+/// Kinds of content-addressed objects a server stores.
+///
+/// Files and symlink targets are raw bytes; commits, trees, and copies are
+/// jj's `simple_store` protobuf encodings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    File,
+    Symlink,
+    Copy,
+}
+
+impl ObjectKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tree => "tree",
+            ObjectKind::File => "file",
+            ObjectKind::Symlink => "symlink",
+            ObjectKind::Copy => "copy",
+        }
+    }
+
+    fn to_capnp(self) -> crate::tandem_capnp::ObjectKind {
+        match self {
+            ObjectKind::Commit => crate::tandem_capnp::ObjectKind::Commit,
+            ObjectKind::Tree => crate::tandem_capnp::ObjectKind::Tree,
+            ObjectKind::File => crate::tandem_capnp::ObjectKind::File,
+            ObjectKind::Symlink => crate::tandem_capnp::ObjectKind::Symlink,
+            ObjectKind::Copy => crate::tandem_capnp::ObjectKind::Copy,
+        }
+    }
+}
+
+/// The server's op heads (`getHeads`).
+#[derive(Debug, Clone)]
+pub struct HeadsState {
+    pub heads: Vec<Vec<u8>>,
+    pub version: u64,
+    pub workspace_heads: BTreeMap<String, Vec<u8>>,
+}
+
+impl HeadsState {
+    /// Decode a `getHeads` reply.
+    pub fn read(reader: store::get_heads_results::Reader<'_>) -> capnp::Result<Self> {
+        let version = reader.get_version();
+        let heads_reader = reader.get_heads()?;
+        let mut heads = Vec::with_capacity(heads_reader.len() as usize);
+        for i in 0..heads_reader.len() {
+            heads.push(heads_reader.get(i)?.to_vec());
+        }
+
+        let workspace_heads_reader = reader.get_workspace_heads()?;
+        let mut workspace_heads = BTreeMap::new();
+        for i in 0..workspace_heads_reader.len() {
+            let entry = workspace_heads_reader.get(i);
+            let workspace_id = entry.get_workspace_id()?.to_string()?;
+            let op_id = entry.get_commit_id()?.to_vec();
+            if !workspace_id.is_empty() && !op_id.is_empty() {
+                workspace_heads.insert(workspace_id, op_id);
+            }
+        }
+
+        Ok(HeadsState {
+            heads,
+            version,
+            workspace_heads,
+        })
+    }
+}
+
+/// One change of the server's op heads, as pushed to watchers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeadsChange {
+    pub version: u64,
+    /// Head operation ids.
+    pub heads: Vec<Vec<u8>>,
+    /// Tags on the new head operation.
+    pub tags: BTreeMap<String, String>,
+}
+
+/// What a [`HeadsWatch`] yields.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchEvent {
+    Changed(HeadsChange),
+    /// The server is shutting down gracefully; reconnecting after
+    /// `retry_after` should reach it again. Nothing follows this.
+    ShuttingDown {
+        retry_after: Duration,
+        message: String,
+    },
+}
+
+/// A live `watchHeads` subscription, from [`TandemClient::watch`].
+pub struct HeadsWatch {
+    events: mpsc::UnboundedReceiver<WatchEvent>,
+    /// Keeps the connection up while the watch is, even if the client that
+    /// started it is dropped.
+    _connection: mpsc::UnboundedSender<Request>,
+}
+
+impl HeadsWatch {
+    /// The next event, or `None` once the watch has ended: after a shutdown
+    /// notice, or when the connection dropped.
+    pub async fn next(&mut self) -> Option<WatchEvent> {
+        let event = self.events.recv().await?;
+        if matches!(event, WatchEvent::ShuttingDown { .. }) {
+            self.events.close();
+        }
+        Some(event)
+    }
+}
+
+type Reply<T> = oneshot::Sender<Result<T>>;
+
+enum Request {
+    GetObject {
+        kind: ObjectKind,
+        id: Vec<u8>,
+        reply: Reply<Vec<u8>>,
+    },
+    PutObject {
+        kind: ObjectKind,
+        data: Vec<u8>,
+        reply: Reply<Vec<u8>>,
+    },
+    GetHeads {
+        reply: Reply<HeadsState>,
+    },
+    Watch {
+        after_version: u64,
+        events: mpsc::UnboundedSender<WatchEvent>,
+        reply: Reply<()>,
+    },
+}
+
+/// An async connection to a tandem server.
+///
+/// Dropping the client closes the connection once every [`HeadsWatch`] it
+/// started is gone too.
+pub struct TandemClient {
+    requests: mpsc::UnboundedSender<Request>,
+    endpoint: String,
+    repo_info: RepoInfo,
+}
+
+impl std::fmt::Debug for TandemClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TandemClient")
+            .field("endpoint", &self.endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TandemClient {
+    /// Connect to the server at `endpoint` (`host:port`, `tcp://host:port`,
+    /// or `unix:///path`) and read its repo info.
+    pub async fn connect(endpoint: &str) -> Result<Self> {
+        let (requests, request_rx) = mpsc::unbounded_channel();
+        let (ready_tx, ready_rx) = oneshot::channel();
+        let thread_endpoint = endpoint.to_string();
+        std::thread::Builder::new()
+            .name("tandem-client".to_string())
+            .spawn(move || run_connection(&thread_endpoint, ready_tx, request_rx))
+            .context("spawn tandem client thread")?;
+        let repo_info = ready_rx
+            .await
+            .map_err(|_| anyhow!("tandem client thread exited"))??;
+        Ok(Self {
+            requests,
+            endpoint: endpoint.to_string(),
+            repo_info,
+        })
+    }
+
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The repo info the server sent when the client connected.
+    pub fn repo_info(&self) -> &RepoInfo {
+        &self.repo_info
+    }
+
+    pub fn supports_capability(&self, capability: RepoCapability) -> bool {
+        self.repo_info.capabilities.contains(&capability)
+    }
+
+    /// The stored bytes of object `id`.
+    pub async fn get_object(&self, kind: ObjectKind, id: &[u8]) -> Result<Vec<u8>> {
+        let id = id.to_vec();
+        self.call(|reply| Request::GetObject { kind, id, reply })
+            .await
+    }
+
+    /// Store an object and return its id. The server may normalize commits;
+    /// read one back to see what was stored.
+    pub async fn put_object(&self, kind: ObjectKind, data: Vec<u8>) -> Result<Vec<u8>> {
+        limits::check(&format!("{} object", kind.name()), data.len())?;
+        self.call(|reply| Request::PutObject { kind, data, reply })
+            .await
+    }
+
+    /// The current op heads and their version.
+    pub async fn get_heads(&self) -> Result<HeadsState> {
+        self.call(|reply| Request::GetHeads { reply }).await
+    }
+
+    /// Subscribe to head changes after `after_version`. A version older than
+    /// the server's current one (0 in particular) delivers the current heads
+    /// as the first event.
+    pub async fn watch(&self, after_version: u64) -> Result<HeadsWatch> {
+        if !self.supports_capability(RepoCapability::WatchHeads) {
+            anyhow::bail!("server {} does not support watching heads", self.endpoint);
+        }
+        let (events, event_rx) = mpsc::unbounded_channel();
+        self.call(|reply| Request::Watch {
+            after_version,
+            events,
+            reply,
+        })
+        .await?;
+        Ok(HeadsWatch {
+            events: event_rx,
+            _connection: self.requests.clone(),
+        })
+    }
+
+    async fn call<T>(&self, request: impl FnOnce(Reply<T>) -> Request) -> Result<T> {
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.requests
+            .send(request(reply_tx))
+            .map_err(|_| anyhow!("connection to {} is closed", self.endpoint))?;
+        reply_rx
+            .await
+            .map_err(|_| anyhow!("connection to {} is closed", self.endpoint))?
+    }
+}
+
+// ─── Connection thread ────────────────────────────────────────────────────────
+
+fn run_connection(
+    endpoint: &str,
+    ready: Reply<RepoInfo>,
+    mut requests: mpsc::UnboundedReceiver<Request>,
+) {
+    let rt = match tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+    {
+        Ok(rt) => rt,
+        Err(err) => {
+            let _ = ready.send(Err(err.into()));
+            return;
+        }
+    };
+    let local = tokio::task::LocalSet::new();
+    local.block_on(&rt, async move {
+        let client = match open(endpoint).await {
+            Ok((client, repo_info)) => {
+                if ready.send(Ok(repo_info)).is_err() {
+                    return;
+                }
+                client
+            }
+            Err(err) => {
+                let _ = ready.send(Err(err));
+                return;
+            }
+        };
+        while let Some(request) = requests.recv().await {
+            tokio::task::spawn_local(handle(client.clone(), request));
+        }
+    });
+}
+
+async fn open(endpoint: &str) -> Result<(store::Client, RepoInfo)> {
+    let stream = connect_stream(endpoint).await?;
+    let (reader, writer) = stream.into_split();
+    let network = twoparty::VatNetwork::new(
+        reader.compat(),
+        writer.compat_write(),
+        rpc_twoparty_capnp::Side::Client,
+        limits::reader_options(),
+    );
+    let mut rpc_system = RpcSystem::new(Box::new(network), None);
+    let client: store::Client = rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+    tokio::task::spawn_local(rpc_system);
+
+    let response = client
+        .get_repo_info_request()
+        .send()
+        .promise
+        .await
+        .with_context(|| format!("failed to read repo info from {endpoint}"))?;
+    let repo_info = RepoInfo::read(response.get()?.get_info()?)?;
+    Ok((client, repo_info))
+}
+
+async fn handle(client: store::Client, request: Request) {
+    match request {
+        Request::GetObject { kind, id, reply } => {
+            let _ = reply.send(get_object(&client, kind, &id).await);
+        }
+        Request::PutObject { kind, data, reply } => {
+            let _ = reply.send(put_object(&client, kind, &data).await);
+        }
+        Request::GetHeads { reply } => {
+            let _ = reply.send(get_heads(&client).await);
+        }
+        Request::Watch {
+            after_version,
+            events,
+            reply,
+        } => {
+            let _ = reply.send(watch(&client, after_version, events).await);
+        }
+    }
+}
+
+/// A remote error, with size-limit failures given their readable message.
+fn rpc_error(err: capnp::Error) -> anyhow::Error {
+    match limits::message_from_error(&err.extra) {
+        Some(message) => anyhow!("{message}"),
+        None => err.into(),
+    }
+}
+
+async fn get_object(client: &store::Client, kind: ObjectKind, id: &[u8]) -> Result<Vec<u8>> {
+    let mut request = client.get_object_request();
+    {
+        let mut params = request.get();
+        params.set_kind(kind.to_capnp());
+        params.set_id(id);
+    }
+    let response = request.send().promise.await.map_err(rpc_error)?;
+    Ok(response.get()?.get_data()?.to_vec())
+}
+
+async fn put_object(client: &store::Client, kind: ObjectKind, data: &[u8]) -> Result<Vec<u8>> {
+    let mut request = client.put_object_request();
+    {
+        let mut params = request.get();
+        params.set_kind(kind.to_capnp());
+        params.set_data(data);
+    }
+    let response = request.send().promise.await.map_err(rpc_error)?;
+    Ok(response.get()?.get_id()?.to_vec())
+}
+
+async fn get_heads(client: &store::Client) -> Result<HeadsState> {
+    let response = client
+        .get_heads_request()
+        .send()
+        .promise
+        .await
+        .map_err(rpc_error)?;
+    Ok(HeadsState::read(response.get()?)?)
+}
+
+async fn watch(
+    client: &store::Client,
+    after_version: u64,
+    events: mpsc::UnboundedSender<WatchEvent>,
+) -> Result<()> {
+    let watcher: head_watcher::Client = capnp_rpc::new_client(Watcher { events });
+    let mut request = client.watch_heads_request();
+    {
+        let mut params = request.get();
+        params.set_watcher(watcher);
+        params.set_after_version(after_version);
+    }
+    request.send().promise.await.map_err(rpc_error)?;
+    Ok(())
+}
+
+/// Forwards `HeadWatcher` callbacks to a [`HeadsWatch`].
+struct Watcher {
+    events: mpsc::UnboundedSender<WatchEvent>,
+}
+
+impl Watcher {
+    fn read_change(params: head_watcher::NotifyParams) -> capnp::Result<HeadsChange> {
+        let reader = params.get()?;
+        let heads_reader = reader.get_heads()?;
+        let mut heads = Vec::with_capacity(heads_reader.len() as usize);
+        for i in 0..heads_reader.len() {
+            heads.push(heads_reader.get(i)?.to_vec());
+        }
+        let mut tags = BTreeMap::new();
+        for tag in reader.get_tags()?.iter() {
+            tags.insert(tag.get_key()?.to_string()?, tag.get_value()?.to_string()?);
+        }
+        Ok(HeadsChange {
+            version: reader.get_version(),
+            heads,
+            tags,
+        })
+    }
+}
+
+impl head_watcher::Server for Watcher {
+    fn notify(
+        &mut self,
+        params: head_watcher::NotifyParams,
+        _results: head_watcher::NotifyResults,
+    ) -> Promise<(), capnp::Error> {
+        match Self::read_change(params) {
+            Ok(change) => {
+                let _ = self.events.send(WatchEvent::Changed(change));
+                Promise::ok(())
+            }
+            Err(e) => Promise::err(e),
+        }
+    }
+
+    fn shutting_down(
+        &mut self,
+        params: head_watcher::ShuttingDownParams,
+        _results: head_watcher::ShuttingDownResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = match params.get() {
+            Ok(r) => r,
+            Err(e) => return Promise::err(e),
+        };
+        let message = match reader.get_message() {
+            Ok(text) => text.to_string().unwrap_or_default(),
+            Err(e) => return Promise::err(e),
+        };
+        let _ = self.events.send(WatchEvent::ShuttingDown {
+            retry_after: Duration::from_millis(u64::from(reader.get_retry_after_ms())),
+            message,
+        });
+        Promise::ok(())
+    }
+}
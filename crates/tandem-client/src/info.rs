@@ -0,0 +1,270 @@
+//! What a server says about its repo: protocol version, store types, hash
+//! functions, root ids, and the optional capabilities it supports
+//! (`getRepoInfo`).
+
+use std::collections::BTreeSet;
+
+use crate::tandem_capnp::repo_info;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum RepoCapability {
+    WatchHeads,
+    HeadsSnapshot,
+    CopyTracking,
+    OperationTags,
+    HeadsHistory,
+    ConsistentSnapshot,
+    Migration,
+    ViewDeltas,
+    HeadsVersion,
+    HeadsSummaries,
+    TreePages,
+    CommitSearch,
+    ChangeLocks,
+    PathOverlaps,
+    MergePreview,
+    CommitGraph,
+    IdempotentHeadUpdates,
+}
+
+impl RepoCapability {
+    /// Every capability this build knows.
+    pub const ALL: &'static [RepoCapability] = &[
+        RepoCapability::WatchHeads,
+        RepoCapability::HeadsSnapshot,
+        RepoCapability::CopyTracking,
+        RepoCapability::OperationTags,
+        RepoCapability::HeadsHistory,
+        RepoCapability::ConsistentSnapshot,
+        RepoCapability::Migration,
+        RepoCapability::ViewDeltas,
+        RepoCapability::HeadsVersion,
+        RepoCapability::HeadsSummaries,
+        RepoCapability::TreePages,
+        RepoCapability::CommitSearch,
+        RepoCapability::ChangeLocks,
+        RepoCapability::PathOverlaps,
+        RepoCapability::MergePreview,
+        RepoCapability::CommitGraph,
+        RepoCapability::IdempotentHeadUpdates,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RepoCapability::WatchHeads => "watchHeads",
+            RepoCapability::HeadsSnapshot => "headsSnapshot",
+            RepoCapability::CopyTracking => "copyTracking",
+            RepoCapability::OperationTags => "operationTags",
+            RepoCapability::HeadsHistory => "headsHistory",
+            RepoCapability::ConsistentSnapshot => "consistentSnapshot",
+            RepoCapability::Migration => "migration",
+            RepoCapability::ViewDeltas => "viewDeltas",
+            RepoCapability::HeadsVersion => "headsVersion",
+            RepoCapability::HeadsSummaries => "headsSummaries",
+            RepoCapability::TreePages => "treePages",
+            RepoCapability::CommitSearch => "commitSearch",
+            RepoCapability::ChangeLocks => "changeLocks",
+            RepoCapability::PathOverlaps => "pathOverlaps",
+            RepoCapability::MergePreview => "mergePreview",
+            RepoCapability::CommitGraph => "commitGraph",
+            RepoCapability::IdempotentHeadUpdates => "idempotentHeadUpdates",
+        }
+    }
+
+    /// What the capability gives a client, for messages aimed at users.
+    pub fn describe(self) -> &'static str {
+        match self {
+            RepoCapability::WatchHeads => "live head notifications",
+            RepoCapability::HeadsSnapshot => "head snapshots",
+            RepoCapability::CopyTracking => "copy tracking",
+            RepoCapability::OperationTags => "operation tags",
+            RepoCapability::HeadsHistory => "heads history",
+            RepoCapability::ConsistentSnapshot => "consistent snapshots",
+            RepoCapability::Migration => "migration",
+            RepoCapability::ViewDeltas => "view deltas",
+            RepoCapability::HeadsVersion => "heads version polling",
+            RepoCapability::HeadsSummaries => "heads change summaries",
+            RepoCapability::TreePages => "paged tree reads",
+            RepoCapability::CommitSearch => "commit search",
+            RepoCapability::ChangeLocks => "change locks",
+            RepoCapability::PathOverlaps => "path overlap warnings",
+            RepoCapability::MergePreview => "merge previews",
+            RepoCapability::CommitGraph => "bulk commit reads for index builds",
+            RepoCapability::IdempotentHeadUpdates => "safe retries of head updates",
+        }
+    }
+
+    pub fn from_capnp(cap: crate::tandem_capnp::Capability) -> Self {
+        match cap {
+            crate::tandem_capnp::Capability::WatchHeads => RepoCapability::WatchHeads,
+            crate::tandem_capnp::Capability::HeadsSnapshot => RepoCapability::HeadsSnapshot,
+            crate::tandem_capnp::Capability::CopyTracking => RepoCapability::CopyTracking,
+            crate::tandem_capnp::Capability::OperationTags => RepoCapability::OperationTags,
+            crate::tandem_capnp::Capability::HeadsHistory => RepoCapability::HeadsHistory,
+            crate::tandem_capnp::Capability::ConsistentSnapshot => {
+                RepoCapability::ConsistentSnapshot
+            }
+            crate::tandem_capnp::Capability::Migration => RepoCapability::Migration,
+            crate::tandem_capnp::Capability::ViewDeltas => RepoCapability::ViewDeltas,
+            crate::tandem_capnp::Capability::HeadsVersion => RepoCapability::HeadsVersion,
+            crate::tandem_capnp::Capability::HeadsSummaries => RepoCapability::HeadsSummaries,
+            crate::tandem_capnp::Capability::TreePages => RepoCapability::TreePages,
+            crate::tandem_capnp::Capability::CommitSearch => RepoCapability::CommitSearch,
+            crate::tandem_capnp::Capability::ChangeLocks => RepoCapability::ChangeLocks,
+            crate::tandem_capnp::Capability::PathOverlaps => RepoCapability::PathOverlaps,
+            crate::tandem_capnp::Capability::MergePreview => RepoCapability::MergePreview,
+            crate::tandem_capnp::Capability::CommitGraph => RepoCapability::CommitGraph,
+            crate::tandem_capnp::Capability::IdempotentHeadUpdates => {
+                RepoCapability::IdempotentHeadUpdates
+            }
+        }
+    }
+}
+
+/// A hash function behind a repo's ids (`RepoInfo.commitHash` and
+/// `RepoInfo.operationHash`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// Git's object ids in a SHA-1 repo.
+    Sha1,
+    /// Git's object ids in a SHA-256 repo (`extensions.objectFormat`).
+    Sha256,
+    /// jj's operation and view ids.
+    Blake2b512,
+}
+
+impl HashAlgorithm {
+    /// What a server advertises for commit ids when it leaves `commitHash`
+    /// empty (servers from before the field).
+    pub const DEFAULT_COMMIT: HashAlgorithm = HashAlgorithm::Sha1;
+    /// What a server advertises for operation ids when it leaves
+    /// `operationHash` empty.
+    pub const DEFAULT_OPERATION: HashAlgorithm = HashAlgorithm::Blake2b512;
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha1 => "sha1",
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake2b512 => "blake2b-512",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sha1" => Some(HashAlgorithm::Sha1),
+            "sha256" => Some(HashAlgorithm::Sha256),
+            "blake2b-512" => Some(HashAlgorithm::Blake2b512),
+            _ => None,
+        }
+    }
+
+    /// Bytes in a digest.
+    pub fn digest_len(self) -> usize {
+        match self {
+            HashAlgorithm::Sha1 => 20,
+            HashAlgorithm::Sha256 => 32,
+            HashAlgorithm::Blake2b512 => 64,
+        }
+    }
+
+    /// The git object format whose ids are `len` bytes long.
+    pub fn for_git_id_length(len: usize) -> Option<Self> {
+        [HashAlgorithm::Sha1, HashAlgorithm::Sha256]
+            .into_iter()
+            .find(|algorithm| algorithm.digest_len() == len)
+    }
+}
+
+/// A server's answer to `getRepoInfo`.
+#[derive(Debug, Clone)]
+pub struct RepoInfo {
+    pub protocol_major: u16,
+    pub protocol_minor: u16,
+    /// jj-lib version the server was built against; `None` from servers that
+    /// predate `tandemVersion`.
+    pub jj_version: Option<String>,
+    /// Version of tandem the server runs, when it says.
+    pub tandem_version: Option<String>,
+    pub backend_name: String,
+    pub op_store_name: String,
+    pub commit_id_length: usize,
+    pub change_id_length: usize,
+    /// Hash behind commit, tree, and file ids, as advertised (`sha1` when the
+    /// server did not say); see [`HashAlgorithm`].
+    pub commit_hash: String,
+    /// Hash behind operation and view ids (`blake2b-512` when the server did
+    /// not say).
+    pub operation_hash: String,
+    pub root_commit_id: Vec<u8>,
+    pub root_change_id: Vec<u8>,
+    pub empty_tree_id: Vec<u8>,
+    pub root_operation_id: Vec<u8>,
+    pub capabilities: BTreeSet<RepoCapability>,
+    /// Set when the dialed server pointed us elsewhere: the address this
+    /// info (and the connection) actually came from.
+    pub redirect: Option<String>,
+    /// Git URL the server advertises as a mirror of its repo.
+    pub git_remote: Option<String>,
+    /// Field numbers of jj's operation and view protos on the server
+    /// (`opSchema`); empty when the server did not say.
+    pub op_schema: String,
+    /// Repo-wide `snapshot.auto-track` fileset that `tandem init` writes into
+    /// new workspaces.
+    pub snapshot_auto_track: Option<String>,
+}
+
+impl RepoInfo {
+    /// Decode the `RepoInfo` struct from a `getRepoInfo` reply.
+    pub fn read(info: repo_info::Reader<'_>) -> capnp::Result<Self> {
+        let mut capabilities = BTreeSet::new();
+        let caps_reader = info.get_capabilities()?;
+        for i in 0..caps_reader.len() {
+            capabilities.insert(RepoCapability::from_capnp(caps_reader.get(i)?));
+        }
+
+        // Older servers put their own version in `jjVersion`.
+        let tandem_version =
+            Some(info.get_tandem_version()?.to_string()?).filter(|version| !version.is_empty());
+        let jj_version = match tandem_version {
+            Some(_) => Some(info.get_jj_version()?.to_string()?),
+            None => None,
+        };
+
+        Ok(RepoInfo {
+            protocol_major: info.get_protocol_major(),
+            protocol_minor: info.get_protocol_minor(),
+            jj_version,
+            tandem_version,
+            backend_name: info.get_backend_name()?.to_string()?,
+            op_store_name: info.get_op_store_name()?.to_string()?,
+            commit_id_length: info.get_commit_id_length() as usize,
+            change_id_length: info.get_change_id_length() as usize,
+            commit_hash: advertised_or(
+                info.get_commit_hash()?.to_string()?,
+                HashAlgorithm::DEFAULT_COMMIT,
+            ),
+            operation_hash: advertised_or(
+                info.get_operation_hash()?.to_string()?,
+                HashAlgorithm::DEFAULT_OPERATION,
+            ),
+            root_commit_id: info.get_root_commit_id()?.to_vec(),
+            root_change_id: info.get_root_change_id()?.to_vec(),
+            empty_tree_id: info.get_empty_tree_id()?.to_vec(),
+            root_operation_id: info.get_root_operation_id()?.to_vec(),
+            capabilities,
+            redirect: Some(info.get_redirect()?.to_string()?).filter(|addr| !addr.is_empty()),
+            git_remote: Some(info.get_git_remote()?.to_string()?).filter(|url| !url.is_empty()),
+            op_schema: info.get_op_schema()?.to_string()?,
+            snapshot_auto_track: Some(info.get_snapshot_auto_track()?.to_string()?)
+                .filter(|fileset| !fileset.is_empty()),
+        })
+    }
+}
+
+fn advertised_or(name: String, default: HashAlgorithm) -> String {
+    if name.is_empty() {
+        default.as_str().to_string()
+    } else {
+        name
+    }
+}
@@ -0,0 +1,39 @@
+//! tandem-client — async client for a tandem server.
+//!
+//! Bots and dashboards can read heads, move objects, and follow head changes
+//! without linking jj-lib or speaking Cap'n Proto:
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! use tandem_client::{ObjectKind, TandemClient, WatchEvent};
+//!
+//! let client = TandemClient::connect("127.0.0.1:13013").await?;
+//! let heads = client.get_heads().await?;
+//! println!("version {}: {} head(s)", heads.version, heads.heads.len());
+//!
+//! let id = client.put_object(ObjectKind::File, b"hello\n".to_vec()).await?;
+//! assert_eq!(client.get_object(ObjectKind::File, &id).await?, b"hello\n");
+//!
+//! let mut watch = client.watch(heads.version).await?;
+//! while let Some(WatchEvent::Changed(change)) = watch.next().await {
+//!     println!("heads moved to version {}", change.version);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The schema bindings ([`tandem_capnp`]), message limits, and transports are
+//! public too; `tandem-jj-store` and the `tandem` binary build on them.
+
+#[allow(unused_parens, dead_code)]
+pub mod tandem_capnp {
+    include!(concat!(env!("OUT_DIR"), "/tandem_capnp.rs"));
+}
+
+mod client;
+mod info;
+pub mod limits;
+pub mod transport;
+
+pub use client::{HeadsChange, HeadsState, HeadsWatch, ObjectKind, TandemClient, WatchEvent};
+pub use info::{HashAlgorithm, RepoCapability, RepoInfo};
@@ -0,0 +1,135 @@
+//! Cap'n Proto message size limits.
+//!
+//! capnp readers refuse messages over 8M words (64 MiB) by default, and a
+//! huge tree, file, or view used to surface as "Message has N words, which
+//! is too large" from deep inside the RPC stack, taking the connection down
+//! with it. Both ends now read with a limit of [`max_message_bytes`]
+//! (`TANDEM_MAX_MESSAGE_MB`, default 512), and payloads are checked before
+//! they are sent, so an oversized object fails on its own with
+//!
+//!   tandem message too large: file object is 600.0 MiB, over the 512 MiB limit; ...
+//!
+//! A single `Data` field cannot exceed 2^29 bytes on the wire whatever the
+//! limit, so objects that large cannot be stored through tandem at all.
+
+use anyhow::{anyhow, Result};
+
+/// Every oversized-payload error starts with this; clients match on it.
+pub const TOO_LARGE_PREFIX: &str = "tandem message too large";
+
+pub const DEFAULT_MAX_MESSAGE_MB: u64 = 512;
+
+/// Largest byte count a capnp `List(UInt8)` can hold (29-bit element count).
+const MAX_DATA_BYTES: u64 = (1 << 29) - 1;
+
+/// Room for the rest of the message around a payload.
+const FRAMING_HEADROOM: u64 = 64 * 1024;
+
+/// What capnp says when a reader rejects a message over its traversal limit.
+const CAPNP_TOO_LARGE: &str = "which is too large";
+
+/// The message limit in bytes, from `TANDEM_MAX_MESSAGE_MB` when set.
+pub fn max_message_bytes() -> u64 {
+    let mb = std::env::var("TANDEM_MAX_MESSAGE_MB")
+        .ok()
+        .and_then(|value| value.trim().parse::<u64>().ok())
+        .filter(|mb| *mb > 0)
+        .unwrap_or(DEFAULT_MAX_MESSAGE_MB);
+    mb.saturating_mul(1024 * 1024)
+}
+
+/// Reader options for every tandem RPC connection, client and server.
+pub fn reader_options() -> capnp::message::ReaderOptions {
+    let mut options = capnp::message::ReaderOptions::new();
+    let words = (max_message_bytes() / 8).try_into().unwrap_or(usize::MAX);
+    options.traversal_limit_in_words(Some(words));
+    options
+}
+
+/// The largest single payload (object, operation, or view) that fits.
+pub fn max_payload_bytes() -> u64 {
+    max_message_bytes()
+        .min(MAX_DATA_BYTES)
+        .saturating_sub(FRAMING_HEADROOM)
+}
+
+fn mib(bytes: u64) -> String {
+    format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+}
+
+/// The error for a payload of `size` bytes that cannot be sent.
+pub fn too_large(what: &str, size: u64) -> anyhow::Error {
+    let limit = max_payload_bytes();
+    let advice = if size > MAX_DATA_BYTES {
+        "keep files this large out of the repo (git-lfs, build artifacts storage)".to_string()
+    } else {
+        "raise TANDEM_MAX_MESSAGE_MB on the server and every client, or keep the file out of the repo"
+            .to_string()
+    };
+    anyhow!(
+        "{TOO_LARGE_PREFIX}: {what} is {}, over the {} limit; {advice}",
+        mib(size),
+        mib(limit)
+    )
+}
+
+/// Pass `data` through if it fits in one message, else a too-large error.
+pub fn fit(what: &str, data: Vec<u8>) -> Result<Vec<u8>> {
+    if data.len() as u64 > max_payload_bytes() {
+        return Err(too_large(what, data.len() as u64));
+    }
+    Ok(data)
+}
+
+/// Check an outgoing payload of `size` bytes.
+pub fn check(what: &str, size: usize) -> Result<()> {
+    if size as u64 > max_payload_bytes() {
+        return Err(too_large(what, size as u64));
+    }
+    Ok(())
+}
+
+/// A readable message for a remote error caused by message size: either our
+/// own too-large error, or capnp's reader limit tripping on either end.
+pub fn message_from_error(description: &str) -> Option<String> {
+    if let Some(start) = description.find(TOO_LARGE_PREFIX) {
+        return Some(description[start..].trim_end().to_string());
+    }
+    description.contains(CAPNP_TOO_LARGE).then(|| {
+        format!(
+            "{TOO_LARGE_PREFIX}: a message exceeded the {} limit of one side of the connection; \
+             set the same TANDEM_MAX_MESSAGE_MB on the server and every client",
+            mib(max_message_bytes())
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_payloads_are_named() {
+        assert!(check("tree object", 1024).is_ok());
+        let err = check("file object", (MAX_DATA_BYTES + 1) as usize).unwrap_err();
+        let text = err.to_string();
+        assert!(text.starts_with(TOO_LARGE_PREFIX), "{text}");
+        assert!(text.contains("file object is 512.0 MiB"), "{text}");
+        assert!(text.contains("out of the repo"), "{text}");
+    }
+
+    #[test]
+    fn recognises_remote_size_errors() {
+        let ours = format!("remote exception: {TOO_LARGE_PREFIX}: view is 600.0 MiB, ...");
+        assert_eq!(
+            message_from_error(&ours).as_deref(),
+            Some(&ours["remote exception: ".len()..])
+        );
+        let capnp = "Message has 90000000 words, which is too large. To increase the limit \
+                     on the receiving end, see capnp::message::ReaderOptions.";
+        assert!(message_from_error(capnp)
+            .unwrap()
+            .contains("TANDEM_MAX_MESSAGE_MB"));
+        assert_eq!(message_from_error("object not found"), None);
+    }
+}
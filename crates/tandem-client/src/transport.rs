@@ -0,0 +1,340 @@
+//! Byte streams to a tandem server: TCP, a Unix socket, or a server running
+//! in the same process (`inproc://`, and `local://` for local-mode repos).
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{bail, Context, Result};
+use tokio::sync::mpsc;
+
+const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Buffer per direction of an in-process connection.
+const IN_PROCESS_BUFFER: usize = 256 * 1024;
+
+/// Where a tandem endpoint lives: `host:port` (or `tcp://host:port`), a
+/// Unix socket as `unix:///path/to/socket`, an [`InProcessListener`] in
+/// this process as `inproc://<name>`, or a local-mode server repo as
+/// `local:///path/to/repo` (see [`serve_local_repos_with`]).
+#[derive(Debug, Clone)]
+pub enum ConnectorTarget {
+    Tcp { addr: String },
+    Unix { path: std::path::PathBuf },
+    InProcess { name: String },
+    Local { path: std::path::PathBuf },
+}
+
+impl ConnectorTarget {
+    pub fn parse(endpoint: &str) -> Result<Self> {
+        if let Some((scheme, rest)) = endpoint.split_once("://") {
+            if scheme.eq_ignore_ascii_case("tcp") {
+                if rest.is_empty() {
+                    bail!("invalid tcp endpoint: missing host:port in {endpoint:?}");
+                }
+                return Ok(Self::Tcp {
+                    addr: rest.to_string(),
+                });
+            }
+            if scheme.eq_ignore_ascii_case("unix") {
+                if rest.is_empty() {
+                    bail!("invalid unix endpoint: missing socket path in {endpoint:?}");
+                }
+                return Ok(Self::Unix {
+                    path: std::path::PathBuf::from(rest),
+                });
+            }
+            if scheme.eq_ignore_ascii_case("inproc") {
+                if rest.is_empty() {
+                    bail!("invalid inproc endpoint: missing name in {endpoint:?}");
+                }
+                return Ok(Self::InProcess {
+                    name: rest.to_string(),
+                });
+            }
+            if scheme.eq_ignore_ascii_case("local") {
+                if rest.is_empty() {
+                    bail!("invalid local endpoint: missing repo path in {endpoint:?}");
+                }
+                return Ok(Self::Local {
+                    path: std::path::PathBuf::from(rest),
+                });
+            }
+
+            bail!(
+                "unsupported tandem transport scheme {scheme:?}; use host:port, tcp://host:port, unix:///path, inproc://name, or local:///path"
+            );
+        }
+
+        Ok(Self::Tcp {
+            addr: endpoint.to_string(),
+        })
+    }
+
+    pub fn display_addr(&self) -> String {
+        match self {
+            Self::Tcp { addr } => addr.clone(),
+            Self::Unix { path } => format!("unix://{}", path.display()),
+            Self::InProcess { name } => format!("inproc://{name}"),
+            Self::Local { path } => format!("local://{}", path.display()),
+        }
+    }
+}
+
+/// A connected RPC byte stream, over TCP, a Unix socket, or in memory.
+pub enum RpcStream {
+    Tcp(tokio::net::TcpStream),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixStream),
+    InProcess(tokio::io::DuplexStream),
+}
+
+pub type RpcReadHalf = Box<dyn tokio::io::AsyncRead + Unpin>;
+pub type RpcWriteHalf = Box<dyn tokio::io::AsyncWrite + Unpin>;
+
+impl RpcStream {
+    pub fn into_split(self) -> (RpcReadHalf, RpcWriteHalf) {
+        match self {
+            Self::Tcp(stream) => {
+                let (reader, writer) = stream.into_split();
+                (Box::new(reader), Box::new(writer))
+            }
+            #[cfg(unix)]
+            Self::Unix(stream) => {
+                let (reader, writer) = stream.into_split();
+                (Box::new(reader), Box::new(writer))
+            }
+            Self::InProcess(stream) => {
+                let (reader, writer) = tokio::io::split(stream);
+                (Box::new(reader), Box::new(writer))
+            }
+        }
+    }
+}
+
+type InProcessRegistry = Mutex<HashMap<String, mpsc::UnboundedSender<tokio::io::DuplexStream>>>;
+
+fn in_process_listeners() -> &'static InProcessRegistry {
+    static LISTENERS: OnceLock<InProcessRegistry> = OnceLock::new();
+    LISTENERS.get_or_init(Default::default)
+}
+
+/// Accepts `inproc://<name>` connections from this process. The name is
+/// free again once the listener is dropped.
+pub struct InProcessListener {
+    name: String,
+    incoming: mpsc::UnboundedReceiver<tokio::io::DuplexStream>,
+}
+
+impl InProcessListener {
+    pub fn bind(name: &str) -> Result<Self> {
+        let mut listeners = in_process_listeners().lock().unwrap();
+        if listeners.get(name).is_some_and(|tx| !tx.is_closed()) {
+            bail!("inproc://{name} is already in use");
+        }
+        let (tx, incoming) = mpsc::unbounded_channel();
+        listeners.insert(name.to_string(), tx);
+        Ok(Self {
+            name: name.to_string(),
+            incoming,
+        })
+    }
+
+    pub fn local_addr(&self) -> String {
+        format!("inproc://{}", self.name)
+    }
+
+    /// The next client's end of a new connection.
+    pub async fn accept(&mut self) -> Option<tokio::io::DuplexStream> {
+        self.incoming.recv().await
+    }
+}
+
+impl Drop for InProcessListener {
+    fn drop(&mut self) {
+        self.incoming.close();
+        let mut listeners = in_process_listeners().lock().unwrap();
+        if listeners.get(&self.name).is_some_and(|tx| tx.is_closed()) {
+            listeners.remove(&self.name);
+        }
+    }
+}
+
+/// Starts, or finds, the server for a local-mode repo in this process and
+/// returns its `inproc://` address.
+pub type LocalServerStarter = fn(&std::path::Path) -> Result<String>;
+
+static LOCAL_SERVER_STARTER: OnceLock<LocalServerStarter> = OnceLock::new();
+
+/// Let `local://` addresses connect, through servers `start` runs in this
+/// process. The `tandem` binary, which has a server to embed, calls this at
+/// startup; elsewhere `local://` addresses fail to connect.
+pub fn serve_local_repos_with(start: LocalServerStarter) {
+    let _ = LOCAL_SERVER_STARTER.set(start);
+}
+
+fn connect_in_process(name: &str) -> std::io::Result<tokio::io::DuplexStream> {
+    let refused = || {
+        std::io::Error::new(
+            std::io::ErrorKind::ConnectionRefused,
+            format!("no tandem server is listening on inproc://{name} in this process"),
+        )
+    };
+    let listeners = in_process_listeners().lock().unwrap();
+    let tx = listeners.get(name).ok_or_else(refused)?;
+    let (client, server) = tokio::io::duplex(IN_PROCESS_BUFFER);
+    tx.send(server).map_err(|_| refused())?;
+    Ok(client)
+}
+
+/// Open a byte stream to `endpoint`, giving up after 5 seconds.
+pub async fn connect_stream(endpoint: &str) -> Result<RpcStream> {
+    let target = ConnectorTarget::parse(endpoint)?;
+    let addr = target.display_addr();
+    let timed_out = || {
+        std::io::Error::new(
+            std::io::ErrorKind::TimedOut,
+            format!(
+                "connection timed out after {}s to {addr}",
+                CONNECT_TIMEOUT.as_secs()
+            ),
+        )
+    };
+
+    match target {
+        ConnectorTarget::Tcp { addr: tcp_addr } => {
+            let stream =
+                tokio::time::timeout(CONNECT_TIMEOUT, tokio::net::TcpStream::connect(&tcp_addr))
+                    .await
+                    .map_err(|_| timed_out())?
+                    .with_context(|| format!("failed to connect to tandem server at {addr}"))?;
+            stream.set_nodelay(true).ok();
+            Ok(RpcStream::Tcp(stream))
+        }
+        #[cfg(unix)]
+        ConnectorTarget::Unix { path } => {
+            let stream =
+                tokio::time::timeout(CONNECT_TIMEOUT, tokio::net::UnixStream::connect(&path))
+                    .await
+                    .map_err(|_| timed_out())?
+                    .with_context(|| format!("failed to connect to tandem server at {addr}"))?;
+            Ok(RpcStream::Unix(stream))
+        }
+        #[cfg(not(unix))]
+        ConnectorTarget::Unix { .. } => {
+            bail!("cannot connect to {addr}: Unix sockets are not supported on this platform")
+        }
+        ConnectorTarget::InProcess { name } => {
+            let stream = connect_in_process(&name)
+                .with_context(|| format!("failed to connect to tandem server at {addr}"))?;
+            Ok(RpcStream::InProcess(stream))
+        }
+        ConnectorTarget::Local { path } => {
+            let Some(start) = LOCAL_SERVER_STARTER.get() else {
+                bail!("cannot connect to {addr}: local-mode repos are only served by the tandem binary");
+            };
+            let inproc = start(&path)
+                .with_context(|| format!("failed to start the local server for {addr}"))?;
+            let ConnectorTarget::InProcess { name } = ConnectorTarget::parse(&inproc)? else {
+                bail!("local server for {addr} did not give an inproc:// address: {inproc}");
+            };
+            let stream = connect_in_process(&name)
+                .with_context(|| format!("failed to connect to tandem server at {addr}"))?;
+            Ok(RpcStream::InProcess(stream))
+        }
+    }
+}
+
+/// Whether a connect error means the server could not be reached at all
+/// (refused, timed out, no route), as opposed to answering badly.
+pub fn is_unreachable(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(|cause| cause.downcast_ref::<std::io::Error>().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::{connect_stream, is_unreachable, ConnectorTarget, InProcessListener, RpcStream};
+
+    #[test]
+    fn connector_target_parses_raw_host_port_as_tcp() {
+        let parsed = ConnectorTarget::parse("127.0.0.1:12345").expect("parse endpoint");
+        match parsed {
+            ConnectorTarget::Tcp { addr } => assert_eq!(addr, "127.0.0.1:12345"),
+            other => panic!("expected tcp, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn connector_target_parses_unix_socket_paths() {
+        let parsed = ConnectorTarget::parse("unix:///run/tandem/rpc.sock").expect("parse endpoint");
+        match &parsed {
+            ConnectorTarget::Unix { path } => {
+                assert_eq!(path, std::path::Path::new("/run/tandem/rpc.sock"))
+            }
+            other => panic!("expected unix, got {other:?}"),
+        }
+        assert_eq!(parsed.display_addr(), "unix:///run/tandem/rpc.sock");
+        assert!(ConnectorTarget::parse("unix://").is_err());
+    }
+
+    #[test]
+    fn in_process_connections_reach_the_named_listener() {
+        let parsed = ConnectorTarget::parse("inproc://transport-test").expect("parse endpoint");
+        assert_eq!(parsed.display_addr(), "inproc://transport-test");
+        assert!(ConnectorTarget::parse("inproc://").is_err());
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let mut listener = InProcessListener::bind("transport-test").unwrap();
+            assert!(InProcessListener::bind("transport-test").is_err());
+            let mut client = match connect_stream("inproc://transport-test").await.unwrap() {
+                RpcStream::InProcess(stream) => stream,
+                _ => panic!("expected an in-process stream"),
+            };
+            let mut server = listener.accept().await.unwrap();
+            client.write_all(b"ping").await.unwrap();
+            let mut buf = [0; 4];
+            server.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ping");
+
+            drop(listener);
+            let Err(err) = connect_stream("inproc://transport-test").await else {
+                panic!("dropped listener still accepts connections");
+            };
+            assert!(is_unreachable(&err), "{err:#}");
+            InProcessListener::bind("transport-test").expect("name is free again");
+        });
+    }
+
+    #[test]
+    fn local_repos_need_a_server_to_embed() {
+        let parsed = ConnectorTarget::parse("local:///work/.jj/repo/tandem-local").unwrap();
+        assert_eq!(parsed.display_addr(), "local:///work/.jj/repo/tandem-local");
+        assert!(ConnectorTarget::parse("local://").is_err());
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let Err(err) = rt.block_on(connect_stream("local:///work/.jj/repo/tandem-local")) else {
+            panic!("connected without a local server starter");
+        };
+        assert!(
+            err.to_string().contains("only served by the tandem binary"),
+            "{err:#}"
+        );
+    }
+
+    #[test]
+    fn connector_target_rejects_unknown_transport_scheme() {
+        let err = ConnectorTarget::parse("wss://example.com:443").expect_err("must reject wss");
+        assert!(
+            err.to_string()
+                .contains("unsupported tandem transport scheme"),
+            "unexpected error: {err:#}"
+        );
+    }
+}
@@ -0,0 +1,715 @@
+//! TandemBackend — jj-lib Backend impl that routes all object I/O
+//! to a remote tandem server over Cap'n Proto RPC.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use futures::stream::BoxStream;
+use jj_lib::backend::*;
+use jj_lib::hex_util::{decode_hex, encode_hex};
+use jj_lib::index::Index;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo_path::{RepoPath, RepoPathBuf};
+use jj_lib::settings::UserSettings;
+use prost::Message as _;
+use tokio::io::AsyncRead;
+
+use crate::offline;
+use crate::offline_queue;
+use crate::profile;
+use crate::proto_convert;
+use crate::read_mirror;
+use crate::rpc::{self, HashAlgorithm, PendingGet, PendingPut, RepoCapability, TandemClient};
+use crate::settings::TandemSettings;
+use crate::tmp_file::tmp_path;
+use crate::tree_hash;
+
+// Object kind discriminants matching the Cap'n Proto schema
+const KIND_COMMIT: u16 = 0;
+const KIND_TREE: u16 = 1;
+const KIND_FILE: u16 = 2;
+const KIND_SYMLINK: u16 = 3;
+// const KIND_COPY: u16 = 4;
+const CACHE_DIR: &str = "cache";
+/// Next to the object cache: the heads whose ancestors the last commit graph
+/// prefetch cached.
+const GRAPH_HEADS_FILE: &str = "commit-graph-heads";
+
+/// Backend implementation that proxies all reads/writes to a tandem server.
+pub struct TandemBackend {
+    client: Arc<TandemClient>,
+    /// Answers object reads in place of `client` (see `read_mirror.rs`).
+    mirror: Option<Arc<TandemClient>>,
+    commit_id_len: usize,
+    change_id_len: usize,
+    /// How the server's git repo hashes objects, for pipelined tree ids.
+    commit_hash: HashAlgorithm,
+    root_commit_id: CommitId,
+    root_change_id: ChangeId,
+    empty_tree_id: TreeId,
+    cache: ObjectCache,
+    tree_writes: TreeWrites,
+    prefetch: Prefetch,
+    /// Gives writes their ids while the server is unreachable.
+    offline_writes: Option<offline_queue::ObjectWriter>,
+}
+
+impl fmt::Debug for TandemBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TandemBackend")
+            .field("server", &self.client.server_addr())
+            .field(
+                "read_mirror",
+                &self.mirror.as_ref().map(|m| m.server_addr()),
+            )
+            .finish()
+    }
+}
+
+impl TandemBackend {
+    /// Initialize a new tandem backend (called during workspace init).
+    pub fn init(
+        settings: &UserSettings,
+        store_path: &Path,
+        server_addr: &str,
+    ) -> Result<Self, BackendInitError> {
+        let settings =
+            TandemSettings::from_user(settings).map_err(|e| BackendInitError(e.into()))?;
+        // Write server address for future loads
+        std::fs::write(store_path.join("server_address"), server_addr)
+            .map_err(|e| BackendInitError(e.into()))?;
+
+        let client =
+            TandemClient::connect_with_options(server_addr, &[], settings.client_options())
+                .map_err(|e| BackendInitError(e.into()))?;
+        let info = client.repo_info().clone();
+        let mirror = read_mirror::connect(store_path, &client, &settings);
+
+        Ok(Self {
+            client,
+            mirror,
+            commit_id_len: info.commit_id_length,
+            change_id_len: info.change_id_length,
+            commit_hash: HashAlgorithm::parse(&info.commit_hash)
+                .unwrap_or(HashAlgorithm::DEFAULT_COMMIT),
+            root_commit_id: CommitId::new(info.root_commit_id),
+            root_change_id: ChangeId::new(info.root_change_id),
+            empty_tree_id: TreeId::new(info.empty_tree_id),
+            cache: ObjectCache::new(store_path, &settings),
+            tree_writes: TreeWrites::new(&settings),
+            prefetch: Prefetch::new(&settings),
+            offline_writes: None,
+        })
+    }
+
+    /// Load an existing tandem backend from `store_path`.
+    pub fn load(user_settings: &UserSettings, store_path: &Path) -> Result<Self, BackendLoadError> {
+        let settings =
+            TandemSettings::from_user(user_settings).map_err(|e| BackendLoadError(e.into()))?;
+        let client = offline::connect(store_path, &settings)?;
+        let info = client.repo_info().clone();
+        let mirror = read_mirror::connect(store_path, &client, &settings);
+        let offline_writes = client.is_offline().then(|| {
+            offline_queue::ObjectWriter::new(
+                offline_queue::Queue::for_store(store_path),
+                user_settings,
+                &info,
+            )
+        });
+
+        Ok(Self {
+            client,
+            mirror,
+            commit_id_len: info.commit_id_length,
+            change_id_len: info.change_id_length,
+            commit_hash: HashAlgorithm::parse(&info.commit_hash)
+                .unwrap_or(HashAlgorithm::DEFAULT_COMMIT),
+            root_commit_id: CommitId::new(info.root_commit_id),
+            root_change_id: ChangeId::new(info.root_change_id),
+            empty_tree_id: TreeId::new(info.empty_tree_id),
+            cache: ObjectCache::new(store_path, &settings),
+            tree_writes: TreeWrites::new(&settings),
+            prefetch: Prefetch::new(&settings),
+            offline_writes,
+        })
+    }
+
+    /// The connection to the server, for tandem-specific queries.
+    pub fn client(&self) -> &TandemClient {
+        &self.client
+    }
+
+    /// Cache every commit jj reads to index the server's visible heads, a
+    /// page per RPC instead of a `getObject` each (`getCommitGraph`), and
+    /// return how many were fetched. Commits the previous prefetch into the
+    /// same cache covered are skipped; servers without `commitGraph` are
+    /// left to jj's one-at-a-time reads.
+    pub fn prefetch_commit_graph(&self) -> anyhow::Result<usize> {
+        if !self.client.supports_capability(RepoCapability::CommitGraph) {
+            return Ok(0);
+        }
+        let known = self.cache.graph_heads();
+        let mut heads = Vec::new();
+        let mut cursor = String::new();
+        let mut fetched = 0;
+        loop {
+            let page = self.client.get_commit_graph(&heads, &known, &cursor, 0)?;
+            for (id, data) in &page.commits {
+                self.cache.store(KIND_COMMIT, id, data);
+            }
+            fetched += page.commits.len();
+            heads = page.heads;
+            if page.next_cursor.is_empty() {
+                break;
+            }
+            cursor = page.next_cursor;
+        }
+        self.cache.set_graph_heads(&heads);
+        Ok(fetched)
+    }
+}
+
+/// Tree writes sent to the server but not yet confirmed. jj writes a
+/// snapshot's trees one at a time, deepest first, and each needs its id
+/// before the parent can be built; with the id computed locally
+/// (`tree_hash.rs`) `write_tree` returns at once and up to the in-flight
+/// limit of writes overlap. Everything pending is settled, and each id
+/// checked against the server's, before a commit is written, before one of
+/// those trees is read back, and when the backend is dropped.
+struct TreeWrites {
+    limit: usize,
+    pending: Mutex<VecDeque<PendingTree>>,
+}
+
+struct PendingTree {
+    id: Vec<u8>,
+    reply: PendingPut,
+}
+
+impl TreeWrites {
+    fn new(settings: &TandemSettings) -> Self {
+        Self {
+            limit: rpc::rpc_max_inflight(settings.max_inflight),
+            pending: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        self.limit > 1
+    }
+}
+
+/// Reads issued ahead of jj's own under `tandem.prefetch-paths`, for sparse
+/// workspaces in large repos. When jj reads a tree on the way to or inside
+/// one of those paths, the subtrees on the way and the files inside are
+/// requested at once, so checking out a directory costs about one round trip
+/// per level instead of one per object. Nothing outside the paths is
+/// fetched. Replies land in the object cache; at most the in-flight limit of
+/// reads are outstanding, and ones jj never asks for are simply cached.
+struct Prefetch {
+    paths: Vec<String>,
+    limit: usize,
+    pending: Mutex<HashMap<(u16, Vec<u8>), PendingGet>>,
+}
+
+impl Prefetch {
+    fn new(settings: &TandemSettings) -> Self {
+        Self {
+            paths: settings.prefetch_paths.clone(),
+            limit: rpc::rpc_max_inflight(settings.max_inflight),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.paths.is_empty() && self.limit > 1
+    }
+
+    /// Whether the directory at `path` is inside a prefetch path or on the
+    /// way to one.
+    fn wants_dir(&self, path: &str) -> bool {
+        self.paths
+            .iter()
+            .any(|prefix| is_within(path, prefix) || is_within(prefix, path))
+    }
+
+    /// Whether the file at `path` is inside a prefetch path.
+    fn wants_file(&self, path: &str) -> bool {
+        self.paths.iter().any(|prefix| is_within(path, prefix))
+    }
+}
+
+/// `path` equals `prefix` or lies below it; every path is within the root.
+fn is_within(path: &str, prefix: &str) -> bool {
+    prefix.is_empty()
+        || path
+            .strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+/// Local copies of objects read from or written to the server, one file per
+/// hex id under `cache/objects/<kind>/` (or `tandem.cache-dir`). Objects are
+/// immutable, so a cached copy is always served first; this is also what
+/// offline mode reads from. Ids are the server's git hashes, so entries are
+/// not re-hashed, but they are only ever written whole, from server replies
+/// or from the offline writer that stores objects as the server would.
+struct ObjectCache {
+    dir: PathBuf,
+}
+
+impl ObjectCache {
+    fn new(store_path: &Path, settings: &TandemSettings) -> Self {
+        let dir = settings
+            .cache_dir
+            .clone()
+            .unwrap_or_else(|| store_path.join(CACHE_DIR));
+        Self {
+            dir: dir.join("objects"),
+        }
+    }
+
+    fn path(&self, kind: u16, id: &[u8]) -> PathBuf {
+        self.dir.join(kind.to_string()).join(encode_hex(id))
+    }
+
+    fn load(&self, kind: u16, id: &[u8]) -> Option<Vec<u8>> {
+        let _phase = profile::enter(&["cache read"]);
+        std::fs::read(self.path(kind, id)).ok()
+    }
+
+    fn contains(&self, kind: u16, id: &[u8]) -> bool {
+        self.path(kind, id).exists()
+    }
+
+    fn store(&self, kind: u16, id: &[u8], data: &[u8]) {
+        let path = self.path(kind, id);
+        if path.exists() {
+            return;
+        }
+        let write = || -> std::io::Result<()> {
+            std::fs::create_dir_all(self.dir.join(kind.to_string()))?;
+            let tmp = tmp_path(&path);
+            std::fs::write(&tmp, data)?;
+            std::fs::rename(&tmp, &path)
+        };
+        if let Err(err) = write() {
+            tracing::debug!(path = %path.display(), error = %err, "could not cache object");
+        }
+    }
+
+    fn graph_heads(&self) -> Vec<Vec<u8>> {
+        std::fs::read_to_string(self.dir.with_file_name(GRAPH_HEADS_FILE))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| decode_hex(line.trim()))
+            .collect()
+    }
+
+    fn set_graph_heads(&self, heads: &[Vec<u8>]) {
+        let path = self.dir.with_file_name(GRAPH_HEADS_FILE);
+        let contents: String = heads.iter().map(|id| encode_hex(id) + "\n").collect();
+        let write = || -> std::io::Result<()> {
+            std::fs::create_dir_all(path.parent().unwrap_or(&self.dir))?;
+            let tmp = tmp_path(&path);
+            std::fs::write(&tmp, contents)?;
+            std::fs::rename(&tmp, &path)
+        };
+        if let Err(err) = write() {
+            tracing::debug!(path = %path.display(), error = %err, "could not record commit graph heads");
+        }
+    }
+}
+
+impl TandemBackend {
+    fn get_object(&self, kind: u16, id: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if kind == KIND_TREE && self.tree_write_pending(id) {
+            self.settle_tree_writes(0)?;
+        }
+        if let Some(data) = self.cache.load(kind, id) {
+            return Ok(data);
+        }
+        let prefetched = self
+            .prefetch
+            .pending
+            .lock()
+            .unwrap()
+            .remove(&(kind, id.to_vec()));
+        let data = match prefetched {
+            // A read ahead from the mirror may miss; the primary has it.
+            Some(reply) if self.mirror.is_some() => match rpc::wait(reply, "getObject") {
+                Ok(data) => data,
+                Err(_) => self.fetch_object(kind, id)?,
+            },
+            Some(reply) => rpc::wait(reply, "getObject")?,
+            None => self.fetch_object(kind, id)?,
+        };
+        self.cache.store(kind, id, &data);
+        Ok(data)
+    }
+
+    /// Fetch from the read mirror if there is one, else the primary.
+    fn fetch_object(&self, kind: u16, id: &[u8]) -> anyhow::Result<Vec<u8>> {
+        read_mirror::read(self.mirror.as_deref(), &self.client, |client| {
+            if kind == KIND_TREE {
+                fetch_tree(client, id)
+            } else {
+                client.get_object(kind, id)
+            }
+        })
+    }
+
+    /// Request the children of `tree` (at `dir`) that fall under the
+    /// prefetch paths, without waiting for them.
+    fn prefetch_children(&self, dir: &RepoPath, tree: &Tree) {
+        if !self.prefetch.enabled() || self.client.is_offline() {
+            return;
+        }
+        let mut pending = self.prefetch.pending.lock().unwrap();
+        // Move finished reads into the cache to free their slots.
+        pending.retain(|(kind, id), reply| match reply.try_recv() {
+            Ok(Ok(data)) => {
+                self.cache.store(*kind, id, &data);
+                false
+            }
+            Ok(Err(_)) | Err(std::sync::mpsc::TryRecvError::Disconnected) => false,
+            Err(std::sync::mpsc::TryRecvError::Empty) => true,
+        });
+        for entry in tree.entries() {
+            if pending.len() >= self.prefetch.limit {
+                break;
+            }
+            let path = dir.join(entry.name());
+            let path = path.as_internal_file_string();
+            let (kind, id) = match entry.value() {
+                TreeValue::Tree(id) if self.prefetch.wants_dir(path) => (KIND_TREE, id.as_bytes()),
+                TreeValue::File { id, .. } if self.prefetch.wants_file(path) => {
+                    (KIND_FILE, id.as_bytes())
+                }
+                _ => continue,
+            };
+            let key = (kind, id.to_vec());
+            if pending.contains_key(&key) || self.cache.contains(kind, id) {
+                continue;
+            }
+            match self
+                .mirror
+                .as_ref()
+                .unwrap_or(&self.client)
+                .begin_get_object(kind, id)
+            {
+                Ok(reply) => {
+                    pending.insert(key, reply);
+                }
+                Err(err) => {
+                    tracing::debug!(error = %err, "prefetch stopped");
+                    break;
+                }
+            }
+        }
+    }
+
+    async fn put_object(&self, kind: u16, data: &[u8]) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        let (id, normalized) = match &self.offline_writes {
+            Some(offline) => offline.put(kind, data).await?,
+            None => self.client.put_object(kind, data)?,
+        };
+        self.cache.store(kind, &id, &normalized);
+        Ok((id, normalized))
+    }
+
+    /// Send a tree without waiting for the server, returning its id.
+    fn put_tree_pipelined(&self, tree: &Tree, data: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let limit = self.tree_writes.limit;
+        self.settle_tree_writes(limit - 1)?;
+        let id = tree_hash::git_tree_id(tree, self.commit_hash);
+        let reply = self.client.begin_put_object(KIND_TREE, data)?;
+        let mut pending = self.tree_writes.pending.lock().unwrap();
+        pending.push_back(PendingTree {
+            id: id.clone(),
+            reply,
+        });
+        Ok(id)
+    }
+
+    fn tree_write_pending(&self, id: &[u8]) -> bool {
+        let pending = self.tree_writes.pending.lock().unwrap();
+        pending.iter().any(|tree| tree.id == id)
+    }
+
+    /// Wait for pending tree writes, oldest first, until at most `keep` are
+    /// left in flight.
+    fn settle_tree_writes(&self, keep: usize) -> anyhow::Result<()> {
+        let mut pending = self.tree_writes.pending.lock().unwrap();
+        while pending.len() > keep {
+            let PendingTree { id, reply } = pending.pop_front().unwrap();
+            let (stored_id, normalized) = rpc::wait(reply, "putObject")?;
+            if stored_id != id {
+                pending.clear();
+                anyhow::bail!(
+                    "server stored tree as {}, but it was written as {}",
+                    encode_hex(&stored_id),
+                    encode_hex(&id)
+                );
+            }
+            self.cache.store(KIND_TREE, &id, &normalized);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for TandemBackend {
+    fn drop(&mut self) {
+        if let Err(err) = self.settle_tree_writes(0) {
+            tracing::warn!(error = %err, "pending tree writes failed");
+        }
+    }
+}
+
+/// Fetch tree `id` a page at a time when the server supports
+/// `treePages`, so a directory with tens of thousands of entries never
+/// needs one huge reply. The stitched tree encodes exactly as the
+/// `getObject` reply would have.
+fn fetch_tree(client: &TandemClient, id: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if !client.supports_capability(RepoCapability::TreePages) {
+        return client.get_object(KIND_TREE, id);
+    }
+    let mut tree = jj_lib::protos::simple_store::Tree::default();
+    let mut cursor = String::new();
+    loop {
+        let (data, next) = client.get_tree_entries(id, &cursor, 0)?;
+        let page = jj_lib::protos::simple_store::Tree::decode(&*data)?;
+        tree.entries.extend(page.entries);
+        if next.is_empty() {
+            return Ok(tree.encode_to_vec());
+        }
+        anyhow::ensure!(
+            next > cursor,
+            "server repeated tree page cursor {next:?} for {}",
+            encode_hex(id)
+        );
+        cursor = next;
+    }
+}
+
+fn to_backend_err(err: anyhow::Error) -> BackendError {
+    BackendError::Other(err.into())
+}
+
+#[async_trait]
+impl Backend for TandemBackend {
+    fn name(&self) -> &str {
+        "tandem"
+    }
+
+    fn commit_id_length(&self) -> usize {
+        self.commit_id_len
+    }
+
+    fn change_id_length(&self) -> usize {
+        self.change_id_len
+    }
+
+    fn root_commit_id(&self) -> &CommitId {
+        &self.root_commit_id
+    }
+
+    fn root_change_id(&self) -> &ChangeId {
+        &self.root_change_id
+    }
+
+    fn empty_tree_id(&self) -> &TreeId {
+        &self.empty_tree_id
+    }
+
+    fn concurrency(&self) -> usize {
+        64
+    }
+
+    async fn read_file(
+        &self,
+        _path: &RepoPath,
+        id: &FileId,
+    ) -> BackendResult<Pin<Box<dyn AsyncRead + Send>>> {
+        let data =
+            self.get_object(KIND_FILE, id.as_bytes())
+                .map_err(|e| BackendError::ReadObject {
+                    object_type: "file".into(),
+                    hash: id.hex(),
+                    source: e.into(),
+                })?;
+        Ok(Box::pin(Cursor::new(data)))
+    }
+
+    async fn write_file(
+        &self,
+        _path: &RepoPath,
+        contents: &mut (dyn AsyncRead + Send + Unpin),
+    ) -> BackendResult<FileId> {
+        let mut buf = Vec::new();
+        tokio::io::AsyncReadExt::read_to_end(contents, &mut buf)
+            .await
+            .map_err(|e| to_backend_err(e.into()))?;
+        let (id, _) = self
+            .put_object(KIND_FILE, &buf)
+            .await
+            .map_err(to_backend_err)?;
+        Ok(FileId::new(id))
+    }
+
+    async fn read_symlink(&self, _path: &RepoPath, id: &SymlinkId) -> BackendResult<String> {
+        let data =
+            self.get_object(KIND_SYMLINK, id.as_bytes())
+                .map_err(|e| BackendError::ReadObject {
+                    object_type: "symlink".into(),
+                    hash: id.hex(),
+                    source: e.into(),
+                })?;
+        String::from_utf8(data).map_err(|e| to_backend_err(e.into()))
+    }
+
+    async fn write_symlink(&self, _path: &RepoPath, target: &str) -> BackendResult<SymlinkId> {
+        let (id, _) = self
+            .put_object(KIND_SYMLINK, target.as_bytes())
+            .await
+            .map_err(to_backend_err)?;
+        Ok(SymlinkId::new(id))
+    }
+
+    async fn read_copy(&self, _id: &CopyId) -> BackendResult<CopyHistory> {
+        Err(BackendError::Unsupported(
+            "Copy tracking not yet supported".into(),
+        ))
+    }
+
+    async fn write_copy(&self, _copy: &CopyHistory) -> BackendResult<CopyId> {
+        Err(BackendError::Unsupported(
+            "Copy tracking not yet supported".into(),
+        ))
+    }
+
+    async fn get_related_copies(&self, copy_id: &CopyId) -> BackendResult<Vec<CopyHistory>> {
+        let maybe_wire_copies = self
+            .client
+            .get_related_copies(copy_id.as_bytes())
+            .map_err(to_backend_err)?;
+
+        if maybe_wire_copies.is_none() {
+            return Err(BackendError::Unsupported(
+                "Copy tracking not supported by server capabilities".into(),
+            ));
+        }
+
+        Err(BackendError::Unsupported(
+            "Copy tracking decoding not yet implemented".into(),
+        ))
+    }
+
+    async fn read_tree(&self, path: &RepoPath, id: &TreeId) -> BackendResult<Tree> {
+        let data =
+            self.get_object(KIND_TREE, id.as_bytes())
+                .map_err(|e| BackendError::ReadObject {
+                    object_type: "tree".into(),
+                    hash: id.hex(),
+                    source: e.into(),
+                })?;
+        let tree = {
+            let _phase = profile::enter(&["proto decode", "tree"]);
+            let proto = jj_lib::protos::simple_store::Tree::decode(&*data)
+                .map_err(|e| to_backend_err(e.into()))?;
+            proto_convert::tree_from_proto(proto)
+        };
+        self.prefetch_children(path, &tree);
+        Ok(tree)
+    }
+
+    async fn write_tree(&self, _path: &RepoPath, contents: &Tree) -> BackendResult<TreeId> {
+        let proto = proto_convert::tree_to_proto(contents);
+        let data = proto.encode_to_vec();
+        if self.tree_writes.enabled() && self.offline_writes.is_none() {
+            let id = self
+                .put_tree_pipelined(contents, data)
+                .map_err(to_backend_err)?;
+            return Ok(TreeId::new(id));
+        }
+        let (id, _) = self
+            .put_object(KIND_TREE, &data)
+            .await
+            .map_err(to_backend_err)?;
+        Ok(TreeId::new(id))
+    }
+
+    async fn read_commit(&self, id: &CommitId) -> BackendResult<Commit> {
+        if *id == self.root_commit_id {
+            return Ok(make_root_commit(
+                self.root_change_id.clone(),
+                self.empty_tree_id.clone(),
+            ));
+        }
+        let data =
+            self.get_object(KIND_COMMIT, id.as_bytes())
+                .map_err(|e| BackendError::ReadObject {
+                    object_type: "commit".into(),
+                    hash: id.hex(),
+                    source: e.into(),
+                })?;
+        let _phase = profile::enter(&["proto decode", "commit"]);
+        let proto = jj_lib::protos::simple_store::Commit::decode(&*data)
+            .map_err(|e| to_backend_err(e.into()))?;
+        Ok(proto_convert::commit_from_proto(proto))
+    }
+
+    async fn write_commit(
+        &self,
+        mut commit: Commit,
+        sign_with: Option<&mut SigningFn>,
+    ) -> BackendResult<(CommitId, Commit)> {
+        assert!(commit.secure_sig.is_none(), "commit.secure_sig was set");
+
+        if commit.parents.is_empty() {
+            return Err(BackendError::Other(
+                "Cannot write a commit with no parents".into(),
+            ));
+        }
+
+        let mut proto = jj_lib::simple_backend::commit_to_proto(&commit);
+        if let Some(sign) = sign_with {
+            let data = proto.encode_to_vec();
+            let sig = sign(&data).map_err(|e| BackendError::Other(e.into()))?;
+            proto.secure_sig = Some(sig.clone());
+            commit.secure_sig = Some(SecureSig { data, sig });
+        }
+
+        let data = proto.encode_to_vec();
+        // The commit's tree must be on the server before the commit is.
+        self.settle_tree_writes(0).map_err(to_backend_err)?;
+        let (id, normalized_data) = self
+            .put_object(KIND_COMMIT, &data)
+            .await
+            .map_err(to_backend_err)?;
+
+        // Decode the normalized data to get the commit as stored
+        let stored_proto = jj_lib::protos::simple_store::Commit::decode(&*normalized_data)
+            .map_err(|e| to_backend_err(e.into()))?;
+        let stored_commit = proto_convert::commit_from_proto(stored_proto);
+        Ok((CommitId::new(id), stored_commit))
+    }
+
+    fn get_copy_records(
+        &self,
+        _paths: Option<&[RepoPathBuf]>,
+        _root: &CommitId,
+        _head: &CommitId,
+    ) -> BackendResult<BoxStream<'_, BackendResult<CopyRecord>>> {
+        Ok(Box::pin(futures::stream::empty()))
+    }
+
+    fn gc(&self, _index: &dyn Index, _keep_newer: SystemTime) -> BackendResult<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,127 @@
+//! Graceful shutdown — warn clients, refuse new writes, let commits land.
+//!
+//! When the server is told to stop (a signal, or `tandem down` over the
+//! control socket) it does not just drop its connections:
+//!
+//! 1. Every `watchHeads` subscriber gets `HeadWatcher.shuttingDown` with a
+//!    retry hint, so `tandem watch` and editor integrations can say why the
+//!    stream is ending.
+//! 2. Mutating calls (`putObject`, `putOperation`, `putView`,
+//!    `updateOpHeads`, ...) from connections that have not started writing
+//!    fail at once with a notice of the form
+//!
+//!      tandem server is shutting down; retry in 30s
+//!
+//!    which clients show as-is, like a maintenance notice.
+//! 3. A connection that is mid-commit — it has written objects or operations
+//!    but not yet moved the op heads — may finish. The server waits up to
+//!    `--drain-timeout` (default 30s) for those, and up to [`IDLE_GRACE`] for
+//!    everything else to hang up.
+
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Every shutdown refusal starts with this; clients match on it.
+pub const NOTICE_PREFIX: &str = "tandem server is shutting down";
+
+/// How long mid-commit connections get when `--drain-timeout` is not given.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long connections with no commit in progress get to close.
+pub const IDLE_GRACE: Duration = Duration::from_secs(5);
+
+/// Shutdown state shared by every connection of one server.
+#[derive(Debug, Default)]
+pub struct Drain(Mutex<DrainState>);
+
+#[derive(Debug, Default)]
+struct DrainState {
+    /// Set once shutdown begins: the retry hint handed to clients.
+    retry_after: Option<Duration>,
+    /// Connections that wrote something their commit has not published yet.
+    committing: BTreeSet<u64>,
+}
+
+impl Drain {
+    /// Stop admitting new writes; clients are told to retry after `retry_after`.
+    pub fn start(&self, retry_after: Duration) {
+        self.0.lock().unwrap().retry_after = Some(retry_after);
+    }
+
+    /// Admit a mutating call on `conn_id`. While draining, only connections
+    /// already mid-commit get through; the error is the notice for the rest.
+    pub fn admit_write(&self, conn_id: u64) -> Result<(), String> {
+        let mut state = self.0.lock().unwrap();
+        match state.retry_after {
+            Some(retry_after) if !state.committing.contains(&conn_id) => Err(describe(retry_after)),
+            _ => {
+                state.committing.insert(conn_id);
+                Ok(())
+            }
+        }
+    }
+
+    /// `conn_id` published its commit (or finished a one-shot write).
+    pub fn write_finished(&self, conn_id: u64) {
+        self.0.lock().unwrap().committing.remove(&conn_id);
+    }
+
+    pub fn connection_closed(&self, conn_id: u64) {
+        self.write_finished(conn_id);
+    }
+
+    /// Connections with a commit in progress.
+    pub fn committing(&self) -> usize {
+        self.0.lock().unwrap().committing.len()
+    }
+}
+
+/// The text returned to clients whose writes are refused.
+pub fn describe(retry_after: Duration) -> String {
+    format!(
+        "{NOTICE_PREFIX}; retry in {}s",
+        retry_after.as_secs().max(1)
+    )
+}
+
+/// Extract the shutdown notice from a remote error description, dropping
+/// any RPC framing in front of it.
+pub fn notice_from_error(description: &str) -> Option<&str> {
+    description
+        .find(NOTICE_PREFIX)
+        .map(|start| description[start..].trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_connections_mid_commit_may_write_while_draining() {
+        let drain = Drain::default();
+        drain.admit_write(1).unwrap();
+        drain.admit_write(2).unwrap();
+        drain.write_finished(2);
+
+        drain.start(Duration::from_secs(30));
+        assert!(drain.admit_write(1).is_ok());
+        let refused = drain.admit_write(2).unwrap_err();
+        assert_eq!(refused, "tandem server is shutting down; retry in 30s");
+        assert_eq!(drain.committing(), 1);
+
+        drain.connection_closed(1);
+        assert_eq!(drain.committing(), 0);
+        assert!(drain.admit_write(1).is_err());
+    }
+
+    #[test]
+    fn finds_the_notice_in_remote_errors() {
+        let err = "remote exception: tandem server is shutting down; retry in 5s\n";
+        assert_eq!(
+            notice_from_error(err),
+            Some("tandem server is shutting down; retry in 5s")
+        );
+        assert_eq!(notice_from_error("some other failure"), None);
+    }
+}
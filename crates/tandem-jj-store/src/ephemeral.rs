@@ -0,0 +1,171 @@
+//! Ephemeral workspaces — short-lived CI workspaces the server cleans up.
+//!
+//! `tandem init --ephemeral` makes every operation the workspace writes carry
+//! an `tandem.ephemeral-ttl=<secs>` tag. When the server sees that tag on an
+//! `updateOpHeads`, it records the workspace with an expiry of now + TTL, and
+//! every later update from it pushes the expiry forward. A sweeper forgets
+//! expired workspaces: their working-copy commits leave the view and their
+//! `workspace_heads` entries are dropped.
+//!
+//! `tandem workspace release` is `jj workspace forget` with a
+//! `tandem.release` tag, which drops the entry immediately.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Operation tag carrying the workspace's TTL in seconds.
+pub const TTL_TAG: &str = "tandem.ephemeral-ttl";
+/// Operation tag marking an explicit release of the writing workspace.
+pub const RELEASE_TAG: &str = "tandem.release";
+/// How often the server looks for expired workspaces.
+pub const SWEEP_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EphemeralWorkspace {
+    pub ttl_secs: u64,
+    /// Unix seconds after which the sweeper forgets the workspace.
+    pub expires_at: u64,
+}
+
+/// Parse a TTL or other duration such as `90`, `90s`, `30m`, `2h`, or `1d`,
+/// in seconds.
+pub fn parse_ttl(raw: &str) -> Result<u64> {
+    let raw = raw.trim();
+    let split = raw.find(|c: char| !c.is_ascii_digit()).unwrap_or(raw.len());
+    let (digits, unit) = raw.split_at(split);
+    let Ok(value) = digits.parse::<u64>() else {
+        bail!("invalid duration {raw:?}: expected a number with an optional s/m/h/d suffix");
+    };
+    let scale = match unit {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => bail!("invalid duration {raw:?}: unknown unit {unit:?} (use s, m, h, or d)"),
+    };
+    if value == 0 {
+        bail!("invalid duration {raw:?}: must be greater than zero");
+    }
+    Ok(value.saturating_mul(scale))
+}
+
+/// What an `updateOpHeads` from `workspace_id` means for its ephemeral entry.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Update {
+    /// Not an ephemeral workspace; nothing to do.
+    Untracked,
+    /// Record or refresh the entry.
+    Renew(EphemeralWorkspace),
+    /// Drop the workspace now.
+    Release,
+}
+
+pub fn classify_update(
+    current: Option<&EphemeralWorkspace>,
+    tags: &BTreeMap<String, String>,
+    now: u64,
+) -> Update {
+    if tags.contains_key(RELEASE_TAG) {
+        return Update::Release;
+    }
+    let ttl_secs = tags
+        .get(TTL_TAG)
+        .and_then(|v| v.parse::<u64>().ok())
+        .filter(|ttl| *ttl > 0)
+        .or(current.map(|entry| entry.ttl_secs));
+    match ttl_secs {
+        Some(ttl_secs) => Update::Renew(EphemeralWorkspace {
+            ttl_secs,
+            expires_at: now.saturating_add(ttl_secs),
+        }),
+        None => Update::Untracked,
+    }
+}
+
+/// Workspaces whose TTL has elapsed at `now`.
+pub fn expired(entries: &BTreeMap<String, EphemeralWorkspace>, now: u64) -> Vec<String> {
+    entries
+        .iter()
+        .filter(|(_, entry)| entry.expires_at <= now)
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ttl_units() {
+        assert_eq!(parse_ttl("90").unwrap(), 90);
+        assert_eq!(parse_ttl("45s").unwrap(), 45);
+        assert_eq!(parse_ttl("30m").unwrap(), 1800);
+        assert_eq!(parse_ttl("2h").unwrap(), 7200);
+        assert_eq!(parse_ttl("1d").unwrap(), 86400);
+        assert!(parse_ttl("0").is_err());
+        assert!(parse_ttl("soon").is_err());
+        assert!(parse_ttl("5w").is_err());
+        // Too large to count in seconds: saturates rather than overflowing.
+        assert_eq!(parse_ttl("999999999999999999d").unwrap(), u64::MAX);
+    }
+
+    #[test]
+    fn ttl_tag_registers_and_later_updates_renew() {
+        let tags = BTreeMap::from([(TTL_TAG.to_string(), "60".to_string())]);
+        let first = classify_update(None, &tags, 1000);
+        assert_eq!(
+            first,
+            Update::Renew(EphemeralWorkspace {
+                ttl_secs: 60,
+                expires_at: 1060
+            })
+        );
+
+        let entry = EphemeralWorkspace {
+            ttl_secs: 60,
+            expires_at: 1060,
+        };
+        assert_eq!(
+            classify_update(Some(&entry), &BTreeMap::new(), 1030),
+            Update::Renew(EphemeralWorkspace {
+                ttl_secs: 60,
+                expires_at: 1090
+            })
+        );
+        assert_eq!(
+            classify_update(None, &BTreeMap::new(), 1030),
+            Update::Untracked
+        );
+    }
+
+    #[test]
+    fn release_tag_wins_and_expiry_is_inclusive() {
+        let tags = BTreeMap::from([
+            (TTL_TAG.to_string(), "60".to_string()),
+            (RELEASE_TAG.to_string(), String::new()),
+        ]);
+        assert_eq!(classify_update(None, &tags, 0), Update::Release);
+
+        let entries = BTreeMap::from([
+            (
+                "ci-1".to_string(),
+                EphemeralWorkspace {
+                    ttl_secs: 10,
+                    expires_at: 100,
+                },
+            ),
+            (
+                "ci-2".to_string(),
+                EphemeralWorkspace {
+                    ttl_secs: 10,
+                    expires_at: 101,
+                },
+            ),
+        ]);
+        assert_eq!(expired(&entries, 100), vec!["ci-1".to_string()]);
+    }
+}
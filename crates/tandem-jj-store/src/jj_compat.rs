@@ -0,0 +1,267 @@
+//! Which jj this build speaks — the jj-lib version and the field numbers of
+//! jj's operation and view protos.
+//!
+//! Operations and views cross the wire as jj's own protobuf encoding, and
+//! their ids are hashes of what each side decodes. A client and server built
+//! against different jj releases that disagree on a field do not fail
+//! cleanly: protobuf drops the fields it does not know, so a view loses
+//! data on the way through or an operation comes back under a different id.
+//! The server advertises both in `getRepoInfo` (`jjVersion`, `opSchema`) and
+//! the client compares them with its own on connect, so the mismatch is
+//! reported by name. Differing jj versions whose protos agree are fine.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The jj-lib release this build is compiled against (kept in step with
+/// `Cargo.toml`; a unit test checks).
+pub const JJ_LIB_VERSION: &str = "0.38.0";
+
+/// Field numbers and names of the messages that hold operations and views,
+/// as jj-lib's `simple_op_store.proto` defines them.
+const MESSAGES: &[(&str, &[(u32, &str)])] = &[
+    (
+        "Operation",
+        &[
+            (1, "view_id"),
+            (2, "parents"),
+            (3, "metadata"),
+            (4, "commit_predecessors"),
+            (5, "stores_commit_predecessors"),
+        ],
+    ),
+    (
+        "OperationMetadata",
+        &[
+            (1, "start_time"),
+            (2, "end_time"),
+            (3, "description"),
+            (4, "hostname"),
+            (5, "username"),
+            (6, "tags"),
+            (7, "is_snapshot"),
+        ],
+    ),
+    (
+        "View",
+        &[
+            (1, "head_ids"),
+            (2, "wc_commit_id"),
+            (3, "git_refs"),
+            (5, "bookmarks"),
+            (6, "local_tags"),
+            (7, "git_head_legacy"),
+            (8, "wc_commit_ids"),
+            (9, "git_head"),
+            (11, "remote_views"),
+            (12, "has_git_refs_migrated_to_remote_tags"),
+        ],
+    ),
+    ("RemoteView", &[(1, "name"), (2, "bookmarks"), (3, "tags")]),
+];
+
+/// This build's `opSchema`: `Message=1,2,3;...` in a fixed order.
+pub fn op_schema() -> String {
+    MESSAGES
+        .iter()
+        .map(|(message, fields)| {
+            let numbers: Vec<String> = fields.iter().map(|(n, _)| n.to_string()).collect();
+            format!("{message}={}", numbers.join(","))
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn parse(schema: &str) -> BTreeMap<&str, BTreeSet<u32>> {
+    schema
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .map(|(message, numbers)| {
+            let numbers = numbers
+                .split(',')
+                .filter_map(|n| n.trim().parse().ok())
+                .collect();
+            (message.trim(), numbers)
+        })
+        .collect()
+}
+
+fn field_name(message: &str, number: u32) -> Option<&'static str> {
+    MESSAGES
+        .iter()
+        .find(|(name, _)| *name == message)?
+        .1
+        .iter()
+        .find(|(n, _)| *n == number)
+        .map(|(_, name)| *name)
+}
+
+/// Why a server advertising `server_schema`, built against jj
+/// `server_jj_version`, cannot share operations with this build; `None`
+/// when the protos agree or the server did not say.
+pub fn op_schema_problem(server_jj_version: &str, server_schema: &str) -> Option<String> {
+    let ours = op_schema();
+    if server_schema.is_empty() || server_schema == ours {
+        return None;
+    }
+    let ours = parse(&ours);
+    let theirs = parse(server_schema);
+    let messages: BTreeSet<&str> = ours.keys().chain(theirs.keys()).copied().collect();
+    let none = BTreeSet::new();
+    let mut differences = Vec::new();
+    for message in messages {
+        let here = ours.get(message).unwrap_or(&none);
+        let there = theirs.get(message).unwrap_or(&none);
+        for number in there.difference(here) {
+            differences.push(format!(
+                "{message} field {number} is unknown to this tandem"
+            ));
+        }
+        for number in here.difference(there) {
+            let name = field_name(message, *number).unwrap_or("?");
+            differences.push(format!(
+                "{message}.{name} (field {number}) is unknown to the server"
+            ));
+        }
+    }
+    if differences.is_empty() {
+        // Same fields, listed differently.
+        return None;
+    }
+    Some(format!(
+        "the server was built against jj {server_jj_version} and this tandem against jj \
+         {JJ_LIB_VERSION}, and their operation formats differ: {}; install tandem builds \
+         of the same jj version on both sides (op_schema)",
+        differences.join(", ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use jj_lib::protos::simple_op_store as proto;
+    use prost::Message as _;
+
+    use super::*;
+
+    /// Field numbers present in an encoded message.
+    fn encoded_fields(mut bytes: &[u8]) -> BTreeSet<u32> {
+        fn varint(bytes: &mut &[u8]) -> u64 {
+            let mut value = 0;
+            for shift in (0..64).step_by(7) {
+                let byte = bytes[0];
+                *bytes = &bytes[1..];
+                value |= u64::from(byte & 0x7f) << shift;
+                if byte < 0x80 {
+                    break;
+                }
+            }
+            value
+        }
+        let mut fields = BTreeSet::new();
+        while !bytes.is_empty() {
+            let key = varint(&mut bytes);
+            fields.insert((key >> 3) as u32);
+            match key & 7 {
+                0 => {
+                    varint(&mut bytes);
+                }
+                1 => bytes = &bytes[8..],
+                2 => {
+                    let len = varint(&mut bytes) as usize;
+                    bytes = &bytes[len..];
+                }
+                5 => bytes = &bytes[4..],
+                wire_type => panic!("unexpected wire type {wire_type}"),
+            }
+        }
+        fields
+    }
+
+    fn listed(message: &str) -> BTreeSet<u32> {
+        parse(&op_schema())[message].clone()
+    }
+
+    // The probes name every field without `..Default::default()`, so a jj
+    // upgrade that adds one stops this from compiling until MESSAGES has it.
+    #[test]
+    fn messages_match_jj_lib_protos() {
+        let metadata = proto::OperationMetadata {
+            start_time: Some(proto::Timestamp::default()),
+            end_time: Some(proto::Timestamp::default()),
+            description: "d".into(),
+            hostname: "h".into(),
+            username: "u".into(),
+            is_snapshot: true,
+            tags: HashMap::from([("k".into(), "v".into())]),
+        };
+        let operation = proto::Operation {
+            view_id: vec![1],
+            parents: vec![vec![2]],
+            metadata: Some(metadata.clone()),
+            commit_predecessors: vec![proto::CommitPredecessors::default()],
+            stores_commit_predecessors: true,
+        };
+        let remote_view = proto::RemoteView {
+            name: "origin".into(),
+            bookmarks: vec![proto::RemoteRef::default()],
+            tags: vec![proto::RemoteRef::default()],
+        };
+        #[allow(deprecated)]
+        let view = proto::View {
+            head_ids: vec![vec![1]],
+            wc_commit_id: vec![2],
+            wc_commit_ids: HashMap::from([("default".into(), vec![3])]),
+            bookmarks: vec![proto::Bookmark::default()],
+            local_tags: vec![proto::Tag::default()],
+            remote_views: vec![remote_view.clone()],
+            git_refs: vec![proto::GitRef::default()],
+            git_head_legacy: vec![4],
+            git_head: Some(proto::RefTarget::default()),
+            has_git_refs_migrated_to_remote_tags: true,
+        };
+        assert_eq!(
+            encoded_fields(&operation.encode_to_vec()),
+            listed("Operation")
+        );
+        assert_eq!(
+            encoded_fields(&metadata.encode_to_vec()),
+            listed("OperationMetadata")
+        );
+        assert_eq!(encoded_fields(&view.encode_to_vec()), listed("View"));
+        assert_eq!(
+            encoded_fields(&remote_view.encode_to_vec()),
+            listed("RemoteView")
+        );
+    }
+
+    #[test]
+    fn jj_lib_version_matches_the_manifest() {
+        let manifest = include_str!("../Cargo.toml");
+        assert!(
+            manifest.contains(&format!("jj-lib = {{ version = \"{JJ_LIB_VERSION}\"")),
+            "update JJ_LIB_VERSION to the jj-lib version in Cargo.toml"
+        );
+    }
+
+    #[test]
+    fn names_the_fields_that_differ() {
+        assert_eq!(op_schema_problem("0.38.0", &op_schema()), None);
+        assert_eq!(op_schema_problem("0.30.0", ""), None);
+
+        let newer = op_schema().replace("View=1,", "View=13,1,");
+        let problem = op_schema_problem("0.40.0", &newer).unwrap();
+        assert!(problem.contains("built against jj 0.40.0"), "{problem}");
+        assert!(problem.contains("View field 13 is unknown to this tandem"));
+        assert!(problem.ends_with("(op_schema)"));
+
+        let older = op_schema().replace(",5;", ";");
+        let problem = op_schema_problem("0.29.0", &older).unwrap();
+        assert!(
+            problem.contains(
+                "Operation.stores_commit_predecessors (field 5) is unknown to the server"
+            ),
+            "{problem}"
+        );
+    }
+}
@@ -0,0 +1,326 @@
+//! Client operation journal — what a command asked the server, and how it
+//! went, for bug reports.
+//!
+//! With `tandem.journal = true` in jj's config, every command that opens a
+//! tandem store appends one JSON line to `.jj/repo/tandem/journal.jsonl`
+//! when it exits: its arguments, the server, each RPC (method, latency,
+//! error, heads version returned), the heads versions it saw, how many head
+//! updates lost the CAS, and the exit code. `tandem journal show --last 10`
+//! prints the newest entries.
+//!
+//! The file is rotated to `journal.1.jsonl` (then `journal.2.jsonl`) once
+//! it passes [`MAX_FILE_BYTES`], so it never grows past about three times
+//! that. An entry keeps at most [`MAX_RPCS`] calls and counts the rest.
+//!
+//! Arguments are recorded as given, commit messages included; look over an
+//! entry before attaching it to a public issue.
+
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Journal file, under the repo's `tandem/` directory.
+pub const FILE_NAME: &str = "journal.jsonl";
+
+/// Size at which the journal is rotated.
+pub const MAX_FILE_BYTES: u64 = 4 * 1024 * 1024;
+
+/// Rotated files kept (`journal.1.jsonl`, `journal.2.jsonl`).
+const ROTATED_FILES: usize = 2;
+
+/// Calls one entry keeps; later ones are only counted.
+pub const MAX_RPCS: usize = 2_000;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static CURRENT: Mutex<Option<Recording>> = Mutex::new(None);
+
+/// One command, as a line of the journal.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Entry {
+    pub started_at_millis: u64,
+    pub pid: u32,
+    /// The command line, program name first.
+    pub command: Vec<String>,
+    pub server: String,
+    pub duration_ms: u64,
+    pub exit_code: u8,
+    #[serde(default)]
+    pub rpcs: Vec<RpcCall>,
+    /// Calls past [`MAX_RPCS`], not listed in `rpcs`.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub rpcs_dropped: u64,
+    /// Distinct heads versions the server returned, in the order seen.
+    #[serde(default)]
+    pub versions_seen: Vec<u64>,
+    /// `updateOpHeads` calls that lost the CAS and were retried.
+    #[serde(default)]
+    pub cas_retries: u64,
+}
+
+/// One RPC within an [`Entry`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RpcCall {
+    /// Milliseconds from the start of the command.
+    pub at_ms: u64,
+    pub method: String,
+    /// Round trip, in milliseconds.
+    pub ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Heads version in the reply, for calls that return one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub cas_lost: bool,
+}
+
+/// What the journal notes about one call's reply.
+#[derive(Debug, Default)]
+pub(crate) struct Outcome {
+    pub error: Option<String>,
+    pub version: Option<u64>,
+    pub cas_lost: bool,
+}
+
+impl Outcome {
+    pub fn version(version: u64) -> Self {
+        Self {
+            version: Some(version),
+            ..Self::default()
+        }
+    }
+}
+
+struct Recording {
+    path: PathBuf,
+    started: Instant,
+    entry: Entry,
+}
+
+fn is_zero(n: &u64) -> bool {
+    *n == 0
+}
+
+/// Start journaling this command into `repo_dir` (the workspace's
+/// `.jj/repo`). Later calls, from the command's other stores, are no-ops.
+pub fn enable(repo_dir: &Path, server: &str) {
+    let mut current = CURRENT.lock().unwrap();
+    if current.is_some() {
+        return;
+    }
+    *current = Some(Recording {
+        path: repo_dir.join("tandem").join(FILE_NAME),
+        started: Instant::now(),
+        entry: Entry {
+            started_at_millis: unix_millis(SystemTime::now()),
+            pid: std::process::id(),
+            command: std::env::args().collect(),
+            server: server.to_string(),
+            ..Entry::default()
+        },
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Note one finished call. Cheap when the journal is off.
+pub(crate) fn record(method: &str, elapsed: Duration, outcome: Outcome) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let mut current = CURRENT.lock().unwrap();
+    let Some(recording) = current.as_mut() else {
+        return;
+    };
+    let entry = &mut recording.entry;
+    if let Some(version) = outcome.version {
+        if !entry.versions_seen.contains(&version) {
+            entry.versions_seen.push(version);
+        }
+    }
+    if outcome.cas_lost {
+        entry.cas_retries += 1;
+    }
+    if entry.rpcs.len() >= MAX_RPCS {
+        entry.rpcs_dropped += 1;
+        return;
+    }
+    let at = recording.started.elapsed().saturating_sub(elapsed);
+    entry.rpcs.push(RpcCall {
+        at_ms: at.as_millis() as u64,
+        method: method.to_string(),
+        ms: (elapsed.as_secs_f64() * 10_000.0).round() / 10.0,
+        error: outcome.error,
+        version: outcome.version,
+        cas_lost: outcome.cas_lost,
+    });
+}
+
+/// Write this command's entry, if it was journaled. Called once, as the
+/// command exits; failing to write only logs.
+pub fn finish(exit_code: u8) {
+    ENABLED.store(false, Ordering::Relaxed);
+    let Some(recording) = CURRENT.lock().unwrap().take() else {
+        return;
+    };
+    let mut entry = recording.entry;
+    entry.duration_ms = recording.started.elapsed().as_millis() as u64;
+    entry.exit_code = exit_code;
+    if let Err(err) = append(&recording.path, &entry) {
+        tracing::debug!(error = %err, "could not write the tandem journal");
+    }
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let len = std::fs::metadata(path).map(|meta| meta.len()).unwrap_or(0);
+    if len > 0 && len + line.len() as u64 > MAX_FILE_BYTES {
+        rotate(path)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("opening {}", path.display()))?;
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// `journal.1.jsonl` for `n = 1`, and so on; `journal.jsonl` for `n = 0`.
+fn generation(path: &Path, n: usize) -> PathBuf {
+    if n == 0 {
+        return path.to_path_buf();
+    }
+    path.with_file_name(format!("journal.{n}.jsonl"))
+}
+
+fn rotate(path: &Path) -> Result<()> {
+    let _ = std::fs::remove_file(generation(path, ROTATED_FILES));
+    for n in (0..ROTATED_FILES).rev() {
+        let from = generation(path, n);
+        if from.exists() {
+            std::fs::rename(&from, generation(path, n + 1))
+                .with_context(|| format!("rotating {}", from.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// The newest `last` entries in the journal under `repo_dir`, oldest first,
+/// reading rotated files as needed. Lines that do not parse are skipped.
+pub fn read_last(repo_dir: &Path, last: usize) -> Result<Vec<Entry>> {
+    let path = repo_dir.join("tandem").join(FILE_NAME);
+    let mut newest_first = Vec::new();
+    for n in 0..=ROTATED_FILES {
+        let file = generation(&path, n);
+        let text = match std::fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => {
+                return Err(err).with_context(|| format!("reading {}", file.display()));
+            }
+        };
+        for line in text.lines().rev() {
+            if newest_first.len() == last {
+                break;
+            }
+            if let Ok(entry) = serde_json::from_str::<Entry>(line) {
+                newest_first.push(entry);
+            }
+        }
+    }
+    newest_first.reverse();
+    Ok(newest_first)
+}
+
+impl Entry {
+    /// Calls by method, e.g. `getObject 12, putObject 3`, busiest first.
+    pub fn describe_rpcs(&self) -> String {
+        let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+        for call in &self.rpcs {
+            *counts.entry(&call.method).or_default() += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+            .iter()
+            .map(|(method, count)| format!("{method} {count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    pub fn failed_rpcs(&self) -> impl Iterator<Item = &RpcCall> {
+        self.rpcs.iter().filter(|call| call.error.is_some())
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(exit_code: u8) -> Entry {
+        Entry {
+            command: vec!["tandem".to_string(), "log".to_string()],
+            exit_code,
+            ..Entry::default()
+        }
+    }
+
+    #[test]
+    fn rotates_and_reads_across_files() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("tandem").join(FILE_NAME);
+        append(&path, &entry(0)).unwrap();
+        rotate(&path).unwrap();
+        append(&path, &entry(1)).unwrap();
+        rotate(&path).unwrap();
+        append(&path, &entry(2)).unwrap();
+        rotate(&path).unwrap();
+        append(&path, &entry(3)).unwrap();
+
+        // The oldest fell off the end.
+        assert!(!generation(&path, ROTATED_FILES + 1).exists());
+        let codes = |entries: Vec<Entry>| entries.iter().map(|e| e.exit_code).collect::<Vec<_>>();
+        assert_eq!(codes(read_last(tmp.path(), 10).unwrap()), vec![1, 2, 3]);
+        assert_eq!(codes(read_last(tmp.path(), 2).unwrap()), vec![2, 3]);
+        assert!(read_last(&tmp.path().join("elsewhere"), 10)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn summarizes_calls_by_method() {
+        let call = |method: &str, error: Option<&str>| RpcCall {
+            method: method.to_string(),
+            error: error.map(str::to_string),
+            ..RpcCall::default()
+        };
+        let entry = Entry {
+            rpcs: vec![
+                call("putObject", None),
+                call("getObject", None),
+                call("putObject", Some("overloaded")),
+            ],
+            ..Entry::default()
+        };
+        assert_eq!(entry.describe_rpcs(), "putObject 2, getObject 1");
+        assert_eq!(entry.failed_rpcs().count(), 1);
+    }
+}
@@ -0,0 +1,94 @@
+//! tandem-jj-store — jj-lib stores backed by a tandem server.
+//!
+//! The `tandem` binary is stock jj plus these stores. Other programs built
+//! on jj-lib (TUIs, CI bots, review tools) can open a tandem workspace the
+//! same way, without shelling out to `tandem`:
+//!
+//! ```no_run
+//! let mut factories = jj_lib::repo::StoreFactories::default();
+//! tandem_jj_store::register(&mut factories);
+//! // Pass `factories` to `Workspace::load` or `RepoLoader::init_from_file_system`.
+//! ```
+//!
+//! A workspace's stores read the same `[tandem]` jj config table (see
+//! [`settings`]) and environment variables (`TANDEM_SERVER`, ...) as the
+//! `tandem` binary does.
+
+pub mod backend;
+pub mod drain;
+pub mod ephemeral;
+pub mod jj_compat;
+pub mod journal;
+pub mod maintenance;
+pub mod metadata_schema;
+pub mod offline;
+pub mod offline_queue;
+pub mod op_heads_store;
+pub mod op_store;
+pub mod ops;
+pub mod profile;
+pub mod proto_convert;
+pub mod read_mirror;
+pub mod redirect;
+pub mod rpc;
+pub mod rpc_record;
+pub mod server_address;
+pub mod settings;
+pub mod sharded_op_store;
+pub mod time_travel;
+pub mod tmp_file;
+pub mod tree_hash;
+pub mod view_delta;
+pub mod watchdog;
+
+pub use tandem_client::{limits, tandem_capnp};
+
+use jj_lib::repo::StoreFactories;
+
+/// `store/type` of a tandem backend.
+pub const BACKEND_NAME: &str = "tandem";
+/// `op_store/type` of a tandem op store.
+pub const OP_STORE_NAME: &str = "tandem_op_store";
+/// `op_heads/type` of a tandem op heads store.
+pub const OP_HEADS_STORE_NAME: &str = "tandem_op_heads_store";
+
+/// Register the tandem backend, op store, and op heads store, so jj-lib can
+/// load repos whose store type is `tandem`, along with the sharded op store
+/// a tandem server keeps its own operations in.
+pub fn register(factories: &mut StoreFactories) {
+    factories.add_backend(
+        BACKEND_NAME,
+        Box::new(|settings, store_path| {
+            Ok(Box::new(backend::TandemBackend::load(
+                settings, store_path,
+            )?))
+        }),
+    );
+
+    factories.add_op_store(
+        OP_STORE_NAME,
+        Box::new(|settings, store_path, root_data| {
+            Ok(Box::new(op_store::TandemOpStore::load(
+                settings, store_path, root_data,
+            )?))
+        }),
+    );
+
+    factories.add_op_store(
+        sharded_op_store::SHARDED_OP_STORE_NAME,
+        Box::new(|_settings, store_path, root_data| {
+            Ok(Box::new(sharded_op_store::ShardedOpStore::load(
+                store_path, root_data,
+            )))
+        }),
+    );
+
+    factories.add_op_heads_store(
+        OP_HEADS_STORE_NAME,
+        Box::new(|settings, store_path| {
+            Ok(Box::new(op_heads_store::TandemOpHeadsStore::load(
+                settings, store_path,
+            )?))
+        }),
+    );
+}
@@ -0,0 +1,148 @@
+//! Maintenance mode — reject new writes with an operator-supplied notice.
+//!
+//! `tandem server maintenance` flips the daemon into maintenance through the
+//! control socket. While it is active, `putObject`, `putOperation`,
+//! `putView`, and `updateOpHeads` fail with a notice of the form
+//!
+//!   tandem server is in maintenance until 2026-10-16T18:00:00Z: <message>
+//!
+//! Reads keep working and in-flight calls finish. Clients recognise the
+//! notice by its prefix and show it as-is instead of a generic RPC failure.
+//! Maintenance ends at `until`, or earlier via `tandem server maintenance --off`.
+
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+/// Every maintenance error starts with this; clients match on it.
+pub const NOTICE_PREFIX: &str = "tandem server is in maintenance";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceNotice {
+    /// Unix seconds at which maintenance ends on its own.
+    pub until: u64,
+    pub message: String,
+}
+
+impl MaintenanceNotice {
+    /// The text returned to clients for rejected writes.
+    pub fn describe(&self) -> String {
+        let mut text = format!("{NOTICE_PREFIX} until {}", format_utc(self.until));
+        if !self.message.is_empty() {
+            text.push_str(": ");
+            text.push_str(&self.message);
+        }
+        text
+    }
+}
+
+/// Maintenance state shared by the RPC server and the control socket.
+#[derive(Debug, Clone, Default)]
+pub struct Maintenance(Arc<Mutex<Option<MaintenanceNotice>>>);
+
+impl Maintenance {
+    pub fn enter(&self, notice: MaintenanceNotice) {
+        *self.0.lock().unwrap() = Some(notice);
+    }
+
+    pub fn exit(&self) -> Option<MaintenanceNotice> {
+        self.0.lock().unwrap().take()
+    }
+
+    /// The current notice, clearing it once its `until` has passed.
+    pub fn active(&self) -> Option<MaintenanceNotice> {
+        let mut slot = self.0.lock().unwrap();
+        if slot
+            .as_ref()
+            .is_some_and(|notice| notice.until <= now_secs())
+        {
+            *slot = None;
+        }
+        slot.clone()
+    }
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Extract the maintenance notice from a remote error description, dropping
+/// any RPC framing in front of it.
+pub fn notice_from_error(description: &str) -> Option<&str> {
+    description
+        .find(NOTICE_PREFIX)
+        .map(|start| description[start..].trim_end())
+}
+
+/// Format unix seconds as an RFC 3339 UTC timestamp.
+pub fn format_utc(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let rem = secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        rem / 3600,
+        (rem % 3600) / 60,
+        rem % 60
+    )
+}
+
+/// Days since 1970-01-01 to (year, month, day), proleptic Gregorian.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_utc_timestamps() {
+        assert_eq!(format_utc(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_utc(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format_utc(1_792_108_800 + 3_723), "2026-10-16T01:02:03Z");
+    }
+
+    #[test]
+    fn notice_survives_rpc_framing() {
+        let notice = MaintenanceNotice {
+            until: 0,
+            message: "migrating disks".to_string(),
+        };
+        let framed = format!("remote exception: {}", notice.describe());
+        assert_eq!(
+            notice_from_error(&framed),
+            Some("tandem server is in maintenance until 1970-01-01T00:00:00Z: migrating disks")
+        );
+        assert_eq!(notice_from_error("Failed: disk full"), None);
+    }
+
+    #[test]
+    fn expired_notice_is_cleared() {
+        let maintenance = Maintenance::default();
+        maintenance.enter(MaintenanceNotice {
+            until: now_secs() + 3600,
+            message: String::new(),
+        });
+        assert!(maintenance.active().is_some());
+        maintenance.enter(MaintenanceNotice {
+            until: 1,
+            message: String::new(),
+        });
+        assert!(maintenance.active().is_none());
+    }
+}
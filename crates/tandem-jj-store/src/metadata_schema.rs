@@ -0,0 +1,489 @@
+//! Schema versions for the server's metadata files in `.jj/repo/tandem/`.
+//!
+//! `heads.json`, `integration.json`, `abandoned-ops.json`, and
+//! `redirect.json` carry a `schemaVersion`, as did the `op-tags.json` that
+//! `op-tags.jsonl` replaced; a file without one predates versioning and is
+//! version 0. The JSON Lines journals, `heads-history.jsonl` and
+//! `op-tags.jsonl`, hold their version in a first line of its own
+//! (`{"schemaVersion":1}`), and their migrations apply to each record line.
+//!
+//! Each [`Format`] lists the migrations from one version to the next. On
+//! startup [`upgrade`] brings every file to the version this binary writes,
+//! keeping the original as `<file>.v<old>.bak`, and [`read`] and
+//! [`read_journal`] apply the same migrations in memory, so a file restored
+//! by hand still loads.
+//!
+//! A file with a version this binary does not know was written by a newer
+//! tandem. Rewriting it would silently drop whatever the newer format added,
+//! so the server refuses to start and says how to get going again.
+//!
+//! Changing a format means appending a [`Migration`] to it; the version it
+//! writes is the number of migrations.
+
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// The field holding a file's version.
+pub const VERSION_FIELD: &str = "schemaVersion";
+
+/// One metadata file's format history.
+pub struct Format {
+    pub file: &'static str,
+    /// `migrations[n]` upgrades version `n` to `n + 1`.
+    migrations: &'static [Migration],
+}
+
+/// One step between versions, applied to the file's top-level object.
+pub struct Migration {
+    pub summary: &'static str,
+    apply: fn(&mut Map<String, Value>),
+}
+
+/// Version 1 only adds [`VERSION_FIELD`], which [`stamp`] writes.
+const VERSIONED: Migration = Migration {
+    summary: "record the schema version",
+    apply: |_| {},
+};
+
+/// CAS version, workspace heads, and ephemeral workspaces.
+pub const HEADS: Format = Format {
+    file: "heads.json",
+    migrations: &[VERSIONED],
+};
+
+/// Integration worker state (serve --enable-integration-workspace).
+pub const INTEGRATION: Format = Format {
+    file: "integration.json",
+    migrations: &[VERSIONED],
+};
+
+/// Operation tags, keyed by operation id. Superseded by `op-tags.jsonl`,
+/// and only read to import an old server's tags into it.
+pub const OPERATION_TAGS: Format = Format {
+    file: "op-tags.json",
+    migrations: &[VERSIONED],
+};
+
+/// Heads replaced by op log rewrites.
+pub const ABANDONED_OPERATIONS: Format = Format {
+    file: "abandoned-ops.json",
+    migrations: &[VERSIONED],
+};
+
+/// Where the server sends clients after the repo moved (`tandem migrate`,
+/// `tandem server redirect`).
+pub const REDIRECT: Format = Format {
+    file: "redirect.json",
+    migrations: &[VERSIONED],
+};
+
+/// Every versioned JSON file, in the order [`upgrade`] visits them.
+pub const FORMATS: &[&Format] = &[&HEADS, &INTEGRATION, &ABANDONED_OPERATIONS, &REDIRECT];
+
+/// Version 1 of a journal only adds the header line.
+const JOURNAL_HEADER: Migration = Migration {
+    summary: "record the schema version in a header line",
+    apply: |_| {},
+};
+
+/// Heads-version transitions, one per line.
+pub const HEADS_HISTORY: Format = Format {
+    file: "heads-history.jsonl",
+    migrations: &[JOURNAL_HEADER],
+};
+
+/// Operation tags, one operation's additions per line.
+pub const OPERATION_TAG_LOG: Format = Format {
+    file: "op-tags.jsonl",
+    migrations: &[JOURNAL_HEADER],
+};
+
+/// Every versioned JSON Lines journal, in the order [`upgrade`] visits them.
+pub const JOURNALS: &[&Format] = &[&HEADS_HISTORY, &OPERATION_TAG_LOG];
+
+impl Format {
+    /// The version this binary reads and writes.
+    pub fn current(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// Where [`upgrade`] keeps the file as it was at `version`.
+    pub fn backup_path(&self, tandem_dir: &Path, version: u32) -> PathBuf {
+        tandem_dir.join(format!("{}.v{version}.bak", self.file))
+    }
+
+    /// Bring `object` to [`Format::current`]. Returns the version it had.
+    fn migrate(&self, path: &Path, object: &mut Map<String, Value>) -> Result<u32> {
+        let found = match object.get(VERSION_FIELD) {
+            None => 0,
+            Some(value) => value
+                .as_u64()
+                .and_then(|n| u32::try_from(n).ok())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "{}: {VERSION_FIELD} is not a version: {value}",
+                        path.display()
+                    )
+                })?,
+        };
+        if found > self.current() {
+            return Err(self.too_new(path, found));
+        }
+        for migration in &self.migrations[found as usize..] {
+            (migration.apply)(object);
+        }
+        stamp(self, object);
+        Ok(found)
+    }
+
+    fn too_new(&self, path: &Path, found: u32) -> anyhow::Error {
+        let current = self.current();
+        let mut fix =
+            String::from("serve this repo with the tandem that upgraded it, or a newer one");
+        let backup = path
+            .parent()
+            .map(|dir| self.backup_path(dir, current))
+            .filter(|backup| backup.exists());
+        if let Some(backup) = backup {
+            fix.push_str(&format!(
+                "\n  or, to go back to this tandem, stop the server and copy {} over {} \
+                 (changes made since the upgrade are lost)",
+                backup.display(),
+                path.display()
+            ));
+        }
+        anyhow!(
+            "{} has schema version {found}, written by a newer tandem; \
+             tandem {} reads up to version {current}\n  fix: {fix}",
+            path.display(),
+            env!("CARGO_PKG_VERSION"),
+        )
+    }
+}
+
+fn stamp(format: &Format, object: &mut Map<String, Value>) {
+    object.insert(VERSION_FIELD.to_string(), Value::from(format.current()));
+}
+
+fn parse(path: &Path, bytes: &[u8]) -> Result<Map<String, Value>> {
+    match serde_json::from_slice(bytes).with_context(|| format!("parse {}", path.display()))? {
+        Value::Object(object) => Ok(object),
+        _ => bail!("{} is not a JSON object", path.display()),
+    }
+}
+
+/// Read `path` as `format`, migrating older versions in memory.
+pub fn read<T: DeserializeOwned>(format: &Format, path: &Path) -> Result<T> {
+    let bytes = fs::read(path)?;
+    let mut object = parse(path, &bytes)?;
+    format.migrate(path, &mut object)?;
+    serde_json::from_value(Value::Object(object))
+        .with_context(|| format!("parse {}", path.display()))
+}
+
+/// Write `value` to `path` as the current version of `format`.
+pub fn write<T: Serialize>(format: &Format, path: &Path, value: &T) -> Result<()> {
+    let Value::Object(mut object) = serde_json::to_value(value)? else {
+        bail!("{} must be written as a JSON object", format.file);
+    };
+    stamp(format, &mut object);
+    fs::write(path, serde_json::to_vec_pretty(&object)?)?;
+    Ok(())
+}
+
+/// A journal's header line at the current version of `format`.
+fn journal_header(format: &Format) -> String {
+    let mut header = Map::new();
+    stamp(format, &mut header);
+    Value::Object(header).to_string()
+}
+
+/// The version a journal's first line declares; `None` when the line is a
+/// record, as in journals from before versioning.
+fn journal_version(path: &Path, first_line: &str) -> Result<Option<u32>> {
+    let Ok(Value::Object(object)) = serde_json::from_str::<Value>(first_line) else {
+        return Ok(None);
+    };
+    match (object.len(), object.get(VERSION_FIELD)) {
+        (1, Some(value)) => value
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .map(Some)
+            .ok_or_else(|| {
+                anyhow!(
+                    "{}: {VERSION_FIELD} is not a version: {value}",
+                    path.display()
+                )
+            }),
+        _ => Ok(None),
+    }
+}
+
+/// Split journal `contents` into the version it was written at and its
+/// record lines, migrated to the current version. Lines that do not parse
+/// (e.g. a torn final write) are passed through for the caller to skip.
+fn parse_journal(format: &Format, path: &Path, contents: &str) -> Result<(u32, Vec<String>)> {
+    let mut lines = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .peekable();
+    let found = match lines.peek() {
+        Some(first) => journal_version(path, first)?,
+        None => Some(format.current()),
+    };
+    if found.is_some() {
+        lines.next();
+    }
+    let found = found.unwrap_or(0);
+    if found > format.current() {
+        return Err(format.too_new(path, found));
+    }
+    let migrations = &format.migrations[found as usize..];
+    let records = lines
+        .map(|line| match serde_json::from_str::<Value>(line) {
+            Ok(Value::Object(mut object)) if !migrations.is_empty() => {
+                for migration in migrations {
+                    (migration.apply)(&mut object);
+                }
+                Value::Object(object).to_string()
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+    Ok((found, records))
+}
+
+/// The record lines of the journal at `path`, migrated to the current
+/// version of `format`; none when the file does not exist. Lines that do not
+/// parse are passed through for the caller to skip.
+pub fn read_journal(format: &Format, path: &Path) -> Result<Vec<String>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(parse_journal(format, path, &contents)?.1),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err).with_context(|| format!("read {}", path.display())),
+    }
+}
+
+/// Replace the journal at `path` with `records`, after the header for the
+/// current version of `format`.
+pub fn write_journal(format: &Format, path: &Path, records: &[String]) -> Result<()> {
+    let mut contents = journal_header(format);
+    contents.push('\n');
+    for record in records {
+        contents.push_str(record);
+        contents.push('\n');
+    }
+    let tmp = path.with_extension("jsonl.tmp");
+    fs::write(&tmp, contents).with_context(|| format!("write {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))
+}
+
+/// Append `record` to the journal at `path`, starting the file with the
+/// header for the current version of `format` if it is new or empty.
+pub fn append_journal(format: &Format, path: &Path, record: &str) -> Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("open {}", path.display()))?;
+    let mut line = String::new();
+    if file.metadata()?.len() == 0 {
+        line.push_str(&journal_header(format));
+        line.push('\n');
+    }
+    line.push_str(record);
+    line.push('\n');
+    file.write_all(line.as_bytes())
+        .with_context(|| format!("append to {}", path.display()))
+}
+
+/// A file [`upgrade`] migrated.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Upgraded {
+    pub file: &'static str,
+    pub from: u32,
+    pub to: u32,
+    /// What each migration did, oldest first.
+    pub changes: Vec<&'static str>,
+    pub backup: PathBuf,
+}
+
+/// Migrate every metadata file and journal in `tandem_dir` to the current
+/// version, backing up the originals. Fails, changing nothing further, on a file
+/// from a newer tandem.
+pub fn upgrade(tandem_dir: &Path) -> Result<Vec<Upgraded>> {
+    let mut upgraded = Vec::new();
+    for format in FORMATS {
+        let path = tandem_dir.join(format.file);
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+        };
+        let mut object = parse(&path, &bytes)?;
+        let from = format.migrate(&path, &mut object)?;
+        if from == format.current() {
+            continue;
+        }
+        let backup = format.backup_path(tandem_dir, from);
+        fs::write(&backup, &bytes).with_context(|| format!("write {}", backup.display()))?;
+        fs::write(&path, serde_json::to_vec_pretty(&object)?)
+            .with_context(|| format!("write {}", path.display()))?;
+        upgraded.push(Upgraded::new(format, from, backup));
+    }
+    for format in JOURNALS {
+        let path = tandem_dir.join(format.file);
+        let contents = match fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+        };
+        let (from, records) = parse_journal(format, &path, &contents)?;
+        if from == format.current() {
+            continue;
+        }
+        let backup = format.backup_path(tandem_dir, from);
+        fs::write(&backup, &contents).with_context(|| format!("write {}", backup.display()))?;
+        write_journal(format, &path, &records)?;
+        upgraded.push(Upgraded::new(format, from, backup));
+    }
+    Ok(upgraded)
+}
+
+impl Upgraded {
+    fn new(format: &Format, from: u32, backup: PathBuf) -> Self {
+        Self {
+            file: format.file,
+            from,
+            to: format.current(),
+            changes: format.migrations[from as usize..]
+                .iter()
+                .map(|migration| migration.summary)
+                .collect(),
+            backup,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unversioned_files_are_upgraded_once_with_a_backup() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let original = br#"{"version":7,"workspaceHeads":{"default":"ab"}}"#;
+        fs::write(dir.path().join("heads.json"), original).unwrap();
+
+        let upgraded = upgrade(dir.path()).unwrap();
+        assert_eq!(
+            upgraded,
+            vec![Upgraded {
+                file: "heads.json",
+                from: 0,
+                to: HEADS.current(),
+                changes: vec!["record the schema version"],
+                backup: dir.path().join("heads.json.v0.bak"),
+            }]
+        );
+        assert_eq!(
+            fs::read(dir.path().join("heads.json.v0.bak")).unwrap(),
+            original
+        );
+        let heads: Value = read(&HEADS, &dir.path().join("heads.json")).unwrap();
+        assert_eq!(heads[VERSION_FIELD], HEADS.current());
+        assert_eq!(heads["version"], 7);
+        assert!(upgrade(dir.path()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reads_migrate_in_memory_and_writes_stamp() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("op-tags.json");
+        fs::write(&path, br#"{"operations":{}}"#).unwrap();
+        let tags: Value = read(&OPERATION_TAGS, &path).unwrap();
+        assert_eq!(tags[VERSION_FIELD], OPERATION_TAGS.current());
+
+        write(
+            &OPERATION_TAGS,
+            &path,
+            &serde_json::json!({"operations": {}}),
+        )
+        .unwrap();
+        let written: Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        assert_eq!(written[VERSION_FIELD], OPERATION_TAGS.current());
+    }
+
+    #[test]
+    fn newer_versions_are_refused_with_a_way_back() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("heads.json");
+        fs::write(&path, br#"{"schemaVersion":99,"version":3}"#).unwrap();
+
+        let err = upgrade(dir.path()).unwrap_err().to_string();
+        assert!(err.contains("schema version 99"), "{err}");
+        assert!(err.contains("newer tandem"), "{err}");
+        assert!(!err.contains("copy"), "{err}");
+        assert!(read::<Value>(&HEADS, &path).is_err());
+
+        fs::write(HEADS.backup_path(dir.path(), HEADS.current()), b"{}").unwrap();
+        let err = upgrade(dir.path()).unwrap_err().to_string();
+        assert!(err.contains("heads.json.v1.bak"), "{err}");
+        // Nothing was rewritten.
+        assert_eq!(
+            fs::read(&path).unwrap(),
+            br#"{"schemaVersion":99,"version":3}"#
+        );
+    }
+
+    #[test]
+    fn journals_get_a_header_line_and_keep_their_records() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("heads-history.jsonl");
+        let original = "{\"version\":1,\"heads\":[]}\n{\"version\":2,\"heads\":[]}\n";
+        fs::write(&path, original).unwrap();
+
+        let upgraded = upgrade(dir.path()).unwrap();
+        assert_eq!(upgraded.len(), 1);
+        assert_eq!(upgraded[0].file, "heads-history.jsonl");
+        assert_eq!(upgraded[0].from, 0);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("heads-history.jsonl.v0.bak")).unwrap(),
+            original
+        );
+        let contents = fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().next(), Some(r#"{"schemaVersion":1}"#));
+        assert_eq!(read_journal(&HEADS_HISTORY, &path).unwrap().len(), 2);
+        assert!(upgrade(dir.path()).unwrap().is_empty());
+
+        append_journal(&HEADS_HISTORY, &path, r#"{"version":3,"heads":[]}"#).unwrap();
+        assert_eq!(read_journal(&HEADS_HISTORY, &path).unwrap().len(), 3);
+
+        let fresh = dir.path().join("op-tags.jsonl");
+        append_journal(&OPERATION_TAG_LOG, &fresh, r#"{"op":"ab","tags":{}}"#).unwrap();
+        assert_eq!(
+            fs::read_to_string(&fresh).unwrap(),
+            "{\"schemaVersion\":1}\n{\"op\":\"ab\",\"tags\":{}}\n"
+        );
+    }
+
+    #[test]
+    fn newer_journals_are_refused() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("op-tags.jsonl");
+        fs::write(
+            &path,
+            "{\"schemaVersion\":5}\n{\"op\":\"ab\",\"tags\":{}}\n",
+        )
+        .unwrap();
+        let err = upgrade(dir.path()).unwrap_err().to_string();
+        assert!(err.contains("schema version 5"), "{err}");
+        assert!(read_journal(&OPERATION_TAG_LOG, &path).is_err());
+    }
+}
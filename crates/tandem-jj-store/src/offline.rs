@@ -0,0 +1,342 @@
+//! Offline mode — keep working when the server cannot be reached.
+//!
+//! On every successful connect a store saves the server's repo info next to
+//! `server_address` (`repo_info.json`), the op heads store saves the last
+//! heads it saw (`heads_cache.json`), and objects, operations, and views are
+//! cached as they are read. When the server is unreachable — or
+//! `--tandem-offline` / `tandem.offline = true` asks for it — the stores load
+//! from those instead of failing, with an offline client that refuses every
+//! RPC. Anything this workspace has already seen can be read. Writes are
+//! given the ids the server would give them and queued locally
+//! (`offline_queue.rs`), so commands that write succeed and later commands
+//! see their results; the first command to reach the server again replays
+//! the queue.
+//!
+//! Only connection failures (refused, timed out) switch to offline mode. A
+//! server that answers but serves another repo is still an error.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use jj_lib::backend::BackendLoadError;
+use jj_lib::hex_util::{decode_hex, encode_hex};
+use serde::{Deserialize, Serialize};
+
+use crate::journal;
+use crate::offline_queue::{self, Queue};
+use crate::rpc::{self, HashAlgorithm, HeadsState, RepoInfoResponse, TandemClient};
+use crate::server_address;
+use crate::settings::TandemSettings;
+use crate::tmp_file::tmp_path;
+
+const REPO_INFO_FILE: &str = "repo_info.json";
+
+static FORCED: AtomicBool = AtomicBool::new(false);
+/// Set once one store found the server unreachable, so the others in the
+/// same command do not each wait out a connect timeout.
+static DETECTED: AtomicBool = AtomicBool::new(false);
+static ANNOUNCED: AtomicBool = AtomicBool::new(false);
+/// Set once a store of this command has replayed the offline queue.
+static SYNCED: AtomicBool = AtomicBool::new(false);
+
+/// Set by `--tandem-offline`: do not dial the server at all.
+pub fn force() {
+    FORCED.store(true, Ordering::Relaxed);
+}
+
+/// Connect the store at `store_path` to its server, or fall back to an
+/// offline client when the server cannot be reached.
+pub fn connect(
+    store_path: &Path,
+    settings: &TandemSettings,
+) -> Result<Arc<TandemClient>, BackendLoadError> {
+    let server_addr = server_address::read(store_path, settings)?;
+    if settings.journal {
+        if let Some(repo_dir) = store_path.parent() {
+            journal::enable(repo_dir, &server_addr);
+        }
+    }
+    if FORCED.load(Ordering::Relaxed) || settings.offline {
+        let client =
+            open_offline(store_path, &server_addr).map_err(|e| BackendLoadError(e.into()))?;
+        announce(&format!(
+            "tandem: offline mode; not contacting {server_addr}"
+        ));
+        return Ok(client);
+    }
+    if DETECTED.load(Ordering::Relaxed) {
+        if let Ok(client) = open_offline(store_path, &server_addr) {
+            return Ok(client);
+        }
+    }
+
+    match TandemClient::connect_with_options(&server_addr, &[], settings.client_options()) {
+        Ok(client) => {
+            server_address::verify(store_path, settings, &client)?;
+            save_repo_info(store_path, client.repo_info());
+            sync_queue(store_path, &client);
+            Ok(client)
+        }
+        Err(err) if rpc::is_unreachable(&err) => {
+            let Ok(client) = open_offline(store_path, &server_addr) else {
+                return Err(BackendLoadError(err.into()));
+            };
+            DETECTED.store(true, Ordering::Relaxed);
+            announce(&format!(
+                "tandem: offline: {err:#}\n\
+                 tandem: showing the last state this workspace saw; writes are queued until the server is back"
+            ));
+            Ok(client)
+        }
+        Err(err) => Err(BackendLoadError(err.into())),
+    }
+}
+
+/// Replay writes queued while the server was unreachable, once per command.
+fn sync_queue(store_path: &Path, client: &TandemClient) {
+    if SYNCED.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let server_addr = client.server_addr();
+    match offline_queue::replay(&Queue::for_store(store_path), client) {
+        Ok(replay) => {
+            if replay.operations > 0 {
+                let merged = if replay.merged {
+                    ", merged with operations published meanwhile"
+                } else {
+                    ""
+                };
+                eprintln!(
+                    "tandem: synced {} offline operation(s) to {server_addr}{merged}",
+                    replay.operations
+                );
+            }
+            if let Some(conflict) = replay.conflict {
+                eprintln!(
+                    "tandem: offline writes could not be synced to {server_addr}: {}\n\
+                     tandem: {} write(s) stay queued; `tandem status` shows them",
+                    conflict.reason, conflict.queued
+                );
+            }
+        }
+        Err(err) => tracing::warn!(error = %format!("{err:#}"), "offline writes not synced yet"),
+    }
+}
+
+fn announce(message: &str) {
+    if !ANNOUNCED.swap(true, Ordering::Relaxed) {
+        eprintln!("{message}");
+    }
+}
+
+fn open_offline(store_path: &Path, server_addr: &str) -> Result<Arc<TandemClient>> {
+    let info = load_repo_info(store_path).with_context(|| {
+        format!(
+            "cannot work offline: no saved state in {} (run a command while {server_addr} is reachable first)",
+            store_path.display()
+        )
+    })?;
+    Ok(TandemClient::offline(server_addr, info))
+}
+
+/// The parts of [`RepoInfoResponse`] the stores are built from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SavedRepoInfo {
+    protocol_major: u16,
+    protocol_minor: u16,
+    backend_name: String,
+    op_store_name: String,
+    commit_id_length: usize,
+    change_id_length: usize,
+    /// Missing from files written before servers advertised it.
+    #[serde(default = "default_commit_hash")]
+    commit_hash: String,
+    #[serde(default = "default_operation_hash")]
+    operation_hash: String,
+    root_commit_id: String,
+    root_change_id: String,
+    empty_tree_id: String,
+    root_operation_id: String,
+}
+
+fn default_commit_hash() -> String {
+    HashAlgorithm::DEFAULT_COMMIT.as_str().to_string()
+}
+
+fn default_operation_hash() -> String {
+    HashAlgorithm::DEFAULT_OPERATION.as_str().to_string()
+}
+
+fn save_repo_info(store_path: &Path, info: &RepoInfoResponse) {
+    let saved = SavedRepoInfo {
+        protocol_major: info.protocol_major,
+        protocol_minor: info.protocol_minor,
+        backend_name: info.backend_name.clone(),
+        op_store_name: info.op_store_name.clone(),
+        commit_id_length: info.commit_id_length,
+        change_id_length: info.change_id_length,
+        commit_hash: info.commit_hash.clone(),
+        operation_hash: info.operation_hash.clone(),
+        root_commit_id: encode_hex(&info.root_commit_id),
+        root_change_id: encode_hex(&info.root_change_id),
+        empty_tree_id: encode_hex(&info.empty_tree_id),
+        root_operation_id: encode_hex(&info.root_operation_id),
+    };
+    if let Err(err) = write_json(&store_path.join(REPO_INFO_FILE), &saved) {
+        tracing::debug!(error = %err, "could not save repo info for offline mode");
+    }
+}
+
+fn load_repo_info(store_path: &Path) -> Result<RepoInfoResponse> {
+    let saved: SavedRepoInfo = read_json(&store_path.join(REPO_INFO_FILE))?;
+    let hex = |field: &str, value: &str| {
+        decode_hex(value).ok_or_else(|| anyhow!("{REPO_INFO_FILE}: bad {field} {value:?}"))
+    };
+    Ok(RepoInfoResponse {
+        protocol_major: saved.protocol_major,
+        protocol_minor: saved.protocol_minor,
+        jj_version: None,
+        tandem_version: None,
+        backend_name: saved.backend_name,
+        op_store_name: saved.op_store_name,
+        commit_id_length: saved.commit_id_length,
+        change_id_length: saved.change_id_length,
+        commit_hash: saved.commit_hash,
+        operation_hash: saved.operation_hash,
+        root_commit_id: hex("rootCommitId", &saved.root_commit_id)?,
+        root_change_id: hex("rootChangeId", &saved.root_change_id)?,
+        empty_tree_id: hex("emptyTreeId", &saved.empty_tree_id)?,
+        root_operation_id: hex("rootOperationId", &saved.root_operation_id)?,
+        capabilities: Default::default(),
+        redirect: None,
+        git_remote: None,
+        op_schema: String::new(),
+        snapshot_auto_track: None,
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SavedHeads {
+    version: u64,
+    heads: Vec<String>,
+    workspace_heads: BTreeMap<String, String>,
+}
+
+/// Remember `state` as the heads to show when offline.
+pub fn save_heads(path: &Path, state: &HeadsState) {
+    let saved = SavedHeads {
+        version: state.version,
+        heads: state.heads.iter().map(|id| encode_hex(id)).collect(),
+        workspace_heads: state
+            .workspace_heads
+            .iter()
+            .map(|(workspace, id)| (workspace.clone(), encode_hex(id)))
+            .collect(),
+    };
+    if let Err(err) = write_json(path, &saved) {
+        tracing::debug!(error = %err, "could not save op heads for offline mode");
+    }
+}
+
+/// The heads saved by [`save_heads`].
+pub fn load_heads(path: &Path) -> Result<HeadsState> {
+    let saved: SavedHeads = read_json(path)?;
+    let hex = |value: &String| {
+        decode_hex(value).ok_or_else(|| anyhow!("{}: bad id {value:?}", path.display()))
+    };
+    Ok(HeadsState {
+        version: saved.version,
+        heads: saved.heads.iter().map(hex).collect::<Result<_>>()?,
+        workspace_heads: saved
+            .workspace_heads
+            .iter()
+            .map(|(workspace, id)| Ok((workspace.clone(), hex(id)?)))
+            .collect::<Result<_>>()?,
+    })
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let tmp = tmp_path(path);
+    std::fs::write(&tmp, serde_json::to_vec_pretty(value)?)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    let bytes = std::fs::read(path).with_context(|| format!("read {}", path.display()))?;
+    serde_json::from_slice(&bytes).with_context(|| format!("parse {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_state_round_trips() {
+        let dir = tempfile::tempdir().unwrap();
+        let info = RepoInfoResponse {
+            protocol_major: 0,
+            protocol_minor: 1,
+            jj_version: None,
+            tandem_version: None,
+            backend_name: "tandem".to_string(),
+            op_store_name: "tandem_op_store".to_string(),
+            commit_id_length: 20,
+            change_id_length: 16,
+            commit_hash: "sha1".to_string(),
+            operation_hash: "blake2b-512".to_string(),
+            root_commit_id: vec![0; 20],
+            root_change_id: vec![0; 16],
+            empty_tree_id: vec![0x4b; 20],
+            root_operation_id: vec![0; 64],
+            capabilities: Default::default(),
+            redirect: None,
+            git_remote: None,
+            op_schema: String::new(),
+            snapshot_auto_track: None,
+        };
+        save_repo_info(dir.path(), &info);
+        let loaded = load_repo_info(dir.path()).unwrap();
+        assert_eq!(loaded.empty_tree_id, info.empty_tree_id);
+        assert_eq!(loaded.root_operation_id, info.root_operation_id);
+        assert_eq!(loaded.commit_id_length, 20);
+
+        // Files saved before servers advertised their hashes.
+        let path = dir.path().join(REPO_INFO_FILE);
+        let mut old: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let fields = old.as_object_mut().unwrap();
+        fields.remove("commitHash").unwrap();
+        fields.remove("operationHash").unwrap();
+        std::fs::write(&path, serde_json::to_vec(&old).unwrap()).unwrap();
+        let loaded = load_repo_info(dir.path()).unwrap();
+        assert_eq!(loaded.commit_hash, "sha1");
+        assert_eq!(loaded.operation_hash, "blake2b-512");
+
+        let heads_path = dir.path().join("heads_cache.json");
+        let state = HeadsState {
+            heads: vec![vec![1; 64], vec![2; 64]],
+            version: 7,
+            workspace_heads: BTreeMap::from([("default".to_string(), vec![2; 64])]),
+        };
+        save_heads(&heads_path, &state);
+        let loaded = load_heads(&heads_path).unwrap();
+        assert_eq!(loaded.heads, state.heads);
+        assert_eq!(loaded.version, 7);
+        assert_eq!(loaded.workspace_heads, state.workspace_heads);
+    }
+
+    #[test]
+    fn no_saved_state_means_no_offline_client() {
+        let dir = tempfile::tempdir().unwrap();
+        let err = open_offline(dir.path(), "127.0.0.1:1").unwrap_err();
+        assert!(
+            format!("{err:#}").contains("run a command while 127.0.0.1:1 is reachable"),
+            "{err:#}"
+        );
+    }
+}
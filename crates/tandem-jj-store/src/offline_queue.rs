@@ -0,0 +1,506 @@
+//! Offline write queue — writes made while the server is unreachable.
+//!
+//! In offline mode (see `offline.rs`) the stores give every write the id the
+//! server would give it. Files, trees, symlinks, and commits go through a
+//! scratch git backend under `.jj/repo/tandem/offline/git/`, the same way the
+//! server's `putObject` stores them in its git repo, and operations and
+//! views are named by their blake2b content hash, as on the server. The
+//! encoded write lands in the local caches, so later commands read it like
+//! anything fetched, and in `offline/queue.jsonl`: one line per write, in
+//! the order it was made, with the bytes under `offline/data/`. Head
+//! updates are queued too, and move the saved heads (`heads_cache.json`) to
+//! the new operation.
+//!
+//! The first store to connect once the server is reachable again replays
+//! the queue ([`replay`]): every object, view, and operation is uploaded in
+//! order and the id the server gives it is checked against the offline one,
+//! then each head update is published with the usual CAS, under an
+//! idempotency key derived from its operation. Operations published
+//! meanwhile by other workspaces make the server merge ours in, as for any
+//! concurrent writer. Writes that replay are dropped from the queue. If the
+//! server stores a write under a different id, or refuses it, the rest stay
+//! queued, the reason is kept in `offline/conflict.json`, and `tandem status`
+//! reports it until a later replay gets through.
+//!
+//! Offline ids are only computed for servers whose repo hashes commits with
+//! SHA-1; other writes still fail naming the server.
+
+use std::collections::BTreeMap;
+use std::io::{Cursor, Read as _, Seek as _, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use anyhow::{anyhow, bail, Context, Result};
+use jj_lib::backend::Backend as _;
+use jj_lib::git_backend::GitBackend;
+use jj_lib::hex_util::{decode_hex, encode_hex};
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo_path::RepoPath;
+use jj_lib::settings::UserSettings;
+use prost::Message as _;
+use serde::{Deserialize, Serialize};
+
+use crate::proto_convert;
+use crate::rpc::{self, HashAlgorithm, RepoCapability, RepoInfoResponse, TandemClient};
+use crate::tmp_file::tmp_path;
+
+/// Queue directory, under the repo's `tandem/` directory.
+pub const DIR: &str = "offline";
+const QUEUE_FILE: &str = "queue.jsonl";
+const DATA_DIR: &str = "data";
+const GIT_STORE_DIR: &str = "git";
+const CONFLICT_FILE: &str = "conflict.json";
+const LOCK_FILE: &str = "lock";
+/// CAS attempts for one replayed head update.
+const HEADS_ATTEMPTS: usize = 20;
+
+// Object kinds, as in `backend.rs`.
+const KIND_COMMIT: u16 = 0;
+const KIND_TREE: u16 = 1;
+const KIND_FILE: u16 = 2;
+const KIND_SYMLINK: u16 = 3;
+
+/// Serializes appends within one process; each line is also written with a
+/// single `write` so other processes never see half of one.
+static APPEND: Mutex<()> = Mutex::new(());
+
+/// One queued write. Ids are hex.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "write", rename_all = "camelCase")]
+pub enum Write {
+    Object {
+        kind: u16,
+        id: String,
+    },
+    Operation {
+        id: String,
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        tags: BTreeMap<String, String>,
+    },
+    View {
+        id: String,
+    },
+    Heads {
+        old: Vec<String>,
+        new: String,
+        workspace: String,
+    },
+}
+
+impl Write {
+    /// Where the bytes of this write are kept, if it has any.
+    fn data_path(&self, dir: &Path) -> Option<PathBuf> {
+        let (subdir, id) = match self {
+            Write::Object { kind, id } => (kind.to_string(), id),
+            Write::Operation { id, .. } => ("operations".to_string(), id),
+            Write::View { id } => ("views".to_string(), id),
+            Write::Heads { .. } => return None,
+        };
+        Some(dir.join(DATA_DIR).join(subdir).join(id))
+    }
+}
+
+/// The queue of one repo, at `.jj/repo/tandem/offline/`.
+#[derive(Debug, Clone)]
+pub struct Queue {
+    dir: PathBuf,
+}
+
+impl Queue {
+    /// The queue of the repo whose stores live under `repo_dir`.
+    pub fn new(repo_dir: &Path) -> Self {
+        Self {
+            dir: repo_dir.join("tandem").join(DIR),
+        }
+    }
+
+    /// The queue of the store at `store_path` (`.jj/repo/<store>`).
+    pub fn for_store(store_path: &Path) -> Self {
+        Self::new(store_path.parent().unwrap_or(store_path))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Append `write`, keeping `data` for replay.
+    pub fn push(&self, write: &Write, data: &[u8]) -> Result<()> {
+        if let Some(path) = write.data_path(&self.dir) {
+            if !path.exists() {
+                let parent = path.parent().unwrap_or(&self.dir);
+                std::fs::create_dir_all(parent)
+                    .with_context(|| format!("create {}", parent.display()))?;
+                let tmp = tmp_path(&path);
+                std::fs::write(&tmp, data).with_context(|| format!("write {}", tmp.display()))?;
+                std::fs::rename(&tmp, &path)
+                    .with_context(|| format!("write {}", path.display()))?;
+            }
+        }
+        let mut line = serde_json::to_string(write)?;
+        line.push('\n');
+        let path = self.dir.join(QUEUE_FILE);
+        let _guard = APPEND.lock().unwrap();
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("create {}", self.dir.display()))?;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("open {}", path.display()))?;
+        if ends_torn(&mut file)? {
+            // Keep an interrupted append from swallowing this line.
+            line.insert(0, '\n');
+        }
+        file.write_all(line.as_bytes())
+            .with_context(|| format!("append to {}", path.display()))
+    }
+
+    /// Queued writes, oldest first. A torn final line is dropped.
+    pub fn writes(&self) -> Result<Vec<Write>> {
+        let path = self.dir.join(QUEUE_FILE);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err).with_context(|| format!("read {}", path.display())),
+        };
+        let mut writes = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(write) => writes.push(write),
+                Err(err) => {
+                    tracing::warn!(path = %path.display(), error = %err, "skipping bad offline queue line")
+                }
+            }
+        }
+        Ok(writes)
+    }
+
+    /// Why the last replay stopped, if it did not get through.
+    pub fn conflict(&self) -> Option<Conflict> {
+        let data = std::fs::read(self.dir.join(CONFLICT_FILE)).ok()?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    /// The bytes kept for `write`.
+    pub fn data(&self, write: &Write) -> Result<Vec<u8>> {
+        let Some(path) = write.data_path(&self.dir) else {
+            return Ok(Vec::new());
+        };
+        std::fs::read(&path).with_context(|| format!("read {}", path.display()))
+    }
+}
+
+/// A write the server did not take as queued.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conflict {
+    pub write: Write,
+    pub reason: String,
+    /// Writes still queued, this one included.
+    pub queued: usize,
+}
+
+/// What [`replay`] got through.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Replay {
+    /// Writes the server took.
+    pub replayed: usize,
+    /// Head updates among them.
+    pub operations: usize,
+    /// Whether the server merged them with operations published meanwhile.
+    pub merged: bool,
+    /// Why the replay stopped short, if it did.
+    pub conflict: Option<Conflict>,
+}
+
+/// Send the queued writes to the server behind `client`, oldest first.
+/// Returns `Ok` with a conflict when the server did not take one of them;
+/// an error means the server could not be reached and the queue is intact.
+pub fn replay(queue: &Queue, client: &TandemClient) -> Result<Replay> {
+    if !queue.dir.join(QUEUE_FILE).exists() {
+        return Ok(Replay::default());
+    }
+    // One replay at a time; a second process waits and finds less to do.
+    let _lock = jj_lib::lock::FileLock::lock(queue.dir.join(LOCK_FILE))?;
+    let writes = queue.writes()?;
+    let mut done = Replay::default();
+    for (index, write) in writes.iter().enumerate() {
+        let reason = match send(queue, client, write) {
+            Ok(Sent::Stored) => None,
+            Ok(Sent::Published { merged }) => {
+                done.operations += 1;
+                done.merged |= merged;
+                None
+            }
+            Ok(Sent::Mismatch(reason)) => Some(reason),
+            Err(err) if rpc::is_unreachable(&err) => {
+                queue.keep(&writes[index..])?;
+                return Err(err);
+            }
+            Err(err) => Some(format!("{err:#}")),
+        };
+        if let Some(reason) = reason {
+            let conflict = Conflict {
+                write: write.clone(),
+                reason,
+                queued: writes.len() - index,
+            };
+            queue.keep(&writes[index..])?;
+            write_file(
+                &queue.dir.join(CONFLICT_FILE),
+                &serde_json::to_vec_pretty(&conflict)?,
+            )?;
+            done.conflict = Some(conflict);
+            return Ok(done);
+        }
+        done.replayed += 1;
+    }
+    // Everything went through: drop the queue, its bytes, and the scratch
+    // git store, but not the lock we hold.
+    for name in [QUEUE_FILE, CONFLICT_FILE] {
+        remove_if_present(&queue.dir.join(name), |path| std::fs::remove_file(path))?;
+    }
+    for name in [DATA_DIR, GIT_STORE_DIR] {
+        remove_if_present(&queue.dir.join(name), |path| std::fs::remove_dir_all(path))?;
+    }
+    Ok(done)
+}
+
+enum Sent {
+    Stored,
+    Published { merged: bool },
+    Mismatch(String),
+}
+
+fn send(queue: &Queue, client: &TandemClient, write: &Write) -> Result<Sent> {
+    let data = queue.data(write)?;
+    let (expected, stored) = match write {
+        Write::Object { kind, id } => (id, client.put_object(*kind, &data)?.0),
+        Write::Operation { id, tags } => (id, client.put_operation(&data, tags)?),
+        Write::View { id } => (id, client.put_view(&data)?),
+        Write::Heads {
+            old,
+            new,
+            workspace,
+        } => return publish(client, old, new, workspace),
+    };
+    if encode_hex(&stored) == *expected {
+        Ok(Sent::Stored)
+    } else {
+        Ok(Sent::Mismatch(format!(
+            "the server stored {expected} as {}",
+            encode_hex(&stored)
+        )))
+    }
+}
+
+/// Move the heads from `old` to `new` as the op heads store would.
+fn publish(client: &TandemClient, old: &[String], new: &str, workspace: &str) -> Result<Sent> {
+    let hex = |id: &str| decode_hex(id).ok_or_else(|| anyhow!("bad operation id {id:?}"));
+    let old: Vec<Vec<u8>> = old.iter().map(|id| hex(id)).collect::<Result<_>>()?;
+    let new = hex(new)?;
+    // Deterministic, so replaying again after a lost reply cannot publish
+    // the operation twice.
+    let key = if client.supports_capability(RepoCapability::IdempotentHeadUpdates) {
+        new.clone()
+    } else {
+        Vec::new()
+    };
+    let mut version = client.heads_version()?;
+    for _ in 0..HEADS_ATTEMPTS {
+        let result = client.update_op_heads(&old, &new, version, workspace, &key)?;
+        if result.ok {
+            let merged = result.heads != [new];
+            return Ok(Sent::Published { merged });
+        }
+        version = result.version;
+        std::thread::sleep(std::time::Duration::from_millis(
+            u64::from(result.min_backoff_ms).max(10),
+        ));
+    }
+    Ok(Sent::Mismatch(format!(
+        "the heads kept moving; gave up after {HEADS_ATTEMPTS} attempts"
+    )))
+}
+
+impl Queue {
+    /// Replace the queue with `writes`.
+    fn keep(&self, writes: &[Write]) -> Result<()> {
+        let mut contents = String::new();
+        for write in writes {
+            contents.push_str(&serde_json::to_string(write)?);
+            contents.push('\n');
+        }
+        let _guard = APPEND.lock().unwrap();
+        write_file(&self.dir.join(QUEUE_FILE), contents.as_bytes())
+    }
+}
+
+fn write_file(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = tmp_path(path);
+    std::fs::write(&tmp, data).with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("write {}", path.display()))
+}
+
+fn remove_if_present(path: &Path, remove: fn(&Path) -> std::io::Result<()>) -> Result<()> {
+    match remove(path) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(err).with_context(|| format!("remove {}", path.display())),
+    }
+}
+
+/// Whether `file` is non-empty and does not end in a newline.
+fn ends_torn(file: &mut std::fs::File) -> Result<bool> {
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(false);
+    }
+    let mut last = [0u8];
+    file.seek(SeekFrom::Start(len - 1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] != b'\n')
+}
+
+/// Stores objects offline the way the server does, for their ids.
+pub struct ObjectWriter {
+    queue: Queue,
+    settings: UserSettings,
+    supported: Result<(), String>,
+    /// Opened on the first write.
+    git: OnceLock<GitBackend>,
+}
+
+impl ObjectWriter {
+    pub fn new(queue: Queue, settings: &UserSettings, info: &RepoInfoResponse) -> Self {
+        let supported = match HashAlgorithm::parse(&info.commit_hash) {
+            Some(HashAlgorithm::Sha1) => Ok(()),
+            _ => Err(format!(
+                "the server's repo hashes commits with {}, so offline writes cannot be given ids",
+                info.commit_hash
+            )),
+        };
+        Self {
+            queue,
+            settings: settings.clone(),
+            supported,
+            git: OnceLock::new(),
+        }
+    }
+
+    /// Store object `data` of `kind` and queue it; returns the id and the
+    /// encoded object as the server would have.
+    pub async fn put(&self, kind: u16, data: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        self.supported.clone().map_err(|err| anyhow!(err))?;
+        let git = self.git()?;
+        let (id, normalized) = match kind {
+            KIND_FILE => {
+                let mut cursor = Cursor::new(data.to_vec());
+                let id = git.write_file(RepoPath::root(), &mut cursor).await?;
+                (id.to_bytes(), data.to_vec())
+            }
+            KIND_TREE => {
+                let proto = jj_lib::protos::simple_store::Tree::decode(data)
+                    .context("decode tree proto")?;
+                let tree = proto_convert::tree_from_proto(proto);
+                let id = git.write_tree(RepoPath::root(), &tree).await?;
+                (id.to_bytes(), data.to_vec())
+            }
+            KIND_COMMIT => {
+                let proto = jj_lib::protos::simple_store::Commit::decode(data)
+                    .context("decode commit proto")?;
+                let commit = proto_convert::commit_from_proto(proto);
+                let (id, stored) = git.write_commit(commit, None).await?;
+                let normalized = jj_lib::simple_backend::commit_to_proto(&stored).encode_to_vec();
+                (id.to_bytes(), normalized)
+            }
+            KIND_SYMLINK => {
+                let target =
+                    std::str::from_utf8(data).context("symlink target is not valid UTF-8")?;
+                let id = git.write_symlink(RepoPath::root(), target).await?;
+                (id.to_bytes(), data.to_vec())
+            }
+            _ => bail!("cannot write object kind {kind} offline"),
+        };
+        let write = Write::Object {
+            kind,
+            id: encode_hex(&id),
+        };
+        self.queue.push(&write, &normalized)?;
+        Ok((id, normalized))
+    }
+
+    fn git(&self) -> Result<&GitBackend> {
+        if let Some(git) = self.git.get() {
+            return Ok(git);
+        }
+        let store = self.queue.dir.join(GIT_STORE_DIR);
+        let git = if store.join("git_target").exists() {
+            GitBackend::load(&self.settings, &store).map_err(|err| anyhow!(err))?
+        } else {
+            std::fs::create_dir_all(&store)
+                .with_context(|| format!("create {}", store.display()))?;
+            GitBackend::init_internal(&self.settings, &store).map_err(|err| anyhow!(err))?
+        };
+        Ok(self.git.get_or_init(|| git))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queue_keeps_writes_in_order_with_their_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = Queue::new(dir.path());
+        assert!(queue.writes().unwrap().is_empty());
+
+        let object = Write::Object {
+            kind: KIND_FILE,
+            id: "aa".to_string(),
+        };
+        let heads = Write::Heads {
+            old: vec!["01".to_string()],
+            new: "02".to_string(),
+            workspace: "default".to_string(),
+        };
+        queue.push(&object, b"contents").unwrap();
+        queue.push(&heads, b"").unwrap();
+        // A torn line from an interrupted append is skipped.
+        let path = queue.dir().join(QUEUE_FILE);
+        let mut file = std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap();
+        file.write_all(b"{\"write\":\"vi").unwrap();
+
+        assert_eq!(queue.writes().unwrap(), vec![object.clone(), heads.clone()]);
+        let view = Write::View {
+            id: "bb".to_string(),
+        };
+        queue.push(&view, b"view").unwrap();
+        assert_eq!(
+            queue.writes().unwrap(),
+            vec![object.clone(), heads.clone(), view]
+        );
+        assert_eq!(queue.data(&object).unwrap(), b"contents");
+        assert!(queue.data(&heads).unwrap().is_empty());
+    }
+
+    #[test]
+    fn keep_replaces_the_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = Queue::new(dir.path());
+        let writes: Vec<Write> = ["aa", "bb", "cc"]
+            .iter()
+            .map(|id| Write::View { id: id.to_string() })
+            .collect();
+        for write in &writes {
+            queue.push(write, b"view").unwrap();
+        }
+        queue.keep(&writes[1..]).unwrap();
+        assert_eq!(queue.writes().unwrap(), writes[1..]);
+        assert_eq!(queue.conflict(), None);
+    }
+}
@@ -3,7 +3,7 @@
 
 use std::fmt;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -15,15 +15,27 @@ use jj_lib::op_store::OperationId;
 use jj_lib::settings::UserSettings;
 use prost::Message as _;
 
-use crate::rpc::TandemClient;
+use crate::offline;
+use crate::offline_queue::{self, Queue};
+use crate::op_store;
+use crate::rpc::{self, RepoCapability, TandemClient};
+use crate::settings::TandemSettings;
+use crate::time_travel;
+use crate::watchdog;
 
 const WORKSPACE_ID_FILE: &str = "workspace_id";
+/// jj-lib keeps the op store next to the op heads store, under this name.
+const OP_STORE_DIR: &str = "op_store";
 const CAS_MAX_ATTEMPTS: usize = 80;
 const CAS_BACKOFF_BASE_MS: u64 = 2;
 const CAS_BACKOFF_MAX_MS: u64 = 256;
+/// Resends of an update whose reply was a transient error, under its
+/// idempotency key.
+const LOST_REPLY_RETRIES: usize = 3;
 const BENCH_DISABLE_OPTIMISTIC_VERSION_ENV: &str =
     "TANDEM_BENCH_DISABLE_OPTIMISTIC_OP_HEAD_VERSION_CACHE";
 const VERSION_CACHE_FILE: &str = "heads_version_cache";
+const HEADS_CACHE_FILE: &str = "heads_cache.json";
 
 /// OpHeadsStore implementation that proxies all reads/writes to a tandem server.
 pub struct TandemOpHeadsStore {
@@ -31,9 +43,17 @@ pub struct TandemOpHeadsStore {
     workspace_id: String,
     cached_version: Mutex<Option<u64>>,
     version_cache_path: PathBuf,
+    /// Last heads seen, for offline mode.
+    heads_cache_path: PathBuf,
     optimistic_version_cache: bool,
     update_guard: Mutex<()>,
     pending_updates: AtomicUsize,
+    /// Set by `init` until jj's "add workspace" operation has been seen.
+    unpublished_workspace_add: AtomicBool,
+    /// The op store whose cache holds that operation, and its settings.
+    op_store: Option<(PathBuf, TandemSettings)>,
+    /// Where head updates go while the server is unreachable.
+    offline_queue: Option<Queue>,
 }
 
 impl fmt::Debug for TandemOpHeadsStore {
@@ -44,25 +64,6 @@ impl fmt::Debug for TandemOpHeadsStore {
     }
 }
 
-/// Read server address from env var or file.
-fn read_server_address(store_path: &Path) -> Result<String, BackendLoadError> {
-    if let Ok(addr) = std::env::var("TANDEM_SERVER") {
-        if !addr.is_empty() {
-            return Ok(addr);
-        }
-    }
-    let addr_path = store_path.join("server_address");
-    std::fs::read_to_string(&addr_path).map_err(|e| {
-        BackendLoadError(
-            anyhow::anyhow!(
-                "cannot read tandem server address from {} or TANDEM_SERVER env: {e}",
-                addr_path.display()
-            )
-            .into(),
-        )
-    })
-}
-
 fn read_workspace_id(store_path: &Path) -> Result<String, BackendLoadError> {
     if let Ok(workspace_id) = std::env::var("TANDEM_WORKSPACE") {
         let trimmed = workspace_id.trim();
@@ -104,6 +105,43 @@ fn cas_retry_backoff(attempt: usize, new_id: &[u8]) -> Duration {
     Duration::from_millis(base_ms + jitter_ms)
 }
 
+/// A fresh key for one logical head update; every retry resends it.
+fn new_idempotency_key() -> Vec<u8> {
+    use std::hash::{BuildHasher as _, Hasher as _};
+
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or_default();
+    let mut key = Vec::with_capacity(16);
+    for part in [u64::from(std::process::id()), nanos] {
+        let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+        hasher.write_u64(part);
+        key.extend_from_slice(&hasher.finish().to_le_bytes());
+    }
+    key
+}
+
+/// Whether a failed update may have been applied anyway and is worth
+/// resending: the server was overloaded or dropped the call at its
+/// `--rpc-timeout`, or the connection went away before the reply arrived.
+/// Maintenance and shutdown notices are final.
+fn is_transient_write_error(err: &anyhow::Error) -> bool {
+    is_lost_connection(err)
+        || err
+            .downcast_ref::<capnp::Error>()
+            .is_some_and(|err| err.kind == capnp::ErrorKind::Overloaded)
+        || err.to_string().starts_with(watchdog::TIMEOUT_PREFIX)
+}
+
+/// Whether the call failed because its connection is gone, so a resend
+/// needs a new one.
+fn is_lost_connection(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<capnp::Error>()
+        .is_some_and(|err| err.kind == capnp::ErrorKind::Disconnected)
+        || err.to_string() == rpc::REPLY_DROPPED
+}
+
 fn optimistic_version_cache_enabled() -> bool {
     !std::env::var(BENCH_DISABLE_OPTIMISTIC_VERSION_ENV)
         .map(|value| {
@@ -137,17 +175,21 @@ impl Drop for PendingUpdateGuard<'_> {
 impl TandemOpHeadsStore {
     /// Initialize a new tandem op heads store (called during workspace init).
     pub fn init(
+        settings: &UserSettings,
         store_path: &Path,
         server_addr: &str,
         workspace_id: &str,
     ) -> Result<Self, jj_lib::backend::BackendInitError> {
+        let settings = TandemSettings::from_user(settings)
+            .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
         std::fs::write(store_path.join("server_address"), server_addr)
             .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
         std::fs::write(store_path.join(WORKSPACE_ID_FILE), workspace_id)
             .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
 
-        let client = TandemClient::connect(server_addr)
-            .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
+        let client =
+            TandemClient::connect_with_options(server_addr, &[], settings.client_options())
+                .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
         let version_cache_path = store_path.join(VERSION_CACHE_FILE);
         let optimistic_version_cache = optimistic_version_cache_enabled();
         let cached_version = if optimistic_version_cache {
@@ -161,17 +203,23 @@ impl TandemOpHeadsStore {
             workspace_id: workspace_id.to_string(),
             cached_version: Mutex::new(cached_version),
             version_cache_path,
+            heads_cache_path: store_path.join(HEADS_CACHE_FILE),
             optimistic_version_cache,
             update_guard: Mutex::new(()),
             pending_updates: AtomicUsize::new(0),
+            unpublished_workspace_add: AtomicBool::new(true),
+            op_store: Some((store_path.with_file_name(OP_STORE_DIR), settings)),
+            offline_queue: None,
         })
     }
 
     /// Load an existing tandem op heads store from `store_path`.
-    pub fn load(_settings: &UserSettings, store_path: &Path) -> Result<Self, BackendLoadError> {
-        let server_addr = read_server_address(store_path)?;
+    pub fn load(settings: &UserSettings, store_path: &Path) -> Result<Self, BackendLoadError> {
+        let settings =
+            TandemSettings::from_user(settings).map_err(|e| BackendLoadError(e.into()))?;
         let workspace_id = read_workspace_id(store_path)?;
-        let client = TandemClient::connect(&server_addr).map_err(|e| BackendLoadError(e.into()))?;
+        let client = offline::connect(store_path, &settings)?;
+        let offline_queue = client.is_offline().then(|| Queue::for_store(store_path));
         let version_cache_path = store_path.join(VERSION_CACHE_FILE);
         let optimistic_version_cache = optimistic_version_cache_enabled();
         let cached_version = if optimistic_version_cache {
@@ -184,9 +232,13 @@ impl TandemOpHeadsStore {
             workspace_id,
             cached_version: Mutex::new(cached_version),
             version_cache_path,
+            heads_cache_path: store_path.join(HEADS_CACHE_FILE),
             optimistic_version_cache,
             update_guard: Mutex::new(()),
             pending_updates: AtomicUsize::new(0),
+            unpublished_workspace_add: AtomicBool::new(false),
+            op_store: None,
+            offline_queue,
         })
     }
 
@@ -231,6 +283,32 @@ impl TandemOpHeadsStore {
         Some(proto.view_id)
     }
 
+    /// Queue an update made offline, and move the saved heads the way the
+    /// server would have moved its own.
+    fn queue_update(
+        &self,
+        queue: &Queue,
+        old_ids: &[OperationId],
+        new_id: &OperationId,
+    ) -> anyhow::Result<()> {
+        let write = offline_queue::Write::Heads {
+            old: old_ids.iter().map(|id| id.hex()).collect(),
+            new: new_id.hex(),
+            workspace: self.workspace_id.clone(),
+        };
+        queue.push(&write, &[])?;
+        let mut state = offline::load_heads(&self.heads_cache_path)?;
+        state
+            .heads
+            .retain(|head| !old_ids.iter().any(|old| old.as_bytes() == head.as_slice()));
+        state.heads.push(new_id.as_bytes().to_vec());
+        state
+            .workspace_heads
+            .insert(self.workspace_id.clone(), new_id.as_bytes().to_vec());
+        offline::save_heads(&self.heads_cache_path, &state);
+        Ok(())
+    }
+
     fn heads_for_workspace(&self, state: crate::rpc::HeadsState) -> Vec<OperationId> {
         let mut ids = state.heads;
         let workspace_head = state.workspace_heads.get(&self.workspace_id).cloned();
@@ -276,6 +354,46 @@ impl OpHeadsStore for TandemOpHeadsStore {
         old_ids: &[OperationId],
         new_id: &OperationId,
     ) -> Result<(), OpHeadsStoreError> {
+        if let Some(version) = time_travel::pinned() {
+            return Err(OpHeadsStoreError::Write {
+                new_op_id: new_id.clone(),
+                source: anyhow::anyhow!(
+                    "op heads are pinned to heads version {version} (--at-version); \
+                     the repo is read-only"
+                )
+                .into(),
+            });
+        }
+        // jj-lib's init commits "add workspace" on top of the root operation.
+        // Published, it would become a side branch to the root that jj's
+        // stale check mistakes for the closest common ancestor of later ops
+        // ("sibling of the working copy's operation"). `tandem init` replaces
+        // it right away with an operation built on the server head, so keep
+        // it local, and out of the op cache where nothing would read it back.
+        if old_ids.len() == 1
+            && old_ids[0].as_bytes() == self.client.repo_info().root_operation_id.as_slice()
+            && self.unpublished_workspace_add.swap(false, Ordering::SeqCst)
+        {
+            tracing::debug!(
+                workspace_id = %self.workspace_id,
+                op_id = %new_id.hex(),
+                "not publishing the workspace-add operation of init"
+            );
+            if let Some((op_store_path, settings)) = &self.op_store {
+                op_store::evict_cached_operation(op_store_path, settings, new_id.as_bytes());
+            }
+            return Ok(());
+        }
+
+        if let Some(queue) = &self.offline_queue {
+            return self.queue_update(queue, old_ids, new_id).map_err(|e| {
+                OpHeadsStoreError::Write {
+                    new_op_id: new_id.clone(),
+                    source: e.into(),
+                }
+            });
+        }
+
         let old_bytes: Vec<Vec<u8>> = old_ids.iter().map(|id| id.as_bytes().to_vec()).collect();
         let new_bytes = new_id.as_bytes().to_vec();
 
@@ -295,52 +413,107 @@ impl OpHeadsStore for TandemOpHeadsStore {
             if let Some(version) = self.cached_version() {
                 version
             } else {
-                let state =
+                let version =
                     self.client
-                        .get_heads_state()
+                        .heads_version()
                         .map_err(|e| OpHeadsStoreError::Write {
                             new_op_id: new_id.clone(),
                             source: e.into(),
                         })?;
-                self.remember_version(state.version);
-                state.version
+                self.remember_version(version);
+                version
             }
         } else {
-            let state = self
+            let version = self
                 .client
-                .get_heads_state()
+                .heads_version()
                 .map_err(|e| OpHeadsStoreError::Write {
                     new_op_id: new_id.clone(),
                     source: e.into(),
                 })?;
-            self.remember_version(state.version);
-            state.version
+            self.remember_version(version);
+            version
         };
 
         let mut cas_retries = 0usize;
         let mut saw_contention = false;
         let started_at = Instant::now();
-
-        for attempt in 1..=CAS_MAX_ATTEMPTS {
-            let result = self
-                .client
-                .update_op_heads(&old_bytes, &new_bytes, expected_version, &self.workspace_id)
-                .map_err(|e| {
-                    tracing::error!(
+        // Lets a resend after a lost reply come back as the update it
+        // repeats instead of publishing the operation a second time.
+        let idempotency_key = if self
+            .client
+            .supports_capability(RepoCapability::IdempotentHeadUpdates)
+        {
+            new_idempotency_key()
+        } else {
+            Vec::new()
+        };
+        let mut resends = 0usize;
+        // A resend after the connection dropped goes out on a new one.
+        let mut reconnected: Option<Arc<TandemClient>> = None;
+
+        // Only lost CAS races count as attempts; resends repeat the attempt
+        // whose reply went missing.
+        let mut attempt = 1;
+        loop {
+            let client = reconnected.as_ref().unwrap_or(&self.client);
+            let result = match client.update_op_heads(
+                &old_bytes,
+                &new_bytes,
+                expected_version,
+                &self.workspace_id,
+                &idempotency_key,
+            ) {
+                Err(err)
+                    if !idempotency_key.is_empty()
+                        && resends < LOST_REPLY_RETRIES
+                        && is_transient_write_error(&err) =>
+                {
+                    resends += 1;
+                    let backoff = cas_retry_backoff(resends, &new_bytes);
+                    tracing::warn!(
                         rpc_method = "updateOpHeads",
                         workspace_id = %self.workspace_id,
                         attempt,
-                        cas_retries,
-                        queue_depth,
-                        latency_ms = started_at.elapsed().as_millis() as u64,
-                        error = %e,
-                        "op-head update failed"
+                        resends,
+                        backoff_ms = backoff.as_millis() as u64,
+                        error = %err,
+                        "op-head update failed; resending with the same idempotency key"
                     );
-                    OpHeadsStoreError::Write {
-                        new_op_id: new_id.clone(),
-                        source: e.into(),
+                    std::thread::sleep(backoff);
+                    if is_lost_connection(&err) {
+                        match TandemClient::connect_with_requirements(
+                            self.client.server_addr(),
+                            &[RepoCapability::IdempotentHeadUpdates],
+                        ) {
+                            Ok(client) => reconnected = Some(client),
+                            Err(err) => tracing::warn!(
+                                workspace_id = %self.workspace_id,
+                                error = %format!("{err:#}"),
+                                "cannot reconnect to resend the op-head update"
+                            ),
+                        }
                     }
-                })?;
+                    continue;
+                }
+                result => result,
+            }
+            .map_err(|e| {
+                tracing::error!(
+                    rpc_method = "updateOpHeads",
+                    workspace_id = %self.workspace_id,
+                    attempt,
+                    cas_retries,
+                    queue_depth,
+                    latency_ms = started_at.elapsed().as_millis() as u64,
+                    error = %e,
+                    "op-head update failed"
+                );
+                OpHeadsStoreError::Write {
+                    new_op_id: new_id.clone(),
+                    source: e.into(),
+                }
+            })?;
 
             if result.ok {
                 if saw_contention {
@@ -367,7 +540,9 @@ impl OpHeadsStore for TandemOpHeadsStore {
                 break;
             }
 
-            let backoff = cas_retry_backoff(attempt, &new_bytes);
+            // During a CAS storm the server sets a floor under the backoff.
+            let backoff = cas_retry_backoff(attempt, &new_bytes)
+                .max(Duration::from_millis(result.min_backoff_ms.into()));
             tracing::warn!(
                 rpc_method = "updateOpHeads",
                 workspace_id = %self.workspace_id,
@@ -376,9 +551,11 @@ impl OpHeadsStore for TandemOpHeadsStore {
                 queue_depth,
                 latency_ms = started_at.elapsed().as_millis() as u64,
                 backoff_ms = backoff.as_millis() as u64,
+                server_min_backoff_ms = result.min_backoff_ms,
                 "CAS contention detected; retrying"
             );
             std::thread::sleep(backoff);
+            attempt += 1;
         }
 
         tracing::error!(
@@ -401,10 +578,20 @@ impl OpHeadsStore for TandemOpHeadsStore {
     }
 
     async fn get_op_heads(&self) -> Result<Vec<OperationId>, OpHeadsStoreError> {
-        let state = self
-            .client
-            .get_heads_state()
-            .map_err(|e| OpHeadsStoreError::Read(e.into()))?;
+        if let Some(version) = time_travel::pinned() {
+            let heads = time_travel::heads_at(&self.client, version)
+                .map_err(|e| OpHeadsStoreError::Read(e.into()))?;
+            return Ok(heads.into_iter().map(OperationId::new).collect());
+        }
+        let state = if self.client.is_offline() {
+            offline::load_heads(&self.heads_cache_path)
+        } else {
+            self.client.get_heads_state()
+        }
+        .map_err(|e| OpHeadsStoreError::Read(e.into()))?;
+        if !self.client.is_offline() {
+            offline::save_heads(&self.heads_cache_path, &state);
+        }
         let workspace_head_present = state.workspace_heads.contains_key(&self.workspace_id);
         let effective_heads = self.heads_for_workspace(state.clone());
         tracing::debug!(
@@ -432,7 +619,9 @@ impl OpHeadsStoreLock for NoopLock {}
 
 #[cfg(test)]
 mod tests {
-    use super::{load_cached_version, persist_cached_version};
+    use super::{
+        is_lost_connection, is_transient_write_error, load_cached_version, persist_cached_version,
+    };
 
     #[test]
     fn heads_version_cache_round_trip() {
@@ -452,4 +641,22 @@ mod tests {
         std::fs::write(&cache_path, "not-a-version").expect("write invalid cache file");
         assert_eq!(load_cached_version(&cache_path), None);
     }
+
+    #[test]
+    fn lost_connections_and_overload_are_resent() {
+        let disconnected = anyhow::Error::new(capnp::Error::disconnected("gone".into()));
+        assert!(is_transient_write_error(&disconnected));
+        assert!(is_lost_connection(&disconnected));
+
+        let dropped = anyhow::anyhow!(crate::rpc::REPLY_DROPPED);
+        assert!(is_transient_write_error(&dropped));
+        assert!(is_lost_connection(&dropped));
+
+        let overloaded = anyhow::Error::new(capnp::Error::overloaded("busy".into()));
+        assert!(is_transient_write_error(&overloaded));
+        assert!(!is_lost_connection(&overloaded));
+
+        let refused = anyhow::Error::new(capnp::Error::failed("maintenance".into()));
+        assert!(!is_transient_write_error(&refused));
+    }
 }
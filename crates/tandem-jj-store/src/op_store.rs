@@ -0,0 +1,511 @@
+//! TandemOpStore — jj-lib OpStore impl that routes operations and views
+//! to a remote tandem server over Cap'n Proto RPC.
+//!
+//! Operations and views are immutable, so fetched ones are kept under
+//! `op_store/cache/` (or `tandem.cache-dir`). A cached entry is only used after its blake2b content
+//! hash matches the requested id; anything else is evicted and re-fetched.
+//!
+//! The most recently seen view doubles as the base for view deltas: new
+//! views are sent and fetched as changes against it when the server
+//! supports `viewDeltas`, and the full view is used whenever a delta does
+//! not reproduce the expected id.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use jj_lib::backend::{BackendLoadError, CommitId};
+use jj_lib::content_hash::{blake2b_hash, ContentHash};
+use jj_lib::hex_util::{decode_hex, encode_hex};
+use jj_lib::object_id::{HexPrefix, ObjectId as _, PrefixResolution};
+use jj_lib::op_store::*;
+use jj_lib::settings::UserSettings;
+use prost::Message as _;
+
+use crate::offline;
+use crate::offline_queue::{self, Queue};
+use crate::profile;
+use crate::proto_convert;
+use crate::read_mirror;
+use crate::rpc::{PrefixResult, RepoCapability, TandemClient, ViewFetch};
+use crate::settings::TandemSettings;
+use crate::tmp_file::tmp_path;
+use crate::view_delta;
+
+const OPERATION_ID_LENGTH: usize = 64;
+const VIEW_ID_LENGTH: usize = 64;
+const EPHEMERAL_TTL_FILE: &str = "ephemeral_ttl";
+const CACHE_DIR: &str = "cache";
+const DELTA_BASE_FILE: &str = "delta_base";
+
+/// OpStore implementation that proxies all reads/writes to a tandem server.
+pub struct TandemOpStore {
+    client: Arc<TandemClient>,
+    /// Answers operation reads in place of `client` (see `read_mirror.rs`).
+    mirror: Option<Arc<TandemClient>>,
+    root_operation_id: OperationId,
+    root_view_id: ViewId,
+    root_commit_id: CommitId,
+    /// Tags this workspace attaches to every operation (e.g. its ephemeral TTL).
+    workspace_tags: BTreeMap<String, String>,
+    cache: OpCache,
+    /// Where writes go while the server is unreachable.
+    offline_queue: Option<Queue>,
+}
+
+impl fmt::Debug for TandemOpStore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TandemOpStore").finish()
+    }
+}
+
+/// Local copies of operations and views, one file per hex id under
+/// `cache/operations/` and `cache/views/`. Failures to write are ignored:
+/// the cache is an optimization and the server stays authoritative.
+struct OpCache {
+    dir: PathBuf,
+}
+
+impl OpCache {
+    fn new(store_path: &Path, settings: &TandemSettings) -> Self {
+        Self {
+            dir: settings
+                .cache_dir
+                .clone()
+                .unwrap_or_else(|| store_path.join(CACHE_DIR)),
+        }
+    }
+
+    /// Drop the cached operation `id`, if any.
+    fn evict_operation(&self, id: &[u8]) {
+        let _ = std::fs::remove_file(self.path("operations", id));
+    }
+
+    fn path(&self, kind: &str, id: &[u8]) -> PathBuf {
+        let hex: String = id.iter().map(|b| format!("{b:02x}")).collect();
+        self.dir.join(kind).join(hex)
+    }
+
+    /// Decode a cached entry and check its content hash against `id`.
+    /// Corrupt or tampered entries are evicted.
+    fn load_verified<T: ContentHash>(
+        &self,
+        kind: &str,
+        id: &[u8],
+        decode: fn(&[u8]) -> anyhow::Result<T>,
+    ) -> Option<T> {
+        self.load_verified_data(kind, id, decode)
+            .map(|(value, _)| value)
+    }
+
+    /// Like `load_verified`, also returning the encoded bytes.
+    fn load_verified_data<T: ContentHash>(
+        &self,
+        kind: &str,
+        id: &[u8],
+        decode: fn(&[u8]) -> anyhow::Result<T>,
+    ) -> Option<(T, Vec<u8>)> {
+        let path = self.path(kind, id);
+        let data = {
+            let _phase = profile::enter(&["cache read"]);
+            std::fs::read(&path).ok()?
+        };
+        match decode(&data) {
+            Ok(value) if blake2b_hash(&value).as_slice() == id => Some((value, data)),
+            _ => {
+                tracing::warn!(
+                    path = %path.display(),
+                    "cached {kind} failed its integrity check; evicting and re-fetching"
+                );
+                let _ = std::fs::remove_file(&path);
+                None
+            }
+        }
+    }
+
+    /// Cache `data` for `id` if `value` (its decoded form) hashes to `id`.
+    fn store_verified<T: ContentHash>(&self, kind: &str, id: &[u8], value: &T, data: &[u8]) {
+        if blake2b_hash(value).as_slice() != id {
+            return;
+        }
+        let path = self.path(kind, id);
+        if path.exists() {
+            return;
+        }
+        let write = || -> std::io::Result<()> {
+            std::fs::create_dir_all(self.dir.join(kind))?;
+            let tmp = tmp_path(&path);
+            std::fs::write(&tmp, data)?;
+            std::fs::rename(&tmp, &path)
+        };
+        if let Err(err) = write() {
+            tracing::debug!(path = %path.display(), error = %err, "could not cache {kind}");
+        }
+    }
+
+    /// The view deltas are computed against: id and encoded bytes.
+    fn delta_base(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let hex = std::fs::read_to_string(self.dir.join(DELTA_BASE_FILE)).ok()?;
+        let id = decode_hex(hex.trim())?;
+        let (_, data) = self.load_verified_data("views", &id, decode_view)?;
+        Some((id, data))
+    }
+
+    fn set_delta_base(&self, id: &[u8]) {
+        let hex: String = id.iter().map(|b| format!("{b:02x}")).collect();
+        let path = self.dir.join(DELTA_BASE_FILE);
+        let tmp = tmp_path(&path);
+        let _ = std::fs::write(&tmp, hex).and_then(|()| std::fs::rename(&tmp, &path));
+    }
+}
+
+/// Drop operation `id` from the cache of the op store at `store_path`. For
+/// operations that are written but never published, which nothing would
+/// otherwise read back (and so verify) again.
+pub fn evict_cached_operation(store_path: &Path, settings: &TandemSettings, id: &[u8]) {
+    OpCache::new(store_path, settings).evict_operation(id);
+}
+
+fn view_has_id(data: &[u8], id: &[u8]) -> bool {
+    decode_view(data).is_ok_and(|view| blake2b_hash(&view).as_slice() == id)
+}
+
+fn decode_view(data: &[u8]) -> anyhow::Result<View> {
+    let _phase = profile::enter(&["proto decode", "view"]);
+    let proto = jj_lib::protos::simple_op_store::View::decode(data)?;
+    proto_convert::view_from_proto(proto)
+}
+
+fn decode_operation(data: &[u8]) -> anyhow::Result<Operation> {
+    let _phase = profile::enter(&["proto decode", "operation"]);
+    let proto = jj_lib::protos::simple_op_store::Operation::decode(data)?;
+    proto_convert::operation_from_proto(proto)
+}
+
+fn read_workspace_tags(store_path: &Path) -> BTreeMap<String, String> {
+    let mut tags = BTreeMap::new();
+    if let Ok(ttl) = std::fs::read_to_string(store_path.join(EPHEMERAL_TTL_FILE)) {
+        tags.insert(
+            crate::ephemeral::TTL_TAG.to_string(),
+            ttl.trim().to_string(),
+        );
+    }
+    tags
+}
+
+impl TandemOpStore {
+    /// Initialize a new tandem op store (called during workspace init).
+    /// `ephemeral_ttl_secs` marks the workspace for server-side expiry.
+    pub fn init(
+        settings: &UserSettings,
+        store_path: &Path,
+        server_addr: &str,
+        root_data: RootOperationData,
+        ephemeral_ttl_secs: Option<u64>,
+    ) -> Result<Self, jj_lib::backend::BackendInitError> {
+        let settings = TandemSettings::from_user(settings)
+            .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
+        std::fs::write(store_path.join("server_address"), server_addr)
+            .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
+        if let Some(ttl) = ephemeral_ttl_secs {
+            std::fs::write(store_path.join(EPHEMERAL_TTL_FILE), ttl.to_string())
+                .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
+        }
+
+        let client =
+            TandemClient::connect_with_options(server_addr, &[], settings.client_options())
+                .map_err(|e| jj_lib::backend::BackendInitError(e.into()))?;
+        let info = client.repo_info().clone();
+        let mirror = read_mirror::connect(store_path, &client, &settings);
+
+        Ok(Self {
+            client,
+            mirror,
+            root_operation_id: OperationId::new(info.root_operation_id),
+            root_view_id: ViewId::from_bytes(&[0u8; VIEW_ID_LENGTH]),
+            root_commit_id: root_data.root_commit_id,
+            workspace_tags: read_workspace_tags(store_path),
+            cache: OpCache::new(store_path, &settings),
+            offline_queue: None,
+        })
+    }
+
+    /// Load an existing tandem op store from `store_path`.
+    pub fn load(
+        settings: &UserSettings,
+        store_path: &Path,
+        root_data: RootOperationData,
+    ) -> Result<Self, BackendLoadError> {
+        let settings =
+            TandemSettings::from_user(settings).map_err(|e| BackendLoadError(e.into()))?;
+        let client = offline::connect(store_path, &settings)?;
+        let offline_queue = client.is_offline().then(|| Queue::for_store(store_path));
+        let info = client.repo_info().clone();
+        let mirror = read_mirror::connect(store_path, &client, &settings);
+
+        Ok(Self {
+            client,
+            mirror,
+            root_operation_id: OperationId::new(info.root_operation_id),
+            root_view_id: ViewId::from_bytes(&[0u8; VIEW_ID_LENGTH]),
+            root_commit_id: root_data.root_commit_id,
+            workspace_tags: read_workspace_tags(store_path),
+            cache: OpCache::new(store_path, &settings),
+            offline_queue,
+        })
+    }
+}
+
+impl TandemOpStore {
+    fn view_deltas(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        if !self.client.supports_capability(RepoCapability::ViewDeltas) {
+            return None;
+        }
+        self.cache.delta_base()
+    }
+
+    /// Fetch a view's bytes, as a delta against the cached base if possible.
+    fn fetch_view(&self, id: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if let Some((base_id, base)) = self.view_deltas() {
+            match self.client.get_view_delta(id, &base_id) {
+                Ok(ViewFetch::Full(data)) => return Ok(data),
+                Ok(ViewFetch::Delta(delta)) => match view_delta::apply(&base, &delta) {
+                    Ok(data) if view_has_id(&data, id) => return Ok(data),
+                    _ => tracing::debug!("view delta did not reproduce the view; fetching in full"),
+                },
+                Err(err) => tracing::debug!(error = %err, "view delta fetch failed"),
+            }
+        }
+        self.client.get_view(id)
+    }
+
+    /// Store a view, as a delta against the cached base if that is smaller.
+    fn send_view(&self, contents: &View, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if let Some((base_id, base)) = self.view_deltas() {
+            let expected = blake2b_hash(contents).to_vec();
+            match view_delta::diff(&base, data) {
+                Ok(delta) if view_delta::encoded_len(&delta) < data.len() => {
+                    match self.client.put_view_delta(&base_id, delta) {
+                        Ok(id) if id == expected => return Ok(id),
+                        Ok(_) => {
+                            tracing::debug!("server rebuilt a different view; sending in full")
+                        }
+                        Err(err) => tracing::debug!(error = %err, "view delta write failed"),
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.client.put_view(data)
+    }
+}
+
+fn to_op_err(err: anyhow::Error) -> OpStoreError {
+    OpStoreError::Other(err.into())
+}
+
+#[async_trait]
+impl OpStore for TandemOpStore {
+    fn name(&self) -> &str {
+        "tandem_op_store"
+    }
+
+    fn root_operation_id(&self) -> &OperationId {
+        &self.root_operation_id
+    }
+
+    async fn read_view(&self, id: &ViewId) -> OpStoreResult<View> {
+        if *id == self.root_view_id {
+            return Ok(View::make_root(self.root_commit_id.clone()));
+        }
+        if let Some(view) = self
+            .cache
+            .load_verified("views", id.as_bytes(), decode_view)
+        {
+            return Ok(view);
+        }
+
+        let data = self
+            .fetch_view(id.as_bytes())
+            .map_err(|e| OpStoreError::ReadObject {
+                object_type: id.object_type(),
+                hash: id.hex(),
+                source: e.into(),
+            })?;
+
+        let view = decode_view(&data).map_err(to_op_err)?;
+        self.cache
+            .store_verified("views", id.as_bytes(), &view, &data);
+        self.cache.set_delta_base(id.as_bytes());
+        Ok(view)
+    }
+
+    async fn write_view(&self, contents: &View) -> OpStoreResult<ViewId> {
+        let proto = proto_convert::view_to_proto(contents);
+        let data = proto.encode_to_vec();
+        let id = match &self.offline_queue {
+            Some(queue) => {
+                let id = blake2b_hash(contents).to_vec();
+                let write = offline_queue::Write::View {
+                    id: encode_hex(&id),
+                };
+                queue.push(&write, &data).map_err(to_op_err)?;
+                id
+            }
+            None => self.send_view(contents, &data).map_err(to_op_err)?,
+        };
+        self.cache.store_verified("views", &id, contents, &data);
+        self.cache.set_delta_base(&id);
+        Ok(ViewId::new(id))
+    }
+
+    async fn read_operation(&self, id: &OperationId) -> OpStoreResult<Operation> {
+        if *id == self.root_operation_id {
+            return Ok(Operation::make_root(self.root_view_id.clone()));
+        }
+
+        let mut operation =
+            match self
+                .cache
+                .load_verified("operations", id.as_bytes(), decode_operation)
+            {
+                Some(operation) => operation,
+                None => {
+                    let data = read_mirror::read(self.mirror.as_deref(), &self.client, |client| {
+                        client.get_operation(id.as_bytes())
+                    })
+                    .map_err(|e| OpStoreError::ReadObject {
+                        object_type: id.object_type(),
+                        hash: id.hex(),
+                        source: e.into(),
+                    })?;
+                    let operation = decode_operation(&data).map_err(to_op_err)?;
+                    self.cache
+                        .store_verified("operations", id.as_bytes(), &operation, &data);
+                    operation
+                }
+            };
+
+        // Repos created before root operation support will have parentless operations
+        if operation.parents.is_empty() {
+            operation.parents.push(self.root_operation_id.clone());
+        }
+
+        Ok(operation)
+    }
+
+    async fn write_operation(&self, contents: &Operation) -> OpStoreResult<OperationId> {
+        assert!(!contents.parents.is_empty());
+        let proto = proto_convert::operation_to_proto(contents);
+        let data = proto.encode_to_vec();
+        let mut tags = crate::ops::tags_from_env();
+        tags.extend(self.workspace_tags.clone());
+        let id = match &self.offline_queue {
+            Some(queue) => {
+                let id = blake2b_hash(contents).to_vec();
+                let write = offline_queue::Write::Operation {
+                    id: encode_hex(&id),
+                    tags,
+                };
+                queue.push(&write, &data).map_err(to_op_err)?;
+                id
+            }
+            None => self.client.put_operation(&data, &tags).map_err(to_op_err)?,
+        };
+        self.cache
+            .store_verified("operations", &id, contents, &data);
+        Ok(OperationId::new(id))
+    }
+
+    async fn resolve_operation_id_prefix(
+        &self,
+        prefix: &HexPrefix,
+    ) -> OpStoreResult<PrefixResolution<OperationId>> {
+        let hex = prefix.hex();
+
+        // Check if it matches the root operation
+        let matches_root = prefix.matches(&self.root_operation_id);
+
+        // Full-length fast path
+        if hex.len() == OPERATION_ID_LENGTH * 2 && matches_root {
+            return Ok(PrefixResolution::SingleMatch(
+                self.root_operation_id.clone(),
+            ));
+        }
+
+        let (result, matched) = self.client.resolve_op_prefix(&hex).map_err(to_op_err)?;
+
+        match result {
+            PrefixResult::NoMatch => {
+                if matches_root {
+                    Ok(PrefixResolution::SingleMatch(
+                        self.root_operation_id.clone(),
+                    ))
+                } else {
+                    Ok(PrefixResolution::NoMatch)
+                }
+            }
+            PrefixResult::SingleMatch => {
+                if matches_root {
+                    // Both root and a stored operation match → ambiguous
+                    Ok(PrefixResolution::AmbiguousMatch)
+                } else if let Some(id_bytes) = matched {
+                    Ok(PrefixResolution::SingleMatch(OperationId::new(id_bytes)))
+                } else {
+                    Ok(PrefixResolution::NoMatch)
+                }
+            }
+            PrefixResult::Ambiguous => Ok(PrefixResolution::AmbiguousMatch),
+        }
+    }
+
+    fn gc(&self, _head_ids: &[OperationId], _keep_newer: SystemTime) -> OpStoreResult<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_serves_verified_entries_and_evicts_tampered_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = OpCache::new(dir.path(), &TandemSettings::default());
+        let view = View::make_root(CommitId::from_bytes(&[0u8; 20]));
+        let data = proto_convert::view_to_proto(&view).encode_to_vec();
+        let id = blake2b_hash(&view).to_vec();
+
+        cache.store_verified("views", &[0u8; VIEW_ID_LENGTH], &view, &data);
+        assert!(!cache.path("views", &[0u8; VIEW_ID_LENGTH]).exists());
+
+        cache.store_verified("views", &id, &view, &data);
+        assert_eq!(cache.load_verified("views", &id, decode_view), Some(view));
+
+        std::fs::write(cache.path("views", &id), b"tampered").unwrap();
+        assert_eq!(cache.load_verified("views", &id, decode_view), None);
+        assert!(!cache.path("views", &id).exists());
+    }
+
+    #[test]
+    fn delta_base_is_the_last_cached_view() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = OpCache::new(dir.path(), &TandemSettings::default());
+        let view = View::make_root(CommitId::from_bytes(&[0u8; 20]));
+        let data = proto_convert::view_to_proto(&view).encode_to_vec();
+        let id = blake2b_hash(&view).to_vec();
+        assert_eq!(cache.delta_base(), None);
+
+        cache.store_verified("views", &id, &view, &data);
+        cache.set_delta_base(&id);
+        assert_eq!(cache.delta_base(), Some((id.clone(), data.clone())));
+        assert!(view_has_id(&data, &id));
+
+        // An evicted base is simply not used.
+        std::fs::remove_file(cache.path("views", &id)).unwrap();
+        assert_eq!(cache.delta_base(), None);
+    }
+}
@@ -0,0 +1,86 @@
+//! tandem ops — query operation tags stored on a tandem server.
+//!
+//! Tags are free-form key/value pairs attached to an operation when the
+//! client writes it (`TANDEM_OP_TAGS=ci=123,agent=alice`). They live in a
+//! server-side sidecar so they do not change jj's operation content hashes.
+
+use std::collections::BTreeMap;
+
+use anyhow::{bail, Context, Result};
+
+use crate::rpc::{RepoCapability, TandemClient};
+
+/// Environment variable holding tags for operations written by this process.
+pub const OP_TAGS_ENV: &str = "TANDEM_OP_TAGS";
+
+/// Parse a single `key=value` tag. An empty value is allowed; an empty key is not.
+pub fn parse_tag(raw: &str) -> Result<(String, String)> {
+    let (key, value) = raw.split_once('=').unwrap_or((raw, ""));
+    let key = key.trim();
+    if key.is_empty() {
+        bail!("invalid tag {raw:?}: expected key=value");
+    }
+    Ok((key.to_string(), value.trim().to_string()))
+}
+
+/// Parse a comma-separated `key=value,key=value` list.
+pub fn parse_tag_list(raw: &str) -> Result<BTreeMap<String, String>> {
+    let mut tags = BTreeMap::new();
+    for token in raw.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let (key, value) = parse_tag(token)?;
+        tags.insert(key, value);
+    }
+    Ok(tags)
+}
+
+/// Tags requested for operations written by this process via `TANDEM_OP_TAGS`.
+/// Malformed entries are logged and dropped rather than failing the write.
+pub fn tags_from_env() -> BTreeMap<String, String> {
+    let Ok(raw) = std::env::var(OP_TAGS_ENV) else {
+        return BTreeMap::new();
+    };
+    parse_tag_list(&raw).unwrap_or_else(|err| {
+        tracing::warn!(env = OP_TAGS_ENV, error = %err, "ignoring malformed operation tags");
+        BTreeMap::new()
+    })
+}
+
+pub fn format_tags(tags: &BTreeMap<String, String>) -> String {
+    tags.iter()
+        .map(|(key, value)| format!("{key}={value}"))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+// ─── Public entry point ───────────────────────────────────────────────────────
+
+pub fn run_ops(server_addr: &str, filters: &[String], json: bool) -> Result<()> {
+    let mut parsed = BTreeMap::new();
+    for filter in filters {
+        let (key, value) = parse_tag(filter)?;
+        parsed.insert(key, value);
+    }
+
+    let client =
+        TandemClient::connect_with_requirements(server_addr, &[RepoCapability::OperationTags])
+            .with_context(|| format!("cannot query operation tags on {server_addr}"))?;
+    let operations = client.get_operation_tags(&parsed)?;
+
+    if json {
+        let entries: Vec<serde_json::Value> = operations
+            .iter()
+            .map(|op| {
+                serde_json::json!({
+                    "id": op.id_hex(),
+                    "tags": op.tags,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+    } else {
+        for op in &operations {
+            println!("{} {}", op.id_hex(), format_tags(&op.tags));
+        }
+    }
+    Ok(())
+}
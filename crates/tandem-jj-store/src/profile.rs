@@ -0,0 +1,229 @@
+//! Built-in profiler — where a slow command spends its time, without a
+//! custom build.
+//!
+//! `tandem --tandem-profile <file> <jj command>` samples every thread that
+//! is inside tandem code once a millisecond and writes folded stacks, the
+//! input `flamegraph.pl` and `inferno-flamegraph` take:
+//!
+//! ```text
+//! tandem log;jj-lib 182345
+//! tandem log;rpc wait;getObject 96120
+//! tandem log;proto decode;commit 4210
+//! ```
+//!
+//! Frames are not native stacks but the phases tandem marks with [`enter`]:
+//! waiting on an RPC reply (by method), decoding a proto (by kind), and
+//! reading the local object cache. Time the command's main thread spends
+//! outside them is jj-lib's own work (revsets, diffing, the working copy)
+//! and shows as `jj-lib`. Other threads are only sampled inside a phase.
+//! Each line's count is wall time in microseconds: the main thread's lines
+//! add up to how long the command ran, and other threads' come on top.
+//!
+//! Marking a phase costs one atomic load while no profile is being taken.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+
+/// How often threads are sampled.
+pub const INTERVAL: Duration = Duration::from_millis(1);
+
+/// Main-thread time outside any marked phase.
+const UNMARKED: &str = "jj-lib";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static PROFILER: Mutex<Option<Profiler>> = Mutex::new(None);
+/// Every thread that has marked a phase (or started the profile).
+static THREADS: Mutex<Vec<Arc<Mutex<ThreadStack>>>> = Mutex::new(Vec::new());
+
+struct Profiler {
+    path: PathBuf,
+    shared: Arc<Shared>,
+    sampler: std::thread::JoinHandle<()>,
+}
+
+struct Shared {
+    /// First frame of every stack, e.g. `tandem log`.
+    root: String,
+    samples: Mutex<BTreeMap<String, u64>>,
+    stop: AtomicBool,
+}
+
+#[derive(Default)]
+struct ThreadStack {
+    /// The thread that started profiling; sampled even outside phases.
+    main: bool,
+    frames: Vec<&'static str>,
+}
+
+thread_local! {
+    static STACK: RefCell<Option<Arc<Mutex<ThreadStack>>>> = const { RefCell::new(None) };
+}
+
+/// Start sampling, to be written to `path` by [`finish`]. `command` names
+/// the root frame, e.g. `tandem log`.
+pub fn start(path: &Path, command: &str) {
+    let mut profiler = PROFILER.lock().unwrap();
+    if profiler.is_some() {
+        return;
+    }
+    let shared = Arc::new(Shared {
+        root: command.replace(';', ":"),
+        samples: Mutex::new(BTreeMap::new()),
+        stop: AtomicBool::new(false),
+    });
+    ENABLED.store(true, Ordering::Relaxed);
+    this_thread().lock().unwrap().main = true;
+    let sampler_shared = shared.clone();
+    let sampler = std::thread::Builder::new()
+        .name("tandem-profile".to_string())
+        .spawn(move || sample(&sampler_shared))
+        .expect("spawn profiler thread");
+    *profiler = Some(Profiler {
+        path: path.to_path_buf(),
+        shared,
+        sampler,
+    });
+}
+
+/// Stop sampling and write the folded stacks. Returns where they went and
+/// how many microseconds were sampled, or `None` if no profile was started.
+pub fn finish() -> Option<Result<(PathBuf, u64)>> {
+    let profiler = PROFILER.lock().unwrap().take()?;
+    ENABLED.store(false, Ordering::Relaxed);
+    profiler.shared.stop.store(true, Ordering::Relaxed);
+    let _ = profiler.sampler.join();
+    let samples = std::mem::take(&mut *profiler.shared.samples.lock().unwrap());
+    Some(write_folded(&profiler.path, &samples).map(|()| {
+        let total = samples.values().sum();
+        (profiler.path, total)
+    }))
+}
+
+fn write_folded(path: &Path, samples: &BTreeMap<String, u64>) -> Result<()> {
+    let mut out = Vec::new();
+    for (stack, micros) in samples {
+        writeln!(out, "{stack} {micros}")?;
+    }
+    std::fs::write(path, out).with_context(|| format!("writing {}", path.display()))
+}
+
+/// A marked phase; it ends when dropped.
+#[must_use = "the phase ends when this is dropped"]
+pub struct Phase {
+    depth: usize,
+}
+
+/// Mark the calling thread as being in `frames` (outermost first) until the
+/// returned guard is dropped.
+pub fn enter(frames: &[&'static str]) -> Phase {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Phase { depth: 0 };
+    }
+    this_thread()
+        .lock()
+        .unwrap()
+        .frames
+        .extend_from_slice(frames);
+    Phase {
+        depth: frames.len(),
+    }
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        if self.depth == 0 {
+            return;
+        }
+        STACK.with(|stack| {
+            if let Some(stack) = stack.borrow().as_ref() {
+                let mut stack = stack.lock().unwrap();
+                let keep = stack.frames.len().saturating_sub(self.depth);
+                stack.frames.truncate(keep);
+            }
+        });
+    }
+}
+
+/// The calling thread's stack, registered on first use.
+fn this_thread() -> Arc<Mutex<ThreadStack>> {
+    STACK.with(|stack| {
+        stack
+            .borrow_mut()
+            .get_or_insert_with(|| {
+                let thread = Arc::new(Mutex::new(ThreadStack::default()));
+                THREADS.lock().unwrap().push(thread.clone());
+                thread
+            })
+            .clone()
+    })
+}
+
+fn sample(shared: &Shared) {
+    let mut last = Instant::now();
+    while !shared.stop.load(Ordering::Relaxed) {
+        std::thread::sleep(INTERVAL);
+        let now = Instant::now();
+        let micros = now.duration_since(last).as_micros() as u64;
+        last = now;
+
+        let threads = THREADS.lock().unwrap();
+        let mut samples = shared.samples.lock().unwrap();
+        for thread in threads.iter() {
+            let thread = thread.lock().unwrap();
+            let stack = match (thread.frames.is_empty(), thread.main) {
+                (true, false) => continue,
+                (true, true) => format!("{};{UNMARKED}", shared.root),
+                (false, _) => format!("{};{}", shared.root, thread.frames.join(";")),
+            };
+            *samples.entry(stack).or_default() += micros;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_marked_phases_as_folded_stacks() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let path = tmp.path().join("profile.folded");
+        start(&path, "tandem log");
+        {
+            let _phase = enter(&["rpc wait", "getObject"]);
+            std::thread::sleep(Duration::from_millis(30));
+        }
+        std::thread::spawn(|| {
+            let _phase = enter(&["proto decode", "commit"]);
+            std::thread::sleep(Duration::from_millis(30));
+        })
+        .join()
+        .unwrap();
+        let (written, total) = finish().unwrap().unwrap();
+        assert_eq!(written, path);
+        assert!(total >= 50_000, "{total}");
+
+        let folded = std::fs::read_to_string(&path).unwrap();
+        let stacks: Vec<&str> = folded
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert!(
+            stacks.contains(&"tandem log;rpc wait;getObject"),
+            "{folded}"
+        );
+        assert!(
+            stacks.contains(&"tandem log;proto decode;commit"),
+            "{folded}"
+        );
+        assert!(stacks.contains(&"tandem log;jj-lib"), "{folded}");
+        assert!(finish().is_none());
+    }
+}
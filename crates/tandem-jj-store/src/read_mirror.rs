@@ -0,0 +1,157 @@
+//! Read mirror — fetch objects and operations from a nearby server while
+//! writes and head updates stay on the primary.
+//!
+//! ```toml
+//! [tandem]
+//! read-mirror = "replica-eu:13013"   # or TANDEM_READ_MIRROR
+//! ```
+//!
+//! `tandem init --read-mirror <addr>` sets the key in the workspace's repo
+//! config. The mirror is usually a read-only replica (`tandem serve --follow
+//! <primary>`) close to the workspace. The backend and op store send
+//! `getObject` (and tree pages) and `getOperation` to it; views, op heads,
+//! and every write go to the primary.
+//!
+//! A replica trails its primary, so a read the mirror cannot answer — most
+//! often something this workspace wrote a moment ago — is retried on the
+//! primary. A mirror that is unreachable or serves another repo is skipped
+//! with one warning per command; "another repo" uses the same identity check
+//! as a server override (`server_address.rs`): the mirror must know the
+//! operations the workspace anchored itself to. Its root ids must also match
+//! the primary's, which catches a mirror using other hash lengths.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+
+use crate::rpc::{self, RepoInfoResponse, TandemClient};
+use crate::server_address;
+use crate::settings::TandemSettings;
+
+pub const ENV: &str = "TANDEM_READ_MIRROR";
+
+/// Set once one store gave up on the mirror, so the others in the same
+/// command neither dial it again nor warn twice.
+static SKIPPED: AtomicBool = AtomicBool::new(false);
+
+/// The mirror configured for this workspace, if any.
+pub fn address(settings: &TandemSettings) -> Option<String> {
+    std::env::var(ENV)
+        .ok()
+        .map(|addr| addr.trim().to_string())
+        .filter(|addr| !addr.is_empty())
+        .or_else(|| settings.read_mirror.clone())
+}
+
+/// Connect to the read mirror for the store at `store_path`, whose primary
+/// connection is `primary`. `None` when no mirror is configured, the primary
+/// is offline, or the mirror cannot be used.
+pub fn connect(
+    store_path: &Path,
+    primary: &TandemClient,
+    settings: &TandemSettings,
+) -> Option<Arc<TandemClient>> {
+    let addr = address(settings)?;
+    if primary.is_offline() || addr == primary.server_addr() || SKIPPED.load(Ordering::Relaxed) {
+        return None;
+    }
+    let result = TandemClient::connect_with_options(&addr, &[], settings.client_options())
+        .and_then(|mirror| {
+            ensure_same_repo(primary.repo_info(), mirror.repo_info(), &addr)?;
+            if let Some(reason) = server_address::anchor_mismatch(store_path, &mirror)? {
+                bail!("{reason}");
+            }
+            Ok(mirror)
+        });
+    match result {
+        Ok(mirror) => Some(mirror),
+        Err(err) => {
+            if !SKIPPED.swap(true, Ordering::Relaxed) {
+                eprintln!(
+                    "warning: not using read mirror {addr}: {err:#}; reading from {}",
+                    primary.server_addr()
+                );
+            }
+            None
+        }
+    }
+}
+
+/// Refuse a mirror whose root ids differ from the primary's.
+pub fn ensure_same_repo(
+    primary: &RepoInfoResponse,
+    mirror: &RepoInfoResponse,
+    mirror_addr: &str,
+) -> Result<()> {
+    if primary.root_commit_id != mirror.root_commit_id
+        || primary.root_change_id != mirror.root_change_id
+        || primary.root_operation_id != mirror.root_operation_id
+    {
+        bail!("{mirror_addr} serves a different repo (its root ids differ from the primary's)");
+    }
+    Ok(())
+}
+
+/// Check before `tandem init --read-mirror` records `mirror_addr`.
+pub fn probe(primary: &RepoInfoResponse, mirror_addr: &str) -> Result<()> {
+    let mirror = rpc::probe_repo_info(mirror_addr)?;
+    ensure_same_repo(primary, &mirror, mirror_addr)
+}
+
+/// Run `fetch` against the mirror when there is one, and against the primary
+/// if there is none or the mirror fails.
+pub fn read<T>(
+    mirror: Option<&TandemClient>,
+    primary: &TandemClient,
+    fetch: impl Fn(&TandemClient) -> Result<T>,
+) -> Result<T> {
+    if let Some(mirror) = mirror {
+        match fetch(mirror) {
+            Ok(value) => return Ok(value),
+            Err(err) => tracing::debug!(
+                mirror = %mirror.server_addr(),
+                error = %err,
+                "read mirror miss; asking the primary"
+            ),
+        }
+    }
+    fetch(primary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(root_operation_id: u8) -> RepoInfoResponse {
+        RepoInfoResponse {
+            protocol_major: 0,
+            protocol_minor: 1,
+            jj_version: None,
+            tandem_version: None,
+            backend_name: "tandem".to_string(),
+            op_store_name: "tandem_op_store".to_string(),
+            commit_id_length: 20,
+            change_id_length: 16,
+            commit_hash: "sha1".to_string(),
+            operation_hash: "blake2b-512".to_string(),
+            root_commit_id: vec![0; 20],
+            root_change_id: vec![0; 16],
+            empty_tree_id: vec![1; 20],
+            root_operation_id: vec![root_operation_id; 64],
+            capabilities: Default::default(),
+            redirect: None,
+            git_remote: None,
+            op_schema: String::new(),
+            snapshot_auto_track: None,
+        }
+    }
+
+    #[test]
+    fn a_mirror_must_serve_the_same_repo() {
+        assert!(ensure_same_repo(&info(0), &info(0), "mirror:13013").is_ok());
+        let err = ensure_same_repo(&info(0), &info(7), "replica:13013").unwrap_err();
+        assert!(err.to_string().contains("serves a different repo"), "{err}");
+    }
+}
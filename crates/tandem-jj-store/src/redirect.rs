@@ -0,0 +1,114 @@
+//! Endpoint redirects — "this repo now lives at <addr>".
+//!
+//! A redirected server answers `getRepoInfo` with the new address in
+//! `RepoInfo.redirect` and refuses writes. Clients follow the chain (at most
+//! `MAX_HOPS` servers) and rewrite their workspace's `server_address`, so a
+//! repo can change hosts without DNS and without touching every workspace.
+//!
+//! `tandem migrate` sets the redirect once the copy is complete; operators
+//! who moved the repo directory themselves use `tandem server redirect`.
+//! The target survives restarts in `.jj/repo/tandem/redirect.json`
+//! ([`metadata_schema::REDIRECT`]).
+
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata_schema;
+
+/// Longest redirect chain a client follows before giving up.
+pub const MAX_HOPS: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedirectTarget {
+    pub address: String,
+    /// Heads version the repo had when it moved (set by `tandem migrate`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<u64>,
+    pub moved_at_millis: u64,
+}
+
+/// Redirect state shared by the RPC server and the control socket.
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    path: PathBuf,
+    target: Arc<Mutex<Option<RedirectTarget>>>,
+}
+
+impl Redirect {
+    /// Load the persisted redirect, if any, from `path`.
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let target = if path.exists() {
+            Some(metadata_schema::read::<RedirectTarget>(
+                &metadata_schema::REDIRECT,
+                &path,
+            )?)
+        } else {
+            None
+        };
+        Ok(Self {
+            path,
+            target: Arc::new(Mutex::new(target)),
+        })
+    }
+
+    pub fn current(&self) -> Option<RedirectTarget> {
+        self.target.lock().unwrap().clone()
+    }
+
+    pub fn address(&self) -> Option<String> {
+        self.current().map(|target| target.address)
+    }
+
+    pub fn set(&self, target: RedirectTarget) -> Result<()> {
+        metadata_schema::write(&metadata_schema::REDIRECT, &self.path, &target)
+            .with_context(|| format!("write {}", self.path.display()))?;
+        tracing::warn!(address = %target.address, "redirecting clients; writes are refused");
+        *self.target.lock().unwrap() = Some(target);
+        Ok(())
+    }
+
+    /// Stop redirecting. Returns the redirect that was in effect.
+    pub fn clear(&self) -> Result<Option<RedirectTarget>> {
+        let previous = self.target.lock().unwrap().take();
+        if previous.is_some() {
+            match fs::remove_file(&self.path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).with_context(|| format!("remove {}", self.path.display())),
+            }
+            tracing::warn!("redirect cleared; accepting writes again");
+        }
+        Ok(previous)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redirect_persists_across_loads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("redirect.json");
+
+        let redirect = Redirect::load(path.clone()).unwrap();
+        assert_eq!(redirect.current(), None);
+        let target = RedirectTarget {
+            address: "new-host:13013".to_string(),
+            version: Some(7),
+            moved_at_millis: 1,
+        };
+        redirect.set(target.clone()).unwrap();
+
+        let reloaded = Redirect::load(path.clone()).unwrap();
+        assert_eq!(reloaded.current(), Some(target.clone()));
+        assert_eq!(reloaded.clear().unwrap(), Some(target));
+        assert!(!path.exists());
+        assert_eq!(Redirect::load(path).unwrap().address(), None);
+    }
+}
@@ -0,0 +1,2114 @@
+//! Shared RPC client wrapper for connecting to a tandem server.
+//!
+//! Manages a Cap'n Proto connection on a dedicated thread (because capnp-rpc
+//! types are !Send). Communication from Backend/OpStore/OpHeadsStore happens
+//! through std::sync::mpsc channels.
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+use capnp_rpc::{rpc_twoparty_capnp, twoparty, RpcSystem};
+use serde::Serialize;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+use crate::drain;
+use crate::jj_compat;
+use crate::journal;
+use crate::limits;
+use crate::maintenance;
+use crate::profile;
+use crate::rpc_record;
+use crate::tandem_capnp::{path_overlap, store};
+use crate::view_delta::DeltaOp;
+use crate::watchdog;
+
+pub use tandem_client::transport::{
+    connect_stream, is_unreachable, serve_local_repos_with, ConnectorTarget, InProcessListener,
+    RpcReadHalf, RpcStream, RpcWriteHalf,
+};
+pub use tandem_client::{HashAlgorithm, HeadsState, RepoCapability, RepoInfo as RepoInfoResponse};
+
+// ─── Public types ─────────────────────────────────────────────────────────────
+
+const PROTOCOL_MAJOR: u16 = 0;
+const PROTOCOL_MINOR: u16 = 1;
+const EXPECTED_BACKEND_NAME: &str = "tandem";
+const EXPECTED_OP_STORE_NAME: &str = "tandem_op_store";
+const ROOT_OPERATION_ID_LENGTH: usize = 64;
+const BENCH_INJECT_RTT_MS_ENV: &str = "TANDEM_BENCH_INJECT_RTT_MS";
+const BENCH_DISABLE_RPC_INFLIGHT_ENV: &str = "TANDEM_BENCH_DISABLE_RPC_INFLIGHT";
+const RPC_MAX_INFLIGHT_ENV: &str = "TANDEM_RPC_MAX_INFLIGHT";
+const DEFAULT_RPC_MAX_INFLIGHT: usize = 32;
+
+/// Per-connection client tuning (see `settings.rs` for the jj config keys).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClientOptions {
+    /// Concurrent RPC cap; `TANDEM_RPC_MAX_INFLIGHT` takes precedence.
+    pub max_inflight: Option<usize>,
+    /// Print every RPC's latency to stderr.
+    pub timing: bool,
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct UpdateHeadsResult {
+    pub ok: bool,
+    pub heads: Vec<Vec<u8>>,
+    pub version: u64,
+    /// Backoff floor the server asks for during a CAS storm; 0 otherwise.
+    pub min_backoff_ms: u32,
+}
+
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct HeadsSnapshot {
+    pub heads: Vec<Vec<u8>>,
+    pub version: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaggedOperation {
+    pub id: Vec<u8>,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl TaggedOperation {
+    pub fn id_hex(&self) -> String {
+        self.id.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+/// One `searchCommits` hit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommitMatch {
+    pub commit_id: Vec<u8>,
+    pub change_id: Vec<u8>,
+    pub description: String,
+    pub author_name: String,
+    pub author_email: String,
+    pub author_timestamp_ms: i64,
+}
+
+/// One page of `getCommitGraph`.
+#[derive(Debug, Clone, Default)]
+pub struct CommitGraphPage {
+    /// `(commit id, encoded commit)`, newest first.
+    pub commits: Vec<(Vec<u8>, Vec<u8>)>,
+    /// The heads the server walked from; send them with later pages.
+    pub heads: Vec<Vec<u8>>,
+    /// Empty after the last page.
+    pub next_cursor: String,
+}
+
+/// An advisory lock a workspace holds on a change (`lockChange`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChangeLock {
+    pub change_id: Vec<u8>,
+    pub workspace_id: String,
+    /// Milliseconds since the Unix epoch.
+    pub expires_at_millis: u64,
+    pub strict: bool,
+}
+
+/// What merging two commits would give, from `previewMerge`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergePreview {
+    pub clean: bool,
+    /// Repo-relative paths the merge leaves conflicted.
+    pub conflicted_paths: Vec<String>,
+    pub paths_truncated: bool,
+}
+
+/// Paths two workspaces have both changed since their working copies
+/// diverged, from `getHeads(withOverlaps)` and heads summaries.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PathOverlap {
+    pub workspace_a: String,
+    pub workspace_b: String,
+    pub paths: Vec<String>,
+    pub paths_truncated: bool,
+}
+
+impl PathOverlap {
+    /// The workspace on the other side from `workspace_id`, if it is one of
+    /// the pair.
+    pub fn other(&self, workspace_id: &str) -> Option<&str> {
+        if self.workspace_a == workspace_id {
+            Some(&self.workspace_b)
+        } else if self.workspace_b == workspace_id {
+            Some(&self.workspace_a)
+        } else {
+            None
+        }
+    }
+
+    pub fn write(&self, mut builder: path_overlap::Builder<'_>) {
+        builder.set_workspace_a(self.workspace_a.as_str());
+        builder.set_workspace_b(self.workspace_b.as_str());
+        {
+            let mut list = builder.reborrow().init_paths(self.paths.len() as u32);
+            for (i, path) in self.paths.iter().enumerate() {
+                list.set(i as u32, path.as_str());
+            }
+        }
+        builder.set_paths_truncated(self.paths_truncated);
+    }
+
+    pub fn read(reader: path_overlap::Reader<'_>) -> capnp::Result<Self> {
+        let text = |t: capnp::text::Reader<'_>| t.to_string().unwrap_or_default();
+        Ok(PathOverlap {
+            workspace_a: text(reader.get_workspace_a()?),
+            workspace_b: text(reader.get_workspace_b()?),
+            paths: reader
+                .get_paths()?
+                .iter()
+                .map(|t| t.map(text))
+                .collect::<capnp::Result<_>>()?,
+            paths_truncated: reader.get_paths_truncated(),
+        })
+    }
+}
+
+/// One entry of the server's heads history journal.
+#[derive(Debug, Clone)]
+pub struct HeadsTransition {
+    pub version: u64,
+    pub heads: Vec<Vec<u8>>,
+    /// Workspace whose head update caused the transition; `None` for
+    /// server-originated changes.
+    pub workspace_id: Option<String>,
+    pub timestamp_millis: u64,
+}
+
+/// A server-side hold on the heads version, from `beginSnapshot`.
+#[derive(Debug, Clone)]
+pub struct SnapshotHold {
+    pub snapshot_id: u64,
+    pub version: u64,
+    pub lease_seconds: u32,
+}
+
+/// A view from `getViewDelta`: changes against the requested base, or the
+/// whole view when the server could not use the base.
+#[derive(Debug, Clone)]
+pub enum ViewFetch {
+    Delta(Vec<DeltaOp>),
+    Full(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrefixResult {
+    NoMatch,
+    SingleMatch,
+    Ambiguous,
+}
+
+// ─── RPC message types ────────────────────────────────────────────────────────
+
+type Reply<T> = std::sync::mpsc::Sender<Result<T>>;
+
+/// Reply to [`TandemClient::begin_put_object`]: `(id, normalized data)`.
+pub type PendingPut = std::sync::mpsc::Receiver<Result<(Vec<u8>, Vec<u8>)>>;
+
+/// Reply to [`TandemClient::begin_get_object`]: the object's bytes.
+pub type PendingGet = std::sync::mpsc::Receiver<Result<Vec<u8>>>;
+
+#[allow(dead_code)]
+enum RpcMsg {
+    GetObject {
+        kind: u16,
+        id: Vec<u8>,
+        reply: Reply<Vec<u8>>,
+    },
+    GetTreeEntries {
+        id: Vec<u8>,
+        cursor: String,
+        limit: u32,
+        reply: Reply<(Vec<u8>, String)>,
+    },
+    SearchCommits {
+        query: String,
+        author: String,
+        limit: u32,
+        reply: Reply<(Vec<CommitMatch>, bool)>,
+    },
+    LockChange {
+        change_id: Vec<u8>,
+        workspace_id: String,
+        ttl_seconds: u32,
+        strict: bool,
+        reply: Reply<(bool, ChangeLock)>,
+    },
+    UnlockChange {
+        change_id: Vec<u8>,
+        workspace_id: String,
+        force: bool,
+        reply: Reply<(bool, String)>,
+    },
+    GetChangeLocks {
+        reply: Reply<Vec<ChangeLock>>,
+    },
+    PreviewMerge {
+        commit_a: Vec<u8>,
+        commit_b: Vec<u8>,
+        path_limit: u32,
+        reply: Reply<MergePreview>,
+    },
+    GetCommitGraph {
+        heads: Vec<Vec<u8>>,
+        known: Vec<Vec<u8>>,
+        cursor: String,
+        limit: u32,
+        reply: Reply<CommitGraphPage>,
+    },
+    PutObject {
+        kind: u16,
+        data: Vec<u8>,
+        reply: Reply<(Vec<u8>, Vec<u8>)>,
+    },
+    GetOperation {
+        id: Vec<u8>,
+        reply: Reply<Vec<u8>>,
+    },
+    PutOperation {
+        data: Vec<u8>,
+        tags: BTreeMap<String, String>,
+        reply: Reply<Vec<u8>>,
+    },
+    GetView {
+        id: Vec<u8>,
+        reply: Reply<Vec<u8>>,
+    },
+    PutView {
+        data: Vec<u8>,
+        reply: Reply<Vec<u8>>,
+    },
+    GetHeads {
+        reply: Reply<HeadsState>,
+    },
+    GetPathOverlaps {
+        reply: Reply<Vec<PathOverlap>>,
+    },
+    GetHeadsVersion {
+        reply: Reply<u64>,
+    },
+    UpdateOpHeads {
+        old_ids: Vec<Vec<u8>>,
+        new_id: Vec<u8>,
+        expected_version: u64,
+        workspace_id: String,
+        idempotency_key: Vec<u8>,
+        reply: Reply<UpdateHeadsResult>,
+    },
+    GetHeadsSnapshot {
+        reply: Reply<Option<HeadsSnapshot>>,
+    },
+    GetRelatedCopies {
+        copy_id: Vec<u8>,
+        reply: Reply<Option<Vec<Vec<u8>>>>,
+    },
+    ResolveOpPrefix {
+        hex_prefix: String,
+        reply: Reply<(PrefixResult, Option<Vec<u8>>)>,
+    },
+    GetOperationTags {
+        filters: BTreeMap<String, String>,
+        reply: Reply<Vec<TaggedOperation>>,
+    },
+    BeginSnapshot {
+        lease_seconds: u32,
+        reply: Reply<SnapshotHold>,
+    },
+    EndSnapshot {
+        snapshot_id: u64,
+        reply: Reply<bool>,
+    },
+    ImportHeads {
+        state: HeadsState,
+        reply: Reply<u64>,
+    },
+    SetRedirect {
+        address: String,
+        expected_version: u64,
+        reply: Reply<(bool, u64)>,
+    },
+    GetViewDelta {
+        id: Vec<u8>,
+        base_id: Vec<u8>,
+        reply: Reply<ViewFetch>,
+    },
+    PutViewDelta {
+        base_id: Vec<u8>,
+        delta: Vec<DeltaOp>,
+        reply: Reply<Vec<u8>>,
+    },
+    GetHeadsHistory {
+        after_version: u64,
+        reply: Reply<(Vec<HeadsTransition>, u64)>,
+    },
+}
+
+impl RpcMsg {
+    /// Schema method name, for timing output.
+    fn method(&self) -> &'static str {
+        match self {
+            Self::GetObject { .. } => "getObject",
+            Self::GetTreeEntries { .. } => "getTreeEntries",
+            Self::SearchCommits { .. } => "searchCommits",
+            Self::LockChange { .. } => "lockChange",
+            Self::UnlockChange { .. } => "unlockChange",
+            Self::GetChangeLocks { .. } => "getChangeLocks",
+            Self::PreviewMerge { .. } => "previewMerge",
+            Self::GetCommitGraph { .. } => "getCommitGraph",
+            Self::PutObject { .. } => "putObject",
+            Self::GetOperation { .. } => "getOperation",
+            Self::PutOperation { .. } => "putOperation",
+            Self::GetView { .. } => "getView",
+            Self::PutView { .. } => "putView",
+            Self::GetHeads { .. } | Self::GetPathOverlaps { .. } => "getHeads",
+            Self::GetHeadsVersion { .. } => "getHeadsVersion",
+            Self::UpdateOpHeads { .. } => "updateOpHeads",
+            Self::GetHeadsSnapshot { .. } => "getHeadsSnapshot",
+            Self::GetRelatedCopies { .. } => "getRelatedCopies",
+            Self::ResolveOpPrefix { .. } => "resolveOperationIdPrefix",
+            Self::GetOperationTags { .. } => "getOperationTags",
+            Self::BeginSnapshot { .. } => "beginSnapshot",
+            Self::EndSnapshot { .. } => "endSnapshot",
+            Self::ImportHeads { .. } => "importHeads",
+            Self::SetRedirect { .. } => "setRedirect",
+            Self::GetViewDelta { .. } => "getViewDelta",
+            Self::PutViewDelta { .. } => "putViewDelta",
+            Self::GetHeadsHistory { .. } => "getHeadsHistory",
+        }
+    }
+}
+
+// ─── TandemClient ─────────────────────────────────────────────────────────────
+
+/// Cap'n Proto RPC client to a tandem server.
+///
+/// All three trait implementations (TandemBackend, TandemOpStore,
+/// TandemOpHeadsStore) share a connection through this client via Arc.
+pub struct TandemClient {
+    /// `None` for an offline client: every RPC fails without dialing.
+    tx: Option<tokio::sync::mpsc::UnboundedSender<RpcMsg>>,
+    _thread: Option<std::thread::JoinHandle<()>>,
+    server_addr: String,
+    repo_info: RepoInfoResponse,
+}
+
+impl std::fmt::Debug for TandemClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TandemClient")
+            .field("server_addr", &self.server_addr)
+            .finish()
+    }
+}
+
+impl TandemClient {
+    /// Connect to a tandem server at the given address.
+    /// Starts a background thread for the Cap'n Proto RPC event loop.
+    pub fn connect(addr: &str) -> Result<Arc<Self>> {
+        Self::connect_with_requirements(addr, &[])
+    }
+
+    pub fn connect_with_requirements(
+        addr: &str,
+        required_capabilities: &[RepoCapability],
+    ) -> Result<Arc<Self>> {
+        Self::connect_with_options(addr, required_capabilities, ClientOptions::default())
+    }
+
+    pub fn connect_with_options(
+        addr: &str,
+        required_capabilities: &[RepoCapability],
+        options: ClientOptions,
+    ) -> Result<Arc<Self>> {
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel::<RpcMsg>();
+        let addr_owned = addr.to_string();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<RepoInfoResponse>>();
+
+        let addr_for_thread = addr_owned.clone();
+        let required_caps = required_capabilities.to_vec();
+        let thread = std::thread::spawn(move || {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("build tokio runtime for RPC thread");
+            let local = tokio::task::LocalSet::new();
+            local.block_on(
+                &rt,
+                rpc_loop(addr_for_thread, required_caps, options, rx, ready_tx),
+            );
+        });
+
+        // Wait for connection to be established + compatibility validated.
+        let repo_info = ready_rx
+            .recv()
+            .map_err(|_| anyhow!("RPC thread died before signaling readiness"))??;
+
+        Ok(Arc::new(TandemClient {
+            tx: Some(tx),
+            _thread: Some(thread),
+            server_addr: repo_info.redirect.clone().unwrap_or(addr_owned),
+            repo_info,
+        }))
+    }
+
+    /// A client for a server that cannot be reached, answering `repo_info`
+    /// from a saved copy (see `offline.rs`). Every RPC fails.
+    pub fn offline(addr: &str, repo_info: RepoInfoResponse) -> Arc<Self> {
+        Arc::new(TandemClient {
+            tx: None,
+            _thread: None,
+            server_addr: addr.to_string(),
+            repo_info,
+        })
+    }
+
+    pub fn is_offline(&self) -> bool {
+        self.tx.is_none()
+    }
+
+    /// Get the server address this client is connected to.
+    pub fn server_addr(&self) -> &str {
+        &self.server_addr
+    }
+
+    pub fn repo_info(&self) -> &RepoInfoResponse {
+        &self.repo_info
+    }
+
+    pub fn supports_capability(&self, capability: RepoCapability) -> bool {
+        self.repo_info.capabilities.contains(&capability)
+    }
+
+    // ─── Blocking RPC methods ─────────────────────────────────────────
+
+    fn send(&self, msg: RpcMsg) -> Result<()> {
+        let Some(tx) = &self.tx else {
+            bail!(
+                "tandem server {} is unreachable (offline mode)",
+                self.server_addr
+            );
+        };
+        tx.send(msg).map_err(|_| anyhow!("RPC channel closed"))
+    }
+
+    #[allow(dead_code)]
+    pub fn get_repo_info(&self) -> Result<RepoInfoResponse> {
+        Ok(self.repo_info.clone())
+    }
+
+    pub fn get_object(&self, kind: u16, id: &[u8]) -> Result<Vec<u8>> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetObject {
+            kind,
+            id: id.to_vec(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getObject")
+    }
+
+    /// Send a `getObject` without waiting; the reply arrives on the returned
+    /// receiver. Lets a reader keep several fetches in flight.
+    pub fn begin_get_object(&self, kind: u16, id: &[u8]) -> Result<PendingGet> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetObject {
+            kind,
+            id: id.to_vec(),
+            reply: reply_tx,
+        })?;
+        Ok(reply_rx)
+    }
+
+    /// One page of tree `id`'s entries after `cursor`, as an encoded tree,
+    /// and the cursor for the next page (empty after the last). Only for
+    /// servers advertising `treePages`.
+    pub fn get_tree_entries(
+        &self,
+        id: &[u8],
+        cursor: &str,
+        limit: u32,
+    ) -> Result<(Vec<u8>, String)> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetTreeEntries {
+            id: id.to_vec(),
+            cursor: cursor.to_string(),
+            limit,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getTreeEntries")
+    }
+
+    pub fn put_object(&self, kind: u16, data: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::PutObject {
+            kind,
+            data: data.to_vec(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "putObject")
+    }
+
+    /// Send a `putObject` without waiting; the reply arrives on the returned
+    /// receiver. Lets the backend keep several writes in flight.
+    pub fn begin_put_object(&self, kind: u16, data: Vec<u8>) -> Result<PendingPut> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::PutObject {
+            kind,
+            data,
+            reply: reply_tx,
+        })?;
+        Ok(reply_rx)
+    }
+
+    pub fn get_operation(&self, id: &[u8]) -> Result<Vec<u8>> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetOperation {
+            id: id.to_vec(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getOperation")
+    }
+
+    pub fn put_operation(&self, data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::PutOperation {
+            data: data.to_vec(),
+            tags: tags.clone(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "putOperation")
+    }
+
+    pub fn get_view(&self, id: &[u8]) -> Result<Vec<u8>> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetView {
+            id: id.to_vec(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getView")
+    }
+
+    pub fn put_view(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::PutView {
+            data: data.to_vec(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "putView")
+    }
+
+    /// Fetch view `id` as a delta against `base_id`, which the caller has.
+    pub fn get_view_delta(&self, id: &[u8], base_id: &[u8]) -> Result<ViewFetch> {
+        if !self.supports_capability(RepoCapability::ViewDeltas) {
+            bail!("server does not support view deltas");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetViewDelta {
+            id: id.to_vec(),
+            base_id: base_id.to_vec(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getViewDelta")
+    }
+
+    /// Store the view that `delta` builds from `base_id`; returns its id.
+    pub fn put_view_delta(&self, base_id: &[u8], delta: Vec<DeltaOp>) -> Result<Vec<u8>> {
+        if !self.supports_capability(RepoCapability::ViewDeltas) {
+            bail!("server does not support view deltas");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::PutViewDelta {
+            base_id: base_id.to_vec(),
+            delta,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "putViewDelta")
+    }
+
+    pub fn get_heads_state(&self) -> Result<HeadsState> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetHeads { reply: reply_tx })?;
+        wait(reply_rx, "getHeads")
+    }
+
+    /// Paths that pairs of workspaces have both changed since their
+    /// working copies diverged, as the server sees them now.
+    pub fn get_path_overlaps(&self) -> Result<Vec<PathOverlap>> {
+        if !self.supports_capability(RepoCapability::PathOverlaps) {
+            bail!("server does not support path overlap warnings");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetPathOverlaps { reply: reply_tx })?;
+        wait(reply_rx, "getHeads")
+    }
+
+    /// The server's heads version, without the heads themselves. Falls back
+    /// to a full `getHeads` on servers without `getHeadsVersion`.
+    pub fn heads_version(&self) -> Result<u64> {
+        if !self.supports_capability(RepoCapability::HeadsVersion) {
+            return Ok(self.get_heads_state()?.version);
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetHeadsVersion { reply: reply_tx })?;
+        wait(reply_rx, "getHeadsVersion")
+    }
+
+    /// Move the heads from `old_ids` to `new_id` if they are still at
+    /// `expected_version`. Every retry of one update resends the same
+    /// `idempotency_key` (empty for none).
+    pub fn update_op_heads(
+        &self,
+        old_ids: &[Vec<u8>],
+        new_id: &[u8],
+        expected_version: u64,
+        workspace_id: &str,
+        idempotency_key: &[u8],
+    ) -> Result<UpdateHeadsResult> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::UpdateOpHeads {
+            old_ids: old_ids.to_vec(),
+            new_id: new_id.to_vec(),
+            expected_version,
+            workspace_id: workspace_id.to_string(),
+            idempotency_key: idempotency_key.to_vec(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "updateOpHeads")
+    }
+
+    #[allow(dead_code)]
+    pub fn get_heads_snapshot(&self) -> Result<Option<HeadsSnapshot>> {
+        if !self.supports_capability(RepoCapability::HeadsSnapshot) {
+            return Ok(None);
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetHeadsSnapshot { reply: reply_tx })?;
+        wait(reply_rx, "getHeadsSnapshot")
+    }
+
+    pub fn get_related_copies(&self, copy_id: &[u8]) -> Result<Option<Vec<Vec<u8>>>> {
+        if !self.supports_capability(RepoCapability::CopyTracking) {
+            return Ok(None);
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetRelatedCopies {
+            copy_id: copy_id.to_vec(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getRelatedCopies")
+    }
+
+    pub fn resolve_op_prefix(&self, hex_prefix: &str) -> Result<(PrefixResult, Option<Vec<u8>>)> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::ResolveOpPrefix {
+            hex_prefix: hex_prefix.to_string(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "resolveOperationIdPrefix")
+    }
+
+    /// List tagged operations matching every filter. A filter with an empty
+    /// value matches any operation carrying that key.
+    pub fn get_operation_tags(
+        &self,
+        filters: &BTreeMap<String, String>,
+    ) -> Result<Vec<TaggedOperation>> {
+        if !self.supports_capability(RepoCapability::OperationTags) {
+            bail!("server does not support operation tags");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetOperationTags {
+            filters: filters.clone(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getOperationTags")
+    }
+
+    /// Commits whose description contains `query` and whose author matches
+    /// `author` (either may be empty), newest first, and whether more than
+    /// `limit` matched.
+    pub fn search_commits(
+        &self,
+        query: &str,
+        author: &str,
+        limit: u32,
+    ) -> Result<(Vec<CommitMatch>, bool)> {
+        if !self.supports_capability(RepoCapability::CommitSearch) {
+            bail!("server does not support commit search");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::SearchCommits {
+            query: query.to_string(),
+            author: author.to_string(),
+            limit,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "searchCommits")
+    }
+
+    /// Take or renew `workspace_id`'s lock on `change_id` for `ttl_seconds`
+    /// (0 for the server default). Returns whether the workspace holds it
+    /// now, and the lock that stands: another workspace's if it did not.
+    pub fn lock_change(
+        &self,
+        change_id: &[u8],
+        workspace_id: &str,
+        ttl_seconds: u32,
+        strict: bool,
+    ) -> Result<(bool, ChangeLock)> {
+        if !self.supports_capability(RepoCapability::ChangeLocks) {
+            bail!("server does not support change locks");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::LockChange {
+            change_id: change_id.to_vec(),
+            workspace_id: workspace_id.to_string(),
+            ttl_seconds,
+            strict,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "lockChange")
+    }
+
+    /// Release `workspace_id`'s lock on `change_id`, or anyone's with
+    /// `force`. Returns whether a lock was released and, when one was kept,
+    /// the workspace holding it.
+    pub fn unlock_change(
+        &self,
+        change_id: &[u8],
+        workspace_id: &str,
+        force: bool,
+    ) -> Result<(bool, String)> {
+        if !self.supports_capability(RepoCapability::ChangeLocks) {
+            bail!("server does not support change locks");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::UnlockChange {
+            change_id: change_id.to_vec(),
+            workspace_id: workspace_id.to_string(),
+            force,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "unlockChange")
+    }
+
+    /// Every change lock that has not expired.
+    pub fn get_change_locks(&self) -> Result<Vec<ChangeLock>> {
+        if !self.supports_capability(RepoCapability::ChangeLocks) {
+            bail!("server does not support change locks");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetChangeLocks { reply: reply_tx })?;
+        wait(reply_rx, "getChangeLocks")
+    }
+
+    /// Merge `commit_a` and `commit_b` on the server without writing
+    /// anything, listing at most `path_limit` conflicted paths (0 for the
+    /// server default).
+    pub fn preview_merge(
+        &self,
+        commit_a: &[u8],
+        commit_b: &[u8],
+        path_limit: u32,
+    ) -> Result<MergePreview> {
+        if !self.supports_capability(RepoCapability::MergePreview) {
+            bail!("server does not support merge previews");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::PreviewMerge {
+            commit_a: commit_a.to_vec(),
+            commit_b: commit_b.to_vec(),
+            path_limit,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "previewMerge")
+    }
+
+    /// One page of the commits reachable from `heads` (the server's visible
+    /// heads when empty) and not from `known`, after `cursor`, at most
+    /// `limit` of them (0 for the server default).
+    pub fn get_commit_graph(
+        &self,
+        heads: &[Vec<u8>],
+        known: &[Vec<u8>],
+        cursor: &str,
+        limit: u32,
+    ) -> Result<CommitGraphPage> {
+        if !self.supports_capability(RepoCapability::CommitGraph) {
+            bail!("server does not support bulk commit reads");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetCommitGraph {
+            heads: heads.to_vec(),
+            known: known.to_vec(),
+            cursor: cursor.to_string(),
+            limit,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getCommitGraph")
+    }
+
+    /// Hold the server's heads at their current version for up to
+    /// `lease_seconds` (0 for the server default).
+    pub fn begin_snapshot(&self, lease_seconds: u32) -> Result<SnapshotHold> {
+        if !self.supports_capability(RepoCapability::ConsistentSnapshot) {
+            bail!("server does not support consistent snapshots");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::BeginSnapshot {
+            lease_seconds,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "beginSnapshot")
+    }
+
+    /// Release a hold. Returns false if its lease had already run out.
+    pub fn end_snapshot(&self, snapshot_id: u64) -> Result<bool> {
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::EndSnapshot {
+            snapshot_id,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "endSnapshot")
+    }
+
+    /// Install another server's op heads and workspace attributions on a
+    /// server no workspace has used yet. Returns the new version.
+    pub fn import_heads(&self, state: &HeadsState) -> Result<u64> {
+        if !self.supports_capability(RepoCapability::Migration) {
+            bail!("server does not support migration");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::ImportHeads {
+            state: state.clone(),
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "importHeads")
+    }
+
+    /// Heads-history transitions after `after_version`, oldest first, plus
+    /// the oldest version the server still retains.
+    pub fn get_heads_history(&self, after_version: u64) -> Result<(Vec<HeadsTransition>, u64)> {
+        if !self.supports_capability(RepoCapability::HeadsHistory) {
+            bail!("server does not support heads history");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::GetHeadsHistory {
+            after_version,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "getHeadsHistory")
+    }
+
+    /// Point clients at `address` from now on, if the heads are still at
+    /// `expected_version`. Returns whether it was set and the current version.
+    pub fn set_redirect(&self, address: &str, expected_version: u64) -> Result<(bool, u64)> {
+        if !self.supports_capability(RepoCapability::Migration) {
+            bail!("server does not support migration");
+        }
+
+        let (reply_tx, reply_rx) = std::sync::mpsc::channel();
+        self.send(RpcMsg::SetRedirect {
+            address: address.to_string(),
+            expected_version,
+            reply: reply_tx,
+        })?;
+        wait(reply_rx, "setRedirect")
+    }
+}
+
+// ─── RPC event loop (runs on dedicated thread) ───────────────────────────────
+
+fn spawn_store_client(stream: RpcStream) -> store::Client {
+    let (reader, writer) = stream.into_split();
+    let network = twoparty::VatNetwork::new(
+        reader.compat(),
+        writer.compat_write(),
+        rpc_twoparty_capnp::Side::Client,
+        limits::reader_options(),
+    );
+    let mut rpc_system = RpcSystem::new(Box::new(network), None);
+    let client: store::Client = rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+    tokio::task::spawn_local(rpc_system);
+    client
+}
+
+/// Connect and validate, following the server's redirects (at most
+/// `redirect::MAX_HOPS` of them).
+pub async fn connect_store_client(
+    addr: &str,
+    required_capabilities: &[RepoCapability],
+) -> Result<(store::Client, RepoInfoResponse)> {
+    let (client, repo_info) = dial_following_redirects(addr).await?;
+    let served_by = repo_info.redirect.as_deref().unwrap_or(addr);
+    validate_repo_info(&repo_info, required_capabilities)
+        .map_err(|e| anyhow!("server {served_by} is incompatible: {e:#}"))?;
+
+    Ok((client, repo_info))
+}
+
+/// Fetch the server's repo info without judging it, following redirects, so
+/// `tandem init` can explain an incompatibility before creating anything.
+pub fn probe_repo_info(addr: &str) -> Result<RepoInfoResponse> {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let local = tokio::task::LocalSet::new();
+    local.block_on(&rt, async {
+        let (_client, repo_info) = dial_following_redirects(addr).await?;
+        Ok(repo_info)
+    })
+}
+
+async fn dial_following_redirects(addr: &str) -> Result<(store::Client, RepoInfoResponse)> {
+    let (mut client, mut repo_info) = dial_store_client(addr).await?;
+    let mut chain = vec![addr.to_string()];
+    while let Some(target) = repo_info.redirect.take() {
+        if chain.contains(&target) {
+            bail!("redirect loop: {} -> {target}", chain.join(" -> "));
+        }
+        if chain.len() > crate::redirect::MAX_HOPS {
+            bail!(
+                "too many redirects (limit {}): {} -> {target}",
+                crate::redirect::MAX_HOPS,
+                chain.join(" -> ")
+            );
+        }
+        let from = chain.last().cloned().unwrap_or_default();
+        tracing::info!(from = %from, to = %target, "server redirected us");
+        (client, repo_info) = dial_store_client(&target)
+            .await
+            .with_context(|| format!("{} -> {target}", chain.join(" -> ")))?;
+        chain.push(target);
+    }
+    if chain.len() > 1 {
+        repo_info.redirect = chain.pop();
+    }
+    Ok((client, repo_info))
+}
+
+async fn dial_store_client(addr: &str) -> Result<(store::Client, RepoInfoResponse)> {
+    let client = match rpc_record::replay_dir(addr) {
+        Some(dir) => rpc_record::replay(dir)?,
+        None => rpc_record::record_if_enabled(spawn_store_client(connect_stream(addr).await?))?,
+    };
+    let repo_info = do_get_repo_info(&client)
+        .await
+        .map_err(|e| anyhow!("failed to read repo compatibility info from {addr}: {e:#}"))?;
+    Ok((client, repo_info))
+}
+
+fn bench_injected_rtt_delay() -> std::time::Duration {
+    let Some(raw_value) = std::env::var(BENCH_INJECT_RTT_MS_ENV).ok() else {
+        return std::time::Duration::ZERO;
+    };
+
+    match raw_value.trim().parse::<u64>() {
+        Ok(0) => std::time::Duration::ZERO,
+        Ok(ms) => std::time::Duration::from_millis(ms),
+        Err(_) => {
+            tracing::warn!(
+                env = BENCH_INJECT_RTT_MS_ENV,
+                value = %raw_value,
+                "ignoring invalid bench RTT injection value"
+            );
+            std::time::Duration::ZERO
+        }
+    }
+}
+
+fn env_truthy(var: &str) -> bool {
+    std::env::var(var)
+        .map(|value| {
+            let normalized = value.trim().to_ascii_lowercase();
+            matches!(normalized.as_str(), "1" | "true" | "yes" | "on")
+        })
+        .unwrap_or(false)
+}
+
+pub(crate) fn rpc_max_inflight(configured: Option<usize>) -> usize {
+    if env_truthy(BENCH_DISABLE_RPC_INFLIGHT_ENV) {
+        return 1;
+    }
+
+    let parsed = std::env::var(RPC_MAX_INFLIGHT_ENV)
+        .ok()
+        .and_then(|value| value.trim().parse::<usize>().ok());
+
+    parsed
+        .or(configured)
+        .unwrap_or(DEFAULT_RPC_MAX_INFLIGHT)
+        .max(1)
+}
+
+async fn rpc_loop(
+    addr: String,
+    required_capabilities: Vec<RepoCapability>,
+    options: ClientOptions,
+    mut rx: tokio::sync::mpsc::UnboundedReceiver<RpcMsg>,
+    ready_tx: std::sync::mpsc::Sender<Result<RepoInfoResponse>>,
+) {
+    let connect_result = connect_store_client(&addr, &required_capabilities).await;
+
+    let (client, _repo_info) = match connect_result {
+        Ok(v) => {
+            let _ = ready_tx.send(Ok(v.1.clone()));
+            v
+        }
+        Err(e) => {
+            let _ = ready_tx.send(Err(e));
+            return;
+        }
+    };
+
+    let injected_rtt = bench_injected_rtt_delay();
+    let max_inflight = rpc_max_inflight(options.max_inflight);
+    let timing = options.timing;
+
+    if max_inflight <= 1 {
+        while let Some(msg) = rx.recv().await {
+            if !injected_rtt.is_zero() {
+                tokio::time::sleep(injected_rtt).await;
+            }
+            dispatch(&client, msg, timing).await;
+        }
+        return;
+    }
+
+    let permits = Arc::new(tokio::sync::Semaphore::new(max_inflight));
+    while let Some(msg) = rx.recv().await {
+        let Ok(permit) = permits.clone().acquire_owned().await else {
+            break;
+        };
+
+        let client = client.clone();
+        tokio::task::spawn_local(async move {
+            if !injected_rtt.is_zero() {
+                tokio::time::sleep(injected_rtt).await;
+            }
+            dispatch(&client, msg, timing).await;
+            drop(permit);
+        });
+    }
+}
+
+async fn dispatch(client: &store::Client, msg: RpcMsg, timing: bool) {
+    let method = msg.method();
+    let started = std::time::Instant::now();
+    let outcome = handle_msg(client, msg).await;
+    let elapsed = started.elapsed();
+    if timing {
+        eprintln!("tandem: {method} {:.1}ms", elapsed.as_secs_f64() * 1000.0);
+    }
+    journal::record(method, elapsed, outcome);
+}
+
+/// Hand `result` to the waiting store and return what the journal notes
+/// about it.
+fn settle<T>(reply: Reply<T>, result: Result<T>) -> journal::Outcome {
+    settle_noting(reply, result, |_| journal::Outcome::default())
+}
+
+fn settle_noting<T>(
+    reply: Reply<T>,
+    result: Result<T>,
+    note: impl FnOnce(&T) -> journal::Outcome,
+) -> journal::Outcome {
+    let outcome = match &result {
+        Ok(value) => note(value),
+        Err(err) => journal::Outcome {
+            error: Some(format!("{err:#}")),
+            ..Default::default()
+        },
+    };
+    let _ = reply.send(result);
+    outcome
+}
+
+async fn handle_msg(client: &store::Client, msg: RpcMsg) -> journal::Outcome {
+    match msg {
+        RpcMsg::GetObject { kind, id, reply } => {
+            settle(reply, do_get_object(client, kind, &id).await)
+        }
+        RpcMsg::GetTreeEntries {
+            id,
+            cursor,
+            limit,
+            reply,
+        } => settle(
+            reply,
+            do_get_tree_entries(client, &id, &cursor, limit).await,
+        ),
+        RpcMsg::SearchCommits {
+            query,
+            author,
+            limit,
+            reply,
+        } => settle(
+            reply,
+            do_search_commits(client, &query, &author, limit).await,
+        ),
+        RpcMsg::LockChange {
+            change_id,
+            workspace_id,
+            ttl_seconds,
+            strict,
+            reply,
+        } => settle(
+            reply,
+            do_lock_change(client, &change_id, &workspace_id, ttl_seconds, strict).await,
+        ),
+        RpcMsg::UnlockChange {
+            change_id,
+            workspace_id,
+            force,
+            reply,
+        } => settle(
+            reply,
+            do_unlock_change(client, &change_id, &workspace_id, force).await,
+        ),
+        RpcMsg::GetChangeLocks { reply } => settle(reply, do_get_change_locks(client).await),
+        RpcMsg::PreviewMerge {
+            commit_a,
+            commit_b,
+            path_limit,
+            reply,
+        } => settle(
+            reply,
+            do_preview_merge(client, &commit_a, &commit_b, path_limit).await,
+        ),
+        RpcMsg::GetCommitGraph {
+            heads,
+            known,
+            cursor,
+            limit,
+            reply,
+        } => settle(
+            reply,
+            do_get_commit_graph(client, &heads, &known, &cursor, limit).await,
+        ),
+        RpcMsg::PutObject { kind, data, reply } => {
+            settle(reply, do_put_object(client, kind, &data).await)
+        }
+        RpcMsg::GetOperation { id, reply } => settle(reply, do_get_operation(client, &id).await),
+        RpcMsg::PutOperation { data, tags, reply } => {
+            settle(reply, do_put_operation(client, &data, &tags).await)
+        }
+        RpcMsg::GetView { id, reply } => settle(reply, do_get_view(client, &id).await),
+        RpcMsg::PutView { data, reply } => settle(reply, do_put_view(client, &data).await),
+        RpcMsg::GetHeads { reply } => settle_noting(reply, do_get_heads(client).await, |heads| {
+            journal::Outcome::version(heads.version)
+        }),
+        RpcMsg::GetPathOverlaps { reply } => settle(reply, do_get_path_overlaps(client).await),
+        RpcMsg::GetHeadsVersion { reply } => {
+            settle_noting(reply, do_get_heads_version(client).await, |version| {
+                journal::Outcome::version(*version)
+            })
+        }
+        RpcMsg::UpdateOpHeads {
+            old_ids,
+            new_id,
+            expected_version,
+            workspace_id,
+            idempotency_key,
+            reply,
+        } => settle_noting(
+            reply,
+            do_update_op_heads(
+                client,
+                &old_ids,
+                &new_id,
+                expected_version,
+                &workspace_id,
+                &idempotency_key,
+            )
+            .await,
+            |result| journal::Outcome {
+                version: Some(result.version),
+                cas_lost: !result.ok,
+                ..Default::default()
+            },
+        ),
+        RpcMsg::GetHeadsSnapshot { reply } => settle_noting(
+            reply,
+            do_get_heads_snapshot(client).await.map(Some),
+            |snapshot| {
+                snapshot
+                    .as_ref()
+                    .map_or_else(journal::Outcome::default, |snapshot| {
+                        journal::Outcome::version(snapshot.version)
+                    })
+            },
+        ),
+        RpcMsg::GetRelatedCopies { copy_id, reply } => settle(
+            reply,
+            do_get_related_copies(client, &copy_id).await.map(Some),
+        ),
+        RpcMsg::ResolveOpPrefix { hex_prefix, reply } => {
+            settle(reply, do_resolve_op_prefix(client, &hex_prefix).await)
+        }
+        RpcMsg::GetOperationTags { filters, reply } => {
+            settle(reply, do_get_operation_tags(client, &filters).await)
+        }
+        RpcMsg::BeginSnapshot {
+            lease_seconds,
+            reply,
+        } => settle(reply, do_begin_snapshot(client, lease_seconds).await),
+        RpcMsg::EndSnapshot { snapshot_id, reply } => {
+            settle(reply, do_end_snapshot(client, snapshot_id).await)
+        }
+        RpcMsg::ImportHeads { state, reply } => {
+            settle(reply, do_import_heads(client, &state).await)
+        }
+        RpcMsg::SetRedirect {
+            address,
+            expected_version,
+            reply,
+        } => settle(
+            reply,
+            do_set_redirect(client, &address, expected_version).await,
+        ),
+        RpcMsg::GetViewDelta { id, base_id, reply } => {
+            settle(reply, do_get_view_delta(client, &id, &base_id).await)
+        }
+        RpcMsg::PutViewDelta {
+            base_id,
+            delta,
+            reply,
+        } => settle(reply, do_put_view_delta(client, &base_id, &delta).await),
+        RpcMsg::GetHeadsHistory {
+            after_version,
+            reply,
+        } => settle(reply, do_get_heads_history(client, after_version).await),
+    }
+}
+
+/// The error for a call whose reply never came back because the RPC thread
+/// went away.
+pub(crate) const REPLY_DROPPED: &str = "RPC reply dropped";
+
+/// Block on an RPC's reply; profiles show the time as `rpc wait;<method>`.
+pub(crate) fn wait<T>(
+    reply_rx: std::sync::mpsc::Receiver<Result<T>>,
+    method: &'static str,
+) -> Result<T> {
+    let _phase = profile::enter(&["rpc wait", method]);
+    reply_rx.recv().map_err(|_| anyhow!(REPLY_DROPPED))?
+}
+
+// ─── Individual RPC handlers ──────────────────────────────────────────────────
+
+async fn do_get_repo_info(client: &store::Client) -> Result<RepoInfoResponse> {
+    let request = client.get_repo_info_request();
+    let response = request.send().promise.await?;
+    Ok(RepoInfoResponse::read(response.get()?.get_info()?)?)
+}
+
+/// Errors from mutating calls. Maintenance and shutdown notices are passed
+/// through as-is so users see why the write was refused rather than a
+/// generic RPC failure.
+fn write_error(err: capnp::Error) -> anyhow::Error {
+    match maintenance::notice_from_error(&err.extra)
+        .or_else(|| drain::notice_from_error(&err.extra))
+    {
+        Some(notice) => anyhow!("{notice}"),
+        None => read_error(err),
+    }
+}
+
+/// Errors from reads. A message over the size limit on either end is turned
+/// into the structured too-large error instead of capnp's internal wording,
+/// and a call the server's watchdog dropped reads as a timeout.
+fn read_error(err: capnp::Error) -> anyhow::Error {
+    match limits::message_from_error(&err.extra)
+        .or_else(|| watchdog::message_from_error(&err.extra).map(str::to_string))
+    {
+        Some(message) => anyhow!("{message}"),
+        None => err.into(),
+    }
+}
+
+fn kind_name(kind: u16) -> &'static str {
+    match kind {
+        0 => "commit",
+        1 => "tree",
+        2 => "file",
+        3 => "symlink",
+        4 => "copy",
+        _ => "unknown",
+    }
+}
+
+pub async fn do_get_object(client: &store::Client, kind: u16, id: &[u8]) -> Result<Vec<u8>> {
+    let mut request = client.get_object_request();
+    {
+        let mut params = request.get();
+        params.set_kind(capnp_kind(kind)?);
+        params.set_id(id);
+    }
+    let response = request.send().promise.await.map_err(read_error)?;
+    let data = response.get()?.get_data()?;
+    Ok(data.to_vec())
+}
+
+async fn do_get_tree_entries(
+    client: &store::Client,
+    id: &[u8],
+    cursor: &str,
+    limit: u32,
+) -> Result<(Vec<u8>, String)> {
+    let mut request = client.get_tree_entries_request();
+    {
+        let mut params = request.get();
+        params.set_tree_id(id);
+        params.set_cursor(cursor);
+        params.set_limit(limit);
+    }
+    let response = request.send().promise.await.map_err(read_error)?;
+    let results = response.get()?;
+    Ok((
+        results.get_entries()?.to_vec(),
+        results.get_next_cursor()?.to_string()?,
+    ))
+}
+
+async fn do_put_object(
+    client: &store::Client,
+    kind: u16,
+    data: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>)> {
+    limits::check(&format!("{} object", kind_name(kind)), data.len())?;
+    let mut request = client.put_object_request();
+    {
+        let mut params = request.get();
+        params.set_kind(capnp_kind(kind)?);
+        params.set_data(data);
+    }
+    let response = request.send().promise.await.map_err(write_error)?;
+    let reader = response.get()?;
+    let id = reader.get_id()?.to_vec();
+    let normalized = reader.get_normalized_data()?.to_vec();
+    Ok((id, normalized))
+}
+
+pub async fn do_get_operation(client: &store::Client, id: &[u8]) -> Result<Vec<u8>> {
+    let mut request = client.get_operation_request();
+    request.get().set_id(id);
+    let response = request.send().promise.await.map_err(read_error)?;
+    Ok(response.get()?.get_data()?.to_vec())
+}
+
+async fn do_put_operation(
+    client: &store::Client,
+    data: &[u8],
+    tags: &BTreeMap<String, String>,
+) -> Result<Vec<u8>> {
+    limits::check("operation", data.len())?;
+    let mut request = client.put_operation_request();
+    {
+        let mut params = request.get();
+        params.set_data(data);
+        if !tags.is_empty() {
+            let mut tags_builder = params.init_tags(tags.len() as u32);
+            for (i, (key, value)) in tags.iter().enumerate() {
+                let mut entry = tags_builder.reborrow().get(i as u32);
+                entry.set_key(key);
+                entry.set_value(value);
+            }
+        }
+    }
+    let response = request.send().promise.await.map_err(write_error)?;
+    Ok(response.get()?.get_id()?.to_vec())
+}
+
+pub async fn do_get_view(client: &store::Client, id: &[u8]) -> Result<Vec<u8>> {
+    let mut request = client.get_view_request();
+    request.get().set_id(id);
+    let response = request.send().promise.await.map_err(read_error)?;
+    Ok(response.get()?.get_data()?.to_vec())
+}
+
+async fn do_get_view_delta(client: &store::Client, id: &[u8], base_id: &[u8]) -> Result<ViewFetch> {
+    let mut request = client.get_view_delta_request();
+    {
+        let mut params = request.get();
+        params.set_id(id);
+        params.set_base_id(base_id);
+    }
+    let response = request.send().promise.await.map_err(read_error)?;
+    let results = response.get()?;
+    if !results.get_is_delta() {
+        return Ok(ViewFetch::Full(results.get_data()?.to_vec()));
+    }
+    Ok(ViewFetch::Delta(read_delta_ops(results.get_delta()?)?))
+}
+
+async fn do_put_view_delta(
+    client: &store::Client,
+    base_id: &[u8],
+    delta: &[DeltaOp],
+) -> Result<Vec<u8>> {
+    let mut request = client.put_view_delta_request();
+    {
+        let mut params = request.get();
+        params.set_base_id(base_id);
+        write_delta_ops(params.init_delta(delta.len() as u32), delta);
+    }
+    let response = request.send().promise.await.map_err(write_error)?;
+    Ok(response.get()?.get_id()?.to_vec())
+}
+
+pub fn read_delta_ops(
+    reader: capnp::struct_list::Reader<'_, crate::tandem_capnp::view_delta_op::Owned>,
+) -> Result<Vec<DeltaOp>> {
+    let mut ops = Vec::with_capacity(reader.len() as usize);
+    for op in reader.iter() {
+        ops.push(DeltaOp {
+            copy_start: op.get_copy_start(),
+            copy_count: op.get_copy_count(),
+            insert: op.get_insert()?.to_vec(),
+        });
+    }
+    Ok(ops)
+}
+
+pub fn write_delta_ops(
+    mut builder: capnp::struct_list::Builder<'_, crate::tandem_capnp::view_delta_op::Owned>,
+    ops: &[DeltaOp],
+) {
+    for (i, op) in ops.iter().enumerate() {
+        let mut entry = builder.reborrow().get(i as u32);
+        entry.set_copy_start(op.copy_start);
+        entry.set_copy_count(op.copy_count);
+        entry.set_insert(&op.insert);
+    }
+}
+
+async fn do_put_view(client: &store::Client, data: &[u8]) -> Result<Vec<u8>> {
+    limits::check("view", data.len())?;
+    let mut request = client.put_view_request();
+    request.get().set_data(data);
+    let response = request.send().promise.await.map_err(write_error)?;
+    Ok(response.get()?.get_id()?.to_vec())
+}
+
+pub async fn do_get_heads(client: &store::Client) -> Result<HeadsState> {
+    let request = client.get_heads_request();
+    let response = request.send().promise.await?;
+    Ok(HeadsState::read(response.get()?)?)
+}
+
+async fn do_get_path_overlaps(client: &store::Client) -> Result<Vec<PathOverlap>> {
+    let mut request = client.get_heads_request();
+    request.get().set_with_overlaps(true);
+    let response = request.send().promise.await?;
+    let overlaps_reader = response.get()?.get_overlaps()?;
+    let mut overlaps = Vec::with_capacity(overlaps_reader.len() as usize);
+    for overlap in overlaps_reader.iter() {
+        overlaps.push(PathOverlap::read(overlap)?);
+    }
+    Ok(overlaps)
+}
+
+async fn do_get_heads_version(client: &store::Client) -> Result<u64> {
+    let request = client.get_heads_version_request();
+    let response = request.send().promise.await?;
+    Ok(response.get()?.get_version())
+}
+
+async fn do_get_heads_snapshot(client: &store::Client) -> Result<HeadsSnapshot> {
+    let request = client.get_heads_snapshot_request();
+    let response = request.send().promise.await?;
+    let reader = response.get()?;
+
+    let heads_reader = reader.get_heads()?;
+    let mut heads = Vec::with_capacity(heads_reader.len() as usize);
+    for i in 0..heads_reader.len() {
+        heads.push(heads_reader.get(i)?.to_vec());
+    }
+
+    Ok(HeadsSnapshot {
+        heads,
+        version: reader.get_version(),
+    })
+}
+
+async fn do_get_related_copies(client: &store::Client, copy_id: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut request = client.get_related_copies_request();
+    request.get().set_copy_id(copy_id);
+    let response = request.send().promise.await?;
+    let reader = response.get()?;
+    let copies_reader = reader.get_copies()?;
+
+    let mut copies = Vec::with_capacity(copies_reader.len() as usize);
+    for i in 0..copies_reader.len() {
+        copies.push(copies_reader.get(i)?.to_vec());
+    }
+
+    Ok(copies)
+}
+
+async fn do_update_op_heads(
+    client: &store::Client,
+    old_ids: &[Vec<u8>],
+    new_id: &[u8],
+    expected_version: u64,
+    workspace_id: &str,
+    idempotency_key: &[u8],
+) -> Result<UpdateHeadsResult> {
+    let mut request = client.update_op_heads_request();
+    {
+        let mut params = request.get();
+        let mut old_list = params.reborrow().init_old_ids(old_ids.len() as u32);
+        for (i, oid) in old_ids.iter().enumerate() {
+            old_list.set(i as u32, oid);
+        }
+        params.set_new_id(new_id);
+        params.set_expected_version(expected_version);
+        params.set_workspace_id(workspace_id);
+        params.set_idempotency_key(idempotency_key);
+    }
+    let response = request.send().promise.await.map_err(write_error)?;
+    let reader = response.get()?;
+    let ok = reader.get_ok();
+    let version = reader.get_version();
+    let heads_reader = reader.get_heads()?;
+    let mut heads = Vec::with_capacity(heads_reader.len() as usize);
+    for i in 0..heads_reader.len() {
+        heads.push(heads_reader.get(i)?.to_vec());
+    }
+    Ok(UpdateHeadsResult {
+        ok,
+        heads,
+        version,
+        min_backoff_ms: reader.get_min_backoff_ms(),
+    })
+}
+
+async fn do_resolve_op_prefix(
+    client: &store::Client,
+    hex_prefix: &str,
+) -> Result<(PrefixResult, Option<Vec<u8>>)> {
+    let mut request = client.resolve_operation_id_prefix_request();
+    request.get().set_hex_prefix(hex_prefix);
+    let response = request.send().promise.await?;
+    let reader = response.get()?;
+    let resolution = reader.get_resolution()?;
+    let result = match resolution {
+        crate::tandem_capnp::PrefixResolution::NoMatch => PrefixResult::NoMatch,
+        crate::tandem_capnp::PrefixResolution::SingleMatch => PrefixResult::SingleMatch,
+        crate::tandem_capnp::PrefixResolution::Ambiguous => PrefixResult::Ambiguous,
+    };
+    let matched = if result == PrefixResult::SingleMatch {
+        let m = reader.get_match()?;
+        if m.is_empty() {
+            None
+        } else {
+            Some(m.to_vec())
+        }
+    } else {
+        None
+    };
+    Ok((result, matched))
+}
+
+pub async fn do_get_operation_tags(
+    client: &store::Client,
+    filters: &BTreeMap<String, String>,
+) -> Result<Vec<TaggedOperation>> {
+    let mut request = client.get_operation_tags_request();
+    {
+        let mut filters_builder = request.get().init_filters(filters.len() as u32);
+        for (i, (key, value)) in filters.iter().enumerate() {
+            let mut entry = filters_builder.reborrow().get(i as u32);
+            entry.set_key(key);
+            entry.set_value(value);
+        }
+    }
+    let response = request.send().promise.await?;
+    let operations_reader = response.get()?.get_operations()?;
+
+    let mut operations = Vec::with_capacity(operations_reader.len() as usize);
+    for i in 0..operations_reader.len() {
+        let entry = operations_reader.get(i);
+        let tags_reader = entry.get_tags()?;
+        let mut tags = BTreeMap::new();
+        for j in 0..tags_reader.len() {
+            let tag = tags_reader.get(j);
+            tags.insert(tag.get_key()?.to_string()?, tag.get_value()?.to_string()?);
+        }
+        operations.push(TaggedOperation {
+            id: entry.get_id()?.to_vec(),
+            tags,
+        });
+    }
+    Ok(operations)
+}
+
+async fn do_search_commits(
+    client: &store::Client,
+    query: &str,
+    author: &str,
+    limit: u32,
+) -> Result<(Vec<CommitMatch>, bool)> {
+    let mut request = client.search_commits_request();
+    {
+        let mut params = request.get();
+        params.set_query(query);
+        params.set_author(author);
+        params.set_limit(limit);
+    }
+    let response = request.send().promise.await?;
+    let results = response.get()?;
+    let commits_reader = results.get_commits()?;
+    let mut commits = Vec::with_capacity(commits_reader.len() as usize);
+    for entry in commits_reader.iter() {
+        commits.push(CommitMatch {
+            commit_id: entry.get_commit_id()?.to_vec(),
+            change_id: entry.get_change_id()?.to_vec(),
+            description: entry.get_description()?.to_string()?,
+            author_name: entry.get_author_name()?.to_string()?,
+            author_email: entry.get_author_email()?.to_string()?,
+            author_timestamp_ms: entry.get_author_timestamp(),
+        });
+    }
+    Ok((commits, results.get_truncated()))
+}
+
+fn read_change_lock(lock: crate::tandem_capnp::change_lock::Reader<'_>) -> Result<ChangeLock> {
+    Ok(ChangeLock {
+        change_id: lock.get_change_id()?.to_vec(),
+        workspace_id: lock.get_workspace_id()?.to_string()?,
+        expires_at_millis: lock.get_expires_at(),
+        strict: lock.get_strict(),
+    })
+}
+
+async fn do_lock_change(
+    client: &store::Client,
+    change_id: &[u8],
+    workspace_id: &str,
+    ttl_seconds: u32,
+    strict: bool,
+) -> Result<(bool, ChangeLock)> {
+    let mut request = client.lock_change_request();
+    {
+        let mut params = request.get();
+        params.set_change_id(change_id);
+        params.set_workspace_id(workspace_id);
+        params.set_ttl_seconds(ttl_seconds);
+        params.set_strict(strict);
+    }
+    let response = request.send().promise.await?;
+    let results = response.get()?;
+    Ok((results.get_ok(), read_change_lock(results.get_lock()?)?))
+}
+
+async fn do_unlock_change(
+    client: &store::Client,
+    change_id: &[u8],
+    workspace_id: &str,
+    force: bool,
+) -> Result<(bool, String)> {
+    let mut request = client.unlock_change_request();
+    {
+        let mut params = request.get();
+        params.set_change_id(change_id);
+        params.set_workspace_id(workspace_id);
+        params.set_force(force);
+    }
+    let response = request.send().promise.await?;
+    let results = response.get()?;
+    Ok((results.get_released(), results.get_holder()?.to_string()?))
+}
+
+async fn do_get_change_locks(client: &store::Client) -> Result<Vec<ChangeLock>> {
+    let request = client.get_change_locks_request();
+    let response = request.send().promise.await?;
+    let locks_reader = response.get()?.get_locks()?;
+    let mut locks = Vec::with_capacity(locks_reader.len() as usize);
+    for lock in locks_reader.iter() {
+        locks.push(read_change_lock(lock)?);
+    }
+    Ok(locks)
+}
+
+async fn do_preview_merge(
+    client: &store::Client,
+    commit_a: &[u8],
+    commit_b: &[u8],
+    path_limit: u32,
+) -> Result<MergePreview> {
+    let mut request = client.preview_merge_request();
+    {
+        let mut params = request.get();
+        params.set_commit_a(commit_a);
+        params.set_commit_b(commit_b);
+        params.set_path_limit(path_limit);
+    }
+    let response = request.send().promise.await?;
+    let results = response.get()?;
+    let paths_reader = results.get_conflicted_paths()?;
+    let mut conflicted_paths = Vec::with_capacity(paths_reader.len() as usize);
+    for path in paths_reader.iter() {
+        conflicted_paths.push(path?.to_string()?);
+    }
+    Ok(MergePreview {
+        clean: results.get_clean(),
+        conflicted_paths,
+        paths_truncated: results.get_paths_truncated(),
+    })
+}
+
+async fn do_get_commit_graph(
+    client: &store::Client,
+    heads: &[Vec<u8>],
+    known: &[Vec<u8>],
+    cursor: &str,
+    limit: u32,
+) -> Result<CommitGraphPage> {
+    let mut request = client.get_commit_graph_request();
+    {
+        let mut params = request.get();
+        {
+            let mut list = params.reborrow().init_heads(heads.len() as u32);
+            for (i, id) in heads.iter().enumerate() {
+                list.set(i as u32, id);
+            }
+        }
+        {
+            let mut list = params.reborrow().init_known(known.len() as u32);
+            for (i, id) in known.iter().enumerate() {
+                list.set(i as u32, id);
+            }
+        }
+        params.set_cursor(cursor);
+        params.set_limit(limit);
+    }
+    let response = request.send().promise.await.map_err(read_error)?;
+    let results = response.get()?;
+    let commits_reader = results.get_commits()?;
+    let mut commits = Vec::with_capacity(commits_reader.len() as usize);
+    for entry in commits_reader.iter() {
+        commits.push((entry.get_id()?.to_vec(), entry.get_data()?.to_vec()));
+    }
+    let heads = results
+        .get_heads()?
+        .iter()
+        .map(|id| id.map(<[u8]>::to_vec))
+        .collect::<capnp::Result<_>>()?;
+    Ok(CommitGraphPage {
+        commits,
+        heads,
+        next_cursor: results.get_next_cursor()?.to_string()?,
+    })
+}
+
+/// Transitions after `after_version`, oldest first, plus the oldest version
+/// the server still retains.
+pub async fn do_get_heads_history(
+    client: &store::Client,
+    after_version: u64,
+) -> Result<(Vec<HeadsTransition>, u64)> {
+    let mut request = client.get_heads_history_request();
+    request.get().set_after_version(after_version);
+    let response = request.send().promise.await?;
+    let results = response.get()?;
+    let transitions_reader = results.get_transitions()?;
+
+    let mut transitions = Vec::with_capacity(transitions_reader.len() as usize);
+    for entry in transitions_reader.iter() {
+        let heads_reader = entry.get_heads()?;
+        let mut heads = Vec::with_capacity(heads_reader.len() as usize);
+        for i in 0..heads_reader.len() {
+            heads.push(heads_reader.get(i)?.to_vec());
+        }
+        let workspace_id = entry.get_workspace_id()?.to_string()?;
+        transitions.push(HeadsTransition {
+            version: entry.get_version(),
+            heads,
+            workspace_id: (!workspace_id.is_empty()).then_some(workspace_id),
+            timestamp_millis: entry.get_timestamp_millis(),
+        });
+    }
+    Ok((transitions, results.get_oldest_version()))
+}
+
+async fn do_begin_snapshot(client: &store::Client, lease_seconds: u32) -> Result<SnapshotHold> {
+    let mut request = client.begin_snapshot_request();
+    request.get().set_lease_seconds(lease_seconds);
+    let response = request.send().promise.await?;
+    let results = response.get()?;
+    Ok(SnapshotHold {
+        snapshot_id: results.get_snapshot_id(),
+        version: results.get_version(),
+        lease_seconds: results.get_lease_seconds(),
+    })
+}
+
+async fn do_end_snapshot(client: &store::Client, snapshot_id: u64) -> Result<bool> {
+    let mut request = client.end_snapshot_request();
+    request.get().set_snapshot_id(snapshot_id);
+    let response = request.send().promise.await?;
+    Ok(response.get()?.get_released())
+}
+
+async fn do_import_heads(client: &store::Client, state: &HeadsState) -> Result<u64> {
+    let mut request = client.import_heads_request();
+    {
+        let mut params = request.get();
+        let mut heads = params.reborrow().init_heads(state.heads.len() as u32);
+        for (i, head) in state.heads.iter().enumerate() {
+            heads.set(i as u32, head);
+        }
+        params.set_version(state.version);
+        let mut workspace_heads = params.init_workspace_heads(state.workspace_heads.len() as u32);
+        for (i, (workspace_id, op_id)) in state.workspace_heads.iter().enumerate() {
+            let mut entry = workspace_heads.reborrow().get(i as u32);
+            entry.set_workspace_id(workspace_id);
+            entry.set_commit_id(op_id);
+        }
+    }
+    let response = request.send().promise.await.map_err(write_error)?;
+    Ok(response.get()?.get_version())
+}
+
+async fn do_set_redirect(
+    client: &store::Client,
+    address: &str,
+    expected_version: u64,
+) -> Result<(bool, u64)> {
+    let mut request = client.set_redirect_request();
+    {
+        let mut params = request.get();
+        params.set_address(address);
+        params.set_expected_version(expected_version);
+    }
+    let response = request.send().promise.await.map_err(write_error)?;
+    let results = response.get()?;
+    Ok((results.get_ok(), results.get_version()))
+}
+
+// ─── Helpers ──────────────────────────────────────────────────────────────────
+
+/// Capabilities every workspace relies on; `tandem init` checks them up front.
+pub const WORKSPACE_CAPABILITIES: &[RepoCapability] = &[RepoCapability::WatchHeads];
+
+/// Reasons a client cannot work with the server behind `info`, in plain
+/// language with the offending field named at the end. Covers what a user can
+/// act on (versions, a different program on the port, missing features);
+/// malformed ids are left to [`validate_repo_info`].
+pub fn compatibility_problems(
+    info: &RepoInfoResponse,
+    required_capabilities: &[RepoCapability],
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    if (info.protocol_major, info.protocol_minor) != (PROTOCOL_MAJOR, PROTOCOL_MINOR) {
+        let field = if info.protocol_major != PROTOCOL_MAJOR {
+            "protocol_major"
+        } else {
+            "protocol_minor"
+        };
+        problems.push(format!(
+            "the server speaks tandem protocol {}.{} but this tandem speaks {PROTOCOL_MAJOR}.{PROTOCOL_MINOR}; \
+             install the same tandem version on both sides ({field})",
+            info.protocol_major, info.protocol_minor
+        ));
+    }
+    if info.backend_name != EXPECTED_BACKEND_NAME {
+        problems.push(format!(
+            "the server stores objects with a {:?} backend instead of tandem's; \
+             the address may belong to a different program (backend_name)",
+            info.backend_name
+        ));
+    }
+    if info.op_store_name != EXPECTED_OP_STORE_NAME {
+        problems.push(format!(
+            "the server records operations with a {:?} store instead of tandem's; \
+             the address may belong to a different program (op_store_name)",
+            info.op_store_name
+        ));
+    }
+    match HashAlgorithm::parse(&info.commit_hash) {
+        Some(HashAlgorithm::Sha1 | HashAlgorithm::Sha256) => {}
+        _ => problems.push(format!(
+            "the server's repo hashes commits with {:?}, which this tandem does not know; \
+             upgrade tandem on this machine (commit_hash)",
+            info.commit_hash
+        )),
+    }
+    if HashAlgorithm::parse(&info.operation_hash) != Some(HashAlgorithm::Blake2b512) {
+        problems.push(format!(
+            "the server hashes operations with {:?} but this tandem only knows {}; \
+             install the same tandem version on both sides (operation_hash)",
+            info.operation_hash,
+            HashAlgorithm::Blake2b512.as_str()
+        ));
+    }
+    if let Some(problem) = op_schema_problem(info) {
+        problems.push(problem);
+    }
+    for capability in required_capabilities {
+        if !info.capabilities.contains(capability) {
+            problems.push(format!(
+                "the server does not offer {}, which workspaces need; \
+                 upgrade tandem on the server (missing capability {})",
+                capability.describe(),
+                capability.as_str()
+            ));
+        }
+    }
+    problems
+}
+
+fn op_schema_problem(info: &RepoInfoResponse) -> Option<String> {
+    let jj_version = info.jj_version.as_deref().unwrap_or("(unknown)");
+    jj_compat::op_schema_problem(jj_version, &info.op_schema)
+}
+
+pub fn validate_repo_info(
+    info: &RepoInfoResponse,
+    required_capabilities: &[RepoCapability],
+) -> Result<()> {
+    if info.protocol_major != PROTOCOL_MAJOR {
+        bail!(
+            "repo compatibility mismatch: protocol_major expected {PROTOCOL_MAJOR} but server advertised {}",
+            info.protocol_major
+        );
+    }
+
+    if info.protocol_minor != PROTOCOL_MINOR {
+        bail!(
+            "repo compatibility mismatch: protocol_minor expected {PROTOCOL_MINOR} but server advertised {}",
+            info.protocol_minor
+        );
+    }
+
+    if info.backend_name != EXPECTED_BACKEND_NAME {
+        bail!(
+            "repo compatibility mismatch: backend_name expected {:?} but server advertised {:?}",
+            EXPECTED_BACKEND_NAME,
+            info.backend_name
+        );
+    }
+
+    if info.op_store_name != EXPECTED_OP_STORE_NAME {
+        bail!(
+            "repo compatibility mismatch: op_store_name expected {:?} but server advertised {:?}",
+            EXPECTED_OP_STORE_NAME,
+            info.op_store_name
+        );
+    }
+
+    if info.commit_id_length == 0 {
+        bail!("repo compatibility mismatch: commit_id_length must be > 0");
+    }
+
+    if info.change_id_length == 0 {
+        bail!("repo compatibility mismatch: change_id_length must be > 0");
+    }
+
+    let commit_hash = match HashAlgorithm::parse(&info.commit_hash) {
+        Some(algorithm @ (HashAlgorithm::Sha1 | HashAlgorithm::Sha256)) => algorithm,
+        _ => bail!(
+            "repo compatibility mismatch: commit_hash {:?} is not a git object format",
+            info.commit_hash
+        ),
+    };
+
+    if commit_hash.digest_len() != info.commit_id_length {
+        bail!(
+            "repo compatibility mismatch: commit_id_length {} does not match {} ids of {} bytes",
+            info.commit_id_length,
+            commit_hash.as_str(),
+            commit_hash.digest_len()
+        );
+    }
+
+    if info.operation_hash != HashAlgorithm::Blake2b512.as_str() {
+        bail!(
+            "repo compatibility mismatch: operation_hash expected {:?} but server advertised {:?}",
+            HashAlgorithm::Blake2b512.as_str(),
+            info.operation_hash
+        );
+    }
+
+    if info.root_commit_id.len() != info.commit_id_length {
+        bail!(
+            "repo compatibility mismatch: root_commit_id length {} does not match commit_id_length {}",
+            info.root_commit_id.len(),
+            info.commit_id_length
+        );
+    }
+
+    if info.root_change_id.len() != info.change_id_length {
+        bail!(
+            "repo compatibility mismatch: root_change_id length {} does not match change_id_length {}",
+            info.root_change_id.len(),
+            info.change_id_length
+        );
+    }
+
+    if info.empty_tree_id.len() != info.commit_id_length {
+        bail!(
+            "repo compatibility mismatch: empty_tree_id length {} does not match commit_id_length {}",
+            info.empty_tree_id.len(),
+            info.commit_id_length
+        );
+    }
+
+    if info.root_operation_id.len() != ROOT_OPERATION_ID_LENGTH {
+        bail!(
+            "repo compatibility mismatch: root_operation_id length {} does not match expected {}",
+            info.root_operation_id.len(),
+            ROOT_OPERATION_ID_LENGTH
+        );
+    }
+
+    if let Some(problem) = op_schema_problem(info) {
+        bail!("repo compatibility mismatch: {problem}");
+    }
+
+    for capability in required_capabilities {
+        if !info.capabilities.contains(capability) {
+            bail!(
+                "repo compatibility mismatch: missing required capability {}",
+                capability.as_str()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn capnp_kind(kind: u16) -> Result<crate::tandem_capnp::ObjectKind> {
+    match kind {
+        0 => Ok(crate::tandem_capnp::ObjectKind::Commit),
+        1 => Ok(crate::tandem_capnp::ObjectKind::Tree),
+        2 => Ok(crate::tandem_capnp::ObjectKind::File),
+        3 => Ok(crate::tandem_capnp::ObjectKind::Symlink),
+        4 => Ok(crate::tandem_capnp::ObjectKind::Copy),
+        _ => Err(anyhow!("unknown object kind: {kind}")),
+    }
+}
@@ -0,0 +1,440 @@
+//! Record and replay of store RPCs, for deterministic tests and bug reports.
+//!
+//! With `TANDEM_RPC_RECORD=<dir>`, every store connection logs each call —
+//! its method, params, and the server's results or error — as JSON lines in
+//! `<dir>/rpc-<pid>-<n>.jsonl`. Connecting to `replay://<dir>` instead of a
+//! server answers calls from the logs in that directory: a call gets the
+//! recorded reply to the same method with the same params. Identical calls
+//! get their replies in recorded order, the last one repeating once they run
+//! out. A call that was never recorded fails naming its method.
+//!
+//! Both sides work on whole Cap'n Proto messages, so every store method is
+//! covered without per-method code. Params are compared in canonical form.
+
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::{anyhow, Context, Result};
+use capnp::any_pointer;
+use capnp::capability::{
+    DispatchCallResult, FromClientHook, FromServer, Params, Promise, Results, Server,
+};
+use capnp::private::capability::ClientHook;
+use jj_lib::hex_util::{decode_hex, encode_hex};
+use serde::{Deserialize, Serialize};
+
+use crate::limits;
+use crate::tandem_capnp::store;
+
+/// Directory to record store RPCs into.
+pub const RECORD_ENV: &str = "TANDEM_RPC_RECORD";
+
+/// Server address prefix that replays a recording directory.
+pub const REPLAY_SCHEME: &str = "replay://";
+
+static NEXT_LOG: AtomicUsize = AtomicUsize::new(0);
+
+/// One call as it appears in a log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    interface_id: u64,
+    method_id: u16,
+    /// Canonical params message, hex.
+    params: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    results: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    error: Option<RecordedError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RecordedError {
+    kind: String,
+    message: String,
+}
+
+impl RecordedError {
+    fn from_capnp(err: &capnp::Error) -> Self {
+        let kind = match err.kind {
+            capnp::ErrorKind::Overloaded => "overloaded",
+            capnp::ErrorKind::Disconnected => "disconnected",
+            capnp::ErrorKind::Unimplemented => "unimplemented",
+            _ => "failed",
+        };
+        Self {
+            kind: kind.to_string(),
+            message: err.extra.clone(),
+        }
+    }
+
+    fn to_capnp(&self) -> capnp::Error {
+        let message = self.message.clone();
+        match self.kind.as_str() {
+            "overloaded" => capnp::Error::overloaded(message),
+            "disconnected" => capnp::Error::disconnected(message),
+            "unimplemented" => capnp::Error::unimplemented(message),
+            _ => capnp::Error::failed(message),
+        }
+    }
+}
+
+/// The recording directory named by `replay://<dir>`, if `addr` is one.
+pub fn replay_dir(addr: &str) -> Option<&Path> {
+    addr.strip_prefix(REPLAY_SCHEME).map(Path::new)
+}
+
+/// Wrap `client` so its calls are logged, if `TANDEM_RPC_RECORD` is set.
+pub fn record_if_enabled(client: store::Client) -> Result<store::Client> {
+    match std::env::var_os(RECORD_ENV) {
+        Some(dir) if !dir.is_empty() => record(client, Path::new(&dir)),
+        _ => Ok(client),
+    }
+}
+
+/// Wrap `client` so every call and its reply are appended to a new log in
+/// `dir`.
+pub fn record(client: store::Client, dir: &Path) -> Result<store::Client> {
+    std::fs::create_dir_all(dir)
+        .with_context(|| format!("failed to create RPC recording directory {}", dir.display()))?;
+    let path = dir.join(format!(
+        "rpc-{}-{}.jsonl",
+        std::process::id(),
+        NEXT_LOG.fetch_add(1, Ordering::Relaxed)
+    ));
+    let log = File::create(&path)
+        .with_context(|| format!("failed to create RPC recording {}", path.display()))?;
+    tracing::debug!(path = %path.display(), "recording store RPCs");
+    Ok(serve(Recorder {
+        upstream: capnp::capability::Client::new(client.into_client_hook()),
+        log: Rc::new(RefCell::new(log)),
+    }))
+}
+
+/// A store client answered from the logs in `dir`, without a server.
+pub fn replay(dir: &Path) -> Result<store::Client> {
+    let mut replies: HashMap<CallKey, VecDeque<Reply>> = HashMap::new();
+    let mut logs: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("failed to read RPC recording directory {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    logs.sort();
+    if logs.is_empty() {
+        anyhow::bail!("no RPC recordings (*.jsonl) in {}", dir.display());
+    }
+    for path in &logs {
+        let file =
+            File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+        for (index, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let context = || format!("{}:{}", path.display(), index + 1);
+            let entry: Entry = serde_json::from_str(&line).with_context(context)?;
+            let (key, reply) = entry.into_call().with_context(context)?;
+            replies.entry(key).or_default().push_back(reply);
+        }
+    }
+    Ok(serve(Replay { replies }))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CallKey {
+    interface_id: u64,
+    method_id: u16,
+    params: Vec<u8>,
+}
+
+type Reply = std::result::Result<Vec<u8>, RecordedError>;
+
+impl Entry {
+    fn into_call(self) -> Result<(CallKey, Reply)> {
+        let decode = |hex: &str| decode_hex(hex).ok_or_else(|| anyhow!("invalid hex message"));
+        let key = CallKey {
+            interface_id: self.interface_id,
+            method_id: self.method_id,
+            params: decode(&self.params)?,
+        };
+        let reply = match (self.results, self.error) {
+            (Some(results), _) => Ok(decode(&results)?),
+            (None, Some(error)) => Err(error),
+            (None, None) => anyhow::bail!("entry has neither results nor error"),
+        };
+        Ok((key, reply))
+    }
+}
+
+fn canonical(value: any_pointer::Reader<'_>) -> capnp::Result<Vec<u8>> {
+    let mut message = capnp::message::Builder::new_default();
+    message.set_root_canonical(value)?;
+    Ok(capnp::serialize::write_message_to_words(&message))
+}
+
+fn encode(value: any_pointer::Reader<'_>) -> capnp::Result<Vec<u8>> {
+    let mut message = capnp::message::Builder::new_default();
+    message.set_root(value)?;
+    Ok(capnp::serialize::write_message_to_words(&message))
+}
+
+fn decode(bytes: &[u8]) -> capnp::Result<capnp::message::Reader<capnp::serialize::OwnedSegments>> {
+    capnp::serialize::read_message(bytes, limits::reader_options())
+}
+
+fn dispatched(promise: Promise<(), capnp::Error>) -> DispatchCallResult {
+    DispatchCallResult::new(promise, false)
+}
+
+/// Forwards calls to the real server and logs each one.
+struct Recorder {
+    upstream: capnp::capability::Client,
+    log: Rc<RefCell<File>>,
+}
+
+impl Server for Recorder {
+    fn dispatch_call(
+        &mut self,
+        interface_id: u64,
+        method_id: u16,
+        params: Params<any_pointer::Owned>,
+        mut results: Results<any_pointer::Owned>,
+    ) -> DispatchCallResult {
+        let params = match params.get().and_then(canonical) {
+            Ok(params) => params,
+            Err(err) => return dispatched(Promise::err(err)),
+        };
+        let mut request = self
+            .upstream
+            .new_call::<any_pointer::Owned, any_pointer::Owned>(interface_id, method_id, None);
+        let log = Rc::clone(&self.log);
+        dispatched(Promise::from_future(async move {
+            request
+                .get()
+                .set_as(decode(&params)?.get_root::<any_pointer::Reader>()?)?;
+            let mut entry = Entry {
+                interface_id,
+                method_id,
+                params: encode_hex(&params),
+                results: None,
+                error: None,
+            };
+            let outcome = match request.send().promise.await {
+                Ok(response) => {
+                    let reply = response.get()?;
+                    entry.results = Some(encode_hex(&encode(reply)?));
+                    results.get().set_as(reply)
+                }
+                Err(err) => {
+                    entry.error = Some(RecordedError::from_capnp(&err));
+                    Err(err)
+                }
+            };
+            let mut line = serde_json::to_string(&entry).expect("serialize RPC log entry");
+            line.push('\n');
+            if let Err(err) = log.borrow_mut().write_all(line.as_bytes()) {
+                tracing::warn!(error = %err, "failed to record store RPC");
+            }
+            outcome
+        }))
+    }
+}
+
+/// Answers calls from recorded replies.
+struct Replay {
+    replies: HashMap<CallKey, VecDeque<Reply>>,
+}
+
+impl Replay {
+    fn next_reply(&mut self, key: &CallKey) -> Option<Reply> {
+        let queue = self.replies.get_mut(key)?;
+        if queue.len() > 1 {
+            queue.pop_front()
+        } else {
+            queue.front().cloned()
+        }
+    }
+}
+
+impl Server for Replay {
+    fn dispatch_call(
+        &mut self,
+        interface_id: u64,
+        method_id: u16,
+        params: Params<any_pointer::Owned>,
+        mut results: Results<any_pointer::Owned>,
+    ) -> DispatchCallResult {
+        let params = match params.get().and_then(canonical) {
+            Ok(params) => params,
+            Err(err) => return dispatched(Promise::err(err)),
+        };
+        let key = CallKey {
+            interface_id,
+            method_id,
+            params,
+        };
+        let promise = match self.next_reply(&key) {
+            None => Promise::err(capnp::Error::failed(format!(
+                "no recorded reply to method @{method_id} with these params"
+            ))),
+            Some(Err(error)) => Promise::err(error.to_capnp()),
+            Some(Ok(bytes)) => Promise::from_future(async move {
+                results
+                    .get()
+                    .set_as(decode(&bytes)?.get_root::<any_pointer::Reader>()?)
+            }),
+        };
+        dispatched(promise)
+    }
+}
+
+// ─── Untyped servers as store clients ─────────────────────────────────────────
+
+/// A client of an untyped [`Server`], cast to `store::Client` by [`serve`].
+struct RawClient(Box<dyn ClientHook>);
+
+impl FromClientHook for RawClient {
+    fn new(hook: Box<dyn ClientHook>) -> Self {
+        Self(hook)
+    }
+
+    fn into_client_hook(self) -> Box<dyn ClientHook> {
+        self.0
+    }
+
+    fn as_client_hook(&self) -> &dyn ClientHook {
+        &*self.0
+    }
+}
+
+struct Dispatch<S>(S);
+
+impl<S: Server> Server for Dispatch<S> {
+    fn dispatch_call(
+        &mut self,
+        interface_id: u64,
+        method_id: u16,
+        params: Params<any_pointer::Owned>,
+        results: Results<any_pointer::Owned>,
+    ) -> DispatchCallResult {
+        self.0
+            .dispatch_call(interface_id, method_id, params, results)
+    }
+}
+
+impl<S> std::ops::Deref for Dispatch<S> {
+    type Target = S;
+
+    fn deref(&self) -> &S {
+        &self.0
+    }
+}
+
+impl<S> std::ops::DerefMut for Dispatch<S> {
+    fn deref_mut(&mut self) -> &mut S {
+        &mut self.0
+    }
+}
+
+impl<S: Server + 'static> FromServer<S> for RawClient {
+    type Dispatch = Dispatch<S>;
+
+    fn from_server(s: S) -> Dispatch<S> {
+        Dispatch(s)
+    }
+}
+
+/// A store client whose calls go to an untyped [`Server`]. Record, replay,
+/// and the server's fault injection use it to see every call without
+/// per-method code.
+pub fn serve<S: Server + 'static>(server: S) -> store::Client {
+    capnp_rpc::new_client::<RawClient, S>(server).cast_to()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn version_reply(version: u64) -> Vec<u8> {
+        let mut message = capnp::message::Builder::new_default();
+        message
+            .init_root::<store::get_heads_version_results::Builder>()
+            .set_version(version);
+        capnp::serialize::write_message_to_words(&message)
+    }
+
+    fn entry(results: Option<Vec<u8>>, error: Option<RecordedError>) -> Entry {
+        // A request whose params were never set, as `get_heads_version_request`
+        // sends it.
+        let mut params = capnp::message::Builder::new_default();
+        params.init_root::<any_pointer::Builder>();
+        let params = canonical(params.get_root_as_reader().unwrap()).unwrap();
+        Entry {
+            interface_id: <store::Client as capnp::traits::HasTypeId>::TYPE_ID,
+            // getHeadsVersion @21 in schema/tandem.capnp
+            method_id: 21,
+            params: encode_hex(&params),
+            results: results.map(|r| encode_hex(&r)),
+            error,
+        }
+    }
+
+    async fn heads_version(client: &store::Client) -> capnp::Result<u64> {
+        let response = client.get_heads_version_request().send().promise.await?;
+        Ok(response.get()?.get_version())
+    }
+
+    #[test]
+    fn replays_recorded_replies_in_order() {
+        let tmp = tempfile::tempdir().unwrap();
+        let first = entry(Some(version_reply(3)), None);
+        let second = entry(Some(version_reply(4)), None);
+        let lines = [&first, &second]
+            .iter()
+            .map(|e| serde_json::to_string(e).unwrap() + "\n")
+            .collect::<String>();
+        std::fs::write(tmp.path().join("rpc-1-0.jsonl"), lines).unwrap();
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&rt, async {
+            let client = replay(tmp.path()).unwrap();
+            assert_eq!(heads_version(&client).await.unwrap(), 3);
+            assert_eq!(heads_version(&client).await.unwrap(), 4);
+            // The last reply repeats.
+            assert_eq!(heads_version(&client).await.unwrap(), 4);
+
+            let err = client
+                .get_heads_request()
+                .send()
+                .promise
+                .await
+                .err()
+                .expect("unrecorded call fails");
+            assert!(err.extra.contains("no recorded reply"), "{err}");
+        });
+    }
+
+    #[test]
+    fn recorded_errors_keep_their_kind() {
+        let err = capnp::Error::overloaded("busy".to_string());
+        let recorded = RecordedError::from_capnp(&err);
+        let back = recorded.to_capnp();
+        assert_eq!(back.kind, capnp::ErrorKind::Overloaded);
+        assert_eq!(back.extra, "busy");
+    }
+
+    #[test]
+    fn replay_addresses_name_a_directory() {
+        assert_eq!(replay_dir("replay:///tmp/rec"), Some(Path::new("/tmp/rec")));
+        assert_eq!(replay_dir("127.0.0.1:13013"), None);
+    }
+}
@@ -0,0 +1,216 @@
+//! Which server a workspace's stores talk to.
+//!
+//! Every store directory records the address it was initialized against in
+//! `server_address`; `TANDEM_SERVER`, or else the `tandem.endpoint` jj config
+//! key, overrides it. An override naming a
+//! different server is only honoured once that server is shown to host the
+//! same repo: it must know the operations this workspace anchored itself to
+//! (the op heads seen on its first load). Root ids are the same in every jj
+//! repo, so operation ids, which hash their content, are what tell repos
+//! apart. A proxy or a migrated copy passes; an unrelated repo is refused unless
+//! `--allow-server-override` is given, so a stray environment variable or
+//! config entry cannot write one repo's operations into another.
+//!
+//! The same check applies when a migrated server redirects the client
+//! (`tandem migrate`); a redirect that passes rewrites `server_address`.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use anyhow::{bail, Context, Result};
+use jj_lib::backend::BackendLoadError;
+use jj_lib::hex_util::{decode_hex, encode_hex};
+use serde::{Deserialize, Serialize};
+
+use crate::rpc::TandemClient;
+use crate::settings::TandemSettings;
+
+pub const ENV: &str = "TANDEM_SERVER";
+const ADDRESS_FILE: &str = "server_address";
+const ANCHOR_FILE: &str = "server_anchor.json";
+
+static ALLOW_OVERRIDE: AtomicBool = AtomicBool::new(false);
+static OVERRIDE_WARNED: AtomicBool = AtomicBool::new(false);
+static REDIRECT_NOTED: AtomicBool = AtomicBool::new(false);
+
+/// Identity of the repo a workspace was first loaded against. Anchors
+/// written by older versions also carry the root ids; they are ignored.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Anchor {
+    /// Op heads at the time the anchor was recorded (root excluded).
+    operations: Vec<String>,
+}
+
+/// Set by `--allow-server-override`: skip the repo identity check.
+pub fn allow_override() {
+    ALLOW_OVERRIDE.store(true, Ordering::Relaxed);
+}
+
+fn recorded_address(store_path: &Path) -> std::io::Result<String> {
+    Ok(std::fs::read_to_string(store_path.join(ADDRESS_FILE))?
+        .trim()
+        .to_string())
+}
+
+fn env_address() -> Option<String> {
+    std::env::var(ENV)
+        .ok()
+        .map(|addr| addr.trim().to_string())
+        .filter(|addr| !addr.is_empty())
+}
+
+/// The override in effect, if any, and how to name it in messages.
+fn override_address(settings: &TandemSettings) -> Option<(String, &'static str)> {
+    env_address().map(|addr| (addr, ENV)).or_else(|| {
+        settings
+            .endpoint
+            .clone()
+            .map(|addr| (addr, "tandem.endpoint"))
+    })
+}
+
+/// Address the store at `store_path` should connect to.
+pub fn read(store_path: &Path, settings: &TandemSettings) -> Result<String, BackendLoadError> {
+    if let Some((addr, _)) = override_address(settings) {
+        return Ok(addr);
+    }
+    recorded_address(store_path).map_err(|e| {
+        BackendLoadError(
+            anyhow::anyhow!(
+                "cannot read tandem server address from {}, {ENV} env, or tandem.endpoint config: {e}",
+                store_path.join(ADDRESS_FILE).display()
+            )
+            .into(),
+        )
+    })
+}
+
+/// Check that `client` serves the repo this store belongs to.
+///
+/// Records the anchor on first use. When an override or a server-side
+/// redirect moves the store away from its recorded address, compares the
+/// server against the anchor; a redirect that passes is persisted.
+pub fn verify(
+    store_path: &Path,
+    settings: &TandemSettings,
+    client: &TandemClient,
+) -> Result<(), BackendLoadError> {
+    check(store_path, settings, client).map_err(|e| BackendLoadError(e.into()))
+}
+
+/// Why `client` does not serve the repo the store at `store_path` is
+/// anchored to, or `None` if it does (or no anchor was recorded yet). Used
+/// for servers the store reads from besides its own, like a read mirror.
+pub fn anchor_mismatch(store_path: &Path, client: &TandemClient) -> Result<Option<String>> {
+    Ok(load_anchor(store_path)?.and_then(|anchor| mismatch(&anchor, client)))
+}
+
+fn load_anchor(store_path: &Path) -> Result<Option<Anchor>> {
+    let anchor_path = store_path.join(ANCHOR_FILE);
+    match std::fs::read(&anchor_path) {
+        Ok(bytes) => {
+            Ok(Some(serde_json::from_slice(&bytes).with_context(|| {
+                format!("parse {}", anchor_path.display())
+            })?))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("read {}", anchor_path.display())),
+    }
+}
+
+fn check(store_path: &Path, settings: &TandemSettings, client: &TandemClient) -> Result<()> {
+    let anchor_path = store_path.join(ANCHOR_FILE);
+    let anchor = load_anchor(store_path)?;
+
+    let recorded = recorded_address(store_path).ok();
+    let overridden = override_address(settings).map(|(_, source)| source);
+    let server = client.server_addr();
+    let redirected = client.repo_info().redirect.is_some();
+    let moved = recorded
+        .as_deref()
+        .is_some_and(|recorded| recorded != server);
+
+    if !moved {
+        if anchor.is_none() {
+            if let Some(anchor) = current_anchor(client)? {
+                // Best effort: an unwritable store directory only costs the check.
+                let _ = std::fs::write(&anchor_path, serde_json::to_vec_pretty(&anchor)?);
+            }
+        }
+        return Ok(());
+    }
+
+    let recorded = recorded.unwrap_or_default();
+    let (via, hint) = if let Some(source) = overridden.filter(|_| !redirected) {
+        (
+            format!("{source}={server}"),
+            format!("Unset {source}, or pass"),
+        )
+    } else {
+        (format!("redirect to {server}"), "Pass".to_string())
+    };
+    if ALLOW_OVERRIDE.load(Ordering::Relaxed) {
+        if !OVERRIDE_WARNED.swap(true, Ordering::Relaxed) {
+            eprintln!(
+                "warning: {via} overrides this workspace's server {recorded} \
+                 (--allow-server-override)"
+            );
+        }
+        return Ok(());
+    }
+
+    let refuse = |reason: String| -> Result<()> {
+        bail!(
+            "{via} does not match this workspace's server {recorded}: {reason}\n\
+             {hint} --allow-server-override if the move is intentional."
+        )
+    };
+    let Some(anchor) = anchor else {
+        return refuse("the workspace has no recorded repo identity to compare against".into());
+    };
+    if let Some(reason) = mismatch(&anchor, client) {
+        return refuse(reason);
+    }
+
+    // A migrated repo: remember where it lives now.
+    if redirected && overridden.is_none() {
+        std::fs::write(store_path.join(ADDRESS_FILE), server)
+            .with_context(|| format!("record new server address {server}"))?;
+        if !REDIRECT_NOTED.swap(true, Ordering::Relaxed) {
+            eprintln!("note: this repo moved from {recorded} to {server}; workspace updated");
+        }
+    }
+    Ok(())
+}
+
+/// Why `client` does not host the repo `anchor` describes, if it does not.
+fn mismatch(anchor: &Anchor, client: &TandemClient) -> Option<String> {
+    for op_hex in &anchor.operations {
+        let Some(id) = decode_hex(op_hex) else {
+            continue;
+        };
+        if client.get_operation(&id).is_err() {
+            return Some(format!(
+                "the server does not know operation {}, so it hosts a different repo",
+                &op_hex[..op_hex.len().min(12)]
+            ));
+        }
+    }
+    None
+}
+
+fn current_anchor(client: &TandemClient) -> Result<Option<Anchor>> {
+    let root_operation_id = encode_hex(&client.repo_info().root_operation_id);
+    let operations: Vec<String> = client
+        .get_heads_state()?
+        .heads
+        .iter()
+        .map(|id| encode_hex(id))
+        .filter(|hex| *hex != root_operation_id)
+        .collect();
+    if operations.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Anchor { operations }))
+}
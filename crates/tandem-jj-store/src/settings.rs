@@ -0,0 +1,186 @@
+//! Client knobs from jj's config, under the `[tandem]` table.
+//!
+//! ```toml
+//! [tandem]
+//! endpoint = "tandem-host:13013"   # like TANDEM_SERVER, for every workspace
+//! cache-dir = "~/.cache/tandem"    # where operations and views are cached
+//! max-inflight = 16                # concurrent RPCs per connection
+//! timing = true                    # print each RPC's latency to stderr
+//! offline = true                   # never dial; read the local cache
+//! prefetch-paths = ["svc/api"]     # read ahead under these paths only
+//! read-mirror = "replica:13013"    # fetch objects from a nearby replica
+//! journal = true                   # log each command's RPCs (tandem journal)
+//! ```
+//!
+//! Environment variables still win over config (`TANDEM_SERVER`,
+//! `TANDEM_RPC_MAX_INFLIGHT`, `TANDEM_READ_MIRROR`), matching how jj layers
+//! `JJ_*` variables over its config files. Everything here is read when a
+//! store loads, so `jj config set --user tandem.max-inflight 8` applies to
+//! the next command.
+
+use std::path::PathBuf;
+
+use jj_lib::config::ConfigGetResultExt as _;
+use jj_lib::settings::UserSettings;
+
+use crate::rpc::ClientOptions;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TandemSettings {
+    /// `tandem.endpoint`: server address overriding the workspace's own.
+    pub endpoint: Option<String>,
+    /// `tandem.cache-dir`: operation/view cache location. Entries are keyed
+    /// by content hash, so one directory can be shared by every workspace.
+    pub cache_dir: Option<PathBuf>,
+    /// `tandem.max-inflight`: cap on concurrent RPCs per connection.
+    pub max_inflight: Option<usize>,
+    /// `tandem.timing`: report every RPC's latency on stderr.
+    pub timing: bool,
+    /// `tandem.offline`: work from saved state without dialing the server
+    /// (see `offline.rs`).
+    pub offline: bool,
+    /// `tandem.prefetch-paths`: repo paths whose trees and files the backend
+    /// fetches ahead of jj's reads (set by `tandem init --sparse`). Empty
+    /// turns prefetching off.
+    pub prefetch_paths: Vec<String>,
+    /// `tandem.read-mirror`: server answering object and operation reads
+    /// in place of the primary (set by `tandem init --read-mirror`; see
+    /// `read_mirror.rs`).
+    pub read_mirror: Option<String>,
+    /// `tandem.journal`: append each command's RPCs and outcome to
+    /// `.jj/repo/tandem/journal.jsonl` (see `journal.rs`).
+    pub journal: bool,
+}
+
+impl TandemSettings {
+    pub fn from_user(settings: &UserSettings) -> anyhow::Result<Self> {
+        let endpoint = settings
+            .get_string("tandem.endpoint")
+            .optional()?
+            .map(|addr| addr.trim().to_string())
+            .filter(|addr| !addr.is_empty());
+        let cache_dir = settings
+            .get_string("tandem.cache-dir")
+            .optional()?
+            .filter(|dir| !dir.trim().is_empty())
+            .map(|dir| expand_home(dir.trim()));
+        let max_inflight = settings
+            .get::<usize>("tandem.max-inflight")
+            .optional()?
+            .map(|n| n.max(1));
+        let timing = settings
+            .get_bool("tandem.timing")
+            .optional()?
+            .unwrap_or(false);
+        let offline = settings
+            .get_bool("tandem.offline")
+            .optional()?
+            .unwrap_or(false);
+        let prefetch_paths = settings
+            .get::<Vec<String>>("tandem.prefetch-paths")
+            .optional()?
+            .unwrap_or_default()
+            .iter()
+            .map(|path| normalize_repo_path(path))
+            .collect();
+        let read_mirror = settings
+            .get_string("tandem.read-mirror")
+            .optional()?
+            .map(|addr| addr.trim().to_string())
+            .filter(|addr| !addr.is_empty());
+        let journal = settings
+            .get_bool("tandem.journal")
+            .optional()?
+            .unwrap_or(false);
+        Ok(Self {
+            endpoint,
+            cache_dir,
+            max_inflight,
+            timing,
+            offline,
+            prefetch_paths,
+            read_mirror,
+            journal,
+        })
+    }
+
+    pub fn client_options(&self) -> ClientOptions {
+        ClientOptions {
+            max_inflight: self.max_inflight,
+            timing: self.timing,
+        }
+    }
+}
+
+/// A repo path as jj writes it internally: `/`-separated, no leading `./`
+/// or surrounding slashes, and `""` for the repo root.
+pub fn normalize_repo_path(path: &str) -> String {
+    let path = path.trim().trim_start_matches("./").trim_matches('/');
+    if path == "." {
+        String::new()
+    } else {
+        path.to_string()
+    }
+}
+
+fn expand_home(dir: &str) -> PathBuf {
+    match (dir.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(dir),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jj_lib::config::{ConfigLayer, ConfigSource, StackedConfig};
+
+    use super::*;
+
+    fn settings_from(toml: &str) -> UserSettings {
+        let mut config = StackedConfig::with_defaults();
+        config.add_layer(ConfigLayer::parse(ConfigSource::User, toml).unwrap());
+        UserSettings::from_config(config).unwrap()
+    }
+
+    #[test]
+    fn reads_the_tandem_table() {
+        let settings = TandemSettings::from_user(&settings_from(
+            "[tandem]\n\
+             endpoint = \" host:13013 \"\n\
+             cache-dir = \"/var/cache/tandem\"\n\
+             max-inflight = 0\n\
+             timing = true\n\
+             offline = true\n\
+             prefetch-paths = [\"./services/api/\", \".\"]\n\
+             read-mirror = \"replica:13013\"\n\
+             journal = true\n",
+        ))
+        .unwrap();
+        assert_eq!(
+            settings,
+            TandemSettings {
+                endpoint: Some("host:13013".to_string()),
+                cache_dir: Some(PathBuf::from("/var/cache/tandem")),
+                max_inflight: Some(1),
+                timing: true,
+                offline: true,
+                prefetch_paths: vec!["services/api".to_string(), String::new()],
+                read_mirror: Some("replica:13013".to_string()),
+                journal: true,
+            }
+        );
+    }
+
+    #[test]
+    fn missing_table_means_defaults() {
+        let settings = TandemSettings::from_user(&settings_from("")).unwrap();
+        assert_eq!(settings, TandemSettings::default());
+    }
+
+    #[test]
+    fn rejects_mistyped_values() {
+        let err = TandemSettings::from_user(&settings_from("[tandem]\nmax-inflight = \"lots\"\n"))
+            .unwrap_err();
+        assert!(format!("{err:#}").contains("max-inflight"), "{err:#}");
+    }
+}
@@ -0,0 +1,466 @@
+//! The server's op store: jj's simple op store with operations sharded by
+//! id prefix.
+//!
+//! jj's `simple_op_store` keeps every operation in one flat `operations/`
+//! directory, which slows down on most filesystems past ~100k entries. A
+//! long-lived tandem server gets there: every commit, snapshot, and
+//! reconcile merge is an operation. This store keeps the same file format
+//! but places each operation at `operations/<first two hex>/<rest>`, as git
+//! does for loose objects, so no directory holds more than a few hundred
+//! shards or a few thousand operations.
+//!
+//! Sharding is opt-in (`tandem serve --shard-operations`), since stock jj
+//! does not know this store type and a sharded server repo only opens with
+//! `tandem`, which registers it. [`adopt`] switches `op_store/type` over;
+//! after that, an operation still in the flat layout is moved into its
+//! shard the first time it is read, and prefix resolution and gc look in
+//! both layouts. [`flatten`] goes back: it moves every operation out of its
+//! shard and restores `simple_op_store`. Views stay flat — there is one per
+//! operation at most, and they are pruned with the operations.
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use jj_lib::content_hash::blake2b_hash;
+use jj_lib::dag_walk;
+use jj_lib::object_id::{HexPrefix, ObjectId as _, PrefixResolution};
+use jj_lib::op_store::{
+    OpStore, OpStoreError, OpStoreResult, Operation, OperationId, RootOperationData, View, ViewId,
+};
+use jj_lib::simple_op_store::SimpleOpStore;
+use prost::Message as _;
+
+use crate::proto_convert;
+use crate::tmp_file::tmp_path;
+
+/// `op_store/type` of the sharded op store.
+pub const SHARDED_OP_STORE_NAME: &str = "tandem_sharded_op_store";
+
+/// Hex digits of the id that name an operation's shard directory.
+const SHARD_PREFIX_LEN: usize = 2;
+
+/// Hex digits in a full operation id.
+const OPERATION_HEX_LEN: usize = 128;
+
+/// Where operation `hex` is stored in the sharded layout.
+pub fn operation_path(op_store_path: &Path, hex: &str) -> PathBuf {
+    let (shard, rest) = hex.split_at(SHARD_PREFIX_LEN.min(hex.len()));
+    op_store_path.join("operations").join(shard).join(rest)
+}
+
+/// Where operation `hex` is stored in jj's flat layout.
+pub fn flat_operation_path(op_store_path: &Path, hex: &str) -> PathBuf {
+    op_store_path.join("operations").join(hex)
+}
+
+/// The file holding operation `hex`, if it is stored. An operation still in
+/// the flat layout is moved into its shard first.
+pub fn find_operation(op_store_path: &Path, hex: &str) -> io::Result<Option<PathBuf>> {
+    let path = operation_path(op_store_path, hex);
+    if path.is_file() {
+        return Ok(Some(path));
+    }
+    let flat = flat_operation_path(op_store_path, hex);
+    if !flat.is_file() {
+        return Ok(None);
+    }
+    if let Some(shard) = path.parent() {
+        fs::create_dir_all(shard)?;
+    }
+    match fs::rename(&flat, &path) {
+        Ok(()) => Ok(Some(path)),
+        // Another reader migrated it first.
+        Err(err) if err.kind() == ErrorKind::NotFound && path.is_file() => Ok(Some(path)),
+        Err(err) => Err(err),
+    }
+}
+
+/// Store `bytes` as operation `hex` unless it is already stored in either
+/// layout.
+pub fn write_operation_file(op_store_path: &Path, hex: &str, bytes: &[u8]) -> io::Result<()> {
+    if find_operation(op_store_path, hex)?.is_some() {
+        return Ok(());
+    }
+    let path = operation_path(op_store_path, hex);
+    if let Some(shard) = path.parent() {
+        fs::create_dir_all(shard)?;
+    }
+    let tmp = tmp_path(&path);
+    fs::write(&tmp, bytes)?;
+    fs::rename(&tmp, &path)
+}
+
+/// Hex ids of the stored operations that start with `hex_prefix`, from both
+/// layouts, sorted.
+pub fn operation_ids_with_prefix(
+    op_store_path: &Path,
+    hex_prefix: &str,
+) -> io::Result<Vec<String>> {
+    let op_dir = op_store_path.join("operations");
+    let mut ids = Vec::new();
+    let entries = match fs::read_dir(&op_dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(ids),
+        Err(err) => return Err(err),
+    };
+    for entry in entries {
+        let entry = entry?;
+        let Ok(name) = entry.file_name().into_string() else {
+            continue;
+        };
+        if name.len() == SHARD_PREFIX_LEN && entry.file_type()?.is_dir() {
+            let shard_matches = if hex_prefix.len() >= SHARD_PREFIX_LEN {
+                hex_prefix.starts_with(&name)
+            } else {
+                name.starts_with(hex_prefix)
+            };
+            if !shard_matches {
+                continue;
+            }
+            let rest_prefix = hex_prefix.get(SHARD_PREFIX_LEN..).unwrap_or("");
+            for op in fs::read_dir(entry.path())? {
+                let Ok(rest) = op?.file_name().into_string() else {
+                    continue;
+                };
+                if rest.starts_with(rest_prefix) && is_operation_hex(&name, &rest) {
+                    ids.push(format!("{name}{rest}"));
+                }
+            }
+        } else if name.starts_with(hex_prefix) && is_operation_hex(&name, "") {
+            ids.push(name);
+        }
+    }
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+fn is_operation_hex(shard: &str, rest: &str) -> bool {
+    shard.len() + rest.len() == OPERATION_HEX_LEN
+        && shard
+            .chars()
+            .chain(rest.chars())
+            .all(|c| c.is_ascii_hexdigit())
+}
+
+/// Whether the op store at `op_store_path` uses the sharded layout.
+pub fn is_sharded(op_store_path: &Path) -> io::Result<bool> {
+    let current = fs::read_to_string(op_store_path.join("type"))?;
+    Ok(current.trim() == SHARDED_OP_STORE_NAME)
+}
+
+/// Switch a simple op store at `op_store_path` to the sharded store.
+/// Returns whether it changed anything.
+pub fn adopt(op_store_path: &Path) -> io::Result<bool> {
+    let type_path = op_store_path.join("type");
+    let current = fs::read_to_string(&type_path)?;
+    if current.trim() != SimpleOpStore::name() {
+        return Ok(false);
+    }
+    write_type(&type_path, SHARDED_OP_STORE_NAME)?;
+    Ok(true)
+}
+
+/// Switch a sharded op store at `op_store_path` back to jj's simple op
+/// store, moving every operation out of its shard. Returns how many
+/// operations moved, or `None` if the store was not sharded.
+pub fn flatten(op_store_path: &Path) -> io::Result<Option<usize>> {
+    if !is_sharded(op_store_path)? {
+        return Ok(None);
+    }
+    let mut moved = move_to_flat_layout(op_store_path)?;
+    write_type(&op_store_path.join("type"), SimpleOpStore::name())?;
+    // A sharded reader may have moved an operation back in the meantime.
+    moved += move_to_flat_layout(op_store_path)?;
+    Ok(Some(moved))
+}
+
+fn move_to_flat_layout(op_store_path: &Path) -> io::Result<usize> {
+    let op_dir = op_store_path.join("operations");
+    let mut moved = 0;
+    for entry in fs::read_dir(&op_dir)? {
+        let entry = entry?;
+        let Ok(shard) = entry.file_name().into_string() else {
+            continue;
+        };
+        if shard.len() != SHARD_PREFIX_LEN || !entry.file_type()?.is_dir() {
+            continue;
+        }
+        for op in fs::read_dir(entry.path())? {
+            let op = op?;
+            let Ok(rest) = op.file_name().into_string() else {
+                continue;
+            };
+            if !is_operation_hex(&shard, &rest) {
+                continue;
+            }
+            fs::rename(op.path(), op_dir.join(format!("{shard}{rest}")))?;
+            moved += 1;
+        }
+        // Leftover temporary files keep the shard; it is harmless.
+        let _ = fs::remove_dir(entry.path());
+    }
+    Ok(moved)
+}
+
+fn write_type(type_path: &Path, name: &str) -> io::Result<()> {
+    let tmp = tmp_path(type_path);
+    fs::write(&tmp, name)?;
+    fs::rename(&tmp, type_path)
+}
+
+/// jj-lib `OpStore` over the sharded layout. Views and the root operation
+/// are left to the wrapped [`SimpleOpStore`].
+#[derive(Debug)]
+pub struct ShardedOpStore {
+    inner: SimpleOpStore,
+    path: PathBuf,
+}
+
+impl ShardedOpStore {
+    pub fn load(store_path: &Path, root_data: RootOperationData) -> Self {
+        Self {
+            inner: SimpleOpStore::load(store_path, root_data),
+            path: store_path.to_path_buf(),
+        }
+    }
+
+    fn views_dir(&self) -> PathBuf {
+        self.path.join("views")
+    }
+}
+
+#[async_trait]
+impl OpStore for ShardedOpStore {
+    fn name(&self) -> &str {
+        SHARDED_OP_STORE_NAME
+    }
+
+    fn root_operation_id(&self) -> &OperationId {
+        self.inner.root_operation_id()
+    }
+
+    async fn read_view(&self, id: &ViewId) -> OpStoreResult<View> {
+        self.inner.read_view(id).await
+    }
+
+    async fn write_view(&self, view: &View) -> OpStoreResult<ViewId> {
+        self.inner.write_view(view).await
+    }
+
+    async fn read_operation(&self, id: &OperationId) -> OpStoreResult<Operation> {
+        if id == self.root_operation_id() {
+            return self.inner.read_operation(id).await;
+        }
+        let hex = id.hex();
+        let read_error =
+            |source: Box<dyn std::error::Error + Send + Sync>| OpStoreError::ReadObject {
+                object_type: "operation".to_string(),
+                hash: hex.clone(),
+                source,
+            };
+        let path = find_operation(&self.path, &hex)
+            .map_err(|err| read_error(err.into()))?
+            .ok_or_else(|| OpStoreError::ObjectNotFound {
+                object_type: "operation".to_string(),
+                hash: hex.clone(),
+                source: "not in the sharded or flat layout".into(),
+            })?;
+        let buf = fs::read(&path).map_err(|err| read_error(err.into()))?;
+        let proto = jj_lib::protos::simple_op_store::Operation::decode(&*buf)
+            .map_err(|err| read_error(err.into()))?;
+        let mut operation =
+            proto_convert::operation_from_proto(proto).map_err(|err| read_error(err.into()))?;
+        if operation.parents.is_empty() {
+            operation.parents.push(self.root_operation_id().clone());
+        }
+        Ok(operation)
+    }
+
+    async fn write_operation(&self, operation: &Operation) -> OpStoreResult<OperationId> {
+        assert!(!operation.parents.is_empty());
+        let id = OperationId::new(blake2b_hash(operation).to_vec());
+        let bytes = proto_convert::operation_to_proto(operation).encode_to_vec();
+        write_operation_file(&self.path, &id.hex(), &bytes).map_err(|err| {
+            OpStoreError::WriteObject {
+                object_type: "operation",
+                source: err.into(),
+            }
+        })?;
+        Ok(id)
+    }
+
+    async fn resolve_operation_id_prefix(
+        &self,
+        prefix: &HexPrefix,
+    ) -> OpStoreResult<PrefixResolution<OperationId>> {
+        let root_id = self.root_operation_id();
+        let matches_root = prefix.matches(root_id);
+        let hex_prefix = prefix.hex();
+        if hex_prefix.len() == OPERATION_HEX_LEN {
+            let stored = matches_root
+                || find_operation(&self.path, &hex_prefix)
+                    .map_err(|err| OpStoreError::Other(err.into()))?
+                    .is_some();
+            return Ok(match prefix.as_full_bytes() {
+                Some(bytes) if stored => {
+                    PrefixResolution::SingleMatch(OperationId::from_bytes(bytes))
+                }
+                _ => PrefixResolution::NoMatch,
+            });
+        }
+        let ids = operation_ids_with_prefix(&self.path, &hex_prefix)
+            .map_err(|err| OpStoreError::Other(err.into()))?;
+        let mut matched = matches_root.then(|| root_id.clone());
+        for hex in ids {
+            let Some(id) = OperationId::try_from_hex(&hex) else {
+                continue;
+            };
+            if matched.is_some() {
+                return Ok(PrefixResolution::AmbiguousMatch);
+            }
+            matched = Some(id);
+        }
+        Ok(match matched {
+            Some(id) => PrefixResolution::SingleMatch(id),
+            None => PrefixResolution::NoMatch,
+        })
+    }
+
+    fn gc(&self, head_ids: &[OperationId], keep_newer: SystemTime) -> OpStoreResult<()> {
+        let read_op = |id: &OperationId| {
+            futures::executor::block_on(self.read_operation(id)).map(|data| (id.clone(), data))
+        };
+        let reachable: HashMap<OperationId, Operation> = dag_walk::dfs_ok(
+            head_ids.iter().map(read_op),
+            |(id, _)| id.clone(),
+            |(_, data)| data.parents.iter().map(read_op).collect::<Vec<_>>(),
+        )
+        .collect::<Result<_, _>>()?;
+        let reachable_views: HashSet<String> =
+            reachable.values().map(|data| data.view_id.hex()).collect();
+        let reachable_ops: HashSet<String> = reachable.keys().map(|id| id.hex()).collect();
+
+        let prune = || -> io::Result<()> {
+            for hex in operation_ids_with_prefix(&self.path, "")? {
+                if reachable_ops.contains(&hex) {
+                    continue;
+                }
+                for path in [
+                    operation_path(&self.path, &hex),
+                    flat_operation_path(&self.path, &hex),
+                ] {
+                    remove_if_not_newer(&path, keep_newer)?;
+                }
+            }
+            let views_dir = self.views_dir();
+            for entry in fs::read_dir(&views_dir)? {
+                let entry = entry?;
+                let Ok(name) = entry.file_name().into_string() else {
+                    continue;
+                };
+                if ViewId::try_from_hex(&name).is_none() || reachable_views.contains(&name) {
+                    continue;
+                }
+                remove_if_not_newer(&entry.path(), keep_newer)?;
+            }
+            Ok(())
+        };
+        prune().map_err(|err| OpStoreError::Other(err.into()))
+    }
+}
+
+/// Delete `path` unless it is missing or was modified after `keep_newer`.
+fn remove_if_not_newer(path: &Path, keep_newer: SystemTime) -> io::Result<()> {
+    let metadata = match fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    if metadata.modified()? > keep_newer {
+        return Ok(());
+    }
+    fs::remove_file(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex_id(first: char) -> String {
+        std::iter::once(first)
+            .chain(std::iter::repeat_n('0', OPERATION_HEX_LEN - 1))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_prefixes_across_both_layouts() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let op_store = tmp.path();
+        let sharded = hex_id('a');
+        let flat = format!("ab{}", &hex_id('1')[2..]);
+        write_operation_file(op_store, &sharded, b"sharded").unwrap();
+        fs::write(flat_operation_path(op_store, &flat), b"flat").unwrap();
+        assert!(operation_path(op_store, &sharded).is_file());
+
+        assert_eq!(
+            operation_ids_with_prefix(op_store, "a").unwrap(),
+            [sharded.clone(), flat.clone()]
+        );
+        assert_eq!(
+            operation_ids_with_prefix(op_store, "ab").unwrap(),
+            [flat.as_str()]
+        );
+        assert_eq!(
+            operation_ids_with_prefix(op_store, "a0").unwrap(),
+            [sharded]
+        );
+        assert!(operation_ids_with_prefix(op_store, "b").unwrap().is_empty());
+
+        let found = find_operation(op_store, &flat).unwrap().unwrap();
+        assert_eq!(found, operation_path(op_store, &flat));
+        assert_eq!(fs::read(found).unwrap(), b"flat");
+        assert!(!flat_operation_path(op_store, &flat).exists());
+        assert_eq!(operation_ids_with_prefix(op_store, "ab").unwrap(), [flat]);
+    }
+
+    #[test]
+    fn adopts_only_simple_op_stores() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let type_path = tmp.path().join("type");
+        fs::write(&type_path, SimpleOpStore::name()).unwrap();
+        assert!(adopt(tmp.path()).unwrap());
+        assert_eq!(
+            fs::read_to_string(&type_path).unwrap(),
+            SHARDED_OP_STORE_NAME
+        );
+        assert!(!adopt(tmp.path()).unwrap());
+    }
+
+    #[test]
+    fn flatten_moves_operations_out_of_their_shards() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let op_store = tmp.path();
+        fs::write(op_store.join("type"), SimpleOpStore::name()).unwrap();
+        fs::create_dir(op_store.join("operations")).unwrap();
+        assert_eq!(flatten(op_store).unwrap(), None);
+
+        assert!(adopt(op_store).unwrap());
+        let sharded = hex_id('a');
+        write_operation_file(op_store, &sharded, b"sharded").unwrap();
+        assert_eq!(flatten(op_store).unwrap(), Some(1));
+        assert_eq!(
+            fs::read_to_string(op_store.join("type")).unwrap(),
+            SimpleOpStore::name()
+        );
+        assert_eq!(
+            fs::read(flat_operation_path(op_store, &sharded)).unwrap(),
+            b"sharded"
+        );
+        assert!(!op_store.join("operations").join("a0").exists());
+    }
+}
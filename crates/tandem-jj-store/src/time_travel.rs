@@ -0,0 +1,163 @@
+//! Time travel — run a jj command against the heads of an earlier version.
+//!
+//! `tandem --at-version N <jj args>` (or `TANDEM_AT_VERSION=N`) shows the
+//! repo as it was when the server's heads were at version N, as recorded in
+//! the heads-history journal (`tandem timeline` lists the versions). The op
+//! heads store answers with the heads journaled for N and refuses to move
+//! them, and the command runs with jj's `--at-op @ --ignore-working-copy`,
+//! so the working copy is neither snapshotted nor updated. Without
+//! `--ignore-working-copy`, jj would notice that the working copy was
+//! recorded at a later operation and load that one instead.
+//!
+//! jj parses its own arguments, so tandem runs itself again with
+//! `--at-version N` swapped for those flags and the version passed down in
+//! `TANDEM_PINNED_VERSION`. Versions the journal no longer holds, and versions
+//! with divergent op heads, are refused rather than approximated.
+
+use std::process::ExitCode;
+
+use anyhow::{anyhow, bail, Context, Result};
+use jj_lib::hex_util::encode_hex;
+
+use crate::rpc::TandemClient;
+
+/// Environment variable naming the version to travel to.
+pub const AT_VERSION_ENV: &str = "TANDEM_AT_VERSION";
+/// Set on the re-run command: the version its op heads are pinned to.
+const PINNED_ENV: &str = "TANDEM_PINNED_VERSION";
+
+/// The version this process's op heads are pinned to, if any.
+pub fn pinned() -> Option<u64> {
+    std::env::var(PINNED_ENV).ok()?.trim().parse().ok()
+}
+
+/// Split `--at-version N` (or `--at-version=N`) out of `args`.
+fn take_at_version(args: &[String]) -> Result<(Option<u64>, Vec<String>)> {
+    let mut version = None;
+    let mut rest = Vec::with_capacity(args.len());
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let value = if arg == "--at-version" {
+            iter.next()
+                .ok_or_else(|| anyhow!("--at-version needs a heads version"))?
+        } else if let Some(value) = arg.strip_prefix("--at-version=") {
+            value
+        } else {
+            rest.push(arg.clone());
+            continue;
+        };
+        version = Some(parse_version(value)?);
+    }
+    Ok((version, rest))
+}
+
+fn parse_version(value: &str) -> Result<u64> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("invalid heads version {value:?}: expected a number"))
+}
+
+/// When `args` (a jj command line) asks for `--at-version`, run it again
+/// pinned to that version and return its exit code. `None` means run the
+/// command as usual.
+pub fn run_pinned_if_requested(args: &[String]) -> Option<ExitCode> {
+    if pinned().is_some() {
+        return None;
+    }
+    let result = (|| -> Result<Option<ExitCode>> {
+        let (flag, rest) = take_at_version(args)?;
+        let version = match (flag, std::env::var(AT_VERSION_ENV)) {
+            (Some(version), _) => version,
+            (None, Ok(value)) if !value.trim().is_empty() => {
+                parse_version(&value).with_context(|| format!("reading {AT_VERSION_ENV}"))?
+            }
+            (None, _) => return Ok(None),
+        };
+        if rest.iter().any(|arg| {
+            ["--at-op", "--at-operation"]
+                .iter()
+                .any(|flag| arg == flag || arg.starts_with(&format!("{flag}=")))
+        }) {
+            bail!("--at-version cannot be combined with --at-op");
+        }
+
+        let exe = std::env::current_exe().context("cannot determine executable path")?;
+        let mut command = std::process::Command::new(exe);
+        command.args(["--at-op", "@"]);
+        if !rest.iter().any(|arg| arg == "--ignore-working-copy") {
+            command.arg("--ignore-working-copy");
+        }
+        let status = command
+            .args(rest.iter().skip(1))
+            .env(PINNED_ENV, version.to_string())
+            .env_remove(AT_VERSION_ENV)
+            .status()
+            .context("running the pinned command")?;
+        Ok(Some(ExitCode::from(
+            status.code().unwrap_or(1).clamp(0, 255) as u8,
+        )))
+    })();
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            Some(ExitCode::FAILURE)
+        }
+    }
+}
+
+/// The op heads the server journaled for `version`.
+pub fn heads_at(client: &TandemClient, version: u64) -> Result<Vec<Vec<u8>>> {
+    let (transitions, oldest) = client
+        .get_heads_history(version.saturating_sub(1))
+        .with_context(|| format!("looking up heads version {version}"))?;
+    let Some(transition) = transitions.iter().find(|t| t.version == version) else {
+        if version < oldest {
+            bail!(
+                "heads version {version} is older than the server's heads history, \
+                 which starts at {oldest}"
+            );
+        }
+        bail!("the server's heads history has no version {version}");
+    };
+    if transition.heads.len() > 1 {
+        let heads: Vec<String> = transition.heads.iter().map(|id| encode_hex(id)).collect();
+        bail!(
+            "heads version {version} has {} divergent op heads ({}); pick one with --at-op",
+            heads.len(),
+            heads.join(", ")
+        );
+    }
+    Ok(transition.heads.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn takes_at_version_from_anywhere_in_the_command() {
+        let (version, rest) =
+            take_at_version(&args(&["tandem", "--at-version", "12", "log", "-r", "@"])).unwrap();
+        assert_eq!(version, Some(12));
+        assert_eq!(rest, args(&["tandem", "log", "-r", "@"]));
+
+        let (version, rest) = take_at_version(&args(&["tandem", "st", "--at-version=3"])).unwrap();
+        assert_eq!(version, Some(3));
+        assert_eq!(rest, args(&["tandem", "st"]));
+
+        let (version, _) = take_at_version(&args(&["tandem", "log"])).unwrap();
+        assert_eq!(version, None);
+    }
+
+    #[test]
+    fn rejects_missing_or_bad_versions() {
+        assert!(take_at_version(&args(&["tandem", "log", "--at-version"])).is_err());
+        assert!(take_at_version(&args(&["tandem", "--at-version", "yesterday"])).is_err());
+    }
+}
@@ -0,0 +1,32 @@
+//! Temporary file names for write-then-rename updates.
+//!
+//! Every store writes a file by writing a temporary next to it and renaming
+//! it into place. The temporary's name has to be unique per write, not just
+//! per process: several threads of one process may be replacing the same
+//! file at once.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Distinguishes temporary files written concurrently by one process.
+static TMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// A temporary name next to `path`, unique to this call.
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let seq = TMP_SEQ.fetch_add(1, Ordering::Relaxed);
+    path.with_extension(format!("tmp-{}-{seq}", std::process::id()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_call_gets_its_own_name_next_to_the_path() {
+        let path = Path::new("/store/objects/ab12");
+        let first = tmp_path(path);
+        let second = tmp_path(path);
+        assert_ne!(first, second);
+        assert_eq!(first.parent(), path.parent());
+    }
+}
@@ -0,0 +1,138 @@
+//! Client-side git tree ids, so snapshots can write trees without waiting.
+//!
+//! The server stores trees through jj's git backend, so a tree's id is the
+//! hash of its git encoding: SHA-1, or SHA-256 when the server advertises a
+//! SHA-256 repo (`RepoInfo.commitHash`). Computing that locally lets `write_tree` hand
+//! jj the id immediately and leave the `putObject` in flight; sibling
+//! subtrees then go out concurrently instead of one round trip each. The
+//! backend still checks every id the server returns against the local one
+//! before a commit that references the tree is written.
+
+use jj_lib::backend::{Tree, TreeValue};
+use jj_lib::object_id::ObjectId as _;
+use sha1::{Digest, Sha1};
+use sha2::Sha256;
+
+use crate::rpc::HashAlgorithm;
+
+fn mode(value: &TreeValue) -> &'static [u8] {
+    match value {
+        TreeValue::File {
+            executable: true, ..
+        } => b"100755",
+        TreeValue::File { .. } => b"100644",
+        TreeValue::Symlink(_) => b"120000",
+        TreeValue::Tree(_) => b"40000",
+        TreeValue::GitSubmodule(_) => b"160000",
+    }
+}
+
+fn id_bytes(value: &TreeValue) -> &[u8] {
+    match value {
+        TreeValue::File { id, .. } => id.as_bytes(),
+        TreeValue::Symlink(id) => id.as_bytes(),
+        TreeValue::Tree(id) => id.as_bytes(),
+        TreeValue::GitSubmodule(id) => id.as_bytes(),
+    }
+}
+
+/// The git object id the server will assign to `tree` in a repo whose
+/// objects are hashed with `algorithm` (SHA-1 unless it is SHA-256).
+pub fn git_tree_id(tree: &Tree, algorithm: HashAlgorithm) -> Vec<u8> {
+    // git orders entries as if directory names ended in '/', so "a.txt"
+    // sorts before the directory "a" even though jj lists "a" first.
+    let mut entries: Vec<(Vec<u8>, &TreeValue)> = tree
+        .entries()
+        .map(|entry| {
+            let mut key = entry.name().as_internal_str().as_bytes().to_vec();
+            if matches!(entry.value(), TreeValue::Tree(_)) {
+                key.push(b'/');
+            }
+            (key, entry.value())
+        })
+        .collect();
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let mut body = Vec::new();
+    for (mut name, value) in entries {
+        if matches!(value, TreeValue::Tree(_)) {
+            name.pop();
+        }
+        body.extend_from_slice(mode(value));
+        body.push(b' ');
+        body.extend_from_slice(&name);
+        body.push(0);
+        body.extend_from_slice(id_bytes(value));
+    }
+
+    let header = format!("tree {}\0", body.len());
+    match algorithm {
+        HashAlgorithm::Sha256 => Sha256::new()
+            .chain_update(header.as_bytes())
+            .chain_update(&body)
+            .finalize()
+            .to_vec(),
+        _ => Sha1::new()
+            .chain_update(header.as_bytes())
+            .chain_update(&body)
+            .finalize()
+            .to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use jj_lib::backend::{CopyId, FileId, SymlinkId, TreeId};
+    use jj_lib::hex_util::{decode_hex, encode_hex};
+    use jj_lib::repo_path::RepoPathComponentBuf;
+
+    use super::*;
+
+    fn name(name: &str) -> RepoPathComponentBuf {
+        RepoPathComponentBuf::new(name).unwrap()
+    }
+
+    #[test]
+    fn empty_tree_matches_git() {
+        let tree = Tree::from_sorted_entries(vec![]);
+        assert_eq!(
+            encode_hex(&git_tree_id(&tree, HashAlgorithm::Sha1)),
+            "4b825dc642cb6eb9a060e54bf8d69288fbee4904"
+        );
+    }
+
+    #[test]
+    fn empty_tree_matches_sha256_git() {
+        // `git init --object-format=sha256` names the empty tree this.
+        let tree = Tree::from_sorted_entries(vec![]);
+        assert_eq!(
+            encode_hex(&git_tree_id(&tree, HashAlgorithm::Sha256)),
+            "6ef19b41225c5369f1c104d45d8d85efa9b057b53b14b4b9b939dd74decc5321"
+        );
+    }
+
+    #[test]
+    fn mixed_tree_matches_git_mktree() {
+        // `git mktree` over the same four entries prints this id.
+        let blob = decode_hex("ce013625030ba8dba906f756967f9e9ca394464a").unwrap();
+        let empty = decode_hex("4b825dc642cb6eb9a060e54bf8d69288fbee4904").unwrap();
+        let file = |executable| TreeValue::File {
+            id: FileId::new(blob.clone()),
+            executable,
+            copy_id: CopyId::new(vec![]),
+        };
+        let tree = Tree::from_sorted_entries(vec![
+            (name("dir"), TreeValue::Tree(TreeId::new(empty))),
+            (name("dir.txt"), file(false)),
+            (
+                name("link"),
+                TreeValue::Symlink(SymlinkId::new(blob.clone())),
+            ),
+            (name("run.sh"), file(true)),
+        ]);
+        assert_eq!(
+            encode_hex(&git_tree_id(&tree, HashAlgorithm::Sha1)),
+            "378e54c96da8824164d7c5937f58b93d52f3cfac"
+        );
+    }
+}
@@ -0,0 +1,203 @@
+//! View deltas — ship a view as changes against one the peer already has.
+//!
+//! A view lists every workspace's working-copy commit, every bookmark, and
+//! every remote ref, so it grows with the team while each operation changes
+//! only a few entries. The delta works on the encoded protobuf: a view is a
+//! sequence of field records (one per head id, map entry, bookmark, ...), and
+//! the new view is rebuilt by copying runs of base records and inserting the
+//! records that changed. It knows nothing about the View schema, and the
+//! result is always checked against the view id (a content hash), so a base
+//! that differs between client and server only costs a full fetch.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+
+/// Copy base records `[copy_start, copy_start + copy_count)`, then append
+/// `insert` verbatim.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeltaOp {
+    pub copy_start: u32,
+    pub copy_count: u32,
+    pub insert: Vec<u8>,
+}
+
+/// Bytes a delta puts on the wire, roughly; used to fall back to a full view
+/// when the delta would not be smaller.
+pub fn encoded_len(ops: &[DeltaOp]) -> usize {
+    ops.iter().map(|op| op.insert.len() + 16).sum()
+}
+
+/// Split an encoded protobuf message into its top-level field records.
+fn records(data: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut out = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let start = pos;
+        let key = read_varint(data, &mut pos)?;
+        match key & 0x7 {
+            0 => {
+                read_varint(data, &mut pos)?;
+            }
+            1 => pos += 8,
+            2 => {
+                let len = usize::try_from(read_varint(data, &mut pos)?)?;
+                pos = pos.saturating_add(len);
+            }
+            5 => pos += 4,
+            wire_type => bail!("unsupported protobuf wire type {wire_type}"),
+        }
+        if pos > data.len() {
+            bail!("truncated protobuf record at byte {start}");
+        }
+        out.push(&data[start..pos]);
+    }
+    Ok(out)
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let Some(&byte) = data.get(*pos) else {
+            bail!("truncated varint");
+        };
+        *pos += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("varint too long")
+}
+
+/// Delta that turns `base` into `target`.
+pub fn diff(base: &[u8], target: &[u8]) -> Result<Vec<DeltaOp>> {
+    let base_records = records(base)?;
+    let mut positions: HashMap<&[u8], usize> = HashMap::new();
+    for (index, record) in base_records.iter().enumerate() {
+        positions.entry(*record).or_insert(index);
+    }
+
+    let mut ops: Vec<DeltaOp> = Vec::new();
+    for record in records(target)? {
+        if let Some(op) = ops.last_mut() {
+            let next = (op.copy_start + op.copy_count) as usize;
+            if op.insert.is_empty() && op.copy_count > 0 && base_records.get(next) == Some(&record)
+            {
+                op.copy_count += 1;
+                continue;
+            }
+        }
+        match positions.get(record) {
+            Some(&index) => ops.push(DeltaOp {
+                copy_start: u32::try_from(index)?,
+                copy_count: 1,
+                insert: Vec::new(),
+            }),
+            None => match ops.last_mut() {
+                Some(op) => op.insert.extend_from_slice(record),
+                None => ops.push(DeltaOp {
+                    insert: record.to_vec(),
+                    ..DeltaOp::default()
+                }),
+            },
+        }
+    }
+    Ok(ops)
+}
+
+/// Rebuild a view from `base` and a delta produced by [`diff`].
+pub fn apply(base: &[u8], ops: &[DeltaOp]) -> Result<Vec<u8>> {
+    let base_records = records(base)?;
+    let mut out = Vec::with_capacity(base.len());
+    for op in ops {
+        let start = op.copy_start as usize;
+        let end = start + op.copy_count as usize;
+        let Some(copied) = base_records.get(start..end) else {
+            bail!(
+                "delta copies records {start}..{end} but the base has {}",
+                base_records.len()
+            );
+        };
+        for record in copied {
+            out.extend_from_slice(record);
+        }
+        out.extend_from_slice(&op.insert);
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(field: u8, payload: &[u8]) -> Vec<u8> {
+        let mut out = vec![(field << 3) | 2, payload.len() as u8];
+        out.extend_from_slice(payload);
+        out
+    }
+
+    fn message(records: &[Vec<u8>]) -> Vec<u8> {
+        records.concat()
+    }
+
+    #[test]
+    fn round_trips_edits_to_a_view() {
+        let base = message(&[
+            record(1, b"head-a"),
+            record(9, b"ws-a=commit-1"),
+            record(9, b"ws-b=commit-2"),
+            record(9, b"ws-c=commit-3"),
+            record(5, b"main"),
+            vec![0x60, 0x01], // varint field
+        ]);
+        let target = message(&[
+            record(1, b"head-b"),
+            record(9, b"ws-a=commit-1"),
+            record(9, b"ws-b=commit-9"),
+            record(9, b"ws-c=commit-3"),
+            record(5, b"main"),
+            vec![0x60, 0x01],
+            record(5, b"feature"),
+        ]);
+
+        let ops = diff(&base, &target).unwrap();
+        assert_eq!(apply(&base, &ops).unwrap(), target);
+        let inserted: usize = ops.iter().map(|op| op.insert.len()).sum();
+        assert_eq!(
+            inserted,
+            record(1, b"head-b").len()
+                + record(9, b"ws-b=commit-9").len()
+                + record(5, b"feature").len(),
+            "only changed records should be sent: {ops:?}"
+        );
+    }
+
+    #[test]
+    fn identical_views_are_one_copy() {
+        let view = message(&[record(1, b"a"), record(1, b"b"), record(9, b"c")]);
+        let ops = diff(&view, &view).unwrap();
+        assert_eq!(
+            ops,
+            vec![DeltaOp {
+                copy_start: 0,
+                copy_count: 3,
+                insert: Vec::new()
+            }]
+        );
+        assert_eq!(apply(&view, &ops).unwrap(), view);
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        let base = message(&[record(1, b"a")]);
+        let out_of_range = [DeltaOp {
+            copy_start: 0,
+            copy_count: 2,
+            insert: Vec::new(),
+        }];
+        assert!(apply(&base, &out_of_range).is_err());
+        assert!(records(&[0x0a, 0x05, b'a']).is_err());
+        assert!(records(&[0x0b]).is_err());
+    }
+}
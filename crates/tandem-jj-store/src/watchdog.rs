@@ -0,0 +1,390 @@
+//! RPC watchdog — a stuck handler fails its call instead of holding it forever.
+//!
+//! `tandem serve --rpc-timeout <duration>` (default [`DEFAULT_TIMEOUT`], `0`
+//! to turn it off) bounds how long one store call may run. A call still
+//! running at the limit is dropped and fails with
+//!
+//!   tandem rpc timed out: getObject ran for 120s on the server; retry, ...
+//!
+//! which clients show as-is, so a pathological request costs its caller an
+//! error rather than pinning the connection and everything queued behind it.
+//!
+//! Time a handler spends waiting by design — for a snapshot hold to end, or
+//! for its turn in the fair queue — is [`parked`] and does not count.
+//!
+//! The server runs its handlers on one thread, and most of them do their
+//! storage work synchronously. A handler blocked inside a syscall (a hung
+//! NFS mount, say) cannot be dropped: nothing else runs until it returns. A
+//! monitor thread notices that, logs the call at error level once it passes
+//! the limit, and counts it as blocked, so the log explains a frozen server
+//! even though the call itself cannot be cut short.
+//!
+//! Both counts, by method, are in `tandem server status`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+use capnp::any_pointer;
+use capnp::capability::{DispatchCallResult, Params, Promise, Results, Server};
+use serde::{Deserialize, Serialize};
+
+/// Every watchdog failure starts with this; clients match on it.
+pub const TIMEOUT_PREFIX: &str = "tandem rpc timed out";
+
+/// Limit used when `--rpc-timeout` is not given.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
+/// How often the monitor thread looks for blocked calls.
+const MONITOR_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Calls the watchdog gave up on, for `tandem server status`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchdogReport {
+    pub timeout_secs: u64,
+    /// Calls dropped at the limit, by method.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub timed_out: BTreeMap<String, u64>,
+    /// Calls that held the server thread past the limit, by method.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocked: BTreeMap<String, u64>,
+}
+
+impl WatchdogReport {
+    pub fn describe(&self) -> String {
+        let counts = |counts: &BTreeMap<String, u64>| {
+            counts
+                .iter()
+                .map(|(method, count)| format!("{method} {count}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut text = format!("{}s limit", self.timeout_secs);
+        let timed_out: u64 = self.timed_out.values().sum();
+        if timed_out > 0 {
+            text.push_str(&format!(
+                "; {timed_out} timed out ({})",
+                counts(&self.timed_out)
+            ));
+        }
+        let blocked: u64 = self.blocked.values().sum();
+        if blocked > 0 {
+            text.push_str(&format!("; {blocked} blocked ({})", counts(&self.blocked)));
+        }
+        text
+    }
+}
+
+/// Watchdog state shared by every connection of one server and the control
+/// socket.
+#[derive(Debug, Clone)]
+pub struct Watchdog(Arc<WatchdogState>);
+
+#[derive(Debug)]
+struct WatchdogState {
+    timeout: Duration,
+    inner: Mutex<Calls>,
+}
+
+#[derive(Debug, Default)]
+struct Calls {
+    next_id: u64,
+    running: HashMap<u64, Call>,
+    timed_out: BTreeMap<&'static str, u64>,
+    blocked: BTreeMap<&'static str, u64>,
+}
+
+#[derive(Debug)]
+struct Call {
+    method: &'static str,
+    started: Instant,
+    /// Time spent in [`parked`] waits that have ended.
+    parked: Duration,
+    /// Start of the [`parked`] wait in progress, if any.
+    parked_since: Option<Instant>,
+    /// The monitor thread has already logged this call.
+    reported: bool,
+}
+
+impl Call {
+    /// How long the call has been running, minus its parked waits.
+    fn active(&self, now: Instant) -> Duration {
+        let parked = self.parked
+            + self
+                .parked_since
+                .map_or(Duration::ZERO, |since| now.duration_since(since));
+        now.duration_since(self.started).saturating_sub(parked)
+    }
+}
+
+tokio::task_local! {
+    static CURRENT_CALL: (Watchdog, u64);
+}
+
+impl Watchdog {
+    /// A watchdog dropping calls after `timeout`, or `None` when it is zero.
+    /// Starts the monitor thread, which exits once the watchdog is dropped.
+    pub fn start(timeout: Duration) -> Option<Self> {
+        if timeout.is_zero() {
+            return None;
+        }
+        let watchdog = Self(Arc::new(WatchdogState {
+            timeout,
+            inner: Mutex::new(Calls::default()),
+        }));
+        let weak = Arc::downgrade(&watchdog.0);
+        std::thread::Builder::new()
+            .name("tandem-rpc-watchdog".to_string())
+            .spawn(move || monitor(weak))
+            .ok()?;
+        Some(watchdog)
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.0.timeout
+    }
+
+    pub fn report(&self) -> WatchdogReport {
+        let calls = self.0.inner.lock().unwrap();
+        let owned = |counts: &BTreeMap<&'static str, u64>| {
+            counts
+                .iter()
+                .map(|(method, count)| (method.to_string(), *count))
+                .collect()
+        };
+        WatchdogReport {
+            timeout_secs: self.0.timeout.as_secs(),
+            timed_out: owned(&calls.timed_out),
+            blocked: owned(&calls.blocked),
+        }
+    }
+
+    fn begin(&self, method: &'static str) -> u64 {
+        let mut calls = self.0.inner.lock().unwrap();
+        let id = calls.next_id;
+        calls.next_id += 1;
+        calls.running.insert(
+            id,
+            Call {
+                method,
+                started: Instant::now(),
+                parked: Duration::ZERO,
+                parked_since: None,
+                reported: false,
+            },
+        );
+        id
+    }
+
+    fn end(&self, id: u64) {
+        self.0.inner.lock().unwrap().running.remove(&id);
+    }
+
+    /// How much longer call `id` may run before it is dropped; `None` once
+    /// it is over the limit.
+    fn remaining(&self, id: u64) -> Option<Duration> {
+        let calls = self.0.inner.lock().unwrap();
+        let call = calls.running.get(&id)?;
+        if call.parked_since.is_some() {
+            // Parked waits do not count; look again a limit from now.
+            return Some(self.0.timeout);
+        }
+        self.0
+            .timeout
+            .checked_sub(call.active(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    fn timed_out(&self, id: u64) {
+        let mut calls = self.0.inner.lock().unwrap();
+        if let Some(call) = calls.running.remove(&id) {
+            *calls.timed_out.entry(call.method).or_default() += 1;
+        }
+    }
+
+    fn set_parked(&self, id: u64, parked: bool) {
+        let mut calls = self.0.inner.lock().unwrap();
+        if let Some(call) = calls.running.get_mut(&id) {
+            let now = Instant::now();
+            match (parked, call.parked_since) {
+                (true, None) => call.parked_since = Some(now),
+                (false, Some(since)) => {
+                    call.parked += now.duration_since(since);
+                    call.parked_since = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Wrap a store's dispatch so every call is bounded by the watchdog.
+    /// `methods` names the interface's methods by ordinal.
+    pub fn serve<S>(&self, inner: S, methods: &'static [&'static str]) -> WatchedStore<S> {
+        WatchedStore {
+            inner,
+            watchdog: self.clone(),
+            methods,
+        }
+    }
+}
+
+/// Await `wait` without it counting towards the current call's limit. Use it
+/// for waits that are part of the protocol (snapshot holds, fair queue
+/// turns), not for work.
+pub async fn parked<F: Future>(wait: F) -> F::Output {
+    let Ok((watchdog, id)) = CURRENT_CALL.try_with(|call| call.clone()) else {
+        return wait.await;
+    };
+    watchdog.set_parked(id, true);
+    let output = wait.await;
+    watchdog.set_parked(id, false);
+    output
+}
+
+/// The error a call dropped by the watchdog fails with.
+pub fn timeout_error(method: &str, timeout: Duration) -> capnp::Error {
+    capnp::Error::overloaded(format!(
+        "{TIMEOUT_PREFIX}: {method} ran for {}s on the server; retry, and tell the \
+         server operator if it keeps happening",
+        timeout.as_secs()
+    ))
+}
+
+/// Extract the watchdog message from a remote error description, dropping
+/// any RPC framing in front of it.
+pub fn message_from_error(description: &str) -> Option<&str> {
+    description
+        .find(TIMEOUT_PREFIX)
+        .map(|start| description[start..].trim_end())
+}
+
+/// A store whose calls fail with [`timeout_error`] past the watchdog's limit.
+pub struct WatchedStore<S> {
+    inner: S,
+    watchdog: Watchdog,
+    methods: &'static [&'static str],
+}
+
+impl<S: Server> Server for WatchedStore<S> {
+    fn dispatch_call(
+        &mut self,
+        interface_id: u64,
+        method_id: u16,
+        params: Params<any_pointer::Owned>,
+        results: Results<any_pointer::Owned>,
+    ) -> DispatchCallResult {
+        let method = self
+            .methods
+            .get(usize::from(method_id))
+            .copied()
+            .unwrap_or("unknown");
+        let watchdog = self.watchdog.clone();
+        let id = watchdog.begin(method);
+        // Handlers that block the thread do it in here; the monitor thread
+        // is what sees those.
+        let call = CURRENT_CALL.sync_scope((watchdog.clone(), id), || {
+            self.inner
+                .dispatch_call(interface_id, method_id, params, results)
+        });
+        let is_streaming = call.is_streaming;
+        let mut promise = Box::pin(CURRENT_CALL.scope((watchdog.clone(), id), call.promise));
+        DispatchCallResult::new(
+            Promise::from_future(async move {
+                loop {
+                    let remaining = watchdog.remaining(id).unwrap_or(Duration::ZERO);
+                    // A result that is ready wins over the limit: the work is
+                    // already done, so failing the call would only waste it.
+                    tokio::select! {
+                        biased;
+                        result = &mut promise => {
+                            watchdog.end(id);
+                            return result;
+                        }
+                        _ = tokio::time::sleep(remaining) => {}
+                    }
+                    if watchdog.remaining(id).is_none() {
+                        watchdog.timed_out(id);
+                        let timeout = watchdog.timeout();
+                        tracing::error!(
+                            rpc = method,
+                            timeout_secs = timeout.as_secs(),
+                            "rpc handler timed out; dropping it"
+                        );
+                        return Err(timeout_error(method, timeout));
+                    }
+                }
+            }),
+            is_streaming,
+        )
+    }
+}
+
+/// Log and count calls that keep the server thread busy past the limit.
+fn monitor(state: Weak<WatchdogState>) {
+    loop {
+        std::thread::sleep(MONITOR_INTERVAL);
+        let Some(state) = state.upgrade() else {
+            return;
+        };
+        let now = Instant::now();
+        // A call the async timeout could drop is gone by the time it is a
+        // monitor tick past the limit; one still here is holding the thread.
+        let limit = state.timeout + MONITOR_INTERVAL;
+        let mut calls = state.inner.lock().unwrap();
+        let mut blocked = Vec::new();
+        for call in calls.running.values_mut() {
+            if !call.reported && call.parked_since.is_none() && call.active(now) > limit {
+                call.reported = true;
+                blocked.push((call.method, call.active(now)));
+            }
+        }
+        for (method, active) in blocked {
+            *calls.blocked.entry(method).or_default() += 1;
+            tracing::error!(
+                rpc = method,
+                running_secs = active.as_secs(),
+                "rpc handler is blocking the server thread; nothing else is served until it returns"
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parked_time_does_not_count() {
+        let watchdog = Watchdog::start(Duration::from_secs(60)).unwrap();
+        let id = watchdog.begin("updateOpHeads");
+        watchdog.set_parked(id, true);
+        assert_eq!(watchdog.remaining(id), Some(Duration::from_secs(60)));
+        watchdog.set_parked(id, false);
+        assert!(watchdog.remaining(id).unwrap() <= Duration::from_secs(60));
+
+        watchdog.timed_out(id);
+        assert_eq!(watchdog.remaining(id), None);
+        let report = watchdog.report();
+        assert_eq!(report.timed_out.get("updateOpHeads"), Some(&1));
+        assert_eq!(
+            report.describe(),
+            "60s limit; 1 timed out (updateOpHeads 1)"
+        );
+
+        assert!(Watchdog::start(Duration::ZERO).is_none());
+    }
+
+    #[test]
+    fn finds_the_message_in_remote_errors() {
+        let err = timeout_error("getObject", Duration::from_secs(120));
+        let description = format!("remote exception: {}\n", err.extra);
+        let message = message_from_error(&description).unwrap();
+        assert!(
+            message.starts_with("tandem rpc timed out: getObject ran for 120s"),
+            "{message}"
+        );
+        assert_eq!(message_from_error("some other failure"), None);
+    }
+}
@@ -0,0 +1,285 @@
+//! Python bindings for the tandem client.
+//!
+//! A thin wrapper over [`tandem_client::TandemClient`] for orchestration
+//! scripts that would otherwise shell out to `tandem` and parse its text:
+//!
+//! ```python
+//! import tandem
+//!
+//! client = tandem.Client("127.0.0.1:13013")
+//! heads = client.get_heads()
+//! for change in client.watch(heads["version"]):
+//!     print(change["version"], change["heads"])
+//! ```
+//!
+//! Ids are hex strings, object contents are `bytes`, and structured replies
+//! are plain dicts. Calls release the GIL while they wait on the server.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use tandem_client::{ObjectKind, TandemClient, WatchEvent};
+use tokio::runtime::Runtime;
+
+create_exception!(
+    tandem,
+    TandemError,
+    PyException,
+    "A tandem server refused or failed a request."
+);
+
+/// How often a blocked `watch` iteration wakes up to let Ctrl-C through.
+const SIGNAL_POLL: Duration = Duration::from_millis(200);
+
+fn tandem_error(err: anyhow::Error) -> PyErr {
+    TandemError::new_err(format!("{err:#}"))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> PyResult<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(PyValueError::new_err(format!("invalid hex id {hex:?}")));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .map_err(|_| PyValueError::new_err(format!("invalid hex id {hex:?}")))
+        })
+        .collect()
+}
+
+fn parse_kind(kind: &str) -> PyResult<ObjectKind> {
+    match kind {
+        "commit" => Ok(ObjectKind::Commit),
+        "tree" => Ok(ObjectKind::Tree),
+        "file" => Ok(ObjectKind::File),
+        "symlink" => Ok(ObjectKind::Symlink),
+        "copy" => Ok(ObjectKind::Copy),
+        _ => Err(PyValueError::new_err(format!(
+            "unknown object kind {kind:?}; expected commit, tree, file, symlink, or copy"
+        ))),
+    }
+}
+
+/// A connection to a tandem server.
+#[pyclass(module = "tandem", frozen)]
+struct Client {
+    inner: TandemClient,
+    runtime: Arc<Runtime>,
+}
+
+#[pymethods]
+impl Client {
+    /// Connect to `endpoint` (`host:port`, `tcp://host:port`, or
+    /// `unix:///path`).
+    #[new]
+    fn new(py: Python<'_>, endpoint: &str) -> PyResult<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()?;
+        let inner = py
+            .allow_threads(|| runtime.block_on(TandemClient::connect(endpoint)))
+            .map_err(tandem_error)?;
+        Ok(Self {
+            inner,
+            runtime: Arc::new(runtime),
+        })
+    }
+
+    #[getter]
+    fn endpoint(&self) -> &str {
+        self.inner.endpoint()
+    }
+
+    /// What the server said about its repo when the client connected.
+    fn repo_info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let info = self.inner.repo_info();
+        let dict = PyDict::new(py);
+        dict.set_item("protocol_major", info.protocol_major)?;
+        dict.set_item("protocol_minor", info.protocol_minor)?;
+        dict.set_item("jj_version", info.jj_version.as_deref())?;
+        dict.set_item("tandem_version", info.tandem_version.as_deref())?;
+        dict.set_item("backend_name", &info.backend_name)?;
+        dict.set_item("op_store_name", &info.op_store_name)?;
+        dict.set_item("commit_hash", &info.commit_hash)?;
+        dict.set_item("operation_hash", &info.operation_hash)?;
+        dict.set_item("root_operation_id", to_hex(&info.root_operation_id))?;
+        let capabilities: Vec<&str> = info.capabilities.iter().map(|c| c.as_str()).collect();
+        dict.set_item("capabilities", capabilities)?;
+        dict.set_item("redirect", info.redirect.as_deref())?;
+        dict.set_item("git_remote", info.git_remote.as_deref())?;
+        dict.set_item("snapshot_auto_track", info.snapshot_auto_track.as_deref())?;
+        Ok(dict)
+    }
+
+    /// `{"version": int, "heads": [hex], "workspace_heads": {name: hex}}`.
+    fn get_heads<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let state = py
+            .allow_threads(|| self.runtime.block_on(self.inner.get_heads()))
+            .map_err(tandem_error)?;
+        let dict = PyDict::new(py);
+        dict.set_item("version", state.version)?;
+        let heads: Vec<String> = state.heads.iter().map(|head| to_hex(head)).collect();
+        dict.set_item("heads", heads)?;
+        let workspace_heads = PyDict::new(py);
+        for (workspace, op_id) in &state.workspace_heads {
+            workspace_heads.set_item(workspace, to_hex(op_id))?;
+        }
+        dict.set_item("workspace_heads", workspace_heads)?;
+        Ok(dict)
+    }
+
+    /// One row per workspace: its name, the operation it last published,
+    /// and whether that operation is still one of the heads.
+    fn workspace_stats<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyList>> {
+        let state = py
+            .allow_threads(|| self.runtime.block_on(self.inner.get_heads()))
+            .map_err(tandem_error)?;
+        let rows = PyList::empty(py);
+        for (workspace, op_id) in &state.workspace_heads {
+            let row = PyDict::new(py);
+            row.set_item("workspace", workspace)?;
+            row.set_item("operation", to_hex(op_id))?;
+            row.set_item("at_head", state.heads.contains(op_id))?;
+            rows.append(row)?;
+        }
+        Ok(rows)
+    }
+
+    /// The stored bytes of object `id` (hex) of `kind` (`"commit"`,
+    /// `"tree"`, `"file"`, `"symlink"`, or `"copy"`).
+    fn get_object<'py>(
+        &self,
+        py: Python<'py>,
+        kind: &str,
+        id: &str,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let kind = parse_kind(kind)?;
+        let id = from_hex(id)?;
+        let data = py
+            .allow_threads(|| self.runtime.block_on(self.inner.get_object(kind, &id)))
+            .map_err(tandem_error)?;
+        Ok(PyBytes::new(py, &data))
+    }
+
+    /// Store `data` as an object of `kind` and return its id (hex).
+    fn put_object(&self, py: Python<'_>, kind: &str, data: &[u8]) -> PyResult<String> {
+        let kind = parse_kind(kind)?;
+        let data = data.to_vec();
+        let id = py
+            .allow_threads(|| self.runtime.block_on(self.inner.put_object(kind, data)))
+            .map_err(tandem_error)?;
+        Ok(to_hex(&id))
+    }
+
+    /// Iterate over head changes after `after_version`; 0 starts with the
+    /// current heads. Iteration stops when the connection drops, or after a
+    /// `{"shutting_down": ...}` item when the server shuts down gracefully.
+    #[pyo3(signature = (after_version = 0))]
+    fn watch(&self, py: Python<'_>, after_version: u64) -> PyResult<HeadsWatch> {
+        let inner = py
+            .allow_threads(|| self.runtime.block_on(self.inner.watch(after_version)))
+            .map_err(tandem_error)?;
+        Ok(HeadsWatch {
+            inner,
+            runtime: self.runtime.clone(),
+        })
+    }
+
+    fn __repr__(&self) -> String {
+        format!("tandem.Client({:?})", self.inner.endpoint())
+    }
+}
+
+/// Iterator over head changes, from `Client.watch`.
+#[pyclass(module = "tandem")]
+struct HeadsWatch {
+    inner: tandem_client::HeadsWatch,
+    runtime: Arc<Runtime>,
+}
+
+#[pymethods]
+impl HeadsWatch {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'py>(&mut self, py: Python<'py>) -> PyResult<Option<Bound<'py, PyDict>>> {
+        loop {
+            let next = py.allow_threads(|| {
+                self.runtime
+                    .block_on(async { tokio::time::timeout(SIGNAL_POLL, self.inner.next()).await })
+            });
+            let event = match next {
+                Ok(Some(event)) => event,
+                Ok(None) => return Ok(None),
+                Err(_) => {
+                    py.check_signals()?;
+                    continue;
+                }
+            };
+            let dict = PyDict::new(py);
+            match event {
+                WatchEvent::Changed(change) => {
+                    dict.set_item("version", change.version)?;
+                    let heads: Vec<String> = change.heads.iter().map(|head| to_hex(head)).collect();
+                    dict.set_item("heads", heads)?;
+                    dict.set_item("tags", change.tags)?;
+                }
+                WatchEvent::ShuttingDown {
+                    retry_after,
+                    message,
+                } => {
+                    dict.set_item("shutting_down", message)?;
+                    dict.set_item("retry_after", retry_after.as_secs_f64())?;
+                }
+            }
+            return Ok(Some(dict));
+        }
+    }
+}
+
+#[pymodule]
+fn tandem(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Client>()?;
+    m.add_class::<HeadsWatch>()?;
+    m.add("TandemError", m.py().get_type::<TandemError>())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_ids_round_trip() {
+        let id = vec![0x00, 0x7f, 0xab, 0xff];
+        assert_eq!(to_hex(&id), "007fabff");
+        assert_eq!(from_hex("007fabff").unwrap(), id);
+        assert_eq!(from_hex("007FABFF").unwrap(), id);
+    }
+
+    #[test]
+    fn bad_ids_and_kinds_are_value_errors() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            for err in [
+                from_hex("abc").unwrap_err(),
+                from_hex("zz").unwrap_err(),
+                parse_kind("blob").unwrap_err(),
+            ] {
+                assert!(err.is_instance_of::<PyValueError>(py), "{err}");
+            }
+        });
+        assert_eq!(parse_kind("tree").unwrap(), ObjectKind::Tree);
+    }
+}
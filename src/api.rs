@@ -1 +1,191 @@
-pub fn handle_request(req: Request) -> Response { todo!() }
+//! Read-only JSON API for dashboards.
+//!
+//! Dashboards that show what a swarm of workspaces is doing should not need
+//! a Cap'n Proto client or a `tandem gateway` in front of the server. With
+//! `tandem serve --http-listen <addr>` the server answers, next to the file
+//! content endpoints (`content_http.rs`):
+//!
+//!   GET /api/workspaces  →  each workspace's working-copy commit and head
+//!   GET /api/heads       →  `{"version", "heads", "workspaceHeads"}`
+//!   GET /api/stats       →  counters: heads version, workspaces, connections
+//!
+//! Ids are hex, change ids in jj's reverse-hex form. Answers come from the
+//! server's own task set, like the RPCs, so they match what clients see.
+//! Errors are `{"error": "..."}` with a 4xx/5xx status. Responses allow any
+//! origin, so a dashboard page can fetch them directly; like the RPC port,
+//! there is no authentication, so bind it where only trusted readers reach
+//! it.
+
+use std::collections::BTreeMap;
+
+use anyhow::{Context, Result};
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::{ReadonlyRepo, Repo as _};
+use serde::Serialize;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::ephemeral::EphemeralWorkspace;
+use crate::http::Reply;
+use crate::maintenance::MaintenanceNotice;
+use crate::status_file::ConnectionStats;
+
+/// What a request asks for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiTarget {
+    Workspaces,
+    Heads,
+    Stats,
+}
+
+/// Parse a request path into a target; `None` for anything not served.
+pub fn parse_target(path: &str) -> Option<ApiTarget> {
+    let path = path.split(['?', '#']).next().unwrap_or("");
+    match path.trim_end_matches('/') {
+        "/api/workspaces" => Some(ApiTarget::Workspaces),
+        "/api/heads" => Some(ApiTarget::Heads),
+        "/api/stats" => Some(ApiTarget::Stats),
+        _ => None,
+    }
+}
+
+/// API request handed to the RPC server, which owns the repo.
+pub struct ApiRequest {
+    pub target: ApiTarget,
+    pub reply: oneshot::Sender<Reply>,
+}
+
+/// `GET /api/heads`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Heads {
+    pub version: u64,
+    /// Operation ids.
+    pub heads: Vec<String>,
+    /// Workspace → the operation its last head update published.
+    pub workspace_heads: BTreeMap<String, String>,
+}
+
+/// `GET /api/workspaces`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspaces {
+    pub heads_version: u64,
+    /// The operation the working copies were read at.
+    pub operation: String,
+    pub workspaces: Vec<Workspace>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    pub name: String,
+    pub commit_id: String,
+    pub change_id: String,
+    /// First line of the working-copy commit's description.
+    pub description: String,
+    pub author: String,
+    pub committed_at_millis: i64,
+    /// The operation this workspace's last head update published.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation: Option<String>,
+    /// Unix seconds at which an ephemeral workspace is forgotten.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+/// `GET /api/stats`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Stats {
+    pub version: String,
+    pub uptime_secs: u64,
+    pub heads_version: u64,
+    pub op_heads: usize,
+    /// Workspaces with a recorded head.
+    pub workspaces: usize,
+    pub ephemeral_workspaces: usize,
+    pub connections: ConnectionStats,
+    /// Live `watchHeads` subscriptions.
+    pub watchers: usize,
+    /// A read replica (`serve --follow`) refuses writes.
+    pub replica: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceNotice>,
+}
+
+/// Every workspace with a working copy in `repo`'s view, by name.
+pub fn workspaces(
+    repo: &ReadonlyRepo,
+    heads_version: u64,
+    workspace_heads: &BTreeMap<String, String>,
+    ephemeral: &BTreeMap<String, EphemeralWorkspace>,
+) -> Result<Workspaces> {
+    let mut workspaces = Vec::new();
+    for (name, commit_id) in repo.view().wc_commit_ids() {
+        let commit = repo
+            .store()
+            .get_commit(commit_id)
+            .with_context(|| format!("read commit {}", commit_id.hex()))?;
+        let name = name.as_str().to_string();
+        workspaces.push(Workspace {
+            commit_id: commit_id.hex(),
+            change_id: jj_lib::hex_util::encode_reverse_hex(commit.change_id().as_bytes()),
+            description: commit
+                .description()
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            author: commit.author().email.clone(),
+            committed_at_millis: commit.committer().timestamp.timestamp.0,
+            operation: workspace_heads.get(&name).cloned(),
+            expires_at: ephemeral.get(&name).map(|entry| entry.expires_at),
+            name,
+        });
+    }
+    Ok(Workspaces {
+        heads_version,
+        operation: repo.op_id().hex(),
+        workspaces,
+    })
+}
+
+// ─── HTTP ────────────────────────────────────────────────────────────
+
+/// Ask the RPC server for `target`. Replies may be fetched from any origin
+/// and are never cached.
+pub async fn request(tx: &mpsc::Sender<ApiRequest>, target: ApiTarget) -> Reply {
+    let (reply, rx) = oneshot::channel();
+    let reply = if tx.send(ApiRequest { target, reply }).await.is_err() {
+        Reply::error("503 Service Unavailable", "server is shutting down")
+    } else {
+        rx.await.unwrap_or_else(|_| {
+            Reply::error("503 Service Unavailable", "server dropped the request")
+        })
+    };
+    reply
+        .with_header("Access-Control-Allow-Origin", "*")
+        .with_header("Cache-Control", "no-store")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_api_paths() {
+        assert_eq!(parse_target("/api/workspaces"), Some(ApiTarget::Workspaces));
+        assert_eq!(parse_target("/api/heads/?pretty=1"), Some(ApiTarget::Heads));
+        assert_eq!(parse_target("/api/stats"), Some(ApiTarget::Stats));
+        assert_eq!(parse_target("/api"), None);
+        assert_eq!(parse_target("/api/objects/commit/ab"), None);
+        assert_eq!(parse_target("/healthz"), None);
+    }
+
+    #[test]
+    fn errors_are_json() {
+        let reply = Reply::error("404 Not Found", "not found");
+        let body: serde_json::Value = serde_json::from_slice(&reply.body).unwrap();
+        assert_eq!(body["error"], "not found");
+    }
+}
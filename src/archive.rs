@@ -0,0 +1,418 @@
+//! tandem archive — a clean tree of any revision as a tar archive.
+//!
+//! CI jobs that need the sources of one revision should not have to create
+//! a workspace and check it out. `tandem archive -r <rev> -o out.tar.gz`
+//! resolves the revision against the server's current heads and reads the
+//! commit's tree straight from the object store, keeping up to
+//! [`IN_FLIGHT`] `getObject` calls pipelined on the one connection so a
+//! directory level costs about one round trip rather than one per entry.
+//!
+//! `<rev>` is a full commit id, a bookmark name, or `<workspace>@` for a
+//! workspace's working-copy commit. The format follows the output name:
+//! `.tar`, or `.tar.gz`/`.tgz` for a gzip-framed tar. `-o -` writes a plain
+//! tar to stdout. Entries are stored at the top level with the committer
+//! timestamp as their mtime. The encoder here is shared with the server's
+//! `/archive` HTTP endpoint (`src/content_http.rs`); like it, the gzip
+//! framing uses stored blocks, since tandem carries no compression library.
+
+use std::collections::BTreeSet;
+use std::io::Write as _;
+
+use anyhow::{anyhow, bail, Context, Result};
+use jj_lib::backend::{CommitId, TreeValue};
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::ref_name::{RefName, WorkspaceNameBuf};
+use prost::Message as _;
+
+use crate::proto_convert;
+use crate::ps;
+use crate::rpc::TandemClient;
+
+const KIND_TREE: u16 = 1;
+const KIND_FILE: u16 = 2;
+const KIND_SYMLINK: u16 = 3;
+/// `getObject` calls kept in flight, matching the backend's concurrency.
+const IN_FLIGHT: usize = 64;
+
+/// A tree entry waiting for its object.
+enum Pending {
+    File {
+        path: String,
+        id: Vec<u8>,
+        executable: bool,
+    },
+    Symlink {
+        path: String,
+        id: Vec<u8>,
+    },
+    Dir {
+        path: String,
+    },
+}
+
+// ─── Public entry point ───────────────────────────────────────────────────────
+
+pub fn run_archive(server_addr: &str, rev: &str, output: &str) -> Result<()> {
+    let gzip = if output == "-" || output.ends_with(".tar") {
+        false
+    } else if output.ends_with(".tar.gz") || output.ends_with(".tgz") {
+        true
+    } else {
+        bail!("cannot tell the archive format from {output:?}; name it .tar, .tar.gz or .tgz");
+    };
+    let client = TandemClient::connect(server_addr)
+        .with_context(|| format!("cannot read revisions on {server_addr}"))?;
+
+    let commit_id = resolve_rev(&client, rev)?;
+    let (tar, files) = build_tar(&client, &commit_id)?;
+    let data = if gzip { gzip_stored(&tar) } else { tar };
+    if output == "-" {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(&data).context("write archive to stdout")?;
+        stdout.flush().context("write archive to stdout")?;
+    } else {
+        std::fs::write(output, &data).with_context(|| format!("cannot write {output}"))?;
+        println!(
+            "wrote {output}: {files} file(s) from commit {} ({} bytes)",
+            &commit_id.hex()[..12],
+            data.len()
+        );
+    }
+    Ok(())
+}
+
+// ─── Revision resolution ──────────────────────────────────────────────────────
+
+/// Resolve `rev` against the server's current op heads: a full commit id, a
+/// bookmark, or `<workspace>@`.
+fn resolve_rev(client: &TandemClient, rev: &str) -> Result<CommitId> {
+    let id_length = client.repo_info().root_commit_id.len();
+    if rev.len() == id_length * 2 {
+        if let Some(id) = CommitId::try_from_hex(rev) {
+            return Ok(id);
+        }
+    }
+
+    let state = client.get_heads_state()?;
+    let mut targets = BTreeSet::new();
+    for op_id in &state.heads {
+        let view = ps::read_view(client, op_id)?;
+        let target = match rev.strip_suffix('@') {
+            Some(workspace) => view
+                .wc_commit_ids
+                .get(&WorkspaceNameBuf::from(workspace.to_string()))
+                .cloned()
+                .map(Some),
+            None => {
+                let name: &RefName = rev.as_ref();
+                view.local_bookmarks
+                    .get(name)
+                    .map(|target| target.as_normal().cloned())
+            }
+        };
+        match target {
+            Some(Some(id)) => {
+                targets.insert(id);
+            }
+            Some(None) => bail!("bookmark {rev} is conflicted"),
+            None => {}
+        }
+    }
+    let mut targets = targets.into_iter();
+    match (targets.next(), targets.next()) {
+        (Some(id), None) => Ok(id),
+        (Some(_), Some(_)) => bail!("{rev} points at different commits in divergent op heads"),
+        (None, _) if rev.ends_with('@') => bail!("no workspace {}", rev.trim_end_matches('@')),
+        (None, _) => bail!("no commit or bookmark {rev}"),
+    }
+}
+
+// ─── Tree walk ────────────────────────────────────────────────────────────────
+
+/// The tar of `commit_id`'s tree and the number of files in it.
+fn build_tar(client: &TandemClient, commit_id: &CommitId) -> Result<(Vec<u8>, usize)> {
+    let mut tar = TarBuilder::default();
+    if commit_id.as_bytes() == client.repo_info().root_commit_id.as_slice() {
+        return Ok((tar.finish(), 0));
+    }
+    let commit = ps::read_commit(client, commit_id)
+        .with_context(|| format!("cannot read commit {}", commit_id.hex()))?;
+    let root_tree = match commit.root_tree.as_slice() {
+        [tree_id] => tree_id.clone(),
+        _ => bail!("commit {} has conflicts", commit_id.hex()),
+    };
+    let mtime = commit
+        .committer
+        .as_ref()
+        .and_then(|signature| signature.timestamp.as_ref())
+        .map(|timestamp| u64::try_from(timestamp.millis_since_epoch / 1000).unwrap_or(0))
+        .unwrap_or(0);
+
+    // Walk the tree one directory level at a time, fetching each level's
+    // trees together.
+    let mut entries = Vec::new();
+    let mut level = vec![(String::new(), root_tree)];
+    while !level.is_empty() {
+        let ids: Vec<&[u8]> = level.iter().map(|(_, id)| id.as_slice()).collect();
+        let trees = get_objects(client, KIND_TREE, &ids)?;
+        let mut next = Vec::new();
+        for ((prefix, _), data) in level.iter().zip(trees) {
+            let proto =
+                jj_lib::protos::simple_store::Tree::decode(&*data).context("decoding tree")?;
+            for entry in proto_convert::tree_from_proto(proto).entries() {
+                let path = format!("{prefix}{}", entry.name().as_internal_str());
+                match entry.value() {
+                    TreeValue::Tree(id) => {
+                        let dir = format!("{path}/");
+                        entries.push(Pending::Dir { path: dir.clone() });
+                        next.push((dir, id.to_bytes()));
+                    }
+                    TreeValue::File { id, executable, .. } => entries.push(Pending::File {
+                        path,
+                        id: id.to_bytes(),
+                        executable: *executable,
+                    }),
+                    TreeValue::Symlink(id) => entries.push(Pending::Symlink {
+                        path,
+                        id: id.to_bytes(),
+                    }),
+                    TreeValue::GitSubmodule(_) => entries.push(Pending::Dir {
+                        path: format!("{path}/"),
+                    }),
+                }
+            }
+        }
+        level = next;
+    }
+    entries.sort_by(|a, b| a.path().cmp(b.path()));
+
+    let mut files = 0;
+    for chunk in entries.chunks(IN_FLIGHT) {
+        let pending = chunk
+            .iter()
+            .map(|entry| match entry {
+                Pending::File { id, .. } => client.begin_get_object(KIND_FILE, id).map(Some),
+                Pending::Symlink { id, .. } => client.begin_get_object(KIND_SYMLINK, id).map(Some),
+                Pending::Dir { .. } => Ok(None),
+            })
+            .collect::<Result<Vec<_>>>()?;
+        for (entry, reply) in chunk.iter().zip(pending) {
+            let data = match reply {
+                Some(reply) => reply.recv().map_err(|_| anyhow!("RPC reply dropped"))??,
+                None => Vec::new(),
+            };
+            match entry {
+                Pending::File {
+                    path, executable, ..
+                } => {
+                    let mode = if *executable { 0o755 } else { 0o644 };
+                    tar.append(path, TarEntry::File { mode, data: &data }, mtime);
+                    files += 1;
+                }
+                Pending::Symlink { path, .. } => {
+                    let target = String::from_utf8(data)
+                        .with_context(|| format!("symlink {path} is not UTF-8"))?;
+                    tar.append(path, TarEntry::Symlink(&target), mtime);
+                }
+                Pending::Dir { path } => tar.append(path, TarEntry::Dir, mtime),
+            }
+        }
+    }
+    Ok((tar.finish(), files))
+}
+
+impl Pending {
+    fn path(&self) -> &str {
+        match self {
+            Pending::File { path, .. } | Pending::Symlink { path, .. } | Pending::Dir { path } => {
+                path
+            }
+        }
+    }
+}
+
+/// Fetch `ids` with up to [`IN_FLIGHT`] requests outstanding, in order.
+fn get_objects(client: &TandemClient, kind: u16, ids: &[&[u8]]) -> Result<Vec<Vec<u8>>> {
+    let mut objects = Vec::with_capacity(ids.len());
+    for chunk in ids.chunks(IN_FLIGHT) {
+        let pending = chunk
+            .iter()
+            .map(|id| client.begin_get_object(kind, id))
+            .collect::<Result<Vec<_>>>()?;
+        for reply in pending {
+            objects.push(reply.recv().map_err(|_| anyhow!("RPC reply dropped"))??);
+        }
+    }
+    Ok(objects)
+}
+
+// ─── Tar and gzip encoding ────────────────────────────────────────────────────
+
+pub enum TarEntry<'a> {
+    File { mode: u32, data: &'a [u8] },
+    Symlink(&'a str),
+    Dir,
+}
+
+/// ustar writer; names and link targets past 100 bytes use GNU long-name
+/// records, which GNU tar, bsdtar and Python's tarfile all read.
+#[derive(Default)]
+pub struct TarBuilder {
+    out: Vec<u8>,
+}
+
+impl TarBuilder {
+    pub fn append(&mut self, name: &str, entry: TarEntry<'_>, mtime: u64) {
+        let (mode, typeflag, link, data): (u32, u8, &str, &[u8]) = match entry {
+            TarEntry::File { mode, data } => (mode, b'0', "", data),
+            TarEntry::Symlink(target) => (0o777, b'2', target, &[]),
+            TarEntry::Dir => (0o755, b'5', "", &[]),
+        };
+        if link.len() > 100 {
+            self.long_name(b'K', link);
+        }
+        if name.len() > 100 {
+            self.long_name(b'L', name);
+        }
+        self.header(name, mode, data.len() as u64, mtime, typeflag, link);
+        self.data(data);
+    }
+
+    fn long_name(&mut self, typeflag: u8, name: &str) {
+        let mut data = name.as_bytes().to_vec();
+        data.push(0);
+        self.header("././@LongLink", 0, data.len() as u64, 0, typeflag, "");
+        self.data(&data);
+    }
+
+    fn header(&mut self, name: &str, mode: u32, size: u64, mtime: u64, typeflag: u8, link: &str) {
+        let mut header = [0u8; 512];
+        let put = |header: &mut [u8; 512], offset: usize, len: usize, value: &[u8]| {
+            let n = value.len().min(len);
+            header[offset..offset + n].copy_from_slice(&value[..n]);
+        };
+        let octal = |header: &mut [u8; 512], offset: usize, len: usize, value: u64| {
+            let digits = format!("{value:0width$o}", width = len - 1);
+            put(header, offset, len - 1, digits.as_bytes());
+        };
+        put(&mut header, 0, 100, name.as_bytes());
+        octal(&mut header, 100, 8, u64::from(mode));
+        octal(&mut header, 108, 8, 0);
+        octal(&mut header, 116, 8, 0);
+        octal(&mut header, 124, 12, size);
+        octal(&mut header, 136, 12, mtime);
+        header[148..156].fill(b' ');
+        header[156] = typeflag;
+        put(&mut header, 157, 100, link.as_bytes());
+        put(&mut header, 257, 8, b"ustar\x0000");
+        let checksum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+        put(&mut header, 148, 8, format!("{checksum:06o}\0 ").as_bytes());
+        self.out.extend_from_slice(&header);
+    }
+
+    fn data(&mut self, data: &[u8]) {
+        self.out.extend_from_slice(data);
+        let pad = (512 - data.len() % 512) % 512;
+        self.out.resize(self.out.len() + pad, 0);
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        self.out.resize(self.out.len() + 1024, 0);
+        self.out
+    }
+}
+
+/// Wrap `data` in a gzip member made of stored (uncompressed) deflate blocks.
+pub fn gzip_stored(data: &[u8]) -> Vec<u8> {
+    const BLOCK: usize = 0xffff;
+    let mut out = Vec::with_capacity(data.len() + data.len() / BLOCK * 5 + 23);
+    out.extend_from_slice(&[0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff]);
+    let mut chunks = data.chunks(BLOCK).peekable();
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(chunk) = chunks.next() {
+        let len = chunk.len() as u16;
+        out.push(u8::from(chunks.peek().is_none()));
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn gzip_stores_blocks_with_trailer() {
+        let data = vec![b'x'; 0xffff + 10];
+        let gz = gzip_stored(&data);
+        assert_eq!(&gz[..3], &[0x1f, 0x8b, 8]);
+        // First block is full and not final; the second is final.
+        assert_eq!(gz[10], 0);
+        assert_eq!(&gz[11..15], &[0xff, 0xff, 0, 0]);
+        let second = 10 + 5 + 0xffff;
+        assert_eq!(gz[second], 1);
+        assert_eq!(&gz[second + 1..second + 3], &10u16.to_le_bytes());
+        let trailer = &gz[gz.len() - 8..];
+        assert_eq!(&trailer[..4], &crc32(&data).to_le_bytes());
+        assert_eq!(&trailer[4..], &(data.len() as u32).to_le_bytes());
+        assert_eq!(gzip_stored(b"").len(), 10 + 5 + 8);
+    }
+
+    #[test]
+    fn tar_headers_checksum_and_pad() {
+        let mut tar = TarBuilder::default();
+        tar.append(
+            "a.txt",
+            TarEntry::File {
+                mode: 0o644,
+                data: b"hi\n",
+            },
+            7,
+        );
+        let out = tar.finish();
+        assert_eq!(out.len(), 512 + 512 + 1024);
+        assert_eq!(&out[..5], b"a.txt");
+        assert_eq!(&out[124..135], b"00000000003");
+        assert_eq!(&out[257..263], b"ustar\0");
+        let stored = std::str::from_utf8(&out[148..154]).unwrap();
+        let mut header = out[..512].to_vec();
+        header[148..156].fill(b' ');
+        let sum: u32 = header.iter().map(|b| u32::from(*b)).sum();
+        assert_eq!(u32::from_str_radix(stored, 8).unwrap(), sum);
+        assert_eq!(&out[512..515], b"hi\n");
+
+        let mut tar = TarBuilder::default();
+        let long = "d/".repeat(60) + "f";
+        tar.append(&long, TarEntry::Dir, 0);
+        let out = tar.finish();
+        assert_eq!(out[156], b'L');
+        assert_eq!(&out[512..512 + long.len()], long.as_bytes());
+    }
+}
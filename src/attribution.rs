@@ -0,0 +1,213 @@
+//! Tandem metadata in commit templates: `tandem_workspace`, `tandem_version`,
+//! and `tandem_presence`.
+//!
+//! The server tags every operation it publishes with the workspace that sent
+//! the `updateOpHeads` (`tandem.workspace`). An operation's
+//! `commit_predecessors` lists the commits it wrote, so each commit is
+//! attributed to the workspace whose operation created it:
+//!
+//! ```text
+//! jj log -T 'tandem_workspace ++ " " ++ description'
+//! jj log -T 'tandem_workspace ++ "@" ++ tandem_version ++ if(tandem_presence, " (online)")'
+//! ```
+//!
+//! `tandem_version` is the heads version at which that operation first became
+//! a head, from the server's heads history. `tandem_presence` is true when
+//! the attributed workspace has moved the heads within [`PRESENCE_WINDOW`].
+//! Commits from untagged operations (written before attribution existed, or
+//! by jj versions that do not record predecessors) render as "" and false,
+//! and so does `tandem_version` once the transition has aged out of history.
+
+use std::cell::OnceCell;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use jj_cli::commit_templater::{CommitTemplateBuildFnTable, CommitTemplateLanguageExtension};
+use jj_cli::templater::TemplatePropertyExt as _;
+use jj_lib::backend::CommitId;
+use jj_lib::extensions_map::ExtensionsMap;
+use jj_lib::hex_util::encode_hex;
+use jj_lib::op_store::OperationId;
+use jj_lib::repo::Repo;
+
+use crate::backend::TandemBackend;
+use crate::rpc::RepoCapability;
+
+/// Operation tag naming the workspace that published the operation.
+pub const WORKSPACE_TAG: &str = "tandem.workspace";
+
+/// A workspace counts as online if it published within this long.
+pub const PRESENCE_WINDOW: Duration = Duration::from_secs(10 * 60);
+
+pub struct WorkspaceAttribution;
+
+impl CommitTemplateLanguageExtension for WorkspaceAttribution {
+    fn build_fn_table<'repo>(&self) -> CommitTemplateBuildFnTable<'repo> {
+        let mut table = CommitTemplateBuildFnTable::empty();
+        table.commit_methods.insert(
+            "tandem_workspace",
+            |language, _diagnostics, _build_ctx, self_property, function| {
+                function.expect_no_arguments()?;
+                let metadata = metadata(language);
+                let out_property = self_property.map(move |commit| {
+                    metadata
+                        .landing(commit.id())
+                        .map(|landing| landing.workspace.clone())
+                        .unwrap_or_default()
+                });
+                Ok(out_property.into_dyn_wrapped())
+            },
+        );
+        table.commit_methods.insert(
+            "tandem_version",
+            |language, _diagnostics, _build_ctx, self_property, function| {
+                function.expect_no_arguments()?;
+                let metadata = metadata(language);
+                let out_property = self_property.map(move |commit| {
+                    metadata
+                        .landing(commit.id())
+                        .and_then(|landing| landing.version)
+                        .map(|version| version.to_string())
+                        .unwrap_or_default()
+                });
+                Ok(out_property.into_dyn_wrapped())
+            },
+        );
+        table.commit_methods.insert(
+            "tandem_presence",
+            |language, _diagnostics, _build_ctx, self_property, function| {
+                function.expect_no_arguments()?;
+                let metadata = metadata(language);
+                let out_property = self_property.map(move |commit| {
+                    metadata
+                        .landing(commit.id())
+                        .is_some_and(|landing| metadata.online.contains(&landing.workspace))
+                });
+                Ok(out_property.into_dyn_wrapped())
+            },
+        );
+        table
+    }
+
+    fn build_cache_extensions(&self, extensions: &mut ExtensionsMap) {
+        extensions.insert(MetadataCache::default());
+    }
+}
+
+fn metadata(language: &jj_cli::commit_templater::CommitTemplateLanguage<'_>) -> Arc<Metadata> {
+    language
+        .cache_extension::<MetadataCache>()
+        .expect("registered in build_cache_extensions")
+        .get(language.repo())
+}
+
+/// Where a commit came from.
+struct Landing {
+    workspace: String,
+    /// Heads version at which the writing operation became a head.
+    version: Option<u64>,
+}
+
+#[derive(Default)]
+struct Metadata {
+    landings: HashMap<CommitId, Landing>,
+    /// Workspaces that moved the heads within [`PRESENCE_WINDOW`].
+    online: HashSet<String>,
+}
+
+impl Metadata {
+    fn landing(&self, id: &CommitId) -> Option<&Landing> {
+        self.landings.get(id)
+    }
+}
+
+/// Commit metadata, loaded on first use by a template.
+#[derive(Default)]
+struct MetadataCache {
+    metadata: OnceCell<Arc<Metadata>>,
+}
+
+impl MetadataCache {
+    fn get(&self, repo: &dyn Repo) -> Arc<Metadata> {
+        self.metadata
+            .get_or_init(|| {
+                Arc::new(load_metadata(repo).unwrap_or_else(|err| {
+                    tracing::warn!(error = %err, "cannot load tandem workspace attribution");
+                    Metadata::default()
+                }))
+            })
+            .clone()
+    }
+}
+
+/// The workspace each attributed commit came from, for commands that
+/// attribute commits outside templates (`tandem blame`).
+pub fn commit_workspaces(repo: &dyn Repo) -> anyhow::Result<HashMap<CommitId, String>> {
+    Ok(load_metadata(repo)?
+        .landings
+        .into_iter()
+        .map(|(id, landing)| (id, landing.workspace))
+        .collect())
+}
+
+fn load_metadata(repo: &dyn Repo) -> anyhow::Result<Metadata> {
+    let Some(backend) = repo.store().backend_impl::<TandemBackend>() else {
+        return Ok(Metadata::default());
+    };
+    let client = backend.client();
+
+    // First version each operation appeared in the heads, and who was
+    // publishing recently.
+    let mut first_version = HashMap::new();
+    let mut online = HashSet::new();
+    if client.supports_capability(RepoCapability::HeadsHistory) {
+        let now_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0);
+        let cutoff = now_ms.saturating_sub(PRESENCE_WINDOW.as_millis() as u64);
+        let (transitions, _) = client.get_heads_history(0)?;
+        for transition in transitions {
+            for head in &transition.heads {
+                first_version
+                    .entry(encode_hex(head))
+                    .or_insert(transition.version);
+            }
+            if let Some(workspace) = transition.workspace_id {
+                if transition.timestamp_millis >= cutoff {
+                    online.insert(workspace);
+                }
+            }
+        }
+    }
+
+    let filters = BTreeMap::from([(WORKSPACE_TAG.to_string(), String::new())]);
+    let mut landings = HashMap::new();
+    for tagged in client.get_operation_tags(&filters)? {
+        let Some(workspace) = tagged.tags.get(WORKSPACE_TAG) else {
+            continue;
+        };
+        let version = first_version.get(&tagged.id_hex()).copied();
+        // Abandoned operations keep their tags but can no longer be read.
+        let Ok(operation) =
+            pollster::block_on(repo.op_store().read_operation(&OperationId::new(tagged.id)))
+        else {
+            continue;
+        };
+        for commit_id in operation
+            .commit_predecessors
+            .into_iter()
+            .flat_map(|map| map.into_keys())
+        {
+            landings.insert(
+                commit_id,
+                Landing {
+                    workspace: workspace.clone(),
+                    version,
+                },
+            );
+        }
+    }
+    Ok(Metadata { landings, online })
+}
@@ -0,0 +1,152 @@
+//! `tandem blame <path>` — which workspace wrote each line of a file.
+//!
+//! jj's line annotation (as in `jj file annotate`) finds the commit that
+//! introduced each line; tandem then names the workspace whose operation
+//! created that commit, from the same attribution `tandem_workspace` uses in
+//! templates. With several agents on one repo, that answers "who wrote
+//! this?" where the commit author, shared by every agent, does not.
+//!
+//! Lines from commits written before attribution existed, or outside
+//! tandem, show as `-`. `--summary` prints line counts per workspace
+//! instead of the file.
+
+use std::collections::HashMap;
+use std::io::Write as _;
+
+use jj_cli::cli_util::{CommandHelper, RevisionArg};
+use jj_cli::command_error::{user_error, CommandError};
+use jj_cli::ui::Ui;
+use jj_lib::annotate::FileAnnotator;
+use jj_lib::backend::CommitId;
+use jj_lib::repo::Repo as _;
+use jj_lib::revset::RevsetExpression;
+
+use crate::attribution;
+
+/// Shown for lines whose commit has no workspace attribution.
+const UNATTRIBUTED: &str = "-";
+
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum BlameCommand {
+    /// Show the workspace that wrote each line of a file
+    Blame(BlameArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct BlameArgs {
+    /// The file to annotate
+    #[arg(value_name = "FILESET")]
+    path: String,
+    /// The revision to start at
+    #[arg(long, short, default_value = "@", value_name = "REVSET")]
+    revision: RevisionArg,
+    /// Print how many lines each workspace wrote instead of the file
+    #[arg(long)]
+    summary: bool,
+}
+
+pub fn run(ui: &mut Ui, command: &CommandHelper, args: BlameCommand) -> Result<(), CommandError> {
+    let BlameCommand::Blame(args) = args;
+    let workspace_command = command.workspace_helper(ui)?;
+    let repo = workspace_command.repo();
+    let starting_commit = workspace_command.resolve_single_rev(ui, &args.revision)?;
+    let file_path = workspace_command.parse_file_path(&args.path)?;
+    let ui_path = workspace_command.format_file_path(&file_path);
+    let file_value = starting_commit.tree().path_value(&file_path)?;
+    if file_value.is_absent() {
+        return Err(user_error(format!("No such path: {ui_path}")));
+    }
+    if file_value.to_file_merge().is_none() {
+        return Err(user_error(format!(
+            "Path exists but is not a regular file: {ui_path}"
+        )));
+    }
+
+    let mut annotator = FileAnnotator::from_commit(&starting_commit, &file_path)?;
+    annotator.compute(repo.as_ref(), &RevsetExpression::all())?;
+    let annotation = annotator.to_annotation();
+    let workspaces = attribution::commit_workspaces(repo.as_ref()).map_err(user_error)?;
+    let workspace_of = |commit_id: &CommitId| {
+        workspaces
+            .get(commit_id)
+            .map(String::as_str)
+            .unwrap_or(UNATTRIBUTED)
+    };
+
+    if args.summary {
+        let mut counts: HashMap<&str, usize> = HashMap::new();
+        let mut total = 0;
+        for (commit_id, _) in annotation.lines() {
+            let commit_id = commit_id.unwrap_or_else(|id| id);
+            *counts.entry(workspace_of(commit_id)).or_default() += 1;
+            total += 1;
+        }
+        let mut counts: Vec<_> = counts.into_iter().collect();
+        counts.sort_by(|(a_name, a), (b_name, b)| b.cmp(a).then(a_name.cmp(b_name)));
+        let width = name_width(counts.iter().map(|(name, _)| *name));
+        let mut out = ui.stdout();
+        for (name, count) in counts {
+            writeln!(
+                out,
+                "{name:<width$} {count:>6} {:>5.1}%",
+                100.0 * count as f64 / total as f64
+            )?;
+        }
+        return Ok(());
+    }
+
+    let lines: Vec<_> = annotation.lines().collect();
+    let width = name_width(
+        lines
+            .iter()
+            .map(|(commit_id, _)| workspace_of(commit_id.unwrap_or_else(|id| id))),
+    );
+    let number_width = lines.len().to_string().len();
+    let mut change_ids: HashMap<&CommitId, String> = HashMap::new();
+    let mut out = ui.stdout();
+    for (i, (commit_id, content)) in lines.iter().enumerate() {
+        // Lines the search did not trace further are attributed to the last
+        // commit it reached, as in `jj file annotate`.
+        let commit_id = commit_id.unwrap_or_else(|id| id);
+        let change_id = match change_ids.get(commit_id) {
+            Some(change_id) => change_id.clone(),
+            None => {
+                let commit = repo.store().get_commit(commit_id)?;
+                let change_id: String = commit.change_id().reverse_hex().chars().take(8).collect();
+                change_ids.insert(commit_id, change_id.clone());
+                change_id
+            }
+        };
+        write!(
+            out,
+            "{:<width$} {change_id} {:>number_width$}: ",
+            workspace_of(commit_id),
+            i + 1
+        )?;
+        out.write_all(content)?;
+        if !content.ends_with(b"\n") {
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// Column width for workspace names.
+fn name_width<'a>(names: impl Iterator<Item = &'a str>) -> usize {
+    names
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+        .max(UNATTRIBUTED.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_width_fits_the_longest_name() {
+        assert_eq!(name_width(["agent-a", "human"].into_iter()), 7);
+        assert_eq!(name_width(std::iter::empty()), UNATTRIBUTED.len());
+    }
+}
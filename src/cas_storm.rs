@@ -0,0 +1,183 @@
+//! CAS storm detection and backpressure for `updateOpHeads`.
+//!
+//! When many workspaces race to move the heads, most updates lose the CAS,
+//! re-read, and retry within a few milliseconds, which only makes the next
+//! round more crowded: the server burns CPU on rejected updates and the log
+//! fills with version mismatches. The server counts lost races over the last
+//! second. Once that count reaches `--cas-storm-threshold`, every
+//! `updateOpHeads` response carries `minBackoffMs`, a floor for the client's
+//! retry backoff that grows with the rate, and the start of the storm is
+//! logged and sent to the notification sinks. The storm is over once the
+//! rate drops below half the threshold, so it does not flap at the edge.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Lost races per second at which a storm starts.
+pub const DEFAULT_THRESHOLD: usize = 20;
+
+/// Span the rate is measured over.
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Backoff floor at exactly the threshold; it scales with the rate.
+const BASE_BACKOFF_MS: u64 = 100;
+
+/// Largest backoff floor handed out.
+const MAX_BACKOFF_MS: u64 = 2_000;
+
+pub struct CasStorm {
+    /// 0 turns detection off.
+    threshold: usize,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    /// When each lost race in the window happened, oldest first.
+    conflicts: VecDeque<Instant>,
+    storm: Option<Storm>,
+}
+
+struct Storm {
+    since: Instant,
+    peak: usize,
+}
+
+/// A storm starting or ending, for the caller to log and announce.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Started {
+        conflicts_per_sec: usize,
+        min_backoff_ms: u32,
+    },
+    Ended {
+        lasted: Duration,
+        peak_per_sec: usize,
+    },
+}
+
+impl CasStorm {
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            state: Mutex::new(State::default()),
+        }
+    }
+
+    /// Record how an update went. Returns the backoff floor to hand the
+    /// client (0 outside a storm) and whether a storm started or ended.
+    pub fn record(&self, cas_failed: bool) -> (u32, Option<Change>) {
+        self.record_at(Instant::now(), cas_failed)
+    }
+
+    fn record_at(&self, now: Instant, cas_failed: bool) -> (u32, Option<Change>) {
+        if self.threshold == 0 {
+            return (0, None);
+        }
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        while state
+            .conflicts
+            .front()
+            .is_some_and(|at| now.duration_since(*at) >= WINDOW)
+        {
+            state.conflicts.pop_front();
+        }
+        if cas_failed {
+            state.conflicts.push_back(now);
+        }
+        let rate = state.conflicts.len();
+
+        let mut change = None;
+        match &mut state.storm {
+            None if rate >= self.threshold => {
+                state.storm = Some(Storm {
+                    since: now,
+                    peak: rate,
+                });
+                change = Some(Change::Started {
+                    conflicts_per_sec: rate,
+                    min_backoff_ms: self.backoff_ms(rate),
+                });
+            }
+            Some(storm) if rate * 2 < self.threshold => {
+                change = Some(Change::Ended {
+                    lasted: now.duration_since(storm.since),
+                    peak_per_sec: storm.peak,
+                });
+                state.storm = None;
+            }
+            Some(storm) => storm.peak = storm.peak.max(rate),
+            None => {}
+        }
+        let hint = if state.storm.is_some() {
+            self.backoff_ms(rate)
+        } else {
+            0
+        };
+        (hint, change)
+    }
+
+    fn backoff_ms(&self, rate: usize) -> u32 {
+        let scaled = BASE_BACKOFF_MS.saturating_mul(rate as u64) / self.threshold as u64;
+        scaled.clamp(BASE_BACKOFF_MS / 2, MAX_BACKOFF_MS) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn storm_starts_at_threshold_and_ends_below_half() {
+        let storm = CasStorm::new(4);
+        let start = Instant::now();
+        for i in 0..3 {
+            let at = start + Duration::from_millis(i * 10);
+            assert_eq!(storm.record_at(at, true), (0, None));
+        }
+        let (hint, change) = storm.record_at(start + Duration::from_millis(30), true);
+        assert_eq!(hint, 100);
+        assert_eq!(
+            change,
+            Some(Change::Started {
+                conflicts_per_sec: 4,
+                min_backoff_ms: 100
+            })
+        );
+
+        // Successes inside the window keep the storm going.
+        let (hint, change) = storm.record_at(start + Duration::from_millis(500), false);
+        assert_eq!((hint, change), (100, None));
+
+        // A second later the old conflicts have aged out.
+        let (hint, change) = storm.record_at(start + Duration::from_millis(1_500), false);
+        assert_eq!(hint, 0);
+        assert!(
+            matches!(
+                change,
+                Some(Change::Ended {
+                    peak_per_sec: 4,
+                    ..
+                })
+            ),
+            "{change:?}"
+        );
+    }
+
+    #[test]
+    fn backoff_grows_with_the_rate_and_is_capped() {
+        let storm = CasStorm::new(10);
+        assert_eq!(storm.backoff_ms(10), 100);
+        assert_eq!(storm.backoff_ms(30), 300);
+        assert_eq!(storm.backoff_ms(10_000), 2_000);
+    }
+
+    #[test]
+    fn zero_threshold_disables_detection() {
+        let storm = CasStorm::new(0);
+        for _ in 0..100 {
+            assert_eq!(storm.record(true), (0, None));
+        }
+    }
+}
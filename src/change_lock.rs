@@ -0,0 +1,499 @@
+//! Change locks — claim a change so other workspaces leave it alone.
+//!
+//! `tandem lock` asks the server to record that this workspace is working on
+//! a change; `tandem unlock` (or the lock's TTL running out) lets it go. The
+//! locks are advisory: the server only keeps the table, and tandem checks it
+//! before `describe` and `new` run. A change locked by another workspace gets
+//! a warning, or, if the lock was taken with `--strict`, the command is
+//! refused. Other commands, and plain jj, are not checked.
+//!
+//! Locks live in the server's memory and are gone after a restart, like
+//! snapshot holds.
+
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use jj_cli::cli_util::{CliDispatchFn, CommandHelper, RevisionArg, WorkspaceCommandHelper};
+use jj_cli::command_error::{user_error, CommandError};
+use jj_cli::ui::Ui;
+use jj_lib::backend::ChangeId;
+use jj_lib::commit::Commit;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::Repo as _;
+
+use crate::backend::TandemBackend;
+use crate::ephemeral;
+use crate::rpc::{ChangeLock, RepoCapability, TandemClient};
+
+/// TTL used when the client asks for none.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(30 * 60);
+/// Longest lock a client can take; a crashed agent cannot hold a change for
+/// longer than this.
+pub const MAX_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Clamp a requested TTL (0 means the default) to `MAX_TTL`.
+pub fn ttl_from_secs(secs: u32) -> Duration {
+    if secs == 0 {
+        DEFAULT_TTL
+    } else {
+        Duration::from_secs(u64::from(secs)).min(MAX_TTL)
+    }
+}
+
+// ─── Server side ──────────────────────────────────────────────────────────────
+
+#[derive(Default)]
+pub struct ChangeLocks {
+    /// Change id -> lock; expired entries are dropped on access.
+    locks: Mutex<BTreeMap<Vec<u8>, ChangeLock>>,
+}
+
+impl ChangeLocks {
+    /// Take or renew `workspace_id`'s lock on `change_id`. Returns whether
+    /// the workspace holds it now, and the lock that stands.
+    pub fn lock(
+        &self,
+        change_id: &[u8],
+        workspace_id: &str,
+        ttl: Duration,
+        strict: bool,
+        now_millis: u64,
+    ) -> (bool, ChangeLock) {
+        let mut locks = self.live(now_millis);
+        if let Some(held) = locks.get(change_id) {
+            if held.workspace_id != workspace_id {
+                return (false, held.clone());
+            }
+        }
+        let lock = ChangeLock {
+            change_id: change_id.to_vec(),
+            workspace_id: workspace_id.to_string(),
+            expires_at_millis: now_millis.saturating_add(ttl.as_millis() as u64),
+            strict,
+        };
+        locks.insert(change_id.to_vec(), lock.clone());
+        (true, lock)
+    }
+
+    /// Drop `workspace_id`'s lock on `change_id`, or anyone's with `force`.
+    /// Returns whether a lock was dropped and, when one was kept, its holder.
+    pub fn unlock(
+        &self,
+        change_id: &[u8],
+        workspace_id: &str,
+        force: bool,
+        now_millis: u64,
+    ) -> (bool, String) {
+        let mut locks = self.live(now_millis);
+        match locks.get(change_id) {
+            None => (false, String::new()),
+            Some(held) if held.workspace_id != workspace_id && !force => {
+                (false, held.workspace_id.clone())
+            }
+            Some(_) => {
+                locks.remove(change_id);
+                (true, String::new())
+            }
+        }
+    }
+
+    /// Every lock that has not expired, by change id.
+    pub fn list(&self, now_millis: u64) -> Vec<ChangeLock> {
+        self.live(now_millis).values().cloned().collect()
+    }
+
+    fn live(&self, now_millis: u64) -> std::sync::MutexGuard<'_, BTreeMap<Vec<u8>, ChangeLock>> {
+        let mut locks = self.locks.lock().unwrap();
+        locks.retain(|_, lock| lock.expires_at_millis > now_millis);
+        locks
+    }
+}
+
+// ─── tandem lock / unlock ─────────────────────────────────────────────────────
+
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum LockCommand {
+    /// Claim changes so other workspaces do not edit them
+    Lock(LockArgs),
+    /// Release changes claimed with `tandem lock`
+    Unlock(UnlockArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct LockArgs {
+    /// The revision(s) whose changes to lock (default: @)
+    #[arg(value_name = "REVSETS", conflicts_with = "list")]
+    revisions: Vec<RevisionArg>,
+    /// Release the lock after this long if it is not released sooner
+    /// (e.g. 90s, 30m, 2h; at most 1d)
+    #[arg(long, default_value = "30m")]
+    ttl: String,
+    /// Make other workspaces' `describe` and `new` fail on these changes
+    /// instead of warning
+    #[arg(long)]
+    strict: bool,
+    /// Show every lock on the server instead of taking one
+    #[arg(long)]
+    list: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct UnlockArgs {
+    /// The revision(s) whose changes to unlock (default: @)
+    #[arg(value_name = "REVSETS")]
+    revisions: Vec<RevisionArg>,
+    /// Release the locks even if another workspace holds them
+    #[arg(long)]
+    force: bool,
+}
+
+pub fn run(ui: &mut Ui, command: &CommandHelper, args: LockCommand) -> Result<(), CommandError> {
+    let workspace_command = command.workspace_helper_no_snapshot(ui)?;
+    let client = lock_client(&workspace_command)?;
+    match args {
+        LockCommand::Lock(args) if args.list => list_locks(ui, client),
+        LockCommand::Lock(args) => lock(ui, &workspace_command, client, &args),
+        LockCommand::Unlock(args) => unlock(ui, &workspace_command, client, &args),
+    }
+}
+
+fn lock_client(workspace_command: &WorkspaceCommandHelper) -> Result<&TandemClient, CommandError> {
+    let Some(backend) = workspace_command
+        .repo()
+        .store()
+        .backend_impl::<TandemBackend>()
+    else {
+        return Err(user_error(
+            "this workspace is not backed by a tandem server",
+        ));
+    };
+    let client = backend.client();
+    if !client.supports_capability(RepoCapability::ChangeLocks) {
+        return Err(user_error("the server does not support change locks"));
+    }
+    Ok(client)
+}
+
+fn lock(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    client: &TandemClient,
+    args: &LockArgs,
+) -> Result<(), CommandError> {
+    let ttl = ephemeral::parse_ttl(&args.ttl).map_err(user_error)?;
+    let ttl_secs = u32::try_from(ttl).unwrap_or(u32::MAX);
+    let own = workspace_command.workspace_name().as_str();
+    let commits = resolve(ui, workspace_command, &args.revisions)?;
+
+    // Check them all first, so a refusal leaves no change half-locked.
+    let now = now_millis();
+    let held = client.get_change_locks().map_err(user_error)?;
+    for commit in &commits {
+        if let Some(lock) = held_by_other(&held, commit.change_id(), own) {
+            return Err(locked_error(workspace_command, commit, lock, now));
+        }
+    }
+    for commit in &commits {
+        let (ok, lock) = client
+            .lock_change(commit.change_id().as_bytes(), own, ttl_secs, args.strict)
+            .map_err(user_error)?;
+        if !ok {
+            return Err(locked_error(workspace_command, commit, &lock, now));
+        }
+        writeln!(
+            ui.status(),
+            "Locked {} for {}{}",
+            workspace_command.format_commit_summary(commit),
+            format_remaining(lock.expires_at_millis.saturating_sub(now_millis())),
+            if lock.strict { " (strict)" } else { "" }
+        )?;
+    }
+    Ok(())
+}
+
+fn unlock(
+    ui: &mut Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    client: &TandemClient,
+    args: &UnlockArgs,
+) -> Result<(), CommandError> {
+    let own = workspace_command.workspace_name().as_str();
+    for commit in resolve(ui, workspace_command, &args.revisions)? {
+        let summary = workspace_command.format_commit_summary(&commit);
+        let (released, holder) = client
+            .unlock_change(commit.change_id().as_bytes(), own, args.force)
+            .map_err(user_error)?;
+        if released {
+            writeln!(ui.status(), "Unlocked {summary}")?;
+        } else if holder.is_empty() {
+            writeln!(ui.warning_default(), "{summary} was not locked")?;
+        } else {
+            return Err(
+                user_error(format!("{summary} is locked by workspace '{holder}'"))
+                    .hinted("Use `tandem unlock --force` to release it anyway."),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn list_locks(ui: &mut Ui, client: &TandemClient) -> Result<(), CommandError> {
+    let locks = client.get_change_locks().map_err(user_error)?;
+    if locks.is_empty() {
+        writeln!(ui.status(), "No changes are locked")?;
+        return Ok(());
+    }
+    let now = now_millis();
+    let mut out = ui.stdout();
+    for lock in &locks {
+        let change: String = ChangeId::new(lock.change_id.clone())
+            .reverse_hex()
+            .chars()
+            .take(12)
+            .collect();
+        writeln!(
+            out,
+            "{change} {} for {}{}",
+            lock.workspace_id,
+            format_remaining(lock.expires_at_millis.saturating_sub(now)),
+            if lock.strict { " (strict)" } else { "" }
+        )?;
+    }
+    Ok(())
+}
+
+fn resolve(
+    ui: &Ui,
+    workspace_command: &WorkspaceCommandHelper,
+    revisions: &[RevisionArg],
+) -> Result<Vec<Commit>, CommandError> {
+    let revisions = if revisions.is_empty() {
+        &[RevisionArg::AT][..]
+    } else {
+        revisions
+    };
+    let commit_ids = workspace_command.resolve_some_revsets(ui, revisions)?;
+    let store = workspace_command.repo().store();
+    let mut commits = Vec::with_capacity(commit_ids.len());
+    for id in &commit_ids {
+        commits.push(store.get_commit(id)?);
+    }
+    Ok(commits)
+}
+
+// ─── Checking describe / new ──────────────────────────────────────────────────
+
+/// Dispatch hook: before `describe` or `new`, warn about (or, for strict
+/// locks, refuse) touching changes another workspace has locked.
+pub fn check_locks(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    dispatch: CliDispatchFn,
+) -> Result<(), CommandError> {
+    if let Some(revisions) = edited_revisions(command) {
+        check_revisions(ui, command, &revisions)?;
+    }
+    dispatch(ui, command)
+}
+
+/// The revisions `describe` rewrites or `new` builds on; `None` for other
+/// commands.
+fn edited_revisions(command: &CommandHelper) -> Option<Vec<RevisionArg>> {
+    let (name, matches) = command.matches().subcommand()?;
+    let ids: &[&str] = match name {
+        "describe" => &["revisions_pos", "revisions_opt"],
+        "new" => &[
+            "revisions_pos",
+            "revisions_opt",
+            "insert_after",
+            "insert_before",
+        ],
+        _ => return None,
+    };
+    let mut revisions: Vec<RevisionArg> = ids
+        .iter()
+        .filter_map(|id| matches.try_get_many::<RevisionArg>(id).ok().flatten())
+        .flatten()
+        .cloned()
+        .collect();
+    if revisions.is_empty() {
+        revisions.push(RevisionArg::AT);
+    }
+    Some(revisions)
+}
+
+/// Locks are advisory, so anything that keeps us from reading them (no
+/// tandem backend, an old or unreachable server, a revset the command itself
+/// will reject) lets the command run unchecked.
+fn check_revisions(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    revisions: &[RevisionArg],
+) -> Result<(), CommandError> {
+    let Ok(workspace_command) = command.workspace_helper_no_snapshot(ui) else {
+        return Ok(());
+    };
+    let Ok(client) = lock_client(&workspace_command) else {
+        return Ok(());
+    };
+    let locks = match client.get_change_locks() {
+        Ok(locks) if !locks.is_empty() => locks,
+        Ok(_) => return Ok(()),
+        Err(err) => {
+            tracing::warn!(error = %err, "cannot read change locks");
+            return Ok(());
+        }
+    };
+    let Ok(commits) = resolve(ui, &workspace_command, revisions) else {
+        return Ok(());
+    };
+
+    let own = workspace_command.workspace_name().as_str();
+    let now = now_millis();
+    for commit in &commits {
+        let Some(lock) = held_by_other(&locks, commit.change_id(), own) else {
+            continue;
+        };
+        if lock.strict {
+            return Err(locked_error(&workspace_command, commit, lock, now));
+        }
+        writeln!(
+            ui.warning_default(),
+            "{}",
+            locked_message(&workspace_command, commit, lock, now)
+        )?;
+    }
+    Ok(())
+}
+
+fn held_by_other<'a>(
+    locks: &'a [ChangeLock],
+    change_id: &ChangeId,
+    own: &str,
+) -> Option<&'a ChangeLock> {
+    locks
+        .iter()
+        .find(|lock| lock.change_id == change_id.as_bytes() && lock.workspace_id != own)
+}
+
+fn locked_message(
+    workspace_command: &WorkspaceCommandHelper,
+    commit: &Commit,
+    lock: &ChangeLock,
+    now: u64,
+) -> String {
+    format!(
+        "{} is locked by workspace '{}' for another {}",
+        workspace_command.format_commit_summary(commit),
+        lock.workspace_id,
+        format_remaining(lock.expires_at_millis.saturating_sub(now))
+    )
+}
+
+fn locked_error(
+    workspace_command: &WorkspaceCommandHelper,
+    commit: &Commit,
+    lock: &ChangeLock,
+    now: u64,
+) -> CommandError {
+    user_error(locked_message(workspace_command, commit, lock, now)).hinted(format!(
+        "Ask workspace '{}' to run `tandem unlock`, or wait for the lock to expire.",
+        lock.workspace_id
+    ))
+}
+
+/// `remaining_ms` rounded up to the largest whole unit, e.g. `29m`.
+fn format_remaining(remaining_ms: u64) -> String {
+    let secs = remaining_ms.div_ceil(1000);
+    if secs >= 86400 {
+        format!("{}d", secs / 86400)
+    } else if secs >= 3600 {
+        format!("{}h", secs / 3600)
+    } else if secs >= 60 {
+        format!("{}m", secs.div_ceil(60))
+    } else {
+        format!("{secs}s")
+    }
+}
+
+fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ttl_defaults_and_clamps() {
+        assert_eq!(ttl_from_secs(0), DEFAULT_TTL);
+        assert_eq!(ttl_from_secs(90), Duration::from_secs(90));
+        assert_eq!(ttl_from_secs(u32::MAX), MAX_TTL);
+    }
+
+    #[test]
+    fn locks_belong_to_one_workspace_until_they_expire() {
+        let locks = ChangeLocks::default();
+        let minute = Duration::from_secs(60);
+
+        let (ok, lock) = locks.lock(b"change", "agent-a", minute, false, 1_000);
+        assert!(ok);
+        assert_eq!(lock.expires_at_millis, 61_000);
+
+        let (ok, lock) = locks.lock(b"change", "agent-b", minute, true, 2_000);
+        assert!(!ok);
+        assert_eq!(lock.workspace_id, "agent-a");
+        assert_eq!(
+            locks.unlock(b"change", "agent-b", false, 2_000),
+            (false, "agent-a".to_string())
+        );
+
+        // Renewing keeps the lock with its holder.
+        let (ok, lock) = locks.lock(b"change", "agent-a", minute, true, 30_000);
+        assert!(ok && lock.strict);
+        assert_eq!(lock.expires_at_millis, 90_000);
+
+        assert_eq!(locks.list(89_999).len(), 1);
+        assert!(locks.list(90_000).is_empty());
+        let (ok, _) = locks.lock(b"change", "agent-b", minute, false, 90_000);
+        assert!(ok);
+    }
+
+    #[test]
+    fn unlock_releases_own_locks_or_any_with_force() {
+        let locks = ChangeLocks::default();
+        let minute = Duration::from_secs(60);
+        locks.lock(b"change", "agent-a", minute, false, 0);
+
+        assert_eq!(
+            locks.unlock(b"other", "agent-a", false, 0),
+            (false, String::new())
+        );
+        assert_eq!(
+            locks.unlock(b"change", "agent-b", true, 0),
+            (true, String::new())
+        );
+        assert!(locks.list(0).is_empty());
+
+        locks.lock(b"change", "agent-a", minute, false, 0);
+        assert_eq!(
+            locks.unlock(b"change", "agent-a", false, 0),
+            (true, String::new())
+        );
+    }
+
+    #[test]
+    fn remaining_time_rounds_up() {
+        assert_eq!(format_remaining(0), "0s");
+        assert_eq!(format_remaining(1), "1s");
+        assert_eq!(format_remaining(61_000), "2m");
+        assert_eq!(format_remaining(30 * 60 * 1000), "30m");
+        assert_eq!(format_remaining(2 * 3600 * 1000), "2h");
+        assert_eq!(format_remaining(86_400_000), "1d");
+    }
+}
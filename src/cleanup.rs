@@ -0,0 +1,195 @@
+//! tandem cleanup — forget daemon state nobody will use again.
+//!
+//! `tandem up` remembers each repo's last listen address in the up-state
+//! directory (`last-listen-<hash>.txt`, holding the address, the repo path,
+//! and the daemon's control socket), and daemons leave their control socket behind if they die without
+//! a clean shutdown. Neither is ever removed on its own. A sweep drops
+//! entries whose repo no longer exists and sockets nothing is listening on.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+const LAST_LISTEN_PREFIX: &str = "last-listen-";
+
+/// Something a sweep removed (or, for a dry run, would remove).
+pub struct Removed {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// One repo's entry in the up-state directory.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct LastListen<'a> {
+    pub listen: Option<&'a str>,
+    pub repo: Option<&'a str>,
+    pub control_socket: Option<&'a str>,
+}
+
+/// Split a last-listen file into its address, the repo it belongs to, and
+/// the control socket of the daemon serving it. Files written by older
+/// versions stop after the address or the repo.
+pub fn parse_last_listen(contents: &str) -> LastListen<'_> {
+    let mut lines = contents.lines().map(str::trim).filter(|l| !l.is_empty());
+    LastListen {
+        listen: lines.next(),
+        repo: lines.next(),
+        control_socket: lines.next(),
+    }
+}
+
+pub fn format_last_listen(listen: &str, repo: &Path, control_socket: &str) -> String {
+    format!("{listen}\n{}\n{control_socket}\n", repo.display())
+}
+
+/// Remove stale up-state entries from `state_dir` and dead sockets from
+/// `socket_dir`.
+pub fn sweep(state_dir: &Path, socket_dir: &Path, dry_run: bool) -> Result<Vec<Removed>> {
+    let mut removed = Vec::new();
+    for path in entries(state_dir)? {
+        let is_last_listen = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(LAST_LISTEN_PREFIX));
+        if !is_last_listen {
+            continue;
+        }
+        let contents = std::fs::read_to_string(&path).unwrap_or_default();
+        let reason = match parse_last_listen(&contents).repo {
+            None => "no repo recorded".to_string(),
+            Some(repo) if !Path::new(repo).join(".jj").is_dir() => {
+                format!("repo {repo} no longer exists")
+            }
+            _ => continue,
+        };
+        removed.push(Removed { path, reason });
+    }
+    for path in entries(socket_dir)? {
+        if path.extension().is_some_and(|ext| ext == "sock") && is_stale_socket(&path) {
+            removed.push(Removed {
+                path,
+                reason: "no daemon is listening".to_string(),
+            });
+        }
+    }
+
+    if !dry_run {
+        for entry in &removed {
+            std::fs::remove_file(&entry.path)
+                .with_context(|| format!("remove {}", entry.path.display()))?;
+        }
+    }
+    Ok(removed)
+}
+
+fn entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => Ok(entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .collect()),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err).with_context(|| format!("read {}", dir.display())),
+    }
+}
+
+/// A socket file whose daemon is gone: connecting is refused.
+#[cfg(unix)]
+pub fn is_stale_socket(path: &Path) -> bool {
+    match std::os::unix::net::UnixStream::connect(path) {
+        Ok(_) => false,
+        Err(err) => err.kind() == std::io::ErrorKind::ConnectionRefused,
+    }
+}
+
+/// On Windows the socket file names a named pipe; the pipe is gone once its
+/// server exits.
+#[cfg(windows)]
+pub fn is_stale_socket(path: &Path) -> bool {
+    match crate::control::open_pipe(path) {
+        Ok(_) => false,
+        Err(err) => err.kind() == std::io::ErrorKind::NotFound,
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn is_stale_socket(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweeps_entries_for_missing_repos() {
+        let tmp = tempfile::tempdir().unwrap();
+        let state_dir = tmp.path().join("up-state");
+        std::fs::create_dir_all(&state_dir).unwrap();
+        let live_repo = tmp.path().join("live");
+        std::fs::create_dir_all(live_repo.join(".jj")).unwrap();
+        let gone_repo = tmp.path().join("gone");
+
+        let live = state_dir.join("last-listen-1.txt");
+        let gone = state_dir.join("last-listen-2.txt");
+        let legacy = state_dir.join("last-listen-3.txt");
+        let other = state_dir.join("notes.txt");
+        std::fs::write(
+            &live,
+            format_last_listen("0.0.0.0:13013", &live_repo, "a.sock"),
+        )
+        .unwrap();
+        std::fs::write(
+            &gone,
+            format_last_listen("0.0.0.0:13014", &gone_repo, "b.sock"),
+        )
+        .unwrap();
+        std::fs::write(&legacy, "0.0.0.0:13015").unwrap();
+        std::fs::write(&other, "keep").unwrap();
+
+        let preview = sweep(&state_dir, &tmp.path().join("sockets"), true).unwrap();
+        assert_eq!(preview.len(), 2);
+        assert!(gone.exists() && legacy.exists());
+
+        let removed = sweep(&state_dir, &tmp.path().join("sockets"), false).unwrap();
+        let mut paths: Vec<&PathBuf> = removed.iter().map(|r| &r.path).collect();
+        paths.sort();
+        assert_eq!(paths, vec![&gone, &legacy]);
+        assert!(live.exists() && other.exists());
+        assert!(!gone.exists() && !legacy.exists());
+    }
+
+    #[test]
+    fn last_listen_round_trips() {
+        let contents = format_last_listen(
+            "0.0.0.0:13013",
+            Path::new("/srv/repo"),
+            "/tmp/tandem/control.sock",
+        );
+        assert_eq!(
+            parse_last_listen(&contents),
+            LastListen {
+                listen: Some("0.0.0.0:13013"),
+                repo: Some("/srv/repo"),
+                control_socket: Some("/tmp/tandem/control.sock"),
+            }
+        );
+        assert_eq!(
+            parse_last_listen("0.0.0.0:13013\n"),
+            LastListen {
+                listen: Some("0.0.0.0:13013"),
+                ..LastListen::default()
+            }
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn detects_sockets_without_a_listener() {
+        let tmp = tempfile::tempdir().unwrap();
+        let path = tmp.path().join("control.sock");
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        assert!(!is_stale_socket(&path));
+        drop(listener);
+        assert!(is_stale_socket(&path));
+    }
+}
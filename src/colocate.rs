@@ -0,0 +1,88 @@
+//! `tandem init --colocate` — a plain `.git` next to `.jj`.
+//!
+//! A tandem workspace keeps no objects locally, so tools that only understand
+//! git (IDE integrations, language servers running blame) see nothing. With
+//! `--colocate`, init also creates a git repo in the workspace whose `tandem`
+//! remote is the git mirror the server advertises (`tandem serve
+//! --git-remote <url>`), fetches it, and points a detached `HEAD` at the
+//! working-copy commit's parent so `git status`/`git diff` show the same
+//! change jj does.
+//!
+//! Commit ids are the server's git hashes, so every commit jj shows is the
+//! same commit in the mirror once fetched. Nothing keeps `HEAD` in sync
+//! afterwards; `git fetch tandem` refreshes the objects.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+use anyhow::{bail, Context, Result};
+
+/// Name of the remote pointing at the server's git mirror.
+pub const REMOTE_NAME: &str = "tandem";
+
+/// Fetched on top of the branches `git remote add` sets up: jj keeps
+/// commits that no branch names (working copies, rewritten history)
+/// reachable through `refs/jj/*`.
+const JJ_REFSPEC: &str = "+refs/jj/*:refs/remotes/tandem/jj/*";
+
+fn git(workspace: &Path, args: &[&str]) -> Result<Output> {
+    Command::new("git")
+        .args(args)
+        .current_dir(workspace)
+        .output()
+        .context("run git (is it installed?)")
+}
+
+fn git_ok(workspace: &Path, args: &[&str]) -> Result<()> {
+    let output = git(workspace, args)?;
+    if !output.status.success() {
+        bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn has_commit(workspace: &Path, commit: &str) -> bool {
+    git(
+        workspace,
+        &["cat-file", "-e", &format!("{commit}^{{commit}}")],
+    )
+    .is_ok_and(|output| output.status.success())
+}
+
+/// Create `.git` in `workspace`, wire it to `remote_url` and fetch. `head` is
+/// the working-copy parent to check out, `None` when it is the root commit.
+/// Returns a warning when `head` is not reachable from the mirror's refs.
+pub fn materialize(
+    workspace: &Path,
+    remote_url: &str,
+    head: Option<&str>,
+) -> Result<Option<String>> {
+    git_ok(workspace, &["init", "--quiet"])?;
+    git_ok(workspace, &["remote", "add", REMOTE_NAME, remote_url])?;
+    let key = format!("remote.{REMOTE_NAME}.fetch");
+    git_ok(workspace, &["config", "--add", &key, JJ_REFSPEC])?;
+    git_ok(workspace, &["fetch", "--quiet", REMOTE_NAME])
+        .with_context(|| format!("fetch from git mirror {remote_url}"))?;
+
+    let Some(head) = head else {
+        return Ok(None);
+    };
+    if !has_commit(workspace, head) {
+        // Servers may allow fetching a reachable commit by id even when no
+        // ref we fetched names it.
+        let _ = git_ok(workspace, &["fetch", "--quiet", REMOTE_NAME, head]);
+    }
+    if !has_commit(workspace, head) {
+        return Ok(Some(format!(
+            "commit {head} is not in the git mirror yet; HEAD is unborn until \
+             `git fetch {REMOTE_NAME}` brings it in"
+        )));
+    }
+    git_ok(workspace, &["update-ref", "--no-deref", "HEAD", head])?;
+    git_ok(workspace, &["read-tree", "HEAD"])?;
+    Ok(None)
+}
@@ -0,0 +1,136 @@
+//! Bulk commit reads for building a workspace's index (`getCommitGraph`).
+//!
+//! jj's default index store indexes a repo by reading every commit reachable
+//! from the heads of every operation's view, hidden commits included, and
+//! each commit the backend does not have cached is a `getObject` round trip:
+//! minutes for a long history over a WAN link.
+//! `getCommitGraph` returns those commits in pages instead, newest first,
+//! each with the bytes `getObject` would have returned for it. `tandem init`
+//! writes them to the new workspace's object cache before jj loads the repo
+//! (`TandemBackend::prefetch_commit_graph`), so the index is built from
+//! local reads.
+//!
+//! The walk runs on the server's own index, so a page costs one revset
+//! evaluation plus reading the commits it returns. Pages end at the
+//! requested count or at `PAGE_BYTES`, whichever comes first.
+
+use anyhow::{anyhow, bail, Result};
+use jj_lib::backend::CommitId;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::{Repo as _, RepoLoader};
+use jj_lib::revset::ResolvedRevsetExpression;
+
+/// Commits per page when the client does not pick a limit.
+pub const PAGE_DEFAULT: usize = 2_000;
+/// Upper bound on commits per page, whatever the client asks for.
+pub const PAGE_MAX: usize = 10_000;
+/// A page stops growing once its commits add up to this many bytes.
+const PAGE_BYTES: usize = 8 * 1024 * 1024;
+
+/// One `getCommitGraph` reply.
+#[derive(Debug, Default)]
+pub struct CommitGraphPage {
+    /// The heads the walk started from.
+    pub heads: Vec<Vec<u8>>,
+    /// `(commit id, encoded commit)`, newest first.
+    pub commits: Vec<(Vec<u8>, Vec<u8>)>,
+    /// Empty after the last page.
+    pub next_cursor: String,
+}
+
+/// The page size for a request's `limit` (0 for the default).
+pub fn limit(requested: u32) -> usize {
+    match requested {
+        0 => PAGE_DEFAULT,
+        n => (n as usize).min(PAGE_MAX),
+    }
+}
+
+/// Commits reachable from `heads` (the heads of every indexed commit when
+/// empty) and not from `known`, after `cursor`. `encode` gives a commit's
+/// `getObject` bytes.
+pub fn commit_graph_page(
+    repo_loader: &RepoLoader,
+    heads: &[Vec<u8>],
+    known: &[Vec<u8>],
+    cursor: &str,
+    limit: usize,
+    mut encode: impl FnMut(&CommitId) -> Result<Vec<u8>>,
+) -> Result<CommitGraphPage> {
+    let repo = repo_loader
+        .load_at_head()
+        .map_err(|e| anyhow!("load repo at head: {e}"))?;
+    let index = repo.index();
+    let has_id = |id: &CommitId| {
+        index
+            .has_id(id)
+            .map_err(|e| anyhow!("look up commit {}: {e}", id.hex()))
+    };
+
+    let heads: Vec<CommitId> = if heads.is_empty() {
+        let mut heads: Vec<CommitId> = index
+            .all_heads_for_gc()
+            .map_err(|e| anyhow!("list indexed heads: {e}"))?
+            .collect();
+        heads.sort();
+        heads
+    } else {
+        heads.iter().map(|id| CommitId::new(id.clone())).collect()
+    };
+    for head in &heads {
+        if !has_id(head)? {
+            bail!("unknown commit {}", head.hex());
+        }
+    }
+    // A client's cache may hold commits this server never saw (another
+    // repo sharing `tandem.cache-dir`); they just don't narrow the walk.
+    let mut known_ids = Vec::new();
+    for id in known {
+        let id = CommitId::new(id.clone());
+        if has_id(&id)? {
+            known_ids.push(id);
+        }
+    }
+
+    let wanted = ResolvedRevsetExpression::commits(heads.clone())
+        .ancestors()
+        .minus(&ResolvedRevsetExpression::commits(known_ids).ancestors())
+        .evaluate(repo.as_ref())
+        .map_err(|e| anyhow!("evaluate commit graph: {e}"))?;
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let mut ids = wanted
+        .iter()
+        .filter(|id| id.as_ref().map_or(true, |id| *id != root_commit_id));
+
+    if !cursor.is_empty() {
+        let after = CommitId::try_from_hex(cursor)
+            .ok_or_else(|| anyhow!("invalid commit graph cursor {cursor:?}"))?;
+        loop {
+            let Some(id) = ids.next() else {
+                bail!("commit graph cursor {cursor} is not in the walk");
+            };
+            if id.map_err(|e| anyhow!("walk commit graph: {e}"))? == after {
+                break;
+            }
+        }
+    }
+
+    let mut page = CommitGraphPage {
+        heads: heads.iter().map(|id| id.to_bytes()).collect(),
+        ..CommitGraphPage::default()
+    };
+    let mut bytes = 0;
+    for id in ids {
+        let id = id.map_err(|e| anyhow!("walk commit graph: {e}"))?;
+        if page.commits.len() == limit || bytes >= PAGE_BYTES {
+            if let Some((last, _)) = page.commits.last() {
+                page.next_cursor = CommitId::new(last.clone()).hex();
+            }
+            break;
+        }
+        let data = encode(&id)?;
+        bytes += data.len();
+        page.commits.push((id.to_bytes(), data));
+    }
+    Ok(page)
+}
@@ -0,0 +1,319 @@
+//! Read-only file content over plain HTTP.
+//!
+//! Code review UIs and CI jobs that want one file, or a snapshot of a tree,
+//! should not need a Cap'n Proto client. With
+//! `tandem serve --http-listen <addr>` the server answers:
+//!
+//!   GET /raw/<rev>/<path>         →  the file's bytes (a symlink's target)
+//!   GET /archive/<rev>.tar        →  the commit's tree as a tar archive
+//!   GET /archive/<rev>.tar.gz     →  the same, gzip-framed
+//!
+//! The same listener serves the dashboard API under `/api` (`api.rs`).
+//! `<rev>` is a full commit id in hex or a local bookmark name at the
+//! current head operation. Paths are repo-relative and may be
+//! percent-encoded. Content is read from the same git backend the RPC server
+//! uses, on the server's own task set, so it reflects exactly what clients
+//! see. A commit with a conflicted tree is refused with 409 rather than
+//! guessed at.
+//!
+//! tandem carries no compression library: the `.tar.gz` archive is valid
+//! gzip made of stored deflate blocks, so tools accept it but it is no
+//! smaller than the `.tar`. Put a compressing proxy in front when bandwidth
+//! matters. Archives are built in memory. There is no authentication, as with
+//! the RPC port: bind the endpoint where only trusted readers reach it.
+
+use anyhow::{anyhow, Result};
+use jj_lib::backend::{Backend, CommitId, TreeId, TreeValue};
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::RepoLoader;
+use jj_lib::repo_path::RepoPath;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::archive::{gzip_stored, TarBuilder, TarEntry};
+use crate::http::Reply;
+
+/// What a request asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentTarget {
+    Raw { rev: String, path: String },
+    Archive { rev: String, gzip: bool },
+}
+
+fn ok(content_type: &'static str, body: Vec<u8>) -> Reply {
+    Reply::new("200 OK", content_type, body)
+}
+
+fn not_found(message: &str) -> Reply {
+    Reply::error("404 Not Found", message)
+}
+
+/// Content request handed to the RPC server, which owns the repo.
+pub struct ContentRequest {
+    pub target: ContentTarget,
+    pub reply: oneshot::Sender<Reply>,
+}
+
+/// Parse a request path into a target; `None` for anything not served.
+pub fn parse_target(path: &str) -> Option<ContentTarget> {
+    let path = path.split(['?', '#']).next().unwrap_or("");
+    if let Some(rest) = path.strip_prefix("/raw/") {
+        let (rev, file) = rest.split_once('/')?;
+        let rev = percent_decode(rev)?;
+        let file = percent_decode(file)?;
+        let file = file.trim_matches('/');
+        if rev.is_empty()
+            || file.is_empty()
+            || file.split('/').any(|c| matches!(c, "" | "." | ".."))
+        {
+            return None;
+        }
+        return Some(ContentTarget::Raw {
+            rev,
+            path: file.to_string(),
+        });
+    }
+    let name = percent_decode(path.strip_prefix("/archive/")?)?;
+    let (rev, gzip) = if let Some(rev) = name.strip_suffix(".tar.gz") {
+        (rev, true)
+    } else {
+        (name.strip_suffix(".tar")?, false)
+    };
+    (!rev.is_empty() && !rev.contains('/')).then(|| ContentTarget::Archive {
+        rev: rev.to_string(),
+        gzip,
+    })
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Answer `target` from the repo. Runs on the RPC server's task set.
+pub fn respond(repo_loader: &RepoLoader, target: &ContentTarget) -> Reply {
+    let result = match target {
+        ContentTarget::Raw { rev, path } => raw(repo_loader, rev, path),
+        ContentTarget::Archive { rev, gzip } => archive(repo_loader, rev, *gzip),
+    };
+    result.unwrap_or_else(|err| {
+        tracing::warn!(error = %format!("{err:#}"), "http content request failed");
+        Reply::error("500 Internal Server Error", &format!("{err:#}"))
+    })
+}
+
+/// The commit `rev` names, with its resolved root tree and commit time.
+enum Resolved {
+    Tree {
+        commit_id: CommitId,
+        tree_id: TreeId,
+        mtime: u64,
+    },
+    Refused(Reply),
+}
+
+fn resolve(repo_loader: &RepoLoader, rev: &str) -> Result<Resolved> {
+    let commit_id = match CommitId::try_from_hex(rev) {
+        Some(id) => id,
+        None => {
+            let repo = repo_loader
+                .load_at_head()
+                .map_err(|e| anyhow!("load repo at head: {e}"))?;
+            match repo.view().get_local_bookmark(rev.as_ref()).as_normal() {
+                Some(id) => id.clone(),
+                None => {
+                    return Ok(Resolved::Refused(not_found(&format!(
+                        "no commit or bookmark {rev}"
+                    ))))
+                }
+            }
+        }
+    };
+    let backend = repo_loader.store().backend();
+    if commit_id == *backend.root_commit_id() {
+        return Ok(Resolved::Tree {
+            commit_id,
+            tree_id: backend.empty_tree_id().clone(),
+            mtime: 0,
+        });
+    }
+    let Ok(commit) = pollster::block_on(backend.read_commit(&commit_id)) else {
+        return Ok(Resolved::Refused(not_found(&format!(
+            "no commit {}",
+            commit_id.hex()
+        ))));
+    };
+    let Some(tree_id) = commit.root_tree.as_resolved() else {
+        return Ok(Resolved::Refused(Reply::error(
+            "409 Conflict",
+            &format!("commit {} has conflicts", commit_id.hex()),
+        )));
+    };
+    Ok(Resolved::Tree {
+        tree_id: tree_id.clone(),
+        mtime: u64::try_from(commit.committer.timestamp.timestamp.0 / 1000).unwrap_or(0),
+        commit_id,
+    })
+}
+
+fn raw(repo_loader: &RepoLoader, rev: &str, path: &str) -> Result<Reply> {
+    let tree_id = match resolve(repo_loader, rev)? {
+        Resolved::Tree { tree_id, .. } => tree_id,
+        Resolved::Refused(reply) => return Ok(reply),
+    };
+    let backend = repo_loader.store().backend();
+    let components: Vec<&str> = path.split('/').collect();
+    let mut dir = tree_id;
+    for (depth, name) in components.iter().enumerate() {
+        let tree = pollster::block_on(backend.read_tree(RepoPath::root(), &dir))
+            .map_err(|e| anyhow!("read tree {}: {e}", dir.hex()))?;
+        let Some(value) = tree
+            .entries()
+            .find(|entry| entry.name().as_internal_str() == *name)
+            .map(|entry| entry.value().clone())
+        else {
+            break;
+        };
+        let last = depth + 1 == components.len();
+        match value {
+            TreeValue::Tree(id) if !last => dir = id,
+            TreeValue::File { id, .. } if last => {
+                let body = read_file(backend, &id)?;
+                let content_type = if std::str::from_utf8(&body).is_ok() {
+                    "text/plain; charset=utf-8"
+                } else {
+                    "application/octet-stream"
+                };
+                return Ok(ok(content_type, body));
+            }
+            TreeValue::Symlink(id) if last => {
+                let target = pollster::block_on(backend.read_symlink(RepoPath::root(), &id))
+                    .map_err(|e| anyhow!("read symlink {}: {e}", id.hex()))?;
+                return Ok(ok("text/plain; charset=utf-8", target.into_bytes()));
+            }
+            TreeValue::Tree(_) => return Ok(not_found(&format!("{path} is a directory"))),
+            _ => break,
+        }
+    }
+    Ok(not_found(&format!("no file {path} in {rev}")))
+}
+
+fn archive(repo_loader: &RepoLoader, rev: &str, gzip: bool) -> Result<Reply> {
+    let (commit_id, tree_id, mtime) = match resolve(repo_loader, rev)? {
+        Resolved::Tree {
+            commit_id,
+            tree_id,
+            mtime,
+        } => (commit_id, tree_id, mtime),
+        Resolved::Refused(reply) => return Ok(reply),
+    };
+    let backend = repo_loader.store().backend();
+    let mut tar = TarBuilder::default();
+    let mut stack = vec![(String::new(), tree_id)];
+    while let Some((prefix, dir)) = stack.pop() {
+        let tree = pollster::block_on(backend.read_tree(RepoPath::root(), &dir))
+            .map_err(|e| anyhow!("read tree {}: {e}", dir.hex()))?;
+        let mut subdirs = Vec::new();
+        for entry in tree.entries() {
+            let name = format!("{prefix}{}", entry.name().as_internal_str());
+            match entry.value() {
+                TreeValue::Tree(id) => subdirs.push((format!("{name}/"), id.clone())),
+                TreeValue::File { id, executable, .. } => {
+                    let data = read_file(backend, id)?;
+                    let mode = if *executable { 0o755 } else { 0o644 };
+                    tar.append(&name, TarEntry::File { mode, data: &data }, mtime);
+                }
+                TreeValue::Symlink(id) => {
+                    let target = pollster::block_on(backend.read_symlink(RepoPath::root(), id))
+                        .map_err(|e| anyhow!("read symlink {}: {e}", id.hex()))?;
+                    tar.append(&name, TarEntry::Symlink(&target), mtime);
+                }
+                TreeValue::GitSubmodule(_) => tar.append(&name, TarEntry::Dir, mtime),
+            }
+        }
+        for (name, id) in subdirs.into_iter().rev() {
+            tar.append(&name, TarEntry::Dir, mtime);
+            stack.push((name, id));
+        }
+    }
+    let tar = tar.finish();
+    let hex = commit_id.hex();
+    let (reply, filename) = if gzip {
+        (
+            ok("application/gzip", gzip_stored(&tar)),
+            format!("{hex}.tar.gz"),
+        )
+    } else {
+        (ok("application/x-tar", tar), format!("{hex}.tar"))
+    };
+    Ok(reply.with_header(
+        "Content-Disposition",
+        format!("attachment; filename=\"{filename}\""),
+    ))
+}
+
+fn read_file(backend: &dyn Backend, id: &jj_lib::backend::FileId) -> Result<Vec<u8>> {
+    let mut reader = pollster::block_on(backend.read_file(RepoPath::root(), id))
+        .map_err(|e| anyhow!("read file {}: {e}", id.hex()))?;
+    let mut buf = Vec::new();
+    pollster::block_on(tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut buf))
+        .map_err(|e| anyhow!("read file bytes: {e}"))?;
+    Ok(buf)
+}
+
+// ─── HTTP ────────────────────────────────────────────────────────────
+
+/// Ask the RPC server for `target`.
+pub async fn request(tx: &mpsc::Sender<ContentRequest>, target: ContentTarget) -> Reply {
+    let (reply, rx) = oneshot::channel();
+    if tx.send(ContentRequest { target, reply }).await.is_err() {
+        return Reply::error("503 Service Unavailable", "server is shutting down");
+    }
+    rx.await
+        .unwrap_or_else(|_| Reply::error("503 Service Unavailable", "server dropped the request"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_raw_and_archive_paths() {
+        assert_eq!(
+            parse_target("/raw/main/src/lib%20x.rs?ref=1"),
+            Some(ContentTarget::Raw {
+                rev: "main".into(),
+                path: "src/lib x.rs".into()
+            })
+        );
+        assert_eq!(
+            parse_target("/archive/abc123.tar.gz"),
+            Some(ContentTarget::Archive {
+                rev: "abc123".into(),
+                gzip: true
+            })
+        );
+        assert_eq!(
+            parse_target("/archive/main.tar"),
+            Some(ContentTarget::Archive {
+                rev: "main".into(),
+                gzip: false
+            })
+        );
+        assert_eq!(parse_target("/raw/main/"), None);
+        assert_eq!(parse_target("/raw/main/../secret"), None);
+        assert_eq!(parse_target("/archive/main.zip"), None);
+        assert_eq!(parse_target("/healthz"), None);
+    }
+}
@@ -1,19 +1,249 @@
 //! Control socket — newline-delimited JSON over Unix stream socket.
 //!
+//! On Windows the transport is a named pipe. The control socket path is
+//! then a plain file holding the pipe's name (`\\.\pipe\tandem-control-<pid>`),
+//! so everything that finds daemons by path keeps working unchanged.
+//!
 //! Protocol:
-//!   Client sends one JSON line: {"type": "status"} / {"type": "shutdown"} / {"type": "logs", "level": "debug"}
-//!   Server responds with one or more JSON lines.
-//!   For status/shutdown: single response line, then close.
-//!   For logs: streaming response lines until client disconnects or server shuts down.
+//!   Client sends one JSON line: {"v": 1, "type": "status"} / {"type": "shutdown"} / {"type": "logs", "level": "debug"}
+//!   / {"type": "undoRemote", "operation": "<hex prefix>"}
+//!   / {"type": "maintenance", "enable": true, "until": <unix secs>, "message": "..."}
+//!   / {"type": "redirect", "address": "<host:port>" | null}
+//!   / {"type": "health"} / {"type": "restart"}
+//!   / {"type": "faults", "set": {"dropUpdateOpHeadsEvery": 3, ...} | absent}
+//!   Server responds with one or more JSON lines, each {"v": 1, "type": ..., ...}.
+//!   For status/shutdown/undoRemote/maintenance/redirect/health/restart/faults: single response line, then close.
+//!   For logs: streaming `log` lines until client disconnects or server shuts down.
+//!   Failures of any request are one {"v": 1, "type": "error", "code": ..., "msg": ...} line.
+//!
+//! Versioning: requests and responses are [`ControlRequest`] and
+//! [`ControlResponse`], at [`PROTOCOL_VERSION`]. A request without `v` is
+//! taken to be version 1; one naming any other version gets an
+//! `unsupportedVersion` error instead of a guess. Adding optional fields or
+//! new request types does not change the version; changing or removing
+//! fields does. The JSON Schema for every line is
+//! `docs/design-docs/control-protocol.schema.json`, also printed by
+//! `tandem server schema`, for tools that talk to the socket directly.
+//!
+//! Access: on Unix the socket file is created `0600` (and its directory, when
+//! tandem creates it, `0700`), so only the user running the server can
+//! connect. A server started with a control token (`--control-token` /
+//! `TANDEM_CONTROL_TOKEN`) also requires `"token": "<token>"` on shutdown,
+//! logs, undoRemote, maintenance, redirect, restart, and faults requests; clients
+//! send the token from `TANDEM_CONTROL_TOKEN`. status and health stay open.
+//!
+//! The socket is bound under a temporary name and renamed into place, so a
+//! server taking over in `tandem server restart` replaces the old socket
+//! without a moment where the path is missing.
 
+use anyhow::Context as _;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::path::Path;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::broadcast;
 
+use crate::faults::{FaultConfig, Faults};
+use crate::health::{HealthRequest, HealthResponse};
+use crate::integrity::IntegrityReport;
+use crate::maintenance::{Maintenance, MaintenanceNotice};
+use crate::redirect::{Redirect, RedirectTarget};
+use crate::resources::{ResourceUsage, Resources};
+use crate::watchdog::{Watchdog, WatchdogReport};
+
 // ─── Protocol types ───────────────────────────────────────────────────────────
 
+/// Version of [`ControlRequest`] and [`ControlResponse`] on the wire.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// JSON Schema for every request and response line (`tandem server schema`).
+pub const SCHEMA: &str = include_str!("../docs/design-docs/control-protocol.schema.json");
+
+/// One request line, minus `v` and `token` (see [`RequestLine`]).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ControlRequest {
+    Status,
+    Health,
+    Shutdown,
+    Restart,
+    Logs {
+        #[serde(default = "default_log_level")]
+        level: String,
+    },
+    UndoRemote {
+        /// Hex prefix of the operation to restore.
+        operation: String,
+    },
+    Maintenance {
+        /// Enter maintenance; `false` leaves it.
+        #[serde(default)]
+        enable: bool,
+        /// Unix seconds at which maintenance ends on its own.
+        #[serde(default)]
+        until: u64,
+        #[serde(default)]
+        message: String,
+    },
+    Redirect {
+        /// Where to send clients; `null` stops redirecting.
+        #[serde(default)]
+        address: Option<String>,
+    },
+    Faults {
+        /// Faults to inject instead of the current ones; absent just reads them.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        set: Option<FaultConfig>,
+    },
+}
+
+fn default_log_level() -> String {
+    "info".to_string()
+}
+
+impl ControlRequest {
+    /// Every request `type`, as on the wire.
+    pub const TYPES: &[&str] = &[
+        "status",
+        "health",
+        "shutdown",
+        "restart",
+        "logs",
+        "undoRemote",
+        "maintenance",
+        "redirect",
+        "faults",
+    ];
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Status => "status",
+            Self::Health => "health",
+            Self::Shutdown => "shutdown",
+            Self::Restart => "restart",
+            Self::Logs { .. } => "logs",
+            Self::UndoRemote { .. } => "undoRemote",
+            Self::Maintenance { .. } => "maintenance",
+            Self::Redirect { .. } => "redirect",
+            Self::Faults { .. } => "faults",
+        }
+    }
+
+    /// Requests that change or stream server state; with a control token
+    /// set, they must carry it.
+    fn privileged(&self) -> bool {
+        !matches!(self, Self::Status | Self::Health)
+    }
+}
+
+/// A request as sent: the request plus its protocol version and the
+/// caller's control token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RequestLine {
+    #[serde(default = "protocol_version")]
+    pub v: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(flatten)]
+    pub request: ControlRequest,
+}
+
+fn protocol_version() -> u32 {
+    PROTOCOL_VERSION
+}
+
+/// One response line, minus `v` (see [`ResponseLine`]). Success replies
+/// other than status, health, and log lines carry `ok: true` for clients
+/// that predate the version field.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ControlResponse {
+    Status(Box<StatusResponse>),
+    Health(HealthResponse),
+    Shutdown {
+        ok: bool,
+    },
+    Restart {
+        ok: bool,
+        #[serde(flatten)]
+        restart: RestartResponse,
+    },
+    UndoRemote {
+        ok: bool,
+        #[serde(flatten)]
+        outcome: UndoRemoteResponse,
+    },
+    Maintenance {
+        ok: bool,
+        notice: Option<MaintenanceNotice>,
+    },
+    Redirect {
+        ok: bool,
+        redirect: Option<RedirectTarget>,
+    },
+    Faults {
+        ok: bool,
+        faults: FaultConfig,
+    },
+    Log(LogEvent),
+    Error(ControlError),
+}
+
+impl ControlResponse {
+    fn error(code: ErrorCode, msg: impl Into<String>) -> Self {
+        Self::Error(ControlError {
+            code,
+            msg: msg.into(),
+        })
+    }
+
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::Status(_) => "status",
+            Self::Health(_) => "health",
+            Self::Shutdown { .. } => "shutdown",
+            Self::Restart { .. } => "restart",
+            Self::UndoRemote { .. } => "undoRemote",
+            Self::Maintenance { .. } => "maintenance",
+            Self::Redirect { .. } => "redirect",
+            Self::Faults { .. } => "faults",
+            Self::Log(_) => "log",
+            Self::Error(_) => "error",
+        }
+    }
+}
+
+/// A response as sent, with the protocol version it was written in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseLine {
+    #[serde(default = "protocol_version")]
+    pub v: u32,
+    #[serde(flatten)]
+    pub response: ControlResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlError {
+    #[serde(default)]
+    pub code: ErrorCode,
+    pub msg: String,
+}
+
+/// Why a request failed, for tools that branch on it rather than on `msg`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorCode {
+    /// Not JSON, no or unknown `type`, or fields of the wrong shape.
+    BadRequest,
+    /// `v` names a protocol version this server does not speak.
+    UnsupportedVersion,
+    /// Privileged request without the right control token.
+    Unauthorized,
+    /// The request was understood but could not be carried out.
+    #[default]
+    Failed,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct IntegrationStatus {
@@ -29,17 +259,45 @@ pub struct IntegrationStatus {
     pub updated_at: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub workspace_commit_count: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_auto_advance: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StatusResponse {
     pub running: bool,
     pub pid: u32,
     pub uptime_secs: u64,
     pub repo: String,
     pub listen: String,
+    /// Further `--listen` addresses, after the first.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub also_listen: Vec<String>,
     pub version: String,
     pub integration: IntegrationStatus,
+    /// Primary address when this server is a read-only replica.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub following: Option<String>,
+    /// Active maintenance window, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceNotice>,
+    /// Where clients are sent when the repo has moved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect: Option<RedirectTarget>,
+    /// Injected faults, if any (`tandem server faults`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub faults: Option<FaultConfig>,
+    /// Startup integrity scan (`--integrity-scan`), if one ran.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<IntegrityReport>,
+    /// Memory, file descriptors, and repo disk usage. Absent from servers
+    /// older than the field.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourceUsage>,
+    /// The RPC watchdog's limit and the calls it gave up on; absent when
+    /// `--rpc-timeout 0` turned it off.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watchdog: Option<WatchdogReport>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -54,16 +312,100 @@ pub struct LogEvent {
     pub fields: BTreeMap<String, String>,
 }
 
+/// Result of rolling the server back to a prior operation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UndoRemoteResponse {
+    /// Operation whose view was restored.
+    pub restored_operation: String,
+    /// Compensating operation recorded on top of the previous heads.
+    pub new_operation: String,
+}
+
+/// Rollback request handed from the control socket to the RPC server, which
+/// owns the repo and runs on its own local task set.
+pub struct UndoRemoteRequest {
+    pub operation: String,
+    pub reply: tokio::sync::oneshot::Sender<Result<UndoRemoteResponse, String>>,
+}
+
+/// Restart request handed from the control socket to the accept loop, which
+/// owns the listeners. Replies with the new server's pid, then waits on
+/// `written` so the old server does not exit before the caller hears back.
+pub struct RestartRequest {
+    pub reply: tokio::sync::oneshot::Sender<Result<u32, String>>,
+    pub written: tokio::sync::oneshot::Receiver<()>,
+}
+
+/// Result of `tandem server restart`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RestartResponse {
+    /// The server now answering on the control socket.
+    pub pid: u32,
+    /// The server that handed over and is draining.
+    pub previous_pid: u32,
+}
+
+/// Environment variable holding the control token, read by servers and
+/// clients alike.
+pub const TOKEN_ENV: &str = "TANDEM_CONTROL_TOKEN";
+
 /// Shared server state for the control socket.
 pub struct ControlState {
     pub pid: u32,
     pub start_time: Instant,
     pub repo: String,
     pub listen: String,
+    pub also_listen: Vec<String>,
     pub shutdown_tx: tokio::sync::mpsc::Sender<()>,
+    pub restart_tx: tokio::sync::mpsc::Sender<RestartRequest>,
     pub log_tx: broadcast::Sender<LogEvent>,
     pub integration_enabled: bool,
     pub integration_metadata_path: String,
+    pub following: Option<String>,
+    pub undo_tx: tokio::sync::mpsc::Sender<UndoRemoteRequest>,
+    pub health_tx: tokio::sync::mpsc::Sender<HealthRequest>,
+    pub maintenance: Maintenance,
+    pub redirect: Redirect,
+    pub faults: Faults,
+    pub integrity: Option<IntegrityReport>,
+    pub resources: Resources,
+    pub watchdog: Option<Watchdog>,
+    /// Token privileged requests must carry, if any.
+    pub token: Option<String>,
+}
+
+impl ControlState {
+    fn authorized(&self, token: Option<&str>) -> bool {
+        match (&self.token, token) {
+            (None, _) => true,
+            (Some(expected), Some(token)) => {
+                constant_time_eq(expected.as_bytes(), token.as_bytes())
+            }
+            (Some(_), None) => false,
+        }
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Create `dir` (and its parents) readable only by the current user. An
+/// existing directory is left as it is.
+pub fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    let mut builder = std::fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)
 }
 
 fn level_rank(level: &str) -> u8 {
@@ -90,6 +432,7 @@ fn read_integration_status(enabled: bool, metadata_path: &str) -> IntegrationSta
         last_input_fingerprint: None,
         updated_at: None,
         workspace_commit_count: None,
+        last_auto_advance: None,
     };
 
     if !enabled {
@@ -135,25 +478,44 @@ fn read_integration_status(enabled: bool, metadata_path: &str) -> IntegrationSta
         .get("workspaceCommitCount")
         .or_else(|| value.get("workspace_commit_count"))
         .and_then(|v| v.as_u64());
+    status.last_auto_advance = value
+        .get("lastAutoAdvance")
+        .or_else(|| value.get("last_auto_advance"))
+        .and_then(|v| v.as_str())
+        .map(|s| s.to_string());
 
     status
 }
 
 // ─── Control socket server ───────────────────────────────────────────────────
 
+/// Serve the control socket at `socket_path`; `bound` fires once clients can
+/// connect.
 #[cfg(unix)]
 pub async fn run_control_socket(
     socket_path: String,
     state: std::sync::Arc<ControlState>,
+    bound: tokio::sync::oneshot::Sender<()>,
 ) -> anyhow::Result<()> {
-    // Remove stale socket if present
-    let _ = std::fs::remove_file(&socket_path);
+    use std::os::unix::fs::PermissionsExt;
+
     if let Some(parent) = Path::new(&socket_path).parent() {
-        std::fs::create_dir_all(parent)?;
+        create_private_dir(parent)?;
     }
 
-    let listener = tokio::net::UnixListener::bind(&socket_path)?;
+    // Bind beside the final path and rename over it: a stale socket, or the
+    // live one of a server being replaced, is swapped out in one step.
+    let temp_path = format!("{socket_path}.{}.tmp", std::process::id());
+    let _ = std::fs::remove_file(&temp_path);
+    let listener = tokio::net::UnixListener::bind(&temp_path)?;
+    // Connecting needs write permission on the socket file.
+    std::fs::set_permissions(&temp_path, std::fs::Permissions::from_mode(0o600))?;
+    if let Err(e) = std::fs::rename(&temp_path, &socket_path) {
+        let _ = std::fs::remove_file(&temp_path);
+        return Err(e.into());
+    }
     tracing::info!(socket_path = %socket_path, "control socket listening");
+    let _ = bound.send(());
 
     loop {
         let (stream, _) = listener.accept().await?;
@@ -167,14 +529,50 @@ pub async fn run_control_socket(
     }
 }
 
-#[cfg(unix)]
-async fn handle_control_connection(
-    stream: tokio::net::UnixStream,
+#[cfg(windows)]
+pub async fn run_control_socket(
+    socket_path: String,
     state: std::sync::Arc<ControlState>,
+    bound: tokio::sync::oneshot::Sender<()>,
 ) -> anyhow::Result<()> {
-    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::windows::named_pipe::ServerOptions;
 
-    let (reader, mut writer) = stream.into_split();
+    let pipe_name = format!(r"\\.\pipe\tandem-control-{}", std::process::id());
+    let mut pipe = ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&pipe_name)?;
+    if let Some(parent) = Path::new(&socket_path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&socket_path, &pipe_name)?;
+    tracing::info!(socket_path = %socket_path, pipe = %pipe_name, "control pipe listening");
+    let _ = bound.send(());
+
+    loop {
+        pipe.connect().await?;
+        tracing::debug!("control connection accepted");
+        // Create the next instance before handing this one off so a client
+        // never finds the pipe missing between connections.
+        let stream = std::mem::replace(&mut pipe, ServerOptions::new().create(&pipe_name)?);
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_control_connection(stream, state).await {
+                tracing::error!(error = %e, "control connection error");
+            }
+        });
+    }
+}
+
+async fn handle_control_connection<S>(
+    stream: S,
+    state: std::sync::Arc<ControlState>,
+) -> anyhow::Result<()>
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite,
+{
+    use tokio::io::{AsyncBufReadExt, BufReader};
+
+    let (reader, mut writer) = tokio::io::split(stream);
     let mut reader = BufReader::new(reader);
     let mut line = String::new();
     reader.read_line(&mut line).await?;
@@ -184,46 +582,191 @@ async fn handle_control_connection(
         return Ok(());
     }
 
-    let request: serde_json::Value =
-        serde_json::from_str(line).map_err(|e| anyhow::anyhow!("invalid JSON: {e}"))?;
-
-    let req_type = request["type"].as_str().unwrap_or("");
+    let RequestLine { token, request, .. } = match parse_request(line) {
+        Ok(request) => request,
+        Err(error) => {
+            tracing::warn!(code = ?error.code, msg = %error.msg, "control request refused");
+            return write_response(&mut writer, ControlResponse::Error(error)).await;
+        }
+    };
+    let req_type = request.type_name();
     tracing::debug!(request_type = req_type, "control request received");
 
-    match req_type {
-        "status" => {
+    if request.privileged() && !state.authorized(token.as_deref()) {
+        tracing::warn!(
+            request_type = req_type,
+            "control request refused: bad token"
+        );
+        let resp = ControlResponse::error(
+            ErrorCode::Unauthorized,
+            format!("control token missing or wrong; set {TOKEN_ENV}"),
+        );
+        return write_response(&mut writer, resp).await;
+    }
+
+    let resp = match request {
+        ControlRequest::Status => {
             let uptime = state.start_time.elapsed().as_secs();
             tracing::trace!(uptime_secs = uptime, "serving status request");
-            let resp = StatusResponse {
+            ControlResponse::Status(Box::new(StatusResponse {
                 running: true,
                 pid: state.pid,
                 uptime_secs: uptime,
                 repo: state.repo.clone(),
                 listen: state.listen.clone(),
+                also_listen: state.also_listen.clone(),
                 version: env!("CARGO_PKG_VERSION").to_string(),
                 integration: read_integration_status(
                     state.integration_enabled,
                     &state.integration_metadata_path,
                 ),
-            };
-            let json = serde_json::to_string(&resp)?;
-            writer.write_all(json.as_bytes()).await?;
-            writer.write_all(b"\n").await?;
-            writer.flush().await?;
+                following: state.following.clone(),
+                maintenance: state.maintenance.active(),
+                redirect: state.redirect.current(),
+                faults: state.faults.active(),
+                integrity: state.integrity.clone(),
+                resources: Some(state.resources.usage().await),
+                watchdog: state.watchdog.as_ref().map(Watchdog::report),
+            }))
         }
-        "shutdown" => {
+        ControlRequest::Shutdown => {
             tracing::info!("shutdown requested via control socket");
-            let resp = serde_json::json!({"type": "shutdown", "ok": true});
-            writer.write_all(resp.to_string().as_bytes()).await?;
-            writer.write_all(b"\n").await?;
-            writer.flush().await?;
+            write_response(&mut writer, ControlResponse::Shutdown { ok: true }).await?;
             // Signal shutdown
             let _ = state.shutdown_tx.send(()).await;
+            return Ok(());
+        }
+        ControlRequest::Restart => {
+            tracing::info!("restart requested via control socket");
+            let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+            let (written_tx, written_rx) = tokio::sync::oneshot::channel();
+            let result = if state
+                .restart_tx
+                .send(RestartRequest {
+                    reply: reply_tx,
+                    written: written_rx,
+                })
+                .await
+                .is_err()
+            {
+                Err("server is shutting down".to_string())
+            } else {
+                reply_rx
+                    .await
+                    .unwrap_or_else(|_| Err("server dropped restart request".to_string()))
+            };
+            let resp = match result {
+                Ok(pid) => ControlResponse::Restart {
+                    ok: true,
+                    restart: RestartResponse {
+                        pid,
+                        previous_pid: state.pid,
+                    },
+                },
+                Err(msg) => ControlResponse::error(ErrorCode::Failed, msg),
+            };
+            let written = write_response(&mut writer, resp).await;
+            let _ = written_tx.send(());
+            return written;
+        }
+        ControlRequest::Health => {
+            let report = crate::health::request(&state.health_tx).await;
+            tracing::debug!(healthy = report.healthy, "serving health request");
+            ControlResponse::Health(report)
+        }
+        ControlRequest::UndoRemote { operation } => {
+            tracing::warn!(operation = %operation, "undo-remote requested via control socket");
+            let (reply_tx, reply_rx) = tokio::sync::oneshot::channel();
+            let result = if operation.is_empty() {
+                Err("undoRemote requires an operation id".to_string())
+            } else if state
+                .undo_tx
+                .send(UndoRemoteRequest {
+                    operation,
+                    reply: reply_tx,
+                })
+                .await
+                .is_err()
+            {
+                Err("server is shutting down".to_string())
+            } else {
+                reply_rx
+                    .await
+                    .unwrap_or_else(|_| Err("server dropped undo-remote request".to_string()))
+            };
+            match result {
+                Ok(outcome) => ControlResponse::UndoRemote { ok: true, outcome },
+                Err(msg) => ControlResponse::error(ErrorCode::Failed, msg),
+            }
+        }
+        ControlRequest::Maintenance {
+            enable,
+            until,
+            message,
+        } => {
+            if enable {
+                let notice = MaintenanceNotice { until, message };
+                if notice.until <= crate::maintenance::now_secs() {
+                    ControlResponse::error(ErrorCode::Failed, "maintenance end time is in the past")
+                } else {
+                    tracing::warn!(until = notice.until, message = %notice.message, "entering maintenance mode");
+                    state.maintenance.enter(notice.clone());
+                    ControlResponse::Maintenance {
+                        ok: true,
+                        notice: Some(notice),
+                    }
+                }
+            } else {
+                if state.maintenance.exit().is_some() {
+                    tracing::warn!("leaving maintenance mode");
+                }
+                ControlResponse::Maintenance {
+                    ok: true,
+                    notice: None,
+                }
+            }
+        }
+        ControlRequest::Redirect { address } => {
+            let result = match address.as_deref().map(str::trim) {
+                Some("") => Err(anyhow::anyhow!("redirect address is empty")),
+                Some(address) => {
+                    let target = RedirectTarget {
+                        address: address.to_string(),
+                        version: None,
+                        moved_at_millis: std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_millis() as u64)
+                            .unwrap_or(0),
+                    };
+                    state.redirect.set(target.clone()).map(|()| Some(target))
+                }
+                None => state.redirect.clear().map(|_| None),
+            };
+            match result {
+                Ok(redirect) => ControlResponse::Redirect { ok: true, redirect },
+                Err(err) => ControlResponse::error(ErrorCode::Failed, format!("{err:#}")),
+            }
         }
-        "logs" => {
-            let level_filter = request["level"].as_str().unwrap_or("info").to_string();
-            let min_rank = level_rank(&level_filter);
-            tracing::info!(level = %level_filter, "log stream subscribed");
+        ControlRequest::Faults { set } => match set {
+            None => ControlResponse::Faults {
+                ok: true,
+                faults: state.faults.config(),
+            },
+            Some(config) => match config.validate() {
+                Ok(()) => {
+                    tracing::warn!(faults = ?config.describe(), "fault injection updated");
+                    state.faults.set(config.clone());
+                    ControlResponse::Faults {
+                        ok: true,
+                        faults: config,
+                    }
+                }
+                Err(msg) => ControlResponse::error(ErrorCode::BadRequest, msg),
+            },
+        },
+        ControlRequest::Logs { level } => {
+            let min_rank = level_rank(&level);
+            tracing::info!(level = %level, "log stream subscribed");
 
             let mut rx = state.log_tx.subscribe();
 
@@ -231,97 +774,323 @@ async fn handle_control_connection(
             loop {
                 match rx.recv().await {
                     Ok(event) => {
-                        if level_rank(&event.level) >= min_rank {
-                            let json = match serde_json::to_string(&event) {
-                                Ok(j) => j,
-                                Err(_) => continue,
-                            };
-                            if writer.write_all(json.as_bytes()).await.is_err() {
-                                break;
-                            }
-                            if writer.write_all(b"\n").await.is_err() {
-                                break;
-                            }
-                            if writer.flush().await.is_err() {
-                                break;
-                            }
+                        if level_rank(&event.level) >= min_rank
+                            && write_response(&mut writer, ControlResponse::Log(event))
+                                .await
+                                .is_err()
+                        {
+                            break;
                         }
                     }
                     Err(broadcast::error::RecvError::Closed) => break,
                     Err(broadcast::error::RecvError::Lagged(_)) => continue,
                 }
             }
+            return Ok(());
         }
-        _ => {
-            tracing::warn!(request_type = req_type, "unknown control request type");
-            let resp = serde_json::json!({"type": "error", "msg": format!("unknown request type: {req_type}")});
-            writer.write_all(resp.to_string().as_bytes()).await?;
-            writer.write_all(b"\n").await?;
-            writer.flush().await?;
+    };
+    write_response(&mut writer, resp).await
+}
+
+/// Parse a request line, refusing other protocol versions before looking at
+/// anything else in it.
+fn parse_request(line: &str) -> Result<RequestLine, ControlError> {
+    let bad_request = |msg: String| ControlError {
+        code: ErrorCode::BadRequest,
+        msg,
+    };
+    let value: serde_json::Value =
+        serde_json::from_str(line).map_err(|e| bad_request(format!("invalid JSON: {e}")))?;
+    if let Some(v) = value.get("v") {
+        if v.as_u64() != Some(u64::from(PROTOCOL_VERSION)) {
+            return Err(ControlError {
+                code: ErrorCode::UnsupportedVersion,
+                msg: format!(
+                    "control protocol version {v} is not supported; this server speaks version {PROTOCOL_VERSION}"
+                ),
+            });
         }
     }
+    let req_type = match value.get("type") {
+        Some(serde_json::Value::String(req_type)) => req_type.clone(),
+        Some(_) | None => return Err(bad_request("request has no type".to_string())),
+    };
+    if !ControlRequest::TYPES.contains(&req_type.as_str()) {
+        return Err(bad_request(format!("unknown request type: {req_type}")));
+    }
+    serde_json::from_value(value)
+        .map_err(|e| bad_request(format!("invalid {req_type} request: {e}")))
+}
+
+async fn write_response<W>(writer: &mut W, response: ControlResponse) -> anyhow::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt;
 
+    let line = ResponseLine {
+        v: PROTOCOL_VERSION,
+        response,
+    };
+    writer
+        .write_all(serde_json::to_string(&line)?.as_bytes())
+        .await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await?;
     Ok(())
 }
 
 // ─── Control socket client ───────────────────────────────────────────────────
 
+/// Daemons answering on a `*.sock` control socket in `dir`, with their status.
+pub fn running_daemons(dir: &Path) -> Vec<(String, StatusResponse)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut daemons: Vec<(String, StatusResponse)> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sock"))
+        .filter_map(|path| {
+            let socket = path.to_string_lossy().to_string();
+            let status = client_status(&socket).ok()?;
+            status.running.then_some((socket, status))
+        })
+        .collect();
+    daemons.sort_by(|a, b| a.0.cmp(&b.0));
+    daemons
+}
+
+/// A connected control socket: a Unix stream, or a named pipe on Windows.
 #[cfg(unix)]
-pub fn client_status(socket_path: &str) -> anyhow::Result<StatusResponse> {
-    use std::io::{BufRead, BufReader, Write};
-    use std::os::unix::net::UnixStream;
-    use std::time::Duration;
+type ControlStream = std::os::unix::net::UnixStream;
+#[cfg(windows)]
+type ControlStream = std::fs::File;
 
-    let mut stream = UnixStream::connect(socket_path)
+#[cfg(unix)]
+fn connect(socket_path: &str, read_timeout: Option<Duration>) -> anyhow::Result<ControlStream> {
+    let stream = ControlStream::connect(socket_path)
         .map_err(|e| anyhow::anyhow!("cannot connect to control socket: {e}"))?;
-    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    stream.set_read_timeout(read_timeout)?;
     stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+    Ok(stream)
+}
+
+/// Named pipe handles opened as files take no timeouts; a wedged server
+/// blocks the client, as it would without `set_read_timeout` on Unix.
+#[cfg(windows)]
+fn connect(socket_path: &str, _read_timeout: Option<Duration>) -> anyhow::Result<ControlStream> {
+    open_pipe(Path::new(socket_path))
+        .map_err(|e| anyhow::anyhow!("cannot connect to control socket: {e}"))
+}
+
+/// Open the named pipe recorded in the control socket file at `socket_path`.
+#[cfg(windows)]
+pub fn open_pipe(socket_path: &Path) -> std::io::Result<std::fs::File> {
+    // All instances busy (ERROR_PIPE_BUSY): the server creates the next one
+    // as soon as it accepts, so retry briefly.
+    const ERROR_PIPE_BUSY: i32 = 231;
+    let pipe_name = std::fs::read_to_string(socket_path)?;
+    let deadline = Instant::now() + Duration::from_secs(5);
+    loop {
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(pipe_name.trim())
+        {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) && Instant::now() < deadline => {
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            result => return result,
+        }
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+type ControlStream = std::fs::File;
+
+#[cfg(not(any(unix, windows)))]
+fn connect(_socket_path: &str, _read_timeout: Option<Duration>) -> anyhow::Result<ControlStream> {
+    anyhow::bail!("control socket not supported on this platform")
+}
+
+/// `request` as a line, with the caller's control token from [`TOKEN_ENV`],
+/// if set.
+fn request_line(request: ControlRequest) -> RequestLine {
+    RequestLine {
+        v: PROTOCOL_VERSION,
+        token: std::env::var(TOKEN_ENV)
+            .ok()
+            .filter(|token| !token.is_empty()),
+        request,
+    }
+}
+
+/// Decode a response line to `request_type`, turning `error` lines into
+/// errors.
+fn decode_response(line: &str, request_type: &str) -> anyhow::Result<ControlResponse> {
+    let mut value: serde_json::Value =
+        serde_json::from_str(line.trim()).context("invalid control response")?;
+    // Servers from before the protocol was versioned sent status, health,
+    // and log lines without a type.
+    if let Some(object) = value.as_object_mut() {
+        object.entry("type").or_insert_with(|| request_type.into());
+    }
+    if let Some(v) = value.get("v").and_then(|v| v.as_u64()) {
+        if v != u64::from(PROTOCOL_VERSION) {
+            anyhow::bail!(
+                "the server speaks control protocol version {v}; this tandem speaks version {PROTOCOL_VERSION}"
+            );
+        }
+    }
+    let line: ResponseLine = serde_json::from_value(value).context("invalid control response")?;
+    match line.response {
+        ControlResponse::Error(error) => anyhow::bail!("{}", error.msg),
+        response => Ok(response),
+    }
+}
+
+/// Send one request and read its single response line.
+fn send(
+    socket_path: &str,
+    read_timeout: Option<Duration>,
+    request: ControlRequest,
+) -> anyhow::Result<ControlResponse> {
+    use std::io::{BufRead, BufReader, Write};
 
-    let request = serde_json::json!({"type": "status"});
-    writeln!(stream, "{}", request)?;
+    let mut stream = connect(socket_path, read_timeout)?;
+    let request_type = request.type_name();
+    writeln!(stream, "{}", serde_json::to_string(&request_line(request))?)?;
     stream.flush()?;
 
     let mut reader = BufReader::new(stream);
     let mut line = String::new();
     reader.read_line(&mut line)?;
+    decode_response(&line, request_type)
+}
 
-    let status: StatusResponse = serde_json::from_str(line.trim())?;
-    Ok(status)
+fn unexpected(response: ControlResponse) -> anyhow::Error {
+    anyhow::anyhow!("unexpected control response: {}", response.type_name())
+}
+
+pub fn client_status(socket_path: &str) -> anyhow::Result<StatusResponse> {
+    match send(
+        socket_path,
+        Some(Duration::from_secs(5)),
+        ControlRequest::Status,
+    )? {
+        ControlResponse::Status(status) => Ok(*status),
+        other => Err(unexpected(other)),
+    }
+}
+
+pub fn client_health(socket_path: &str) -> anyhow::Result<HealthResponse> {
+    // The server itself gives up after HEALTH_TIMEOUT; leave room for that.
+    let timeout = crate::health::HEALTH_TIMEOUT + Duration::from_secs(5);
+    match send(socket_path, Some(timeout), ControlRequest::Health)? {
+        ControlResponse::Health(report) => Ok(report),
+        other => Err(unexpected(other)),
+    }
 }
 
-#[cfg(unix)]
 pub fn client_shutdown(socket_path: &str) -> anyhow::Result<()> {
-    use std::io::{BufRead, BufReader, Write};
-    use std::os::unix::net::UnixStream;
-    use std::time::Duration;
+    match send(
+        socket_path,
+        Some(Duration::from_secs(5)),
+        ControlRequest::Shutdown,
+    )? {
+        ControlResponse::Shutdown { .. } => Ok(()),
+        other => Err(unexpected(other)),
+    }
+}
 
-    let mut stream = UnixStream::connect(socket_path)
-        .map_err(|e| anyhow::anyhow!("cannot connect to control socket: {e}"))?;
-    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
-    stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+/// Have the server hand its sockets to a freshly started copy of itself.
+pub fn client_restart(socket_path: &str) -> anyhow::Result<RestartResponse> {
+    // The new server may take up to the handover timeout to open the repo.
+    let timeout = crate::handover::TIMEOUT + Duration::from_secs(10);
+    match send(socket_path, Some(timeout), ControlRequest::Restart)? {
+        ControlResponse::Restart { restart, .. } => Ok(restart),
+        other => Err(unexpected(other)),
+    }
+}
 
-    let request = serde_json::json!({"type": "shutdown"});
-    writeln!(stream, "{}", request)?;
-    stream.flush()?;
+pub fn client_undo_remote(
+    socket_path: &str,
+    operation: &str,
+) -> anyhow::Result<UndoRemoteResponse> {
+    // Restoring loads the repo at two operations; allow more than a status call.
+    let request = ControlRequest::UndoRemote {
+        operation: operation.to_string(),
+    };
+    match send(socket_path, Some(Duration::from_secs(60)), request)? {
+        ControlResponse::UndoRemote { outcome, .. } => Ok(outcome),
+        other => Err(unexpected(other)),
+    }
+}
 
-    let mut reader = BufReader::new(stream);
-    let mut line = String::new();
-    reader.read_line(&mut line)?;
-    Ok(())
+/// Enter maintenance with `notice`, or leave it when `notice` is `None`.
+/// Returns the notice now in effect.
+pub fn client_maintenance(
+    socket_path: &str,
+    notice: Option<&MaintenanceNotice>,
+) -> anyhow::Result<Option<MaintenanceNotice>> {
+    let request = match notice {
+        Some(notice) => ControlRequest::Maintenance {
+            enable: true,
+            until: notice.until,
+            message: notice.message.clone(),
+        },
+        None => ControlRequest::Maintenance {
+            enable: false,
+            until: 0,
+            message: String::new(),
+        },
+    };
+    match send(socket_path, Some(Duration::from_secs(5)), request)? {
+        ControlResponse::Maintenance { notice, .. } => Ok(notice),
+        other => Err(unexpected(other)),
+    }
+}
+
+/// Redirect clients to `address`, or stop redirecting when it is `None`.
+/// Returns the redirect now in effect.
+pub fn client_redirect(
+    socket_path: &str,
+    address: Option<&str>,
+) -> anyhow::Result<Option<RedirectTarget>> {
+    let request = ControlRequest::Redirect {
+        address: address.map(str::to_string),
+    };
+    match send(socket_path, Some(Duration::from_secs(5)), request)? {
+        ControlResponse::Redirect { redirect, .. } => Ok(redirect),
+        other => Err(unexpected(other)),
+    }
+}
+
+/// Replace the server's injected faults with `config`, or just read them
+/// when it is `None`. Returns the faults now in effect.
+pub fn client_faults(
+    socket_path: &str,
+    config: Option<&FaultConfig>,
+) -> anyhow::Result<FaultConfig> {
+    let request = ControlRequest::Faults {
+        set: config.cloned(),
+    };
+    match send(socket_path, Some(Duration::from_secs(5)), request)? {
+        ControlResponse::Faults { faults, .. } => Ok(faults),
+        other => Err(unexpected(other)),
+    }
 }
 
-#[cfg(unix)]
 pub fn client_logs(socket_path: &str, level: &str, json_output: bool) -> anyhow::Result<()> {
     use std::io::{BufRead, BufReader, Write};
-    use std::os::unix::net::UnixStream;
 
-    let mut stream = UnixStream::connect(socket_path)
-        .map_err(|e| anyhow::anyhow!("cannot connect to control socket: {e}"))?;
     // No read timeout for streaming
-    stream.set_write_timeout(Some(std::time::Duration::from_secs(5)))?;
+    let mut stream = connect(socket_path, None)?;
 
-    let request = serde_json::json!({"type": "logs", "level": level});
-    writeln!(stream, "{}", request)?;
+    let request = request_line(ControlRequest::Logs {
+        level: level.to_string(),
+    });
+    writeln!(stream, "{}", serde_json::to_string(&request)?)?;
     stream.flush()?;
 
     let reader = BufReader::new(stream);
@@ -329,38 +1098,35 @@ pub fn client_logs(socket_path: &str, level: &str, json_output: bool) -> anyhow:
         match line {
             Ok(l) if l.trim().is_empty() => continue,
             Ok(l) => {
+                let event = match decode_response(&l, "log")? {
+                    ControlResponse::Log(event) => event,
+                    other => return Err(unexpected(other)),
+                };
                 if json_output {
                     println!("{l}");
+                } else if event.fields.is_empty() {
+                    if event.target.is_empty() {
+                        println!("[{}] {} {}", event.level, event.ts, event.msg);
+                    } else {
+                        println!(
+                            "[{}] {} {} {}",
+                            event.level, event.ts, event.target, event.msg
+                        );
+                    }
                 } else {
-                    // Parse and format as human-readable
-                    if let Ok(event) = serde_json::from_str::<LogEvent>(&l) {
-                        if event.fields.is_empty() {
-                            if event.target.is_empty() {
-                                println!("[{}] {} {}", event.level, event.ts, event.msg);
-                            } else {
-                                println!(
-                                    "[{}] {} {} {}",
-                                    event.level, event.ts, event.target, event.msg
-                                );
-                            }
-                        } else {
-                            let fields = event
-                                .fields
-                                .iter()
-                                .map(|(k, v)| format!("{k}={v}"))
-                                .collect::<Vec<_>>()
-                                .join(" ");
-                            if event.target.is_empty() {
-                                println!("[{}] {} {} {}", event.level, event.ts, event.msg, fields);
-                            } else {
-                                println!(
-                                    "[{}] {} {} {} {}",
-                                    event.level, event.ts, event.target, event.msg, fields
-                                );
-                            }
-                        }
+                    let fields = event
+                        .fields
+                        .iter()
+                        .map(|(k, v)| format!("{k}={v}"))
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    if event.target.is_empty() {
+                        println!("[{}] {} {} {}", event.level, event.ts, event.msg, fields);
                     } else {
-                        println!("{l}");
+                        println!(
+                            "[{}] {} {} {} {}",
+                            event.level, event.ts, event.target, event.msg, fields
+                        );
                     }
                 }
             }
@@ -370,26 +1136,134 @@ pub fn client_logs(socket_path: &str, level: &str, json_output: bool) -> anyhow:
     Ok(())
 }
 
-// Non-unix stubs
-#[cfg(not(unix))]
+// Platforms without Unix sockets or named pipes
+#[cfg(not(any(unix, windows)))]
 pub async fn run_control_socket(
     _socket_path: String,
     _state: std::sync::Arc<ControlState>,
+    _bound: tokio::sync::oneshot::Sender<()>,
 ) -> anyhow::Result<()> {
     anyhow::bail!("control socket not supported on this platform")
 }
 
-#[cfg(not(unix))]
-pub fn client_status(_socket_path: &str) -> anyhow::Result<StatusResponse> {
-    anyhow::bail!("control socket not supported on this platform")
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-#[cfg(not(unix))]
-pub fn client_shutdown(_socket_path: &str) -> anyhow::Result<()> {
-    anyhow::bail!("control socket not supported on this platform")
-}
+    fn schema_types(kind: &str) -> Vec<String> {
+        let schema: serde_json::Value = serde_json::from_str(SCHEMA).unwrap();
+        schema["$defs"][kind]["properties"]["type"]["enum"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|t| t.as_str().unwrap().to_string())
+            .collect()
+    }
 
-#[cfg(not(unix))]
-pub fn client_logs(_socket_path: &str, _level: &str, _json: bool) -> anyhow::Result<()> {
-    anyhow::bail!("control socket not supported on this platform")
+    #[test]
+    fn schema_lists_every_request_and_response_type() {
+        assert_eq!(schema_types("request"), ControlRequest::TYPES);
+        for request_type in ControlRequest::TYPES {
+            let line = match *request_type {
+                "undoRemote" => r#"{"type":"undoRemote","operation":"abc"}"#.to_string(),
+                other => format!(r#"{{"type":"{other}"}}"#),
+            };
+            let parsed = parse_request(&line).unwrap();
+            assert_eq!(parsed.request.type_name(), *request_type);
+        }
+
+        let responses = [
+            ControlResponse::Health(HealthResponse::from_checks(Vec::new())),
+            ControlResponse::Shutdown { ok: true },
+            ControlResponse::Restart {
+                ok: true,
+                restart: RestartResponse {
+                    pid: 2,
+                    previous_pid: 1,
+                },
+            },
+            ControlResponse::UndoRemote {
+                ok: true,
+                outcome: UndoRemoteResponse {
+                    restored_operation: "a".to_string(),
+                    new_operation: "b".to_string(),
+                },
+            },
+            ControlResponse::Maintenance {
+                ok: true,
+                notice: None,
+            },
+            ControlResponse::Redirect {
+                ok: true,
+                redirect: None,
+            },
+            ControlResponse::Faults {
+                ok: true,
+                faults: FaultConfig::default(),
+            },
+            ControlResponse::error(ErrorCode::Failed, "no"),
+        ];
+        let mut wire_types: Vec<String> = responses
+            .iter()
+            .map(|response| {
+                let line = serde_json::to_value(ResponseLine {
+                    v: PROTOCOL_VERSION,
+                    response: response.clone(),
+                })
+                .unwrap();
+                assert_eq!(line["v"], PROTOCOL_VERSION);
+                line["type"].as_str().unwrap().to_string()
+            })
+            .collect();
+        // Status and log payloads need a running server to build.
+        wire_types.extend(["status".to_string(), "log".to_string()]);
+        let mut listed = schema_types("response");
+        listed.sort();
+        wire_types.sort();
+        assert_eq!(listed, wire_types);
+    }
+
+    #[test]
+    fn requests_default_to_version_one_and_refuse_others() {
+        let line = parse_request(r#"{"type":"shutdown","token":"t"}"#).unwrap();
+        assert_eq!(line.v, PROTOCOL_VERSION);
+        assert_eq!(line.token.as_deref(), Some("t"));
+
+        let err = parse_request(r#"{"v":2,"type":"status"}"#).unwrap_err();
+        assert_eq!(err.code, ErrorCode::UnsupportedVersion);
+        let err = parse_request(r#"{"v":"1","type":"status"}"#).unwrap_err();
+        assert_eq!(err.code, ErrorCode::UnsupportedVersion);
+
+        let err = parse_request(r#"{"type":"reboot"}"#).unwrap_err();
+        assert_eq!(err.code, ErrorCode::BadRequest);
+        assert_eq!(err.msg, "unknown request type: reboot");
+        let err = parse_request(r#"{"type":"undoRemote"}"#).unwrap_err();
+        assert_eq!(err.code, ErrorCode::BadRequest);
+        assert!(
+            err.msg.starts_with("invalid undoRemote request"),
+            "{}",
+            err.msg
+        );
+        let err = parse_request("not json").unwrap_err();
+        assert_eq!(err.code, ErrorCode::BadRequest);
+    }
+
+    #[test]
+    fn clients_read_unversioned_and_refuse_newer_responses() {
+        let legacy = r#"{"running":true,"pid":7,"uptime_secs":1,"repo":"r","listen":"l","version":"0.3.0","integration":{"enabled":false,"lastStatus":"disabled"}}"#;
+        match decode_response(legacy, "status").unwrap() {
+            ControlResponse::Status(status) => assert_eq!(status.pid, 7),
+            other => panic!("unexpected {other:?}"),
+        }
+        let legacy_error = r#"{"type":"error","msg":"control token missing or wrong"}"#;
+        let err = decode_response(legacy_error, "shutdown").unwrap_err();
+        assert_eq!(err.to_string(), "control token missing or wrong");
+
+        let newer = r#"{"v":2,"type":"shutdown","ok":true}"#;
+        let err = decode_response(newer, "shutdown").unwrap_err();
+        assert!(
+            err.to_string().contains("control protocol version 2"),
+            "{err}"
+        );
+    }
 }
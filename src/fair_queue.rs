@@ -0,0 +1,172 @@
+//! Fair queuing of `updateOpHeads` by workspace.
+//!
+//! Under contention the heads CAS favours whoever retries fastest: an agent
+//! next to the server re-reads and retries within a millisecond, while a
+//! human on a 150 ms link loses every race. With `tandem serve --fair-queue`,
+//! a workspace whose update fails the CAS takes a place in a FIFO, at most one
+//! place per workspace. While anyone is queued, updates from other workspaces
+//! wait behind the front, so the front's retry lands with nothing able to move
+//! the heads in between. A place is given up when its update lands, or when
+//! the workspace does not retry within `--fair-queue-wait`, so a client that
+//! gave up cannot stall everyone else for longer than that.
+//!
+//! Updates without a workspace id bypass the queue.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a queued workspace keeps its place without retrying.
+pub const DEFAULT_MAX_WAIT: Duration = Duration::from_secs(2);
+
+pub struct FairQueue {
+    max_wait: Duration,
+    places: Mutex<VecDeque<Place>>,
+    turn: tokio::sync::Notify,
+}
+
+struct Place {
+    workspace: String,
+    /// The place is dropped if the workspace has not retried by then.
+    deadline: Instant,
+}
+
+impl FairQueue {
+    pub fn new(max_wait: Duration) -> Self {
+        Self {
+            max_wait,
+            places: Mutex::new(VecDeque::new()),
+            turn: tokio::sync::Notify::new(),
+        }
+    }
+
+    /// Workspaces currently waiting for a turn.
+    pub fn depth(&self) -> usize {
+        self.live_places().len()
+    }
+
+    /// Wait until `workspace` may try its update: the queue is empty or it is
+    /// at the front. Returns how many workspaces were queued on arrival.
+    pub async fn admit(&self, workspace: Option<&str>) -> usize {
+        let Some(workspace) = workspace else {
+            return 0;
+        };
+        let depth = self.depth();
+        loop {
+            let notified = self.turn.notified();
+            let front_deadline = {
+                let places = self.live_places();
+                match places.front() {
+                    None => return depth,
+                    Some(front) if front.workspace == workspace => return depth,
+                    Some(front) => front.deadline,
+                }
+            };
+            tokio::select! {
+                _ = notified => {}
+                _ = tokio::time::sleep_until(tokio::time::Instant::from_std(front_deadline)) => {}
+            }
+        }
+    }
+
+    /// Record how an admitted update went. A CAS failure queues the
+    /// workspace (or renews its place); anything else frees its place.
+    pub fn finish(&self, workspace: Option<&str>, cas_failed: bool) {
+        let Some(workspace) = workspace else {
+            return;
+        };
+        let mut places = self.live_places();
+        let deadline = Instant::now() + self.max_wait;
+        let existing = places.iter().position(|place| place.workspace == workspace);
+        match (existing, cas_failed) {
+            (Some(index), true) => places[index].deadline = deadline,
+            (None, true) => {
+                tracing::debug!(
+                    workspace,
+                    queue_depth = places.len() + 1,
+                    "workspace queued for a fair turn"
+                );
+                places.push_back(Place {
+                    workspace: workspace.to_string(),
+                    deadline,
+                });
+            }
+            (Some(index), false) => {
+                places.remove(index);
+                drop(places);
+                self.turn.notify_waiters();
+            }
+            (None, false) => {}
+        }
+    }
+
+    /// The queue with places whose workspace stopped retrying removed.
+    fn live_places(&self) -> std::sync::MutexGuard<'_, VecDeque<Place>> {
+        let mut places = self.places.lock().unwrap();
+        let now = Instant::now();
+        let before = places.len();
+        places.retain(|place| {
+            let live = place.deadline > now;
+            if !live {
+                tracing::debug!(workspace = %place.workspace, "fair-queue place expired");
+            }
+            live
+        });
+        if places.len() != before {
+            self.turn.notify_waiters();
+        }
+        places
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+
+    #[test]
+    fn empty_queue_admits_everyone() {
+        let queue = FairQueue::new(Duration::from_secs(60));
+        assert_eq!(block_on(queue.admit(Some("agent"))), 0);
+        assert_eq!(block_on(queue.admit(None)), 0);
+        queue.finish(Some("agent"), false);
+        assert_eq!(queue.depth(), 0);
+    }
+
+    #[test]
+    fn loser_goes_first_until_it_lands() {
+        let queue = FairQueue::new(Duration::from_secs(60));
+        queue.finish(Some("human"), true);
+        queue.finish(Some("human"), true);
+        queue.finish(Some("agent"), true);
+        assert_eq!(queue.depth(), 2, "one place per workspace");
+
+        // The front is admitted; the agent behind it waits.
+        assert_eq!(block_on(queue.admit(Some("human"))), 2);
+        let waited = block_on(async {
+            tokio::time::timeout(Duration::from_millis(20), queue.admit(Some("agent"))).await
+        });
+        assert!(waited.is_err(), "agent should wait behind human");
+
+        queue.finish(Some("human"), false);
+        assert_eq!(block_on(queue.admit(Some("agent"))), 1);
+    }
+
+    #[test]
+    fn places_expire_without_a_retry() {
+        let queue = FairQueue::new(Duration::from_millis(5));
+        queue.finish(Some("gone"), true);
+        let admitted = block_on(async {
+            tokio::time::timeout(Duration::from_secs(5), queue.admit(Some("agent"))).await
+        });
+        assert!(admitted.is_ok(), "an abandoned place must not block others");
+        assert_eq!(queue.depth(), 0);
+    }
+}
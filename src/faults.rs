@@ -0,0 +1,361 @@
+//! Fault injection — make the server misbehave on purpose.
+//!
+//! `tandem server faults` sets faults through the control socket, so
+//! integration tests and chaos drills can exercise client retry and
+//! reconnect paths against a real server:
+//!
+//!   --drop-update-op-heads-every N   every Nth updateOpHeads fails unapplied
+//!   --lose-update-op-heads-reply-every N
+//!                                    every Nth updateOpHeads is applied, but
+//!                                    its caller gets an overloaded error
+//!   --get-object-delay MS            getObject replies wait MS milliseconds
+//!   --error-every N                  every Nth call fails as overloaded
+//!   --error-method NAME              ...counting only these methods
+//!
+//! Counters restart whenever the faults are replaced. Faults live in memory
+//! only and show up in `tandem server status`, so a forgotten drill is
+//! visible; `tandem server faults --off` clears them.
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use capnp::any_pointer;
+use capnp::capability::{DispatchCallResult, Params, Promise, Results, Server};
+use serde::{Deserialize, Serialize};
+
+/// Store methods by schema ordinal (`schema/tandem.capnp`).
+pub(crate) const STORE_METHODS: &[&str] = &[
+    "getRepoInfo",
+    "getObject",
+    "putObject",
+    "getOperation",
+    "putOperation",
+    "getView",
+    "putView",
+    "resolveOperationIdPrefix",
+    "getHeads",
+    "updateOpHeads",
+    "watchHeads",
+    "getHeadsSnapshot",
+    "getRelatedCopies",
+    "getOperationTags",
+    "getHeadsHistory",
+    "beginSnapshot",
+    "endSnapshot",
+    "importHeads",
+    "setRedirect",
+    "getViewDelta",
+    "putViewDelta",
+    "getHeadsVersion",
+    "getTreeEntries",
+    "searchCommits",
+    "lockChange",
+    "unlockChange",
+    "getChangeLocks",
+    "previewMerge",
+    "getCommitGraph",
+];
+
+/// Every injected error starts with this.
+pub const FAULT_PREFIX: &str = "tandem fault injection";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FaultConfig {
+    /// Fail every Nth `updateOpHeads` without applying it (0: never).
+    #[serde(default)]
+    pub drop_update_op_heads_every: u64,
+    /// Apply every Nth `updateOpHeads` but fail its reply (0: never).
+    #[serde(default)]
+    pub lose_update_op_heads_reply_every: u64,
+    /// Hold every `getObject` reply back this long, in milliseconds.
+    #[serde(default)]
+    pub get_object_delay_ms: u64,
+    /// Fail every Nth call with an overloaded error (0: never).
+    #[serde(default)]
+    pub error_every: u64,
+    /// Methods `error_every` counts and fails; empty for all of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub error_methods: Vec<String>,
+}
+
+impl FaultConfig {
+    pub fn is_active(&self) -> bool {
+        self.drop_update_op_heads_every > 0
+            || self.lose_update_op_heads_reply_every > 0
+            || self.get_object_delay_ms > 0
+            || self.error_every > 0
+    }
+
+    /// Reject method names the schema does not have.
+    pub fn validate(&self) -> Result<(), String> {
+        match self
+            .error_methods
+            .iter()
+            .find(|method| !STORE_METHODS.contains(&method.as_str()))
+        {
+            Some(method) => Err(format!("unknown store method {method:?}")),
+            None => Ok(()),
+        }
+    }
+
+    /// One line per active fault, for `tandem server faults`.
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        if self.drop_update_op_heads_every > 0 {
+            lines.push(format!(
+                "dropping every {} updateOpHeads",
+                ordinal(self.drop_update_op_heads_every)
+            ));
+        }
+        if self.lose_update_op_heads_reply_every > 0 {
+            lines.push(format!(
+                "losing the reply to every {} updateOpHeads",
+                ordinal(self.lose_update_op_heads_reply_every)
+            ));
+        }
+        if self.get_object_delay_ms > 0 {
+            lines.push(format!(
+                "delaying getObject by {}ms",
+                self.get_object_delay_ms
+            ));
+        }
+        if self.error_every > 0 {
+            let methods = if self.error_methods.is_empty() {
+                "call".to_string()
+            } else {
+                self.error_methods.join("/")
+            };
+            lines.push(format!(
+                "failing every {} {methods} as overloaded",
+                ordinal(self.error_every)
+            ));
+        }
+        lines
+    }
+}
+
+fn ordinal(n: u64) -> String {
+    let suffix = match (n % 10, n % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    };
+    format!("{n}{suffix}")
+}
+
+#[derive(Debug, Default)]
+struct FaultState {
+    config: FaultConfig,
+    update_op_heads_calls: u64,
+    lost_reply_calls: u64,
+    error_counted_calls: u64,
+}
+
+/// What to do with one call.
+#[derive(Debug)]
+enum Fault {
+    Pass,
+    Delay(Duration),
+    Fail(capnp::Error),
+    /// Run the call, then fail it with this error.
+    LoseReply(capnp::Error),
+}
+
+/// Fault settings shared by the RPC server and the control socket.
+#[derive(Debug, Clone, Default)]
+pub struct Faults(Arc<Mutex<FaultState>>);
+
+impl Faults {
+    /// Replace the faults in effect and restart their counters.
+    pub fn set(&self, config: FaultConfig) {
+        *self.0.lock().unwrap() = FaultState {
+            config,
+            ..FaultState::default()
+        };
+    }
+
+    pub fn config(&self) -> FaultConfig {
+        self.0.lock().unwrap().config.clone()
+    }
+
+    /// The faults in effect, if any.
+    pub fn active(&self) -> Option<FaultConfig> {
+        Some(self.config()).filter(FaultConfig::is_active)
+    }
+
+    fn on_call(&self, method: &str) -> Fault {
+        let mut state = self.0.lock().unwrap();
+        if !state.config.is_active() {
+            return Fault::Pass;
+        }
+        let config = state.config.clone();
+        if config.error_every > 0
+            && (config.error_methods.is_empty() || config.error_methods.iter().any(|m| m == method))
+        {
+            state.error_counted_calls += 1;
+            if state.error_counted_calls.is_multiple_of(config.error_every) {
+                return Fault::Fail(capnp::Error::overloaded(format!(
+                    "{FAULT_PREFIX}: transient error in {method}"
+                )));
+            }
+        }
+        if method == "updateOpHeads" && config.drop_update_op_heads_every > 0 {
+            state.update_op_heads_calls += 1;
+            if state
+                .update_op_heads_calls
+                .is_multiple_of(config.drop_update_op_heads_every)
+            {
+                return Fault::Fail(capnp::Error::disconnected(format!(
+                    "{FAULT_PREFIX}: updateOpHeads dropped"
+                )));
+            }
+        }
+        if method == "updateOpHeads" && config.lose_update_op_heads_reply_every > 0 {
+            state.lost_reply_calls += 1;
+            if state
+                .lost_reply_calls
+                .is_multiple_of(config.lose_update_op_heads_reply_every)
+            {
+                return Fault::LoseReply(capnp::Error::overloaded(format!(
+                    "{FAULT_PREFIX}: updateOpHeads reply lost"
+                )));
+            }
+        }
+        if method == "getObject" && config.get_object_delay_ms > 0 {
+            return Fault::Delay(Duration::from_millis(config.get_object_delay_ms));
+        }
+        Fault::Pass
+    }
+}
+
+/// Wraps the store's dispatch, applying the current faults to each call.
+pub struct FaultyStore<S> {
+    inner: S,
+    faults: Faults,
+}
+
+impl<S> FaultyStore<S> {
+    pub fn new(inner: S, faults: Faults) -> Self {
+        Self { inner, faults }
+    }
+}
+
+impl<S: Server> Server for FaultyStore<S> {
+    fn dispatch_call(
+        &mut self,
+        interface_id: u64,
+        method_id: u16,
+        params: Params<any_pointer::Owned>,
+        results: Results<any_pointer::Owned>,
+    ) -> DispatchCallResult {
+        let method = STORE_METHODS
+            .get(usize::from(method_id))
+            .copied()
+            .unwrap_or("unknown");
+        match self.faults.on_call(method) {
+            Fault::Pass => self
+                .inner
+                .dispatch_call(interface_id, method_id, params, results),
+            Fault::Fail(err) => {
+                tracing::warn!(rpc = method, error = %err, "injected fault");
+                DispatchCallResult::new(Promise::err(err), false)
+            }
+            Fault::Delay(delay) => {
+                let call = self
+                    .inner
+                    .dispatch_call(interface_id, method_id, params, results);
+                DispatchCallResult::new(
+                    Promise::from_future(async move {
+                        tokio::time::sleep(delay).await;
+                        call.promise.await
+                    }),
+                    call.is_streaming,
+                )
+            }
+            Fault::LoseReply(err) => {
+                let call = self
+                    .inner
+                    .dispatch_call(interface_id, method_id, params, results);
+                DispatchCallResult::new(
+                    Promise::from_future(async move {
+                        let outcome = call.promise.await;
+                        tracing::warn!(rpc = method, error = %err, applied = outcome.is_ok(), "injected fault");
+                        Err(err)
+                    }),
+                    call.is_streaming,
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_calls_per_fault() {
+        let faults = Faults::default();
+        assert!(matches!(faults.on_call("updateOpHeads"), Fault::Pass));
+
+        faults.set(FaultConfig {
+            drop_update_op_heads_every: 2,
+            get_object_delay_ms: 50,
+            ..FaultConfig::default()
+        });
+        assert!(matches!(faults.on_call("updateOpHeads"), Fault::Pass));
+        assert!(matches!(faults.on_call("updateOpHeads"), Fault::Fail(_)));
+        assert!(matches!(faults.on_call("updateOpHeads"), Fault::Pass));
+        assert!(matches!(
+            faults.on_call("getObject"),
+            Fault::Delay(delay) if delay == Duration::from_millis(50)
+        ));
+        assert!(matches!(faults.on_call("getHeads"), Fault::Pass));
+
+        faults.set(FaultConfig {
+            lose_update_op_heads_reply_every: 2,
+            ..FaultConfig::default()
+        });
+        assert!(matches!(faults.on_call("updateOpHeads"), Fault::Pass));
+        assert!(matches!(
+            faults.on_call("updateOpHeads"),
+            Fault::LoseReply(_)
+        ));
+
+        faults.set(FaultConfig {
+            error_every: 1,
+            error_methods: vec!["putObject".to_string()],
+            ..FaultConfig::default()
+        });
+        match faults.on_call("putObject") {
+            Fault::Fail(err) => assert_eq!(err.kind, capnp::ErrorKind::Overloaded),
+            other => panic!("expected a failure, got {other:?}"),
+        }
+        assert!(matches!(faults.on_call("getObject"), Fault::Pass));
+
+        faults.set(FaultConfig::default());
+        assert!(faults.active().is_none());
+    }
+
+    #[test]
+    fn method_names_follow_the_schema() {
+        assert_eq!(STORE_METHODS[9], "updateOpHeads");
+        assert_eq!(STORE_METHODS[23], "searchCommits");
+        assert_eq!(STORE_METHODS[26], "getChangeLocks");
+        assert_eq!(STORE_METHODS[27], "previewMerge");
+        assert_eq!(STORE_METHODS[28], "getCommitGraph");
+        let config = FaultConfig {
+            error_every: 3,
+            error_methods: vec!["putBlob".to_string()],
+            ..FaultConfig::default()
+        };
+        assert!(config.validate().is_err());
+        assert_eq!(
+            config.describe(),
+            vec!["failing every 3rd putBlob as overloaded".to_string()]
+        );
+    }
+}
@@ -0,0 +1,321 @@
+//! tandem gateway — read-only JSON over HTTP in front of a tandem server.
+//!
+//! For ecosystems where a Cap'n Proto dependency is a non-starter, the
+//! gateway answers plain HTTP and talks to the server with the async
+//! [`tandem_client::TandemClient`]:
+//!
+//!   GET /v1/repo                        →  repo info (protocol, root ids, capabilities)
+//!   GET /v1/heads                       →  `{"version", "heads", "workspaceHeads"}`
+//!   GET /v1/heads?after=<N>[&wait=<s>]  →  the same, once the version passes N
+//!   GET /v1/objects/<kind>/<id>         →  an object's stored bytes
+//!
+//! Ids are hex. `<kind>` is `commit`, `tree`, `file`, `symlink`, or `copy`;
+//! files and symlink targets are raw bytes, the rest jj's protobuf
+//! encodings. `after` turns `/v1/heads` into a long poll: it answers as soon
+//! as the heads move past version N, or with the unchanged heads after
+//! `wait` seconds (default 30, at most 300). Errors are
+//! `{"error": "..."}` with a 4xx/5xx status; 502 means the server could not
+//! be reached, and the gateway reconnects on the next request.
+//!
+//! Nothing is written through the gateway. Like the RPC port it has no
+//! authentication: bind it where only trusted readers reach it.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use tandem_client::{HeadsState, ObjectKind, RepoInfo, TandemClient, WatchEvent};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::control;
+use crate::http::{self, Reply};
+use crate::logging;
+
+const DEFAULT_WAIT: Duration = Duration::from_secs(30);
+const MAX_WAIT: Duration = Duration::from_secs(300);
+
+pub struct GatewayOptions {
+    pub listen_addr: String,
+    pub upstream_addr: String,
+    pub log_level: String,
+    pub log_format: String,
+}
+
+/// What a request asks for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GatewayTarget {
+    Repo,
+    Heads { after: Option<u64>, wait: Duration },
+    Object { kind: ObjectKind, id: Vec<u8> },
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if text.is_empty() || !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+fn parse_kind(kind: &str) -> Option<ObjectKind> {
+    match kind {
+        "commit" => Some(ObjectKind::Commit),
+        "tree" => Some(ObjectKind::Tree),
+        "file" => Some(ObjectKind::File),
+        "symlink" => Some(ObjectKind::Symlink),
+        "copy" => Some(ObjectKind::Copy),
+        _ => None,
+    }
+}
+
+/// Parse a request path into a target; `Err` carries a 400 message, `Ok(None)`
+/// is a path the gateway does not serve.
+pub fn parse_target(path: &str) -> Result<Option<GatewayTarget>, String> {
+    let path = path.split('#').next().unwrap_or("");
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    match path.trim_end_matches('/') {
+        "/v1/repo" => return Ok(Some(GatewayTarget::Repo)),
+        "/v1/heads" => {
+            let mut after = None;
+            let mut wait = DEFAULT_WAIT;
+            for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+                let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+                match key {
+                    "after" => {
+                        after = Some(
+                            value
+                                .parse()
+                                .map_err(|_| format!("after must be a version, got {value:?}"))?,
+                        )
+                    }
+                    "wait" => {
+                        let secs: u64 = value
+                            .parse()
+                            .map_err(|_| format!("wait must be seconds, got {value:?}"))?;
+                        wait = Duration::from_secs(secs).min(MAX_WAIT);
+                    }
+                    _ => {}
+                }
+            }
+            return Ok(Some(GatewayTarget::Heads { after, wait }));
+        }
+        _ => {}
+    }
+    let Some(rest) = path.strip_prefix("/v1/objects/") else {
+        return Ok(None);
+    };
+    let Some((kind, id)) = rest.split_once('/') else {
+        return Ok(None);
+    };
+    let kind = parse_kind(kind).ok_or_else(|| format!("unknown object kind {kind:?}"))?;
+    let id = decode_hex(id).ok_or_else(|| format!("invalid object id {id:?}"))?;
+    Ok(Some(GatewayTarget::Object { kind, id }))
+}
+
+pub fn repo_json(info: &RepoInfo) -> serde_json::Value {
+    serde_json::json!({
+        "protocolMajor": info.protocol_major,
+        "protocolMinor": info.protocol_minor,
+        "jjVersion": info.jj_version,
+        "tandemVersion": info.tandem_version,
+        "backendName": info.backend_name,
+        "opStoreName": info.op_store_name,
+        "commitHash": info.commit_hash,
+        "operationHash": info.operation_hash,
+        "rootCommitId": hex(&info.root_commit_id),
+        "rootChangeId": hex(&info.root_change_id),
+        "emptyTreeId": hex(&info.empty_tree_id),
+        "rootOperationId": hex(&info.root_operation_id),
+        "capabilities": info.capabilities.iter().map(|c| c.as_str()).collect::<Vec<_>>(),
+        "gitRemote": info.git_remote,
+        "snapshotAutoTrack": info.snapshot_auto_track,
+    })
+}
+
+pub fn heads_json(state: &HeadsState) -> serde_json::Value {
+    let workspace_heads: serde_json::Map<String, serde_json::Value> = state
+        .workspace_heads
+        .iter()
+        .map(|(workspace, op_id)| (workspace.clone(), hex(op_id).into()))
+        .collect();
+    serde_json::json!({
+        "version": state.version,
+        "heads": state.heads.iter().map(|head| hex(head)).collect::<Vec<_>>(),
+        "workspaceHeads": workspace_heads,
+    })
+}
+
+/// One upstream connection, reopened after it fails.
+struct Upstream {
+    addr: String,
+    client: Mutex<Option<Arc<TandemClient>>>,
+}
+
+impl Upstream {
+    async fn client(&self) -> Result<Arc<TandemClient>> {
+        let mut slot = self.client.lock().await;
+        if let Some(client) = slot.as_ref() {
+            return Ok(Arc::clone(client));
+        }
+        let client = Arc::new(
+            TandemClient::connect(&self.addr)
+                .await
+                .with_context(|| format!("cannot reach tandem server {}", self.addr))?,
+        );
+        *slot = Some(Arc::clone(&client));
+        Ok(client)
+    }
+
+    /// Forget `client` if it is still the current one, so the next request
+    /// dials again.
+    async fn reset(&self, client: &Arc<TandemClient>) {
+        let mut slot = self.client.lock().await;
+        if slot
+            .as_ref()
+            .is_some_and(|current| Arc::ptr_eq(current, client))
+        {
+            *slot = None;
+        }
+    }
+
+    async fn respond(&self, target: GatewayTarget) -> Reply {
+        let client = match self.client().await {
+            Ok(client) => client,
+            Err(err) => return Reply::error("502 Bad Gateway", &format!("{err:#}")),
+        };
+        let result = match target {
+            GatewayTarget::Repo => Ok(Reply::json(&repo_json(client.repo_info()))),
+            GatewayTarget::Heads { after, wait } => heads(&client, after, wait)
+                .await
+                .map(|state| Reply::json(&heads_json(&state))),
+            GatewayTarget::Object { kind, id } => client
+                .get_object(kind, &id)
+                .await
+                .map(|body| Reply::new("200 OK", "application/octet-stream", body)),
+        };
+        match result {
+            Ok(reply) => reply,
+            // The server answered but refused: most often an unknown id.
+            Err(err) if is_refusal(&err) => Reply::error("404 Not Found", &format!("{err:#}")),
+            Err(err) => {
+                tracing::warn!(error = %format!("{err:#}"), "upstream request failed; reconnecting");
+                self.reset(&client).await;
+                Reply::error("502 Bad Gateway", &format!("{err:#}"))
+            }
+        }
+    }
+}
+
+fn is_refusal(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<capnp::Error>()
+        .is_some_and(|err| err.kind != capnp::ErrorKind::Disconnected)
+}
+
+/// The heads now, or once they pass `after` (at most `wait` later).
+async fn heads(client: &TandemClient, after: Option<u64>, wait: Duration) -> Result<HeadsState> {
+    let Some(after) = after else {
+        return client.get_heads().await;
+    };
+    let current = client.get_heads().await?;
+    if current.version > after {
+        return Ok(current);
+    }
+    let mut watch = client.watch(after).await?;
+    let changed = tokio::time::timeout(wait, async {
+        while let Some(event) = watch.next().await {
+            if let WatchEvent::Changed(change) = event {
+                if change.version > after {
+                    return true;
+                }
+            }
+        }
+        false
+    })
+    .await
+    .unwrap_or(false);
+    if changed {
+        client.get_heads().await
+    } else {
+        Ok(current)
+    }
+}
+
+pub async fn run_gateway(opts: GatewayOptions) -> Result<()> {
+    let (log_tx, _) = broadcast::channel::<control::LogEvent>(1024);
+    logging::init_tracing(&opts.log_level, &opts.log_format, log_tx)?;
+
+    let upstream = Arc::new(Upstream {
+        addr: opts.upstream_addr.clone(),
+        client: Mutex::new(None),
+    });
+    // Fail fast on an unreachable upstream.
+    upstream.client().await?;
+
+    let listener = tokio::net::TcpListener::bind(&opts.listen_addr)
+        .await
+        .with_context(|| format!("failed to bind {}", opts.listen_addr))?;
+    tracing::info!(
+        listen_addr = %listener.local_addr()?,
+        upstream = %opts.upstream_addr,
+        "tandem gateway listening"
+    );
+
+    http::serve(listener, "gateway", move |request| {
+        let upstream = Arc::clone(&upstream);
+        async move { respond(&upstream, request).await }
+    })
+    .await;
+    Ok(())
+}
+
+async fn respond(upstream: &Upstream, request: http::Request) -> Reply {
+    if !request.is_read() {
+        return Reply::error("405 Method Not Allowed", "the gateway is read-only");
+    }
+    match parse_target(&request.path) {
+        Ok(Some(target)) => upstream.respond(target).await,
+        Err(message) => Reply::error("400 Bad Request", &message),
+        Ok(None) => Reply::error("404 Not Found", "not found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_gateway_paths() {
+        assert_eq!(parse_target("/v1/repo"), Ok(Some(GatewayTarget::Repo)));
+        assert_eq!(
+            parse_target("/v1/heads"),
+            Ok(Some(GatewayTarget::Heads {
+                after: None,
+                wait: DEFAULT_WAIT
+            }))
+        );
+        assert_eq!(
+            parse_target("/v1/heads?after=7&wait=9000"),
+            Ok(Some(GatewayTarget::Heads {
+                after: Some(7),
+                wait: MAX_WAIT
+            }))
+        );
+        assert_eq!(
+            parse_target("/v1/objects/file/00ff"),
+            Ok(Some(GatewayTarget::Object {
+                kind: ObjectKind::File,
+                id: vec![0x00, 0xff]
+            }))
+        );
+        assert!(parse_target("/v1/heads?after=soon").is_err());
+        assert!(parse_target("/v1/objects/blob/00ff").is_err());
+        assert!(parse_target("/v1/objects/file/0g").is_err());
+        assert_eq!(parse_target("/raw/main/README.md"), Ok(None));
+    }
+}
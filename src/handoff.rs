@@ -0,0 +1,93 @@
+//! `tandem handoff <workspace>` — give this workspace's change to another.
+//!
+//! For "agent drafts, human takes over": the target workspace starts editing
+//! the change this workspace has checked out, and this workspace moves to a
+//! fresh empty change on the same parents. Both working-copy commits move in
+//! one operation, so no other workspace ever sees the change checked out
+//! twice or nowhere. The target's previous working-copy commit is abandoned
+//! if it was empty, as `jj edit` would.
+//!
+//! Runs inside jj's CLI (it is registered as a custom jj subcommand), so the
+//! working copy is snapshotted first and updated afterwards like any jj
+//! command's. The target workspace's files catch up with
+//! `tandem workspace update-stale`, as after any jj command that rewrites
+//! another workspace's working copy.
+
+use std::io::Write as _;
+
+use jj_cli::cli_util::CommandHelper;
+use jj_cli::command_error::{user_error, CommandError};
+use jj_cli::ui::Ui;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::repo::Repo as _;
+
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum HandoffCommand {
+    /// Move this workspace's working-copy change to another workspace
+    Handoff(HandoffArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct HandoffArgs {
+    /// Workspace that takes over the change
+    target: String,
+}
+
+pub fn run(ui: &mut Ui, command: &CommandHelper, args: HandoffCommand) -> Result<(), CommandError> {
+    let HandoffCommand::Handoff(args) = args;
+    let mut workspace_command = command.workspace_helper(ui)?;
+    let own = workspace_command.workspace_name().to_owned();
+    let target = WorkspaceNameBuf::from(args.target);
+    if target == own {
+        return Err(user_error(format!(
+            "workspace '{}' is this workspace",
+            target.as_symbol()
+        )));
+    }
+    let repo = workspace_command.repo().clone();
+    let Some(wc_commit_id) = workspace_command.get_wc_commit_id().cloned() else {
+        return Err(user_error("this workspace has no working-copy commit"));
+    };
+    let Some(target_wc_commit_id) = repo.view().get_wc_commit_id(&target) else {
+        return Err(user_error(format!(
+            "no workspace named '{}'",
+            target.as_symbol()
+        )));
+    };
+    if *target_wc_commit_id == wc_commit_id {
+        return Err(user_error(format!(
+            "workspace '{}' is already editing this change",
+            target.as_symbol()
+        )));
+    }
+    let change = repo.store().get_commit(&wc_commit_id)?;
+
+    let mut tx = workspace_command.start_transaction();
+    tx.repo_mut().edit(target.clone(), &change)?;
+    let parent_tree = change.parent_tree(tx.repo())?;
+    let fresh = tx
+        .repo_mut()
+        .new_commit(change.parent_ids().to_vec(), parent_tree)
+        .write()?;
+    tx.edit(&fresh)?;
+    let summary = tx.format_commit_summary(&change);
+    tx.finish(
+        ui,
+        format!(
+            "hand off working-copy change from workspace {} to workspace {}",
+            own.as_symbol(),
+            target.as_symbol()
+        ),
+    )?;
+    writeln!(
+        ui.status(),
+        "Handed off {summary} to workspace '{}'",
+        target.as_symbol()
+    )?;
+    writeln!(
+        ui.hint_default(),
+        "Run `tandem workspace update-stale` in workspace '{}' to check it out there.",
+        target.as_symbol()
+    )?;
+    Ok(())
+}
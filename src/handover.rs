@@ -0,0 +1,477 @@
+//! Socket handover — `tandem server restart` without dropping clients.
+//!
+//! The running server (old) and its replacement (new) meet on a private Unix
+//! socket next to the control socket:
+//!
+//! 1. `tandem server restart` asks old over the control socket.
+//! 2. Old binds `<control socket>.handover` and runs its executable again
+//!    with the same arguments and `TANDEM_HANDOVER_SOCKET` set. The
+//!    executable on disk may be a newer tandem; that is the point.
+//! 3. New connects and receives old's listening sockets (`SCM_RIGHTS`) with
+//!    their addresses, instead of binding them itself. Clients keep
+//!    connecting to the same sockets throughout; the kernel queues them.
+//! 4. New opens the repo, takes over the control socket (bound under a
+//!    temporary name and renamed into place), and writes `ready`.
+//! 5. Old stops accepting and drains like any graceful shutdown, leaving the
+//!    sockets, control socket, and status file to new.
+//!
+//! If new exits or is not ready within [`TIMEOUT`], old kills it and keeps
+//! serving. While both run, op-heads updates are serialized across the two
+//! processes by [`HeadsLock`]. Handover needs Unix; elsewhere `restart` is
+//! refused.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use anyhow::Result;
+#[cfg(unix)]
+use anyhow::{anyhow, bail, Context};
+#[cfg(unix)]
+use serde::{Deserialize, Serialize};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+
+/// Set in the new server's environment: where to fetch the sockets from.
+pub const SOCKET_ENV: &str = "TANDEM_HANDOVER_SOCKET";
+
+/// How long the new server gets to open the repo and report ready.
+pub const TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The retry hint for clients refused while the old server drains: the new
+/// one is already accepting.
+pub const RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// The line the new server writes once it accepts clients.
+#[cfg(unix)]
+const READY: &str = "ready";
+
+/// Most sockets one handover carries.
+#[cfg(unix)]
+const MAX_SOCKETS: usize = 64;
+
+/// Socket kinds: RPC listeners (`--listen`), `--health-listen`, `--http-listen`.
+pub const LISTEN: &str = "listen";
+pub const HEALTH: &str = "health";
+pub const HTTP: &str = "http";
+
+/// A listening socket's descriptor, as handed to the new server.
+#[cfg(unix)]
+pub type SocketFd = RawFd;
+#[cfg(not(unix))]
+pub type SocketFd = ();
+
+/// A socket received from the server being replaced, owned by this process.
+#[cfg(unix)]
+pub type InheritedFd = OwnedFd;
+#[cfg(not(unix))]
+pub type InheritedFd = std::convert::Infallible;
+
+/// One listening socket the old server hands over.
+pub struct Socket {
+    pub kind: &'static str,
+    /// Bound address in `--listen` form.
+    pub addr: String,
+    pub fd: SocketFd,
+}
+
+/// The descriptor to hand over for `listener`.
+#[cfg(unix)]
+pub fn fd_of(listener: &impl AsRawFd) -> SocketFd {
+    listener.as_raw_fd()
+}
+
+#[cfg(not(unix))]
+pub fn fd_of<T>(_listener: &T) -> SocketFd {}
+
+/// The handover socket of the server whose control socket is `control_socket`.
+pub fn socket_path(control_socket: &str) -> PathBuf {
+    PathBuf::from(format!("{control_socket}.handover"))
+}
+
+// ─── Old server ──────────────────────────────────────────────────────────────
+
+#[cfg(unix)]
+#[derive(Serialize, Deserialize)]
+struct Manifest {
+    /// `(kind, address)` of each descriptor, in the order they are sent.
+    sockets: Vec<(String, String)>,
+}
+
+/// Start a replacement server and give it `sockets`. Returns its pid once it
+/// accepts clients; on failure it has been stopped again.
+#[cfg(unix)]
+pub async fn hand_over(sockets: &[Socket], control_socket: &str) -> Result<u32> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if sockets.len() > MAX_SOCKETS {
+        bail!("cannot hand over more than {MAX_SOCKETS} sockets");
+    }
+    let path = socket_path(control_socket);
+    let _ = std::fs::remove_file(&path);
+    let listener = tokio::net::UnixListener::bind(&path)
+        .with_context(|| format!("failed to bind {}", path.display()))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+
+    let exe = std::env::current_exe().context("cannot find the tandem executable")?;
+    let child = tokio::process::Command::new(&exe)
+        .args(std::env::args_os().skip(1))
+        .env(SOCKET_ENV, &path)
+        .spawn()
+        .with_context(|| format!("failed to start {}", exe.display()));
+    let mut child = match child {
+        Ok(child) => child,
+        Err(err) => {
+            let _ = std::fs::remove_file(&path);
+            return Err(err);
+        }
+    };
+    let pid = child.id().unwrap_or(0);
+    tracing::info!(pid, exe = %exe.display(), "started replacement server");
+
+    let result = tokio::select! {
+        result = tokio::time::timeout(TIMEOUT, send_sockets(&listener, sockets)) => match result {
+            Ok(result) => result,
+            Err(_) => Err(anyhow!("new server was not ready within {}s", TIMEOUT.as_secs())),
+        },
+        status = child.wait() => Err(match status {
+            Ok(status) => anyhow!("new server exited during handover ({status})"),
+            Err(err) => anyhow!("new server failed during handover: {err}"),
+        }),
+    };
+    let _ = std::fs::remove_file(&path);
+    if result.is_err() {
+        let _ = child.start_kill();
+    }
+    result.map(|()| pid)
+}
+
+#[cfg(not(unix))]
+pub async fn hand_over(_sockets: &[Socket], _control_socket: &str) -> Result<u32> {
+    anyhow::bail!(
+        "restart hands sockets over with SCM_RIGHTS, which needs Unix; stop and start the server instead"
+    )
+}
+
+#[cfg(unix)]
+async fn send_sockets(listener: &tokio::net::UnixListener, sockets: &[Socket]) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, BufReader, Interest};
+
+    let (stream, _) = listener.accept().await?;
+    let manifest = Manifest {
+        sockets: sockets
+            .iter()
+            .map(|socket| (socket.kind.to_string(), socket.addr.clone()))
+            .collect(),
+    };
+    let payload = serde_json::to_vec(&manifest)?;
+    let fds: Vec<RawFd> = sockets.iter().map(|socket| socket.fd).collect();
+    loop {
+        stream.writable().await?;
+        match stream.try_io(Interest::WRITABLE, || {
+            send_fds(stream.as_raw_fd(), &payload, &fds)
+        }) {
+            Ok(()) => break,
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => continue,
+            Err(err) => return Err(anyhow!("failed to send sockets: {err}")),
+        }
+    }
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).await?;
+    match line.trim() {
+        READY => Ok(()),
+        "" => bail!("new server exited before it was ready"),
+        other => bail!("new server failed to start: {other}"),
+    }
+}
+
+/// `sendmsg` with `fds` attached as `SCM_RIGHTS`.
+#[cfg(unix)]
+fn send_fds(socket: RawFd, payload: &[u8], fds: &[RawFd]) -> std::io::Result<()> {
+    let fd_bytes = std::mem::size_of_val(fds) as u32;
+    // u64 storage keeps the control buffer aligned for `cmsghdr`.
+    let space = unsafe { libc::CMSG_SPACE(fd_bytes) } as usize;
+    let mut control = vec![0u64; space.div_ceil(8)];
+    let mut iov = libc::iovec {
+        iov_base: payload.as_ptr() as *mut libc::c_void,
+        iov_len: payload.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = space as _;
+    let sent = unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(fd_bytes) as _;
+        std::ptr::copy_nonoverlapping(
+            fds.as_ptr(),
+            libc::CMSG_DATA(cmsg).cast::<RawFd>(),
+            fds.len(),
+        );
+        libc::sendmsg(socket, &msg, 0)
+    };
+    if sent < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    if sent as usize != payload.len() {
+        return Err(std::io::Error::other(
+            "short write of the handover manifest",
+        ));
+    }
+    Ok(())
+}
+
+// ─── New server ──────────────────────────────────────────────────────────────
+
+/// Sockets taken over from the server this process replaces.
+#[cfg(unix)]
+pub struct Inherited {
+    stream: std::os::unix::net::UnixStream,
+    sockets: Vec<(String, String, OwnedFd)>,
+}
+
+#[cfg(not(unix))]
+pub enum Inherited {}
+
+/// When this process was started by [`hand_over`], receive the old server's
+/// sockets.
+#[cfg(unix)]
+pub fn inherit() -> Result<Option<Inherited>> {
+    let Some(path) = std::env::var_os(SOCKET_ENV) else {
+        return Ok(None);
+    };
+    // Not for the processes this server starts in turn.
+    std::env::remove_var(SOCKET_ENV);
+    let stream = std::os::unix::net::UnixStream::connect(&path).with_context(|| {
+        format!(
+            "cannot connect to handover socket {}",
+            Path::new(&path).display()
+        )
+    })?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    let (payload, fds) = recv_fds(stream.as_raw_fd()).context("failed to receive sockets")?;
+    let manifest: Manifest =
+        serde_json::from_slice(&payload).context("invalid handover manifest")?;
+    if manifest.sockets.len() != fds.len() {
+        bail!(
+            "handover listed {} sockets but carried {}",
+            manifest.sockets.len(),
+            fds.len()
+        );
+    }
+    let sockets = manifest
+        .sockets
+        .into_iter()
+        .zip(fds)
+        .map(|((kind, addr), fd)| (kind, addr, fd))
+        .collect();
+    Ok(Some(Inherited { stream, sockets }))
+}
+
+#[cfg(not(unix))]
+pub fn inherit() -> Result<Option<Inherited>> {
+    Ok(None)
+}
+
+#[cfg(unix)]
+impl Inherited {
+    /// The sockets of `kind`, in the order the old server listed them, with
+    /// their addresses.
+    pub fn take(&mut self, kind: &str) -> Vec<(String, InheritedFd)> {
+        let (taken, rest) = std::mem::take(&mut self.sockets)
+            .into_iter()
+            .partition(|(socket_kind, _, _)| socket_kind == kind);
+        self.sockets = rest;
+        taken.into_iter().map(|(_, addr, fd)| (addr, fd)).collect()
+    }
+
+    /// Tell the old server to stop accepting and drain.
+    pub fn ready(mut self) -> Result<()> {
+        use std::io::Write;
+
+        writeln!(self.stream, "{READY}").context("failed to report ready")?;
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+impl Inherited {
+    pub fn take(&mut self, _kind: &str) -> Vec<(String, InheritedFd)> {
+        match *self {}
+    }
+
+    pub fn ready(self) -> Result<()> {
+        match self {}
+    }
+}
+
+/// An inherited TCP listener, registered with tokio.
+#[cfg(unix)]
+pub fn tcp_listener(fd: InheritedFd) -> Result<tokio::net::TcpListener> {
+    let listener = std::net::TcpListener::from(fd);
+    listener.set_nonblocking(true)?;
+    Ok(tokio::net::TcpListener::from_std(listener)?)
+}
+
+#[cfg(not(unix))]
+pub fn tcp_listener(fd: InheritedFd) -> Result<tokio::net::TcpListener> {
+    match fd {}
+}
+
+/// An inherited Unix socket listener, registered with tokio.
+#[cfg(unix)]
+pub fn unix_listener(fd: InheritedFd) -> Result<tokio::net::UnixListener> {
+    let listener = std::os::unix::net::UnixListener::from(fd);
+    listener.set_nonblocking(true)?;
+    Ok(tokio::net::UnixListener::from_std(listener)?)
+}
+
+/// `recvmsg` one manifest with its `SCM_RIGHTS` descriptors, close-on-exec.
+#[cfg(unix)]
+fn recv_fds(socket: RawFd) -> std::io::Result<(Vec<u8>, Vec<OwnedFd>)> {
+    let mut payload = vec![0u8; 64 * 1024];
+    let space =
+        unsafe { libc::CMSG_SPACE((MAX_SOCKETS * std::mem::size_of::<RawFd>()) as u32) } as usize;
+    let mut control = vec![0u64; space.div_ceil(8)];
+    let mut iov = libc::iovec {
+        iov_base: payload.as_mut_ptr().cast(),
+        iov_len: payload.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr().cast();
+    msg.msg_controllen = space as _;
+    let received = unsafe { libc::recvmsg(socket, &mut msg, 0) };
+    if received < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let mut fds = Vec::new();
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let data = libc::CMSG_DATA(cmsg).cast::<RawFd>();
+                // `cmsg_len` is `size_t` on Linux but `socklen_t` elsewhere.
+                let len: usize = (*cmsg).cmsg_len as _;
+                let count = (len - libc::CMSG_LEN(0) as usize) / std::mem::size_of::<RawFd>();
+                for i in 0..count {
+                    let fd = std::ptr::read_unaligned(data.add(i));
+                    libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+                    fds.push(OwnedFd::from_raw_fd(fd));
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    if msg.msg_flags & libc::MSG_CTRUNC != 0 {
+        return Err(std::io::Error::other("handover sockets were truncated"));
+    }
+    payload.truncate(received as usize);
+    Ok((payload, fds))
+}
+
+// ─── Heads lock ──────────────────────────────────────────────────────────────
+
+/// Serializes op-heads updates between threads and, through an advisory lock
+/// on `heads.lock`, between the old and new server during a handover.
+pub struct HeadsLock {
+    threads: Mutex<()>,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    file: File,
+}
+
+/// Held while `heads.json` is read and rewritten.
+pub struct HeadsGuard<'a> {
+    _threads: MutexGuard<'a, ()>,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    file: &'a File,
+}
+
+impl HeadsLock {
+    pub fn open(tandem_dir: &Path) -> Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(tandem_dir.join("heads.lock"))?;
+        Ok(Self {
+            threads: Mutex::new(()),
+            file,
+        })
+    }
+
+    pub fn lock(&self) -> std::io::Result<HeadsGuard<'_>> {
+        // The mutex guards no data, and `heads.json` is replaced atomically,
+        // so a thread that panicked while holding it left nothing half done.
+        let threads = self.threads.lock().unwrap_or_else(PoisonError::into_inner);
+        #[cfg(unix)]
+        if unsafe { libc::flock(self.file.as_raw_fd(), libc::LOCK_EX) } != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(HeadsGuard {
+            _threads: threads,
+            file: &self.file,
+        })
+    }
+}
+
+impl Drop for HeadsGuard<'_> {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        unsafe {
+            libc::flock(self.file.as_raw_fd(), libc::LOCK_UN);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sockets_travel_with_their_manifest() {
+        let (left, right) = std::os::unix::net::UnixStream::pair().unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        send_fds(left.as_raw_fd(), b"manifest", &[listener.as_raw_fd()]).unwrap();
+        let (payload, fds) = recv_fds(right.as_raw_fd()).unwrap();
+        assert_eq!(payload, b"manifest");
+        assert_eq!(fds.len(), 1);
+
+        drop(listener);
+        let received = std::net::TcpListener::from(fds.into_iter().next().unwrap());
+        assert_eq!(received.local_addr().unwrap(), addr);
+        let _client = std::net::TcpStream::connect(addr).unwrap();
+        received.accept().unwrap();
+    }
+
+    #[test]
+    fn take_returns_sockets_of_one_kind_in_order() {
+        let (stream, _other) = std::os::unix::net::UnixStream::pair().unwrap();
+        let null = || OwnedFd::from(File::open("/dev/null").unwrap());
+        let mut inherited = Inherited {
+            stream,
+            sockets: vec![
+                (LISTEN.into(), "a".into(), null()),
+                (HEALTH.into(), "h".into(), null()),
+                (LISTEN.into(), "b".into(), null()),
+            ],
+        };
+        let listen: Vec<String> = inherited
+            .take(LISTEN)
+            .into_iter()
+            .map(|(addr, _)| addr)
+            .collect();
+        assert_eq!(listen, ["a", "b"]);
+        assert_eq!(inherited.take(HEALTH).len(), 1);
+        assert!(inherited.take(HTTP).is_empty());
+    }
+}
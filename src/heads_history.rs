@@ -0,0 +1,171 @@
+//! Heads history — a bounded journal of heads-version transitions.
+//!
+//! Every version bump on the server appends one entry: the new version, the
+//! op heads after it, the workspace whose `updateOpHeads` caused it (none for
+//! server-originated changes such as integration or reconciliation), and a
+//! timestamp. `getHeadsHistory(afterVersion)` serves these so debugging tools
+//! and watchers that fell behind can see every step, not just the latest state.
+//!
+//! The journal lives in `.jj/repo/tandem/heads-history.jsonl`: one JSON entry
+//! per line after a schema version header (see
+//! [`metadata_schema::HEADS_HISTORY`]), appended in place and rewritten down
+//! to the newest `capacity` entries once the file holds twice that many.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::metadata_schema;
+
+pub const DEFAULT_CAPACITY: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadsTransition {
+    pub version: u64,
+    /// Hex-encoded op heads after this transition.
+    pub heads: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    pub timestamp_millis: u64,
+}
+
+pub struct HeadsHistory {
+    path: PathBuf,
+    capacity: usize,
+    state: Mutex<JournalState>,
+}
+
+struct JournalState {
+    entries: VecDeque<HeadsTransition>,
+    /// Lines currently in the file, including ones already evicted in memory.
+    lines_on_disk: usize,
+}
+
+impl HeadsHistory {
+    /// Load the journal at `path`, keeping the newest `capacity` entries.
+    /// Unreadable lines (e.g. a torn final write) are skipped.
+    pub fn open(path: PathBuf, capacity: usize) -> Result<Self> {
+        let capacity = capacity.max(1);
+        let mut entries = VecDeque::new();
+        let mut lines_on_disk = 0;
+        for line in metadata_schema::read_journal(&metadata_schema::HEADS_HISTORY, &path)? {
+            lines_on_disk += 1;
+            match serde_json::from_str::<HeadsTransition>(&line) {
+                Ok(entry) => {
+                    entries.push_back(entry);
+                    if entries.len() > capacity {
+                        entries.pop_front();
+                    }
+                }
+                Err(err) => {
+                    tracing::warn!(path = %path.display(), error = %err, "skipping bad heads history line")
+                }
+            }
+        }
+        Ok(Self {
+            path,
+            capacity,
+            state: Mutex::new(JournalState {
+                entries,
+                lines_on_disk,
+            }),
+        })
+    }
+
+    pub fn record(&self, entry: HeadsTransition) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let line = serde_json::to_string(&entry)?;
+        state.entries.push_back(entry);
+        if state.entries.len() > self.capacity {
+            state.entries.pop_front();
+        }
+
+        if state.lines_on_disk + 1 >= self.capacity * 2 {
+            let records = state
+                .entries
+                .iter()
+                .map(serde_json::to_string)
+                .collect::<serde_json::Result<Vec<_>>>()?;
+            metadata_schema::write_journal(&metadata_schema::HEADS_HISTORY, &self.path, &records)?;
+            state.lines_on_disk = records.len();
+        } else {
+            metadata_schema::append_journal(&metadata_schema::HEADS_HISTORY, &self.path, &line)?;
+            state.lines_on_disk += 1;
+        }
+        Ok(())
+    }
+
+    /// Entries with `version > after_version`, oldest first, plus the oldest
+    /// version still retained (0 when the journal is empty). A caller whose
+    /// `after_version + 1` is below that has lost history to eviction.
+    pub fn since(&self, after_version: u64) -> (Vec<HeadsTransition>, u64) {
+        let state = self.state.lock().unwrap();
+        let oldest = state.entries.front().map_or(0, |entry| entry.version);
+        let entries = state
+            .entries
+            .iter()
+            .filter(|entry| entry.version > after_version)
+            .cloned()
+            .collect();
+        (entries, oldest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transition(version: u64) -> HeadsTransition {
+        HeadsTransition {
+            version,
+            heads: vec![format!("{version:02x}")],
+            workspace_id: version.is_multiple_of(2).then(|| "agent-a".to_string()),
+            timestamp_millis: version * 1000,
+        }
+    }
+
+    #[test]
+    fn keeps_newest_entries_and_reports_oldest() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = HeadsHistory::open(dir.path().join("h.jsonl"), 3).unwrap();
+        for version in 1..=5 {
+            history.record(transition(version)).unwrap();
+        }
+        let (entries, oldest) = history.since(0);
+        assert_eq!(oldest, 3);
+        assert_eq!(
+            entries.iter().map(|e| e.version).collect::<Vec<_>>(),
+            vec![3, 4, 5]
+        );
+        let (entries, _) = history.since(4);
+        assert_eq!(entries, vec![transition(5)]);
+    }
+
+    #[test]
+    fn survives_reopen_and_compacts_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("h.jsonl");
+        {
+            let history = HeadsHistory::open(path.clone(), 2).unwrap();
+            for version in 1..=4 {
+                history.record(transition(version)).unwrap();
+            }
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents.lines().next(), Some(r#"{"schemaVersion":1}"#));
+        let lines = contents.lines().count();
+        assert!(
+            lines <= 3,
+            "journal should have been compacted: {lines} lines"
+        );
+
+        let reopened = HeadsHistory::open(path, 2).unwrap();
+        let (entries, oldest) = reopened.since(0);
+        assert_eq!(oldest, 3);
+        assert_eq!(entries, vec![transition(3), transition(4)]);
+    }
+}
@@ -0,0 +1,280 @@
+//! Heads summaries — what a heads change did, for watchers that ask.
+//!
+//! `watchHeads(withSummary = true)` makes every notification carry a summary
+//! of the new head operation against its parents: the operation's
+//! description and author, the workspace that published it, the local
+//! bookmarks it moved, and the paths touched by the commits it added. A
+//! watcher that shows "agent-b committed `fix parser` touching src/parse.rs"
+//! gets that from the notification instead of a round of `getOperation`,
+//! `getView` and `getObject` calls per change.
+//!
+//! Touched paths are capped (the watcher's `summaryPathLimit`, at most
+//! [`MAX_PATH_LIMIT`]) so one sweeping rewrite cannot bloat every
+//! notification; `pathsTruncated` says when the list stops short.
+//!
+//! The server also attaches the path overlaps between the publishing
+//! workspace and the others (see `path_overlaps.rs`).
+
+use std::collections::BTreeSet;
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt as _;
+use jj_lib::backend::CommitId;
+use jj_lib::matchers::EverythingMatcher;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::OperationId;
+use jj_lib::repo::{Repo as _, RepoLoader};
+use jj_lib::revset::ResolvedRevsetExpression;
+use jj_lib::rewrite::merge_commit_trees;
+use serde::Serialize;
+
+use crate::rpc::PathOverlap;
+use crate::tandem_capnp::heads_summary;
+
+/// Touched paths per summary when the watcher does not pick a limit.
+pub const DEFAULT_PATH_LIMIT: usize = 100;
+/// Upper bound on touched paths per summary, whatever the watcher asks for.
+pub const MAX_PATH_LIMIT: usize = 1000;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HeadsSummary {
+    pub description: String,
+    /// `user@host` of the operation.
+    pub author: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    /// Local bookmarks whose target differs from every parent operation's.
+    pub bookmarks: Vec<String>,
+    /// Repo-relative paths changed by the commits the operation added.
+    pub paths: Vec<String>,
+    pub paths_truncated: bool,
+    /// Paths the publishing workspace and another have both changed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub overlaps: Vec<PathOverlap>,
+}
+
+/// The path limit a `watchHeads` request asked for, with 0 meaning the
+/// default.
+pub fn path_limit(requested: u32) -> usize {
+    match requested {
+        0 => DEFAULT_PATH_LIMIT,
+        n => (n as usize).min(MAX_PATH_LIMIT),
+    }
+}
+
+impl HeadsSummary {
+    /// Summarize the head operations `heads`. With several heads, the
+    /// description and author come from the one that ended last, and the
+    /// bookmarks and paths are those of all of them.
+    pub fn compute(
+        repo_loader: &RepoLoader,
+        heads: &[OperationId],
+        workspace_id: Option<&str>,
+        path_limit: usize,
+    ) -> Result<Self> {
+        let op_store = repo_loader.op_store();
+        let mut ops = Vec::with_capacity(heads.len());
+        for head in heads {
+            let op = pollster::block_on(op_store.read_operation(head))
+                .map_err(|e| anyhow!("read operation {}: {e}", head.hex()))?;
+            ops.push((head, op));
+        }
+        let mut summary = HeadsSummary {
+            workspace_id: workspace_id.filter(|id| !id.is_empty()).map(str::to_string),
+            ..HeadsSummary::default()
+        };
+        if let Some((_, latest)) = ops
+            .iter()
+            .max_by_key(|(_, op)| op.metadata.time.end.timestamp.0)
+        {
+            summary.description = latest.metadata.description.clone();
+            summary.author = format!("{}@{}", latest.metadata.username, latest.metadata.hostname);
+        }
+
+        let mut bookmarks = BTreeSet::new();
+        let mut paths = BTreeSet::new();
+        for (head, op) in &ops {
+            let view = pollster::block_on(op_store.read_view(&op.view_id))
+                .map_err(|e| anyhow!("read view for operation {}: {e}", head.hex()))?;
+            let mut parent_views = Vec::with_capacity(op.parents.len());
+            for parent in &op.parents {
+                let parent_op = pollster::block_on(op_store.read_operation(parent))
+                    .map_err(|e| anyhow!("read operation {}: {e}", parent.hex()))?;
+                let parent_view = pollster::block_on(op_store.read_view(&parent_op.view_id))
+                    .map_err(|e| anyhow!("read view for operation {}: {e}", parent.hex()))?;
+                parent_views.push(parent_view);
+            }
+            let names = view
+                .local_bookmarks
+                .keys()
+                .chain(parent_views.iter().flat_map(|v| v.local_bookmarks.keys()));
+            for name in names {
+                let target = view.local_bookmarks.get(name);
+                if parent_views
+                    .iter()
+                    .all(|parent| parent.local_bookmarks.get(name) != target)
+                {
+                    bookmarks.insert(name.as_str().to_string());
+                }
+            }
+
+            if paths.len() < path_limit {
+                let (touched, truncated) =
+                    touched_paths(repo_loader, &op.parents, head, path_limit - paths.len())?;
+                paths.extend(touched);
+                summary.paths_truncated |= truncated;
+            } else {
+                summary.paths_truncated = true;
+            }
+        }
+        summary.bookmarks = bookmarks.into_iter().collect();
+        summary.paths = paths.into_iter().take(path_limit).collect();
+        Ok(summary)
+    }
+
+    /// This summary with at most `path_limit` paths.
+    pub fn limited(&self, path_limit: usize) -> Self {
+        let mut summary = self.clone();
+        if summary.paths.len() > path_limit {
+            summary.paths.truncate(path_limit);
+            summary.paths_truncated = true;
+        }
+        summary
+    }
+
+    pub fn write(&self, mut builder: heads_summary::Builder<'_>) {
+        builder.set_description(self.description.as_str());
+        builder.set_author(self.author.as_str());
+        if let Some(workspace_id) = &self.workspace_id {
+            builder.set_workspace_id(workspace_id.as_str());
+        }
+        {
+            let mut list = builder
+                .reborrow()
+                .init_bookmarks(self.bookmarks.len() as u32);
+            for (i, name) in self.bookmarks.iter().enumerate() {
+                list.set(i as u32, name.as_str());
+            }
+        }
+        {
+            let mut list = builder.reborrow().init_paths(self.paths.len() as u32);
+            for (i, path) in self.paths.iter().enumerate() {
+                list.set(i as u32, path.as_str());
+            }
+        }
+        builder.set_paths_truncated(self.paths_truncated);
+        let mut list = builder.init_overlaps(self.overlaps.len() as u32);
+        for (i, overlap) in self.overlaps.iter().enumerate() {
+            overlap.write(list.reborrow().get(i as u32));
+        }
+    }
+
+    pub fn read(reader: heads_summary::Reader<'_>) -> capnp::Result<Self> {
+        let text = |t: capnp::text::Reader<'_>| t.to_string().unwrap_or_default();
+        let list = |l: capnp::text_list::Reader<'_>| -> capnp::Result<Vec<String>> {
+            l.iter().map(|t| t.map(text)).collect()
+        };
+        let workspace_id = text(reader.get_workspace_id()?);
+        Ok(HeadsSummary {
+            description: text(reader.get_description()?),
+            author: text(reader.get_author()?),
+            workspace_id: (!workspace_id.is_empty()).then_some(workspace_id),
+            bookmarks: list(reader.get_bookmarks()?)?,
+            paths: list(reader.get_paths()?)?,
+            paths_truncated: reader.get_paths_truncated(),
+            overlaps: reader
+                .get_overlaps()?
+                .iter()
+                .map(PathOverlap::read)
+                .collect::<capnp::Result<_>>()?,
+        })
+    }
+}
+
+/// Paths changed by the commits `new_op_id` added on top of `old_op_ids`,
+/// stopping after `limit`. Returns whether the walk stopped short.
+pub fn touched_paths(
+    repo_loader: &RepoLoader,
+    old_op_ids: &[OperationId],
+    new_op_id: &OperationId,
+    limit: usize,
+) -> Result<(BTreeSet<String>, bool)> {
+    let op_store = repo_loader.op_store();
+    let mut old_heads = Vec::new();
+    for old_op_id in old_op_ids {
+        let old_op = pollster::block_on(op_store.read_operation(old_op_id))
+            .map_err(|e| anyhow!("read operation {}: {e}", old_op_id.hex()))?;
+        let old_view = pollster::block_on(op_store.read_view(&old_op.view_id))
+            .map_err(|e| anyhow!("read view for operation {}: {e}", old_op_id.hex()))?;
+        old_heads.extend(old_view.head_ids);
+    }
+
+    let new_op = repo_loader
+        .load_operation(new_op_id)
+        .map_err(|e| anyhow!("load operation {}: {e}", new_op_id.hex()))?;
+    let repo = repo_loader
+        .load_at(&new_op)
+        .map_err(|e| anyhow!("load repo at {}: {e}", new_op_id.hex()))?;
+    let new_heads: Vec<CommitId> = repo.view().heads().iter().cloned().collect();
+
+    let added = ResolvedRevsetExpression::commits(old_heads)
+        .range(&ResolvedRevsetExpression::commits(new_heads))
+        .evaluate(repo.as_ref())
+        .map_err(|e| anyhow!("evaluate new commits: {e}"))?;
+
+    let mut touched = BTreeSet::new();
+    for commit_id in added.iter() {
+        let commit_id = commit_id.map_err(|e| anyhow!("walk new commits: {e}"))?;
+        let commit = repo
+            .store()
+            .get_commit(&commit_id)
+            .map_err(|e| anyhow!("load commit {}: {e}", commit_id.hex()))?;
+        let parents = commit
+            .parent_ids()
+            .iter()
+            .map(|id| repo.store().get_commit(id))
+            .collect::<std::result::Result<Vec<_>, _>>()
+            .map_err(|e| anyhow!("load parent commit: {e}"))?;
+        let parent_tree = pollster::block_on(merge_commit_trees(repo.as_ref(), &parents))
+            .map_err(|e| anyhow!("merge parent trees: {e}"))?;
+        let tree = pollster::block_on(merge_commit_trees(
+            repo.as_ref(),
+            std::slice::from_ref(&commit),
+        ))
+        .map_err(|e| anyhow!("load commit tree: {e}"))?;
+        let mut diff = parent_tree.diff_stream(&tree, &EverythingMatcher);
+        while let Some(entry) = pollster::block_on(diff.next()) {
+            let path = entry.path.as_internal_file_string().to_string();
+            if touched.len() >= limit && !touched.contains(&path) {
+                return Ok((touched, true));
+            }
+            touched.insert(path);
+        }
+    }
+    Ok((touched, false))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_limit_defaults_and_caps() {
+        assert_eq!(path_limit(0), DEFAULT_PATH_LIMIT);
+        assert_eq!(path_limit(3), 3);
+        assert_eq!(path_limit(u32::MAX), MAX_PATH_LIMIT);
+    }
+
+    #[test]
+    fn limited_marks_dropped_paths() {
+        let summary = HeadsSummary {
+            paths: vec!["a".into(), "b".into(), "c".into()],
+            ..HeadsSummary::default()
+        };
+        let limited = summary.limited(2);
+        assert_eq!(limited.paths, ["a", "b"]);
+        assert!(limited.paths_truncated);
+        assert_eq!(summary.limited(5), summary);
+    }
+}
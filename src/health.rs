@@ -0,0 +1,141 @@
+//! Health checks — is the server able to serve, not just running?
+//!
+//! `tandem server status` only proves the process answers on its control
+//! socket. A health check also asks the RPC server (on its own task set) to
+//! read the op-heads store, load the repo at head, and read a head commit
+//! straight from the git backend. The report is available as a control
+//! socket `health` request (`tandem server health`) and, with
+//! `tandem serve --health-listen <addr>`, over plain HTTP:
+//!
+//!   GET /healthz  →  200 {"healthy":true,"checks":[...]}
+//!                    503 when any check fails or the server does not answer
+//!
+//! The HTTP endpoint exists for container orchestrators that cannot speak the
+//! control socket protocol; it serves nothing else.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::http;
+
+/// How long the RPC server has to answer before it counts as not ready.
+pub const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// One probe in a health report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthCheck {
+    pub name: String,
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl HealthCheck {
+    pub fn from_result(name: &str, result: anyhow::Result<Option<String>>) -> Self {
+        match result {
+            Ok(detail) => Self {
+                name: name.to_string(),
+                ok: true,
+                detail,
+            },
+            Err(err) => Self {
+                name: name.to_string(),
+                ok: false,
+                detail: Some(format!("{err:#}")),
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HealthResponse {
+    pub healthy: bool,
+    pub checks: Vec<HealthCheck>,
+}
+
+impl HealthResponse {
+    pub fn from_checks(checks: Vec<HealthCheck>) -> Self {
+        Self {
+            healthy: checks.iter().all(|check| check.ok),
+            checks,
+        }
+    }
+}
+
+/// Health request handed to the RPC server, which owns the repo.
+pub struct HealthRequest {
+    pub reply: oneshot::Sender<HealthResponse>,
+}
+
+/// Ask the RPC server for a report; a server that is shutting down or busy
+/// past [`HEALTH_TIMEOUT`] is reported as unhealthy.
+pub async fn request(tx: &mpsc::Sender<HealthRequest>) -> HealthResponse {
+    let (reply, rx) = oneshot::channel();
+    let outcome = match tx.send(HealthRequest { reply }).await {
+        Ok(()) => match tokio::time::timeout(HEALTH_TIMEOUT, rx).await {
+            Ok(Ok(report)) => return report,
+            Ok(Err(_)) => "server dropped the health request".to_string(),
+            Err(_) => format!("server did not answer within {}s", HEALTH_TIMEOUT.as_secs()),
+        },
+        Err(_) => "server is shutting down".to_string(),
+    };
+    HealthResponse::from_checks(vec![HealthCheck {
+        name: "server".to_string(),
+        ok: false,
+        detail: Some(outcome),
+    }])
+}
+
+/// Answer `GET /healthz` on `listener` until the process exits.
+pub async fn serve_http(listener: tokio::net::TcpListener, tx: mpsc::Sender<HealthRequest>) {
+    http::serve(listener, "health", move |request| {
+        let tx = tx.clone();
+        async move { respond(&tx, &request).await }
+    })
+    .await
+}
+
+async fn respond(tx: &mpsc::Sender<HealthRequest>, incoming: &http::Request) -> http::Reply {
+    if !incoming.is_read() || incoming.path != "/healthz" {
+        return http::Reply::error("404 Not Found", "not found");
+    }
+    let report = request(tx).await;
+    let status = if report.healthy {
+        "200 OK"
+    } else {
+        "503 Service Unavailable"
+    };
+    http::Reply {
+        status,
+        ..http::Reply::json(&report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn healthy_only_when_every_check_passes() {
+        let ok = HealthCheck::from_result("repo", Ok(None));
+        let failed = HealthCheck::from_result("git", Err(anyhow::anyhow!("object missing")));
+        assert_eq!(failed.detail.as_deref(), Some("object missing"));
+        assert!(HealthResponse::from_checks(vec![ok.clone()]).healthy);
+        assert!(!HealthResponse::from_checks(vec![ok, failed]).healthy);
+    }
+
+    #[test]
+    fn unanswered_request_is_unhealthy() {
+        let (tx, rx) = mpsc::channel(1);
+        drop(rx);
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let report = rt.block_on(request(&tx));
+        assert!(!report.healthy);
+        assert_eq!(report.checks[0].name, "server");
+    }
+}
@@ -0,0 +1,257 @@
+//! The plain HTTP/1.1 front shared by `--health-listen`, `--http-listen`,
+//! and `tandem gateway`.
+//!
+//! Each connection carries one read-only request and is closed after the
+//! response. The request line and headers must arrive within
+//! [`READ_TIMEOUT`] and fit in [`MAX_HEAD_BYTES`]; a request body is never
+//! read. The response gets [`WRITE_TIMEOUT`] to drain, so a client that
+//! stops reading cannot hold a task forever.
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+/// Most bytes of request line plus headers read from one request.
+pub const MAX_HEAD_BYTES: u64 = 16 * 1024;
+
+/// How long a client has to send its request line and headers.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a client has to take the response.
+pub const WRITE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The parts of a request the endpoints route on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Request {
+    pub method: String,
+    /// The request target as sent, query string included.
+    pub path: String,
+}
+
+impl Request {
+    /// Whether the request only reads; everything served here is read-only.
+    pub fn is_read(&self) -> bool {
+        matches!(self.method.as_str(), "GET" | "HEAD")
+    }
+}
+
+/// A finished response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reply {
+    pub status: &'static str,
+    pub content_type: &'static str,
+    /// Extra headers, after `Content-Type` and `Content-Length`.
+    pub headers: Vec<(&'static str, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    pub fn new(status: &'static str, content_type: &'static str, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            content_type,
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    /// `200 OK` with `value` as the JSON body.
+    pub fn json(value: &impl Serialize) -> Self {
+        match serde_json::to_vec(value) {
+            Ok(body) => Self::new("200 OK", "application/json", body),
+            Err(err) => Self::error("500 Internal Server Error", &err.to_string()),
+        }
+    }
+
+    /// `{"error": message}` with `status`.
+    pub fn error(status: &'static str, message: &str) -> Self {
+        let body = serde_json::json!({ "error": message })
+            .to_string()
+            .into_bytes();
+        Self::new(status, "application/json", body)
+    }
+
+    pub fn with_header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+/// Answer requests on `listener` with `handler` until the process exits.
+/// `what` names the endpoint in logs.
+pub async fn serve<H, F>(listener: tokio::net::TcpListener, what: &'static str, handler: H)
+where
+    H: Fn(Request) -> F + Clone + Send + 'static,
+    F: Future<Output = Reply> + Send,
+{
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                tracing::debug!(endpoint = what, error = %err, "http accept failed");
+                continue;
+            }
+        };
+        let handler = handler.clone();
+        tokio::spawn(async move {
+            if let Err(err) = handle(stream, handler).await {
+                tracing::debug!(endpoint = what, peer = %peer, error = %err, "http connection error");
+            }
+        });
+    }
+}
+
+async fn handle<H, F>(stream: tokio::net::TcpStream, handler: H) -> Result<()>
+where
+    H: Fn(Request) -> F,
+    F: Future<Output = Reply>,
+{
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let (request, reply) = match tokio::time::timeout(READ_TIMEOUT, read_request(&mut reader)).await
+    {
+        Err(_) => (
+            None,
+            Reply::error("408 Request Timeout", "request timed out"),
+        ),
+        Ok(Err(err)) => (None, Reply::error("400 Bad Request", &format!("{err:#}"))),
+        Ok(Ok(None)) => return Ok(()),
+        Ok(Ok(Some(request))) => {
+            let reply = handler(request.clone()).await;
+            (Some(request), reply)
+        }
+    };
+    let with_body = request.is_none_or(|request| request.method != "HEAD");
+    tokio::time::timeout(WRITE_TIMEOUT, write_reply(&mut writer, &reply, with_body))
+        .await
+        .map_err(|_| anyhow!("client did not take the response within {WRITE_TIMEOUT:?}"))?
+}
+
+/// Read the request line and drain the headers. `None` when the client
+/// hung up without sending anything.
+async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<Request>> {
+    let mut budget = MAX_HEAD_BYTES;
+    let Some(request_line) = read_head_line(reader, &mut budget).await? else {
+        return Ok(None);
+    };
+    while let Some(header) = read_head_line(reader, &mut budget).await? {
+        if header.is_empty() {
+            break;
+        }
+    }
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        bail!("malformed request line");
+    };
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+    }))
+}
+
+/// One CRLF- or LF-terminated line, charged against `budget`. `None` at
+/// end of stream.
+async fn read_head_line<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    budget: &mut u64,
+) -> Result<Option<String>> {
+    if *budget == 0 {
+        bail!("request headers exceed {MAX_HEAD_BYTES} bytes");
+    }
+    let mut line = Vec::new();
+    let read = (&mut *reader)
+        .take(*budget)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if read == 0 {
+        return Ok(None);
+    }
+    *budget -= read as u64;
+    // A line cut short with budget left means the client hung up mid-line;
+    // take what arrived.
+    if line.last() != Some(&b'\n') && *budget == 0 {
+        bail!("request headers exceed {MAX_HEAD_BYTES} bytes");
+    }
+    let line = String::from_utf8(line).map_err(|_| anyhow!("request headers are not UTF-8"))?;
+    Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+}
+
+async fn write_reply<W: tokio::io::AsyncWrite + Unpin>(
+    writer: &mut W,
+    reply: &Reply,
+    with_body: bool,
+) -> Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\n",
+        reply.status,
+        reply.content_type,
+        reply.body.len()
+    );
+    for (name, value) in &reply.headers {
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("Connection: close\r\n\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    if with_body {
+        writer.write_all(&reply.body).await?;
+    }
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(input: &[u8]) -> Result<Option<Request>> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let mut reader = BufReader::new(input);
+        rt.block_on(read_request(&mut reader))
+    }
+
+    #[test]
+    fn reads_the_request_line_and_skips_headers() {
+        let request = read(b"GET /api/heads HTTP/1.1\r\nHost: x\r\nAccept: */*\r\n\r\n")
+            .unwrap()
+            .unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/api/heads");
+        assert!(request.is_read());
+        assert_eq!(read(b"").unwrap(), None);
+        assert!(read(b"GET\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn refuses_oversized_headers() {
+        let mut input = b"GET / HTTP/1.1\r\nX-Filler: ".to_vec();
+        input.resize(MAX_HEAD_BYTES as usize * 2, b'a');
+        let err = read(&input).unwrap_err();
+        assert!(err.to_string().contains("exceed"), "{err}");
+
+        let many = format!(
+            "GET / HTTP/1.1\r\n{}\r\n",
+            "X-A: b\r\n".repeat(MAX_HEAD_BYTES as usize / 8 + 1)
+        );
+        assert!(read(many.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn replies_carry_extra_headers() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let reply =
+            Reply::error("404 Not Found", "not found").with_header("Cache-Control", "no-store");
+        let mut out = Vec::new();
+        rt.block_on(write_reply(&mut out, &reply, false)).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("HTTP/1.1 404 Not Found\r\n"), "{out}");
+        assert!(out.contains("Cache-Control: no-store\r\n"), "{out}");
+        assert!(out.ends_with("\r\n\r\n"), "{out}");
+    }
+}
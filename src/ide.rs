@@ -0,0 +1,404 @@
+//! tandem ide-server — JSON-RPC over stdio for editor integrations.
+//!
+//! Editor plugins (VS Code, Neovim, ...) spawn `tandem ide-server` once and
+//! keep it running instead of shelling out to the CLI for every status-bar
+//! refresh. Each line on stdin is a JSON-RPC 2.0 request; each line on stdout
+//! is a response or notification:
+//!
+//!   heads                        → {version, heads, workspaces: {name: opId}}
+//!   presence                     → [{workspace, change, description, committedAtMs, ahead, behind}]
+//!   whoTouched {path}            → [{workspace, change, description}]
+//!   subscribe {since?, summary?} → {subscribed: true}, then `headsChanged` notifications
+//!   shutdown                     → null, then exit
+//!
+//! `whoTouched` lists the other workspaces whose working-copy commit changes
+//! `path` (repo-relative, `/`-separated) against its first parent. The
+//! server connection is shared by all requests; `subscribe` opens a second
+//! one for `watchHeads`, which ends with `serverShuttingDown` (when the server
+//! stops gracefully) and `disconnected`. stdin EOF ends the server.
+
+use std::io::{BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use jj_lib::backend::{CommitId, Tree, TreeValue};
+use jj_lib::hex_util::encode_hex;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use prost::Message as _;
+use serde_json::{json, Value};
+
+use crate::proto_convert;
+use crate::ps;
+use crate::rpc::{RepoCapability, TandemClient};
+use crate::watch::{self, HeadsUpdate, WatchFrom};
+
+const KIND_TREE: u16 = 1;
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// Implementation-defined server error: the tandem server call failed.
+const SERVER_ERROR: i64 = -32000;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::new(SERVER_ERROR, format!("{err:#}"))
+    }
+}
+
+type Stdout = Arc<Mutex<std::io::Stdout>>;
+
+fn send(out: &Stdout, message: &Value) {
+    let mut out = out.lock().unwrap();
+    // A closed stdout means the editor went away; stdin EOF ends the loop.
+    let _ = writeln!(out, "{message}");
+    let _ = out.flush();
+}
+
+fn notify(out: &Stdout, method: &str, params: Value) {
+    send(
+        out,
+        &json!({"jsonrpc": "2.0", "method": method, "params": params}),
+    );
+}
+
+// ─── Public entry point ───────────────────────────────────────────────────────
+
+pub fn run_ide_server(server_addr: &str, own_workspace: Option<&str>) -> Result<()> {
+    let client = TandemClient::connect(server_addr)
+        .with_context(|| format!("cannot connect to {server_addr}"))?;
+    let out: Stdout = Arc::new(Mutex::new(std::io::stdout()));
+    let mut subscribed = false;
+    let mut ancestry = ps::AncestryCache::default();
+
+    for line in std::io::stdin().lock().lines() {
+        let line = line.context("reading stdin")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Value = match serde_json::from_str(&line) {
+            Ok(request) => request,
+            Err(err) => {
+                send(
+                    &out,
+                    &error_response(Value::Null, RpcError::new(PARSE_ERROR, err.to_string())),
+                );
+                continue;
+            }
+        };
+        let id = request.get("id").cloned();
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+        let method = request.get("method").and_then(Value::as_str);
+        // Dropped after the response is written (see `subscribe`).
+        let mut replied = None;
+
+        let result = match method {
+            Some("heads") => heads(&client),
+            Some("presence") => presence(&client, &mut ancestry),
+            Some("whoTouched") => who_touched(&client, own_workspace, &params),
+            Some("subscribe") if subscribed => {
+                Err(RpcError::new(INVALID_REQUEST, "already subscribed"))
+            }
+            Some("subscribe") => {
+                subscribe(&client, server_addr, &params, &out).map(|(result, sender)| {
+                    subscribed = true;
+                    replied = Some(sender);
+                    result
+                })
+            }
+            Some("shutdown") => {
+                if let Some(id) = id {
+                    send(&out, &json!({"jsonrpc": "2.0", "id": id, "result": null}));
+                }
+                return Ok(());
+            }
+            Some(other) => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method {other}"),
+            )),
+            None => Err(RpcError::new(INVALID_REQUEST, "missing method")),
+        };
+
+        // Requests without an id are notifications and get no reply.
+        let Some(id) = id else {
+            continue;
+        };
+        let response = match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(err) => error_response(id, err),
+        };
+        send(&out, &response);
+        drop(replied);
+    }
+    Ok(())
+}
+
+fn error_response(id: Value, err: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": {"code": err.code, "message": err.message},
+    })
+}
+
+// ─── Methods ──────────────────────────────────────────────────────────────────
+
+fn heads(client: &TandemClient) -> Result<Value, RpcError> {
+    let state = client.get_heads_state()?;
+    let workspaces: serde_json::Map<String, Value> = state
+        .workspace_heads
+        .iter()
+        .map(|(name, op_id)| (name.clone(), Value::String(encode_hex(op_id))))
+        .collect();
+    Ok(json!({
+        "version": state.version,
+        "heads": state.heads.iter().map(|head| encode_hex(head)).collect::<Vec<_>>(),
+        "workspaces": workspaces,
+    }))
+}
+
+fn presence(client: &TandemClient, ancestry: &mut ps::AncestryCache) -> Result<Value, RpcError> {
+    let rows = ps::collect_rows(client, ancestry)?;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            json!({
+                "workspace": row.workspace,
+                "change": row.change,
+                "description": row.description,
+                "committedAtMs": row.committed_at_ms,
+                "ahead": row.ahead_behind.map(|(ahead, _)| ahead),
+                "behind": row.ahead_behind.map(|(_, behind)| behind),
+            })
+        })
+        .collect())
+}
+
+fn who_touched(
+    client: &TandemClient,
+    own_workspace: Option<&str>,
+    params: &Value,
+) -> Result<Value, RpcError> {
+    let path = params
+        .get("path")
+        .and_then(Value::as_str)
+        .map(|path| path.trim_start_matches("./").trim_matches('/'))
+        .filter(|path| !path.is_empty())
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, "whoTouched needs a repo-relative path"))?;
+    let components: Vec<&str> = path.split('/').collect();
+
+    let state = client.get_heads_state()?;
+    let mut touched = Vec::new();
+    for (workspace, op_id) in &state.workspace_heads {
+        if Some(workspace.as_str()) == own_workspace {
+            continue;
+        }
+        let view = ps::read_view(client, op_id)
+            .with_context(|| format!("reading view for workspace {workspace}"))?;
+        let Some(wc_commit_id) = view
+            .wc_commit_ids
+            .get(&WorkspaceNameBuf::from(workspace.clone()))
+            .cloned()
+        else {
+            continue;
+        };
+        let commit = ps::read_commit(client, &wc_commit_id)?;
+        let current = values_at(client, &commit.root_tree, &components)?;
+        let base = match commit.parents.first().map(|id| CommitId::new(id.clone())) {
+            Some(parent) if parent.as_bytes() != client.repo_info().root_commit_id.as_slice() => {
+                let parent = ps::read_commit(client, &parent)?;
+                values_at(client, &parent.root_tree, &components)?
+            }
+            _ => vec![None],
+        };
+        if current != base {
+            touched.push(json!({
+                "workspace": workspace,
+                "change": jj_lib::hex_util::encode_reverse_hex(&commit.change_id)
+                    .chars()
+                    .take(12)
+                    .collect::<String>(),
+                "description": commit.description.lines().next().unwrap_or(""),
+            }));
+        }
+    }
+    Ok(Value::Array(touched))
+}
+
+/// The value at `components` in each term of a (possibly conflicted) root
+/// tree, `None` where the path does not exist.
+fn values_at(
+    client: &TandemClient,
+    root_tree: &[Vec<u8>],
+    components: &[&str],
+) -> Result<Vec<Option<TreeValue>>> {
+    root_tree
+        .iter()
+        .map(|tree_id| value_at(client, tree_id, components))
+        .collect()
+}
+
+fn value_at(
+    client: &TandemClient,
+    tree_id: &[u8],
+    components: &[&str],
+) -> Result<Option<TreeValue>> {
+    let mut tree_id = tree_id.to_vec();
+    for (depth, name) in components.iter().enumerate() {
+        let tree = read_tree(client, &tree_id)?;
+        let Some(value) = tree
+            .entries()
+            .find(|entry| entry.name().as_internal_str() == *name)
+            .map(|entry| entry.value().clone())
+        else {
+            return Ok(None);
+        };
+        if depth + 1 == components.len() {
+            return Ok(Some(value));
+        }
+        match value {
+            TreeValue::Tree(id) => tree_id = id.as_bytes().to_vec(),
+            _ => return Ok(None),
+        }
+    }
+    Ok(None)
+}
+
+fn read_tree(client: &TandemClient, id: &[u8]) -> Result<Tree> {
+    let data = client.get_object(KIND_TREE, id)?;
+    let proto = jj_lib::protos::simple_store::Tree::decode(&*data).context("decoding tree")?;
+    Ok(proto_convert::tree_from_proto(proto))
+}
+
+fn subscribe(
+    client: &TandemClient,
+    server_addr: &str,
+    params: &Value,
+    out: &Stdout,
+) -> Result<(Value, std::sync::mpsc::Sender<()>), RpcError> {
+    if !client.supports_capability(RepoCapability::WatchHeads) {
+        return Err(RpcError::new(
+            SERVER_ERROR,
+            "server does not support watching heads",
+        ));
+    }
+    let since = match params.get("since") {
+        None | Some(Value::Null) => None,
+        Some(since) => Some(
+            since
+                .as_u64()
+                .ok_or_else(|| RpcError::new(INVALID_PARAMS, "since must be a heads version"))?,
+        ),
+    };
+    if since.is_some() && !client.supports_capability(RepoCapability::HeadsHistory) {
+        return Err(RpcError::new(
+            SERVER_ERROR,
+            "server does not keep heads history; subscribe without since",
+        ));
+    }
+    let summary = match params.get("summary") {
+        None | Some(Value::Null) => false,
+        Some(summary) => summary
+            .as_bool()
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, "summary must be a boolean"))?,
+    };
+    if summary && !client.supports_capability(RepoCapability::HeadsSummaries) {
+        return Err(RpcError::new(
+            SERVER_ERROR,
+            "server does not summarize heads changes; subscribe without summary",
+        ));
+    }
+
+    // Reply once the watch is registered so no change slips between the
+    // response and the first notification. Notifications wait until the
+    // response is written: replayed transitions are held back, and the
+    // server's current state, sent right after registering, waits for the
+    // caller to drop `replied`.
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<(), String>>();
+    let (replied, replied_rx) = std::sync::mpsc::channel::<()>();
+    let addr = server_addr.to_string();
+    let out = out.clone();
+    std::thread::spawn(move || {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let local = tokio::task::LocalSet::new();
+        let mut ready = Some(ready_tx);
+        let mut held: Vec<Value> = Vec::new();
+        let result = local.block_on(
+            &rt,
+            watch::follow_heads(&addr, WatchFrom::since(since), summary.then_some(0), |update| match update {
+                HeadsUpdate::Replayed(transition) => held.push(json!({
+                    "version": transition.version,
+                    "heads": transition.heads.iter().map(|head| encode_hex(head)).collect::<Vec<_>>(),
+                    "workspace": transition.workspace_id,
+                })),
+                HeadsUpdate::Subscribed => {
+                    if let Some(ready) = ready.take() {
+                        let _ = ready.send(Ok(()));
+                        // Returns once the response is out.
+                        let _ = replied_rx.recv();
+                    }
+                    for params in held.drain(..) {
+                        notify(&out, "headsChanged", params);
+                    }
+                }
+                HeadsUpdate::Live(event) => notify(
+                    &out,
+                    "headsChanged",
+                    json!({
+                        "version": event.version,
+                        "heads": event.heads,
+                        "tags": event.tags,
+                        "summary": event.summary,
+                    }),
+                ),
+                HeadsUpdate::ShuttingDown {
+                    retry_after,
+                    message,
+                } => notify(
+                    &out,
+                    "serverShuttingDown",
+                    json!({
+                        "server": addr,
+                        "retryAfterMs": retry_after.as_millis() as u64,
+                        "message": message,
+                    }),
+                ),
+            }),
+        );
+        match ready.take() {
+            Some(ready) => {
+                let message = match result {
+                    Ok(()) => "watch ended before it was registered".to_string(),
+                    Err(err) => format!("{err:#}"),
+                };
+                let _ = ready.send(Err(message));
+            }
+            None => notify(&out, "disconnected", json!({"server": addr})),
+        }
+    });
+
+    match ready_rx.recv() {
+        Ok(Ok(())) => Ok((json!({"subscribed": true}), replied)),
+        Ok(Err(message)) => Err(RpcError::new(SERVER_ERROR, message)),
+        Err(_) => Err(RpcError::new(SERVER_ERROR, "watch thread exited")),
+    }
+}
@@ -0,0 +1,179 @@
+//! Idempotency keys for `updateOpHeads`.
+//!
+//! A client that sends a head update and then sees it fail (the reply was
+//! lost, or the call was reported as timed out after the heads had already
+//! moved) cannot tell whether the update landed. Retrying it as a new update
+//! would move the heads a second time: the retry loses the CAS against its
+//! own first attempt, re-reads, and publishes the same operation again as
+//! an extra version bump. So clients put a random key on each logical
+//! update and resend it with every retry. The server remembers the keys of
+//! updates it applied, with the operation each published and its result,
+//! and answers a repeated key with the stored result instead of applying it
+//! again. A key that comes back with a different operation is a client bug,
+//! not a retry, and is refused rather than answered with someone else's
+//! result.
+//!
+//! Keys are kept in memory for [`TTL`], at most [`CAPACITY`] of them, oldest
+//! dropped first. Retries come within seconds, so neither limit is reached
+//! in practice; a key that outlives them is treated as new.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+/// How long an applied key is remembered.
+pub const TTL: Duration = Duration::from_secs(10 * 60);
+
+/// Most keys remembered at once.
+pub const CAPACITY: usize = 4096;
+
+/// Keys of applied updates, with the operation each published and what it
+/// returned.
+pub struct AppliedUpdates<T> {
+    state: Mutex<State<T>>,
+}
+
+struct Applied<T> {
+    at: Instant,
+    new_id: Vec<u8>,
+    result: T,
+}
+
+struct State<T> {
+    results: HashMap<Vec<u8>, Applied<T>>,
+    /// Keys in the order they were applied, for expiry.
+    order: VecDeque<Vec<u8>>,
+}
+
+impl<T> Default for AppliedUpdates<T> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State {
+                results: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+}
+
+impl<T: Clone> AppliedUpdates<T> {
+    /// The result of the update that published `new_id` under `key`, if it
+    /// is remembered. Fails when `key` was applied for another operation.
+    pub fn get(&self, key: &[u8], new_id: &[u8]) -> Result<Option<T>> {
+        self.get_at(Instant::now(), key, new_id)
+    }
+
+    /// Remember that the update under `key` published `new_id` with
+    /// `result`.
+    pub fn insert(&self, key: Vec<u8>, new_id: Vec<u8>, result: T) {
+        self.insert_at(Instant::now(), key, new_id, result);
+    }
+
+    fn get_at(&self, now: Instant, key: &[u8], new_id: &[u8]) -> Result<Option<T>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.expire(now);
+        let Some(applied) = state.results.get(key) else {
+            return Ok(None);
+        };
+        if applied.new_id != new_id {
+            bail!(
+                "idempotency key was already used for operation {}, not {}",
+                hex(&applied.new_id),
+                hex(new_id)
+            );
+        }
+        Ok(Some(applied.result.clone()))
+    }
+
+    fn insert_at(&self, now: Instant, key: Vec<u8>, new_id: Vec<u8>, result: T) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.expire(now);
+        while state.order.len() >= CAPACITY {
+            if let Some(oldest) = state.order.pop_front() {
+                state.results.remove(&oldest);
+            }
+        }
+        let applied = Applied {
+            at: now,
+            new_id,
+            result,
+        };
+        if state.results.insert(key.clone(), applied).is_none() {
+            state.order.push_back(key);
+        }
+    }
+}
+
+impl<T> State<T> {
+    fn expire(&mut self, now: Instant) {
+        while let Some(oldest) = self.order.front() {
+            let expired = self
+                .results
+                .get(oldest)
+                .is_none_or(|applied| now.duration_since(applied.at) >= TTL);
+            if !expired {
+                break;
+            }
+            if let Some(oldest) = self.order.pop_front() {
+                self.results.remove(&oldest);
+            }
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeated_key_returns_the_stored_result() {
+        let applied = AppliedUpdates::default();
+        assert_eq!(applied.get(b"key-1", b"op").unwrap(), None);
+        applied.insert(b"key-1".to_vec(), b"op".to_vec(), 7u64);
+        assert_eq!(applied.get(b"key-1", b"op").unwrap(), Some(7));
+        assert_eq!(applied.get(b"key-2", b"op").unwrap(), None);
+    }
+
+    #[test]
+    fn key_reused_for_another_operation_is_refused() {
+        let applied = AppliedUpdates::default();
+        applied.insert(b"key".to_vec(), vec![0xab], 7u64);
+        let err = applied.get(b"key", &[0xcd]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "idempotency key was already used for operation ab, not cd"
+        );
+    }
+
+    #[test]
+    fn keys_expire_and_are_bounded() {
+        let applied = AppliedUpdates::default();
+        let start = Instant::now();
+        applied.insert_at(start, b"old".to_vec(), b"op".to_vec(), 1usize);
+        assert_eq!(
+            applied.get_at(start + TTL / 2, b"old", b"op").unwrap(),
+            Some(1)
+        );
+        assert_eq!(applied.get_at(start + TTL, b"old", b"op").unwrap(), None);
+
+        for i in 0..=CAPACITY {
+            applied.insert_at(start, i.to_be_bytes().to_vec(), b"op".to_vec(), i);
+        }
+        assert_eq!(
+            applied.get_at(start, &0usize.to_be_bytes(), b"op").unwrap(),
+            None
+        );
+        assert_eq!(
+            applied
+                .get_at(start, &CAPACITY.to_be_bytes(), b"op")
+                .unwrap(),
+            Some(CAPACITY)
+        );
+    }
+}
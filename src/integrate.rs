@@ -0,0 +1,225 @@
+//! tandem op integrate-all — integrate every operation the workspace is
+//! missing, without scraping `jj op integrate <id>` hints out of stderr.
+//!
+//! Two things leave operations outside the op log a workspace sees:
+//!
+//! - several op heads on the server (concurrent writers that nobody has
+//!   loaded since), which jj merges the next time any command loads the repo;
+//! - a working copy that records an operation the heads do not reach (the
+//!   command was interrupted, or the server refused its `updateOpHeads`), which
+//!   jj only reports with a hint to run `jj op integrate <id>`.
+//!
+//! Divergent heads are merged first, since the working-copy operation may
+//! build on one of them, then the working-copy operation is integrated. Each
+//! step runs stock jj (`op log`, `op integrate`) in this workspace and is
+//! printed as it happens.
+
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+use jj_lib::hex_util::encode_hex;
+use jj_lib::protos::simple_op_store::Operation;
+use jj_lib::settings::UserSettings;
+use prost::Message as _;
+
+use crate::rpc::TandemClient;
+use crate::server_address;
+use crate::settings::TandemSettings;
+
+/// Upper bound on operations walked when checking reachability.
+pub(crate) const OP_WALK_LIMIT: usize = 10_000;
+/// Operations that ended this long before the working-copy operation are
+/// not searched for it; the slack absorbs clock skew between machines.
+pub(crate) const CLOCK_SKEW_SLACK_MS: i64 = 60 * 60 * 1000;
+
+/// A step `integrate-all` takes, in order.
+enum Step {
+    MergeHeads(Vec<Vec<u8>>),
+    Integrate { op_id: Vec<u8>, description: String },
+}
+
+pub fn run_integrate_all(settings: &UserSettings, dry_run: bool) -> Result<()> {
+    let cwd = std::env::current_dir().context("cannot read current directory")?;
+    let workspace_root = find_workspace_root(&cwd)?;
+    let repo_dir = repo_dir(&workspace_root)?;
+    let tandem_settings = TandemSettings::from_user(settings)?;
+    let addr = server_address::read(&repo_dir.join("store"), &tandem_settings)
+        .map_err(|e| anyhow!("{e}"))?;
+    let client = TandemClient::connect(&addr)
+        .with_context(|| format!("cannot connect to tandem server at {addr}"))?;
+
+    let steps = plan(&client, &workspace_root)?;
+    if steps.is_empty() {
+        println!("nothing to integrate");
+        return Ok(());
+    }
+    for step in &steps {
+        match step {
+            Step::MergeHeads(heads) => {
+                let ids: Vec<String> = heads.iter().map(|id| short(id)).collect();
+                println!(
+                    "merging {} divergent op heads: {}",
+                    heads.len(),
+                    ids.join(" ")
+                );
+                if !dry_run {
+                    run_jj(
+                        &workspace_root,
+                        &[
+                            "op",
+                            "log",
+                            "--ignore-working-copy",
+                            "--no-graph",
+                            "--limit",
+                            "1",
+                        ],
+                    )?;
+                }
+            }
+            Step::Integrate { op_id, description } => {
+                println!(
+                    "integrating working-copy operation {}: {description}",
+                    short(op_id)
+                );
+                if !dry_run {
+                    run_jj(&workspace_root, &["op", "integrate", &encode_hex(op_id)])?;
+                }
+            }
+        }
+    }
+    if dry_run {
+        println!("(dry run: nothing was changed)");
+    }
+    Ok(())
+}
+
+fn plan(client: &TandemClient, workspace_root: &Path) -> Result<Vec<Step>> {
+    let state = client.get_heads_state()?;
+    let mut steps = Vec::new();
+    if state.heads.len() > 1 {
+        steps.push(Step::MergeHeads(state.heads.clone()));
+    }
+
+    let wc_op_id = working_copy_operation(workspace_root)?;
+    if state.heads.contains(&wc_op_id) {
+        return Ok(steps);
+    }
+    let wc_op = read_operation(client, &wc_op_id).with_context(|| {
+        format!(
+            "the working copy records operation {}, which the server does not have",
+            short(&wc_op_id)
+        )
+    })?;
+    if !reaches(client, &state.heads, &wc_op_id, end_time(&wc_op))? {
+        let description = wc_op
+            .metadata
+            .map(|metadata| metadata.description)
+            .unwrap_or_default();
+        steps.push(Step::Integrate {
+            op_id: wc_op_id,
+            description,
+        });
+    }
+    Ok(steps)
+}
+
+/// Whether `target` is an ancestor of any of `heads`. The walk skips
+/// operations that ended well before `target` did.
+fn reaches(
+    client: &TandemClient,
+    heads: &[Vec<u8>],
+    target: &[u8],
+    target_end_ms: i64,
+) -> Result<bool> {
+    let cutoff = target_end_ms.saturating_sub(CLOCK_SKEW_SLACK_MS);
+    // The root operation is implicit: the server stores nothing for it.
+    let root_id = client.repo_info().root_operation_id.as_slice();
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<Vec<u8>> = heads.iter().cloned().collect();
+    while let Some(id) = queue.pop_front() {
+        if id == target {
+            return Ok(true);
+        }
+        if id == root_id || !seen.insert(id.clone()) {
+            continue;
+        }
+        if seen.len() > OP_WALK_LIMIT {
+            // Too far back to tell; `jj op integrate` is a no-op for an
+            // operation that turns out to be integrated already.
+            return Ok(false);
+        }
+        let op = read_operation(client, &id)?;
+        if end_time(&op) < cutoff {
+            continue;
+        }
+        queue.extend(op.parents);
+    }
+    Ok(false)
+}
+
+fn read_operation(client: &TandemClient, id: &[u8]) -> Result<Operation> {
+    let data = client.get_operation(id)?;
+    Operation::decode(&*data).context("decoding operation")
+}
+
+fn end_time(op: &Operation) -> i64 {
+    op.metadata
+        .as_ref()
+        .and_then(|metadata| metadata.end_time.as_ref())
+        .map(|ts| ts.millis_since_epoch)
+        .unwrap_or(i64::MAX)
+}
+
+fn working_copy_operation(workspace_root: &Path) -> Result<Vec<u8>> {
+    let path = workspace_root
+        .join(".jj")
+        .join("working_copy")
+        .join("checkout");
+    let data = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+    let checkout = jj_lib::protos::local_working_copy::Checkout::decode(&*data)
+        .with_context(|| format!("decoding {}", path.display()))?;
+    Ok(checkout.operation_id)
+}
+
+pub fn find_workspace_root(start: &Path) -> Result<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(".jj").is_dir())
+        .map(Path::to_path_buf)
+        .ok_or_else(|| anyhow!("there is no jj repo in \"{}\"", start.display()))
+}
+
+/// `.jj/repo` is the repo itself, or a file pointing at it in secondary
+/// workspaces.
+pub fn repo_dir(workspace_root: &Path) -> Result<PathBuf> {
+    let jj_dir = workspace_root.join(".jj");
+    let repo = jj_dir.join("repo");
+    if repo.is_file() {
+        let target = std::fs::read_to_string(&repo)
+            .with_context(|| format!("reading {}", repo.display()))?;
+        return Ok(jj_dir.join(target.trim()));
+    }
+    Ok(repo)
+}
+
+fn run_jj(workspace_root: &Path, args: &[&str]) -> Result<()> {
+    let exe = std::env::current_exe().context("cannot determine executable path")?;
+    let output = std::process::Command::new(exe)
+        .args(args)
+        .current_dir(workspace_root)
+        .output()
+        .with_context(|| format!("failed to run jj {}", args.join(" ")))?;
+    if !output.status.success() {
+        bail!(
+            "jj {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+fn short(id: &[u8]) -> String {
+    encode_hex(id).chars().take(12).collect()
+}
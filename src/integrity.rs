@@ -0,0 +1,261 @@
+//! Startup integrity scan — quarantine operations and views that no longer
+//! match their ids.
+//!
+//! `tandem serve --integrity-scan <all|count>` reads operations and views
+//! from the op store before the server takes clients, and checks that each
+//! decodes and hashes back to the id it is stored under. `all` checks every
+//! file; a count checks that many of the most recently written ones, which
+//! are the ones clients are about to read. A file that fails is moved to
+//! `op_store/corrupt/<operations|views>/<id>` and listed in
+//! `tandem server status`, so a torn write shows up as one missing object
+//! instead of a decode error in every client that reads it.
+//!
+//! Nothing is repaired: a quarantined operation or view is gone from the
+//! server until it is restored from a backup (or, for a view a workspace
+//! still has cached, written back by that workspace).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Result};
+use jj_lib::content_hash::blake2b_hash;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::{OperationId, ViewId};
+use prost::Message as _;
+use serde::{Deserialize, Serialize};
+
+use crate::proto_convert;
+use crate::sharded_op_store;
+
+/// Directory under `op_store` that corrupt files are moved to.
+pub const CORRUPT_DIR: &str = "corrupt";
+
+/// Hex digits in an operation or view id.
+const ID_HEX_LEN: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrityScan {
+    /// Check every operation and view.
+    All,
+    /// Check this many of the most recently written.
+    Newest(usize),
+}
+
+impl IntegrityScan {
+    /// Parse `all`, or a number of files to sample.
+    pub fn parse(raw: &str) -> Result<Self> {
+        let raw = raw.trim();
+        if raw == "all" {
+            return Ok(Self::All);
+        }
+        match raw.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(Self::Newest(n)),
+            _ => Err(anyhow!(
+                "invalid integrity scan {raw:?}: expected `all` or a number of files (e.g. 500)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Operation,
+    View,
+}
+
+impl Kind {
+    fn name(self) -> &'static str {
+        match self {
+            Self::Operation => "operation",
+            Self::View => "view",
+        }
+    }
+
+    fn dir(self) -> &'static str {
+        match self {
+            Self::Operation => "operations",
+            Self::View => "views",
+        }
+    }
+}
+
+/// Outcome of a scan, reported by `tandem server status`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IntegrityReport {
+    /// Operations and views checked.
+    pub checked: usize,
+    /// Operations and views stored when the scan ran.
+    pub stored: usize,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quarantined: Vec<Quarantined>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quarantined {
+    /// `operation` or `view`.
+    pub kind: String,
+    pub id: String,
+    pub reason: String,
+}
+
+impl IntegrityReport {
+    pub fn describe(&self) -> String {
+        format!(
+            "checked {} of {} operations and views, {} quarantined",
+            self.checked,
+            self.stored,
+            self.quarantined.len()
+        )
+    }
+}
+
+/// Check the op store at `op_store_path` and move what fails into
+/// [`CORRUPT_DIR`].
+pub fn scan(op_store_path: &Path, scope: IntegrityScan) -> Result<IntegrityReport> {
+    let mut files = stored_files(op_store_path)?;
+    let stored = files.len();
+    if let IntegrityScan::Newest(n) = scope {
+        files.sort_by_key(|(_, _, _, modified)| std::cmp::Reverse(*modified));
+        files.truncate(n);
+    }
+    let mut report = IntegrityReport {
+        checked: files.len(),
+        stored,
+        quarantined: Vec::new(),
+    };
+    for (kind, hex, path, _) in files {
+        let bytes = fs::read(&path).with_context(|| format!("read {}", path.display()))?;
+        let Err(reason) = verify(kind, &hex, &bytes) else {
+            continue;
+        };
+        let dest_dir = op_store_path.join(CORRUPT_DIR).join(kind.dir());
+        fs::create_dir_all(&dest_dir).with_context(|| format!("create {}", dest_dir.display()))?;
+        let dest = dest_dir.join(&hex);
+        fs::rename(&path, &dest)
+            .with_context(|| format!("move {} to {}", path.display(), dest.display()))?;
+        tracing::error!(
+            kind = kind.name(),
+            id = %hex,
+            reason = %reason,
+            quarantine = %dest.display(),
+            "quarantined corrupt op store file"
+        );
+        report.quarantined.push(Quarantined {
+            kind: kind.name().to_string(),
+            id: hex,
+            reason,
+        });
+    }
+    Ok(report)
+}
+
+type StoredFile = (Kind, String, PathBuf, SystemTime);
+
+/// Every stored operation and view, in either operation layout.
+fn stored_files(op_store_path: &Path) -> Result<Vec<StoredFile>> {
+    let mut files = Vec::new();
+    let mut push = |kind: Kind, hex: String, path: PathBuf| -> Result<()> {
+        let modified = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| format!("stat {}", path.display()))?;
+        files.push((kind, hex, path, modified));
+        Ok(())
+    };
+    for hex in sharded_op_store::operation_ids_with_prefix(op_store_path, "")? {
+        let sharded = sharded_op_store::operation_path(op_store_path, &hex);
+        let path = if sharded.is_file() {
+            sharded
+        } else {
+            op_store_path.join(Kind::Operation.dir()).join(&hex)
+        };
+        push(Kind::Operation, hex, path)?;
+    }
+    let views_dir = op_store_path.join(Kind::View.dir());
+    if views_dir.is_dir() {
+        for entry in fs::read_dir(&views_dir)? {
+            let entry = entry?;
+            let Ok(name) = entry.file_name().into_string() else {
+                continue;
+            };
+            if name.len() == ID_HEX_LEN && ViewId::try_from_hex(&name).is_some() {
+                push(Kind::View, name, entry.path())?;
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Whether `bytes` decode as a `kind` whose id is `hex`; the reason if not.
+fn verify(kind: Kind, hex: &str, bytes: &[u8]) -> Result<(), String> {
+    let actual = match kind {
+        Kind::Operation => {
+            let proto = jj_lib::protos::simple_op_store::Operation::decode(bytes)
+                .map_err(|err| format!("does not decode: {err}"))?;
+            let operation = proto_convert::operation_from_proto(proto)
+                .map_err(|err| format!("does not decode: {err}"))?;
+            OperationId::new(blake2b_hash(&operation).to_vec()).hex()
+        }
+        Kind::View => {
+            let proto = jj_lib::protos::simple_op_store::View::decode(bytes)
+                .map_err(|err| format!("does not decode: {err}"))?;
+            let view = proto_convert::view_from_proto(proto)
+                .map_err(|err| format!("does not decode: {err}"))?;
+            ViewId::new(blake2b_hash(&view).to_vec()).hex()
+        }
+    };
+    if actual != hex {
+        return Err(format!("content hashes to {}", &actual[..12]));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jj_lib::backend::CommitId;
+    use jj_lib::op_store::View;
+
+    #[test]
+    fn parses_all_and_counts() {
+        assert_eq!(IntegrityScan::parse("all").unwrap(), IntegrityScan::All);
+        assert_eq!(
+            IntegrityScan::parse("500").unwrap(),
+            IntegrityScan::Newest(500)
+        );
+        assert!(IntegrityScan::parse("0").is_err());
+        assert!(IntegrityScan::parse("some").is_err());
+    }
+
+    #[test]
+    fn quarantines_views_that_do_not_match_their_id() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let op_store = tmp.path();
+        let views = op_store.join("views");
+        fs::create_dir_all(&views).unwrap();
+        let view = View::make_root(CommitId::from_bytes(&[0; 20]));
+        let bytes = proto_convert::view_to_proto(&view).encode_to_vec();
+        let good = ViewId::new(blake2b_hash(&view).to_vec()).hex();
+        let renamed = "a".repeat(ID_HEX_LEN);
+        let garbage = "b".repeat(ID_HEX_LEN);
+        fs::write(views.join(&good), &bytes).unwrap();
+        fs::write(views.join(&renamed), &bytes).unwrap();
+        fs::write(views.join(&garbage), b"\xff\xff\xff").unwrap();
+
+        let report = scan(op_store, IntegrityScan::All).unwrap();
+        assert_eq!(report.checked, 3);
+        let mut ids: Vec<&str> = report.quarantined.iter().map(|q| q.id.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, [renamed.as_str(), garbage.as_str()]);
+        assert!(views.join(&good).is_file());
+        assert!(!views.join(&garbage).exists());
+        assert!(op_store.join("corrupt/views").join(&garbage).is_file());
+        assert!(op_store.join("corrupt/views").join(&renamed).is_file());
+
+        let report = scan(op_store, IntegrityScan::Newest(5)).unwrap();
+        assert_eq!((report.checked, report.stored), (1, 1));
+        assert!(report.quarantined.is_empty());
+    }
+}
@@ -0,0 +1,64 @@
+//! tandem's server and commands as a library, for the `tandem` binary and
+//! for programs that run a server in their own process
+//! ([`EmbeddedServer`]).
+
+pub mod api;
+pub mod archive;
+pub mod attribution;
+pub mod blame;
+pub mod cas_storm;
+pub mod change_lock;
+pub mod cleanup;
+pub mod colocate;
+pub mod commit_graph;
+pub mod content_http;
+pub mod control;
+pub mod fair_queue;
+pub mod faults;
+pub mod gateway;
+pub mod handoff;
+pub mod handover;
+pub mod heads_history;
+pub mod heads_summary;
+pub mod health;
+pub mod http;
+pub mod ide;
+pub mod idempotency;
+pub mod integrate;
+pub mod integrity;
+pub mod logging;
+pub mod merge_preview;
+pub mod migrate;
+pub mod notifications;
+pub mod notify;
+pub mod offline_status;
+pub mod op_retention;
+pub mod op_tags;
+pub mod path_overlaps;
+pub mod protected_paths;
+pub mod proxy;
+pub mod ps;
+pub mod replica;
+pub mod repo_check;
+pub mod resources;
+pub mod revsets;
+pub mod search;
+pub mod server;
+pub mod snapshot_hold;
+pub mod snapshot_policy;
+pub mod stale_working_copy;
+pub mod status_file;
+pub mod timeline;
+pub mod upgrade_check;
+pub mod watch;
+
+// The stores and their RPC client live in the tandem-jj-store library so
+// other jj-lib programs can use them; the rest of the crate reaches them
+// through these imports as `crate::rpc` and so on.
+use tandem_jj_store::{
+    backend, drain, ephemeral, jj_compat, limits, maintenance, metadata_schema, offline_queue, ops,
+    proto_convert, redirect, rpc, rpc_record, server_address, settings, sharded_op_store,
+    tandem_capnp, tmp_file, view_delta, watchdog,
+};
+
+pub use server::EmbeddedServer;
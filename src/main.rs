@@ -5,21 +5,6 @@
 //!   tandem init --server <addr> [path]           → initialize tandem workspace
 //!   tandem <jj args>                             → stock jj via CliRunner
 
-#[allow(unused_parens, dead_code)]
-mod tandem_capnp {
-    include!(concat!(env!("OUT_DIR"), "/tandem_capnp.rs"));
-}
-
-mod backend;
-mod control;
-mod logging;
-mod op_heads_store;
-mod op_store;
-mod proto_convert;
-mod rpc;
-mod server;
-mod watch;
-
 use std::path::Path;
 use std::process::ExitCode;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -27,6 +12,22 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use clap::{CommandFactory, Parser, Subcommand};
 
+// The server and the commands live in the jj-tandem library (src/lib.rs) so
+// other programs can run a server in their own process.
+use jj_tandem::{
+    archive, attribution, blame, cas_storm, change_lock, cleanup, colocate, control, fair_queue,
+    faults, gateway, handoff, ide, integrate, integrity, merge_preview, migrate, offline_status,
+    op_retention, path_overlaps, proxy, ps, resources, revsets, search, server, snapshot_hold,
+    snapshot_policy, stale_working_copy, status_file, timeline, upgrade_check, watch,
+};
+
+// The stores and their RPC client live in the tandem-jj-store library so
+// other jj-lib programs can use them.
+use tandem_jj_store::{
+    backend, drain, ephemeral, journal, maintenance, offline, op_heads_store, op_store, ops,
+    profile, read_mirror, rpc, server_address, settings, time_travel, watchdog,
+};
+
 // ─── Help text ────────────────────────────────────────────────────────────────
 
 const AFTER_HELP: &str = "\
@@ -40,17 +41,40 @@ JJ COMMANDS:
       tandem describe       Update change description
       ... and every other jj command
 
+    Added by tandem, inside a workspace:
+      tandem handoff <ws>   Move this workspace's change to workspace <ws>
+      tandem lock [<rev>]   Claim a change so other workspaces leave it alone
+      tandem unlock [<rev>] Release a change claimed with `tandem lock`
+      tandem preview-merge <a> [<b>]
+                            Show whether <a> and <b> (default @) would merge
+                            cleanly, without merging them
+      tandem blame <path>   Show which workspace wrote each line of a file
+
 ENVIRONMENT:
     TANDEM_SERVER           Server address (host:port) — used by the tandem
-                            backend when connecting to a remote store
-    TANDEM_WORKSPACE        Workspace name for `tandem init` when --workspace
-                            is not provided
+                            backend when connecting to a remote store. If it
+                            names a server other than the workspace's own,
+                            jj commands refuse to run unless that server hosts
+                            the same repo or --allow-server-override is passed
+    TANDEM_WORKSPACE        Workspace name for `tandem init` and
+                            `tandem watch --notify` when --workspace is not
+                            provided
     TANDEM_ENABLE_INTEGRATION_WORKSPACE
                             Set to 1/true to enable server-side integration
                             workspace recompute mode
     TANDEM_LISTEN           Listen address for `tandem up` (host:port).
                             If unset, tandem auto-selects a free port
                             in 0.0.0.0:13013-13063
+    TANDEM_UP_HOST          Host `tandem up` binds when auto-selecting
+                            (default 0.0.0.0; 127.0.0.1 for loopback only)
+    TANDEM_UP_PORT_RANGE    Ports `tandem up` tries when auto-selecting
+                            (START-END, default 13013-13063)
+    TANDEM_GIT_REMOTE       Git URL mirroring the served repo, advertised by
+                            `tandem serve` for `tandem init --colocate`
+    TANDEM_MAX_MESSAGE_MB   Largest RPC message in MiB (default 512); set the
+                            same value on the server and every client
+    TANDEM_OP_TAGS          Comma-separated key=value tags attached to every
+                            operation this process writes (e.g. ci=1234,agent=a)
 
 SETUP:
     # Start a server
@@ -68,19 +92,192 @@ SETUP:
 const SERVE_AFTER_HELP: &str = "\
 EXAMPLES:
     tandem serve --listen 0.0.0.0:13013 --repo /srv/project
-    tandem serve --listen 127.0.0.1:13013 --repo .";
+    tandem serve --listen 127.0.0.1:13013 --repo .
+    tandem serve --listen 127.0.0.1:13013 --listen unix:///run/tandem/project.sock --repo /srv/project
+    tandem serve --listen 0.0.0.0:13014 --repo /srv/mirror --follow primary:13013
+    tandem serve --listen 0.0.0.0:13013 --repo /srv/project --git-remote ssh://vps/srv/project
+    tandem serve --listen 0.0.0.0:13013 --repo /srv/project --op-retention 90d
+    tandem serve --listen 0.0.0.0:13013 --repo /srv/project --integrity-scan all
+    tandem serve --listen 0.0.0.0:13013 --repo /srv/project --snapshot-exclude target/ --snapshot-exclude node_modules/";
 
 const INIT_AFTER_HELP: &str = "\
 EXAMPLES:
     tandem init --server server:13013 my-workspace
     tandem init --server server:13013 --workspace agent-a .
-    TANDEM_SERVER=server:13013 tandem init .";
+    TANDEM_SERVER=server:13013 tandem init .
+    tandem init --server server:13013 --ephemeral --ttl 30m ci-$BUILD_ID
+    tandem init --server server:13013 --colocate my-workspace
+    tandem init --server server:13013 --sparse services/api --sparse libs/proto .
+    tandem init --server primary:13013 --read-mirror replica-eu:13014 .
+    tandem init --server server:13013 --workspace review --from-workspace agent-a review";
+
+const WORKSPACE_RELEASE_AFTER_HELP: &str = "\
+Forgets the current workspace and tells the server to drop its entry now,
+instead of waiting for an ephemeral workspace's TTL to run out.
+
+EXAMPLES:
+    tandem workspace release";
+
+const OP_INTEGRATE_ALL_AFTER_HELP: &str = "\
+Merges divergent op heads on the server, then integrates the working copy's
+operation if the heads do not reach it (what `jj op integrate <id>` hints at).
+
+EXAMPLES:
+    tandem op integrate-all
+    tandem op integrate-all --dry-run";
+
+const WATCH_AFTER_HELP: &str = "\
+EXAMPLES:
+    tandem watch --server server:13013
+    tandem watch --notify --workspace agent-a
+    tandem watch --since 120      Replay every heads change after version 120, then follow";
+
+const OPS_AFTER_HELP: &str = "\
+EXAMPLES:
+    TANDEM_OP_TAGS=ci=1234,agent=alice tandem new -m 'fix flaky test'
+    tandem ops --server server:13013 --tag ci=1234
+    tandem ops --server server:13013 --tag agent --json";
+
+const JOURNAL_AFTER_HELP: &str = "\
+EXAMPLES:
+    jj config set --repo tandem.journal true     Start journaling commands
+    tandem journal show                          The last 10 commands
+    tandem journal show --last 3 --json          Full traces, for a bug report";
+
+const DELTA_AFTER_HELP: &str = "\
+EXAMPLES:
+    tandem delta                  Diff @ against the integration bookmark
+    tandem delta agent-b          Diff @ against agent-b's working copy
+    tandem delta --summary        Only list changed paths";
+
+const PS_AFTER_HELP: &str = "\
+EXAMPLES:
+    tandem ps --server server:13013
+    tandem ps --watch";
+
+const IDE_SERVER_AFTER_HELP: &str = "\
+Speaks newline-delimited JSON-RPC 2.0 on stdin/stdout. Methods: heads,
+presence, whoTouched {path}, subscribe {since?} (then headsChanged
+notifications), shutdown.
+
+EXAMPLES:
+    tandem ide-server --server server:13013 --workspace agent-a
+    echo '{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"heads\"}' | tandem ide-server";
+
+const TIMELINE_AFTER_HELP: &str = "\
+EXAMPLES:
+    tandem timeline --server server:13013
+    tandem timeline --workspace agent-a --since 2h
+    tandem timeline --since 1d --json";
+
+const SEARCH_AFTER_HELP: &str = "\
+Searches the descriptions of all visible commits on the server, newest first,
+ignoring case. Prints change id, commit id, author, age, and the first line
+of the description.
+
+EXAMPLES:
+    tandem search --server server:13013 \"fix flaky test\"
+    tandem search --author alice@example.com parser
+    tandem search --author bob --limit 200 --json";
+
+const UPGRADE_CHECK_AFTER_HELP: &str = "\
+Compares this tandem with the version, jj version, and features the server
+advertises, and says whether the client, the server, or both need upgrading.
+--index (or TANDEM_UPGRADE_INDEX) names a JSON file with the latest release,
+{\"latest\": \"0.4.0\", \"url\": \"...\"}, as a path or URL (http(s) is fetched
+with curl). Nothing is installed.
+
+EXAMPLES:
+    tandem upgrade-check --server server:13013
+    tandem upgrade-check --index https://example.com/tandem/index.json
+    tandem upgrade-check --json";
+
+const ARCHIVE_AFTER_HELP: &str = "\
+Reads the tree straight from the server; no workspace or checkout needed.
+REV is a full commit id, a bookmark name, or WORKSPACE@ for a workspace's
+working-copy commit. The format follows the output name (.tar, .tar.gz, .tgz);
+`-o -` writes a plain tar to stdout.
+
+EXAMPLES:
+    tandem archive --server server:13013 -r main -o main.tar.gz
+    tandem archive -r agent-a@ -o - | tar -x -C build/";
+
+const PROXY_AFTER_HELP: &str = "\
+Agents point TANDEM_SERVER (or `tandem init --server`) at the proxy instead
+of the server. Objects, operations, and views are cached; heads, writes, and
+watches are forwarded to the server.
+
+EXAMPLES:
+    tandem proxy --listen 127.0.0.1:13100 --upstream server:13013
+    tandem proxy --listen 0.0.0.0:13100 --upstream server:13013 \\
+        --upstream-connections 8 --cache-mb 1024";
+
+const GATEWAY_AFTER_HELP: &str = "\
+Read-only JSON over HTTP for clients that cannot speak Cap'n Proto:
+    GET /v1/repo                         repo info and capabilities
+    GET /v1/heads[?after=N&wait=SECS]    op heads; long-polls past version N
+    GET /v1/objects/<kind>/<hex-id>      stored object bytes
+Ids are hex; <kind> is commit, tree, file, symlink, or copy. Errors are
+{\"error\": ...} with a 4xx/5xx status. There is no authentication.
+
+EXAMPLES:
+    tandem gateway --listen 127.0.0.1:13080 --upstream server:13013
+    curl -s '127.0.0.1:13080/v1/heads?after=41&wait=60'";
+
+const UNDO_REMOTE_AFTER_HELP: &str = "\
+Run on the server host; requires access to the daemon's control socket.
+The rollback is recorded as a new operation, so it can be undone the same way.
+Workspaces whose working copy was rolled back need `tandem workspace update-stale`.
+
+EXAMPLES:
+    tandem op log --limit 5
+    tandem undo-remote 3f2a9c1d0b7e";
+
+const SNAPSHOT_AFTER_HELP: &str = "\
+Head updates wait while the hold is active, so everything under .jj/repo
+(op store, git objects, tandem metadata) is consistent at the held version.
+The command sees that version as TANDEM_SNAPSHOT_VERSION.
+
+EXAMPLES:
+    tandem snapshot --server server:13013 -- \\
+        sh -c 'tar czf backup-v$TANDEM_SNAPSHOT_VERSION.tgz -C /srv/project .jj'
+    tandem snapshot --lease 1h -- restic backup /srv/project";
+
+const MIGRATE_AFTER_HELP: &str = "\
+Copies every operation, view, and object reachable from the source's heads,
+then the heads and workspace attributions, to a destination server no
+workspace has used yet. The source then redirects clients to the destination
+and refuses writes; each workspace follows the redirect on its next command
+and rewrites its stored server address.
+
+EXAMPLES:
+    tandem serve --listen 0.0.0.0:13013 --repo /srv/new-project   # on the new host
+    tandem migrate --from old-host:13013 --to new-host:13013";
+
+const PUBLISH_AFTER_HELP: &str = "\
+Run inside a workspace made with `tandem init --local`. Copies every
+operation, view, and object of the local repo, then the heads and workspace
+attributions, to a server no workspace has used yet. Once the server's heads
+read back the same as the local repo's, the workspace's stores are pointed at
+the server; until then the workspace keeps using its local repo.
+
+EXAMPLES:
+    tandem serve --listen 0.0.0.0:13013 --repo /srv/project   # on the server
+    tandem publish --server server-host:13013";
 
 const SERVER_AFTER_HELP: &str = "\
 EXAMPLES:
     tandem server status
     tandem server logs --level debug
-    tandem server logs --json";
+    tandem server logs --json
+    tandem server maintenance --for 30m --message 'disk migration, back by 18:00 UTC'
+    tandem server maintenance --off
+    tandem server redirect new-host:13013   # after moving the repo by hand
+    tandem server redirect --off
+    tandem server restart   # after upgrading the tandem binary
+    tandem server faults --drop-update-op-heads-every 3 --get-object-delay 200
+    tandem server faults --error-every 10 --error-method putObject
+    tandem server faults --off";
 
 // ─── CLI definition ───────────────────────────────────────────────────────────
 
@@ -96,14 +293,17 @@ struct Cli {
     command: Option<Commands>,
 }
 
+// Parsed once per process; `Serve` is big because it has every server flag.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Start the tandem server (foreground)
     #[command(after_help = SERVE_AFTER_HELP)]
     Serve {
-        /// Address to listen on (e.g. 0.0.0.0:13013)
-        #[arg(long)]
-        listen: String,
+        /// Address to listen on (e.g. 0.0.0.0:13013, or unix:///run/tandem.sock).
+        /// Repeat to listen on several at once
+        #[arg(long, required = true)]
+        listen: Vec<String>,
         /// Path to the repository directory
         #[arg(long)]
         repo: String,
@@ -125,27 +325,280 @@ enum Commands {
         /// Enable server-side integration workspace recompute mode
         #[arg(long)]
         enable_integration_workspace: bool,
+        /// Move this bookmark to each clean integration commit (repeatable;
+        /// requires --enable-integration-workspace)
+        #[arg(long = "auto-advance", value_name = "BOOKMARK")]
+        auto_advance: Vec<String>,
+        /// Shell command that must exit 0 before bookmarks auto-advance.
+        /// Runs in the repo root with TANDEM_INTEGRATION_COMMIT set
+        #[arg(long, value_name = "COMMAND")]
+        auto_advance_check: Option<String>,
+        /// CODEOWNERS-like rules file; head updates touching a protected path
+        /// are rejected unless the workspace owns it
+        #[arg(long, value_name = "FILE")]
+        protected_paths: Option<String>,
+        /// Run as a read-only replica that mirrors this primary server
+        #[arg(long, value_name = "PRIMARY")]
+        follow: Option<String>,
+        /// Serve GET /healthz over plain HTTP on this address (e.g. 0.0.0.0:13080)
+        #[arg(long, value_name = "ADDR")]
+        health_listen: Option<String>,
+        /// Serve read-only HTTP on this address: file content at
+        /// GET /raw/<rev>/<path> and /archive/<rev>.tar[.gz], and a JSON API
+        /// for dashboards at /api/workspaces, /api/heads, and /api/stats
+        #[arg(long, value_name = "ADDR")]
+        http_listen: Option<String>,
+        /// JSON file of notification sinks (Slack, webhook, command) and the
+        /// events each receives
+        #[arg(long, value_name = "FILE")]
+        notifications: Option<String>,
+        /// Fix recoverable repo problems found at startup (interrupted init,
+        /// dangling git link) instead of refusing to start
+        #[arg(long)]
+        repair: bool,
+        /// Git URL mirroring this repo, offered to `tandem init --colocate`
+        #[arg(long, value_name = "URL", env = "TANDEM_GIT_REMOTE")]
+        git_remote: Option<String>,
+        /// Queue head updates by workspace so a client that loses the CAS
+        /// goes next instead of racing faster clients again
+        #[arg(long)]
+        fair_queue: bool,
+        /// How long a queued workspace keeps its turn without retrying
+        /// (e.g. 2s, 10s)
+        #[arg(long, requires = "fair_queue", value_parser = parse_duration_arg)]
+        fair_queue_wait: Option<u64>,
+        /// Lost CAS races per second at which the server tells clients to
+        /// back off longer and notifies the sinks; 0 turns it off
+        #[arg(long, value_name = "PER_SECOND", default_value_t = cas_storm::DEFAULT_THRESHOLD)]
+        cas_storm_threshold: usize,
+        /// Require this token on control socket requests that stop the
+        /// server, stream logs, or change its state
+        #[arg(long, env = control::TOKEN_ENV, hide_env_values = true)]
+        control_token: Option<String>,
+        /// Rewrite a JSON snapshot of server status to this file on a timer,
+        /// for cron checks and dashboards without a metrics scraper
+        #[arg(long, value_name = "PATH")]
+        status_file: Option<String>,
+        /// How often the status file is rewritten (e.g. 10s, 5m; default 30s)
+        #[arg(long, requires = "status_file", value_parser = parse_duration_arg)]
+        status_interval: Option<u64>,
+        /// On shutdown, how long clients in the middle of a commit get to
+        /// finish it (e.g. 10s, 2m; default 30s). New writes are refused
+        /// right away
+        #[arg(long, value_parser = parse_duration_arg)]
+        drain_timeout: Option<u64>,
+        /// Prune operations older than this many days (e.g. 90d) or beyond
+        /// this many of the newest (e.g. 5000), keeping everything the
+        /// current heads reach
+        #[arg(long, value_name = "DAYS|COUNT", value_parser = parse_op_retention_arg)]
+        op_retention: Option<op_retention::OpRetention>,
+        /// At startup, check that operations and views hash to their ids:
+        /// `all`, or this many of the newest. Failures move to
+        /// op_store/corrupt/ and show in `tandem server status`
+        #[arg(long, value_name = "all|COUNT", value_parser = parse_integrity_scan_arg)]
+        integrity_scan: Option<integrity::IntegrityScan>,
+        /// Fail store calls still running after this long (e.g. 30s, 10m;
+        /// default 5m; 0 disables), so a hung handler does not hold its
+        /// connection forever
+        #[arg(long, value_parser = parse_rpc_timeout_arg)]
+        rpc_timeout: Option<u64>,
+        /// Store operations in id-prefix shards (`operations/<2 hex>/<rest>`),
+        /// for repos with hundreds of thousands of operations. Stock jj
+        /// cannot open a sharded repo; serving without this flag moves the
+        /// operations back to jj's flat layout
+        #[arg(long)]
+        shard_operations: bool,
+        #[command(flatten)]
+        snapshot_policy: snapshot_policy::SnapshotPolicyArgs,
     },
 
     /// Initialize a tandem-backed workspace
     #[command(after_help = INIT_AFTER_HELP)]
     Init {
         /// Server address (host:port)
-        #[arg(long, env = "TANDEM_SERVER")]
-        server: String,
+        #[arg(long, env = "TANDEM_SERVER", required_unless_present = "local")]
+        server: Option<String>,
+        /// Keep the repo inside the workspace and serve it in-process: no
+        /// daemon, no network, one user
+        #[arg(long, conflicts_with_all = ["colocate", "read_mirror", "ephemeral"])]
+        local: bool,
         /// Workspace name (auto-generated if omitted)
         #[arg(long, env = "TANDEM_WORKSPACE")]
         workspace: Option<String>,
         /// Workspace directory
         #[arg(default_value = ".")]
         path: String,
+        /// Mark the workspace as short-lived; the server forgets it after
+        /// --ttl without activity
+        #[arg(long)]
+        ephemeral: bool,
+        /// Inactivity TTL for an ephemeral workspace (e.g. 90s, 30m, 2h)
+        #[arg(long, requires = "ephemeral", value_parser = parse_duration_arg)]
+        ttl: Option<u64>,
+        /// Leave a half-initialized workspace in place when init fails
+        /// (for debugging)
+        #[arg(long)]
+        keep_partial: bool,
+        /// Also create a colocated .git fetched from the server's git mirror,
+        /// for tools that only understand git
+        #[arg(long)]
+        colocate: bool,
+        /// Git URL to colocate with instead of the one the server advertises
+        #[arg(long, value_name = "URL", requires = "colocate")]
+        git_remote: Option<String>,
+        /// Check out only this repo path (repeatable); the backend prefetches
+        /// nothing outside the sparse paths
+        #[arg(long, value_name = "PATH")]
+        sparse: Vec<String>,
+        /// Read objects and operations from this server (e.g. a nearby
+        /// `serve --follow` replica); writes still go to --server
+        #[arg(long, value_name = "ADDR")]
+        read_mirror: Option<String>,
+        /// Start on top of this workspace's working-copy commit instead of
+        /// next to the default workspace's
+        #[arg(long, value_name = "NAME", conflicts_with = "local")]
+        from_workspace: Option<String>,
     },
 
     /// Stream head change notifications (requires server)
+    #[command(after_help = WATCH_AFTER_HELP)]
     Watch {
         /// Server address (host:port)
         #[arg(long, env = "TANDEM_SERVER")]
         server: String,
+        /// Raise desktop notifications when another workspace lands a change
+        /// or a bookmark moves
+        #[arg(long)]
+        notify: bool,
+        /// Your own workspace name; its changes do not raise notifications
+        #[arg(long, env = "TANDEM_WORKSPACE")]
+        workspace: Option<String>,
+        /// Replay recorded heads changes after this version before following
+        #[arg(long, value_name = "VERSION")]
+        since: Option<u64>,
+        /// Print a summary under each change: description, author, moved
+        /// bookmarks and touched paths
+        #[arg(long)]
+        summary: bool,
+        /// Most touched paths to list per change (default 100, at most 1000)
+        #[arg(long, value_name = "N", requires = "summary")]
+        summary_paths: Option<u32>,
+    },
+
+    /// JSON-RPC over stdio for editor plugins (requires server)
+    #[command(after_help = IDE_SERVER_AFTER_HELP)]
+    IdeServer {
+        /// Server address (host:port)
+        #[arg(long, env = "TANDEM_SERVER")]
+        server: String,
+        /// Your own workspace name; left out of whoTouched results
+        #[arg(long, env = "TANDEM_WORKSPACE")]
+        workspace: Option<String>,
+    },
+
+    /// List operations carrying tandem tags (requires server)
+    #[command(after_help = OPS_AFTER_HELP)]
+    Ops {
+        /// Server address (host:port)
+        #[arg(long, env = "TANDEM_SERVER")]
+        server: String,
+        /// Only show operations with this tag (key=value, or key for any value).
+        /// Repeat to require several tags.
+        #[arg(long = "tag", value_name = "KEY=VALUE")]
+        tags: Vec<String>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Diff your working copy against integration or another workspace
+    #[command(after_help = DELTA_AFTER_HELP)]
+    Delta {
+        /// Compare against this workspace's working-copy commit instead of
+        /// the integration bookmark
+        workspace: Option<String>,
+        /// For each path, show only whether it was modified, added, or deleted
+        #[arg(long, short)]
+        summary: bool,
+        /// Show a histogram of the changes
+        #[arg(long)]
+        stat: bool,
+    },
+
+    /// Show what each workspace is working on (requires server)
+    #[command(after_help = PS_AFTER_HELP)]
+    Ps {
+        /// Server address (host:port)
+        #[arg(long, env = "TANDEM_SERVER")]
+        server: String,
+        /// Redraw the table every few seconds until interrupted
+        #[arg(long)]
+        watch: bool,
+    },
+
+    /// Time-ordered activity across workspaces (requires server)
+    #[command(after_help = TIMELINE_AFTER_HELP)]
+    Timeline {
+        /// Server address (host:port)
+        #[arg(long, env = "TANDEM_SERVER")]
+        server: String,
+        /// Only show steps taken by this workspace
+        #[arg(long)]
+        workspace: Option<String>,
+        /// Only show activity this recent (e.g. 30m, 2h, 1d)
+        #[arg(long, value_parser = parse_duration_arg)]
+        since: Option<u64>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Find commits by description (requires server)
+    #[command(after_help = SEARCH_AFTER_HELP)]
+    Search {
+        /// Server address (host:port)
+        #[arg(long, env = "TANDEM_SERVER")]
+        server: String,
+        /// Text the description must contain
+        #[arg(default_value = "")]
+        query: String,
+        /// Only commits whose author name or email contains this
+        #[arg(long)]
+        author: Option<String>,
+        /// Most commits to show (default 50, at most 1000)
+        #[arg(long, value_name = "N")]
+        limit: Option<u32>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Export a revision's tree as a tar archive (requires server)
+    #[command(after_help = ARCHIVE_AFTER_HELP)]
+    Archive {
+        /// Server address (host:port)
+        #[arg(long, env = "TANDEM_SERVER")]
+        server: String,
+        /// Revision to export
+        #[arg(short, long, value_name = "REV")]
+        revision: String,
+        /// Output file (.tar, .tar.gz or .tgz), or - for a tar on stdout
+        #[arg(short, long, value_name = "FILE")]
+        output: String,
+    },
+
+    /// Check whether the client or server needs upgrading (requires server)
+    #[command(after_help = UPGRADE_CHECK_AFTER_HELP)]
+    UpgradeCheck {
+        /// Server address (host:port)
+        #[arg(long, env = "TANDEM_SERVER")]
+        server: String,
+        /// Release index with the latest version (path or URL)
+        #[arg(long, env = upgrade_check::INDEX_ENV, value_name = "URL")]
+        index: Option<String>,
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
     },
 
     /// Start tandem server as a background daemon
@@ -156,6 +609,18 @@ enum Commands {
         /// Address to listen on (e.g. 0.0.0.0:13013). If omitted, tandem auto-selects.
         #[arg(long, env = "TANDEM_LISTEN")]
         listen: Option<String>,
+        /// Host to bind when auto-selecting a listen address (e.g. 127.0.0.1
+        /// to accept loopback connections only)
+        #[arg(long, env = "TANDEM_UP_HOST")]
+        listen_host: Option<String>,
+        /// Ports to try when auto-selecting a listen address
+        #[arg(
+            long,
+            env = "TANDEM_UP_PORT_RANGE",
+            value_name = "START-END",
+            value_parser = parse_port_range
+        )]
+        port_range: Option<std::ops::RangeInclusive<u16>>,
         /// Log level for the daemon (trace, debug, info, warn, error)
         #[arg(long, default_value = "info")]
         log_level: String,
@@ -168,13 +633,175 @@ enum Commands {
         /// Enable server-side integration workspace recompute mode
         #[arg(long)]
         enable_integration_workspace: bool,
+        /// Move this bookmark to each clean integration commit (repeatable;
+        /// requires --enable-integration-workspace)
+        #[arg(long = "auto-advance", value_name = "BOOKMARK")]
+        auto_advance: Vec<String>,
+        /// Shell command that must exit 0 before bookmarks auto-advance.
+        /// Runs in the repo root with TANDEM_INTEGRATION_COMMIT set
+        #[arg(long, value_name = "COMMAND")]
+        auto_advance_check: Option<String>,
+        /// CODEOWNERS-like rules file; head updates touching a protected path
+        /// are rejected unless the workspace owns it
+        #[arg(long, value_name = "FILE")]
+        protected_paths: Option<String>,
+        /// Run as a read-only replica that mirrors this primary server
+        #[arg(long, value_name = "PRIMARY")]
+        follow: Option<String>,
+        /// Print the daemon's pid, listen address, control socket, and log
+        /// file as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Multiplex many agent connections onto a few upstream connections
+    #[command(after_help = PROXY_AFTER_HELP)]
+    Proxy {
+        /// Address agents connect to (e.g. 127.0.0.1:13100)
+        #[arg(long)]
+        listen: String,
+        /// Server address (host:port) to forward to
+        #[arg(long)]
+        upstream: String,
+        /// Number of upstream connections to spread agent calls over
+        #[arg(long, default_value_t = 4)]
+        upstream_connections: usize,
+        /// Memory budget for cached objects, operations, and views
+        #[arg(long, default_value_t = 256, value_name = "MB")]
+        cache_mb: usize,
+        /// Log level (trace, debug, info, warn, error)
+        #[arg(long, default_value = "info")]
+        log_level: String,
+        /// Log format (text, json)
+        #[arg(long, default_value = "text")]
+        log_format: String,
+    },
+
+    /// Serve read-only JSON over HTTP for a server's heads and objects
+    #[command(after_help = GATEWAY_AFTER_HELP)]
+    Gateway {
+        /// Address HTTP clients connect to (e.g. 127.0.0.1:13080)
+        #[arg(long)]
+        listen: String,
+        /// Server address (host:port) to read from
+        #[arg(long)]
+        upstream: String,
+        /// Log level (trace, debug, info, warn, error)
+        #[arg(long, default_value = "info")]
+        log_level: String,
+        /// Log format (text, json)
+        #[arg(long, default_value = "text")]
+        log_format: String,
+    },
+
+    /// Simulate workspaces contending on op-head updates, deterministically
+    #[cfg(feature = "sim")]
+    #[command(hide = true)]
+    Sim {
+        /// First seed to run
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// How many consecutive seeds to run
+        #[arg(long, default_value_t = 1)]
+        seeds: u64,
+        /// Simulated workspaces
+        #[arg(long, default_value_t = 4)]
+        workspaces: usize,
+        /// Commits each workspace lands
+        #[arg(long, default_value_t = 4)]
+        commits: usize,
+        /// Upper bound on one RPC's virtual latency, in milliseconds
+        #[arg(long, default_value_t = 20)]
+        max_latency_ms: u64,
+        /// Print every step of each run
+        #[arg(long)]
+        verbose: bool,
+    },
+
+    /// Roll the server's op heads back to a prior operation (operator only)
+    #[command(name = "undo-remote", after_help = UNDO_REMOTE_AFTER_HELP)]
+    UndoRemote {
+        /// Operation id (or unique prefix) whose repo state to restore
+        operation: String,
+        /// Path to control socket
+        #[arg(long)]
+        control_socket: Option<String>,
+        /// Talk to the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
+    },
+
+    /// Hold the server's heads at one version while a backup command runs
+    #[command(after_help = SNAPSHOT_AFTER_HELP)]
+    Snapshot {
+        /// Server address (host:port)
+        #[arg(long, env = "TANDEM_SERVER")]
+        server: String,
+        /// Longest the server keeps the hold if it is never released
+        /// (e.g. 90s, 10m, 1h; at most 1h)
+        #[arg(long, default_value = "10m")]
+        lease: String,
+        /// Backup command to run while the hold is active
+        #[arg(last = true, required = true, value_name = "COMMAND")]
+        command: Vec<String>,
+    },
+
+    /// Move the repo to another server, keeping every workspace
+    #[command(after_help = MIGRATE_AFTER_HELP)]
+    Migrate {
+        /// Server currently hosting the repo (host:port)
+        #[arg(long)]
+        from: String,
+        /// Fresh server to move the repo to (host:port)
+        #[arg(long)]
+        to: String,
+    },
+
+    /// Move a local-mode workspace's repo onto a server
+    #[command(after_help = PUBLISH_AFTER_HELP)]
+    Publish {
+        /// Fresh server to publish the repo to (host:port)
+        #[arg(long)]
+        server: String,
+    },
+
+    /// Tandem-specific workspace commands (others go to jj)
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommands,
+    },
+
+    /// Tandem-specific operation commands (others go to jj)
+    #[command(alias = "operation")]
+    Op {
+        #[command(subcommand)]
+        command: OpCommands,
+    },
+
+    /// Read this repo's client journal (tandem.journal = true)
+    Journal {
+        #[command(subcommand)]
+        command: JournalCommands,
     },
 
     /// Stop the tandem daemon
     Down {
+        /// Stop the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
         /// Path to control socket
         #[arg(long)]
         control_socket: Option<String>,
+        /// Print the stopped daemon's details as JSON
+        #[arg(long)]
+        json: bool,
+    },
+
+    /// Remove saved state for repos that no longer exist and dead control sockets
+    Cleanup {
+        /// Only list what would be removed
+        #[arg(long)]
+        dry_run: bool,
     },
 
     /// Tandem daemon status/log streaming commands
@@ -185,6 +812,40 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum WorkspaceCommands {
+    /// Forget this workspace and drop its server-side entry immediately
+    #[command(after_help = WORKSPACE_RELEASE_AFTER_HELP)]
+    Release,
+}
+
+#[derive(Subcommand)]
+enum OpCommands {
+    /// Integrate every divergent op head and unintegrated working-copy
+    /// operation, in dependency order
+    #[command(after_help = OP_INTEGRATE_ALL_AFTER_HELP)]
+    IntegrateAll {
+        /// Print what would be integrated without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum JournalCommands {
+    /// Show the newest journal entries: each command's RPCs, the heads
+    /// versions it saw, CAS retries, and how it exited
+    #[command(after_help = JOURNAL_AFTER_HELP)]
+    Show {
+        /// How many commands to show
+        #[arg(long, default_value_t = 10)]
+        last: usize,
+        /// Print the entries as JSON lines, every RPC included
+        #[arg(long)]
+        json: bool,
+    },
+}
+
 #[derive(Subcommand)]
 enum ServerCommands {
     /// Show tandem daemon status
@@ -195,8 +856,110 @@ enum ServerCommands {
         /// Path to control socket
         #[arg(long)]
         control_socket: Option<String>,
+        /// Talk to the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
+    },
+
+    /// Check that the daemon can serve: repo loads, git backend and op heads readable
+    Health {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Path to control socket
+        #[arg(long)]
+        control_socket: Option<String>,
+        /// Talk to the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
+    },
+
+    /// Reject new writes with a notice while reads keep working
+    Maintenance {
+        /// How long maintenance lasts (e.g. 30m, 2h); ends automatically
+        #[arg(long = "for", value_name = "DURATION", default_value = "1h", value_parser = parse_duration_arg)]
+        duration: u64,
+        /// Notice shown verbatim to clients whose writes are rejected
+        #[arg(long, default_value = "")]
+        message: String,
+        /// Leave maintenance now
+        #[arg(long, conflicts_with = "message")]
+        off: bool,
+        /// Path to control socket
+        #[arg(long)]
+        control_socket: Option<String>,
+        /// Talk to the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
+    },
+
+    /// Inject faults for resilience testing, or show the ones in effect
+    Faults {
+        /// Fail every Nth updateOpHeads without applying it
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        drop_update_op_heads_every: u64,
+        /// Apply every Nth updateOpHeads but fail its reply, as if the
+        /// connection dropped it
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        lose_update_op_heads_reply_every: u64,
+        /// Delay every getObject reply
+        #[arg(long, value_name = "MS", default_value_t = 0)]
+        get_object_delay: u64,
+        /// Fail every Nth call as overloaded
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        error_every: u64,
+        /// Only count and fail this store method (repeatable; default all)
+        #[arg(long = "error-method", value_name = "METHOD", requires = "error_every")]
+        error_methods: Vec<String>,
+        /// Clear every injected fault
+        #[arg(long, conflicts_with_all = [
+            "drop_update_op_heads_every",
+            "lose_update_op_heads_reply_every",
+            "get_object_delay",
+            "error_every",
+        ])]
+        off: bool,
+        /// Path to control socket
+        #[arg(long)]
+        control_socket: Option<String>,
+        /// Talk to the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
+    },
+
+    /// Send clients to another server (after moving the repo by hand)
+    Redirect {
+        /// Address clients should use instead (host:port)
+        #[arg(required_unless_present = "off")]
+        address: Option<String>,
+        /// Stop redirecting and accept writes again
+        #[arg(long, conflicts_with = "address")]
+        off: bool,
+        /// Path to control socket
+        #[arg(long)]
+        control_socket: Option<String>,
+        /// Talk to the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
+    },
+
+    /// Replace the daemon with a fresh copy of the tandem binary without
+    /// dropping clients (Unix only)
+    Restart {
+        /// Output as JSON
+        #[arg(long)]
+        json: bool,
+        /// Path to control socket
+        #[arg(long)]
+        control_socket: Option<String>,
+        /// Talk to the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
     },
 
+    /// Print the JSON Schema of the control socket protocol
+    Schema,
+
     /// Stream logs from a running tandem daemon
     Logs {
         /// Log level filter (trace, debug, info, warn, error)
@@ -208,6 +971,9 @@ enum ServerCommands {
         /// Path to control socket
         #[arg(long)]
         control_socket: Option<String>,
+        /// Talk to the daemon `tandem up` started for this repository
+        #[arg(long, conflicts_with = "control_socket")]
+        repo: Option<String>,
     },
 }
 
@@ -215,13 +981,25 @@ enum ServerCommands {
 
 fn main() -> ExitCode {
     let args: Vec<String> = std::env::args().collect();
+    server::serve_local_repos();
 
     // Route tandem-specific commands through clap.
     // Everything else falls through to jj's CliRunner which does its own
     // argument parsing — this avoids conflicts with jj global flags like
     // --no-pager, --color, -R that appear before the subcommand.
     match args.get(1).map(|s| s.as_str()) {
-        None | Some("serve" | "init" | "watch" | "up" | "down" | "server" | "--help" | "-h") => {}
+        None
+        | Some(
+            "serve" | "init" | "watch" | "ide-server" | "ops" | "ps" | "timeline" | "delta" | "up"
+            | "down" | "cleanup" | "server" | "undo-remote" | "proxy" | "gateway" | "sim"
+            | "snapshot" | "migrate" | "publish" | "archive" | "search" | "upgrade-check"
+            | "journal" | "--help" | "-h",
+        ) => {}
+        // `tandem workspace release` is ours; every other `workspace`
+        // subcommand belongs to jj.
+        Some("workspace") if args.get(2).map(|s| s.as_str()) == Some("release") => {}
+        // Likewise `tandem op integrate-all`.
+        Some("op" | "operation") if args.get(2).map(|s| s.as_str()) == Some("integrate-all") => {}
         _ => return run_jj(),
     }
 
@@ -241,6 +1019,27 @@ fn main() -> ExitCode {
             daemon,
             log_file,
             enable_integration_workspace,
+            auto_advance,
+            auto_advance_check,
+            protected_paths,
+            follow,
+            health_listen,
+            http_listen,
+            notifications,
+            repair,
+            git_remote,
+            fair_queue,
+            fair_queue_wait,
+            cas_storm_threshold,
+            control_token,
+            status_file,
+            status_interval,
+            drain_timeout,
+            op_retention,
+            integrity_scan,
+            rpc_timeout,
+            shard_operations,
+            snapshot_policy,
         }) => run_serve(
             &listen,
             &repo,
@@ -250,60 +1049,462 @@ fn main() -> ExitCode {
             daemon,
             log_file.as_deref(),
             enable_integration_workspace,
+            server::AutoAdvancePolicy {
+                bookmarks: auto_advance,
+                check_command: auto_advance_check,
+            },
+            protected_paths.as_deref(),
+            follow.as_deref(),
+            health_listen.as_deref(),
+            http_listen.as_deref(),
+            notifications.as_deref(),
+            repair,
+            git_remote.as_deref(),
+            fair_queue.then(|| {
+                fair_queue_wait
+                    .map(std::time::Duration::from_secs)
+                    .unwrap_or(fair_queue::DEFAULT_MAX_WAIT)
+            }),
+            cas_storm_threshold,
+            control_token.filter(|token| !token.is_empty()),
+            status_file.as_deref(),
+            status_interval
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(status_file::DEFAULT_INTERVAL),
+            drain_timeout
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(drain::DEFAULT_TIMEOUT),
+            op_retention,
+            integrity_scan,
+            rpc_timeout
+                .map(std::time::Duration::from_secs)
+                .unwrap_or(watchdog::DEFAULT_TIMEOUT),
+            shard_operations,
+            &snapshot_policy,
         ),
         Some(Commands::Init {
             server,
+            local,
             workspace,
             path,
+            ephemeral,
+            ttl,
+            keep_partial,
+            colocate,
+            git_remote,
+            sparse,
+            read_mirror,
+            from_workspace,
         }) => {
             let workspace_name = resolve_init_workspace_name(workspace.as_deref());
-            run_tandem_init(&server, &workspace_name, &path)
+            let options = InitOptions {
+                ephemeral_ttl_secs: ephemeral.then(|| ttl.unwrap_or(DEFAULT_EPHEMERAL_TTL_SECS)),
+                keep_partial,
+                colocate: colocate.then_some(git_remote),
+                sparse,
+                read_mirror: read_mirror
+                    .map(|addr| addr.trim().to_string())
+                    .filter(|addr| !addr.is_empty()),
+                local,
+                from_workspace,
+            };
+            run_tandem_init(
+                server.as_deref().unwrap_or_default(),
+                &workspace_name,
+                &path,
+                options,
+            )
         }
-        Some(Commands::Watch { server }) => run_watch(&server),
+        Some(Commands::Watch {
+            server,
+            notify,
+            workspace,
+            since,
+            summary,
+            summary_paths,
+        }) => run_watch(
+            &server,
+            notify,
+            workspace.as_deref(),
+            since,
+            summary.then(|| summary_paths.unwrap_or(0)),
+        ),
+        Some(Commands::IdeServer { server, workspace }) => {
+            run_ide_server(&server, workspace.as_deref())
+        }
+        Some(Commands::Ops { server, tags, json }) => run_ops(&server, &tags, json),
+        Some(Commands::Ps { server, watch }) => run_ps(&server, watch),
+        Some(Commands::Timeline {
+            server,
+            workspace,
+            since,
+            json,
+        }) => run_timeline(&server, workspace.as_deref(), since, json),
+        Some(Commands::Search {
+            server,
+            query,
+            author,
+            limit,
+            json,
+        }) => run_search(&server, &query, author.as_deref(), limit, json),
+        Some(Commands::Archive {
+            server,
+            revision,
+            output,
+        }) => run_archive(&server, &revision, &output),
+        Some(Commands::UpgradeCheck {
+            server,
+            index,
+            json,
+        }) => run_upgrade_check(&server, index.as_deref(), json),
+        Some(Commands::Delta {
+            workspace,
+            summary,
+            stat,
+        }) => run_delta(workspace.as_deref(), summary, stat),
         Some(Commands::Up {
             repo,
             listen,
+            listen_host,
+            port_range,
             log_level,
             log_file,
             control_socket,
             enable_integration_workspace,
+            auto_advance,
+            auto_advance_check,
+            protected_paths,
+            follow,
+            json,
         }) => run_up(
             &repo,
             listen.as_deref(),
+            &AutoListen::new(listen_host, port_range),
             &log_level,
             log_file.as_deref(),
             control_socket.as_deref(),
             enable_integration_workspace,
+            &server::AutoAdvancePolicy {
+                bookmarks: auto_advance,
+                check_command: auto_advance_check,
+            },
+            protected_paths.as_deref(),
+            follow.as_deref(),
+            json,
+        ),
+        Some(Commands::Proxy {
+            listen,
+            upstream,
+            upstream_connections,
+            cache_mb,
+            log_level,
+            log_format,
+        }) => run_proxy(proxy::ProxyOptions {
+            listen_addr: listen,
+            upstream_addr: upstream,
+            upstream_connections,
+            cache_bytes: cache_mb.saturating_mul(1024 * 1024),
+            log_level,
+            log_format,
+        }),
+        Some(Commands::Gateway {
+            listen,
+            upstream,
+            log_level,
+            log_format,
+        }) => run_gateway(gateway::GatewayOptions {
+            listen_addr: listen,
+            upstream_addr: upstream,
+            log_level,
+            log_format,
+        }),
+        #[cfg(feature = "sim")]
+        Some(Commands::Sim {
+            seed,
+            seeds,
+            workspaces,
+            commits,
+            max_latency_ms,
+            verbose,
+        }) => run_sim(
+            server::sim::SimConfig {
+                seed,
+                workspaces,
+                commits,
+                max_latency_ms,
+            },
+            seeds,
+            verbose,
+        ),
+        Some(Commands::UndoRemote {
+            operation,
+            control_socket,
+            repo,
+        }) => run_undo_remote(
+            &operation,
+            &resolve_control_socket(control_socket.as_deref(), repo.as_deref()),
         ),
-        Some(Commands::Down { control_socket }) => run_down(control_socket.as_deref()),
+        Some(Commands::Snapshot {
+            server,
+            lease,
+            command,
+        }) => run_snapshot(&server, &lease, &command),
+        Some(Commands::Migrate { from, to }) => run_migrate(&from, &to),
+        Some(Commands::Publish { server }) => run_publish(&server),
+        Some(Commands::Workspace { command }) => match command {
+            WorkspaceCommands::Release => run_workspace_release(),
+        },
+        Some(Commands::Op { command }) => match command {
+            OpCommands::IntegrateAll { dry_run } => run_op_integrate_all(dry_run),
+        },
+        Some(Commands::Journal { command }) => match command {
+            JournalCommands::Show { last, json } => run_journal_show(last, json),
+        },
+        Some(Commands::Down {
+            repo,
+            control_socket,
+            json,
+        }) => run_down(repo.as_deref(), control_socket.as_deref(), json),
+        Some(Commands::Cleanup { dry_run }) => run_cleanup(dry_run),
         Some(Commands::Server { command }) => match command {
             ServerCommands::Status {
                 json,
                 control_socket,
-            } => run_status(json, control_socket.as_deref()),
+                repo,
+            } => run_status(
+                json,
+                &resolve_control_socket(control_socket.as_deref(), repo.as_deref()),
+            ),
+            ServerCommands::Health {
+                json,
+                control_socket,
+                repo,
+            } => run_health(
+                json,
+                &resolve_control_socket(control_socket.as_deref(), repo.as_deref()),
+            ),
             ServerCommands::Logs {
                 level,
                 json,
                 control_socket,
-            } => run_logs(&level, json, control_socket.as_deref()),
+                repo,
+            } => run_logs(
+                &level,
+                json,
+                &resolve_control_socket(control_socket.as_deref(), repo.as_deref()),
+            ),
+            ServerCommands::Maintenance {
+                duration,
+                message,
+                off,
+                control_socket,
+                repo,
+            } => run_maintenance(
+                duration,
+                &message,
+                off,
+                &resolve_control_socket(control_socket.as_deref(), repo.as_deref()),
+            ),
+            ServerCommands::Faults {
+                drop_update_op_heads_every,
+                lose_update_op_heads_reply_every,
+                get_object_delay,
+                error_every,
+                error_methods,
+                off,
+                control_socket,
+                repo,
+            } => {
+                let config = faults::FaultConfig {
+                    drop_update_op_heads_every,
+                    lose_update_op_heads_reply_every,
+                    get_object_delay_ms: get_object_delay,
+                    error_every,
+                    error_methods,
+                };
+                // No flags at all only reads the faults in effect.
+                let change = (off || config.is_active()).then_some(config);
+                run_faults(
+                    change.as_ref(),
+                    &resolve_control_socket(control_socket.as_deref(), repo.as_deref()),
+                )
+            }
+            ServerCommands::Redirect {
+                address,
+                off: _,
+                control_socket,
+                repo,
+            } => run_redirect(
+                address.as_deref(),
+                &resolve_control_socket(control_socket.as_deref(), repo.as_deref()),
+            ),
+            ServerCommands::Restart {
+                json,
+                control_socket,
+                repo,
+            } => run_restart(
+                json,
+                &resolve_control_socket(control_socket.as_deref(), repo.as_deref()),
+            ),
+            ServerCommands::Schema => {
+                print!("{}", control::SCHEMA);
+                ExitCode::SUCCESS
+            }
         },
     }
 }
 
-// ─── Watch mode ───────────────────────────────────────────────────────────────
+// ─── Watch mode ───────────────────────────────────────────────────────────────
+
+fn run_watch(
+    server_addr: &str,
+    notify: bool,
+    workspace: Option<&str>,
+    since: Option<u64>,
+    summary_paths: Option<u32>,
+) -> ExitCode {
+    if let Err(err) = watch::run_watch(server_addr, notify, workspace, since, summary_paths) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+// ─── Operation tags ───────────────────────────────────────────────────────────
+
+fn run_ops(server_addr: &str, tags: &[String], json: bool) -> ExitCode {
+    if let Err(err) = ops::run_ops(server_addr, tags, json) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+// ─── Workspace activity ───────────────────────────────────────────────────────
+
+fn run_ide_server(server_addr: &str, own_workspace: Option<&str>) -> ExitCode {
+    if let Err(err) = ide::run_ide_server(server_addr, own_workspace) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_ps(server_addr: &str, watch: bool) -> ExitCode {
+    if let Err(err) = ps::run_ps(server_addr, watch) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_search(
+    server_addr: &str,
+    query: &str,
+    author: Option<&str>,
+    limit: Option<u32>,
+    json: bool,
+) -> ExitCode {
+    if let Err(err) = search::run_search(server_addr, query, author, limit, json) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_archive(server_addr: &str, revision: &str, output: &str) -> ExitCode {
+    if let Err(err) = archive::run_archive(server_addr, revision, output) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_upgrade_check(server_addr: &str, index: Option<&str>, json: bool) -> ExitCode {
+    if let Err(err) = upgrade_check::run_upgrade_check(server_addr, index, json) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
 
-fn run_watch(server_addr: &str) -> ExitCode {
-    if let Err(err) = watch::run_watch(server_addr) {
+fn run_timeline(
+    server_addr: &str,
+    workspace: Option<&str>,
+    since_secs: Option<u64>,
+    json: bool,
+) -> ExitCode {
+    if let Err(err) = timeline::run_timeline(server_addr, workspace, since_secs, json) {
         eprintln!("error: {err:#}");
         return ExitCode::FAILURE;
     }
     ExitCode::SUCCESS
 }
 
+// ─── Delta ────────────────────────────────────────────────────────────────────
+
+/// Runs `jj diff --from <target> --to @` through this binary so the diff goes
+/// through the tandem store like any other jj command.
+fn run_delta(workspace: Option<&str>, summary: bool, stat: bool) -> ExitCode {
+    let target = match workspace {
+        Some(name) => workspace_revset(name),
+        None => "integration".to_string(),
+    };
+
+    let exe = match std::env::current_exe() {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("error: cannot determine executable path: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut cmd = std::process::Command::new(exe);
+    cmd.args(["diff", "--from", &target, "--to", "@"]);
+    if summary {
+        cmd.arg("--summary");
+    }
+    if stat {
+        cmd.arg("--stat");
+    }
+
+    match cmd.status() {
+        Ok(status) if status.success() => ExitCode::SUCCESS,
+        Ok(_) => {
+            if workspace.is_none() {
+                eprintln!(
+                    "hint: the `integration` bookmark exists only when the server runs with \
+                     --enable-integration-workspace; pass a workspace name to diff against that \
+                     workspace instead"
+                );
+            }
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("error: failed to run jj diff: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Revset for a workspace's working-copy commit, quoting names that are not
+/// plain revset symbols.
+fn workspace_revset(name: &str) -> String {
+    let plain = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if plain {
+        format!("{name}@")
+    } else {
+        format!("{name:?}@")
+    }
+}
+
 // ─── Server mode ──────────────────────────────────────────────────────────────
 
+#[allow(clippy::too_many_arguments)]
 fn run_serve(
-    listen_addr: &str,
+    listen_addrs: &[String],
     repo_path: &str,
     log_level: &str,
     log_format: &str,
@@ -311,10 +1512,37 @@ fn run_serve(
     daemon: bool,
     log_file: Option<&str>,
     enable_integration_workspace_flag: bool,
+    auto_advance: server::AutoAdvancePolicy,
+    protected_paths: Option<&str>,
+    follow: Option<&str>,
+    health_listen: Option<&str>,
+    http_listen: Option<&str>,
+    notifications: Option<&str>,
+    repair: bool,
+    git_remote: Option<&str>,
+    fair_queue: Option<std::time::Duration>,
+    cas_storm_threshold: usize,
+    control_token: Option<String>,
+    status_file: Option<&str>,
+    status_interval: std::time::Duration,
+    drain_timeout: std::time::Duration,
+    op_retention: Option<op_retention::OpRetention>,
+    integrity_scan: Option<integrity::IntegrityScan>,
+    rpc_timeout: std::time::Duration,
+    shard_operations: bool,
+    snapshot_policy: &snapshot_policy::SnapshotPolicyArgs,
 ) -> ExitCode {
     // In daemon mode, stdout/stderr are already redirected to the log file
     // by `run_up` before spawning this process. Nothing extra needed here.
 
+    let snapshot_auto_track = match snapshot_policy.auto_track() {
+        Ok(fileset) => fileset,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+
     let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
@@ -322,7 +1550,7 @@ fn run_serve(
     let local = tokio::task::LocalSet::new();
 
     let opts = server::ServeOptions {
-        listen_addr: listen_addr.to_string(),
+        listen_addrs: listen_addrs.to_vec(),
         repo_path: repo_path.to_string(),
         log_level: log_level.to_string(),
         log_format: log_format.to_string(),
@@ -332,6 +1560,25 @@ fn run_serve(
         enable_integration_workspace: resolve_integration_workspace_enabled(
             enable_integration_workspace_flag,
         ),
+        auto_advance,
+        protected_paths: protected_paths.map(|s| s.to_string()),
+        follow: follow.map(|s| s.to_string()),
+        health_listen: health_listen.map(|s| s.to_string()),
+        http_listen: http_listen.map(|s| s.to_string()),
+        notifications: notifications.map(|s| s.to_string()),
+        repair,
+        git_remote: git_remote.map(|s| s.to_string()),
+        fair_queue,
+        cas_storm_threshold,
+        control_token,
+        status_file: status_file.map(|s| s.to_string()),
+        status_interval,
+        drain_timeout,
+        op_retention,
+        integrity_scan,
+        rpc_timeout,
+        shard_operations,
+        snapshot_auto_track,
     };
 
     if let Err(err) = local.block_on(&rt, server::run_serve(opts)) {
@@ -342,17 +1589,147 @@ fn run_serve(
     ExitCode::SUCCESS
 }
 
+// ─── Proxy mode ───────────────────────────────────────────────────────────────
+
+fn run_proxy(opts: proxy::ProxyOptions) -> ExitCode {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let local = tokio::task::LocalSet::new();
+
+    if let Err(err) = local.block_on(&rt, proxy::run_proxy(opts)) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+// ─── Gateway mode ─────────────────────────────────────────────────────────────
+
+fn run_gateway(opts: gateway::GatewayOptions) -> ExitCode {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+
+    if let Err(err) = rt.block_on(gateway::run_gateway(opts)) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+#[cfg(feature = "sim")]
+fn run_sim(config: server::sim::SimConfig, seeds: u64, verbose: bool) -> ExitCode {
+    for seed in config.seed..config.seed.saturating_add(seeds) {
+        let repo_dir =
+            std::env::temp_dir().join(format!("tandem-sim-{}-{seed}", std::process::id()));
+        let config = server::sim::SimConfig {
+            seed,
+            ..config.clone()
+        };
+        let result = server::sim::run(&repo_dir, &config);
+        let _ = std::fs::remove_dir_all(&repo_dir);
+        match result {
+            Ok(report) => {
+                if verbose {
+                    for line in &report.schedule {
+                        println!("  {line}");
+                    }
+                }
+                println!(
+                    "seed {seed}: {} commits, {} CAS conflicts, at most {} attempts, \
+                     heads v{}, {}ms virtual",
+                    report.commits,
+                    report.cas_conflicts,
+                    report.max_attempts,
+                    report.final_version,
+                    report.virtual_ms
+                );
+            }
+            Err(err) => {
+                eprintln!("error: {err:#}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+// ─── Snapshot holds ───────────────────────────────────────────────────────────
+
+fn run_snapshot(server_addr: &str, lease: &str, command: &[String]) -> ExitCode {
+    let result = ephemeral::parse_ttl(lease)
+        .map(std::time::Duration::from_secs)
+        .and_then(|lease| snapshot_hold::run_snapshot(server_addr, lease, command));
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ─── Migration ────────────────────────────────────────────────────────────────
+
+fn run_migrate(from: &str, to: &str) -> ExitCode {
+    match migrate::run_migrate(from, to) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_publish(server: &str) -> ExitCode {
+    match migrate::run_publish(server) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
 // ─── Up / Down / Status / Logs ────────────────────────────────────────────────
 
+/// Where daemons put their control sockets: the per-user
+/// `$XDG_RUNTIME_DIR/tandem` when it is set, otherwise `tandem/` in the
+/// temp dir.
+fn control_socket_dir() -> std::path::PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => std::path::PathBuf::from(dir).join("tandem"),
+        _ => std::env::temp_dir().join("tandem"),
+    }
+}
+
 fn default_control_socket() -> String {
-    let dir = std::env::temp_dir().join("tandem");
-    std::fs::create_dir_all(&dir).ok();
+    let dir = control_socket_dir();
+    control::create_private_dir(&dir).ok();
     dir.join("control.sock").to_string_lossy().to_string()
 }
 
-fn resolve_control_socket(explicit: Option<&str>) -> String {
-    explicit
-        .map(|s| s.to_string())
+/// The control socket a daemon command talks to: `--control-socket`, else
+/// the daemon for `--repo`, else the daemon for the repo around the current
+/// directory, else the shared `control.sock` older versions started.
+fn resolve_control_socket(explicit: Option<&str>, repo: Option<&str>) -> String {
+    if let Some(socket) = explicit {
+        return socket.to_string();
+    }
+    if let Some(repo) = repo {
+        return find_repo_control_socket(repo).unwrap_or_else(|| repo_control_socket(repo));
+    }
+    std::env::current_dir()
+        .ok()
+        .and_then(|dir| {
+            let repo = dir.ancestors().find(|dir| dir.join(".jj").is_dir())?;
+            find_repo_control_socket(&repo.to_string_lossy())
+        })
         .unwrap_or_else(default_control_socket)
 }
 
@@ -376,6 +1753,60 @@ const DEFAULT_UP_HOST: &str = "0.0.0.0";
 const DEFAULT_UP_PORT_START: u16 = 13013;
 const DEFAULT_UP_PORT_END: u16 = 13063;
 
+/// Where `tandem up` looks for a listen address when `--listen` is omitted.
+struct AutoListen {
+    host: String,
+    ports: std::ops::RangeInclusive<u16>,
+    /// Host or range came from the command line or environment rather than
+    /// the defaults.
+    configured: bool,
+}
+
+impl AutoListen {
+    fn new(host: Option<String>, ports: Option<std::ops::RangeInclusive<u16>>) -> Self {
+        let host = host.map(|h| h.trim().to_string()).filter(|h| !h.is_empty());
+        Self {
+            configured: host.is_some() || ports.is_some(),
+            host: host.unwrap_or_else(|| DEFAULT_UP_HOST.to_string()),
+            ports: ports.unwrap_or(DEFAULT_UP_PORT_START..=DEFAULT_UP_PORT_END),
+        }
+    }
+
+    /// Whether `addr` is one this policy could have picked.
+    fn allows(&self, addr: &str) -> bool {
+        listen_port(addr).is_some_and(|port| self.ports.contains(&port) && addr == self.addr(port))
+    }
+
+    fn addr(&self, port: u16) -> String {
+        if self.host.contains(':') && !self.host.starts_with('[') {
+            format!("[{}]:{port}", self.host)
+        } else {
+            format!("{}:{port}", self.host)
+        }
+    }
+
+    fn describe(&self) -> String {
+        format!("{}-{}", self.addr(*self.ports.start()), self.ports.end())
+    }
+}
+
+fn parse_port_range(raw: &str) -> Result<std::ops::RangeInclusive<u16>, String> {
+    let raw = raw.trim();
+    let (start, end) = raw.split_once('-').unwrap_or((raw, raw));
+    let parse = |port: &str| {
+        port.trim()
+            .parse::<u16>()
+            .ok()
+            .filter(|&port| port > 0)
+            .ok_or_else(|| format!("invalid port {port:?} in range {raw:?}"))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        return Err(format!("port range {raw:?} ends before it starts"));
+    }
+    Ok(start..=end)
+}
+
 fn up_state_dir() -> std::path::PathBuf {
     let dir = std::env::temp_dir().join("tandem").join("up-state");
     let _ = std::fs::create_dir_all(&dir);
@@ -398,28 +1829,64 @@ fn last_listen_path(repo: &str) -> std::path::PathBuf {
 
 fn read_last_listen(repo: &str) -> Option<String> {
     let path = last_listen_path(repo);
-    std::fs::read_to_string(path)
-        .ok()
-        .map(|v| v.trim().to_string())
-        .filter(|v| !v.is_empty())
+    let contents = std::fs::read_to_string(path).ok()?;
+    cleanup::parse_last_listen(&contents)
+        .listen
+        .map(|listen| listen.to_string())
+}
+
+/// Control socket of the daemon `tandem up` last started for `repo`.
+fn read_repo_control_socket(repo: &str) -> Option<String> {
+    let path = last_listen_path(repo);
+    let contents = std::fs::read_to_string(path).ok()?;
+    cleanup::parse_last_listen(&contents)
+        .control_socket
+        .map(|socket| socket.to_string())
+}
+
+/// The control socket `tandem up` uses for `repo` by default, named after
+/// the repo identity like its last-listen file.
+fn repo_control_socket(repo: &str) -> String {
+    let key = format!("{:016x}", hash_repo_identity(repo));
+    control_socket_dir()
+        .join(format!("control-{key}.sock"))
+        .to_string_lossy()
+        .to_string()
+}
+
+/// The control socket of a daemon started for `repo`: the one `tandem up`
+/// recorded, else the repo's default socket if one exists.
+fn find_repo_control_socket(repo: &str) -> Option<String> {
+    read_repo_control_socket(repo).or_else(|| {
+        let socket = repo_control_socket(repo);
+        std::path::Path::new(&socket).exists().then_some(socket)
+    })
 }
 
-fn write_last_listen(repo: &str, listen: &str) {
+fn write_last_listen(repo: &str, listen: &str, control_socket: &str) {
     let path = last_listen_path(repo);
-    let _ = std::fs::write(path, listen);
+    let canonical = std::fs::canonicalize(repo).unwrap_or_else(|_| std::path::PathBuf::from(repo));
+    let socket = std::path::absolute(control_socket)
+        .map(|socket| socket.to_string_lossy().to_string())
+        .unwrap_or_else(|_| control_socket.to_string());
+    let _ = std::fs::write(
+        path,
+        cleanup::format_last_listen(listen, &canonical, &socket),
+    );
 }
 
 fn can_bind_listen_addr(addr: &str) -> bool {
     std::net::TcpListener::bind(addr).is_ok()
 }
 
-fn find_auto_listen_addr(repo: &str) -> Option<String> {
-    let span = (DEFAULT_UP_PORT_END - DEFAULT_UP_PORT_START + 1) as usize;
+fn find_auto_listen_addr(repo: &str, auto: &AutoListen) -> Option<String> {
+    let first = *auto.ports.start();
+    let span = (*auto.ports.end() - first) as usize + 1;
     let start_offset = (hash_repo_identity(repo) as usize) % span;
 
     for i in 0..span {
-        let port = DEFAULT_UP_PORT_START + ((start_offset + i) % span) as u16;
-        let candidate = format!("{DEFAULT_UP_HOST}:{port}");
+        let port = first + ((start_offset + i) % span) as u16;
+        let candidate = auto.addr(port);
         if can_bind_listen_addr(&candidate) {
             return Some(candidate);
         }
@@ -427,33 +1894,90 @@ fn find_auto_listen_addr(repo: &str) -> Option<String> {
     None
 }
 
-fn resolve_up_listen(repo: &str, explicit: Option<&str>) -> Result<String, String> {
+fn listen_port(addr: &str) -> Option<u16> {
+    addr.rsplit_once(':')?.1.parse().ok()
+}
+
+/// Describe the tandem daemon listening on `port`, if one of `daemons` is.
+fn port_owner(port: u16, daemons: &[(String, control::StatusResponse)]) -> Option<String> {
+    daemons
+        .iter()
+        .find(|(_, status)| listen_port(&status.listen) == Some(port))
+        .map(|(socket, status)| {
+            format!(
+                "tandem PID {} serving {} (control socket {socket})",
+                status.pid, status.repo
+            )
+        })
+}
+
+fn resolve_up_listen(
+    repo: &str,
+    explicit: Option<&str>,
+    auto: &AutoListen,
+) -> Result<String, String> {
     if let Some(addr) = explicit.map(|s| s.trim()).filter(|s| !s.is_empty()) {
         return Ok(addr.to_string());
     }
 
-    if let Some(last) = read_last_listen(repo) {
+    let daemons = control::running_daemons(&control_socket_dir());
+    // Once a host or range is configured, only reuse the last address while it
+    // fits, so switching to loopback takes effect on the next `tandem up`.
+    let last = read_last_listen(repo).filter(|last| !auto.configured || auto.allows(last));
+    if let Some(last) = last {
         if can_bind_listen_addr(&last) {
             return Ok(last);
         }
+        let owner = listen_port(&last)
+            .and_then(|port| port_owner(port, &daemons))
+            .map(|owner| format!(" by {owner}"))
+            .unwrap_or_default();
+        eprintln!(
+            "note: {last}, this repo's last listen address, is taken{owner}; picking another port"
+        );
     }
 
-    find_auto_listen_addr(repo).ok_or_else(|| {
-        format!(
-            "could not find a free listen address in {DEFAULT_UP_HOST}:{DEFAULT_UP_PORT_START}-{DEFAULT_UP_PORT_END}; pass --listen <addr>"
-        )
+    find_auto_listen_addr(repo, auto).ok_or_else(|| {
+        let mut message = format!(
+            "could not find a free listen address in {}",
+            auto.describe()
+        );
+        let mut other = 0;
+        for port in auto.ports.clone() {
+            match port_owner(port, &daemons) {
+                Some(owner) => message.push_str(&format!("\n  port {port}: {owner}")),
+                None => other += 1,
+            }
+        }
+        if other > 0 {
+            message.push_str(&format!(
+                "\n  {other} port(s) held by processes other than tandem"
+            ));
+        }
+        message.push_str(
+            "\npass --listen <addr>, or widen the range with --port-range / TANDEM_UP_PORT_RANGE",
+        );
+        message
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_up(
     repo: &str,
     listen: Option<&str>,
+    auto_listen: &AutoListen,
     log_level: &str,
     log_file: Option<&str>,
     control_socket: Option<&str>,
     enable_integration_workspace_flag: bool,
+    auto_advance: &server::AutoAdvancePolicy,
+    protected_paths: Option<&str>,
+    follow: Option<&str>,
+    json: bool,
 ) -> ExitCode {
-    let sock_path = resolve_control_socket(control_socket);
+    let sock_path = control_socket
+        .map(|s| s.to_string())
+        .unwrap_or_else(|| repo_control_socket(repo));
     let enable_integration_workspace =
         resolve_integration_workspace_enabled(enable_integration_workspace_flag);
 
@@ -468,7 +1992,7 @@ fn run_up(
         }
     }
 
-    let listen_addr = match resolve_up_listen(repo, listen) {
+    let listen_addr = match resolve_up_listen(repo, listen, auto_listen) {
         Ok(addr) => addr,
         Err(e) => {
             eprintln!("error: {e}");
@@ -509,6 +2033,18 @@ fn run_up(
     if enable_integration_workspace {
         cmd.arg("--enable-integration-workspace");
     }
+    for bookmark in &auto_advance.bookmarks {
+        cmd.args(["--auto-advance", bookmark]);
+    }
+    if let Some(check) = auto_advance.check_command.as_deref() {
+        cmd.args(["--auto-advance-check", check]);
+    }
+    if let Some(path) = protected_paths {
+        cmd.args(["--protected-paths", path]);
+    }
+    if let Some(primary) = follow {
+        cmd.args(["--follow", primary]);
+    }
 
     // Redirect stdout/stderr to log file for daemon
     let log_file_handle = match std::fs::File::create(&log_file_path) {
@@ -528,6 +2064,14 @@ fn run_up(
     cmd.stdout(std::process::Stdio::from(log_file_handle));
     cmd.stderr(std::process::Stdio::from(stderr_file));
     cmd.stdin(std::process::Stdio::null());
+    // Keep the daemon off this console so closing it, or Ctrl+C here, does
+    // not reach the server.
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use windows_sys::Win32::System::Threading::{CREATE_NEW_PROCESS_GROUP, DETACHED_PROCESS};
+        cmd.creation_flags(DETACHED_PROCESS | CREATE_NEW_PROCESS_GROUP);
+    }
 
     // Inherit HOME/XDG env from current process for isolation in tests
     let child = match cmd.spawn() {
@@ -545,15 +2089,23 @@ fn run_up(
     loop {
         let sock = std::path::Path::new(&sock_path);
         if sock.exists() {
-            #[cfg(unix)]
-            if std::os::unix::net::UnixStream::connect(sock).is_ok() {
-                // Verify healthy via status
-                if let Ok(status) = control::client_status(&sock_path) {
-                    if status.running {
-                        write_last_listen(repo, &listen_addr);
+            // Verify healthy via status
+            if let Ok(status) = control::client_status(&sock_path) {
+                if status.running {
+                    write_last_listen(repo, &listen_addr, &sock_path);
+                    if json {
+                        let out = serde_json::json!({
+                            "pid": pid,
+                            "listen": listen_addr,
+                            "controlSocket": sock_path,
+                            "logFile": log_file_path,
+                            "repo": repo,
+                        });
+                        println!("{out}");
+                    } else {
                         println!("tandem running on {listen_addr}, PID {pid}");
-                        return ExitCode::SUCCESS;
                     }
+                    return ExitCode::SUCCESS;
                 }
             }
         }
@@ -565,14 +2117,30 @@ fn run_up(
     }
 }
 
-fn run_down(control_socket: Option<&str>) -> ExitCode {
-    let sock_path = resolve_control_socket(control_socket);
+fn run_down(repo: Option<&str>, control_socket: Option<&str>, json: bool) -> ExitCode {
+    let sock_path = match repo {
+        Some(repo) => match find_repo_control_socket(repo) {
+            Some(socket) => socket,
+            None => {
+                eprintln!(
+                    "error: no daemon recorded for repo {repo}; pass --control-socket <path>"
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+        None => resolve_control_socket(control_socket, None),
+    };
 
     // Try to get status first
     let status = match control::client_status(&sock_path) {
         Ok(s) => s,
         Err(_) => {
-            eprintln!("tandem is not running");
+            let sock = std::path::Path::new(&sock_path);
+            if cleanup::is_stale_socket(sock) && std::fs::remove_file(sock).is_ok() {
+                eprintln!("tandem is not running (removed stale control socket {sock_path})");
+            } else {
+                eprintln!("tandem is not running");
+            }
             return ExitCode::FAILURE;
         }
     };
@@ -593,32 +2161,210 @@ fn run_down(control_socket: Option<&str>) -> ExitCode {
     // Wait for process to exit
     let deadline = std::time::Instant::now() + std::time::Duration::from_secs(10);
     loop {
-        // Check if process is still alive
-        #[cfg(unix)]
-        {
-            let alive = unsafe { libc::kill(pid as libc::pid_t, 0) } == 0;
-            if !alive {
+        if !process_alive(pid) {
+            if json {
+                let out = serde_json::json!({
+                    "pid": pid,
+                    "listen": status.listen,
+                    "controlSocket": sock_path,
+                    "repo": status.repo,
+                    "stopped": true,
+                });
+                println!("{out}");
+            } else {
                 println!("tandem stopped");
-                return ExitCode::SUCCESS;
             }
-        }
-        #[cfg(not(unix))]
-        {
-            println!("tandem stopped");
             return ExitCode::SUCCESS;
         }
         if std::time::Instant::now() > deadline {
             eprintln!("warning: daemon did not exit within timeout");
             return ExitCode::FAILURE;
         }
-        std::thread::sleep(std::time::Duration::from_millis(50));
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+}
+
+/// Whether `pid` is still running. A zombie has exited, so it counts as
+/// gone: a daemon started by a supervisor or test harness that has not
+/// reaped it yet would otherwise look alive until `down` gives up.
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    if unsafe { libc::kill(pid as libc::pid_t, 0) } != 0 {
+        return false;
+    }
+    !is_zombie(pid)
+}
+
+#[cfg(target_os = "linux")]
+fn is_zombie(pid: u32) -> bool {
+    // The state follows the parenthesized command name in /proc/<pid>/stat.
+    std::fs::read_to_string(format!("/proc/{pid}/stat"))
+        .ok()
+        .and_then(|stat| {
+            let (_, rest) = stat.rsplit_once(')')?;
+            rest.split_whitespace().next().map(|state| state == "Z")
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_zombie(pid: u32) -> bool {
+    std::process::Command::new("ps")
+        .args(["-o", "stat=", "-p", &pid.to_string()])
+        .output()
+        .map(|out| {
+            String::from_utf8_lossy(&out.stdout)
+                .trim_start()
+                .starts_with('Z')
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut code = 0u32;
+        let ok = GetExitCodeProcess(handle, &mut code) != 0;
+        CloseHandle(handle);
+        ok && code == STILL_ACTIVE as u32
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn process_alive(_pid: u32) -> bool {
+    false
+}
+
+fn run_cleanup(dry_run: bool) -> ExitCode {
+    match cleanup::sweep(&up_state_dir(), &control_socket_dir(), dry_run) {
+        Ok(removed) if removed.is_empty() => {
+            println!("nothing to clean up");
+            ExitCode::SUCCESS
+        }
+        Ok(removed) => {
+            let verb = if dry_run { "would remove" } else { "removed" };
+            for entry in &removed {
+                println!("{verb} {} ({})", entry.path.display(), entry.reason);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_undo_remote(operation: &str, sock_path: &str) -> ExitCode {
+    if control::client_status(sock_path).is_err() {
+        eprintln!("no tandem daemon running. Start one with `tandem up`.");
+        return ExitCode::FAILURE;
+    }
+
+    match control::client_undo_remote(sock_path, operation) {
+        Ok(outcome) => {
+            println!("restored operation {}", outcome.restored_operation);
+            println!("recorded operation {}", outcome.new_operation);
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_maintenance(duration_secs: u64, message: &str, off: bool, sock_path: &str) -> ExitCode {
+    let notice = (!off).then(|| maintenance::MaintenanceNotice {
+        until: maintenance::now_secs().saturating_add(duration_secs),
+        message: message.to_string(),
+    });
+    match control::client_maintenance(sock_path, notice.as_ref()) {
+        Ok(Some(notice)) => {
+            println!("{}", notice.describe());
+            ExitCode::SUCCESS
+        }
+        Ok(None) => {
+            println!("maintenance mode off");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_faults(config: Option<&faults::FaultConfig>, sock_path: &str) -> ExitCode {
+    match control::client_faults(sock_path, config) {
+        Ok(config) => {
+            let lines = config.describe();
+            if lines.is_empty() {
+                println!("no faults injected");
+            }
+            for line in lines {
+                println!("{line}");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_redirect(address: Option<&str>, sock_path: &str) -> ExitCode {
+    match control::client_redirect(sock_path, address) {
+        Ok(Some(target)) => {
+            println!(
+                "redirecting clients to {}; writes are refused here",
+                target.address
+            );
+            ExitCode::SUCCESS
+        }
+        Ok(None) => {
+            println!("redirect off");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run_restart(json: bool, sock_path: &str) -> ExitCode {
+    match control::client_restart(sock_path) {
+        Ok(restart) => {
+            if json {
+                println!("{}", serde_json::to_string_pretty(&restart).unwrap());
+            } else {
+                println!(
+                    "tandem restarted (PID {} → {}); the old server is draining",
+                    restart.previous_pid, restart.pid
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            ExitCode::FAILURE
+        }
     }
 }
 
-fn run_status(json: bool, control_socket: Option<&str>) -> ExitCode {
-    let sock_path = resolve_control_socket(control_socket);
-
-    match control::client_status(&sock_path) {
+fn run_status(json: bool, sock_path: &str) -> ExitCode {
+    match control::client_status(sock_path) {
         Ok(status) if status.running => {
             if json {
                 println!("{}", serde_json::to_string_pretty(&status).unwrap());
@@ -635,7 +2381,52 @@ fn run_status(json: bool, control_socket: Option<&str>) -> ExitCode {
                 }
                 println!("  Repo:     {}", status.repo);
                 println!("  Listen:   {}", status.listen);
+                for addr in &status.also_listen {
+                    println!("            {addr}");
+                }
+                if let Some(primary) = status.following.as_deref() {
+                    println!("  Follows:  {primary} (read-only replica)");
+                }
                 println!("  Version:  {}", status.version);
+                if let Some(resources) = status.resources.as_ref() {
+                    let disk = &resources.disk;
+                    println!("  Process:  {}", resources.describe_process());
+                    println!(
+                        "  Disk:     {} (git objects {}, operations {}, tandem {})",
+                        resources::human_bytes(disk.total_bytes()),
+                        resources::human_bytes(disk.git_objects_bytes),
+                        resources::human_bytes(disk.op_store_bytes),
+                        resources::human_bytes(disk.tandem_bytes),
+                    );
+                    println!(
+                        "  Caches:   commit index {}",
+                        resources::human_bytes(disk.index_cache_bytes)
+                    );
+                }
+                if let Some(notice) = status.maintenance.as_ref() {
+                    println!("  Maintenance: {}", notice.describe());
+                }
+                if let Some(target) = status.redirect.as_ref() {
+                    println!("  Redirect: {} (writes refused)", target.address);
+                }
+                if let Some(faults) = status.faults.as_ref() {
+                    println!("  Faults:   {}", faults.describe().join("; "));
+                }
+                if let Some(watchdog) = status.watchdog.as_ref() {
+                    println!("  Watchdog: {}", watchdog.describe());
+                }
+                if let Some(report) = status.integrity.as_ref() {
+                    println!("  Integrity: {}", report.describe());
+                    for file in &report.quarantined {
+                        println!(
+                            "            {} {}: {} (in op_store/{}/)",
+                            file.kind,
+                            &file.id[..12.min(file.id.len())],
+                            file.reason,
+                            integrity::CORRUPT_DIR
+                        );
+                    }
+                }
                 println!(
                     "  Integration workspace: {}",
                     if status.integration.enabled {
@@ -652,6 +2443,9 @@ fn run_status(json: bool, control_socket: Option<&str>) -> ExitCode {
                     if let Some(error) = status.integration.last_error.as_deref() {
                         println!("  Integration error:  {error}");
                     }
+                    if let Some(outcome) = status.integration.last_auto_advance.as_deref() {
+                        println!("  Auto-advance:       {outcome}");
+                    }
                 }
             }
             ExitCode::SUCCESS
@@ -667,15 +2461,51 @@ fn run_status(json: bool, control_socket: Option<&str>) -> ExitCode {
     }
 }
 
-fn run_logs(level: &str, json: bool, control_socket: Option<&str>) -> ExitCode {
-    let sock_path = resolve_control_socket(control_socket);
+fn run_health(json: bool, sock_path: &str) -> ExitCode {
+    let report = match control::client_health(sock_path) {
+        Ok(report) => report,
+        Err(_) => {
+            if json {
+                println!("{{\"healthy\":false,\"checks\":[]}}");
+            } else {
+                eprintln!("tandem is not running");
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    } else {
+        println!(
+            "tandem is {}",
+            if report.healthy {
+                "healthy"
+            } else {
+                "unhealthy"
+            }
+        );
+        for check in &report.checks {
+            let mark = if check.ok { "ok" } else { "FAIL" };
+            match check.detail.as_deref() {
+                Some(detail) => println!("  {:<9} {mark}  {detail}", check.name),
+                None => println!("  {:<9} {mark}", check.name),
+            }
+        }
+    }
+    if report.healthy {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
 
-    if control::client_status(&sock_path).is_err() {
+fn run_logs(level: &str, json: bool, sock_path: &str) -> ExitCode {
+    if control::client_status(sock_path).is_err() {
         eprintln!("no tandem daemon running. Start one with `tandem up`.");
         return ExitCode::FAILURE;
     }
 
-    if let Err(e) = control::client_logs(&sock_path, level, json) {
+    if let Err(e) = control::client_logs(sock_path, level, json) {
         // Connection closed = server shut down, not an error
         let msg = format!("{e}");
         if msg.contains("broken pipe")
@@ -726,8 +2556,317 @@ fn load_user_settings_from_environment() -> Result<jj_lib::settings::UserSetting
         .map_err(|e| format!("cannot create settings: {e}"))
 }
 
-fn run_tandem_init(server_addr: &str, workspace_name: &str, workspace_path_str: &str) -> ExitCode {
+/// Check the server before `tandem init` touches the disk, so an unreachable
+/// or incompatible server leaves nothing behind.
+fn preflight_init_server(server_addr: &str) -> Result<rpc::RepoInfoResponse, String> {
+    let info = rpc::probe_repo_info(server_addr).map_err(|e| {
+        format!(
+            "error: cannot reach a tandem server at {server_addr}: {e:#}\n\
+             nothing was created; check the address and that the server is running"
+        )
+    })?;
+    let served_by = info.redirect.as_deref().unwrap_or(server_addr);
+    let problems = rpc::compatibility_problems(&info, rpc::WORKSPACE_CAPABILITIES);
+    if !problems.is_empty() {
+        let mut message = format!("error: the tandem server at {served_by} is not compatible:");
+        for problem in &problems {
+            message.push_str(&format!("\n  - {problem}"));
+        }
+        message.push_str("\nnothing was created");
+        return Err(message);
+    }
+    rpc::validate_repo_info(&info, rpc::WORKSPACE_CAPABILITIES)
+        .map_err(|e| format!("error: the tandem server at {served_by} is not compatible: {e:#}"))?;
+    Ok(info)
+}
+
+/// Check that `tandem init --from-workspace` names a workspace the server
+/// knows, other than the one being created.
+fn preflight_source_workspace(server_addr: &str, source: &str, new: &str) -> Result<(), String> {
+    if source == new {
+        return Err(format!(
+            "error: --from-workspace {source} names the workspace being created\n\
+             nothing was created"
+        ));
+    }
+    let workspaces = rpc::TandemClient::connect(server_addr)
+        .and_then(|client| client.get_heads_state())
+        .map_err(|e| {
+            format!("error: cannot read workspaces from {server_addr}: {e:#}\nnothing was created")
+        })?
+        .workspace_heads;
+    if !workspaces.contains_key(source) {
+        let known: Vec<&str> = workspaces.keys().map(String::as_str).collect();
+        return Err(format!(
+            "error: the server has no workspace named '{source}' (known: {})\n\
+             nothing was created",
+            known.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// The git URL `tandem init --colocate` fetches from: `--git-remote` if given,
+/// else whatever the server advertises.
+fn resolve_colocate_remote(
+    workspace_path: &Path,
+    explicit: Option<String>,
+    info: &rpc::RepoInfoResponse,
+    server_addr: &str,
+) -> Result<String, String> {
+    if workspace_path.join(".git").exists() {
+        return Err(format!(
+            "error: {} already has a .git; drop --colocate or pick an empty directory\n\
+             nothing was created",
+            workspace_path.display()
+        ));
+    }
+    explicit.or_else(|| info.git_remote.clone()).ok_or_else(|| {
+        format!(
+            "error: --colocate needs a git mirror, but the server at {server_addr} does not \
+             advertise one\n\
+             pass --git-remote <url>, or start the server with --git-remote <url>\n\
+             nothing was created"
+        )
+    })
+}
+
+/// Removes what a failed `tandem init` created: the workspace directory if
+/// init made it, otherwise just its `.jj` (and a `.git` made by
+/// `--colocate`). Disarmed once init succeeds.
+struct PartialInitGuard {
+    workspace: std::path::PathBuf,
+    created_dir: bool,
+    had_git: bool,
+    keep: bool,
+    armed: bool,
+}
+
+impl PartialInitGuard {
+    /// Take stock of `workspace` before init touches it. A `.jj` that is
+    /// already there is never ours to remove.
+    fn new(workspace: &Path, keep: bool) -> Self {
+        Self {
+            workspace: workspace.to_path_buf(),
+            created_dir: !workspace.exists(),
+            had_git: workspace.join(".git").exists(),
+            keep,
+            armed: !workspace.join(".jj").exists(),
+        }
+    }
+
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for PartialInitGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let targets = if self.created_dir {
+            vec![self.workspace.clone()]
+        } else if self.had_git {
+            vec![self.workspace.join(".jj")]
+        } else {
+            vec![self.workspace.join(".jj"), self.workspace.join(".git")]
+        };
+        for target in targets.iter().filter(|target| target.exists()) {
+            if self.keep {
+                eprintln!(
+                    "kept partially initialized workspace state at {} (--keep-partial)",
+                    target.display()
+                );
+                continue;
+            }
+            match std::fs::remove_dir_all(target) {
+                Ok(()) => eprintln!(
+                    "removed partially initialized workspace state at {}",
+                    target.display()
+                ),
+                Err(e) => eprintln!(
+                    "warning: could not remove partially initialized {}: {e}",
+                    target.display()
+                ),
+            }
+        }
+    }
+}
+
+/// `Workspace::init_with_factories` with jj's default index, submodule, and
+/// working-copy stores, except that a failure leaves `.jj` behind: jj-lib
+/// would remove it, defeating `--keep-partial`, and [`PartialInitGuard`]
+/// rolls back otherwise.
+fn init_workspace(
+    settings: &jj_lib::settings::UserSettings,
+    workspace_root: &Path,
+    backend_init: &jj_lib::repo::BackendInitializer,
+    signer: jj_lib::signing::Signer,
+    op_store_init: &jj_lib::repo::OpStoreInitializer,
+    op_heads_init: &jj_lib::repo::OpHeadsStoreInitializer,
+    workspace_name: jj_lib::ref_name::WorkspaceNameBuf,
+) -> Result<
+    (
+        jj_lib::workspace::Workspace,
+        std::sync::Arc<jj_lib::repo::ReadonlyRepo>,
+    ),
+    jj_lib::workspace::WorkspaceInitError,
+> {
+    use jj_lib::file_util::IoResultExt as _;
+    use jj_lib::repo::{Repo as _, RepoInitError};
+    use jj_lib::workspace::WorkspaceInitError;
+    use jj_lib::workspace_store::WorkspaceStore as _;
+
+    let jj_dir = workspace_root.join(".jj");
+    match std::fs::create_dir(&jj_dir) {
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(WorkspaceInitError::DestinationExists(jj_dir));
+        }
+        result => result.context(&jj_dir)?,
+    }
+    let repo_dir = jj_dir.join("repo");
+    std::fs::create_dir(&repo_dir).context(&repo_dir)?;
+    let repo = jj_lib::repo::ReadonlyRepo::init(
+        settings,
+        &repo_dir,
+        backend_init,
+        signer,
+        op_store_init,
+        op_heads_init,
+        jj_lib::repo::ReadonlyRepo::default_index_store_initializer(),
+        jj_lib::repo::ReadonlyRepo::default_submodule_store_initializer(),
+    )
+    .map_err(|err| match err {
+        RepoInitError::Backend(err) => WorkspaceInitError::Backend(err),
+        RepoInitError::OpHeadsStore(err) => WorkspaceInitError::OpHeadsStore(err),
+        RepoInitError::Path(err) => WorkspaceInitError::Path(err),
+    })?;
+    let workspace_store = jj_lib::workspace_store::SimpleWorkspaceStore::load(&repo_dir)?;
+
+    let working_copy_dir = jj_dir.join("working_copy");
+    std::fs::create_dir(&working_copy_dir).context(&working_copy_dir)?;
+    let mut tx = repo.start_transaction();
+    tx.repo_mut()
+        .check_out(workspace_name.clone(), &repo.store().root_commit())?;
+    let repo = tx.commit(format!("add workspace '{}'", workspace_name.as_symbol()))?;
+    let working_copy_factory = jj_lib::workspace::default_working_copy_factory();
+    let working_copy = working_copy_factory.init_working_copy(
+        repo.store().clone(),
+        workspace_root.to_path_buf(),
+        working_copy_dir.clone(),
+        repo.op_id().clone(),
+        workspace_name,
+        repo.settings(),
+    )?;
+    let type_path = working_copy_dir.join("type");
+    std::fs::write(&type_path, working_copy.name()).context(&type_path)?;
+
+    let workspace = jj_lib::workspace::Workspace::new(
+        workspace_root,
+        repo_dir,
+        working_copy,
+        repo.loader().clone(),
+    )?;
+    workspace_store.add(workspace.workspace_name(), workspace.workspace_root())?;
+    Ok((workspace, repo))
+}
+
+/// The optional parts of `tandem init`.
+struct InitOptions {
+    ephemeral_ttl_secs: Option<u64>,
+    keep_partial: bool,
+    /// `--colocate`, with the `--git-remote` override if one was given.
+    colocate: Option<Option<String>>,
+    sparse: Vec<String>,
+    read_mirror: Option<String>,
+    /// `--local`: serve the repo from inside the workspace instead.
+    local: bool,
+    /// `--from-workspace`: the workspace whose working-copy commit becomes
+    /// the new workspace's parent.
+    from_workspace: Option<String>,
+}
+
+/// Where a local-mode workspace keeps the repo its embedded server serves.
+const LOCAL_SERVER_REPO: &str = ".jj/repo/tandem-local";
+
+#[allow(clippy::type_complexity)]
+fn run_tandem_init(
+    server_addr: &str,
+    workspace_name: &str,
+    workspace_path_str: &str,
+    options: InitOptions,
+) -> ExitCode {
+    let InitOptions {
+        ephemeral_ttl_secs,
+        keep_partial,
+        colocate,
+        sparse,
+        read_mirror,
+        local,
+        from_workspace,
+    } = options;
+    let sparse_paths: Vec<String> = sparse
+        .iter()
+        .map(|path| settings::normalize_repo_path(path))
+        .collect();
+    let sparse_patterns = match sparse_paths
+        .iter()
+        .map(|path| parse_sparse_path(path))
+        .collect::<Result<Vec<_>, String>>()
+    {
+        Ok(patterns) => patterns,
+        Err(message) => {
+            eprintln!("error: {message}");
+            return ExitCode::FAILURE;
+        }
+    };
+    // A local-mode workspace brings its own server, which starts once jj has
+    // created `.jj/repo` to hold it.
+    let info = if local {
+        None
+    } else {
+        match preflight_init_server(server_addr) {
+            Ok(info) => Some(info),
+            Err(message) => {
+                eprintln!("{message}");
+                return ExitCode::FAILURE;
+            }
+        }
+    };
+    if let Some(source) = from_workspace.as_deref() {
+        if let Err(message) = preflight_source_workspace(server_addr, source, workspace_name) {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    }
+    if let (Some(mirror), Some(info)) = (read_mirror.as_deref(), info.as_ref()) {
+        if let Err(e) = read_mirror::probe(info, mirror) {
+            eprintln!(
+                "error: cannot use read mirror {mirror}: {e:#}\n\
+                 nothing was created"
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+
     let workspace_path = Path::new(workspace_path_str);
+    let snapshot_auto_track = info
+        .as_ref()
+        .and_then(|info| info.snapshot_auto_track.clone());
+    let colocate_remote = match (colocate, info.as_ref()) {
+        (Some(explicit), Some(info)) => {
+            match resolve_colocate_remote(workspace_path, explicit, info, server_addr) {
+                Ok(url) => Some(url),
+                Err(message) => {
+                    eprintln!("{message}");
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        _ => None,
+    };
+    let guard = PartialInitGuard::new(workspace_path, keep_partial);
 
     // Create workspace directory if needed
     if let Err(e) = std::fs::create_dir_all(workspace_path) {
@@ -743,6 +2882,14 @@ fn run_tandem_init(server_addr: &str, workspace_name: &str, workspace_path_str:
             return ExitCode::FAILURE;
         }
     };
+    let server_addr = if local {
+        format!(
+            "local://{}",
+            workspace_path.join(LOCAL_SERVER_REPO).display()
+        )
+    } else {
+        server_addr.to_string()
+    };
 
     // Use jj-lib's workspace init with our custom factories
     let settings = match load_user_settings_from_environment() {
@@ -752,6 +2899,23 @@ fn run_tandem_init(server_addr: &str, workspace_name: &str, workspace_path_str:
             return ExitCode::FAILURE;
         }
     };
+    // The backend created below does the initial checkout, so it has to
+    // know the prefetch paths and read mirror before they are saved to the
+    // repo config.
+    let mut init_config = Vec::new();
+    if !sparse_paths.is_empty() {
+        init_config.push(("tandem.prefetch-paths", prefetch_paths_value(&sparse_paths)));
+    }
+    if let Some(mirror) = read_mirror.as_deref() {
+        init_config.push(("tandem.read-mirror", toml_string(mirror)));
+    }
+    let settings = match with_repo_config(&settings, &init_config) {
+        Ok(s) => s,
+        Err(e) => {
+            eprintln!("error: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
 
     let signer = match jj_lib::signing::Signer::from_settings(&settings) {
         Ok(s) => s,
@@ -774,7 +2938,11 @@ fn run_tandem_init(server_addr: &str, workspace_name: &str, workspace_path_str:
     ) -> Result<
         Box<dyn jj_lib::backend::Backend>,
         jj_lib::backend::BackendInitError,
-    > = &|_settings, store_path| Ok(Box::new(backend::TandemBackend::init(store_path, &sa1)?));
+    > = &|settings, store_path| {
+        Ok(Box::new(backend::TandemBackend::init(
+            settings, store_path, &sa1,
+        )?))
+    };
 
     let op_store_init: &dyn Fn(
         &jj_lib::settings::UserSettings,
@@ -783,9 +2951,13 @@ fn run_tandem_init(server_addr: &str, workspace_name: &str, workspace_path_str:
     ) -> Result<
         Box<dyn jj_lib::op_store::OpStore>,
         jj_lib::backend::BackendInitError,
-    > = &|_settings, store_path, root_data| {
+    > = &|settings, store_path, root_data| {
         Ok(Box::new(op_store::TandemOpStore::init(
-            store_path, &sa2, root_data,
+            settings,
+            store_path,
+            &sa2,
+            root_data,
+            ephemeral_ttl_secs,
         )?))
     };
 
@@ -795,27 +2967,34 @@ fn run_tandem_init(server_addr: &str, workspace_name: &str, workspace_path_str:
     ) -> Result<
         Box<dyn jj_lib::op_heads_store::OpHeadsStore>,
         jj_lib::backend::BackendInitError,
-    > = &|_settings, store_path| {
+    > = &|settings, store_path| {
         Ok(Box::new(op_heads_store::TandemOpHeadsStore::init(
-            store_path, &sa3, &wn1,
+            settings, store_path, &sa3, &wn1,
         )?))
     };
 
-    match jj_lib::workspace::Workspace::init_with_factories(
+    match init_workspace(
         &settings,
         &workspace_path,
         backend_init,
         signer,
         op_store_init,
         op_heads_init,
-        jj_lib::repo::ReadonlyRepo::default_index_store_initializer(),
-        jj_lib::repo::ReadonlyRepo::default_submodule_store_initializer(),
-        &*jj_lib::workspace::default_working_copy_factory(),
         jj_lib::ref_name::WorkspaceNameBuf::from(workspace_name.to_string()),
     ) {
         Ok((mut workspace, repo)) => {
             use jj_lib::repo::Repo as _;
 
+            // Loading at head indexes every visible commit; fetch them in
+            // bulk first rather than one read per commit.
+            if let Some(backend) = repo.store().backend_impl::<backend::TandemBackend>() {
+                match backend.prefetch_commit_graph() {
+                    Ok(0) => {}
+                    Ok(count) => eprintln!("Fetched {count} commits for the index"),
+                    Err(e) => eprintln!("warning: cannot prefetch commits: {e:#}"),
+                }
+            }
+
             let head_repo = match repo.loader().load_at_head() {
                 Ok(repo) => repo,
                 Err(e) => {
@@ -824,7 +3003,28 @@ fn run_tandem_init(server_addr: &str, workspace_name: &str, workspace_path_str:
                 }
             };
 
-            let source_parent_commits = if let Some(source_wc_commit_id) = head_repo
+            // By default the new working copy sits next to the default
+            // workspace's (`jj workspace add` style); `--from-workspace`
+            // starts on top of that workspace's working-copy commit instead.
+            let source_parent_commits = if let Some(source) = from_workspace.as_deref() {
+                let source_name = jj_lib::ref_name::WorkspaceNameBuf::from(source.to_string());
+                let Some(source_wc_commit_id) = head_repo.view().get_wc_commit_id(&source_name)
+                else {
+                    eprintln!(
+                        "error: workspace init failed: workspace '{source}' has no working-copy commit"
+                    );
+                    return ExitCode::FAILURE;
+                };
+                match head_repo.store().get_commit(source_wc_commit_id) {
+                    Ok(commit) => vec![commit],
+                    Err(e) => {
+                        eprintln!(
+                            "error: workspace init failed: cannot load workspace '{source}' commit: {e}"
+                        );
+                        return ExitCode::FAILURE;
+                    }
+                }
+            } else if let Some(source_wc_commit_id) = head_repo
                 .view()
                 .get_wc_commit_id(jj_lib::ref_name::WorkspaceName::DEFAULT)
             {
@@ -920,70 +3120,398 @@ fn run_tandem_init(server_addr: &str, workspace_name: &str, workspace_path_str:
                 }
             };
 
+            if !sparse_patterns.is_empty() {
+                if let Err(e) =
+                    set_initial_sparse_patterns(&mut workspace, repo.op_id(), sparse_patterns)
+                {
+                    eprintln!("error: workspace init failed: {e:#}");
+                    return ExitCode::FAILURE;
+                }
+            }
+
             if let Err(e) = workspace.check_out(updated_repo.op_id().clone(), None, &new_wc_commit)
             {
                 eprintln!("error: workspace init failed: cannot update working copy checkout: {e}");
                 return ExitCode::FAILURE;
             }
 
-            eprintln!(
-                "Initialized tandem workspace '{}' at {} (server: {})",
-                workspace_name,
-                workspace_path.display(),
-                server_addr
-            );
+            // Before anything else runs jj here, so no snapshot predates it.
+            if let Some(fileset) = snapshot_auto_track.as_deref() {
+                if let Err(e) = save_repo_config(&workspace_path, "snapshot.auto-track", fileset) {
+                    eprintln!("warning: {e:#}; this workspace snapshots every new file");
+                }
+                eprintln!("Snapshot policy from the server: auto-track {fileset}");
+            }
+
+            if let Some(url) = colocate_remote.as_deref() {
+                use jj_lib::object_id::ObjectId as _;
+
+                let root_id = head_repo.store().root_commit_id().clone();
+                let head = source_parent_commits
+                    .first()
+                    .map(|commit| commit.id())
+                    .filter(|id| **id != root_id)
+                    .map(|id| id.hex());
+                match colocate::materialize(&workspace_path, url, head.as_deref()) {
+                    Ok(None) => {}
+                    Ok(Some(warning)) => eprintln!("warning: {warning}"),
+                    Err(e) => {
+                        eprintln!("error: workspace init failed: cannot colocate git: {e:#}");
+                        return ExitCode::FAILURE;
+                    }
+                }
+            }
+
+            if !sparse_paths.is_empty() {
+                let value = prefetch_paths_value(&sparse_paths);
+                if let Err(e) = save_repo_config(&workspace_path, "tandem.prefetch-paths", &value) {
+                    eprintln!("warning: {e:#}; prefetching stays off in this workspace");
+                }
+                eprintln!("Sparse checkout of: {}", sparse_paths.join(", "));
+            }
+            if let Some(mirror) = read_mirror.as_deref() {
+                if let Err(e) = save_repo_config(&workspace_path, "tandem.read-mirror", mirror) {
+                    eprintln!("warning: {e:#}; reads go to {server_addr} in this workspace");
+                }
+                eprintln!("Reading objects from mirror {mirror}");
+            }
+
+            guard.disarm();
+            if local {
+                eprintln!(
+                    "Initialized local tandem workspace '{}' at {} (no server; repo in {})",
+                    workspace_name,
+                    workspace_path.display(),
+                    LOCAL_SERVER_REPO
+                );
+            } else {
+                eprintln!(
+                    "Initialized tandem workspace '{}' at {} (server: {})",
+                    workspace_name,
+                    workspace_path.display(),
+                    server_addr
+                );
+            }
+            if let Some(source) = from_workspace.as_deref() {
+                eprintln!(
+                    "Working copy starts on top of workspace '{source}' ({})",
+                    new_wc_commit.parent_ids()[0]
+                );
+            }
             ExitCode::SUCCESS
         }
         Err(e) => {
-            eprintln!("error: workspace init failed: {e}");
+            // The server's reason (e.g. a read-only replica refusing the
+            // write) is in the source chain, not in jj-lib's message.
+            eprintln!("error: workspace init failed: {:#}", anyhow::Error::new(e));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// ─── Sparse workspaces ────────────────────────────────────────────────────────
+
+/// A `--sparse` path as a jj sparse pattern (a repo path prefix).
+fn parse_sparse_path(path: &str) -> Result<jj_lib::repo_path::RepoPathBuf, String> {
+    if path
+        .split('/')
+        .any(|component| matches!(component, "." | ".."))
+        || path.contains("//")
+    {
+        return Err(format!("--sparse {path:?} is not a plain repo path"));
+    }
+    jj_lib::repo_path::RepoPathBuf::from_internal_string(path)
+        .map_err(|e| format!("--sparse {path:?}: {e}"))
+}
+
+/// `tandem.prefetch-paths` as a TOML array; JSON string arrays are valid TOML.
+fn prefetch_paths_value(paths: &[String]) -> String {
+    serde_json::to_string(paths).expect("string list serializes")
+}
+
+/// A TOML string literal; JSON strings are valid TOML.
+fn toml_string(value: &str) -> String {
+    serde_json::to_string(value).expect("string serializes")
+}
+
+/// `settings` with each `(key, TOML value)` in `entries` layered on as repo
+/// config.
+fn with_repo_config(
+    settings: &jj_lib::settings::UserSettings,
+    entries: &[(&str, String)],
+) -> Result<jj_lib::settings::UserSettings, String> {
+    if entries.is_empty() {
+        return Ok(settings.clone());
+    }
+    let mut config = settings.config().clone();
+    let text: String = entries
+        .iter()
+        .map(|(key, value)| format!("{key} = {value}\n"))
+        .collect();
+    let layer = jj_lib::config::ConfigLayer::parse(jj_lib::config::ConfigSource::Repo, &text)
+        .map_err(|e| format!("cannot set repo config: {e}"))?;
+    config.add_layer(layer);
+    jj_lib::settings::UserSettings::from_config(config)
+        .map_err(|e| format!("cannot create settings: {e}"))
+}
+
+/// Narrow the fresh working copy to `patterns` before anything is checked
+/// out, so files outside them are never fetched or written.
+fn set_initial_sparse_patterns(
+    workspace: &mut jj_lib::workspace::Workspace,
+    op_id: &jj_lib::op_store::OperationId,
+    patterns: Vec<jj_lib::repo_path::RepoPathBuf>,
+) -> anyhow::Result<()> {
+    use anyhow::Context as _;
+
+    let mut locked_ws = workspace
+        .start_working_copy_mutation()
+        .context("cannot lock working copy")?;
+    pollster::block_on(locked_ws.locked_wc().set_sparse_patterns(patterns))
+        .context("cannot set sparse patterns")?;
+    locked_ws
+        .finish(op_id.clone())
+        .context("cannot save working copy state")?;
+    Ok(())
+}
+
+/// Record `key = value` in the workspace's repo config, where later
+/// commands (and `jj config edit --repo`) find it.
+fn save_repo_config(workspace_path: &Path, key: &str, value: &str) -> anyhow::Result<()> {
+    use anyhow::Context as _;
+
+    let exe = std::env::current_exe().context("cannot determine executable path")?;
+    let status = std::process::Command::new(exe)
+        .args(["config", "set", "--repo", key, value])
+        .current_dir(workspace_path)
+        .status()
+        .context("cannot run jj config set")?;
+    if !status.success() {
+        anyhow::bail!("jj config set --repo {key} exited with {status}");
+    }
+    Ok(())
+}
+
+// ─── Ephemeral workspaces ─────────────────────────────────────────────────────
+
+const DEFAULT_EPHEMERAL_TTL_SECS: u64 = 60 * 60;
+
+fn parse_duration_arg(raw: &str) -> Result<u64, String> {
+    ephemeral::parse_ttl(raw).map_err(|err| err.to_string())
+}
+
+/// A duration, or `0` to turn the RPC watchdog off.
+fn parse_rpc_timeout_arg(raw: &str) -> Result<u64, String> {
+    match raw.trim() {
+        "0" => Ok(0),
+        raw => parse_duration_arg(raw),
+    }
+}
+
+fn parse_op_retention_arg(raw: &str) -> Result<op_retention::OpRetention, String> {
+    op_retention::OpRetention::parse(raw).map_err(|err| err.to_string())
+}
+
+fn parse_integrity_scan_arg(raw: &str) -> Result<integrity::IntegrityScan, String> {
+    integrity::IntegrityScan::parse(raw).map_err(|err| err.to_string())
+}
+
+/// `jj workspace forget` for the current workspace, tagged so the server
+/// drops the workspace's entry as part of the same head update.
+fn run_workspace_release() -> ExitCode {
+    let exe = match std::env::current_exe() {
+        Ok(e) => e,
+        Err(e) => {
+            eprintln!("error: cannot determine executable path: {e}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut tags = ops::tags_from_env();
+    tags.insert(ephemeral::RELEASE_TAG.to_string(), "1".to_string());
+
+    let mut cmd = std::process::Command::new(exe);
+    cmd.args(["workspace", "forget"])
+        .env(ops::OP_TAGS_ENV, ops::format_tags(&tags));
+    match cmd.status() {
+        Ok(status) if status.success() => ExitCode::SUCCESS,
+        Ok(_) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("error: failed to run jj workspace forget: {e}");
             ExitCode::FAILURE
         }
     }
 }
 
+fn run_op_integrate_all(dry_run: bool) -> ExitCode {
+    let settings = match load_user_settings_from_environment() {
+        Ok(settings) => settings,
+        Err(err) => {
+            eprintln!("error: {err}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if let Err(err) = integrate::run_integrate_all(&settings, dry_run) {
+        eprintln!("error: {err:#}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run_journal_show(last: usize, json: bool) -> ExitCode {
+    let repo_dir = match std::env::current_dir()
+        .map_err(anyhow::Error::from)
+        .and_then(|dir| integrate::find_workspace_root(&dir))
+        .and_then(|root| integrate::repo_dir(&root))
+    {
+        Ok(repo_dir) => repo_dir,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+    let entries = match journal::read_last(&repo_dir, last) {
+        Ok(entries) => entries,
+        Err(err) => {
+            eprintln!("error: {err:#}");
+            return ExitCode::FAILURE;
+        }
+    };
+    if json {
+        for entry in &entries {
+            println!("{}", serde_json::to_string(entry).unwrap());
+        }
+        return ExitCode::SUCCESS;
+    }
+    if entries.is_empty() {
+        println!(
+            "No journal entries in {}.",
+            repo_dir.join("tandem").join(journal::FILE_NAME).display()
+        );
+        println!("Turn the journal on with: jj config set --repo tandem.journal true");
+        return ExitCode::SUCCESS;
+    }
+    for (i, entry) in entries.iter().enumerate() {
+        if i > 0 {
+            println!();
+        }
+        println!(
+            "{}  {}",
+            maintenance::format_utc(entry.started_at_millis / 1000),
+            entry.command.join(" ")
+        );
+        println!(
+            "  Exit:     {} after {}ms (pid {}, server {})",
+            entry.exit_code, entry.duration_ms, entry.pid, entry.server
+        );
+        let calls = entry.rpcs.len() as u64 + entry.rpcs_dropped;
+        if calls > 0 {
+            println!("  RPCs:     {calls} ({})", entry.describe_rpcs());
+        }
+        if !entry.versions_seen.is_empty() {
+            let versions: Vec<String> = entry.versions_seen.iter().map(u64::to_string).collect();
+            println!("  Versions: {}", versions.join(", "));
+        }
+        if entry.cas_retries > 0 {
+            println!("  CAS retries: {}", entry.cas_retries);
+        }
+        for call in entry.failed_rpcs() {
+            println!(
+                "  Failed:   {} at {}ms: {}",
+                call.method,
+                call.at_ms,
+                call.error.as_deref().unwrap_or_default()
+            );
+        }
+    }
+    ExitCode::SUCCESS
+}
+
 // ─── jj CLI mode ──────────────────────────────────────────────────────────────
 
+/// Flags tandem adds to every jj command.
+#[derive(clap::Args, Clone, Debug)]
+struct TandemGlobalArgs {
+    /// Let TANDEM_SERVER point this workspace at a server that cannot be
+    /// shown to host the same repo (for intentional migrations)
+    #[arg(long, global = true)]
+    allow_server_override: bool,
+    /// Do not contact the tandem server: read what this workspace has
+    /// already cached, and refuse writes
+    #[arg(long, global = true)]
+    tandem_offline: bool,
+    /// Sample where this command spends its time (RPC waits, proto decoding,
+    /// jj-lib) and write folded stacks for a flamegraph to FILE
+    #[arg(long, global = true, value_name = "FILE")]
+    tandem_profile: Option<std::path::PathBuf>,
+}
+
 fn run_jj() -> ExitCode {
     use jj_cli::cli_util::CliRunner;
 
-    CliRunner::init()
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = time_travel::run_pinned_if_requested(&args) {
+        return code;
+    }
+    let args_for_profile = args.clone();
+
+    let code = CliRunner::init()
         .version(env!("CARGO_PKG_VERSION"))
+        .add_global_args(|_ui, args: TandemGlobalArgs| {
+            if args.allow_server_override {
+                server_address::allow_override();
+            }
+            if args.tandem_offline {
+                offline::force();
+            }
+            if let Some(path) = &args.tandem_profile {
+                profile::start(path, &profile_root(&args_for_profile));
+            }
+            Ok(())
+        })
         .add_store_factories(tandem_factories())
-        .run()
-        .into()
+        .add_subcommand(handoff::run)
+        .add_subcommand(change_lock::run)
+        .add_subcommand(merge_preview::run)
+        .add_subcommand(blame::run)
+        .add_dispatch_hook(change_lock::check_locks)
+        .add_dispatch_hook(stale_working_copy::catch_up)
+        .add_dispatch_hook(path_overlaps::warn_after_change)
+        .add_dispatch_hook(offline_status::report)
+        .add_commit_template_extension(Box::new(attribution::WorkspaceAttribution))
+        .add_revset_function_extension("tandem_workspace", revsets::tandem_workspace)
+        .add_revset_function_extension("tandem_integration", revsets::tandem_integration)
+        .run();
+    journal::finish(code);
+    match profile::finish() {
+        Some(Ok((path, micros))) => eprintln!(
+            "tandem: profile written to {} ({:.2}s sampled)",
+            path.display(),
+            micros as f64 / 1_000_000.0
+        ),
+        Some(Err(err)) => eprintln!("tandem: could not write the profile: {err:#}"),
+        None => {}
+    }
+    code.into()
+}
+
+/// The command line without `--tandem-profile`, as the profile's root frame.
+fn profile_root(args: &[String]) -> String {
+    let mut root = vec!["tandem"];
+    let mut rest = args.iter().skip(1);
+    while let Some(arg) = rest.next() {
+        if arg == "--tandem-profile" {
+            rest.next();
+        } else if !arg.starts_with("--tandem-profile=") {
+            root.push(arg);
+        }
+    }
+    root.join(" ")
 }
 
 /// Register tandem backend/opstore/opheadsstore factories so that jj
 /// can load repos with store/type = "tandem".
 fn tandem_factories() -> jj_lib::repo::StoreFactories {
     let mut factories = jj_lib::repo::StoreFactories::empty();
-
-    factories.add_backend(
-        "tandem",
-        Box::new(|settings, store_path| {
-            Ok(Box::new(backend::TandemBackend::load(
-                settings, store_path,
-            )?))
-        }),
-    );
-
-    factories.add_op_store(
-        "tandem_op_store",
-        Box::new(|settings, store_path, root_data| {
-            Ok(Box::new(op_store::TandemOpStore::load(
-                settings, store_path, root_data,
-            )?))
-        }),
-    );
-
-    factories.add_op_heads_store(
-        "tandem_op_heads_store",
-        Box::new(|settings, store_path| {
-            Ok(Box::new(op_heads_store::TandemOpHeadsStore::load(
-                settings, store_path,
-            )?))
-        }),
-    );
-
+    tandem_jj_store::register(&mut factories);
     factories
 }
@@ -0,0 +1,169 @@
+//! `tandem preview-merge <a> [<b>]` — would these two revisions merge
+//! cleanly?
+//!
+//! `previewMerge` merges the two commits' trees on the server, the way
+//! `jj new a b` would (against their common ancestors), and reports the
+//! paths left conflicted. Nothing is written, and the client downloads no
+//! trees: two agents' working copies can be checked against each other
+//! before either of them lands anything. The warning `commit` prints about
+//! overlapping paths uses it too (see `path_overlaps.rs`).
+//!
+//! Conflicts either side already had show up in the preview as well, since
+//! they are still conflicts after the merge.
+
+use std::io::Write as _;
+
+use anyhow::{anyhow, Result};
+use jj_cli::cli_util::{CommandHelper, RevisionArg};
+use jj_cli::command_error::{user_error, CommandError};
+use jj_cli::ui::Ui;
+use jj_lib::backend::CommitId;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::{Repo as _, RepoLoader};
+use jj_lib::rewrite::merge_commit_trees;
+
+use crate::backend::TandemBackend;
+use crate::rpc::{MergePreview, RepoCapability};
+
+/// Conflicted paths per preview when the client does not pick a limit.
+pub const DEFAULT_PATH_LIMIT: usize = 100;
+/// Upper bound on conflicted paths per preview, whatever the client asks for.
+pub const MAX_PATH_LIMIT: usize = 1000;
+
+/// The path limit for a request's `pathLimit` (0 for the default).
+pub fn path_limit(requested: u32) -> usize {
+    match requested {
+        0 => DEFAULT_PATH_LIMIT,
+        n => (n as usize).min(MAX_PATH_LIMIT),
+    }
+}
+
+// ─── Server side ──────────────────────────────────────────────────────────────
+
+/// Merge the trees of `commit_a` and `commit_b` (raw commit ids) as seen
+/// from the head operation, listing at most `path_limit` conflicted paths.
+pub fn preview_merge(
+    repo_loader: &RepoLoader,
+    commit_a: &[u8],
+    commit_b: &[u8],
+    path_limit: usize,
+) -> Result<MergePreview> {
+    let repo = repo_loader
+        .load_at_head()
+        .map_err(|e| anyhow!("load repo at head: {e}"))?;
+    let load = |bytes: &[u8]| {
+        let id = CommitId::new(bytes.to_vec());
+        repo.store()
+            .get_commit(&id)
+            .map_err(|e| anyhow!("load commit {}: {e}", id.hex()))
+    };
+    let commits = [load(commit_a)?, load(commit_b)?];
+    let tree = pollster::block_on(merge_commit_trees(repo.as_ref(), &commits))
+        .map_err(|e| anyhow!("merge trees: {e}"))?;
+
+    let mut preview = MergePreview {
+        clean: !tree.has_conflict(),
+        ..MergePreview::default()
+    };
+    for (path, _) in tree.conflicts() {
+        if preview.conflicted_paths.len() == path_limit {
+            preview.paths_truncated = true;
+            break;
+        }
+        preview
+            .conflicted_paths
+            .push(path.as_internal_file_string().to_string());
+    }
+    Ok(preview)
+}
+
+// ─── tandem preview-merge ─────────────────────────────────────────────────────
+
+#[derive(clap::Subcommand, Clone, Debug)]
+pub enum PreviewMergeCommand {
+    /// Show whether two revisions would merge cleanly, without merging them
+    PreviewMerge(PreviewMergeArgs),
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct PreviewMergeArgs {
+    /// One side of the merge
+    #[arg(value_name = "REVSET")]
+    revision_a: RevisionArg,
+    /// The other side of the merge
+    #[arg(value_name = "REVSET", default_value = "@")]
+    revision_b: RevisionArg,
+    /// List at most this many conflicted paths (default 100, at most 1000)
+    #[arg(long)]
+    limit: Option<u32>,
+}
+
+pub fn run(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    args: PreviewMergeCommand,
+) -> Result<(), CommandError> {
+    let PreviewMergeCommand::PreviewMerge(args) = args;
+    let workspace_command = command.workspace_helper(ui)?;
+    let Some(backend) = workspace_command
+        .repo()
+        .store()
+        .backend_impl::<TandemBackend>()
+    else {
+        return Err(user_error(
+            "this workspace is not backed by a tandem server",
+        ));
+    };
+    let client = backend.client();
+    if !client.supports_capability(RepoCapability::MergePreview) {
+        return Err(user_error("the server does not support merge previews"));
+    }
+    let commit_a = workspace_command.resolve_single_rev(ui, &args.revision_a)?;
+    let commit_b = workspace_command.resolve_single_rev(ui, &args.revision_b)?;
+    let preview = client
+        .preview_merge(
+            commit_a.id().as_bytes(),
+            commit_b.id().as_bytes(),
+            args.limit.unwrap_or(0),
+        )
+        .map_err(user_error)?;
+
+    writeln!(
+        ui.status(),
+        "Merging {} with {}",
+        workspace_command.format_commit_summary(&commit_a),
+        workspace_command.format_commit_summary(&commit_b)
+    )?;
+    let mut out = ui.stdout();
+    if preview.clean {
+        writeln!(out, "The merge is clean")?;
+        return Ok(());
+    }
+    writeln!(
+        out,
+        "The merge would conflict in {}{} {}:",
+        preview.conflicted_paths.len(),
+        if preview.paths_truncated { "+" } else { "" },
+        if preview.conflicted_paths.len() == 1 && !preview.paths_truncated {
+            "path"
+        } else {
+            "paths"
+        }
+    )?;
+    for path in &preview.conflicted_paths {
+        writeln!(out, "  {path}")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_limit_defaults_and_caps() {
+        assert_eq!(path_limit(0), DEFAULT_PATH_LIMIT);
+        assert_eq!(path_limit(7), 7);
+        assert_eq!(path_limit(u32::MAX), MAX_PATH_LIMIT);
+    }
+}
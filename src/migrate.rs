@@ -0,0 +1,320 @@
+//! `tandem migrate` — move a repo to another server.
+//!
+//! The copy reuses the read-replica sync: every operation, view, and object
+//! reachable from the source's heads is written to the destination, parents
+//! first, and the destination then imports the source's op heads and
+//! workspace attributions. Finally the source is told to redirect: from then
+//! on its `getRepoInfo` names the destination, clients follow it and rewrite
+//! their `server_address`, and the source refuses writes.
+//!
+//! The source's heads are held (`beginSnapshot`) during the copy so no update
+//! slips in between the copy and the redirect. The redirect is also a
+//! compare-and-swap on the copied version; servers without snapshot holds
+//! are re-synced until it sticks.
+//!
+//! `tandem publish` makes the same copy out of a local-mode workspace
+//! (`tandem init --local`), whose repo has no server to redirect from.
+//! Instead, once the destination's heads are read back and match the local
+//! repo's, the workspace's stores are rewritten to point at the destination.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::replica::{self, ObjectKind, ReplicaSink};
+use crate::rpc::{self, HeadsState, RepoCapability, TandemClient};
+
+const MAX_SYNC_ATTEMPTS: usize = 5;
+/// Store directories under `.jj/repo` that record their server's address.
+const STORE_DIRS: &[&str] = &["store", "op_store", "op_heads"];
+const ADDRESS_FILE: &str = "server_address";
+
+/// The destination server, written to over RPC.
+struct Destination {
+    client: Arc<TandemClient>,
+}
+
+impl ReplicaSink for Destination {
+    fn has_operation(&self, id: &[u8]) -> bool {
+        self.client.get_operation(id).is_ok()
+    }
+
+    fn has_view(&self, id: &[u8]) -> bool {
+        self.client.get_view(id).is_ok()
+    }
+
+    fn has_object(&self, kind: ObjectKind, id: &[u8]) -> bool {
+        self.client.get_object(kind.code(), id).is_ok()
+    }
+
+    fn put_operation(&self, data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+        self.client.put_operation(data, tags)
+    }
+
+    fn put_view(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.client.put_view(data)
+    }
+
+    fn put_object(&self, kind: ObjectKind, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(self.client.put_object(kind.code(), data)?.0)
+    }
+
+    fn adopt_heads(&self, primary: &HeadsState) -> Result<()> {
+        self.client.import_heads(primary).map(|_| ())
+    }
+
+    fn heads_held(&self) -> bool {
+        false
+    }
+}
+
+// ─── Public entry point ───────────────────────────────────────────────────────
+
+pub fn run_migrate(from: &str, to: &str) -> Result<()> {
+    if from == to {
+        bail!("--from and --to are the same server");
+    }
+
+    let source = TandemClient::connect_with_requirements(from, &[RepoCapability::Migration])
+        .with_context(|| format!("cannot migrate from {from}"))?;
+    if source.server_addr() != from {
+        bail!(
+            "{from} already redirects to {}; nothing to migrate",
+            source.server_addr()
+        );
+    }
+    let destination = TandemClient::connect_with_requirements(to, &[RepoCapability::Migration])
+        .with_context(|| format!("cannot migrate to {to}"))?;
+    if destination.server_addr() != to {
+        bail!("{to} redirects to {}", destination.server_addr());
+    }
+
+    let hold = if source.supports_capability(RepoCapability::ConsistentSnapshot) {
+        let hold = source.begin_snapshot(0)?;
+        eprintln!("holding {from} at version {}", hold.version);
+        Some(hold)
+    } else {
+        eprintln!("warning: {from} cannot hold its heads; writes during the copy force a re-sync");
+        None
+    };
+
+    let result = copy_and_redirect(&source, from, destination, to);
+    if let Some(hold) = hold {
+        let _ = source.end_snapshot(hold.snapshot_id);
+    }
+    let state = result?;
+
+    eprintln!(
+        "migrated {from} -> {to} at version {} ({} workspaces); \
+         clients switch on their next command",
+        state.version,
+        state.workspace_heads.len()
+    );
+    Ok(())
+}
+
+fn copy_and_redirect(
+    source: &TandemClient,
+    from: &str,
+    destination: Arc<TandemClient>,
+    to: &str,
+) -> Result<HeadsState> {
+    let sink = Destination {
+        client: destination,
+    };
+    let copier = Copier::new()?;
+    for attempt in 1..=MAX_SYNC_ATTEMPTS {
+        let state = copier.sync(from, &sink)?;
+        let (redirected, current) = source.set_redirect(to, state.version)?;
+        if redirected {
+            return Ok(state);
+        }
+        eprintln!(
+            "{from} moved from version {} to {current} during the copy; re-syncing \
+             (attempt {attempt}/{MAX_SYNC_ATTEMPTS})",
+            state.version
+        );
+    }
+    bail!("{from} kept changing during the copy; stop writers or retry later")
+}
+
+/// Runs replica syncs, which need a local task set for the RPC client.
+struct Copier {
+    rt: tokio::runtime::Runtime,
+    local: tokio::task::LocalSet,
+}
+
+impl Copier {
+    fn new() -> Result<Self> {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .context("build tokio runtime")?;
+        Ok(Self {
+            rt,
+            local: tokio::task::LocalSet::new(),
+        })
+    }
+
+    /// Copy everything reachable from `from`'s heads into `sink`.
+    fn sync(&self, from: &str, sink: &Destination) -> Result<HeadsState> {
+        self.local.block_on(&self.rt, async {
+            let (client, info) = rpc::connect_store_client(from, &[]).await?;
+            replica::sync_from_primary(&client, sink, &info.root_operation_id).await
+        })
+    }
+}
+
+// ─── Publishing a local-mode repo ─────────────────────────────────────────────
+
+pub fn run_publish(to: &str) -> Result<()> {
+    let cwd = std::env::current_dir().context("cannot read current directory")?;
+    let workspace_root = cwd
+        .ancestors()
+        .find(|dir| dir.join(".jj").is_dir())
+        .ok_or_else(|| anyhow!("there is no jj repo in \"{}\"", cwd.display()))?;
+    let repo_dir = repo_dir(workspace_root)?;
+    let address_path = repo_dir.join("store").join(ADDRESS_FILE);
+    let from = std::fs::read_to_string(&address_path)
+        .with_context(|| format!("read {}", address_path.display()))?
+        .trim()
+        .to_string();
+    if !from.starts_with("local://") {
+        bail!(
+            "this workspace already uses the server at {from}; \
+             use `tandem migrate` to move it to another server"
+        );
+    }
+    if from == to {
+        bail!("--server is this workspace's own local repo");
+    }
+
+    let source = TandemClient::connect(&from)
+        .with_context(|| format!("cannot open the local repo at {from}"))?;
+    let destination = TandemClient::connect_with_requirements(to, &[RepoCapability::Migration])
+        .with_context(|| format!("cannot publish to {to}"))?;
+    if destination.server_addr() != to {
+        bail!("{to} redirects to {}", destination.server_addr());
+    }
+
+    let sink = Destination {
+        client: destination.clone(),
+    };
+    let copier = Copier::new()?;
+    let mut published = None;
+    for attempt in 1..=MAX_SYNC_ATTEMPTS {
+        let state = copier.sync(&from, &sink)?;
+        // Another command in a workspace of this repo may have written
+        // meanwhile; only switch over once the copy is current.
+        let current = source.get_heads_state()?;
+        if current.version == state.version {
+            published = Some(state);
+            break;
+        }
+        eprintln!(
+            "the local repo moved from version {} to {} during the copy; re-syncing \
+             (attempt {attempt}/{MAX_SYNC_ATTEMPTS})",
+            state.version, current.version
+        );
+    }
+    let Some(state) = published else {
+        bail!("the local repo kept changing during the copy; finish running commands and retry");
+    };
+    verify_heads(&state, &destination.get_heads_state()?)
+        .with_context(|| format!("{to} does not hold the published heads; workspace unchanged"))?;
+
+    for dir in STORE_DIRS {
+        let path = repo_dir.join(dir).join(ADDRESS_FILE);
+        if path.exists() {
+            std::fs::write(&path, to)
+                .with_context(|| format!("record new server address in {}", path.display()))?;
+        }
+    }
+
+    eprintln!(
+        "published the local repo to {to} at version {} ({} workspaces); \
+         this workspace now uses {to}",
+        state.version,
+        state.workspace_heads.len()
+    );
+    eprintln!(
+        "the local copy in {} is no longer used and can be deleted",
+        from.trim_start_matches("local://")
+    );
+    Ok(())
+}
+
+/// The heads and workspace attributions `published` copied must be exactly
+/// what the destination reports.
+fn verify_heads(published: &HeadsState, destination: &HeadsState) -> Result<()> {
+    let expected: BTreeSet<&Vec<u8>> = published.heads.iter().collect();
+    let actual: BTreeSet<&Vec<u8>> = destination.heads.iter().collect();
+    if expected != actual {
+        bail!(
+            "op heads differ: published {}, found {}",
+            expected.len(),
+            actual.len()
+        );
+    }
+    if published.workspace_heads != destination.workspace_heads {
+        let differing: Vec<&str> = published
+            .workspace_heads
+            .keys()
+            .chain(destination.workspace_heads.keys())
+            .filter(|name| {
+                published.workspace_heads.get(*name) != destination.workspace_heads.get(*name)
+            })
+            .map(String::as_str)
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        bail!("workspace heads differ for {}", differing.join(", "));
+    }
+    Ok(())
+}
+
+/// `.jj/repo` is the repo itself, or a file pointing at it in secondary
+/// workspaces.
+fn repo_dir(workspace_root: &Path) -> Result<PathBuf> {
+    let jj_dir = workspace_root.join(".jj");
+    let repo = jj_dir.join("repo");
+    if repo.is_file() {
+        let target = std::fs::read_to_string(&repo)
+            .with_context(|| format!("reading {}", repo.display()))?;
+        return Ok(jj_dir.join(target.trim()));
+    }
+    Ok(repo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(heads: &[u8], workspaces: &[(&str, u8)]) -> HeadsState {
+        HeadsState {
+            heads: heads.iter().map(|id| vec![*id]).collect(),
+            version: 1,
+            workspace_heads: workspaces
+                .iter()
+                .map(|(name, id)| (name.to_string(), vec![*id]))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn verify_heads_ignores_order_and_version() {
+        let published = state(&[1, 2], &[("default", 7)]);
+        let mut destination = state(&[2, 1], &[("default", 7)]);
+        destination.version = 4;
+        assert!(verify_heads(&published, &destination).is_ok());
+
+        let missing_head = state(&[1], &[("default", 7)]);
+        assert!(verify_heads(&published, &missing_head).is_err());
+
+        let moved = state(&[1, 2], &[("default", 8), ("other", 9)]);
+        let err = verify_heads(&published, &moved).unwrap_err();
+        assert_eq!(err.to_string(), "workspace heads differ for default, other");
+    }
+}
@@ -0,0 +1,627 @@
+//! Server notifications — ops events delivered to Slack, webhooks, or a
+//! command (`tandem serve --notifications <file>`).
+//!
+//! The file lists sinks, each with the events it wants (all of them when
+//! `events` is left out):
+//!
+//! ```json
+//! {"sinks": [
+//!   {"type": "slack", "url": "https://hooks.slack.com/services/...",
+//!    "events": ["integrationFailed", "gcFailed"]},
+//!   {"type": "webhook", "url": "http://dashboard.internal/tandem"},
+//!   {"type": "command", "command": ["/usr/local/bin/page-oncall", "vcs"],
+//!    "events": ["integrationFailed"]}
+//! ]}
+//! ```
+//!
+//! Events are `headsUpdated` (every heads version, with the operation's tags
+//! and a summary of what it did), `integrationFailed` (the integration
+//! worker hit a new error), `gc` (an `--op-retention` pass pruned operations
+//! and gave the kept ones, head included, new ids), `gcFailed`, and
+//! `casStorm` (lost head-update races crossed `--cas-storm-threshold`). A
+//! webhook gets the event as a JSON POST and a command gets it on stdin,
+//! with `TANDEM_EVENT` set to its name:
+//!
+//! ```json
+//! {"event":"gc","kept":5000,"pruned":812,"head":"9be1...",
+//!  "previousHead":"04c7...","repo":"/srv/project","atMillis":1760692364000,
+//!  "summary":"op retention pruned 812 operations, kept 5000 under new ids"}
+//! {"event":"headsUpdated","version":42,"heads":["9be1..."],"workspace":"agent-a",
+//!  "tags":{"ci":"failed"},"operation":{"description":"commit 3f0c...",
+//!  "author":"a@host","workspaceId":"agent-a","bookmarks":[],
+//!  "paths":["src/parse.rs"],"pathsTruncated":false}, ...}
+//! ```
+//!
+//! Slack gets `{"text": <summary>}`, the shape incoming webhooks take.
+//! tandem carries no TLS library: `http://` URLs are posted directly and
+//! `https://` ones through `curl`, which must then be on `PATH`.
+//!
+//! Each sink has its own queue and task, so a slow sink holds up neither
+//! the server nor the other sinks. A delivery that fails or takes longer
+//! than [`DELIVERY_TIMEOUT`] is logged and dropped; events that arrive while
+//! a sink's queue is full are dropped and counted in the log.
+//! (`tandem watch --notify` desktop notifications are `src/notify.rs`.)
+
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::mpsc;
+
+use crate::heads_summary::HeadsSummary;
+
+/// Longest one delivery may take.
+pub const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Events waiting per sink before new ones are dropped.
+const QUEUE: usize = 256;
+
+/// Something ops may want to hear about.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(
+    tag = "event",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Event {
+    HeadsUpdated {
+        version: u64,
+        heads: Vec<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        workspace: Option<String>,
+        /// Tags the publishing operation carried (see `op_tags.rs`).
+        #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+        tags: BTreeMap<String, String>,
+        /// What the new head operation did, as watchers asking for a
+        /// summary get it; none when it could not be computed.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        operation: Option<HeadsSummary>,
+    },
+    IntegrationFailed {
+        error: String,
+    },
+    Gc {
+        kept: usize,
+        pruned: usize,
+        /// The rewritten head.
+        head: String,
+        /// The head it replaced, now tombstoned.
+        previous_head: String,
+    },
+    GcFailed {
+        error: String,
+    },
+    /// Lost CAS races crossed `--cas-storm-threshold`.
+    CasStorm {
+        conflicts_per_sec: usize,
+        min_backoff_ms: u32,
+    },
+}
+
+/// Event names, as sinks filter on them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventKind {
+    HeadsUpdated,
+    IntegrationFailed,
+    Gc,
+    GcFailed,
+    CasStorm,
+}
+
+impl EventKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventKind::HeadsUpdated => "headsUpdated",
+            EventKind::IntegrationFailed => "integrationFailed",
+            EventKind::Gc => "gc",
+            EventKind::GcFailed => "gcFailed",
+            EventKind::CasStorm => "casStorm",
+        }
+    }
+}
+
+impl Event {
+    pub fn kind(&self) -> EventKind {
+        match self {
+            Event::HeadsUpdated { .. } => EventKind::HeadsUpdated,
+            Event::IntegrationFailed { .. } => EventKind::IntegrationFailed,
+            Event::Gc { .. } => EventKind::Gc,
+            Event::GcFailed { .. } => EventKind::GcFailed,
+            Event::CasStorm { .. } => EventKind::CasStorm,
+        }
+    }
+
+    /// One line for people.
+    pub fn summary(&self) -> String {
+        match self {
+            Event::HeadsUpdated {
+                version,
+                workspace,
+                tags,
+                operation,
+                ..
+            } => {
+                let mut line = match workspace {
+                    Some(workspace) => format!("{workspace} moved the heads to version {version}"),
+                    None => format!("heads moved to version {version}"),
+                };
+                if let Some(operation) = operation {
+                    let description = operation.description.lines().next().unwrap_or_default();
+                    if !description.is_empty() {
+                        line.push_str(&format!(": {description}"));
+                    }
+                    if !operation.paths.is_empty() {
+                        let more = if operation.paths_truncated {
+                            ", ..."
+                        } else {
+                            ""
+                        };
+                        line.push_str(&format!(" (touching {}{more})", operation.paths.join(", ")));
+                    }
+                }
+                if !tags.is_empty() {
+                    let tags: Vec<String> = tags
+                        .iter()
+                        .map(|(key, value)| format!("{key}={value}"))
+                        .collect();
+                    line.push_str(&format!(" [{}]", tags.join(" ")));
+                }
+                line
+            }
+            Event::IntegrationFailed { error } => format!("integration failed: {error}"),
+            Event::Gc { kept, pruned, .. } => {
+                format!("op retention pruned {pruned} operations, kept {kept} under new ids")
+            }
+            Event::GcFailed { error } => format!("op retention failed: {error}"),
+            Event::CasStorm {
+                conflicts_per_sec,
+                min_backoff_ms,
+            } => format!(
+                "CAS storm: {conflicts_per_sec} head updates lost the race in the last second; \
+                 clients told to back off at least {min_backoff_ms} ms"
+            ),
+        }
+    }
+}
+
+/// An event as delivered.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Notice {
+    #[serde(flatten)]
+    event: Event,
+    repo: String,
+    at_millis: u64,
+    summary: String,
+}
+
+/// The `--notifications` file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    pub sinks: Vec<SinkConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SinkConfig {
+    #[serde(flatten)]
+    pub target: Target,
+    /// Events to deliver; every event when unset.
+    #[serde(default)]
+    pub events: Option<Vec<EventKind>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Target {
+    Slack { url: String },
+    Webhook { url: String },
+    Command { command: Vec<String> },
+}
+
+impl Target {
+    /// For logs; leaves out URL paths, which often hold a secret.
+    fn describe(&self) -> String {
+        match self {
+            Target::Slack { .. } => "slack".to_string(),
+            Target::Webhook { url } => {
+                let host = url.split("://").nth(1).unwrap_or(url);
+                let host = host.split('/').next().unwrap_or(host);
+                format!("webhook {host}")
+            }
+            Target::Command { command } => {
+                format!("command {}", command.first().map_or("", String::as_str))
+            }
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        match self {
+            Target::Slack { url } | Target::Webhook { url } => {
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    bail!("url must start with http:// or https://, got {url:?}");
+                }
+            }
+            Target::Command { command } => {
+                if command.first().is_none_or(|program| program.is_empty()) {
+                    bail!("command must name a program");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self> {
+        let raw = std::fs::read(path)
+            .with_context(|| format!("read notifications file {}", path.display()))?;
+        let config: Config = serde_json::from_slice(&raw)
+            .with_context(|| format!("parse notifications file {}", path.display()))?;
+        for (n, sink) in config.sinks.iter().enumerate() {
+            sink.target
+                .validate()
+                .with_context(|| format!("{}: sink {}", path.display(), n + 1))?;
+        }
+        Ok(config)
+    }
+}
+
+struct Sink {
+    events: Option<Vec<EventKind>>,
+    tx: mpsc::Sender<Arc<Notice>>,
+    name: String,
+    dropped: AtomicU64,
+}
+
+/// Where events go. The default delivers nothing.
+#[derive(Clone, Default)]
+pub struct Notifications {
+    sinks: Arc<Vec<Sink>>,
+    repo: String,
+}
+
+impl Notifications {
+    /// Start one delivery task per sink. Needs a tokio runtime.
+    pub fn start(config: Config, repo: &str) -> Self {
+        let sinks = config
+            .sinks
+            .into_iter()
+            .map(|sink| {
+                let (tx, rx) = mpsc::channel(QUEUE);
+                let name = sink.target.describe();
+                tokio::spawn(deliver_all(sink.target, rx));
+                Sink {
+                    events: sink.events,
+                    tx,
+                    name,
+                    dropped: AtomicU64::new(0),
+                }
+            })
+            .collect();
+        Self {
+            sinks: Arc::new(sinks),
+            repo: repo.to_string(),
+        }
+    }
+
+    pub fn sinks(&self) -> usize {
+        self.sinks.len()
+    }
+
+    /// Whether any sink takes `kind`, so callers can skip building events
+    /// nobody hears.
+    pub fn wants(&self, kind: EventKind) -> bool {
+        self.sinks.iter().any(|sink| {
+            sink.events
+                .as_ref()
+                .is_none_or(|events| events.contains(&kind))
+        })
+    }
+
+    /// Queue `event` for every sink that wants it. Never blocks.
+    pub fn emit(&self, event: Event) {
+        let kind = event.kind();
+        let mut notice = None;
+        for sink in self.sinks.iter() {
+            if sink
+                .events
+                .as_ref()
+                .is_some_and(|events| !events.contains(&kind))
+            {
+                continue;
+            }
+            let notice = notice
+                .get_or_insert_with(|| {
+                    Arc::new(Notice {
+                        summary: event.summary(),
+                        event: event.clone(),
+                        repo: self.repo.clone(),
+                        at_millis: now_millis(),
+                    })
+                })
+                .clone();
+            if sink.tx.try_send(notice).is_err() {
+                let dropped = sink.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                tracing::warn!(
+                    sink = %sink.name,
+                    event = kind.as_str(),
+                    dropped,
+                    "notification sink is behind; dropped an event"
+                );
+            }
+        }
+    }
+}
+
+async fn deliver_all(target: Target, mut rx: mpsc::Receiver<Arc<Notice>>) {
+    while let Some(notice) = rx.recv().await {
+        let result = tokio::time::timeout(DELIVERY_TIMEOUT, deliver(&target, &notice))
+            .await
+            .unwrap_or_else(|_| Err(anyhow!("timed out after {}s", DELIVERY_TIMEOUT.as_secs())));
+        if let Err(err) = result {
+            tracing::warn!(
+                sink = %target.describe(),
+                event = notice.event.kind().as_str(),
+                error = %format!("{err:#}"),
+                "notification delivery failed"
+            );
+        }
+    }
+}
+
+async fn deliver(target: &Target, notice: &Notice) -> Result<()> {
+    match target {
+        Target::Slack { url } => {
+            let body = serde_json::json!({ "text": format!("tandem: {}", notice.summary) });
+            post_json(url, body.to_string().as_bytes()).await
+        }
+        Target::Webhook { url } => post_json(url, &serde_json::to_vec(notice)?).await,
+        Target::Command { command } => {
+            let mut child = tokio::process::Command::new(&command[0])
+                .args(&command[1..])
+                .env("TANDEM_EVENT", notice.event.kind().as_str())
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .kill_on_drop(true)
+                .spawn()
+                .with_context(|| format!("run {}", command[0]))?;
+            let mut stdin = child.stdin.take().expect("stdin is piped");
+            stdin.write_all(&serde_json::to_vec(notice)?).await?;
+            stdin.write_all(b"\n").await?;
+            drop(stdin);
+            let status = child.wait().await?;
+            if !status.success() {
+                bail!("{} exited with {status}", command[0]);
+            }
+            Ok(())
+        }
+    }
+}
+
+async fn post_json(url: &str, body: &[u8]) -> Result<()> {
+    if let Some(rest) = url.strip_prefix("http://") {
+        return post_plain(rest, body).await;
+    }
+    let mut child = tokio::process::Command::new("curl")
+        .args([
+            "--silent",
+            "--show-error",
+            "--fail",
+            "--max-time",
+            &DELIVERY_TIMEOUT.as_secs().to_string(),
+            "--header",
+            "Content-Type: application/json",
+            "--data-binary",
+            "@-",
+            url,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .context("run curl for an https:// notification")?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin.write_all(body).await?;
+    drop(stdin);
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        bail!("curl: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// POST over plain HTTP/1.1; `rest` is the URL after `http://`.
+async fn post_plain(rest: &str, body: &[u8]) -> Result<()> {
+    let (host, path) = match rest.find('/') {
+        Some(slash) => rest.split_at(slash),
+        None => (rest, "/"),
+    };
+    let addr = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    let mut stream = tokio::net::TcpStream::connect(&addr)
+        .await
+        .with_context(|| format!("connect to {addr}"))?;
+    let head = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: tandem/{}\r\n\
+         Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        env!("CARGO_PKG_VERSION"),
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let status_line = String::from_utf8_lossy(&response);
+    let status_line = status_line.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => bail!("webhook answered {status_line:?}"),
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_sinks_and_filters() {
+        let config: Config = serde_json::from_str(
+            r#"{"sinks": [
+                {"type": "slack", "url": "https://hooks.slack.com/services/T/B/x",
+                 "events": ["integrationFailed", "gcFailed"]},
+                {"type": "command", "command": ["notify", "--team", "vcs"]}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.sinks[0].events,
+            Some(vec![EventKind::IntegrationFailed, EventKind::GcFailed])
+        );
+        assert_eq!(config.sinks[0].target.describe(), "slack");
+        assert!(config.sinks[1].events.is_none());
+
+        let unknown = serde_json::from_str::<Config>(
+            r#"{"sinks": [{"type": "webhook", "url": "http://x", "events": ["nope"]}]}"#,
+        );
+        assert!(unknown.is_err());
+        let bad_url = Target::Webhook {
+            url: "ftp://x".into(),
+        };
+        assert!(bad_url.validate().is_err());
+        let empty = Target::Command { command: vec![] };
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn notices_carry_the_event_and_a_summary() {
+        let event = Event::Gc {
+            kept: 10,
+            pruned: 3,
+            head: "ab".into(),
+            previous_head: "cd".into(),
+        };
+        let notice = Notice {
+            summary: event.summary(),
+            event,
+            repo: "/srv/repo".into(),
+            at_millis: 1,
+        };
+        let json = serde_json::to_value(&notice).unwrap();
+        assert_eq!(json["event"], "gc");
+        assert_eq!(json["pruned"], 3);
+        assert_eq!(json["repo"], "/srv/repo");
+        assert_eq!(
+            json["summary"],
+            "op retention pruned 3 operations, kept 10 under new ids"
+        );
+        assert_eq!(json["head"], "ab");
+        assert_eq!(json["previousHead"], "cd");
+
+        let heads = serde_json::to_value(Event::HeadsUpdated {
+            version: 4,
+            heads: vec!["cd".into()],
+            workspace: None,
+            tags: BTreeMap::new(),
+            operation: None,
+        })
+        .unwrap();
+        assert_eq!(heads["event"], "headsUpdated");
+        assert!(heads.get("workspace").is_none());
+        assert!(heads.get("tags").is_none());
+    }
+
+    #[test]
+    fn webhooks_get_the_tags_and_operation_summary() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}/hook", listener.local_addr().unwrap());
+            let received = tokio::spawn(async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                // The body follows the blank line; read until it is all here.
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some((head, body)) = text.split_once("\r\n\r\n") {
+                        let length: usize = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("Content-Length: "))
+                            .unwrap()
+                            .parse()
+                            .unwrap();
+                        if body.len() >= length {
+                            break;
+                        }
+                    }
+                }
+                stream
+                    .write_all(b"HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n")
+                    .await
+                    .unwrap();
+                let request = String::from_utf8(request).unwrap();
+                request.split_once("\r\n\r\n").unwrap().1.to_string()
+            });
+
+            let event = Event::HeadsUpdated {
+                version: 7,
+                heads: vec!["ab".into()],
+                workspace: Some("agent-a".into()),
+                tags: BTreeMap::from([("ci".to_string(), "failed".to_string())]),
+                operation: Some(HeadsSummary {
+                    description: "commit 3f0c\n\nmore".into(),
+                    author: "a@host".into(),
+                    workspace_id: Some("agent-a".into()),
+                    bookmarks: vec!["main".into()],
+                    paths: vec!["src/parse.rs".into()],
+                    paths_truncated: false,
+                    overlaps: Vec::new(),
+                }),
+            };
+            let notice = Notice {
+                summary: event.summary(),
+                event,
+                repo: "/srv/repo".into(),
+                at_millis: 1,
+            };
+            deliver(&Target::Webhook { url }, &notice).await.unwrap();
+
+            let body: serde_json::Value = serde_json::from_str(&received.await.unwrap()).unwrap();
+            assert_eq!(body["tags"]["ci"], "failed");
+            assert_eq!(body["operation"]["description"], "commit 3f0c\n\nmore");
+            assert_eq!(body["operation"]["bookmarks"][0], "main");
+            assert_eq!(body["operation"]["paths"][0], "src/parse.rs");
+            assert_eq!(
+                body["summary"],
+                "agent-a moved the heads to version 7: commit 3f0c \
+                 (touching src/parse.rs) [ci=failed]"
+            );
+        });
+    }
+}
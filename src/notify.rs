@@ -0,0 +1,307 @@
+//! Desktop notifications for `tandem watch --notify`.
+//!
+//! Each head notification wakes a background thread that diffs the server's
+//! workspace heads and bookmarks against the previous snapshot, then raises a
+//! native notification (`osascript` on macOS, `notify-send` elsewhere) for
+//! changes made by other workspaces.
+//!
+//! The baseline snapshot is taken before the watch subscribes, so the state
+//! the watch catches up on is never announced. A workspace is announced when
+//! the change it landed is a different one (by change id and description),
+//! not for every operation it publishes: one `jj new` is several operations,
+//! starting with working-copy snapshots of the change already announced. The
+//! thread and its snapshot outlive reconnects of the watch, which hand it the
+//! new connection; what landed while the watch was down is announced once.
+//!
+//! Bookmark moves are not announced when this workspace is the only one
+//! whose head operation moved since the previous snapshot: the move is its
+//! own. A burst that mixes in other workspaces' operations is announced.
+
+use std::collections::BTreeMap;
+use std::io::Write as _;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::sync::Arc;
+
+use anyhow::Result;
+use jj_lib::backend::CommitId;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::ref_name::WorkspaceNameBuf;
+
+use crate::ps::{read_commit, read_view};
+use crate::rpc::TandemClient;
+
+/// Test hook: append notifications to this file instead of showing them.
+const TEST_NOTIFY_LOG_ENV: &str = "TANDEM_TEST_NOTIFY_LOG";
+
+#[derive(Default)]
+struct Snapshot {
+    workspace_ops: BTreeMap<String, Vec<u8>>,
+    /// What each other workspace last landed, when it could be read.
+    landed: BTreeMap<String, Landed>,
+    bookmarks: BTreeMap<String, CommitId>,
+}
+
+/// The change a workspace's head operation landed.
+#[derive(Clone, PartialEq, Eq)]
+struct Landed {
+    change_id: Vec<u8>,
+    /// First line of its description, if it has one.
+    description: Option<String>,
+}
+
+enum Message {
+    HeadsChanged,
+    Reconnected(Arc<TandemClient>),
+}
+
+/// Handle on the notifier thread; dropping it stops the thread.
+pub struct Notifier {
+    tx: mpsc::Sender<Message>,
+}
+
+impl Notifier {
+    /// The watch stream reported a head change.
+    pub fn heads_changed(&self) {
+        let _ = self.tx.send(Message::HeadsChanged);
+    }
+
+    /// The watch reconnected; read through `client` from now on.
+    pub fn reconnected(&self, client: Arc<TandemClient>) {
+        let _ = self.tx.send(Message::Reconnected(client));
+    }
+}
+
+/// Take the baseline snapshot, then start the notifier thread.
+pub fn spawn_notifier(client: Arc<TandemClient>, own_workspace: Option<String>) -> Notifier {
+    let own_workspace_ref = own_workspace.as_deref();
+    let mut previous = match take_snapshot(&client, None, own_workspace_ref) {
+        Ok(snapshot) => snapshot,
+        Err(err) => {
+            tracing::warn!(error = %err, "notify: initial snapshot failed");
+            Snapshot::default()
+        }
+    };
+    let (tx, rx) = mpsc::channel::<Message>();
+    std::thread::spawn(move || {
+        let mut client = client;
+        while let Ok(message) = rx.recv() {
+            let mut messages = vec![message];
+            // Coalesce bursts of head updates into one diff.
+            messages.extend(rx.try_iter());
+            for message in messages {
+                if let Message::Reconnected(reconnected) = message {
+                    client = reconnected;
+                }
+            }
+            let current = match take_snapshot(&client, Some(&previous), own_workspace.as_deref()) {
+                Ok(snapshot) => snapshot,
+                Err(err) => {
+                    tracing::warn!(error = %err, "notify: snapshot failed");
+                    continue;
+                }
+            };
+            for (summary, body) in diff_snapshots(&previous, &current, own_workspace.as_deref()) {
+                show_notification(&summary, &body);
+            }
+            previous = current;
+        }
+    });
+    Notifier { tx }
+}
+
+/// Read the current heads. What other workspaces landed is carried over from
+/// `previous` for those whose head operation did not move.
+fn take_snapshot(
+    client: &TandemClient,
+    previous: Option<&Snapshot>,
+    own_workspace: Option<&str>,
+) -> Result<Snapshot> {
+    let state = client.get_heads_state()?;
+    let mut landed = BTreeMap::new();
+    for (workspace, op_id) in &state.workspace_heads {
+        if own_workspace == Some(workspace.as_str()) {
+            continue;
+        }
+        let unchanged = previous
+            .filter(|previous| previous.workspace_ops.get(workspace) == Some(op_id))
+            .and_then(|previous| previous.landed.get(workspace));
+        let entry = match unchanged {
+            Some(entry) => Some(entry.clone()),
+            None => landed_change(client, workspace, op_id),
+        };
+        if let Some(entry) = entry {
+            landed.insert(workspace.clone(), entry);
+        }
+    }
+    let bookmarks = match state.heads.first() {
+        Some(op_id) => read_view(client, op_id)?
+            .local_bookmarks
+            .into_iter()
+            .filter_map(|(name, target)| {
+                let commit_id = target.as_normal()?.clone();
+                Some((AsRef::<str>::as_ref(&name).to_owned(), commit_id))
+            })
+            .collect(),
+        None => BTreeMap::new(),
+    };
+    Ok(Snapshot {
+        workspace_ops: state.workspace_heads,
+        landed,
+        bookmarks,
+    })
+}
+
+fn diff_snapshots(
+    previous: &Snapshot,
+    current: &Snapshot,
+    own_workspace: Option<&str>,
+) -> Vec<(String, String)> {
+    let mut notifications = Vec::new();
+
+    for (workspace, landed) in &current.landed {
+        if previous.landed.get(workspace) == Some(landed) {
+            continue;
+        }
+        let body = landed
+            .description
+            .clone()
+            .unwrap_or_else(|| "(no description set)".to_string());
+        notifications.push((format!("tandem: {workspace} landed a change"), body));
+    }
+
+    // Workspaces whose operations landed since `previous`.
+    let movers: Vec<&str> = current
+        .workspace_ops
+        .iter()
+        .filter(|(workspace, op_id)| previous.workspace_ops.get(*workspace) != Some(op_id))
+        .map(|(workspace, _)| workspace.as_str())
+        .collect();
+    let moved_by_self = !movers.is_empty()
+        && movers
+            .iter()
+            .all(|workspace| Some(*workspace) == own_workspace);
+    for (name, commit_id) in &current.bookmarks {
+        if moved_by_self || previous.bookmarks.get(name) == Some(commit_id) {
+            continue;
+        }
+        let short: String = commit_id.hex().chars().take(12).collect();
+        notifications.push((
+            format!("tandem: bookmark {name} moved"),
+            format!("{name} → {short}"),
+        ));
+    }
+
+    notifications
+}
+
+/// The change the workspace just landed: its working-copy commit, or that
+/// commit's parent when `@` is a fresh change with no description.
+fn landed_change(client: &TandemClient, workspace: &str, op_id: &[u8]) -> Option<Landed> {
+    let view = read_view(client, op_id).ok()?;
+    let wc_commit_id = view
+        .wc_commit_ids
+        .get(&WorkspaceNameBuf::from(workspace.to_owned()))?
+        .clone();
+    let wc_commit = read_commit(client, &wc_commit_id).ok()?;
+    let commit = if wc_commit.description.trim().is_empty() {
+        let parent = CommitId::new(wc_commit.parents.first()?.clone());
+        read_commit(client, &parent).ok()?
+    } else {
+        wc_commit
+    };
+    let description = commit
+        .description
+        .lines()
+        .next()
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned);
+    Some(Landed {
+        change_id: commit.change_id,
+        description,
+    })
+}
+
+fn show_notification(summary: &str, body: &str) {
+    if let Ok(path) = std::env::var(TEST_NOTIFY_LOG_ENV) {
+        let result = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| writeln!(file, "{summary}\t{body}"));
+        if let Err(err) = result {
+            tracing::warn!(path = %path, error = %err, "notify: cannot write test log");
+        }
+        return;
+    }
+
+    let mut command = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification {} with title {}",
+            applescript_string(body),
+            applescript_string(summary)
+        );
+        let mut command = Command::new("osascript");
+        command.args(["-e", &script]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["--app-name=tandem", summary, body]);
+        command
+    };
+    let result = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => tracing::warn!(%status, "notify: notification command failed"),
+        Err(err) => tracing::warn!(error = %err, "notify: cannot run notification command"),
+    }
+}
+
+fn applescript_string(raw: &str) -> String {
+    format!("\"{}\"", raw.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(ops: &[(&str, u8)], bookmarks: &[(&str, u8)]) -> Snapshot {
+        Snapshot {
+            workspace_ops: ops
+                .iter()
+                .map(|(workspace, op)| (workspace.to_string(), vec![*op]))
+                .collect(),
+            landed: BTreeMap::new(),
+            bookmarks: bookmarks
+                .iter()
+                .map(|(name, commit)| (name.to_string(), CommitId::new(vec![*commit; 20])))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn own_bookmark_moves_are_not_announced() {
+        let previous = snapshot(&[("me", 1), ("other", 1)], &[("main", 1)]);
+
+        let mine = snapshot(&[("me", 2), ("other", 1)], &[("main", 2)]);
+        assert!(diff_snapshots(&previous, &mine, Some("me")).is_empty());
+        // Without a workspace of its own, the watch announces every move.
+        assert_eq!(diff_snapshots(&previous, &mine, None).len(), 1);
+
+        let theirs = snapshot(&[("me", 1), ("other", 2)], &[("main", 2)]);
+        assert_eq!(
+            diff_snapshots(&previous, &theirs, Some("me")),
+            vec![(
+                "tandem: bookmark main moved".to_string(),
+                "main → 020202020202".to_string()
+            )]
+        );
+
+        let both = snapshot(&[("me", 2), ("other", 2)], &[("main", 2)]);
+        assert_eq!(diff_snapshots(&previous, &both, Some("me")).len(), 1);
+    }
+}
@@ -0,0 +1,59 @@
+//! Offline queue lines for `tandem status`.
+//!
+//! After jj's own status, say how many writes made offline are still
+//! waiting for the server (see `offline_queue.rs`), and why the last replay
+//! stopped if one did. Nothing is printed while the queue is empty.
+
+use jj_cli::cli_util::{CliDispatchFn, CommandHelper};
+use jj_cli::command_error::CommandError;
+use jj_cli::ui::Ui;
+
+use crate::offline_queue::{Queue, Write};
+
+/// Dispatch hook: report the offline queue after `status`.
+pub fn report(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    dispatch: CliDispatchFn,
+) -> Result<(), CommandError> {
+    dispatch(ui, command)?;
+    if command.matches().subcommand_name() != Some("status") {
+        return Ok(());
+    }
+    let Ok(workspace) = command.load_workspace() else {
+        return Ok(());
+    };
+    let queue = Queue::new(workspace.repo_path());
+    let writes = match queue.writes() {
+        Ok(writes) => writes,
+        Err(err) => {
+            tracing::warn!(error = %format!("{err:#}"), "cannot read the offline queue");
+            return Ok(());
+        }
+    };
+    if writes.is_empty() {
+        return Ok(());
+    }
+    let operations = writes
+        .iter()
+        .filter(|write| matches!(write, Write::Heads { .. }))
+        .count();
+    writeln!(
+        ui.status(),
+        "Offline: {} write(s) for {operations} operation(s) not yet sent to the tandem server",
+        writes.len()
+    )?;
+    if let Some(conflict) = queue.conflict() {
+        writeln!(
+            ui.warning_default(),
+            "The last sync stopped at a queued write: {}",
+            conflict.reason
+        )?;
+        writeln!(
+            ui.hint_default(),
+            "The queue is in {}; every command that reaches the server retries it",
+            queue.dir().display()
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,183 @@
+//! Op retention — bound the server's op log by age or length.
+//!
+//! `tandem serve --op-retention <days|count>` (`90d`, or `5000` operations)
+//! prunes operations beyond the policy. A pass runs at startup and then every
+//! [`PASS_INTERVAL`]: it keeps the newest operations the policy allows (by
+//! end time, so the server's reconcile merges do not crowd out the
+//! operations they merge), rewrites them without the parents it drops the
+//! way `jj op abandon ..<op>` does, and deletes the operations and views no
+//! longer reachable from the new head. Commits are never deleted, and the
+//! view of every kept operation stays, so everything reachable from the
+//! current heads is still there.
+//!
+//! Operation ids hash their parents, so no id survives a pass: the head
+//! changes too. A pass therefore only rewrites once the operations beyond
+//! the policy reach a quarter of the kept ones (see [`worth_pruning`]); in
+//! between, the log grows past the policy by up to that much. The server
+//! tombstones the old head and every workspace's old head, each mapped to
+//! its new id, so a client still on one is told to re-run instead of
+//! failing on a missing operation, and announces the new head in the `gc`
+//! notification. Workspaces may need `tandem workspace update-stale`
+//! afterwards.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Result};
+use jj_lib::op_store::{OpStore, OperationId};
+use jj_lib::operation::Operation;
+
+/// How often the server checks its op log against the policy.
+pub const PASS_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+const MILLIS_PER_DAY: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpRetention {
+    /// Keep operations that ended within this many days.
+    Days(u64),
+    /// Keep this many of the newest operations.
+    Count(usize),
+}
+
+impl OpRetention {
+    /// Parse `<n>d` as a number of days and a bare `<n>` as a number of
+    /// operations.
+    pub fn parse(raw: &str) -> Result<Self> {
+        let raw = raw.trim();
+        let (digits, days) = match raw.strip_suffix('d') {
+            Some(digits) => (digits, true),
+            None => (raw, false),
+        };
+        let n: u64 = digits.parse().map_err(|_| {
+            anyhow!("invalid op retention {raw:?}: expected days (e.g. 90d) or a count (e.g. 5000)")
+        })?;
+        if n == 0 {
+            bail!("invalid op retention {raw:?}: must keep at least one day or operation");
+        }
+        Ok(if days {
+            Self::Days(n)
+        } else {
+            Self::Count(n as usize)
+        })
+    }
+
+    /// Which of `end_millis` (operation end times, in topological order
+    /// from the head) the policy keeps at `now_millis`. The head is always
+    /// kept.
+    pub fn keeps(&self, end_millis: &[i64], now_millis: i64) -> Vec<bool> {
+        let mut keeps = vec![false; end_millis.len()];
+        match *self {
+            Self::Count(n) => {
+                let mut newest: Vec<usize> = (0..end_millis.len()).collect();
+                newest.sort_by_key(|&i| Reverse(end_millis[i]));
+                for i in newest.into_iter().take(n) {
+                    keeps[i] = true;
+                }
+            }
+            Self::Days(days) => {
+                let cutoff =
+                    now_millis.saturating_sub((days as i64).saturating_mul(MILLIS_PER_DAY));
+                for (keep, &end) in keeps.iter_mut().zip(end_millis) {
+                    *keep = end >= cutoff;
+                }
+            }
+        }
+        if let Some(head) = keeps.first_mut() {
+            *head = true;
+        }
+        keeps
+    }
+}
+
+impl fmt::Display for OpRetention {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Days(days) => write!(f, "{days}d"),
+            Self::Count(count) => write!(f, "{count}"),
+        }
+    }
+}
+
+/// Whether `prunable` operations beyond the policy justify rewriting the
+/// `kept` ones.
+pub fn worth_pruning(kept: usize, prunable: usize) -> bool {
+    prunable > 0 && prunable >= (kept / 4).max(1)
+}
+
+/// Write `kept` (in topological order from the head) again with the
+/// parents outside it dropped; those left without parents start from the
+/// root operation. Returns each kept operation's new id.
+pub fn rewrite(
+    op_store: &dyn OpStore,
+    kept: &[&Operation],
+) -> Result<HashMap<OperationId, OperationId>> {
+    let root_id = op_store.root_operation_id();
+    let mut rewritten: HashMap<OperationId, OperationId> = HashMap::new();
+    for op in kept.iter().rev() {
+        let mut data = op.store_operation().clone();
+        data.parents = data
+            .parents
+            .iter()
+            .filter_map(|id| rewritten.get(id).cloned())
+            .collect();
+        if data.parents.is_empty() {
+            data.parents.push(root_id.clone());
+        }
+        let new_id = pollster::block_on(op_store.write_operation(&data))
+            .map_err(|e| anyhow!("rewrite operation {}: {e}", op.id()))?;
+        rewritten.insert(op.id().clone(), new_id);
+    }
+    Ok(rewritten)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_days_and_counts() {
+        assert_eq!(OpRetention::parse("90d").unwrap(), OpRetention::Days(90));
+        assert_eq!(
+            OpRetention::parse("5000").unwrap(),
+            OpRetention::Count(5000)
+        );
+        assert!(OpRetention::parse("0").is_err());
+        assert!(OpRetention::parse("0d").is_err());
+        assert!(OpRetention::parse("3w").is_err());
+        assert_eq!(OpRetention::Days(90).to_string(), "90d");
+    }
+
+    #[test]
+    fn keeps_by_count_and_age() {
+        let day = MILLIS_PER_DAY;
+        let now = 100 * day;
+        // A merge's old parent can come before newer operations.
+        let ends = [now, now - 10 * day, now - day, now - 3 * day];
+        assert_eq!(
+            OpRetention::Count(2).keeps(&ends, now),
+            [true, false, true, false]
+        );
+        assert_eq!(OpRetention::Count(10).keeps(&ends, now), [true; 4]);
+        assert_eq!(
+            OpRetention::Days(2).keeps(&ends, now),
+            [true, false, true, false]
+        );
+        assert_eq!(OpRetention::Days(30).keeps(&ends, now), [true; 4]);
+        // The head stays even when it is older than the policy.
+        assert_eq!(
+            OpRetention::Days(1).keeps(&ends[1..], now + day),
+            [true, false, false]
+        );
+    }
+
+    #[test]
+    fn prunes_only_past_the_slack() {
+        assert!(!worth_pruning(100, 0));
+        assert!(!worth_pruning(100, 24));
+        assert!(worth_pruning(100, 25));
+        assert!(worth_pruning(1, 1));
+    }
+}
@@ -0,0 +1,245 @@
+//! Operation tags — key/value labels the server keeps per operation.
+//!
+//! Tags arrive with `putOperation` and from attribution in `updateOpHeads`,
+//! and never touch jj's content-hashed operation bytes. They live in
+//! `.jj/repo/tandem/op-tags.jsonl`: one JSON entry per line, each adding tags
+//! to one operation, so recording a tag costs one appended line however many
+//! operations were tagged before. The file is only rewritten whole when op
+//! retention renames or drops operations. Its first line records the schema
+//! version (see [`metadata_schema::OPERATION_TAG_LOG`]).
+//!
+//! Servers before the log kept every tag in `op-tags.json`; the first open
+//! folds that file into the log and keeps it as a backup.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::metadata_schema;
+
+pub const FILE: &str = metadata_schema::OPERATION_TAG_LOG.file;
+
+pub type Tags = BTreeMap<String, String>;
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    op: String,
+    tags: Tags,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LegacyTags {
+    #[serde(default)]
+    operations: BTreeMap<String, Tags>,
+}
+
+pub struct OperationTags {
+    path: PathBuf,
+    operations: Mutex<BTreeMap<String, Tags>>,
+}
+
+impl OperationTags {
+    /// Load the log in `tandem_dir`, importing a legacy `op-tags.json`.
+    /// Unreadable lines (e.g. a torn final write) are skipped.
+    pub fn open(tandem_dir: &Path) -> Result<Self> {
+        let path = tandem_dir.join(FILE);
+        let mut operations: BTreeMap<String, Tags> = BTreeMap::new();
+        for line in metadata_schema::read_journal(&metadata_schema::OPERATION_TAG_LOG, &path)? {
+            match serde_json::from_str::<Entry>(&line) {
+                Ok(entry) => operations.entry(entry.op).or_default().extend(entry.tags),
+                Err(err) => {
+                    tracing::warn!(path = %path.display(), error = %err, "skipping bad operation tags line")
+                }
+            }
+        }
+        let tags = Self {
+            path,
+            operations: Mutex::new(operations),
+        };
+        tags.import_legacy(tandem_dir)?;
+        Ok(tags)
+    }
+
+    fn import_legacy(&self, tandem_dir: &Path) -> Result<()> {
+        let format = &metadata_schema::OPERATION_TAGS;
+        let legacy_path = tandem_dir.join(format.file);
+        if !legacy_path.exists() {
+            return Ok(());
+        }
+        let legacy: LegacyTags = metadata_schema::read(format, &legacy_path)?;
+        let mut operations = self.operations.lock().unwrap();
+        for (op, tags) in legacy.operations {
+            // Entries already in the log were written later.
+            let merged = operations.entry(op).or_default();
+            for (key, value) in tags {
+                merged.entry(key).or_insert(value);
+            }
+        }
+        self.write_all(&operations)?;
+        let backup = format.backup_path(tandem_dir, format.current());
+        fs::rename(&legacy_path, &backup)
+            .with_context(|| format!("move {} to {}", legacy_path.display(), backup.display()))?;
+        tracing::info!(backup = %backup.display(), "moved operation tags into {FILE}");
+        Ok(())
+    }
+
+    pub fn get(&self, op_hex: &str) -> Tags {
+        let operations = self.operations.lock().unwrap();
+        operations.get(op_hex).cloned().unwrap_or_default()
+    }
+
+    /// Operations whose tags satisfy `matches`.
+    pub fn filter(&self, mut matches: impl FnMut(&Tags) -> bool) -> Vec<(String, Tags)> {
+        let operations = self.operations.lock().unwrap();
+        operations
+            .iter()
+            .filter(|(_, tags)| matches(tags))
+            .map(|(op, tags)| (op.clone(), tags.clone()))
+            .collect()
+    }
+
+    /// Add `tags` to `op_hex`, replacing values of keys it already has.
+    pub fn extend(&self, op_hex: &str, tags: &Tags) -> Result<()> {
+        let mut operations = self.operations.lock().unwrap();
+        let current = operations.get(op_hex);
+        let changed: Tags = tags
+            .iter()
+            .filter(|(key, value)| current.and_then(|tags| tags.get(*key)) != Some(*value))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+        if changed.is_empty() {
+            return Ok(());
+        }
+        self.append(op_hex, &changed)?;
+        operations
+            .entry(op_hex.to_string())
+            .or_default()
+            .extend(changed);
+        Ok(())
+    }
+
+    /// Tag `op_hex` with `key` unless it already has a value for it; the
+    /// first writer wins. Returns the value the operation ends up with.
+    pub fn insert_if_absent(&self, op_hex: &str, key: &str, value: &str) -> Result<String> {
+        let mut operations = self.operations.lock().unwrap();
+        if let Some(existing) = operations.get(op_hex).and_then(|tags| tags.get(key)) {
+            return Ok(existing.clone());
+        }
+        let tags = Tags::from([(key.to_string(), value.to_string())]);
+        self.append(op_hex, &tags)?;
+        operations
+            .entry(op_hex.to_string())
+            .or_default()
+            .extend(tags);
+        Ok(value.to_string())
+    }
+
+    /// Rewrite every operation's tags at once, e.g. after op retention gave
+    /// the kept operations new ids.
+    pub fn rewrite(&self, update: impl FnOnce(&mut BTreeMap<String, Tags>)) -> Result<()> {
+        let mut operations = self.operations.lock().unwrap();
+        let mut next = operations.clone();
+        update(&mut next);
+        next.retain(|_, tags| !tags.is_empty());
+        self.write_all(&next)?;
+        *operations = next;
+        Ok(())
+    }
+
+    fn append(&self, op_hex: &str, tags: &Tags) -> Result<()> {
+        let line = serde_json::to_string(&Entry {
+            op: op_hex.to_string(),
+            tags: tags.clone(),
+        })?;
+        metadata_schema::append_journal(&metadata_schema::OPERATION_TAG_LOG, &self.path, &line)
+    }
+
+    fn write_all(&self, operations: &BTreeMap<String, Tags>) -> Result<()> {
+        let records = operations
+            .iter()
+            .map(|(op, tags)| {
+                serde_json::to_string(&Entry {
+                    op: op.clone(),
+                    tags: tags.clone(),
+                })
+            })
+            .collect::<serde_json::Result<Vec<_>>>()?;
+        metadata_schema::write_journal(&metadata_schema::OPERATION_TAG_LOG, &self.path, &records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(pairs: &[(&str, &str)]) -> Tags {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn appends_and_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        {
+            let log = OperationTags::open(dir.path()).unwrap();
+            log.extend("aa", &tags(&[("args", "jj new")])).unwrap();
+            assert_eq!(
+                log.insert_if_absent("aa", "workspace", "agent-a").unwrap(),
+                "agent-a"
+            );
+            assert_eq!(
+                log.insert_if_absent("aa", "workspace", "agent-b").unwrap(),
+                "agent-a"
+            );
+            log.extend("bb", &tags(&[("workspace", "agent-b")]))
+                .unwrap();
+        }
+        let lines = fs::read_to_string(dir.path().join(FILE))
+            .unwrap()
+            .lines()
+            .count();
+        // The schema version header plus one line per change.
+        assert_eq!(lines, 4);
+
+        let log = OperationTags::open(dir.path()).unwrap();
+        assert_eq!(
+            log.get("aa"),
+            tags(&[("args", "jj new"), ("workspace", "agent-a")])
+        );
+        let agent_b = log.filter(|t| t.get("workspace").map(String::as_str) == Some("agent-b"));
+        assert_eq!(
+            agent_b,
+            vec![("bb".to_string(), tags(&[("workspace", "agent-b")]))]
+        );
+    }
+
+    #[test]
+    fn imports_legacy_file_once() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("op-tags.json"),
+            br#"{"operations":{"aa":{"workspace":"agent-a"}}}"#,
+        )
+        .unwrap();
+        let log = OperationTags::open(dir.path()).unwrap();
+        assert_eq!(log.get("aa"), tags(&[("workspace", "agent-a")]));
+        assert!(!dir.path().join("op-tags.json").exists());
+        assert!(dir.path().join("op-tags.json.v1.bak").exists());
+
+        log.rewrite(|operations| {
+            let moved = operations.remove("aa").unwrap();
+            operations.insert("cc".to_string(), moved);
+        })
+        .unwrap();
+        let reopened = OperationTags::open(dir.path()).unwrap();
+        assert!(reopened.get("aa").is_empty());
+        assert_eq!(reopened.get("cc"), tags(&[("workspace", "agent-a")]));
+    }
+}
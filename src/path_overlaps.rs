@@ -0,0 +1,298 @@
+//! Path overlaps — warn when two workspaces are changing the same files.
+//!
+//! Every workspace publishes its working-copy commit with its operations, so
+//! the server can tell, for each pair of workspaces, which paths both have
+//! changed since their working copies diverged: each side's tree is diffed
+//! against the pair's common ancestors, and the two sets of touched paths
+//! are intersected. Pairs where one working copy descends from the other are
+//! skipped, since one workspace is building on the other's work rather than
+//! racing it.
+//!
+//! `getHeads(withOverlaps = true)` returns the overlaps, and heads summaries
+//! carry the ones involving the publishing workspace. After a successful
+//! `commit`, `new`, `describe`, or `squash`, tandem asks for them and prints
+//! a warning per overlapping workspace, e.g. "agent-b also modified src/auth.rs", saying whether the
+//! two working copies would merge cleanly when the server offers
+//! `previewMerge`. Nothing is blocked: the warning only says a conflict may
+//! be coming.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{anyhow, Result};
+use futures::StreamExt as _;
+use jj_cli::cli_util::{CliDispatchFn, CommandHelper};
+use jj_cli::command_error::CommandError;
+use jj_cli::ui::Ui;
+use jj_lib::backend::CommitId;
+use jj_lib::matchers::EverythingMatcher;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::OperationId;
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::repo::{ReadonlyRepo, Repo as _, RepoLoader};
+use jj_lib::rewrite::merge_commit_trees;
+
+use crate::backend::TandemBackend;
+use crate::rpc::{PathOverlap, RepoCapability};
+
+/// Overlapping paths reported per pair of workspaces.
+pub const PATH_LIMIT: usize = 100;
+/// Paths named in a warning before it says "and N more".
+const SHOWN_PATHS: usize = 3;
+
+// ─── Server side ──────────────────────────────────────────────────────────────
+
+/// Overlaps between the working-copy commits in `workspace_commits`
+/// (workspace -> hex commit id), read through the repo at operation `head`.
+/// At most `path_limit` paths per pair.
+pub fn compute(
+    repo_loader: &RepoLoader,
+    head: &OperationId,
+    workspace_commits: &BTreeMap<String, String>,
+    path_limit: usize,
+) -> Result<Vec<PathOverlap>> {
+    let op = repo_loader
+        .load_operation(head)
+        .map_err(|e| anyhow!("load operation {}: {e}", head.hex()))?;
+    let repo = repo_loader
+        .load_at(&op)
+        .map_err(|e| anyhow!("load repo at {}: {e}", head.hex()))?;
+    let commits: Vec<(&String, CommitId)> = workspace_commits
+        .iter()
+        .filter_map(|(workspace_id, hex)| Some((workspace_id, CommitId::try_from_hex(hex)?)))
+        .collect();
+
+    let index = repo.index();
+    let mut touched = TouchedPaths::default();
+    let mut overlaps = Vec::new();
+    for (i, (workspace_a, commit_a)) in commits.iter().enumerate() {
+        for (workspace_b, commit_b) in &commits[i + 1..] {
+            if commit_a == commit_b
+                || index
+                    .is_ancestor(commit_a, commit_b)
+                    .map_err(|e| anyhow!("check ancestry: {e}"))?
+                || index
+                    .is_ancestor(commit_b, commit_a)
+                    .map_err(|e| anyhow!("check ancestry: {e}"))?
+            {
+                continue;
+            }
+            let base = index
+                .common_ancestors(
+                    std::slice::from_ref(commit_a),
+                    std::slice::from_ref(commit_b),
+                )
+                .map_err(|e| anyhow!("find common ancestors: {e}"))?;
+            let paths_a = touched.get(&repo, &base, commit_a)?.clone();
+            let paths_b = touched.get(&repo, &base, commit_b)?;
+            let mut shared = paths_a.intersection(paths_b);
+            let paths: Vec<String> = shared.by_ref().take(path_limit).cloned().collect();
+            if paths.is_empty() {
+                continue;
+            }
+            overlaps.push(PathOverlap {
+                workspace_a: workspace_a.to_string(),
+                workspace_b: workspace_b.to_string(),
+                paths,
+                paths_truncated: shared.next().is_some(),
+            });
+        }
+    }
+    Ok(overlaps)
+}
+
+/// Paths changed between a set of base commits and a working-copy commit,
+/// remembered since the same workspace shows up in several pairs.
+#[derive(Default)]
+struct TouchedPaths {
+    cache: HashMap<(Vec<CommitId>, CommitId), BTreeSet<String>>,
+}
+
+impl TouchedPaths {
+    fn get(
+        &mut self,
+        repo: &ReadonlyRepo,
+        base: &[CommitId],
+        commit_id: &CommitId,
+    ) -> Result<&BTreeSet<String>> {
+        let key = (base.to_vec(), commit_id.clone());
+        if !self.cache.contains_key(&key) {
+            let paths = diff_paths(repo, base, commit_id)?;
+            self.cache.insert(key.clone(), paths);
+        }
+        Ok(&self.cache[&key])
+    }
+}
+
+fn diff_paths(
+    repo: &ReadonlyRepo,
+    base: &[CommitId],
+    commit_id: &CommitId,
+) -> Result<BTreeSet<String>> {
+    let load = |id: &CommitId| {
+        repo.store()
+            .get_commit(id)
+            .map_err(|e| anyhow!("load commit {}: {e}", id.hex()))
+    };
+    let base_commits = base.iter().map(load).collect::<Result<Vec<_>>>()?;
+    let commit = load(commit_id)?;
+    let base_tree = pollster::block_on(merge_commit_trees(repo, &base_commits))
+        .map_err(|e| anyhow!("merge base trees: {e}"))?;
+    let tree = pollster::block_on(merge_commit_trees(repo, std::slice::from_ref(&commit)))
+        .map_err(|e| anyhow!("load commit tree: {e}"))?;
+    let mut paths = BTreeSet::new();
+    let mut diff = base_tree.diff_stream(&tree, &EverythingMatcher);
+    while let Some(entry) = pollster::block_on(diff.next()) {
+        paths.insert(entry.path.as_internal_file_string().to_string());
+    }
+    Ok(paths)
+}
+
+// ─── Client side ──────────────────────────────────────────────────────────────
+
+/// Commands after which [`warn_after_change`] checks for overlaps: the ones
+/// that finish a piece of work and publish it.
+const WARNED_COMMANDS: &[&str] = &["commit", "new", "describe", "squash"];
+
+/// Dispatch hook: after a successful [`WARNED_COMMANDS`] command, warn about
+/// paths another workspace has changed too. Like change-lock checks, anything that keeps
+/// us from asking (no tandem backend, an old or unreachable server) skips
+/// the warning.
+pub fn warn_after_change(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    dispatch: CliDispatchFn,
+) -> Result<(), CommandError> {
+    dispatch(ui, command)?;
+    let Some(name) = command.matches().subcommand_name() else {
+        return Ok(());
+    };
+    if !WARNED_COMMANDS.contains(&name) {
+        return Ok(());
+    }
+    let Ok(workspace_command) = command.workspace_helper_no_snapshot(ui) else {
+        return Ok(());
+    };
+    let Some(backend) = workspace_command
+        .repo()
+        .store()
+        .backend_impl::<TandemBackend>()
+    else {
+        return Ok(());
+    };
+    let client = backend.client();
+    if !client.supports_capability(RepoCapability::PathOverlaps) {
+        return Ok(());
+    }
+    let overlaps = match client.get_path_overlaps() {
+        Ok(overlaps) => overlaps,
+        Err(err) => {
+            tracing::warn!(error = %err, "cannot read path overlaps");
+            return Ok(());
+        }
+    };
+    let own = workspace_command.workspace_name().as_str();
+    let own_wc = workspace_command.get_wc_commit_id();
+    let view = workspace_command.repo().view();
+    for overlap in &overlaps {
+        let Some(other) = overlap.other(own) else {
+            continue;
+        };
+        // Whether the overlap is a real conflict, when the server can tell.
+        let other_wc = view.get_wc_commit_id(&WorkspaceNameBuf::from(other.to_owned()));
+        let conflicts = own_wc
+            .zip(other_wc)
+            .filter(|_| client.supports_capability(RepoCapability::MergePreview))
+            .and_then(|(own_wc, other_wc)| {
+                match client.preview_merge(own_wc.as_bytes(), other_wc.as_bytes(), 1) {
+                    Ok(preview) => Some(!preview.clean),
+                    Err(err) => {
+                        tracing::warn!(error = %err, "cannot preview merge");
+                        None
+                    }
+                }
+            });
+        if let Some(message) = format_warning(overlap, own, conflicts) {
+            writeln!(ui.warning_default(), "{message}")?;
+        }
+    }
+    Ok(())
+}
+
+/// "agent-b also modified src/auth.rs", or `None` if `own` is not one of
+/// the pair. `conflicts` says whether merging the two working copies would
+/// conflict, if known.
+fn format_warning(overlap: &PathOverlap, own: &str, conflicts: Option<bool>) -> Option<String> {
+    let other = overlap.other(own)?;
+    let shown = &overlap.paths[..overlap.paths.len().min(SHOWN_PATHS)];
+    let mut message = format!("{other} also modified {}", shown.join(", "));
+    let rest = overlap.paths.len() - shown.len();
+    if overlap.paths_truncated {
+        message.push_str(" and more");
+    } else if rest > 0 {
+        message.push_str(&format!(" and {rest} more"));
+    }
+    match conflicts {
+        Some(true) => message.push_str(" (the merge would conflict)"),
+        Some(false) => message.push_str(" (the merge is clean)"),
+        None => {}
+    }
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn overlap(paths: &[&str], truncated: bool) -> PathOverlap {
+        PathOverlap {
+            workspace_a: "agent-a".into(),
+            workspace_b: "agent-b".into(),
+            paths: paths.iter().map(|p| p.to_string()).collect(),
+            paths_truncated: truncated,
+        }
+    }
+
+    #[test]
+    fn warning_names_the_other_workspace() {
+        let overlap = overlap(&["src/auth.rs"], false);
+        assert_eq!(
+            format_warning(&overlap, "agent-a", None).as_deref(),
+            Some("agent-b also modified src/auth.rs")
+        );
+        assert_eq!(
+            format_warning(&overlap, "agent-b", None).as_deref(),
+            Some("agent-a also modified src/auth.rs")
+        );
+        assert_eq!(format_warning(&overlap, "human", None), None);
+    }
+
+    #[test]
+    fn warning_shortens_long_path_lists() {
+        let overlap = overlap(&["a", "b", "c", "d", "e"], false);
+        assert_eq!(
+            format_warning(&overlap, "agent-a", None).as_deref(),
+            Some("agent-b also modified a, b, c and 2 more")
+        );
+        let truncated = PathOverlap {
+            paths_truncated: true,
+            ..overlap
+        };
+        assert_eq!(
+            format_warning(&truncated, "agent-a", None).as_deref(),
+            Some("agent-b also modified a, b, c and more")
+        );
+    }
+
+    #[test]
+    fn warning_says_whether_the_merge_conflicts() {
+        let overlap = overlap(&["src/auth.rs"], false);
+        assert_eq!(
+            format_warning(&overlap, "agent-a", Some(true)).as_deref(),
+            Some("agent-b also modified src/auth.rs (the merge would conflict)")
+        );
+        assert_eq!(
+            format_warning(&overlap, "agent-a", Some(false)).as_deref(),
+            Some("agent-b also modified src/auth.rs (the merge is clean)")
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Protected-path ownership rules (CODEOWNERS-like), enforced on `updateOpHeads`.
+//!
+//! Rules file, one entry per line:
+//!
+//! ```text
+//! # groups: @name = member...
+//! @infra = ops-agent alice
+//! # rules: <path prefix> <owner>...   (owners are workspace names or @groups)
+//! infra/      @infra
+//! release/    @infra release-bot
+//! ```
+//!
+//! The last matching rule wins. Owners are matched against the workspace
+//! identity sent with `updateOpHeads`; tandem has no authentication yet, so
+//! this guards cooperating agents against mistakes, not hostile clients.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+#[derive(Debug, Default)]
+pub struct ProtectedPaths {
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    prefix: String,
+    owners: BTreeSet<String>,
+}
+
+/// A path modified by a workspace that does not own it.
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+    pub path: String,
+    pub owners: BTreeSet<String>,
+}
+
+impl ProtectedPaths {
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("cannot read protected paths file {}", path.display()))?;
+        Self::parse(&text)
+            .with_context(|| format!("invalid protected paths file {}", path.display()))
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut groups: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut raw_rules = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some((name, members)) = line.split_once('=') {
+                let name = name.trim();
+                if !name.starts_with('@') || name.len() == 1 {
+                    bail!("line {line_no}: group names must look like @name");
+                }
+                groups.insert(
+                    name.to_string(),
+                    members.split_whitespace().map(str::to_string).collect(),
+                );
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let prefix = normalize_prefix(fields.next().unwrap_or(""));
+            let owners: Vec<&str> = fields.collect();
+            if prefix.is_empty() {
+                bail!("line {line_no}: rule path must not be empty or `/`");
+            }
+            if owners.is_empty() {
+                bail!("line {line_no}: rule for {prefix:?} has no owners");
+            }
+            raw_rules.push((line_no, prefix, owners));
+        }
+
+        let mut rules = Vec::with_capacity(raw_rules.len());
+        for (line_no, prefix, owners) in raw_rules {
+            let mut expanded = BTreeSet::new();
+            for owner in owners {
+                if owner.starts_with('@') {
+                    let members = groups
+                        .get(owner)
+                        .with_context(|| format!("line {line_no}: unknown group {owner}"))?;
+                    expanded.extend(members.iter().cloned());
+                } else {
+                    expanded.insert(owner.to_string());
+                }
+            }
+            rules.push(Rule {
+                prefix,
+                owners: expanded,
+            });
+        }
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    fn owners_for(&self, path: &str) -> Option<&BTreeSet<String>> {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                path == rule.prefix
+                    || path
+                        .strip_prefix(&rule.prefix)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .map(|rule| &rule.owners)
+    }
+
+    /// Paths among `paths` that `workspace` may not modify. A missing
+    /// workspace identity owns nothing.
+    pub fn violations<'a>(
+        &self,
+        workspace: Option<&str>,
+        paths: impl IntoIterator<Item = &'a str>,
+    ) -> Vec<Violation> {
+        paths
+            .into_iter()
+            .filter_map(|path| {
+                let owners = self.owners_for(path)?;
+                let allowed = workspace.is_some_and(|ws| owners.contains(ws));
+                (!allowed).then(|| Violation {
+                    path: path.to_string(),
+                    owners: owners.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+fn normalize_prefix(raw: &str) -> String {
+    raw.trim_matches('/').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProtectedPaths;
+
+    const RULES: &str = "\
+# infra is owned by the ops group
+@infra = ops-agent alice
+/infra/ @infra
+release/ @infra release-bot
+infra/docs/ docs-agent   # later rules win
+";
+
+    #[test]
+    fn protected_paths_expand_groups_and_match_prefixes() {
+        let rules = ProtectedPaths::parse(RULES).expect("parse rules");
+
+        assert!(rules
+            .violations(Some("ops-agent"), ["infra/main.tf", "src/lib.rs"])
+            .is_empty());
+        assert!(rules
+            .violations(Some("release-bot"), ["release/notes.md"])
+            .is_empty());
+
+        let violations = rules.violations(Some("agent-a"), ["infra/main.tf", "infrastructure.md"]);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].path, "infra/main.tf");
+        assert!(violations[0].owners.contains("alice"));
+
+        let docs = rules.violations(Some("ops-agent"), ["infra/docs/readme.md"]);
+        assert_eq!(docs.len(), 1, "last matching rule should win");
+
+        assert_eq!(rules.violations(None, ["release/v1.txt"]).len(), 1);
+    }
+
+    #[test]
+    fn protected_paths_reject_malformed_rules() {
+        let err = ProtectedPaths::parse("infra/ @missing\n").expect_err("unknown group");
+        assert!(format!("{err:#}").contains("unknown group @missing"));
+
+        let err = ProtectedPaths::parse("infra/\n").expect_err("no owners");
+        assert!(format!("{err:#}").contains("has no owners"));
+    }
+}
@@ -0,0 +1,540 @@
+//! tandem proxy — multiplex many agent connections onto a few upstream ones.
+//!
+//! Agents connect to the proxy exactly as they would to `tandem serve`. Every
+//! Store call is forwarded to one of a small pool of upstream connections
+//! (round-robin), so a CI farm with hundreds of ephemeral workspaces holds
+//! only `--upstream-connections` sockets on the server.
+//!
+//! Objects, operations, and views are content-addressed and immutable, so
+//! their reads are answered from a shared in-memory cache after the first
+//! miss. Everything else — heads, writes, watches — is forwarded verbatim.
+
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, VecDeque};
+use std::rc::Rc;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use capnp::capability::Promise;
+use capnp_rpc::pry;
+use capnp_rpc::{rpc_twoparty_capnp, twoparty, RpcSystem};
+use tokio::sync::broadcast;
+use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+use crate::control;
+use crate::logging;
+use crate::rpc::{self, connect_stream};
+use crate::tandem_capnp::store;
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+pub struct ProxyOptions {
+    pub listen_addr: String,
+    pub upstream_addr: String,
+    pub upstream_connections: usize,
+    pub cache_bytes: usize,
+    pub log_level: String,
+    pub log_format: String,
+}
+
+pub async fn run_proxy(opts: ProxyOptions) -> Result<()> {
+    let (log_tx, _) = broadcast::channel::<control::LogEvent>(1024);
+    logging::init_tracing(&opts.log_level, &opts.log_format, log_tx)?;
+
+    // Fail fast on an unreachable or incompatible upstream.
+    rpc::connect_store_client(&opts.upstream_addr, &[])
+        .await
+        .with_context(|| format!("upstream {} is not usable", opts.upstream_addr))?;
+
+    let pool = Rc::new(UpstreamPool::new(
+        opts.upstream_addr.clone(),
+        opts.upstream_connections.max(1),
+    ));
+    for (index, slot) in pool.slots.iter().enumerate() {
+        tokio::task::spawn_local(maintain_upstream(
+            opts.upstream_addr.clone(),
+            Rc::clone(slot),
+            index,
+        ));
+    }
+
+    let proxy_client: store::Client = capnp_rpc::new_client(ProxyStore {
+        pool,
+        cache: Rc::new(ObjectCache::new(opts.cache_bytes)),
+    });
+
+    let listener = tokio::net::TcpListener::bind(&opts.listen_addr)
+        .await
+        .with_context(|| format!("failed to bind {}", opts.listen_addr))?;
+    tracing::info!(
+        listen_addr = %listener.local_addr()?,
+        upstream = %opts.upstream_addr,
+        upstream_connections = opts.upstream_connections,
+        cache_bytes = opts.cache_bytes,
+        "tandem proxy listening"
+    );
+
+    let mut next_conn_id = 1u64;
+    loop {
+        tokio::select! {
+            result = listener.accept() => {
+                let (stream, peer) = result?;
+                stream.set_nodelay(true).ok();
+                let conn_id = next_conn_id;
+                next_conn_id += 1;
+                tracing::debug!(conn_id, peer = %peer, "agent connected");
+
+                let (reader, writer) = stream.into_split();
+                let network = twoparty::VatNetwork::new(
+                    reader.compat(),
+                    writer.compat_write(),
+                    rpc_twoparty_capnp::Side::Server,
+                    crate::limits::reader_options(),
+                );
+                let rpc_system =
+                    RpcSystem::new(Box::new(network), Some(proxy_client.clone().client));
+                tokio::task::spawn_local(async move {
+                    if let Err(err) = rpc_system.await {
+                        tracing::debug!(conn_id, error = %err, "agent connection error");
+                    }
+                    tracing::debug!(conn_id, peer = %peer, "agent disconnected");
+                });
+            }
+            _ = tokio::signal::ctrl_c() => {
+                tracing::info!("tandem proxy stopped");
+                return Ok(());
+            }
+        }
+    }
+}
+
+// ─── Upstream pool ────────────────────────────────────────────────────────────
+
+type UpstreamSlot = Rc<RefCell<Option<store::Client>>>;
+
+struct UpstreamPool {
+    addr: String,
+    slots: Vec<UpstreamSlot>,
+    next: Cell<usize>,
+}
+
+impl UpstreamPool {
+    fn new(addr: String, connections: usize) -> Self {
+        Self {
+            addr,
+            slots: (0..connections)
+                .map(|_| Rc::new(RefCell::new(None)))
+                .collect(),
+            next: Cell::new(0),
+        }
+    }
+
+    /// Next connected upstream, round-robin, skipping slots that are
+    /// reconnecting.
+    fn client(&self) -> Result<store::Client, capnp::Error> {
+        for _ in 0..self.slots.len() {
+            let index = self.next.get();
+            self.next.set((index + 1) % self.slots.len());
+            if let Some(client) = self.slots[index].borrow().clone() {
+                return Ok(client);
+            }
+        }
+        Err(capnp::Error::disconnected(format!(
+            "proxy has no live upstream connection to {}",
+            self.addr
+        )))
+    }
+}
+
+async fn maintain_upstream(addr: String, slot: UpstreamSlot, index: usize) {
+    loop {
+        match connect_stream(&addr).await {
+            Ok(stream) => {
+                let (reader, writer) = stream.into_split();
+                let network = twoparty::VatNetwork::new(
+                    reader.compat(),
+                    writer.compat_write(),
+                    rpc_twoparty_capnp::Side::Client,
+                    crate::limits::reader_options(),
+                );
+                let mut rpc_system = RpcSystem::new(Box::new(network), None);
+                let client: store::Client = rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+                *slot.borrow_mut() = Some(client);
+                tracing::info!(upstream = %addr, slot = index, "upstream connected");
+
+                let result = rpc_system.await;
+                *slot.borrow_mut() = None;
+                match result {
+                    Ok(()) => tracing::warn!(upstream = %addr, slot = index, "upstream closed"),
+                    Err(err) => {
+                        tracing::warn!(upstream = %addr, slot = index, error = %err, "upstream failed")
+                    }
+                }
+            }
+            Err(err) => {
+                tracing::warn!(upstream = %addr, slot = index, error = %format!("{err:#}"), "upstream connect failed")
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+// ─── Shared read cache ────────────────────────────────────────────────────────
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum CacheKey {
+    Object(u16, Vec<u8>),
+    Operation(Vec<u8>),
+    View(Vec<u8>),
+}
+
+/// Byte-bounded cache of immutable reads; evicts oldest entries first.
+struct ObjectCache {
+    capacity: usize,
+    entries: RefCell<HashMap<CacheKey, Rc<[u8]>>>,
+    order: RefCell<VecDeque<CacheKey>>,
+    bytes: Cell<usize>,
+    hits: Cell<u64>,
+    misses: Cell<u64>,
+}
+
+impl ObjectCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: RefCell::new(HashMap::new()),
+            order: RefCell::new(VecDeque::new()),
+            bytes: Cell::new(0),
+            hits: Cell::new(0),
+            misses: Cell::new(0),
+        }
+    }
+
+    fn get(&self, key: &CacheKey) -> Option<Rc<[u8]>> {
+        let found = self.entries.borrow().get(key).cloned();
+        if found.is_some() {
+            self.hits.set(self.hits.get() + 1);
+        } else {
+            self.misses.set(self.misses.get() + 1);
+        }
+        found
+    }
+
+    fn insert(&self, key: CacheKey, data: &[u8]) {
+        if data.len() > self.capacity {
+            return;
+        }
+        let mut entries = self.entries.borrow_mut();
+        let mut order = self.order.borrow_mut();
+        if entries.contains_key(&key) {
+            return;
+        }
+        while self.bytes.get() + data.len() > self.capacity {
+            let Some(oldest) = order.pop_front() else {
+                break;
+            };
+            if let Some(evicted) = entries.remove(&oldest) {
+                self.bytes.set(self.bytes.get() - evicted.len());
+            }
+        }
+        self.bytes.set(self.bytes.get() + data.len());
+        order.push_back(key.clone());
+        entries.insert(key, Rc::from(data));
+
+        let lookups = self.hits.get() + self.misses.get();
+        if lookups.is_multiple_of(1000) {
+            tracing::debug!(
+                hits = self.hits.get(),
+                misses = self.misses.get(),
+                entries = entries.len(),
+                bytes = self.bytes.get(),
+                "proxy cache stats"
+            );
+        }
+    }
+}
+
+// ─── Store implementation ─────────────────────────────────────────────────────
+
+struct ProxyStore {
+    pool: Rc<UpstreamPool>,
+    cache: Rc<ObjectCache>,
+}
+
+/// Forward a call verbatim (including capabilities) to an upstream connection.
+macro_rules! forward {
+    ($self:ident, $request:ident, $params:ident, $results:ident) => {{
+        let upstream = pry!($self.pool.client());
+        let mut request = upstream.$request();
+        pry!(request.set(pry!($params.get())));
+        Promise::from_future(async move {
+            let response = request.send().promise.await?;
+            $results.set(response.get()?)?;
+            Ok(())
+        })
+    }};
+}
+
+/// Answer a content-addressed read from the cache, or forward it and cache
+/// the `data` field of the response.
+macro_rules! cached_read {
+    ($self:ident, $key:expr, $request:ident, $params:ident, $results:ident) => {{
+        let key = $key;
+        if let Some(data) = $self.cache.get(&key) {
+            $results.get().set_data(&data);
+            return Promise::ok(());
+        }
+        let upstream = pry!($self.pool.client());
+        let mut request = upstream.$request();
+        pry!(request.set(pry!($params.get())));
+        let cache = Rc::clone(&$self.cache);
+        Promise::from_future(async move {
+            let response = request.send().promise.await?;
+            let data = response.get()?.get_data()?;
+            cache.insert(key, data);
+            $results.get().set_data(data);
+            Ok(())
+        })
+    }};
+}
+
+impl store::Server for ProxyStore {
+    fn get_repo_info(
+        &mut self,
+        params: store::GetRepoInfoParams,
+        mut results: store::GetRepoInfoResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_repo_info_request, params, results)
+    }
+
+    fn get_object(
+        &mut self,
+        params: store::GetObjectParams,
+        mut results: store::GetObjectResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = pry!(params.get());
+        let key = CacheKey::Object(
+            pry!(reader.get_kind()) as u16,
+            pry!(reader.get_id()).to_vec(),
+        );
+        cached_read!(self, key, get_object_request, params, results)
+    }
+
+    fn put_object(
+        &mut self,
+        params: store::PutObjectParams,
+        mut results: store::PutObjectResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, put_object_request, params, results)
+    }
+
+    fn get_operation(
+        &mut self,
+        params: store::GetOperationParams,
+        mut results: store::GetOperationResults,
+    ) -> Promise<(), capnp::Error> {
+        let key = CacheKey::Operation(pry!(pry!(params.get()).get_id()).to_vec());
+        cached_read!(self, key, get_operation_request, params, results)
+    }
+
+    fn put_operation(
+        &mut self,
+        params: store::PutOperationParams,
+        mut results: store::PutOperationResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, put_operation_request, params, results)
+    }
+
+    fn get_view(
+        &mut self,
+        params: store::GetViewParams,
+        mut results: store::GetViewResults,
+    ) -> Promise<(), capnp::Error> {
+        let key = CacheKey::View(pry!(pry!(params.get()).get_id()).to_vec());
+        cached_read!(self, key, get_view_request, params, results)
+    }
+
+    fn put_view(
+        &mut self,
+        params: store::PutViewParams,
+        mut results: store::PutViewResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, put_view_request, params, results)
+    }
+
+    fn resolve_operation_id_prefix(
+        &mut self,
+        params: store::ResolveOperationIdPrefixParams,
+        mut results: store::ResolveOperationIdPrefixResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, resolve_operation_id_prefix_request, params, results)
+    }
+
+    fn get_heads(
+        &mut self,
+        params: store::GetHeadsParams,
+        mut results: store::GetHeadsResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_heads_request, params, results)
+    }
+
+    fn update_op_heads(
+        &mut self,
+        params: store::UpdateOpHeadsParams,
+        mut results: store::UpdateOpHeadsResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, update_op_heads_request, params, results)
+    }
+
+    fn watch_heads(
+        &mut self,
+        params: store::WatchHeadsParams,
+        mut results: store::WatchHeadsResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, watch_heads_request, params, results)
+    }
+
+    fn get_heads_snapshot(
+        &mut self,
+        params: store::GetHeadsSnapshotParams,
+        mut results: store::GetHeadsSnapshotResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_heads_snapshot_request, params, results)
+    }
+
+    fn get_related_copies(
+        &mut self,
+        params: store::GetRelatedCopiesParams,
+        mut results: store::GetRelatedCopiesResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_related_copies_request, params, results)
+    }
+
+    fn get_operation_tags(
+        &mut self,
+        params: store::GetOperationTagsParams,
+        mut results: store::GetOperationTagsResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_operation_tags_request, params, results)
+    }
+
+    fn get_heads_history(
+        &mut self,
+        params: store::GetHeadsHistoryParams,
+        mut results: store::GetHeadsHistoryResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_heads_history_request, params, results)
+    }
+
+    fn begin_snapshot(
+        &mut self,
+        params: store::BeginSnapshotParams,
+        mut results: store::BeginSnapshotResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, begin_snapshot_request, params, results)
+    }
+
+    fn end_snapshot(
+        &mut self,
+        params: store::EndSnapshotParams,
+        mut results: store::EndSnapshotResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, end_snapshot_request, params, results)
+    }
+
+    fn import_heads(
+        &mut self,
+        params: store::ImportHeadsParams,
+        mut results: store::ImportHeadsResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, import_heads_request, params, results)
+    }
+
+    fn set_redirect(
+        &mut self,
+        params: store::SetRedirectParams,
+        mut results: store::SetRedirectResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, set_redirect_request, params, results)
+    }
+
+    fn get_view_delta(
+        &mut self,
+        params: store::GetViewDeltaParams,
+        mut results: store::GetViewDeltaResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_view_delta_request, params, results)
+    }
+
+    fn put_view_delta(
+        &mut self,
+        params: store::PutViewDeltaParams,
+        mut results: store::PutViewDeltaResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, put_view_delta_request, params, results)
+    }
+
+    fn get_heads_version(
+        &mut self,
+        params: store::GetHeadsVersionParams,
+        mut results: store::GetHeadsVersionResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_heads_version_request, params, results)
+    }
+
+    fn get_tree_entries(
+        &mut self,
+        params: store::GetTreeEntriesParams,
+        mut results: store::GetTreeEntriesResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_tree_entries_request, params, results)
+    }
+
+    fn search_commits(
+        &mut self,
+        params: store::SearchCommitsParams,
+        mut results: store::SearchCommitsResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, search_commits_request, params, results)
+    }
+
+    fn lock_change(
+        &mut self,
+        params: store::LockChangeParams,
+        mut results: store::LockChangeResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, lock_change_request, params, results)
+    }
+
+    fn unlock_change(
+        &mut self,
+        params: store::UnlockChangeParams,
+        mut results: store::UnlockChangeResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, unlock_change_request, params, results)
+    }
+
+    fn get_change_locks(
+        &mut self,
+        params: store::GetChangeLocksParams,
+        mut results: store::GetChangeLocksResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_change_locks_request, params, results)
+    }
+
+    fn preview_merge(
+        &mut self,
+        params: store::PreviewMergeParams,
+        mut results: store::PreviewMergeResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, preview_merge_request, params, results)
+    }
+
+    fn get_commit_graph(
+        &mut self,
+        params: store::GetCommitGraphParams,
+        mut results: store::GetCommitGraphResults,
+    ) -> Promise<(), capnp::Error> {
+        forward!(self, get_commit_graph_request, params, results)
+    }
+}
@@ -0,0 +1,409 @@
+//! tandem ps — live view of workspace activity on a tandem server.
+//!
+//! Reads `getHeads` for the workspace → operation mapping, then resolves each
+//! workspace's working-copy commit through the operation's view. Ahead/behind
+//! counts are measured against the `integration` bookmark when it exists.
+
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Context, Result};
+use jj_lib::backend::CommitId;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::ref_name::{RefName, WorkspaceNameBuf};
+use prost::Message as _;
+
+use crate::proto_convert;
+use crate::rpc::TandemClient;
+
+const KIND_COMMIT: u16 = 0;
+const INTEGRATION_BOOKMARK: &str = "integration";
+/// Commit reads kept in flight while walking ancestry.
+const IN_FLIGHT: usize = 64;
+const WATCH_INTERVAL: Duration = Duration::from_secs(2);
+
+pub struct WorkspaceRow {
+    pub workspace: String,
+    pub change: String,
+    pub description: String,
+    pub committed_at_ms: Option<i64>,
+    pub ahead_behind: Option<(usize, usize)>,
+}
+
+// ─── Public entry point ───────────────────────────────────────────────────────
+
+pub fn run_ps(server_addr: &str, watch: bool) -> Result<()> {
+    let client = TandemClient::connect(server_addr)
+        .with_context(|| format!("cannot list workspaces on {server_addr}"))?;
+
+    let mut ancestry = AncestryCache::default();
+    if !watch {
+        let rows = collect_rows(&client, &mut ancestry)?;
+        print!("{}", render_table(&rows));
+        return Ok(());
+    }
+
+    loop {
+        let rows = collect_rows(&client, &mut ancestry)?;
+        // Clear the screen and home the cursor before each redraw.
+        print!("\x1b[2J\x1b[H");
+        println!(
+            "tandem ps — {server_addr} (refreshing every {}s)",
+            WATCH_INTERVAL.as_secs()
+        );
+        println!();
+        print!("{}", render_table(&rows));
+        std::thread::sleep(WATCH_INTERVAL);
+    }
+}
+
+// ─── Data collection ──────────────────────────────────────────────────────────
+
+pub fn collect_rows(
+    client: &TandemClient,
+    ancestry: &mut AncestryCache,
+) -> Result<Vec<WorkspaceRow>> {
+    let state = client.get_heads_state()?;
+    let root_commit_id = CommitId::new(client.repo_info().root_commit_id.clone());
+
+    let integration_name: &RefName = INTEGRATION_BOOKMARK.as_ref();
+    let integration = match state.heads.first() {
+        Some(op_id) => read_view(client, op_id)?
+            .local_bookmarks
+            .get(integration_name)
+            .and_then(|target| target.as_normal().cloned()),
+        None => None,
+    };
+
+    let mut rows = Vec::with_capacity(state.workspace_heads.len());
+    for (workspace, op_id) in &state.workspace_heads {
+        let view = read_view(client, op_id)
+            .with_context(|| format!("reading view for workspace {workspace}"))?;
+        let Some(wc_commit_id) = view
+            .wc_commit_ids
+            .get(&WorkspaceNameBuf::from(workspace.clone()))
+            .cloned()
+        else {
+            continue;
+        };
+        let commit = read_commit(client, &wc_commit_id)?;
+        let ahead_behind = match &integration {
+            Some(integration) => Some(ahead_behind(
+                ancestry,
+                &root_commit_id,
+                &wc_commit_id,
+                integration,
+                |ids| fetch_commits(client, ids),
+            )?),
+            None => None,
+        };
+        rows.push(WorkspaceRow {
+            workspace: workspace.clone(),
+            change: jj_lib::hex_util::encode_reverse_hex(&commit.change_id)
+                .chars()
+                .take(12)
+                .collect(),
+            description: commit.description.lines().next().unwrap_or("").to_string(),
+            committed_at_ms: commit
+                .committer
+                .and_then(|sig| sig.timestamp)
+                .map(|ts| ts.millis_since_epoch),
+            ahead_behind,
+        });
+    }
+    Ok(rows)
+}
+
+pub fn read_view(client: &TandemClient, op_id: &[u8]) -> Result<jj_lib::op_store::View> {
+    let op_bytes = client.get_operation(op_id)?;
+    let operation = jj_lib::protos::simple_op_store::Operation::decode(&*op_bytes)
+        .context("decoding operation")?;
+    let view_bytes = client.get_view(&operation.view_id)?;
+    let view =
+        jj_lib::protos::simple_op_store::View::decode(&*view_bytes).context("decoding view")?;
+    proto_convert::view_from_proto(view)
+}
+
+pub fn read_commit(
+    client: &TandemClient,
+    id: &CommitId,
+) -> Result<jj_lib::protos::simple_store::Commit> {
+    let data = client.get_object(KIND_COMMIT, id.as_bytes())?;
+    jj_lib::protos::simple_store::Commit::decode(&*data).context("decoding commit")
+}
+
+/// Commits `ps` has already fetched, kept across `--watch` refreshes so
+/// each redraw only reads the commits that are new since the last one.
+#[derive(Default)]
+pub struct AncestryCache {
+    commits: HashMap<CommitId, CachedCommit>,
+}
+
+struct CachedCommit {
+    parents: Vec<CommitId>,
+    committed_at_ms: i64,
+}
+
+impl CachedCommit {
+    fn decode(data: &[u8]) -> Result<Self> {
+        let commit =
+            jj_lib::protos::simple_store::Commit::decode(data).context("decoding commit")?;
+        Ok(CachedCommit {
+            parents: commit.parents.into_iter().map(CommitId::new).collect(),
+            committed_at_ms: commit
+                .committer
+                .and_then(|sig| sig.timestamp)
+                .map_or(0, |ts| ts.millis_since_epoch),
+        })
+    }
+}
+
+/// Fetch `ids` with up to [`IN_FLIGHT`] requests outstanding.
+fn fetch_commits(client: &TandemClient, ids: &[CommitId]) -> Result<Vec<CachedCommit>> {
+    let mut commits = Vec::with_capacity(ids.len());
+    for chunk in ids.chunks(IN_FLIGHT) {
+        let pending = chunk
+            .iter()
+            .map(|id| client.begin_get_object(KIND_COMMIT, id.as_bytes()))
+            .collect::<Result<Vec<_>>>()?;
+        for reply in pending {
+            let data = reply.recv().map_err(|_| anyhow!("RPC reply dropped"))??;
+            commits.push(CachedCommit::decode(&data)?);
+        }
+    }
+    Ok(commits)
+}
+
+const FROM: u8 = 1;
+const TO: u8 = 2;
+
+/// Count commits reachable from `from` but not `to` (ahead) and vice versa
+/// (behind). Both sides are walked together, newest commit first, and the
+/// walk stops once every commit left to visit is reachable from both, so
+/// only the commits down to the merge base are read. `fetch` reads the
+/// commits missing from `cache`, a whole frontier at a time.
+fn ahead_behind(
+    cache: &mut AncestryCache,
+    root_commit_id: &CommitId,
+    from: &CommitId,
+    to: &CommitId,
+    mut fetch: impl FnMut(&[CommitId]) -> Result<Vec<CachedCommit>>,
+) -> Result<(usize, usize)> {
+    let mut flags: HashMap<CommitId, u8> = HashMap::new();
+    let mut queue: BinaryHeap<(i64, CommitId)> = BinaryHeap::new();
+    // Commits whose flags changed since they were last queued.
+    let mut dirty = Vec::new();
+    let mark = |id: &CommitId, flag: u8, flags: &mut HashMap<CommitId, u8>| {
+        // The root is in every history; leaving it out keeps it uncounted.
+        if id == root_commit_id {
+            return false;
+        }
+        let entry = flags.entry(id.clone()).or_default();
+        let changed = *entry & flag != flag;
+        *entry |= flag;
+        changed
+    };
+    for (id, flag) in [(from, FROM), (to, TO)] {
+        if mark(id, flag, &mut flags) {
+            dirty.push(id.clone());
+        }
+    }
+
+    loop {
+        if !dirty.is_empty() {
+            let missing: Vec<CommitId> = dirty
+                .iter()
+                .filter(|id| !cache.commits.contains_key(*id))
+                .cloned()
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            if !missing.is_empty() {
+                let fetched = fetch(&missing)?;
+                cache.commits.extend(missing.into_iter().zip(fetched));
+            }
+            for id in dirty.drain(..) {
+                queue.push((cache.commits[&id].committed_at_ms, id));
+            }
+        }
+        if queue.iter().all(|(_, id)| flags[id] == FROM | TO) {
+            break;
+        }
+        let Some((_, id)) = queue.pop() else {
+            break;
+        };
+        let flag = flags[&id];
+        for parent in &cache.commits[&id].parents {
+            if mark(parent, flag, &mut flags) {
+                dirty.push(parent.clone());
+            }
+        }
+    }
+
+    let count = |side: u8| flags.values().filter(|flag| **flag == side).count();
+    Ok((count(FROM), count(TO)))
+}
+
+// ─── Rendering ────────────────────────────────────────────────────────────────
+
+fn render_table(rows: &[WorkspaceRow]) -> String {
+    if rows.is_empty() {
+        return "no workspaces have published operations yet\n".to_string();
+    }
+
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    let header = [
+        "WORKSPACE",
+        "CHANGE",
+        "LAST COMMIT",
+        "AHEAD",
+        "BEHIND",
+        "DESCRIPTION",
+    ];
+    let mut table: Vec<[String; 6]> = vec![header.map(str::to_string)];
+    for row in rows {
+        let (ahead, behind) = match row.ahead_behind {
+            Some((ahead, behind)) => (ahead.to_string(), behind.to_string()),
+            None => ("-".to_string(), "-".to_string()),
+        };
+        let description = if row.description.is_empty() {
+            "(no description set)".to_string()
+        } else {
+            row.description.clone()
+        };
+        table.push([
+            row.workspace.clone(),
+            row.change.clone(),
+            row.committed_at_ms
+                .map(|ms| format_age(now_ms.saturating_sub(ms)))
+                .unwrap_or_else(|| "-".to_string()),
+            ahead,
+            behind,
+            description,
+        ]);
+    }
+
+    let mut widths = [0usize; 6];
+    for line in &table {
+        for (width, cell) in widths.iter_mut().zip(line) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut out = String::new();
+    for line in &table {
+        let cells: Vec<String> = line
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (cell, width))| {
+                if i == line.len() - 1 {
+                    cell.clone()
+                } else {
+                    format!("{cell:<width$}")
+                }
+            })
+            .collect();
+        out.push_str(cells.join("  ").trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+pub fn format_age(elapsed_ms: i64) -> String {
+    let secs = elapsed_ms.max(0) / 1000;
+    if secs >= 86400 {
+        format!("{}d ago", secs / 86400)
+    } else if secs >= 3600 {
+        format!("{}h ago", secs / 3600)
+    } else if secs >= 60 {
+        format!("{}m ago", secs / 60)
+    } else {
+        format!("{secs}s ago")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(n: u32) -> CommitId {
+        CommitId::new(n.to_be_bytes().to_vec())
+    }
+
+    /// Commit `n` has timestamp `n`; `0` is the root.
+    fn graph(edges: &[(u32, &[u32])]) -> HashMap<CommitId, Vec<CommitId>> {
+        edges
+            .iter()
+            .map(|(n, parents)| (id(*n), parents.iter().copied().map(id).collect()))
+            .collect()
+    }
+
+    fn count(
+        graph: &HashMap<CommitId, Vec<CommitId>>,
+        from: u32,
+        to: u32,
+    ) -> ((usize, usize), usize) {
+        let mut fetched = 0;
+        let counts = ahead_behind(
+            &mut AncestryCache::default(),
+            &id(0),
+            &id(from),
+            &id(to),
+            |ids| {
+                fetched += ids.len();
+                Ok(ids
+                    .iter()
+                    .map(|commit| CachedCommit {
+                        parents: graph[commit].clone(),
+                        committed_at_ms: i64::from(u32::from_be_bytes(
+                            commit.as_bytes().try_into().unwrap(),
+                        )),
+                    })
+                    .collect())
+            },
+        )
+        .unwrap();
+        (counts, fetched)
+    }
+
+    #[test]
+    fn counts_past_a_deep_history_by_stopping_at_the_merge_base() {
+        // 1..=5000 in a line, then 5001 and 5002 branching off 4990.
+        let mut edges: Vec<(u32, Vec<u32>)> = (1..=5000).map(|n| (n, vec![n - 1])).collect();
+        edges.push((5001, vec![4990]));
+        edges.push((5002, vec![5001]));
+        let edges: Vec<(u32, &[u32])> = edges.iter().map(|(n, p)| (*n, p.as_slice())).collect();
+        let graph = graph(&edges);
+
+        let ((ahead, behind), fetched) = count(&graph, 5002, 5000);
+        assert_eq!((ahead, behind), (2, 10));
+        assert!(fetched < 20, "walked {fetched} commits");
+
+        assert_eq!(count(&graph, 5000, 5000).0, (0, 0));
+        assert_eq!(count(&graph, 4000, 5000).0, (0, 1000));
+        assert_eq!(count(&graph, 5000, 1).0, (4999, 0));
+    }
+
+    #[test]
+    fn a_merge_counts_each_commit_once() {
+        //   4
+        //  / \
+        // 2   3   5
+        //  \ /   /
+        //   1 --'
+        let graph = graph(&[(1, &[0]), (2, &[1]), (3, &[1]), (4, &[2, 3]), (5, &[1])]);
+        assert_eq!(count(&graph, 4, 5).0, (3, 1));
+        assert_eq!(count(&graph, 2, 4).0, (0, 2));
+    }
+
+    #[test]
+    fn clock_skew_does_not_miscount() {
+        // 3 claims to be older than its parent 2, which is on both sides.
+        let graph = graph(&[(1, &[0]), (10, &[1]), (3, &[10]), (20, &[10])]);
+        assert_eq!(count(&graph, 3, 20).0, (1, 1));
+    }
+}
@@ -0,0 +1,334 @@
+//! Read replicas — `tandem serve --follow <primary>` mirrors a primary server.
+//!
+//! The follower subscribes to the primary's `watchHeads` and, on every
+//! notification, copies the operations, views, and objects that are missing
+//! locally, then adopts the primary's op heads. Objects are written parents
+//! first, and heads move last, so local readers never see a dangling id.
+//!
+//! The protocol has no pack transfer yet: each missing object is one
+//! `getObject` call. Only the first sync after a restart pays for history.
+
+use std::collections::{BTreeMap, HashSet};
+use std::rc::Rc;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use capnp::capability::Promise;
+use capnp_rpc::pry;
+use jj_lib::backend::{CommitId, TreeId, TreeValue};
+use jj_lib::hex_util::encode_hex;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::{RefTarget, View};
+use prost::Message as _;
+
+use crate::proto_convert;
+use crate::rpc::{self, HeadsState, RepoCapability};
+use crate::tandem_capnp::{head_watcher, store};
+
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+const HELD_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+#[derive(Debug, Clone, Copy)]
+pub enum ObjectKind {
+    Commit,
+    Tree,
+    File,
+    Symlink,
+}
+
+impl ObjectKind {
+    /// Kind name used by the server's object store.
+    pub fn name(self) -> &'static str {
+        match self {
+            ObjectKind::Commit => "commit",
+            ObjectKind::Tree => "tree",
+            ObjectKind::File => "file",
+            ObjectKind::Symlink => "symlink",
+        }
+    }
+
+    /// Discriminant used on the wire.
+    pub fn code(self) -> u16 {
+        match self {
+            ObjectKind::Commit => 0,
+            ObjectKind::Tree => 1,
+            ObjectKind::File => 2,
+            ObjectKind::Symlink => 3,
+        }
+    }
+}
+
+/// Local side of a replica. The follower only writes through this.
+pub trait ReplicaSink {
+    fn has_operation(&self, id: &[u8]) -> bool;
+    fn has_view(&self, id: &[u8]) -> bool;
+    fn has_object(&self, kind: ObjectKind, id: &[u8]) -> bool;
+    fn put_operation(&self, data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>>;
+    fn put_view(&self, data: &[u8]) -> Result<Vec<u8>>;
+    fn put_object(&self, kind: ObjectKind, data: &[u8]) -> Result<Vec<u8>>;
+    /// Replace the local op heads with the primary's and notify watchers.
+    fn adopt_heads(&self, primary: &HeadsState) -> Result<()>;
+    /// True while a `beginSnapshot` hold pins the local heads.
+    fn heads_held(&self) -> bool;
+}
+
+// ─── Follower loop ────────────────────────────────────────────────────────────
+
+/// Follow `primary` until the process exits, reconnecting after failures.
+pub async fn follow_primary<S: ReplicaSink>(primary: String, sink: Rc<S>) {
+    loop {
+        match follow_once(&primary, sink.as_ref()).await {
+            Ok(()) => tracing::warn!(primary = %primary, "primary closed the watch; reconnecting"),
+            Err(err) => {
+                tracing::warn!(primary = %primary, error = %format!("{err:#}"), "replication failed; reconnecting")
+            }
+        }
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+struct SyncTrigger {
+    tx: tokio::sync::mpsc::UnboundedSender<()>,
+}
+
+impl head_watcher::Server for SyncTrigger {
+    fn notify(
+        &mut self,
+        _params: head_watcher::NotifyParams,
+        _results: head_watcher::NotifyResults,
+    ) -> Promise<(), capnp::Error> {
+        let _ = self.tx.send(());
+        Promise::ok(())
+    }
+
+    fn shutting_down(
+        &mut self,
+        params: head_watcher::ShuttingDownParams,
+        _results: head_watcher::ShuttingDownResults,
+    ) -> Promise<(), capnp::Error> {
+        let retry_after_ms = pry!(params.get()).get_retry_after_ms();
+        tracing::info!(retry_after_ms, "primary is shutting down");
+        Promise::ok(())
+    }
+}
+
+async fn follow_once<S: ReplicaSink>(primary: &str, sink: &S) -> Result<()> {
+    let (client, repo_info) =
+        rpc::connect_store_client(primary, &[RepoCapability::WatchHeads]).await?;
+    let root_op_id = repo_info.root_operation_id;
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let watcher: head_watcher::Client = capnp_rpc::new_client(SyncTrigger { tx });
+    let mut request = client.watch_heads_request();
+    {
+        let mut params = request.get();
+        params.set_watcher(watcher);
+        params.set_after_version(0);
+    }
+    request.send().promise.await?;
+    tracing::info!(primary = %primary, "following primary");
+
+    // A quiet primary sends no notifications, so catch up explicitly first.
+    sync_from_primary(&client, sink, &root_op_id).await?;
+    while rx.recv().await.is_some() {
+        // Coalesce bursts: one sync covers every notification queued so far.
+        while rx.try_recv().is_ok() {}
+        sync_from_primary(&client, sink, &root_op_id).await?;
+    }
+    Ok(())
+}
+
+/// Copy everything reachable from `client`'s heads into `sink`, then hand
+/// it those heads. Returns the heads state that was adopted.
+pub async fn sync_from_primary<S: ReplicaSink>(
+    client: &store::Client,
+    sink: &S,
+    root_op_id: &[u8],
+) -> Result<HeadsState> {
+    let state = rpc::do_get_heads(client).await?;
+
+    let mut pending = state.heads.clone();
+    let mut seen = HashSet::new();
+    let mut operations = Vec::new();
+    while let Some(id) = pending.pop() {
+        // The root operation is implicit in every op store and never stored.
+        if id == root_op_id || !seen.insert(id.clone()) || sink.has_operation(&id) {
+            continue;
+        }
+        let data = rpc::do_get_operation(client, &id).await?;
+        let proto = jj_lib::protos::simple_op_store::Operation::decode(&*data)
+            .context("decode replicated operation")?;
+        copy_view(client, sink, &proto.view_id).await?;
+        pending.extend(proto.parents);
+        operations.push((id, data));
+    }
+
+    if !operations.is_empty() {
+        let tags: BTreeMap<Vec<u8>, BTreeMap<String, String>> =
+            rpc::do_get_operation_tags(client, &BTreeMap::new())
+                .await?
+                .into_iter()
+                .map(|op| (op.id, op.tags))
+                .collect();
+        let no_tags = BTreeMap::new();
+        for (id, data) in operations.iter().rev() {
+            let written = sink.put_operation(data, tags.get(id).unwrap_or(&no_tags))?;
+            ensure_same_id("operation", id, &written)?;
+        }
+    }
+
+    // Objects may land during a snapshot hold; heads wait until it ends.
+    while sink.heads_held() {
+        tokio::time::sleep(HELD_POLL_INTERVAL).await;
+    }
+    sink.adopt_heads(&state)?;
+    if !operations.is_empty() {
+        tracing::info!(
+            version = state.version,
+            operations = operations.len(),
+            "replicated operations from primary"
+        );
+    }
+    Ok(state)
+}
+
+async fn copy_view<S: ReplicaSink>(client: &store::Client, sink: &S, id: &[u8]) -> Result<()> {
+    if sink.has_view(id) {
+        return Ok(());
+    }
+    let data = rpc::do_get_view(client, id).await?;
+    let proto =
+        jj_lib::protos::simple_op_store::View::decode(&*data).context("decode replicated view")?;
+    let view = proto_convert::view_from_proto(proto)?;
+    copy_commits(client, sink, view_commit_ids(&view)).await?;
+    let written = sink.put_view(&data)?;
+    ensure_same_id("view", id, &written)
+}
+
+/// Every commit a view can name: heads, working copies, and ref targets.
+fn view_commit_ids(view: &View) -> Vec<CommitId> {
+    let mut ids: Vec<CommitId> = view.head_ids.iter().cloned().collect();
+    ids.extend(view.wc_commit_ids.values().cloned());
+    let mut targets: Vec<&RefTarget> = view
+        .local_bookmarks
+        .values()
+        .chain(view.local_tags.values())
+        .chain(view.git_refs.values())
+        .chain(std::iter::once(&view.git_head))
+        .collect();
+    for remote in view.remote_views.values() {
+        targets.extend(remote.bookmarks.values().map(|r| &r.target));
+        targets.extend(remote.tags.values().map(|r| &r.target));
+    }
+    for target in targets {
+        ids.extend(target.added_ids().cloned());
+        ids.extend(target.removed_ids().cloned());
+    }
+    ids
+}
+
+/// Depth-first walk that writes a node only after everything it references.
+enum Visit {
+    Enter(Vec<u8>),
+    Exit(Vec<u8>, Vec<u8>),
+}
+
+async fn copy_commits<S: ReplicaSink>(
+    client: &store::Client,
+    sink: &S,
+    roots: Vec<CommitId>,
+) -> Result<()> {
+    let mut stack: Vec<Visit> = roots
+        .into_iter()
+        .map(|id| Visit::Enter(id.to_bytes()))
+        .collect();
+    let mut seen = HashSet::new();
+    while let Some(visit) = stack.pop() {
+        match visit {
+            Visit::Enter(id) => {
+                if !seen.insert(id.clone()) || sink.has_object(ObjectKind::Commit, &id) {
+                    continue;
+                }
+                let data = rpc::do_get_object(client, ObjectKind::Commit.code(), &id).await?;
+                let proto = jj_lib::protos::simple_store::Commit::decode(&*data)
+                    .context("decode replicated commit")?;
+                let commit = proto_convert::commit_from_proto(proto);
+                for tree_id in commit.root_tree.iter() {
+                    copy_tree(client, sink, tree_id).await?;
+                }
+                stack.push(Visit::Exit(id, data));
+                for parent in commit.parents.iter().chain(&commit.predecessors) {
+                    stack.push(Visit::Enter(parent.to_bytes()));
+                }
+            }
+            Visit::Exit(id, data) => put_object_checked(sink, ObjectKind::Commit, &id, &data)?,
+        }
+    }
+    Ok(())
+}
+
+async fn copy_tree<S: ReplicaSink>(client: &store::Client, sink: &S, root: &TreeId) -> Result<()> {
+    let mut stack = vec![Visit::Enter(root.to_bytes())];
+    while let Some(visit) = stack.pop() {
+        match visit {
+            Visit::Enter(id) => {
+                if sink.has_object(ObjectKind::Tree, &id) {
+                    continue;
+                }
+                let data = rpc::do_get_object(client, ObjectKind::Tree.code(), &id).await?;
+                let proto = jj_lib::protos::simple_store::Tree::decode(&*data)
+                    .context("decode replicated tree")?;
+                let tree = proto_convert::tree_from_proto(proto);
+                stack.push(Visit::Exit(id, data));
+                for entry in tree.entries() {
+                    match entry.value() {
+                        TreeValue::Tree(sub_id) => stack.push(Visit::Enter(sub_id.to_bytes())),
+                        TreeValue::File { id, .. } => {
+                            copy_leaf(client, sink, ObjectKind::File, id.as_bytes()).await?
+                        }
+                        TreeValue::Symlink(id) => {
+                            copy_leaf(client, sink, ObjectKind::Symlink, id.as_bytes()).await?
+                        }
+                        TreeValue::GitSubmodule(_) => {}
+                    }
+                }
+            }
+            Visit::Exit(id, data) => put_object_checked(sink, ObjectKind::Tree, &id, &data)?,
+        }
+    }
+    Ok(())
+}
+
+async fn copy_leaf<S: ReplicaSink>(
+    client: &store::Client,
+    sink: &S,
+    kind: ObjectKind,
+    id: &[u8],
+) -> Result<()> {
+    if sink.has_object(kind, id) {
+        return Ok(());
+    }
+    let data = rpc::do_get_object(client, kind.code(), id).await?;
+    put_object_checked(sink, kind, id, &data)
+}
+
+fn put_object_checked<S: ReplicaSink>(
+    sink: &S,
+    kind: ObjectKind,
+    id: &[u8],
+    data: &[u8],
+) -> Result<()> {
+    let written = sink.put_object(kind, data)?;
+    ensure_same_id(kind.name(), id, &written)
+}
+
+fn ensure_same_id(what: &str, expected: &[u8], actual: &[u8]) -> Result<()> {
+    if expected != actual {
+        bail!(
+            "replicated {what} {} was stored as {}; replica and primary disagree on hashing",
+            encode_hex(expected),
+            encode_hex(actual)
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,317 @@
+//! Startup validation of the repo `tandem serve` is pointed at.
+//!
+//! jj-lib reports a broken `.jj/repo` with whatever low-level error it hit
+//! first ("No such file or directory", a protobuf decode failure, ...). Before
+//! loading, the server walks the layout itself and names the actual problem
+//! with steps to fix it:
+//!
+//! - the path is not a directory, or the repo is not readable/writable
+//! - `jj init` was interrupted, leaving `.jj` without a complete repo
+//! - the store is not a git backend (including a tandem *client* workspace)
+//! - the git backend's `git_target` points at a git dir that is gone
+//! - the op-heads store has no heads
+//!
+//! `tandem serve --repair` fixes the recoverable ones: an interrupted init
+//! with no operations recorded is removed and initialized again, and a
+//! dangling `git_target` is pointed back at the colocated `.git`.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// A fix `--repair` can apply without losing history.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    /// Delete the half-initialized `.jj` so the server initializes afresh.
+    Reinitialize,
+    /// Rewrite `store/git_target` to point at this git dir.
+    RelinkGit(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub summary: String,
+    pub remediation: String,
+    pub repair: Option<Repair>,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}\n  fix: {}", self.summary, self.remediation)?;
+        if self.repair.is_some() {
+            write!(f, "\n  or rerun with --repair")?;
+        }
+        Ok(())
+    }
+}
+
+fn problem(summary: String, remediation: impl Into<String>) -> Problem {
+    Problem {
+        summary,
+        remediation: remediation.into(),
+        repair: None,
+    }
+}
+
+/// Check `repo` before the server loads it. An empty result means either a
+/// loadable repo or a directory the server will initialize.
+pub fn validate(repo: &Path) -> Vec<Problem> {
+    if repo.exists() && !repo.is_dir() {
+        return vec![problem(
+            format!("{} is not a directory", repo.display()),
+            "pass --repo <dir> naming the repository root",
+        )];
+    }
+    let jj_dir = repo.join(".jj");
+    if !jj_dir.exists() {
+        return Vec::new();
+    }
+    let repo_dir = jj_dir.join("repo");
+    if repo_dir.is_file() {
+        return vec![problem(
+            format!(
+                "{} is a secondary jj workspace, not the repo itself",
+                repo.display()
+            ),
+            "pass --repo <dir> naming the workspace that owns .jj/repo",
+        )];
+    }
+    if repo_dir.exists() {
+        if let Some(problem) = unreadable(&repo_dir) {
+            return vec![problem];
+        }
+    }
+
+    let op_heads_dir = repo_dir.join("op_heads").join("heads");
+    let head_count = std::fs::read_dir(&op_heads_dir)
+        .map(|entries| entries.filter_map(|e| e.ok()).count())
+        .unwrap_or(0);
+    let missing: Vec<&str> = ["store/type", "op_store", "op_heads/type"]
+        .into_iter()
+        .filter(|part| !repo_dir.join(part).exists())
+        .collect();
+    if !missing.is_empty() {
+        let interrupted = format!(
+            "{} looks like an interrupted `jj init`: .jj/repo is missing {}",
+            repo.display(),
+            missing.join(", ")
+        );
+        return vec![if head_count == 0 {
+            Problem {
+                summary: interrupted,
+                remediation: format!(
+                    "remove {} (it records no operations) and start the server again",
+                    jj_dir.display()
+                ),
+                repair: Some(Repair::Reinitialize),
+            }
+        } else {
+            problem(
+                interrupted,
+                "restore .jj from a backup; operations exist, so it is not safe to recreate",
+            )
+        }];
+    }
+
+    let mut problems = Vec::new();
+    let store_dir = repo_dir.join("store");
+    let store_type = std::fs::read_to_string(store_dir.join("type")).unwrap_or_default();
+    match store_type.trim() {
+        "git" => problems.extend(check_git_target(repo, &store_dir)),
+        "tandem" => problems.push(problem(
+            format!(
+                "{} is a tandem client workspace; its objects live on another server",
+                repo.display()
+            ),
+            "serve the repository that workspace was initialized from",
+        )),
+        other => problems.push(problem(
+            format!(
+                "{} uses the {other:?} backend; tandem serves git-backed repos only",
+                repo.display()
+            ),
+            "create a git-backed copy with `jj git init` and push the history into it",
+        )),
+    }
+    if head_count == 0 {
+        problems.push(problem(
+            format!("{} has no operation heads", op_heads_dir.display()),
+            "restore .jj/repo/op_heads from a backup",
+        ));
+    }
+    if let Some(problem) = unwritable(&repo_dir) {
+        problems.push(problem);
+    }
+    problems
+}
+
+fn check_git_target(repo: &Path, store_dir: &Path) -> Option<Problem> {
+    let target = std::fs::read_to_string(store_dir.join("git_target")).ok()?;
+    let git_dir = store_dir.join(target.trim());
+    if git_dir.is_dir() {
+        return None;
+    }
+    let summary = format!(
+        "the git backend points at {}, which does not exist",
+        git_dir.display()
+    );
+    let colocated = repo.join(".git");
+    Some(if colocated.is_dir() {
+        Problem {
+            summary,
+            remediation: format!(
+                "write the path of {} into {}",
+                colocated.display(),
+                store_dir.join("git_target").display()
+            ),
+            repair: Some(Repair::RelinkGit(colocated)),
+        }
+    } else {
+        problem(summary, "restore the git directory from a backup")
+    })
+}
+
+fn unreadable(dir: &Path) -> Option<Problem> {
+    let err = std::fs::read_dir(dir).err()?;
+    Some(problem(
+        format!("cannot read {}: {err}", dir.display()),
+        permission_hint(dir),
+    ))
+}
+
+fn unwritable(dir: &Path) -> Option<Problem> {
+    let probe = dir.join(".tandem-write-probe");
+    match std::fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = std::fs::remove_file(&probe);
+            None
+        }
+        Err(err) => Some(problem(
+            format!("cannot write to {}: {err}", dir.display()),
+            permission_hint(dir),
+        )),
+    }
+}
+
+fn permission_hint(dir: &Path) -> String {
+    format!(
+        "run the server as the user owning {0}, or `chown -R <user> {0}`",
+        dir.display()
+    )
+}
+
+/// Apply `repair` to `repo`.
+pub fn repair(repo: &Path, repair: &Repair) -> Result<()> {
+    match repair {
+        Repair::Reinitialize => {
+            let jj_dir = repo.join(".jj");
+            std::fs::remove_dir_all(&jj_dir).with_context(|| format!("remove {}", jj_dir.display()))
+        }
+        Repair::RelinkGit(git_dir) => {
+            let git_dir = dunce::canonicalize(git_dir)
+                .with_context(|| format!("resolve {}", git_dir.display()))?;
+            let target = repo.join(".jj/repo/store/git_target");
+            std::fs::write(&target, git_dir.to_string_lossy().as_bytes())
+                .with_context(|| format!("write {}", target.display()))
+        }
+    }
+}
+
+/// Validate `repo`, repairing what can be repaired when `allow_repair` is
+/// set. Fails with every remaining problem spelled out.
+pub fn ensure_servable(repo: &Path, allow_repair: bool) -> Result<()> {
+    let mut problems = validate(repo);
+    if allow_repair && problems.iter().any(|p| p.repair.is_some()) {
+        for fix in problems.iter().filter_map(|p| p.repair.as_ref()) {
+            repair(repo, fix)?;
+            tracing::warn!(repo = %repo.display(), repair = ?fix, "repaired repo");
+        }
+        problems = validate(repo);
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    let details: Vec<String> = problems.iter().map(|p| p.to_string()).collect();
+    anyhow::bail!("cannot serve {}:\n{}", repo.display(), details.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fake_repo(root: &Path) {
+        let repo_dir = root.join(".jj/repo");
+        for dir in ["store", "op_store", "op_heads/heads"] {
+            std::fs::create_dir_all(repo_dir.join(dir)).unwrap();
+        }
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(repo_dir.join("store/type"), "git").unwrap();
+        std::fs::write(repo_dir.join("store/git_target"), "../../../.git").unwrap();
+        std::fs::write(repo_dir.join("op_heads/type"), "simple_op_heads_store").unwrap();
+        std::fs::write(repo_dir.join("op_heads/heads/abc"), "").unwrap();
+    }
+
+    #[test]
+    fn accepts_missing_and_complete_repos() {
+        let tmp = tempfile::tempdir().unwrap();
+        assert!(validate(&tmp.path().join("new")).is_empty());
+        fake_repo(tmp.path());
+        assert_eq!(validate(tmp.path()), Vec::new());
+    }
+
+    #[test]
+    fn interrupted_init_without_operations_is_repairable() {
+        let tmp = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(tmp.path().join(".jj/working_copy")).unwrap();
+        assert_eq!(validate(tmp.path())[0].repair, Some(Repair::Reinitialize));
+
+        std::fs::create_dir_all(tmp.path().join(".jj/repo/store")).unwrap();
+        let problems = validate(tmp.path());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].summary.contains("interrupted"));
+        assert_eq!(problems[0].repair, Some(Repair::Reinitialize));
+
+        ensure_servable(tmp.path(), true).unwrap();
+        assert!(!tmp.path().join(".jj").exists());
+    }
+
+    #[test]
+    fn names_client_workspaces_and_other_backends() {
+        let tmp = tempfile::tempdir().unwrap();
+        fake_repo(tmp.path());
+        let type_file = tmp.path().join(".jj/repo/store/type");
+        std::fs::write(&type_file, "tandem").unwrap();
+        assert!(validate(tmp.path())[0]
+            .summary
+            .contains("tandem client workspace"));
+        std::fs::write(&type_file, "Simple").unwrap();
+        assert!(validate(tmp.path())[0]
+            .summary
+            .contains("\"Simple\" backend"));
+    }
+
+    #[test]
+    fn relinks_a_dangling_git_target() {
+        let tmp = tempfile::tempdir().unwrap();
+        fake_repo(tmp.path());
+        let target = tmp.path().join(".jj/repo/store/git_target");
+        std::fs::write(&target, "../../../moved.git").unwrap();
+        let err = ensure_servable(tmp.path(), false).unwrap_err();
+        assert!(format!("{err:#}").contains("--repair"), "{err:#}");
+
+        ensure_servable(tmp.path(), true).unwrap();
+        assert!(validate(tmp.path()).is_empty());
+    }
+
+    #[test]
+    fn missing_op_heads_is_not_repairable() {
+        let tmp = tempfile::tempdir().unwrap();
+        fake_repo(tmp.path());
+        std::fs::remove_file(tmp.path().join(".jj/repo/op_heads/heads/abc")).unwrap();
+        let problems = validate(tmp.path());
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].repair.is_none());
+        assert!(ensure_servable(tmp.path(), true).is_err());
+    }
+}
@@ -0,0 +1,276 @@
+//! Resource usage for `tandem server status` — is this box about to fall over?
+//!
+//! A status request reports the server's resident memory and open file
+//! descriptors, read fresh from `/proc` (or `/dev/fd`), and how much disk
+//! the repo takes: git objects, the op store, tandem's own metadata, and
+//! jj's commit index, which is a cache the server rebuilds if it is deleted.
+//!
+//! Walking a large git object directory is not free, and status requests
+//! are frequent (`tandem ps` sends one to every daemon it finds), so the
+//! disk figures are measured at most once per [`DISK_USAGE_TTL`], off the
+//! async runtime. `measuredSecsAgo` says how old they are.
+//!
+//! Figures a platform cannot provide (RSS outside Linux) are left out.
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+
+/// How long disk usage figures are reused before the repo is walked again.
+pub const DISK_USAGE_TTL: Duration = Duration::from_secs(60);
+
+/// The `resources` field of a status response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceUsage {
+    /// Resident set size of the server process.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rss_bytes: Option<u64>,
+    /// File descriptors the server process has open.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub open_fds: Option<u64>,
+    /// The soft limit on open file descriptors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fd_limit: Option<u64>,
+    pub disk: DiskUsage,
+}
+
+/// Bytes on disk under the server's `.jj/repo`, by part.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsage {
+    /// The git backend's `objects/` directory (loose objects and packs).
+    pub git_objects_bytes: u64,
+    /// Operations and views (`op_store/`).
+    pub op_store_bytes: u64,
+    /// tandem's metadata (`tandem/`: heads, history, integration state).
+    pub tandem_bytes: u64,
+    /// jj's commit index (`index/`), rebuilt from the store when missing.
+    pub index_cache_bytes: u64,
+    /// Seconds since these figures were measured.
+    #[serde(default)]
+    pub measured_secs_ago: u64,
+}
+
+impl DiskUsage {
+    pub fn total_bytes(&self) -> u64 {
+        self.git_objects_bytes + self.op_store_bytes + self.tandem_bytes + self.index_cache_bytes
+    }
+
+    fn measure(repo_dir: &Path) -> Self {
+        Self {
+            git_objects_bytes: git_dir(repo_dir)
+                .map(|git_dir| dir_size(&git_dir.join("objects")))
+                .unwrap_or(0),
+            op_store_bytes: dir_size(&repo_dir.join("op_store")),
+            tandem_bytes: dir_size(&repo_dir.join("tandem")),
+            index_cache_bytes: dir_size(&repo_dir.join("index")),
+            measured_secs_ago: 0,
+        }
+    }
+}
+
+impl ResourceUsage {
+    /// Memory and descriptors as one line, e.g. `412.3 MiB RSS, 87 open files`.
+    pub fn describe_process(&self) -> String {
+        let rss = match self.rss_bytes {
+            Some(bytes) => format!("{} RSS", human_bytes(bytes)),
+            None => "RSS unknown".to_string(),
+        };
+        match (self.open_fds, self.fd_limit) {
+            (Some(open), Some(limit)) => format!("{rss}, {open} of {limit} open files"),
+            (Some(open), None) => format!("{rss}, {open} open files"),
+            (None, _) => rss,
+        }
+    }
+}
+
+/// Process figures now, disk figures from the last walk younger than
+/// [`DISK_USAGE_TTL`].
+pub struct Resources {
+    repo_dir: PathBuf,
+    disk: Mutex<Option<(Instant, DiskUsage)>>,
+}
+
+impl Resources {
+    /// `repo_dir` is the server's `.jj/repo`.
+    pub fn new(repo_dir: PathBuf) -> Self {
+        Self {
+            repo_dir,
+            disk: Mutex::new(None),
+        }
+    }
+
+    pub async fn usage(&self) -> ResourceUsage {
+        ResourceUsage {
+            rss_bytes: rss_bytes(),
+            open_fds: open_fds(),
+            fd_limit: fd_limit(),
+            disk: self.disk_usage().await,
+        }
+    }
+
+    async fn disk_usage(&self) -> DiskUsage {
+        if let Some((at, usage)) = self.disk.lock().unwrap().as_ref() {
+            if at.elapsed() < DISK_USAGE_TTL {
+                return DiskUsage {
+                    measured_secs_ago: at.elapsed().as_secs(),
+                    ..usage.clone()
+                };
+            }
+        }
+        let repo_dir = self.repo_dir.clone();
+        let started = Instant::now();
+        let usage = tokio::task::spawn_blocking(move || DiskUsage::measure(&repo_dir))
+            .await
+            .unwrap_or_default();
+        tracing::debug!(
+            total_bytes = usage.total_bytes(),
+            elapsed_ms = started.elapsed().as_millis() as u64,
+            "measured repo disk usage"
+        );
+        *self.disk.lock().unwrap() = Some((Instant::now(), usage.clone()));
+        usage
+    }
+}
+
+/// The git dir the repo's git backend uses, from `store/git_target`.
+fn git_dir(repo_dir: &Path) -> Option<PathBuf> {
+    let store_dir = repo_dir.join("store");
+    let target = std::fs::read_to_string(store_dir.join("git_target")).ok()?;
+    Some(store_dir.join(target.trim()))
+}
+
+/// Total size of the files under `dir`, not following symlinks. Entries that
+/// cannot be read (removed mid-walk, no permission) are skipped.
+fn dir_size(dir: &Path) -> u64 {
+    let mut total = 0;
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if metadata.is_file() {
+                total += metadata.len();
+            }
+        }
+    }
+    total
+}
+
+#[cfg(target_os = "linux")]
+fn rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn rss_bytes() -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn open_fds() -> Option<u64> {
+    let dir = if cfg!(target_os = "linux") {
+        "/proc/self/fd"
+    } else {
+        "/dev/fd"
+    };
+    // Reading the directory opens one more descriptor, which is listed too.
+    let count = std::fs::read_dir(dir).ok()?.count() as u64;
+    Some(count.saturating_sub(1))
+}
+
+#[cfg(not(unix))]
+fn open_fds() -> Option<u64> {
+    None
+}
+
+#[cfg(unix)]
+fn fd_limit() -> Option<u64> {
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit only writes to the struct we pass.
+    if unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) } != 0 {
+        return None;
+    }
+    (limit.rlim_cur != libc::RLIM_INFINITY).then_some(limit.rlim_cur)
+}
+
+#[cfg(not(unix))]
+fn fd_limit() -> Option<u64> {
+    None
+}
+
+/// `1536` → `1.5 KiB`.
+pub fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn human_bytes_picks_a_unit() {
+        assert_eq!(human_bytes(0), "0 B");
+        assert_eq!(human_bytes(1023), "1023 B");
+        assert_eq!(human_bytes(1536), "1.5 KiB");
+        assert_eq!(human_bytes(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    }
+
+    #[test]
+    fn disk_usage_splits_the_repo_by_part() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let repo_dir = tmp.path();
+        let write = |path: &str, len: usize| {
+            let path = repo_dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, vec![0u8; len]).unwrap();
+        };
+        std::fs::create_dir_all(repo_dir.join("store")).unwrap();
+        std::fs::write(repo_dir.join("store/git_target"), "git").unwrap();
+        write("store/git/objects/ab/cdef", 100);
+        write("store/git/objects/pack/pack-1.pack", 1000);
+        write("store/git/config", 50);
+        write("op_store/operations/aa/bb", 20);
+        write("tandem/heads.json", 3);
+        write("index/segments/x", 7);
+
+        let usage = DiskUsage::measure(repo_dir);
+        assert_eq!(usage.git_objects_bytes, 1100);
+        assert_eq!(usage.op_store_bytes, 20);
+        assert_eq!(usage.tandem_bytes, 3);
+        assert_eq!(usage.index_cache_bytes, 7);
+        assert_eq!(usage.total_bytes(), 1130);
+    }
+}
@@ -0,0 +1,43 @@
+//! Revset functions for tandem workspaces.
+//!
+//! ```text
+//! tandem_workspace(name)   the working-copy commit of workspace `name`
+//! tandem_integration()     the commit the `integration` bookmark points at
+//! ```
+//!
+//! so `jj log -r 'tandem_workspace(agent-b)::@'` shows what agent-b has that
+//! you do not. Every workspace reads the same view from the server, so both
+//! resolve locally like `agent-b@` and `integration` do; the functions just
+//! give them names that say where they come from and compose in aliases.
+
+use std::sync::Arc;
+
+use jj_lib::ref_name::WorkspaceNameBuf;
+use jj_lib::revset::{
+    expect_literal, FunctionCallNode, LoweringContext, RevsetDiagnostics, RevsetExpression,
+    RevsetParseError, UserRevsetExpression,
+};
+
+/// Bookmark the server's integration workspace keeps on the merged commit.
+const INTEGRATION_BOOKMARK: &str = "integration";
+
+pub fn tandem_workspace(
+    _diagnostics: &mut RevsetDiagnostics,
+    function: &FunctionCallNode,
+    _context: &LoweringContext,
+) -> Result<Arc<UserRevsetExpression>, RevsetParseError> {
+    let [arg] = function.expect_exact_arguments()?;
+    let name: String = expect_literal("workspace name", arg)?;
+    Ok(RevsetExpression::working_copy(WorkspaceNameBuf::from(name)))
+}
+
+pub fn tandem_integration(
+    _diagnostics: &mut RevsetDiagnostics,
+    function: &FunctionCallNode,
+    _context: &LoweringContext,
+) -> Result<Arc<UserRevsetExpression>, RevsetParseError> {
+    function.expect_no_arguments()?;
+    // Resolved like the bare symbol, so a server without
+    // --enable-integration-workspace reports the missing bookmark.
+    Ok(RevsetExpression::symbol(INTEGRATION_BOOKMARK.to_string()))
+}
@@ -0,0 +1,203 @@
+//! tandem search — find commits by description on the server.
+//!
+//! `searchCommits` walks the commits visible at the server's head operation,
+//! newest first, and returns the ones whose description contains the query.
+//! Doing the same from a client means fetching every commit object through
+//! `getObject`; the server reads them from its local git store instead.
+//!
+//! Matching ignores case and is a plain substring test: `fix flaky test`
+//! finds "Fix flaky test in CI" but not "fix the flaky test". `--author`
+//! narrows the hits to commits whose author name or email contains it.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, bail, Context, Result};
+use jj_lib::backend::CommitId;
+use jj_lib::hex_util::{encode_hex, encode_reverse_hex};
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::repo::{Repo as _, RepoLoader};
+use jj_lib::revset::ResolvedRevsetExpression;
+
+use crate::ps::format_age;
+use crate::rpc::{CommitMatch, RepoCapability, TandemClient};
+
+/// Hits per search when the client does not pick a limit.
+pub const DEFAULT_LIMIT: usize = 50;
+/// Upper bound on hits per search, whatever the client asks for.
+pub const MAX_LIMIT: usize = 1000;
+
+/// The hit limit for a request's `limit` (0 for the default).
+pub fn limit(requested: u32) -> usize {
+    match requested {
+        0 => DEFAULT_LIMIT,
+        n => (n as usize).min(MAX_LIMIT),
+    }
+}
+
+// ─── Server side ──────────────────────────────────────────────────────────────
+
+/// Visible commits at the head operation matching `query` and `author`,
+/// newest first, and whether more than `limit` matched.
+pub fn search_commits(
+    repo_loader: &RepoLoader,
+    query: &str,
+    author: &str,
+    limit: usize,
+) -> Result<(Vec<CommitMatch>, bool)> {
+    let repo = repo_loader
+        .load_at_head()
+        .map_err(|e| anyhow!("load repo at head: {e}"))?;
+    let heads: Vec<CommitId> = repo.view().heads().iter().cloned().collect();
+    let visible = ResolvedRevsetExpression::commits(heads)
+        .ancestors()
+        .evaluate(repo.as_ref())
+        .map_err(|e| anyhow!("evaluate visible commits: {e}"))?;
+
+    let query = query.to_lowercase();
+    let author = author.to_lowercase();
+    let root_commit_id = repo.store().root_commit_id().clone();
+    let mut found = Vec::new();
+    for commit_id in visible.iter() {
+        let commit_id = commit_id.map_err(|e| anyhow!("walk visible commits: {e}"))?;
+        if commit_id == root_commit_id {
+            continue;
+        }
+        let commit = repo
+            .store()
+            .get_commit(&commit_id)
+            .map_err(|e| anyhow!("load commit {}: {e}", commit_id.hex()))?;
+        let signature = commit.author();
+        if !matches(
+            commit.description(),
+            &signature.name,
+            &signature.email,
+            &query,
+            &author,
+        ) {
+            continue;
+        }
+        if found.len() == limit {
+            return Ok((found, true));
+        }
+        found.push(CommitMatch {
+            commit_id: commit_id.to_bytes(),
+            change_id: commit.change_id().to_bytes(),
+            description: commit.description().to_string(),
+            author_name: signature.name.clone(),
+            author_email: signature.email.clone(),
+            author_timestamp_ms: signature.timestamp.timestamp.0,
+        });
+    }
+    Ok((found, false))
+}
+
+/// Whether a commit matches; `query` and `author` are already lowercase, and
+/// an empty one matches everything.
+fn matches(description: &str, name: &str, email: &str, query: &str, author: &str) -> bool {
+    description.to_lowercase().contains(query)
+        && (author.is_empty()
+            || name.to_lowercase().contains(author)
+            || email.to_lowercase().contains(author))
+}
+
+// ─── Public entry point ───────────────────────────────────────────────────────
+
+pub fn run_search(
+    server_addr: &str,
+    query: &str,
+    author: Option<&str>,
+    limit: Option<u32>,
+    json: bool,
+) -> Result<()> {
+    let author = author.unwrap_or("");
+    if query.trim().is_empty() && author.trim().is_empty() {
+        bail!("nothing to search for: give a query, --author, or both");
+    }
+
+    let client =
+        TandemClient::connect_with_requirements(server_addr, &[RepoCapability::CommitSearch])
+            .with_context(|| format!("cannot search commits on {server_addr}"))?;
+    let (commits, truncated) = client.search_commits(query, author, limit.unwrap_or(0))?;
+
+    if json {
+        let entries: Vec<serde_json::Value> = commits
+            .iter()
+            .map(|commit| {
+                serde_json::json!({
+                    "commitId": encode_hex(&commit.commit_id),
+                    "changeId": encode_reverse_hex(&commit.change_id),
+                    "description": commit.description,
+                    "author": {
+                        "name": commit.author_name,
+                        "email": commit.author_email,
+                        "timestampMillis": commit.author_timestamp_ms,
+                    },
+                })
+            })
+            .collect();
+        let out = serde_json::json!({ "commits": entries, "truncated": truncated });
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+
+    if commits.is_empty() {
+        println!("no matching commits");
+        return Ok(());
+    }
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    for commit in &commits {
+        let change: String = encode_reverse_hex(&commit.change_id)
+            .chars()
+            .take(12)
+            .collect();
+        let commit_hex = encode_hex(&commit.commit_id);
+        println!(
+            "{change} {} {} {} {}",
+            &commit_hex[..12.min(commit_hex.len())],
+            commit.author_email,
+            format_age(now_ms.saturating_sub(commit.author_timestamp_ms)),
+            commit.description.lines().next().unwrap_or("")
+        );
+    }
+    if truncated {
+        eprintln!(
+            "showing the newest {} matches; raise --limit for more",
+            commits.len()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_defaults_and_caps() {
+        assert_eq!(limit(0), DEFAULT_LIMIT);
+        assert_eq!(limit(5), 5);
+        assert_eq!(limit(u32::MAX), MAX_LIMIT);
+    }
+
+    #[test]
+    fn matches_description_and_author_ignoring_case() {
+        let m = |query: &str, author: &str| {
+            matches(
+                "Fix flaky test in CI\n\nRetry the socket bind.",
+                "Alice Example",
+                "alice@example.com",
+                query,
+                author,
+            )
+        };
+        assert!(m("fix flaky test", ""));
+        assert!(m("socket bind", ""));
+        assert!(m("", "alice"));
+        assert!(m("flaky", "example.com"));
+        assert!(!m("fix the flaky test", ""));
+        assert!(!m("flaky", "bob"));
+    }
+}
@@ -2,7 +2,8 @@
 //!
 //! The server stores objects through jj's Git backend so that `jj git push`
 //! on the server repo just works. Operations and views are stored in the
-//! standard jj op_store directory. Op heads are managed through jj-lib's
+//! standard jj op_store directory, optionally with operations sharded by id
+//! prefix (`--shard-operations`, see `sharded_op_store`). Op heads are managed through jj-lib's
 //! op-heads store; `.jj/repo/tandem/heads.json` stores tandem metadata only
 //! (CAS version + workspace head attribution).
 
@@ -26,19 +27,78 @@ use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::broadcast;
 
+use crate::api;
+use crate::attribution;
+use crate::cas_storm::{self, CasStorm};
+use crate::change_lock::{self, ChangeLocks};
+use crate::commit_graph;
+use crate::content_http;
 use crate::control;
+use crate::drain::{self, Drain};
+use crate::ephemeral::{self, EphemeralWorkspace};
+use crate::fair_queue::FairQueue;
+use crate::faults::{Faults, FaultyStore, STORE_METHODS};
+use crate::handover::{self, HeadsLock};
+use crate::heads_history::{self, HeadsHistory, HeadsTransition};
+use crate::heads_summary::{self, HeadsSummary};
+use crate::health::{self, HealthCheck, HealthResponse};
+use crate::http;
+use crate::idempotency::AppliedUpdates;
+use crate::integrity::{self, IntegrityScan};
+use crate::jj_compat;
+use crate::limits;
 use crate::logging;
+use crate::maintenance::Maintenance;
+use crate::merge_preview;
+use crate::metadata_schema;
+use crate::notifications::{self, Event, EventKind, Notifications};
+use crate::op_retention::{self, OpRetention};
+use crate::op_tags::OperationTags;
+use crate::path_overlaps;
+use crate::protected_paths::ProtectedPaths;
 use crate::proto_convert;
+use crate::redirect::{Redirect, RedirectTarget};
+use crate::replica::{self, ObjectKind, ReplicaSink};
+use crate::repo_check;
+use crate::resources::Resources;
+use crate::rpc;
+use crate::rpc_record;
+use crate::search;
+use crate::sharded_op_store;
+use crate::snapshot_hold::{self, SnapshotHolds};
+use crate::status_file;
 use crate::tandem_capnp::{cancel, head_watcher, store};
+use crate::view_delta::{self, DeltaOp};
+use crate::watchdog::{self, Watchdog};
+
+/// How many operations `rewritten_heads` walks before giving up on finding
+/// an old head in the new operation's ancestry.
+const MAX_REWRITE_ANCESTRY: usize = 100_000;
+
+/// Tree entries per `getTreeEntries` page when the client leaves the limit
+/// to the server, and the most it may ask for.
+const TREE_PAGE_DEFAULT: usize = 2_000;
+const TREE_PAGE_MAX: usize = 10_000;
+
+/// How long a server that handed over waits for its control socket to
+/// answer `tandem server restart` before it stops.
+const RESTART_REPLY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[cfg(feature = "sim")]
+pub mod sim;
+
+const IMPORTED_VERSION_FILE: &str = "imported-version";
 
 // ─── Public entry point ───────────────────────────────────────────────────────
 
 #[allow(dead_code)]
 pub struct ServeOptions {
-    pub listen_addr: String,
+    /// RPC addresses to accept clients on: `host:port`, `tcp://host:port`,
+    /// or `unix:///path`.
+    pub listen_addrs: Vec<String>,
     pub repo_path: String,
     pub log_level: String,
     pub log_format: String,
@@ -46,6 +106,58 @@ pub struct ServeOptions {
     pub daemon: bool,
     pub log_file: Option<String>,
     pub enable_integration_workspace: bool,
+    pub auto_advance: AutoAdvancePolicy,
+    /// CODEOWNERS-like rules file checked on every `updateOpHeads`.
+    pub protected_paths: Option<String>,
+    /// Primary to mirror; the server then rejects all writes.
+    pub follow: Option<String>,
+    /// Address for the plain-HTTP `/healthz` endpoint.
+    pub health_listen: Option<String>,
+    /// Address for the read-only plain-HTTP `/raw`, `/archive`, and `/api`
+    /// endpoints.
+    pub http_listen: Option<String>,
+    /// JSON file of notification sinks (`--notifications`).
+    pub notifications: Option<String>,
+    /// Fix recoverable repo layout problems found at startup.
+    pub repair: bool,
+    /// Git URL mirroring this repo, advertised for `tandem init --colocate`.
+    pub git_remote: Option<String>,
+    /// `snapshot.auto-track` fileset advertised to new workspaces
+    /// (`--snapshot-exclude`, `--snapshot-auto-track`).
+    pub snapshot_auto_track: Option<String>,
+    /// Queue `updateOpHeads` by workspace, holding a turn this long for a
+    /// workspace that lost the CAS (`--fair-queue`).
+    pub fair_queue: Option<Duration>,
+    /// Lost CAS races per second that start a storm (`--cas-storm-threshold`,
+    /// 0 for never).
+    pub cas_storm_threshold: usize,
+    /// Token the control socket requires on privileged requests.
+    pub control_token: Option<String>,
+    /// JSON snapshot of the server rewritten every `status_interval`.
+    pub status_file: Option<String>,
+    pub status_interval: Duration,
+    /// How long shutdown waits for connections that are mid-commit.
+    pub drain_timeout: Duration,
+    /// Prune operations beyond this policy (`--op-retention`).
+    pub op_retention: Option<OpRetention>,
+    /// Check operations and views at startup (`--integrity-scan`).
+    pub integrity_scan: Option<IntegrityScan>,
+    /// Drop store calls running longer than this (`--rpc-timeout`); zero
+    /// turns the watchdog off.
+    pub rpc_timeout: Duration,
+    /// Keep operations sharded by id prefix (`--shard-operations`); without
+    /// it a sharded op store is moved back to jj's flat layout.
+    pub shard_operations: bool,
+}
+
+/// Bookmarks that follow the integration commit once it is clean and the
+/// optional check command passes.
+#[derive(Debug, Clone, Default)]
+pub struct AutoAdvancePolicy {
+    pub bookmarks: Vec<String>,
+    /// Shell command run in the repo root with `TANDEM_INTEGRATION_COMMIT`
+    /// set; bookmarks advance only when it exits 0.
+    pub check_command: Option<String>,
 }
 
 pub async fn run_serve(opts: ServeOptions) -> Result<()> {
@@ -53,50 +165,339 @@ pub async fn run_serve(opts: ServeOptions) -> Result<()> {
     logging::init_tracing(&opts.log_level, &opts.log_format, log_tx.clone())?;
 
     tracing::info!(
-        listen_addr = %opts.listen_addr,
+        listen_addrs = ?opts.listen_addrs,
         repo = %opts.repo_path,
         daemon = opts.daemon,
         log_level = %opts.log_level,
         log_format = %opts.log_format,
         integration_workspace = opts.enable_integration_workspace,
+        auto_advance = ?opts.auto_advance.bookmarks,
         "starting tandem server"
     );
+    if !opts.auto_advance.bookmarks.is_empty() && !opts.enable_integration_workspace {
+        bail!("--auto-advance requires --enable-integration-workspace");
+    }
+    if opts.follow.is_some() && opts.enable_integration_workspace {
+        bail!("--follow cannot be combined with --enable-integration-workspace");
+    }
+    if opts.follow.is_some() && opts.op_retention.is_some() {
+        bail!("--follow cannot be combined with --op-retention");
+    }
     if let Some(path) = opts.log_file.as_deref() {
         tracing::debug!(log_file = %path, "serve log file argument");
     }
 
+    let protected_paths = match opts.protected_paths.as_deref() {
+        Some(path) => ProtectedPaths::load(Path::new(path))?,
+        None => ProtectedPaths::default(),
+    };
+
+    let notifications = opts
+        .notifications
+        .as_deref()
+        .map(|path| notifications::Config::load(Path::new(path)))
+        .transpose()?;
+
     let repo = PathBuf::from(&opts.repo_path);
-    let server = Rc::new(Server::new(repo, opts.enable_integration_workspace)?);
+    repo_check::ensure_servable(&repo, opts.repair)?;
+    let mut server = Server::new(
+        repo,
+        opts.enable_integration_workspace,
+        opts.auto_advance.clone(),
+        protected_paths,
+        opts.follow.clone(),
+        opts.shard_operations,
+    )?;
+    let integrity = match opts.integrity_scan {
+        Some(scope) => {
+            let report = integrity::scan(&server.op_store_path, scope)
+                .context("integrity scan of the op store")?;
+            tracing::info!(
+                checked = report.checked,
+                stored = report.stored,
+                quarantined = report.quarantined.len(),
+                "integrity scan finished"
+            );
+            Some(report)
+        }
+        None => None,
+    };
+    server.git_remote = opts.git_remote.clone();
+    server.snapshot_auto_track = opts.snapshot_auto_track.clone();
+    if let Some(fileset) = &server.snapshot_auto_track {
+        tracing::info!(fileset = %fileset, "advertising snapshot policy");
+    }
+    if let Some(max_wait) = opts.fair_queue {
+        tracing::info!(
+            max_wait_ms = max_wait.as_millis() as u64,
+            "fair queuing of head updates enabled"
+        );
+        server.fair_queue = Some(FairQueue::new(max_wait));
+    }
+    server.cas_storm = CasStorm::new(opts.cas_storm_threshold);
+    if let Some(config) = notifications {
+        server.notifications = Notifications::start(config, &opts.repo_path);
+        tracing::info!(
+            sinks = server.notifications.sinks(),
+            "notifications enabled"
+        );
+    }
+    server.watchdog = Watchdog::start(opts.rpc_timeout);
+    if let Some(watchdog) = &server.watchdog {
+        tracing::info!(
+            timeout_secs = watchdog.timeout().as_secs(),
+            "rpc watchdog enabled"
+        );
+    }
+    let server = Rc::new(server);
     server.start_integration_worker();
-    let listener = tokio::net::TcpListener::bind(&opts.listen_addr)
-        .await
-        .with_context(|| format!("failed to bind {}", opts.listen_addr))?;
-    let local_addr = listener.local_addr()?;
-    tracing::info!(listen_addr = %local_addr, "tandem server listening on");
+    if let Some(primary) = opts.follow.clone() {
+        tracing::info!(primary = %primary, "serving as read-only replica");
+        tokio::task::spawn_local(replica::follow_primary(primary, Rc::clone(&server)));
+    } else {
+        let sweeper = Rc::clone(&server);
+        tokio::task::spawn_local(async move {
+            loop {
+                tokio::time::sleep(ephemeral::SWEEP_INTERVAL).await;
+                if sweeper.snapshots.is_held() {
+                    continue;
+                }
+                // A panic in jj-lib must not end the sweeps for good.
+                let sweep = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    sweeper.expire_ephemeral_workspaces_sync()
+                }));
+                match sweep {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => {
+                        tracing::warn!(error = %format!("{err:#}"), "ephemeral workspace sweep failed")
+                    }
+                    Err(_) => tracing::error!("ephemeral workspace sweep panicked"),
+                }
+            }
+        });
+        if let Some(policy) = opts.op_retention {
+            tracing::info!(policy = %policy, "op retention enabled");
+            let pruner = Rc::clone(&server);
+            tokio::task::spawn_local(async move {
+                loop {
+                    if !pruner.snapshots.is_held() {
+                        if let Err(err) = pruner.apply_op_retention_sync(policy) {
+                            tracing::warn!(error = %format!("{err:#}"), "op retention pass failed");
+                            pruner.notifications.emit(Event::GcFailed {
+                                error: format!("{err:#}"),
+                            });
+                        }
+                    }
+                    tokio::time::sleep(op_retention::PASS_INTERVAL).await;
+                }
+            });
+        }
+    }
+    if opts.listen_addrs.is_empty() {
+        bail!("no listen address given");
+    }
+    // A server started by `tandem server restart` is handed the sockets of
+    // the one it replaces instead of binding its own.
+    let mut inherited = handover::inherit()?;
+    let mut listeners = Vec::with_capacity(opts.listen_addrs.len());
+    let mut local_addrs = Vec::with_capacity(opts.listen_addrs.len());
+    if let Some(inherited) = inherited.as_mut() {
+        for (addr, fd) in inherited.take(handover::LISTEN) {
+            listeners.push(Rc::new(RpcListener::inherit(&addr, fd)?));
+            local_addrs.push(addr);
+        }
+        if listeners.is_empty() {
+            bail!("previous server handed over no listeners");
+        }
+    } else {
+        for addr in &opts.listen_addrs {
+            let listener = RpcListener::bind(addr).await?;
+            local_addrs.push(listener.local_addr()?);
+            listeners.push(Rc::new(listener));
+        }
+    }
+    for addr in &local_addrs {
+        tracing::info!(listen_addr = %addr, "tandem server listening on");
+    }
+    let local_addr = local_addrs[0].clone();
+    let also_listen = local_addrs[1..].to_vec();
+    let mut handover_sockets: Vec<handover::Socket> = listeners
+        .iter()
+        .zip(&local_addrs)
+        .map(|(listener, addr)| handover::Socket {
+            kind: handover::LISTEN,
+            addr: addr.clone(),
+            fd: listener.handover_fd(),
+        })
+        .collect();
 
     // Set up shutdown signaling
     let (shutdown_tx, mut shutdown_rx) = tokio::sync::mpsc::channel::<()>(1);
+    let (restart_tx, mut restart_rx) = tokio::sync::mpsc::channel::<control::RestartRequest>(1);
+
+    // Health checks run on the server's task set so a wedged server reads as
+    // not ready rather than healthy.
+    let (health_tx, mut health_rx) = tokio::sync::mpsc::channel::<health::HealthRequest>(4);
+    let health_server = Rc::clone(&server);
+    tokio::task::spawn_local(async move {
+        while let Some(request) = health_rx.recv().await {
+            let _ = request.reply.send(health_server.health_sync());
+        }
+    });
+    if let Some(addr) = opts.health_listen.as_deref() {
+        let health_listener = bind_tcp(addr, &mut inherited, handover::HEALTH)
+            .await
+            .with_context(|| format!("failed to bind health endpoint {addr}"))?;
+        tracing::info!(health_addr = %health_listener.local_addr()?, "health endpoint listening");
+        handover_sockets.push(handover::Socket {
+            kind: handover::HEALTH,
+            addr: health_listener.local_addr()?.to_string(),
+            fd: handover::fd_of(&health_listener),
+        });
+        tokio::spawn(health::serve_http(health_listener, health_tx.clone()));
+    }
+    // Track in-flight connections
+    let inflight = Rc::new(std::cell::Cell::new(0u32));
+    let connection_ids = Arc::new(AtomicU64::new(1));
+    let connection_stats = {
+        let inflight = Rc::clone(&inflight);
+        let connection_ids = Arc::clone(&connection_ids);
+        move || status_file::ConnectionStats {
+            active: inflight.get(),
+            total: connection_ids.load(Ordering::Relaxed) - 1,
+        }
+    };
+
+    if let Some(addr) = opts.http_listen.as_deref() {
+        let (content_tx, mut content_rx) =
+            tokio::sync::mpsc::channel::<content_http::ContentRequest>(16);
+        let content_server = Rc::clone(&server);
+        tokio::task::spawn_local(async move {
+            while let Some(request) = content_rx.recv().await {
+                let reply = content_http::respond(&content_server.repo_loader, &request.target);
+                let _ = request.reply.send(reply);
+            }
+        });
+        let (api_tx, mut api_rx) = tokio::sync::mpsc::channel::<api::ApiRequest>(16);
+        let api_server = Rc::clone(&server);
+        let connection_stats = connection_stats.clone();
+        let started = Instant::now();
+        tokio::task::spawn_local(async move {
+            while let Some(request) = api_rx.recv().await {
+                let reply = api_server.api_reply_sync(request.target, started, connection_stats());
+                let _ = request.reply.send(reply);
+            }
+        });
+        let http_listener = bind_tcp(addr, &mut inherited, handover::HTTP)
+            .await
+            .with_context(|| format!("failed to bind http endpoint {addr}"))?;
+        tracing::info!(http_addr = %http_listener.local_addr()?, "http endpoint listening");
+        handover_sockets.push(handover::Socket {
+            kind: handover::HTTP,
+            addr: http_listener.local_addr()?.to_string(),
+            fd: handover::fd_of(&http_listener),
+        });
+        tokio::spawn(http::serve(http_listener, "http", move |request| {
+            let (content_tx, api_tx) = (content_tx.clone(), api_tx.clone());
+            async move {
+                if !request.is_read() {
+                    return http::Reply::error(
+                        "405 Method Not Allowed",
+                        "the http endpoint is read-only",
+                    );
+                }
+                if let Some(target) = api::parse_target(&request.path) {
+                    return api::request(&api_tx, target).await;
+                }
+                match content_http::parse_target(&request.path) {
+                    Some(target) => content_http::request(&content_tx, target).await,
+                    None => http::Reply::error("404 Not Found", "not found"),
+                }
+            }
+        }));
+    }
+
+    let status_file = opts.status_file.as_ref().map(|path| {
+        Rc::new(status_file::StatusFile {
+            path: PathBuf::from(path),
+            interval: opts.status_interval,
+            repo: opts.repo_path.clone(),
+            listen: local_addr.clone(),
+            also_listen: also_listen.clone(),
+            following: opts.follow.clone(),
+            started: std::time::Instant::now(),
+        })
+    });
+    let status_writer = status_file.clone().map(|file| {
+        tracing::info!(
+            path = %file.path.display(),
+            interval_secs = file.interval.as_secs(),
+            "writing status file"
+        );
+        let status_server = Rc::clone(&server);
+        let connection_stats = connection_stats.clone();
+        tokio::task::spawn_local(async move {
+            loop {
+                file.write(&status_server.status_snapshot_sync(&file, true, connection_stats()));
+                tokio::time::sleep(file.interval).await;
+            }
+        })
+    });
 
     // Set up control socket if requested
     let control_socket_path = opts.control_socket.clone();
+    let mut control_bound = None;
     if let Some(ref sock_path) = control_socket_path {
+        let (undo_tx, mut undo_rx) = tokio::sync::mpsc::channel::<control::UndoRemoteRequest>(4);
+        let undo_server = Rc::clone(&server);
+        tokio::task::spawn_local(async move {
+            while let Some(request) = undo_rx.recv().await {
+                undo_server.snapshots.released().await;
+                let result = undo_server
+                    .undo_remote_sync(&request.operation)
+                    .map_err(|e| format!("{e:#}"));
+                let _ = request.reply.send(result);
+            }
+        });
+
         let control_state = Arc::new(control::ControlState {
             pid: std::process::id(),
             start_time: std::time::Instant::now(),
             repo: opts.repo_path.clone(),
-            listen: local_addr.to_string(),
+            listen: local_addr.clone(),
+            also_listen: also_listen.clone(),
             shutdown_tx: shutdown_tx.clone(),
+            restart_tx,
             log_tx: log_tx.clone(),
             integration_enabled: opts.enable_integration_workspace,
             integration_metadata_path: server
                 .integration_metadata_path()
                 .to_string_lossy()
                 .to_string(),
+            following: opts.follow.clone(),
+            undo_tx,
+            health_tx: health_tx.clone(),
+            maintenance: server.maintenance.clone(),
+            redirect: server.redirect.clone(),
+            faults: server.faults.clone(),
+            watchdog: server.watchdog.clone(),
+            integrity: integrity.clone(),
+            resources: Resources::new(
+                server
+                    .tandem_dir
+                    .parent()
+                    .expect("tandem dir is inside .jj/repo")
+                    .to_path_buf(),
+            ),
+            token: opts.control_token.clone(),
         });
 
+        let (bound_tx, bound_rx) = tokio::sync::oneshot::channel();
+        control_bound = Some(bound_rx);
         let sock = sock_path.clone();
         tokio::spawn(async move {
-            if let Err(e) = control::run_control_socket(sock.clone(), control_state).await {
+            if let Err(e) = control::run_control_socket(sock.clone(), control_state, bound_tx).await
+            {
                 tracing::error!(socket_path = %sock, error = %e, "control socket error");
             }
         });
@@ -108,17 +509,11 @@ pub async fn run_serve(opts: ServeOptions) -> Result<()> {
     // Spawn signal handler (multi-threaded tokio task for signal handling)
     let signal_tx_clone = signal_tx.clone();
     tokio::spawn(async move {
-        let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
-            .expect("install SIGINT handler");
-        let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-            .expect("install SIGTERM handler");
+        let mut signals = ShutdownSignals::install();
 
         let mut first_signal = true;
         loop {
-            tokio::select! {
-                _ = sigint.recv() => {},
-                _ = sigterm.recv() => {},
-            }
+            signals.recv().await;
             if first_signal {
                 first_signal = false;
                 tracing::warn!("signal received, shutting down gracefully");
@@ -130,31 +525,98 @@ pub async fn run_serve(opts: ServeOptions) -> Result<()> {
         }
     });
 
-    // Track in-flight connections
-    let inflight = Rc::new(std::cell::Cell::new(0u32));
-    let connection_ids = Arc::new(AtomicU64::new(1));
+    // Each listener accepts on its own task; the loop below takes
+    // connections from all of them.
+    let (accepted_tx, mut accepted_rx) =
+        tokio::sync::mpsc::channel::<std::io::Result<(rpc::RpcStream, String)>>(16);
+    let spawn_acceptors = || -> Vec<tokio::task::JoinHandle<()>> {
+        listeners
+            .iter()
+            .map(|listener| {
+                let listener = Rc::clone(listener);
+                let accepted_tx = accepted_tx.clone();
+                tokio::task::spawn_local(async move {
+                    loop {
+                        let result = listener.accept().await;
+                        let failed = result.is_err();
+                        if accepted_tx.send(result).await.is_err() || failed {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect()
+    };
+    let mut acceptors = spawn_acceptors();
+
+    let serve_connection = |result: std::io::Result<(rpc::RpcStream, String)>| -> Result<()> {
+        let (stream, addr) = result?;
+        let server = Rc::clone(&server);
+        let inflight = Rc::clone(&inflight);
+        let conn_id = connection_ids.fetch_add(1, Ordering::Relaxed);
+
+        let next = inflight.get() + 1;
+        inflight.set(next);
+        tracing::info!(conn_id, peer = %addr, inflight = next, "client connected");
+
+        tokio::task::spawn_local(async move {
+            let result = handle_capnp_connection(Rc::clone(&server), stream, conn_id).await;
+            if let Err(err) = result {
+                tracing::error!(conn_id, peer = %addr, error = %err, "rpc connection error");
+            }
+            server.drain.connection_closed(conn_id);
+            let remaining = inflight.get().saturating_sub(1);
+            inflight.set(remaining);
+            tracing::info!(conn_id, peer = %addr, inflight = remaining, "client disconnected");
+        });
+        Ok(())
+    };
+
+    // Taking over from a previous server: once the control socket is ours,
+    // tell it to stop accepting.
+    if let Some(inherited) = inherited {
+        if let Some(bound) = control_bound {
+            bound
+                .await
+                .map_err(|_| anyhow!("control socket failed to start"))?;
+        }
+        inherited.ready()?;
+        tracing::info!("took over from the previous server");
+    }
 
     // Accept loop with shutdown
+    let mut handed_over = false;
     loop {
         tokio::select! {
-            result = listener.accept() => {
-                let (stream, addr) = result?;
-                let server = Rc::clone(&server);
-                let inflight = Rc::clone(&inflight);
-                let conn_id = connection_ids.fetch_add(1, Ordering::Relaxed);
-
-                let next = inflight.get() + 1;
-                inflight.set(next);
-                tracing::info!(conn_id, peer = %addr, inflight = next, "client connected");
-
-                tokio::task::spawn_local(async move {
-                    if let Err(err) = handle_capnp_connection(server, stream, conn_id).await {
-                        tracing::error!(conn_id, peer = %addr, error = %err, "rpc connection error");
+            Some(result) = accepted_rx.recv() => serve_connection(result)?,
+            Some(request) = restart_rx.recv() => {
+                // Nothing is accepted while the new server starts; clients
+                // wait in the listen backlog for whichever server carries on.
+                for acceptor in &acceptors {
+                    acceptor.abort();
+                }
+                while let Ok(result) = accepted_rx.try_recv() {
+                    serve_connection(result)?;
+                }
+                tracing::info!("restart requested via control socket, handing over");
+                let control_socket = control_socket_path.as_deref().unwrap_or_default();
+                match handover::hand_over(&handover_sockets, control_socket).await {
+                    Ok(pid) => {
+                        tracing::info!(pid, "new server took over, draining connections");
+                        let _ = request.reply.send(Ok(pid));
+                        // Answer `tandem server restart` before this process
+                        // winds down.
+                        let _ = tokio::time::timeout(RESTART_REPLY_TIMEOUT, request.written).await;
+                        handed_over = true;
+                        break;
                     }
-                    let remaining = inflight.get().saturating_sub(1);
-                    inflight.set(remaining);
-                    tracing::info!(conn_id, peer = %addr, inflight = remaining, "client disconnected");
-                });
+                    Err(err) => {
+                        let err = format!("{err:#}");
+                        tracing::error!(error = %err, "restart failed, still serving");
+                        let _ = request.reply.send(Err(err));
+                        acceptors = spawn_acceptors();
+                    }
+                }
             }
             _ = signal_rx.recv() => {
                 tracing::info!("signal received, draining connections");
@@ -167,22 +629,49 @@ pub async fn run_serve(opts: ServeOptions) -> Result<()> {
         }
     }
 
-    // Drain in-flight connections (5s timeout)
+    for acceptor in &acceptors {
+        acceptor.abort();
+    }
+    if let Some(writer) = &status_writer {
+        writer.abort();
+    }
+
+    // Drain: refuse new writes, let commits in progress land (up to
+    // --drain-timeout), and give everything else a short grace to hang up.
+    // After a handover, refused clients can retry right away.
+    server.begin_shutdown(if handed_over {
+        handover::RETRY_AFTER
+    } else {
+        opts.drain_timeout
+    });
     if inflight.get() > 0 {
         tracing::info!(
             inflight = inflight.get(),
+            committing = server.drain.committing(),
             "waiting for in-flight connections to drain"
         );
-        let drain_deadline = tokio::time::Instant::now() + tokio::time::Duration::from_secs(5);
+        let drain_started = tokio::time::Instant::now();
         while inflight.get() > 0 {
-            if tokio::time::Instant::now() > drain_deadline {
-                tracing::warn!(inflight = inflight.get(), "drain timeout reached");
+            let waited = drain_started.elapsed();
+            let committing = server.drain.committing();
+            if waited > opts.drain_timeout || (committing == 0 && waited > drain::IDLE_GRACE) {
+                tracing::warn!(
+                    inflight = inflight.get(),
+                    committing,
+                    "drain timeout reached"
+                );
                 break;
             }
             tokio::time::sleep(tokio::time::Duration::from_millis(50)).await;
         }
     }
 
+    if handed_over {
+        // The sockets, control socket, and status file are the new server's.
+        tracing::info!("tandem server stopped after handing over");
+        return Ok(());
+    }
+
     // Clean up control socket
     if let Some(ref sock_path) = control_socket_path {
         if let Err(e) = std::fs::remove_file(sock_path) {
@@ -192,15 +681,314 @@ pub async fn run_serve(opts: ServeOptions) -> Result<()> {
         }
     }
 
+    for listener in &listeners {
+        listener.remove_socket_file();
+    }
+
+    if let Some(file) = &status_file {
+        file.write(&server.status_snapshot_sync(file, false, connection_stats()));
+    }
+
     tracing::info!("tandem server stopped");
     Ok(())
 }
 
+// ─── Listeners ────────────────────────────────────────────────────────────────
+
+/// One `--listen` address the server accepts RPC clients on.
+enum RpcListener {
+    Tcp(tokio::net::TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: tokio::net::UnixListener,
+        path: PathBuf,
+    },
+}
+
+impl RpcListener {
+    async fn bind(endpoint: &str) -> Result<Self> {
+        match rpc::ConnectorTarget::parse(endpoint)? {
+            rpc::ConnectorTarget::Tcp { addr } => {
+                let listener = tokio::net::TcpListener::bind(&addr)
+                    .await
+                    .with_context(|| format!("failed to bind {addr}"))?;
+                Ok(Self::Tcp(listener))
+            }
+            #[cfg(unix)]
+            rpc::ConnectorTarget::Unix { path } => {
+                // A socket file nobody answers on was left by a server that
+                // did not shut down cleanly; a live one makes bind fail below.
+                if path.exists() && std::os::unix::net::UnixStream::connect(&path).is_err() {
+                    let _ = fs::remove_file(&path);
+                }
+                let listener = tokio::net::UnixListener::bind(&path)
+                    .with_context(|| format!("failed to bind {endpoint}"))?;
+                Ok(Self::Unix { listener, path })
+            }
+            #[cfg(not(unix))]
+            rpc::ConnectorTarget::Unix { .. } => {
+                bail!(
+                    "cannot listen on {endpoint}: Unix sockets are not supported on this platform"
+                )
+            }
+            rpc::ConnectorTarget::InProcess { .. } | rpc::ConnectorTarget::Local { .. } => {
+                bail!("cannot listen on {endpoint}: in-process endpoints are for embedded servers")
+            }
+        }
+    }
+
+    /// The bound address in `--listen` form, with the real port for `:0`.
+    fn local_addr(&self) -> Result<String> {
+        match self {
+            Self::Tcp(listener) => Ok(listener.local_addr()?.to_string()),
+            #[cfg(unix)]
+            Self::Unix { path, .. } => Ok(format!("unix://{}", path.display())),
+        }
+    }
+
+    /// Accept one client, with its peer address for the logs.
+    async fn accept(&self) -> std::io::Result<(rpc::RpcStream, String)> {
+        match self {
+            Self::Tcp(listener) => {
+                let (stream, addr) = listener.accept().await?;
+                Ok((rpc::RpcStream::Tcp(stream), addr.to_string()))
+            }
+            #[cfg(unix)]
+            Self::Unix { listener, path } => {
+                let (stream, _) = listener.accept().await?;
+                Ok((
+                    rpc::RpcStream::Unix(stream),
+                    format!("unix://{}", path.display()),
+                ))
+            }
+        }
+    }
+
+    /// Take over a listener bound by the server this one replaces.
+    fn inherit(endpoint: &str, fd: handover::InheritedFd) -> Result<Self> {
+        match rpc::ConnectorTarget::parse(endpoint)? {
+            rpc::ConnectorTarget::Tcp { .. } => Ok(Self::Tcp(handover::tcp_listener(fd)?)),
+            #[cfg(unix)]
+            rpc::ConnectorTarget::Unix { path } => Ok(Self::Unix {
+                listener: handover::unix_listener(fd)?,
+                path,
+            }),
+            #[cfg(not(unix))]
+            rpc::ConnectorTarget::Unix { .. } => match fd {},
+            rpc::ConnectorTarget::InProcess { .. } | rpc::ConnectorTarget::Local { .. } => {
+                bail!("cannot inherit a listener for {endpoint}")
+            }
+        }
+    }
+
+    /// The descriptor `tandem server restart` hands to the next server.
+    fn handover_fd(&self) -> handover::SocketFd {
+        match self {
+            Self::Tcp(listener) => handover::fd_of(listener),
+            #[cfg(unix)]
+            Self::Unix { listener, .. } => handover::fd_of(listener),
+        }
+    }
+
+    fn remove_socket_file(&self) {
+        #[cfg(unix)]
+        if let Self::Unix { path, .. } = self {
+            if let Err(e) = fs::remove_file(path) {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!(socket_path = %path.display(), error = %e, "failed to remove listen socket");
+                }
+            }
+        }
+    }
+}
+
+/// Bind a TCP endpoint, or take it over from the server this one replaces.
+async fn bind_tcp(
+    addr: &str,
+    inherited: &mut Option<handover::Inherited>,
+    kind: &str,
+) -> Result<tokio::net::TcpListener> {
+    if let Some((_, fd)) = inherited.as_mut().and_then(|i| i.take(kind).pop()) {
+        return handover::tcp_listener(fd);
+    }
+    Ok(tokio::net::TcpListener::bind(addr).await?)
+}
+
+// ─── Embedded server ──────────────────────────────────────────────────────────
+
+/// A server on its own thread inside this process, reachable from this
+/// process only, at an `inproc://` address. For tests, benchmarks, and
+/// single-user local mode: no TCP, no daemon, no control socket.
+///
+/// Dropping it stops the server and closes its connections.
+pub struct EmbeddedServer {
+    address: String,
+    repo: PathBuf,
+    /// The repo is a temporary directory, deleted on drop.
+    temporary: bool,
+    shutdown: Option<tokio::sync::oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl EmbeddedServer {
+    /// Serve `repo` (created if missing), or a new temporary repo if `None`.
+    /// Returns once the server is ready for clients.
+    pub fn start_in_process(repo: Option<&Path>) -> Result<Self> {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = format!(
+            "{}-{}",
+            std::process::id(),
+            NEXT_ID.fetch_add(1, Ordering::Relaxed)
+        );
+        let (repo, temporary) = match repo {
+            Some(repo) => {
+                repo_check::ensure_servable(repo, false)?;
+                (repo.to_path_buf(), false)
+            }
+            None => (
+                std::env::temp_dir().join(format!("tandem-embedded-{id}")),
+                true,
+            ),
+        };
+        let listener = rpc::InProcessListener::bind(&format!("embedded-{id}"))?;
+        let address = listener.local_addr();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel();
+        let server_repo = repo.clone();
+        let thread = std::thread::Builder::new()
+            .name("tandem-embedded".to_string())
+            .spawn(move || {
+                let rt = match tokio::runtime::Builder::new_current_thread()
+                    .enable_all()
+                    .build()
+                {
+                    Ok(rt) => rt,
+                    Err(err) => {
+                        let _ = ready_tx.send(Err(anyhow!(err).context("start embedded runtime")));
+                        return;
+                    }
+                };
+                let local = tokio::task::LocalSet::new();
+                local.block_on(
+                    &rt,
+                    serve_embedded(server_repo, listener, ready_tx, shutdown_rx),
+                );
+            })
+            .context("spawn embedded server thread")?;
+        let mut embedded = Self {
+            address,
+            repo,
+            temporary,
+            shutdown: Some(shutdown_tx),
+            thread: Some(thread),
+        };
+        match ready_rx.recv() {
+            Ok(Ok(())) => Ok(embedded),
+            Ok(Err(err)) => Err(err),
+            Err(_) => {
+                embedded.stop();
+                bail!("embedded server thread exited before it was ready")
+            }
+        }
+    }
+
+    /// Where clients connect, e.g. with `TandemClient::connect`.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// The repo being served; for a temporary repo, gone once this drops.
+    pub fn repo(&self) -> &Path {
+        &self.repo
+    }
+
+    fn stop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        if self.temporary {
+            let _ = fs::remove_dir_all(&self.repo);
+        }
+    }
+}
+
+impl Drop for EmbeddedServer {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// Answer `local://` addresses (local-mode workspaces, `tandem init
+/// --local`) from an embedded server per repo, started on first connect and
+/// kept for the rest of the process.
+pub fn serve_local_repos() {
+    rpc::serve_local_repos_with(local_server_address);
+}
+
+fn local_server_address(repo: &Path) -> Result<String> {
+    static SERVERS: Mutex<BTreeMap<PathBuf, EmbeddedServer>> = Mutex::new(BTreeMap::new());
+    let mut servers = SERVERS.lock().unwrap();
+    if let Some(server) = servers.get(repo) {
+        return Ok(server.address().to_string());
+    }
+    let server = EmbeddedServer::start_in_process(Some(repo))?;
+    let address = server.address().to_string();
+    servers.insert(repo.to_path_buf(), server);
+    Ok(address)
+}
+
+async fn serve_embedded(
+    repo: PathBuf,
+    mut listener: rpc::InProcessListener,
+    ready: std::sync::mpsc::Sender<Result<()>>,
+    mut shutdown: tokio::sync::oneshot::Receiver<()>,
+) {
+    let server = match Server::new(
+        repo,
+        false,
+        AutoAdvancePolicy::default(),
+        ProtectedPaths::default(),
+        None,
+        false,
+    ) {
+        Ok(server) => Rc::new(server),
+        Err(err) => {
+            let _ = ready.send(Err(err));
+            return;
+        }
+    };
+    tracing::debug!(listen_addr = %listener.local_addr(), "embedded server listening");
+    let _ = ready.send(Ok(()));
+    let mut next_conn_id = 0;
+    loop {
+        let stream = tokio::select! {
+            _ = &mut shutdown => break,
+            stream = listener.accept() => match stream {
+                Some(stream) => stream,
+                None => break,
+            },
+        };
+        let conn_id = next_conn_id;
+        next_conn_id += 1;
+        let server = Rc::clone(&server);
+        tokio::task::spawn_local(async move {
+            let stream = rpc::RpcStream::InProcess(stream);
+            if let Err(err) = handle_capnp_connection(server, stream, conn_id).await {
+                tracing::debug!(conn_id, error = %err, "embedded rpc connection error");
+            }
+        });
+    }
+    tracing::debug!(listen_addr = %listener.local_addr(), "embedded server stopped");
+}
+
 // ─── Connection handler ───────────────────────────────────────────────────────
 
 async fn handle_capnp_connection(
     server: Rc<Server>,
-    stream: tokio::net::TcpStream,
+    stream: rpc::RpcStream,
     conn_id: u64,
 ) -> Result<()> {
     use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
@@ -210,13 +998,20 @@ async fn handle_capnp_connection(
         reader.compat(),
         writer.compat_write(),
         rpc_twoparty_capnp::Side::Server,
-        Default::default(),
+        limits::reader_options(),
     );
     let store_impl = StoreImpl {
         server: server.clone(),
         conn_id,
     };
-    let store_client: store::Client = capnp_rpc::new_client(store_impl);
+    let store = FaultyStore::new(
+        <store::Client as capnp::capability::FromServer<StoreImpl>>::from_server(store_impl),
+        server.faults.clone(),
+    );
+    let store_client = match &server.watchdog {
+        Some(watchdog) => rpc_record::serve(watchdog.serve(store, STORE_METHODS)),
+        None => rpc_record::serve(store),
+    };
     let rpc_system = RpcSystem::new(Box::new(network), Some(store_client.client));
     tracing::debug!(conn_id, "rpc session started");
     rpc_system.await?;
@@ -229,6 +1024,8 @@ async fn handle_capnp_connection(
 struct WatcherEntry {
     watcher: head_watcher::Client,
     after_version: u64,
+    /// Touched-path limit for watchers that asked for heads summaries.
+    summary_path_limit: Option<usize>,
 }
 
 struct Server {
@@ -238,16 +1035,107 @@ struct Server {
     repo_loader: jj_lib::repo::RepoLoader,
     /// Path to `.jj/repo/op_store/` for operations and views.
     op_store_path: PathBuf,
+    /// Operations live in id-prefix shards (`--shard-operations`).
+    shard_operations: bool,
     /// jj-lib op heads store — single authority for operation heads.
     op_heads_store: Arc<dyn jj_lib::op_heads_store::OpHeadsStore>,
     /// Path to `.jj/repo/tandem/` for tandem metadata sidecar (CAS/workspace map).
     tandem_dir: PathBuf,
+    /// Working-copy root of the served repo (where check commands run).
+    repo_root: PathBuf,
     integration_enabled: bool,
+    auto_advance: AutoAdvancePolicy,
+    protected_paths: ProtectedPaths,
+    /// Set on read replicas: the primary this server mirrors.
+    primary: Option<String>,
+    /// Operator-set maintenance window; shared with the control socket.
+    maintenance: Maintenance,
+    /// Journal of recent heads-version transitions (`heads-history.jsonl`).
+    heads_history: HeadsHistory,
+    /// Tags per operation (`op-tags.jsonl`).
+    op_tags: OperationTags,
+    /// Active `beginSnapshot` holds; heads stay put while any is live.
+    snapshots: SnapshotHolds,
+    /// Advisory `lockChange` locks, by change id.
+    change_locks: ChangeLocks,
+    /// Path overlaps between workspaces, with the heads version they were
+    /// computed at.
+    path_overlaps: Mutex<Option<(u64, Vec<rpc::PathOverlap>)>>,
+    /// Where the repo moved to, if it did; shared with the control socket.
+    redirect: Redirect,
+    /// Injected faults (`tandem server faults`); shared with the control socket.
+    faults: Faults,
+    notifications: Notifications,
+    /// Advertised in repo info so `tandem init --colocate` can fetch from it.
+    git_remote: Option<String>,
+    /// Advertised in repo info so `tandem init` writes it as
+    /// `snapshot.auto-track`.
+    snapshot_auto_track: Option<String>,
+    /// Per-workspace FIFO for `updateOpHeads`, when `--fair-queue` is set.
+    fair_queue: Option<FairQueue>,
+    /// Lost-race rate, and the backoff floor handed out during a storm.
+    cas_storm: CasStorm,
+    /// Idempotency keys of applied head updates, for retried requests.
+    applied_updates: AppliedUpdates<UpdateResult>,
+    /// Bounds how long a store call may run, unless `--rpc-timeout 0`.
+    watchdog: Option<Watchdog>,
+    /// Which writes are still admitted once shutdown begins.
+    drain: Drain,
     integration_trigger: Mutex<Option<tokio::sync::mpsc::UnboundedSender<()>>>,
-    lock: Mutex<()>,
+    /// Guards `heads.json`; also held against a server taking over from this one.
+    lock: HeadsLock,
     watchers: Mutex<Vec<WatcherEntry>>,
 }
 
+/// Signals that stop the server: SIGINT/SIGTERM on Unix, Ctrl+C/Ctrl+Break
+/// on Windows.
+#[cfg(unix)]
+struct ShutdownSignals {
+    interrupt: tokio::signal::unix::Signal,
+    terminate: tokio::signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl ShutdownSignals {
+    fn install() -> Self {
+        use tokio::signal::unix::{signal, SignalKind};
+        Self {
+            interrupt: signal(SignalKind::interrupt()).expect("install SIGINT handler"),
+            terminate: signal(SignalKind::terminate()).expect("install SIGTERM handler"),
+        }
+    }
+
+    async fn recv(&mut self) {
+        tokio::select! {
+            _ = self.interrupt.recv() => {},
+            _ = self.terminate.recv() => {},
+        }
+    }
+}
+
+#[cfg(windows)]
+struct ShutdownSignals {
+    ctrl_c: tokio::signal::windows::CtrlC,
+    ctrl_break: tokio::signal::windows::CtrlBreak,
+}
+
+#[cfg(windows)]
+impl ShutdownSignals {
+    fn install() -> Self {
+        Self {
+            ctrl_c: tokio::signal::windows::ctrl_c().expect("install Ctrl+C handler"),
+            ctrl_break: tokio::signal::windows::ctrl_break().expect("install Ctrl+Break handler"),
+        }
+    }
+
+    async fn recv(&mut self) {
+        tokio::select! {
+            _ = self.ctrl_c.recv() => {},
+            _ = self.ctrl_break.recv() => {},
+        }
+    }
+}
+
 /// Convert raw bytes to hex string (for filesystem paths)
 fn to_hex(bytes: &[u8]) -> String {
     bytes.iter().map(|b| format!("{b:02x}")).collect()
@@ -265,7 +1153,14 @@ fn from_hex(hex: &str) -> Result<Vec<u8>> {
 }
 
 impl Server {
-    fn new(repo: PathBuf, integration_enabled: bool) -> Result<Self> {
+    fn new(
+        repo: PathBuf,
+        integration_enabled: bool,
+        auto_advance: AutoAdvancePolicy,
+        protected_paths: ProtectedPaths,
+        primary: Option<String>,
+        shard_operations: bool,
+    ) -> Result<Self> {
         fs::create_dir_all(&repo)?;
 
         if !repo.join(".jj").exists() {
@@ -275,9 +1170,31 @@ impl Server {
         let repo_dir = dunce::canonicalize(repo.join(".jj/repo"))
             .with_context(|| format!("cannot canonicalize .jj/repo at {}", repo.display()))?;
         let op_store_path = repo_dir.join("op_store");
+        let repo_root = dunce::canonicalize(&repo)
+            .with_context(|| format!("cannot canonicalize repo at {}", repo.display()))?;
+
+        if shard_operations {
+            if sharded_op_store::adopt(&op_store_path).with_context(|| {
+                format!("switch {} to sharded operations", op_store_path.display())
+            })? {
+                tracing::info!(
+                    op_store = %op_store_path.display(),
+                    "operations are now sharded by id prefix; existing ones move as they are read"
+                );
+            }
+        } else if let Some(moved) = sharded_op_store::flatten(&op_store_path)
+            .with_context(|| format!("move {} back to flat operations", op_store_path.display()))?
+        {
+            tracing::info!(
+                op_store = %op_store_path.display(),
+                moved,
+                "operations are back in jj's flat layout (serve without --shard-operations)"
+            );
+        }
 
         let settings = Self::user_settings()?;
-        let factories = jj_lib::repo::StoreFactories::default();
+        let mut factories = jj_lib::repo::StoreFactories::default();
+        tandem_jj_store::register(&mut factories);
         let loader =
             jj_lib::repo::RepoLoader::init_from_file_system(&settings, &repo_dir, &factories)
                 .context("load jj repo state")?;
@@ -286,25 +1203,72 @@ impl Server {
         let tandem_dir = repo_dir.join("tandem");
         fs::create_dir_all(&tandem_dir)?;
 
-        let metadata_path = tandem_dir.join("heads.json");
+        for upgraded in metadata_schema::upgrade(&tandem_dir)? {
+            tracing::info!(
+                file = upgraded.file,
+                from = upgraded.from,
+                to = upgraded.to,
+                changes = ?upgraded.changes,
+                backup = %upgraded.backup.display(),
+                "upgraded tandem metadata schema"
+            );
+        }
+        let metadata_path = tandem_dir.join(metadata_schema::HEADS.file);
         if !metadata_path.exists() {
             let initial = HeadsMetadata {
                 version: 0,
                 workspace_heads: BTreeMap::new(),
+                ephemeral_workspaces: BTreeMap::new(),
             };
-            fs::write(&metadata_path, serde_json::to_vec_pretty(&initial)?)?;
+            metadata_schema::write(&metadata_schema::HEADS, &metadata_path, &initial)?;
+        }
+
+        let heads_history = HeadsHistory::open(
+            tandem_dir.join(metadata_schema::HEADS_HISTORY.file),
+            heads_history::DEFAULT_CAPACITY,
+        )?;
+        let op_tags = OperationTags::open(&tandem_dir)?;
+
+        let redirect = Redirect::load(tandem_dir.join(metadata_schema::REDIRECT.file))?;
+        if let Some(address) = redirect.address() {
+            tracing::warn!(
+                address = %address,
+                "repo has moved; redirecting clients and refusing writes"
+            );
         }
 
+        let lock = HeadsLock::open(&tandem_dir)?;
         let op_heads_store = loader.op_heads_store().clone();
         let mut server = Self {
             store: loader.store().clone(),
             repo_loader: loader,
             op_store_path,
+            shard_operations,
             op_heads_store,
             tandem_dir,
+            repo_root,
             integration_enabled,
+            auto_advance,
+            protected_paths,
+            primary,
+            maintenance: Maintenance::default(),
+            heads_history,
+            op_tags,
+            snapshots: SnapshotHolds::default(),
+            change_locks: ChangeLocks::default(),
+            path_overlaps: Mutex::new(None),
+            redirect,
+            faults: Faults::default(),
+            notifications: Notifications::default(),
+            git_remote: None,
+            snapshot_auto_track: None,
+            fair_queue: None,
+            cas_storm: CasStorm::new(cas_storm::DEFAULT_THRESHOLD),
+            applied_updates: AppliedUpdates::default(),
+            watchdog: None,
+            drain: Drain::default(),
             integration_trigger: Mutex::new(None),
-            lock: Mutex::new(()),
+            lock,
             watchers: Mutex::new(Vec::new()),
         };
         server.initialize_integration_metadata()?;
@@ -332,6 +1296,104 @@ impl Server {
         Ok(())
     }
 
+    /// Probe the op-heads store, the repo at head, and the git backend.
+    fn health_sync(&self) -> HealthResponse {
+        let op_heads = self.read_jj_op_heads().and_then(|heads| {
+            if heads.is_empty() {
+                bail!("op-heads store has no heads");
+            }
+            Ok(Some(format!("{} head(s)", heads.len())))
+        });
+        let repo = self.repo_loader.load_at_head().context("load repo at head");
+        let git = match &repo {
+            Ok(repo) => {
+                // Straight from the backend: the store's commit cache would
+                // hide a git object database that has gone away.
+                let root = self.store.root_commit_id();
+                match repo.view().heads().iter().find(|id| *id != root) {
+                    Some(id) => pollster::block_on(self.store.backend().read_commit(id))
+                        .map(|_| Some(format!("read commit {}", id.hex())))
+                        .map_err(|e| anyhow!("read commit {}: {e}", id.hex())),
+                    None => Ok(Some("no commits yet".to_string())),
+                }
+            }
+            Err(_) => Err(anyhow!("skipped: repo did not load")),
+        };
+        let repo = repo.map(|repo| Some(format!("operation {}", repo.op_id().hex())));
+        HealthResponse::from_checks(vec![
+            HealthCheck::from_result("op-heads", op_heads),
+            HealthCheck::from_result("repo", repo),
+            HealthCheck::from_result("git", git),
+        ])
+    }
+
+    /// The status file's view of the server: heads, watchers, and health.
+    fn status_snapshot_sync(
+        &self,
+        file: &status_file::StatusFile,
+        running: bool,
+        connections: status_file::ConnectionStats,
+    ) -> status_file::StatusSnapshot {
+        let mut snapshot = file.snapshot(running, connections);
+        if let Ok(metadata) = self.read_heads_metadata() {
+            snapshot.heads_version = Some(metadata.version);
+            snapshot.workspaces = Some(metadata.workspace_heads.len());
+        }
+        snapshot.op_heads = self.read_jj_op_heads().ok().map(|heads| heads.len());
+        snapshot.watchers = self.watchers.lock().unwrap().len();
+        snapshot.health = self.health_sync();
+        snapshot.maintenance = self.maintenance.active();
+        snapshot.redirect = self.redirect.current();
+        snapshot
+    }
+
+    /// Answer a dashboard API request. Reads only: unlike `getHeads`, the
+    /// heads are not reconciled first.
+    fn api_reply_sync(
+        &self,
+        target: api::ApiTarget,
+        started: Instant,
+        connections: status_file::ConnectionStats,
+    ) -> http::Reply {
+        let result = self
+            .read_heads_metadata()
+            .and_then(|metadata| match target {
+                api::ApiTarget::Heads => Ok(http::Reply::json(&api::Heads {
+                    version: metadata.version,
+                    heads: self.read_jj_op_heads()?,
+                    workspace_heads: metadata.workspace_heads,
+                })),
+                api::ApiTarget::Workspaces => {
+                    let repo = self
+                        .repo_loader
+                        .load_at_head()
+                        .context("load repo at head")?;
+                    Ok(http::Reply::json(&api::workspaces(
+                        &repo,
+                        metadata.version,
+                        &metadata.workspace_heads,
+                        &metadata.ephemeral_workspaces,
+                    )?))
+                }
+                api::ApiTarget::Stats => Ok(http::Reply::json(&api::Stats {
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    uptime_secs: started.elapsed().as_secs(),
+                    heads_version: metadata.version,
+                    op_heads: self.read_jj_op_heads()?.len(),
+                    workspaces: metadata.workspace_heads.len(),
+                    ephemeral_workspaces: metadata.ephemeral_workspaces.len(),
+                    connections,
+                    watchers: self.watchers.lock().unwrap().len(),
+                    replica: self.primary.is_some(),
+                    maintenance: self.maintenance.active(),
+                })),
+            });
+        result.unwrap_or_else(|err| {
+            tracing::warn!(error = %format!("{err:#}"), "http api request failed");
+            http::Reply::error("500 Internal Server Error", &format!("{err:#}"))
+        })
+    }
+
     fn read_jj_op_heads(&self) -> Result<Vec<String>> {
         let ids = pollster::block_on(self.op_heads_store.get_op_heads())
             .map_err(|e| anyhow!("read op heads: {e}"))?;
@@ -386,10 +1448,25 @@ impl Server {
             return Ok((before, false));
         }
 
-        let merged_op = self
-            .repo_loader
-            .merge_operations(operations, Some("reconcile divergent operations"))
-            .context("reconcile divergent operation heads")?;
+        // A workspace head the others already descend from adds nothing;
+        // merging it anyway would leave a merge operation with the same view
+        // as its other parent, as jj does not when it resolves op heads.
+        let mut operations: Vec<jj_lib::operation::Operation> = jj_lib::dag_walk::heads_ok(
+            operations.into_iter().map(Ok),
+            |op: &jj_lib::operation::Operation| op.id().clone(),
+            |op: &jj_lib::operation::Operation| op.parents().collect::<Vec<_>>(),
+        )
+        .map_err(|e| anyhow!("find divergent operation heads: {e}"))?
+        .into_iter()
+        .collect();
+        operations.sort_by_key(|op| op.metadata().time.end.timestamp);
+        let merged_op = match <[_; 1]>::try_from(operations) {
+            Ok([head]) => head,
+            Err(operations) => self
+                .repo_loader
+                .merge_operations(operations, Some("reconcile divergent operations"))
+                .context("reconcile divergent operation heads")?,
+        };
 
         let mut old_ids = Vec::new();
         for op_hex in candidate_hex {
@@ -420,7 +1497,7 @@ impl Server {
     }
 
     fn integration_metadata_path(&self) -> PathBuf {
-        self.tandem_dir.join("integration.json")
+        self.tandem_dir.join(metadata_schema::INTEGRATION.file)
     }
 
     fn initialize_integration_metadata(&mut self) -> Result<()> {
@@ -438,6 +1515,7 @@ impl Server {
                     last_error: None,
                     updated_at: Some(now_epoch_secs_string()),
                     workspace_commit_count: Some(0),
+                    last_auto_advance: None,
                 });
         metadata.enabled = self.integration_enabled;
         if !self.integration_enabled {
@@ -447,16 +1525,18 @@ impl Server {
     }
 
     fn read_integration_metadata(&self) -> Result<IntegrationMetadata> {
-        let bytes = fs::read(self.integration_metadata_path())?;
-        Ok(serde_json::from_slice(&bytes)?)
+        metadata_schema::read(
+            &metadata_schema::INTEGRATION,
+            &self.integration_metadata_path(),
+        )
     }
 
     fn write_integration_metadata(&self, metadata: &IntegrationMetadata) -> Result<()> {
-        fs::write(
-            self.integration_metadata_path(),
-            serde_json::to_vec_pretty(metadata)?,
-        )?;
-        Ok(())
+        metadata_schema::write(
+            &metadata_schema::INTEGRATION,
+            &self.integration_metadata_path(),
+            metadata,
+        )
     }
 
     fn record_integration_error(&self, err: &anyhow::Error) {
@@ -470,10 +1550,18 @@ impl Server {
                 last_error: None,
                 updated_at: None,
                 workspace_commit_count: None,
+                last_auto_advance: None,
+            });
+        let error = format!("{err:#}");
+        // Recomputes retry on every head update; only a new error is news.
+        if metadata.last_status != "error" || metadata.last_error.as_deref() != Some(&error) {
+            self.notifications.emit(Event::IntegrationFailed {
+                error: error.clone(),
             });
+        }
         metadata.enabled = self.integration_enabled;
         metadata.last_status = "error".to_string();
-        metadata.last_error = Some(format!("{err:#}"));
+        metadata.last_error = Some(error);
         metadata.updated_at = Some(now_epoch_secs_string());
         if let Err(write_err) = self.write_integration_metadata(&metadata) {
             tracing::error!(error = %write_err, "failed to persist integration error metadata");
@@ -495,9 +1583,14 @@ impl Server {
             while rx.recv().await.is_some() {
                 tokio::time::sleep(tokio::time::Duration::from_millis(250)).await;
                 while rx.try_recv().is_ok() {}
-                if let Err(err) = server.recompute_integration_bookmark() {
-                    tracing::error!(error = %err, "integration recompute failed");
-                    server.record_integration_error(&err);
+                server.snapshots.released().await;
+                match server.recompute_integration_bookmark() {
+                    Ok(Some(clean_commit)) => server.run_auto_advance(&clean_commit).await,
+                    Ok(None) => {}
+                    Err(err) => {
+                        tracing::error!(error = %err, "integration recompute failed");
+                        server.record_integration_error(&err);
+                    }
                 }
             }
             tracing::info!("integration worker stopped");
@@ -511,9 +1604,11 @@ impl Server {
         }
     }
 
-    fn recompute_integration_bookmark(&self) -> Result<()> {
+    /// Returns the new integration commit when it is clean, so auto-advance
+    /// policies can act on it.
+    fn recompute_integration_bookmark(&self) -> Result<Option<CommitId>> {
         if !self.integration_enabled {
-            return Ok(());
+            return Ok(None);
         }
 
         let workspace_heads = {
@@ -533,6 +1628,7 @@ impl Server {
                     last_error: None,
                     updated_at: None,
                     workspace_commit_count: Some(0),
+                    last_auto_advance: None,
                 });
         metadata.enabled = true;
 
@@ -544,14 +1640,14 @@ impl Server {
             metadata.updated_at = Some(now_epoch_secs_string());
             self.write_integration_metadata(&metadata)?;
             tracing::debug!("integration recompute skipped: no workspace commits");
-            return Ok(());
+            return Ok(None);
         }
 
         let already_current = metadata.last_input_fingerprint.as_deref()
             == Some(&input_fingerprint)
             && matches!(metadata.last_status.as_str(), "clean" | "conflicted");
         if already_current {
-            return Ok(());
+            return Ok(None);
         }
 
         let mut parent_hexes: Vec<String> = workspace_commits.values().cloned().collect();
@@ -590,24 +1686,8 @@ impl Server {
         let unpublished = tx
             .write("integration workspace recompute")
             .map_err(|e| anyhow!("write integration operation: {e}"))?;
-
-        {
-            let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
-            unpublished
-                .publish()
-                .map_err(|e| anyhow!("publish integration operation: {e}"))?;
-
-            let heads_metadata = self.read_heads_metadata()?;
-            let next_heads = self.read_jj_op_heads()?;
-            let next_metadata = HeadsMetadata {
-                version: heads_metadata.version + 1,
-                workspace_heads: heads_metadata.workspace_heads,
-            };
-            self.write_heads_metadata(&next_metadata)?;
-            let heads_bytes: Vec<Vec<u8>> =
-                next_heads.iter().filter_map(|h| from_hex(h).ok()).collect();
-            self.notify_watchers(next_metadata.version, &heads_bytes);
-        }
+        self.publish_server_operation(unpublished)
+            .context("publish integration operation")?;
 
         metadata.last_input_fingerprint = Some(input_fingerprint);
         metadata.last_integration_commit = Some(integration_commit.id().hex());
@@ -627,6 +1707,379 @@ impl Server {
             workspace_commits = workspace_commits.len(),
             "integration recompute completed"
         );
+        if integration_commit.has_conflict() {
+            Ok(None)
+        } else {
+            Ok(Some(integration_commit.id().clone()))
+        }
+    }
+
+    /// Publish an operation written by the server itself, bump the heads
+    /// version, and notify watchers.
+    fn publish_server_operation(
+        &self,
+        unpublished: jj_lib::transaction::UnpublishedOperation,
+    ) -> Result<()> {
+        let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+        unpublished.publish().map_err(|e| anyhow!("publish: {e}"))?;
+
+        let heads_metadata = self.read_heads_metadata()?;
+        let next_heads = self.read_jj_op_heads()?;
+        let next_metadata = HeadsMetadata {
+            version: heads_metadata.version + 1,
+            workspace_heads: heads_metadata.workspace_heads,
+            ephemeral_workspaces: heads_metadata.ephemeral_workspaces,
+        };
+        self.write_heads_metadata(&next_metadata)?;
+        let heads_bytes: Vec<Vec<u8>> =
+            next_heads.iter().filter_map(|h| from_hex(h).ok()).collect();
+        self.announce_heads(next_metadata.version, &heads_bytes, None, &BTreeMap::new());
+        Ok(())
+    }
+
+    // ─── Bookmark auto-advance ───────────────────────────────────────
+
+    async fn run_auto_advance(&self, integration_commit: &CommitId) {
+        if self.auto_advance.bookmarks.is_empty() {
+            return;
+        }
+
+        let outcome = match self.run_auto_advance_check(integration_commit).await {
+            Ok(()) => match self.advance_bookmarks(integration_commit) {
+                Ok(true) => format!(
+                    "advanced {} to {}",
+                    self.auto_advance.bookmarks.join(","),
+                    integration_commit.hex()
+                ),
+                Ok(false) => "skipped: integration moved during check".to_string(),
+                Err(err) => format!("error: {err:#}"),
+            },
+            Err(err) => format!("check failed: {err:#}"),
+        };
+        tracing::info!(
+            integration_commit = %integration_commit.hex(),
+            outcome = %outcome,
+            "bookmark auto-advance"
+        );
+
+        match self.read_integration_metadata() {
+            Ok(mut metadata) => {
+                metadata.last_auto_advance = Some(outcome);
+                metadata.updated_at = Some(now_epoch_secs_string());
+                if let Err(err) = self.write_integration_metadata(&metadata) {
+                    tracing::error!(error = %err, "failed to persist auto-advance metadata");
+                }
+            }
+            Err(err) => tracing::error!(error = %err, "failed to read integration metadata"),
+        }
+    }
+
+    async fn run_auto_advance_check(&self, integration_commit: &CommitId) -> Result<()> {
+        let Some(command) = self.auto_advance.check_command.as_deref() else {
+            return Ok(());
+        };
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = tokio::process::Command::new("cmd");
+            cmd.args(["/C", command]);
+            cmd
+        } else {
+            let mut cmd = tokio::process::Command::new("sh");
+            cmd.args(["-c", command]);
+            cmd
+        };
+        let status = cmd
+            .current_dir(&self.repo_root)
+            .env("TANDEM_INTEGRATION_COMMIT", integration_commit.hex())
+            .stdin(std::process::Stdio::null())
+            .status()
+            .await
+            .with_context(|| format!("cannot run check command {command:?}"))?;
+        if !status.success() {
+            bail!("{command:?} exited with {status}");
+        }
+        Ok(())
+    }
+
+    /// Point every auto-advance bookmark at `integration_commit`. Returns
+    /// `false` without writing when `integration` has moved on since.
+    fn advance_bookmarks(&self, integration_commit: &CommitId) -> Result<bool> {
+        let readonly_repo = self
+            .repo_loader
+            .load_at_head()
+            .context("load repo at head")?;
+        let current = readonly_repo
+            .view()
+            .get_local_bookmark("integration".as_ref())
+            .as_normal()
+            .cloned();
+        if current.as_ref() != Some(integration_commit) {
+            return Ok(false);
+        }
+
+        let mut tx = readonly_repo.start_transaction();
+        for bookmark in &self.auto_advance.bookmarks {
+            tx.repo_mut().set_local_bookmark_target(
+                bookmark.as_str().as_ref(),
+                RefTarget::normal(integration_commit.clone()),
+            );
+        }
+        let unpublished = tx
+            .write(format!(
+                "auto-advance {} to integration",
+                self.auto_advance.bookmarks.join(", ")
+            ))
+            .map_err(|e| anyhow!("write auto-advance operation: {e}"))?;
+        self.publish_server_operation(unpublished)
+            .context("publish auto-advance operation")?;
+        Ok(true)
+    }
+
+    // ─── Operator rollback ───────────────────────────────────────────
+
+    /// Restore the view recorded by the operation matching `hex_prefix` as a
+    /// new operation on top of the current heads, so the rollback can itself
+    /// be undone. Workspace heads are repointed at the new operation so the
+    /// integration recompute does not resurrect rolled-back commits.
+    fn undo_remote_sync(&self, hex_prefix: &str) -> Result<control::UndoRemoteResponse> {
+        self.ensure_writable()?;
+        let target_id = match self.resolve_operation_id_prefix_sync(hex_prefix)? {
+            (_, Some(id)) => OperationId::new(id),
+            (resolution, None) if resolution == "ambiguous" => {
+                bail!("operation prefix {hex_prefix} is ambiguous")
+            }
+            _ => bail!("no operation matches {hex_prefix}"),
+        };
+        let target_hex = target_id.hex();
+
+        let op_store = self.repo_loader.op_store();
+        let target_op = pollster::block_on(op_store.read_operation(&target_id))
+            .map_err(|e| anyhow!("read operation {target_hex}: {e}"))?;
+        let target_view = pollster::block_on(op_store.read_view(&target_op.view_id))
+            .map_err(|e| anyhow!("read view for operation {target_hex}: {e}"))?;
+
+        let readonly_repo = self
+            .repo_loader
+            .load_at_head()
+            .context("load repo at head")?;
+        if readonly_repo.op_id() == &target_id {
+            bail!("operation {target_hex} is already the current head");
+        }
+        let mut tx = readonly_repo.start_transaction();
+        tx.repo_mut().set_view(target_view);
+        let unpublished = tx
+            .write(format!(
+                "undo-remote: restore to operation {}",
+                &target_hex[..12]
+            ))
+            .map_err(|e| anyhow!("write undo-remote operation: {e}"))?;
+        let new_hex = unpublished.operation().id().hex();
+
+        {
+            let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+            unpublished.publish().map_err(|e| anyhow!("publish: {e}"))?;
+
+            let heads_metadata = self.read_heads_metadata()?;
+            let next_metadata = HeadsMetadata {
+                version: heads_metadata.version + 1,
+                workspace_heads: heads_metadata
+                    .workspace_heads
+                    .into_keys()
+                    .map(|workspace_id| (workspace_id, new_hex.clone()))
+                    .collect(),
+                ephemeral_workspaces: heads_metadata.ephemeral_workspaces,
+            };
+            self.write_heads_metadata(&next_metadata)?;
+            let heads_bytes: Vec<Vec<u8>> = self
+                .read_jj_op_heads()?
+                .iter()
+                .filter_map(|h| from_hex(h).ok())
+                .collect();
+            self.announce_heads(next_metadata.version, &heads_bytes, None, &BTreeMap::new());
+        }
+
+        tracing::warn!(
+            restored_operation = %target_hex,
+            new_operation = %new_hex,
+            "undo-remote restored server view"
+        );
+        if self.integration_enabled {
+            self.enqueue_integration_recompute();
+        }
+        Ok(control::UndoRemoteResponse {
+            restored_operation: target_hex,
+            new_operation: new_hex,
+        })
+    }
+
+    /// Forget every ephemeral workspace whose TTL has elapsed: drop its
+    /// working-copy commit from the view and its `workspace_heads` entry.
+    fn expire_ephemeral_workspaces_sync(&self) -> Result<()> {
+        let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+        let heads_metadata = self.read_heads_metadata()?;
+        let expired = ephemeral::expired(&heads_metadata.ephemeral_workspaces, now_epoch_secs());
+        if expired.is_empty() {
+            return Ok(());
+        }
+
+        let readonly_repo = self
+            .repo_loader
+            .load_at_head()
+            .context("load repo at head")?;
+        let mut tx = readonly_repo.start_transaction();
+        for workspace_id in &expired {
+            let name = jj_lib::ref_name::WorkspaceNameBuf::from(workspace_id.clone());
+            if tx.repo().view().get_wc_commit_id(&name).is_some() {
+                tx.repo_mut()
+                    .remove_wc_commit(&name)
+                    .map_err(|e| anyhow!("forget workspace {workspace_id}: {e}"))?;
+            }
+        }
+        // Forgetting a workspace abandons its working-copy commit; anything
+        // built on it moves to the commit's parents.
+        tx.repo_mut()
+            .rebase_descendants()
+            .map_err(|e| anyhow!("rebase descendants of expired working copies: {e}"))?;
+        if tx.repo().has_changes() {
+            tx.write(format!(
+                "expire ephemeral workspaces: {}",
+                expired.join(", ")
+            ))
+            .map_err(|e| anyhow!("write expiry operation: {e}"))?
+            .publish()
+            .map_err(|e| anyhow!("publish: {e}"))?;
+        }
+
+        let mut next_metadata = heads_metadata;
+        next_metadata.version += 1;
+        for workspace_id in &expired {
+            next_metadata.workspace_heads.remove(workspace_id);
+            next_metadata.ephemeral_workspaces.remove(workspace_id);
+        }
+        self.write_heads_metadata(&next_metadata)?;
+        let heads_bytes: Vec<Vec<u8>> = self
+            .read_jj_op_heads()?
+            .iter()
+            .filter_map(|h| from_hex(h).ok())
+            .collect();
+        self.announce_heads(next_metadata.version, &heads_bytes, None, &BTreeMap::new());
+
+        tracing::info!(workspaces = ?expired, "expired ephemeral workspaces");
+        if self.integration_enabled {
+            self.enqueue_integration_recompute();
+        }
+        Ok(())
+    }
+
+    /// Rewrite the op log down to what `policy` keeps and delete the
+    /// operations and views that are no longer reachable. Does nothing while
+    /// the log is within the policy's slack.
+    fn apply_op_retention_sync(&self, policy: OpRetention) -> Result<()> {
+        self.ensure_writable()?;
+        // Anything written before the pass starts and left unreachable by it
+        // builds on the old history, and its update would be turned away by
+        // the tombstone anyway.
+        let started = SystemTime::now();
+        let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+        let heads = self.read_jj_op_heads()?;
+        let [head_hex] = heads.as_slice() else {
+            tracing::debug!(
+                heads = heads.len(),
+                "op retention waits for a single op head"
+            );
+            return Ok(());
+        };
+        let op_store = self.repo_loader.op_store();
+        let head = self
+            .repo_loader
+            .load_operation(&OperationId::new(from_hex(head_hex)?))
+            .map_err(|e| anyhow!("load head operation {head_hex}: {e}"))?;
+        let operations: Vec<jj_lib::operation::Operation> =
+            jj_lib::op_walk::walk_ancestors(std::slice::from_ref(&head))
+                .filter(|op| {
+                    op.as_ref()
+                        .map_or(true, |op| op.id() != op_store.root_operation_id())
+                })
+                .collect::<Result<_, _>>()
+                .map_err(|e| anyhow!("walk op log: {e}"))?;
+        let end_millis: Vec<i64> = operations
+            .iter()
+            .map(|op| op.metadata().time.end.timestamp.0)
+            .collect();
+        let keeps = policy.keeps(&end_millis, now_epoch_millis() as i64);
+        let kept: Vec<&jj_lib::operation::Operation> = operations
+            .iter()
+            .zip(&keeps)
+            .filter_map(|(op, &keep)| keep.then_some(op))
+            .collect();
+        let pruned = operations.len() - kept.len();
+        if !op_retention::worth_pruning(kept.len(), pruned) {
+            return Ok(());
+        }
+
+        let rewritten = op_retention::rewrite(op_store.as_ref(), &kept)?;
+        let new_head = rewritten[head.id()].clone();
+        let new_hex = new_head.hex();
+        pollster::block_on(
+            self.op_heads_store
+                .update_op_heads(std::slice::from_ref(head.id()), &new_head),
+        )
+        .map_err(|e| anyhow!("update op heads via jj-lib: {e}"))?;
+        // No kept id survives the rewrite. Tombstone the ids clients may
+        // still build on, the head and each workspace's head, so they hear
+        // where their operation went rather than that it is missing.
+        let mut metadata = self.read_heads_metadata()?;
+        let mut replaced = vec![(head.id().clone(), new_hex.clone())];
+        for op_hex in metadata.workspace_heads.values_mut() {
+            let old = from_hex(op_hex).ok().map(OperationId::new);
+            let new = old
+                .as_ref()
+                .and_then(|id| rewritten.get(id))
+                .map_or_else(|| new_hex.clone(), |id| id.hex());
+            if let Some(old) = old.filter(|old| old != head.id() && rewritten.contains_key(old)) {
+                replaced.push((old, new.clone()));
+            }
+            *op_hex = new;
+        }
+        self.record_abandoned_operations(&replaced)?;
+
+        // Carry tags over to the rewritten ids and drop the pruned ones';
+        // tags of operations outside the old log (not yet published) stay.
+        self.op_tags.rewrite(|tags| {
+            for op in &operations {
+                if let Some(op_tags) = tags.remove(&op.id().hex()) {
+                    if let Some(new_id) = rewritten.get(op.id()) {
+                        tags.insert(new_id.hex(), op_tags);
+                    }
+                }
+            }
+        })?;
+
+        metadata.version += 1;
+        self.write_heads_metadata(&metadata)?;
+        self.announce_heads(
+            metadata.version,
+            &[new_head.to_bytes()],
+            None,
+            &BTreeMap::new(),
+        );
+
+        op_store
+            .gc(std::slice::from_ref(&new_head), started)
+            .map_err(|e| anyhow!("delete unreachable operations: {e}"))?;
+        tracing::warn!(
+            policy = %policy,
+            kept = kept.len(),
+            pruned,
+            previous_head = %head.id().hex(),
+            new_head = %new_hex,
+            "pruned operations beyond the retention policy; every kept operation has a new id"
+        );
+        self.notifications.emit(Event::Gc {
+            kept: kept.len(),
+            pruned,
+            head: new_hex,
+            previous_head: head.id().hex(),
+        });
         Ok(())
     }
 
@@ -677,7 +2130,7 @@ impl Server {
         match kind {
             "file" => {
                 let file_id = jj_lib::backend::FileId::new(id.to_vec());
-                let mut reader = pollster::block_on(backend.read_file(&RepoPath::root(), &file_id))
+                let mut reader = pollster::block_on(backend.read_file(RepoPath::root(), &file_id))
                     .map_err(|e| anyhow!("read file {}: {e}", to_hex(id)))?;
                 let mut buf = Vec::new();
                 pollster::block_on(tokio::io::AsyncReadExt::read_to_end(&mut reader, &mut buf))
@@ -686,7 +2139,7 @@ impl Server {
             }
             "tree" => {
                 let tree_id = TreeId::new(id.to_vec());
-                let tree = pollster::block_on(backend.read_tree(&RepoPath::root(), &tree_id))
+                let tree = pollster::block_on(backend.read_tree(RepoPath::root(), &tree_id))
                     .map_err(|e| anyhow!("read tree {}: {e}", to_hex(id)))?;
                 let proto = proto_convert::tree_to_proto(&tree);
                 Ok(proto.encode_to_vec())
@@ -709,7 +2162,7 @@ impl Server {
             "symlink" => {
                 let symlink_id = jj_lib::backend::SymlinkId::new(id.to_vec());
                 let target =
-                    pollster::block_on(backend.read_symlink(&RepoPath::root(), &symlink_id))
+                    pollster::block_on(backend.read_symlink(RepoPath::root(), &symlink_id))
                         .map_err(|e| anyhow!("read symlink {}: {e}", to_hex(id)))?;
                 Ok(target.into_bytes())
             }
@@ -720,22 +2173,58 @@ impl Server {
         }
     }
 
+    /// One page of a tree: the encoded entries named after `cursor`, at
+    /// most `limit` of them, and the cursor for the next page (empty after
+    /// the last). Entries are in the tree's name order, so a page boundary
+    /// is just the last name sent.
+    fn get_tree_entries_sync(
+        &self,
+        id: &[u8],
+        cursor: &str,
+        limit: usize,
+    ) -> Result<(Vec<u8>, String)> {
+        let backend = self.store.backend();
+        let tree_id = TreeId::new(id.to_vec());
+        let tree = pollster::block_on(backend.read_tree(RepoPath::root(), &tree_id))
+            .map_err(|e| anyhow!("read tree {}: {e}", to_hex(id)))?;
+        let limit = match limit {
+            0 => TREE_PAGE_DEFAULT,
+            n => n.min(TREE_PAGE_MAX),
+        };
+        let mut proto = proto_convert::tree_to_proto(&tree);
+        let start = if cursor.is_empty() {
+            0
+        } else {
+            proto
+                .entries
+                .partition_point(|entry| entry.name.as_str() <= cursor)
+        };
+        let end = proto.entries.len().min(start + limit);
+        let next_cursor = if end < proto.entries.len() {
+            proto.entries[end - 1].name.clone()
+        } else {
+            String::new()
+        };
+        proto.entries.truncate(end);
+        proto.entries.drain(..start);
+        Ok((proto.encode_to_vec(), next_cursor))
+    }
+
     fn put_object_sync(&self, kind: &str, data: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
         let backend = self.store.backend();
 
         match kind {
             "file" => {
                 let mut cursor = Cursor::new(data.to_vec());
-                let file_id =
-                    pollster::block_on(backend.write_file(&RepoPath::root(), &mut cursor))
-                        .map_err(|e| anyhow!("write file: {e}"))?;
+                let file_id = pollster::block_on(backend.write_file(RepoPath::root(), &mut cursor))
+                    .map_err(|e| anyhow!("write file: {e}"))?;
                 Ok((file_id.as_bytes().to_vec(), data.to_vec()))
             }
             "tree" => {
                 let proto = jj_lib::protos::simple_store::Tree::decode(data)
                     .context("decode tree proto")?;
                 let tree = proto_convert::tree_from_proto(proto);
-                let tree_id = pollster::block_on(backend.write_tree(&RepoPath::root(), &tree))
+                let tree_id = pollster::block_on(backend.write_tree(RepoPath::root(), &tree))
                     .map_err(|e| anyhow!("write tree: {e}"))?;
                 // Return the original proto data as normalized (the tree is the same)
                 Ok((tree_id.as_bytes().to_vec(), data.to_vec()))
@@ -756,7 +2245,7 @@ impl Server {
                 let target =
                     std::str::from_utf8(data).context("symlink target is not valid UTF-8")?;
                 let symlink_id =
-                    pollster::block_on(backend.write_symlink(&RepoPath::root(), target))
+                    pollster::block_on(backend.write_symlink(RepoPath::root(), target))
                         .map_err(|e| anyhow!("write symlink: {e}"))?;
                 Ok((symlink_id.as_bytes().to_vec(), data.to_vec()))
             }
@@ -770,15 +2259,27 @@ impl Server {
     // ─── Operation/View operations ────────────────────────────────────
     //
     // Operations and views are stored in jj's op_store directory using
-    // ContentHash-based IDs (compatible with jj's SimpleOpStore).
+    // ContentHash-based IDs (compatible with jj's SimpleOpStore), with
+    // operations sharded by id prefix under --shard-operations.
+
+    /// The file holding operation `hex`, if it is stored.
+    fn operation_file(&self, hex: &str) -> Result<Option<PathBuf>> {
+        if self.shard_operations {
+            return Ok(sharded_op_store::find_operation(&self.op_store_path, hex)?);
+        }
+        let path = sharded_op_store::flat_operation_path(&self.op_store_path, hex);
+        Ok(path.is_file().then_some(path))
+    }
 
     fn get_operation_sync(&self, id: &[u8]) -> Result<Vec<u8>> {
         let hex = to_hex(id);
-        let path = self.op_store_path.join("operations").join(&hex);
+        let path = self
+            .operation_file(&hex)?
+            .ok_or_else(|| anyhow!("operation not found: {hex}"))?;
         fs::read(&path).with_context(|| format!("operation not found: {hex}"))
     }
 
-    fn put_operation_sync(&self, data: &[u8]) -> Result<Vec<u8>> {
+    fn put_operation_sync(&self, data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>> {
         // Decode proto → Operation struct → compute ContentHash-based ID
         let proto = jj_lib::protos::simple_op_store::Operation::decode(data)
             .context("decode operation proto")?;
@@ -789,12 +2290,121 @@ impl Server {
         let id: Vec<u8> = hash.to_vec();
         let hex = to_hex(&id);
 
-        let dir = self.op_store_path.join("operations");
-        let path = dir.join(&hex);
-        write_bytes_if_missing(&path, data)?;
+        if self.shard_operations {
+            sharded_op_store::write_operation_file(&self.op_store_path, &hex, data)
+                .with_context(|| format!("write operation {hex}"))?;
+        } else {
+            write_bytes_if_missing(
+                &sharded_op_store::flat_operation_path(&self.op_store_path, &hex),
+                data,
+            )?;
+        }
+
+        if !tags.is_empty() {
+            self.op_tags.extend(&hex, tags)?;
+        }
         Ok(id)
     }
 
+    // ─── Op log rewrites ──────────────────────────────────────────────
+
+    fn abandoned_operations_path(&self) -> PathBuf {
+        self.tandem_dir
+            .join(metadata_schema::ABANDONED_OPERATIONS.file)
+    }
+
+    fn read_abandoned_operations(&self) -> Result<AbandonedOperations> {
+        let path = self.abandoned_operations_path();
+        if !path.exists() {
+            return Ok(AbandonedOperations::default());
+        }
+        metadata_schema::read(&metadata_schema::ABANDONED_OPERATIONS, &path)
+    }
+
+    /// Tombstone heads replaced by an op log rewrite so late writers that
+    /// still build on them are turned away instead of resurrecting them.
+    /// Tombstone each replaced operation, mapped to the hex id of the
+    /// operation that replaced it.
+    fn record_abandoned_operations(&self, replaced: &[(OperationId, String)]) -> Result<()> {
+        let mut abandoned = self.read_abandoned_operations()?;
+        for (id, replacement_hex) in replaced {
+            abandoned
+                .operations
+                .insert(id.hex(), replacement_hex.clone());
+        }
+        metadata_schema::write(
+            &metadata_schema::ABANDONED_OPERATIONS,
+            &self.abandoned_operations_path(),
+            &abandoned,
+        )?;
+        tracing::info!(
+            abandoned = ?replaced
+                .iter()
+                .map(|(id, replacement)| format!("{} -> {replacement}", id.hex()))
+                .collect::<Vec<_>>(),
+            "op log rewritten; replaced heads tombstoned"
+        );
+        Ok(())
+    }
+
+    /// Client-provided old heads that the new operation does not descend
+    /// from. Normal transactions list their parents as old heads, so this is
+    /// empty unless the op log was rewritten (`jj op abandon`).
+    fn rewritten_heads(
+        &self,
+        provided_old: &[OperationId],
+        parents: &[OperationId],
+        new_op_id: &OperationId,
+    ) -> Result<Option<OpLogRewrite>> {
+        let current_heads = self.read_jj_op_heads()?;
+        let candidates: Vec<&OperationId> = provided_old
+            .iter()
+            .filter(|id| {
+                *id != new_op_id && !parents.contains(id) && current_heads.contains(&id.hex())
+            })
+            .collect();
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        // Walk the new operation's ancestry once; anything not in it was replaced.
+        let op_store = self.repo_loader.op_store();
+        let mut ancestors = std::collections::HashSet::new();
+        let mut pending = parents.to_vec();
+        while let Some(id) = pending.pop() {
+            if ancestors.len() >= MAX_REWRITE_ANCESTRY || !ancestors.insert(id.clone()) {
+                continue;
+            }
+            if id == *op_store.root_operation_id() {
+                continue;
+            }
+            let operation = pollster::block_on(op_store.read_operation(&id))
+                .map_err(|e| anyhow!("read operation {}: {e}", id.hex()))?;
+            pending.extend(operation.parents);
+        }
+        let replaced: Vec<OperationId> = candidates
+            .into_iter()
+            .filter(|id| !ancestors.contains(*id))
+            .cloned()
+            .collect();
+        if replaced.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(OpLogRewrite {
+            replaced,
+            ancestry: ancestors.iter().map(|id| id.hex()).collect(),
+        }))
+    }
+
+    fn get_operation_tags_sync(
+        &self,
+        filters: &BTreeMap<String, String>,
+    ) -> Result<Vec<(String, BTreeMap<String, String>)>> {
+        Ok(self
+            .op_tags
+            .filter(|tags| operation_tags_match(tags, filters)))
+    }
+
     fn get_view_sync(&self, id: &[u8]) -> Result<Vec<u8>> {
         let hex = to_hex(id);
         let path = self.op_store_path.join("views").join(&hex);
@@ -817,25 +2427,36 @@ impl Server {
         Ok(id)
     }
 
+    /// View `id` as a delta against `base_id`, or in full when the base is
+    /// unknown here or the delta would not be smaller.
+    fn get_view_delta_sync(&self, id: &[u8], base_id: &[u8]) -> Result<crate::rpc::ViewFetch> {
+        let data = self.get_view_sync(id)?;
+        let Ok(base) = self.get_view_sync(base_id) else {
+            return Ok(crate::rpc::ViewFetch::Full(data));
+        };
+        match view_delta::diff(&base, &data) {
+            Ok(delta) if view_delta::encoded_len(&delta) < data.len() => {
+                Ok(crate::rpc::ViewFetch::Delta(delta))
+            }
+            _ => Ok(crate::rpc::ViewFetch::Full(data)),
+        }
+    }
+
+    fn put_view_delta_sync(&self, base_id: &[u8], delta: &[DeltaOp]) -> Result<Vec<u8>> {
+        let base = self
+            .get_view_sync(base_id)
+            .context("delta base view is not on this server")?;
+        let data = view_delta::apply(&base, delta)?;
+        self.put_view_sync(&data)
+    }
+
     // ─── Operation prefix resolution ──────────────────────────────────
 
     fn resolve_operation_id_prefix_sync(
         &self,
         hex_prefix: &str,
     ) -> Result<(String, Option<Vec<u8>>)> {
-        let mut matches = Vec::new();
-        let dir = self.op_store_path.join("operations");
-        if let Ok(entries) = fs::read_dir(dir) {
-            for entry in entries {
-                let entry = entry?;
-                let file_name = entry.file_name();
-                let file_name = file_name.to_string_lossy();
-                if file_name.starts_with(hex_prefix) {
-                    matches.push(file_name.to_string());
-                }
-            }
-        }
-        matches.sort();
+        let matches = sharded_op_store::operation_ids_with_prefix(&self.op_store_path, hex_prefix)?;
         match matches.len() {
             0 => Ok(("noMatch".to_string(), None)),
             1 => {
@@ -851,6 +2472,15 @@ impl Server {
     fn get_heads_sync(&self) -> Result<HeadsState> {
         let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
         let mut metadata = self.read_heads_metadata()?;
+        if self.primary.is_some() {
+            // Replicas mirror the primary's heads; merging them here would
+            // write an operation the primary never saw.
+            return Ok(HeadsState {
+                version: metadata.version,
+                heads: self.read_jj_op_heads()?,
+                workspace_heads: metadata.workspace_heads,
+            });
+        }
         let empty_workspace_heads = BTreeMap::new();
         let (heads, reconciled) = self.reconcile_jj_op_heads(&empty_workspace_heads)?;
 
@@ -858,7 +2488,7 @@ impl Server {
             metadata.version += 1;
             self.write_heads_metadata(&metadata)?;
             let heads_bytes: Vec<Vec<u8>> = heads.iter().filter_map(|h| from_hex(h).ok()).collect();
-            self.notify_watchers(metadata.version, &heads_bytes);
+            self.announce_heads(metadata.version, &heads_bytes, None, &BTreeMap::new());
         }
 
         Ok(HeadsState {
@@ -868,43 +2498,189 @@ impl Server {
         })
     }
 
-    fn update_op_heads_sync(
-        &self,
-        old_ids: Vec<Vec<u8>>,
-        new_id: Vec<u8>,
-        expected_version: u64,
-        workspace_id: Option<String>,
-    ) -> Result<UpdateResult> {
+    /// The current heads version alone, for clients polling for changes.
+    /// Unlike `get_heads_sync` this does not reconcile jj's op heads, so an
+    /// out-of-band jj command only shows up after the next `getHeads`.
+    fn get_heads_version_sync(&self) -> Result<u64> {
         let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
-        let mut metadata = self.read_heads_metadata()?;
+        Ok(self.read_heads_metadata()?.version)
+    }
 
-        if metadata.version != expected_version {
-            let empty_workspace_heads = BTreeMap::new();
-            let (current_heads, reconciled) = self.reconcile_jj_op_heads(&empty_workspace_heads)?;
-            if reconciled {
-                metadata.version += 1;
-                self.write_heads_metadata(&metadata)?;
-                let heads_bytes: Vec<Vec<u8>> = current_heads
-                    .iter()
-                    .filter_map(|h| from_hex(h).ok())
-                    .collect();
-                self.notify_watchers(metadata.version, &heads_bytes);
-            }
-            tracing::debug!(
-                expected_version,
-                actual_version = metadata.version,
-                "update_op_heads version mismatch"
-            );
-            return Ok(UpdateResult {
-                ok: false,
-                heads: current_heads
-                    .iter()
-                    .map(|h| from_hex(h).unwrap_or_default())
-                    .collect(),
-                version: metadata.version,
-                workspace_heads: metadata.workspace_heads,
-            });
-        }
+    /// Pin the heads at the current version until the hold ends or its lease
+    /// runs out. Returns the hold id and the pinned version.
+    fn begin_snapshot_sync(&self, lease: std::time::Duration) -> Result<(u64, u64)> {
+        let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+        let version = self.read_heads_metadata()?.version;
+        Ok((self.snapshots.begin(lease), version))
+    }
+
+    // ─── Migration ────────────────────────────────────────────────────
+
+    /// Take over another server's heads (`tandem migrate`). Only a server no
+    /// workspace has written to yet can be a migration target; re-importing
+    /// is fine as long as nothing moved the heads since the last import.
+    fn import_heads_sync(&self, state: &crate::rpc::HeadsState) -> Result<u64> {
+        let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+        let metadata = self.read_heads_metadata()?;
+        let imported_path = self.tandem_dir.join(IMPORTED_VERSION_FILE);
+        let untouched_since_import = fs::read_to_string(&imported_path)
+            .ok()
+            .and_then(|raw| raw.trim().parse::<u64>().ok())
+            == Some(metadata.version);
+        if !metadata.workspace_heads.is_empty() && !untouched_since_import {
+            bail!(
+                "refusing to import heads: this server already has workspaces ({}); \
+                 migrate into a fresh server",
+                metadata
+                    .workspace_heads
+                    .keys()
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+        }
+        self.replace_heads_locked(metadata, state)?;
+        let version = self.read_heads_metadata()?.version;
+        fs::write(&imported_path, version.to_string())?;
+        Ok(version)
+    }
+
+    /// Send clients to `address` from now on, provided no head update landed
+    /// since `expected_version`. Returns whether it was set and the version.
+    fn set_redirect_sync(&self, address: &str, expected_version: u64) -> Result<(bool, u64)> {
+        let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+        let version = self.read_heads_metadata()?.version;
+        if version != expected_version {
+            return Ok((false, version));
+        }
+        self.redirect.set(RedirectTarget {
+            address: address.to_string(),
+            version: Some(version),
+            moved_at_millis: now_epoch_millis(),
+        })?;
+        Ok((true, version))
+    }
+
+    fn ensure_writable(&self) -> Result<()> {
+        if let Some(primary) = &self.primary {
+            bail!("this server is a read-only replica; write to {primary} instead");
+        }
+        if let Some(address) = self.redirect_address() {
+            bail!("this repo moved to {address}; re-run the command to follow it there");
+        }
+        Ok(())
+    }
+
+    fn redirect_address(&self) -> Option<String> {
+        self.redirect.address()
+    }
+
+    /// Gate for every mutating RPC: replicas, servers in maintenance, and
+    /// shutting-down servers refuse new writes. Maintenance and shutdown use
+    /// `Overloaded` so clients can tell them apart from a failed write.
+    fn ensure_accepting_writes(&self, conn_id: u64) -> Result<(), capnp::Error> {
+        self.ensure_writable().map_err(capnp_err)?;
+        if let Some(notice) = self.maintenance.active() {
+            return Err(capnp::Error::overloaded(notice.describe()));
+        }
+        self.drain
+            .admit_write(conn_id)
+            .map_err(capnp::Error::overloaded)
+    }
+
+    /// Reject the head update when commits introduced by `new_op_id` touch
+    /// protected paths that `workspace_id` does not own.
+    fn check_protected_paths(
+        &self,
+        new_op_id: &OperationId,
+        old_op_ids: &[OperationId],
+        workspace_id: Option<&str>,
+    ) -> Result<()> {
+        if self.protected_paths.is_empty() {
+            return Ok(());
+        }
+
+        let (touched, _) =
+            heads_summary::touched_paths(&self.repo_loader, old_op_ids, new_op_id, usize::MAX)?;
+
+        let violations = self
+            .protected_paths
+            .violations(workspace_id, touched.iter().map(String::as_str));
+        if violations.is_empty() {
+            return Ok(());
+        }
+
+        let listed: Vec<String> = violations
+            .iter()
+            .map(|v| {
+                let owners: Vec<&str> = v.owners.iter().map(String::as_str).collect();
+                format!("{} (owners: {})", v.path, owners.join(", "))
+            })
+            .collect();
+        tracing::warn!(
+            workspace_id = workspace_id.unwrap_or("<none>"),
+            new_id = %new_op_id.hex(),
+            paths = %listed.join("; "),
+            "rejected head update touching protected paths"
+        );
+        Err(anyhow!(
+            "workspace {} may not modify protected paths: {}",
+            workspace_id.unwrap_or("<unknown>"),
+            listed.join("; ")
+        ))
+    }
+
+    fn update_op_heads_sync(
+        &self,
+        old_ids: Vec<Vec<u8>>,
+        new_id: Vec<u8>,
+        expected_version: u64,
+        workspace_id: Option<String>,
+        idempotency_key: Option<Vec<u8>>,
+    ) -> Result<UpdateResult> {
+        let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+        let applied = match idempotency_key.as_deref() {
+            Some(key) => self.applied_updates.get(key, &new_id)?,
+            None => None,
+        };
+        if let Some(result) = applied {
+            tracing::info!(
+                workspace_id = workspace_id.as_deref().unwrap_or(""),
+                new_id = %to_hex(&new_id),
+                version = result.version,
+                "head update already applied; returning its result again"
+            );
+            return Ok(result);
+        }
+        let mut metadata = self.read_heads_metadata()?;
+
+        if metadata.version != expected_version {
+            let empty_workspace_heads = BTreeMap::new();
+            let (current_heads, reconciled) = self.reconcile_jj_op_heads(&empty_workspace_heads)?;
+            if reconciled {
+                metadata.version += 1;
+                self.write_heads_metadata(&metadata)?;
+                let heads_bytes: Vec<Vec<u8>> = current_heads
+                    .iter()
+                    .filter_map(|h| from_hex(h).ok())
+                    .collect();
+                self.announce_heads(metadata.version, &heads_bytes, None, &BTreeMap::new());
+            }
+            tracing::debug!(
+                expected_version,
+                actual_version = metadata.version,
+                "update_op_heads version mismatch"
+            );
+            return Ok(UpdateResult {
+                ok: false,
+                heads: current_heads
+                    .iter()
+                    .map(|h| from_hex(h).unwrap_or_default())
+                    .collect(),
+                version: metadata.version,
+                workspace_heads: metadata.workspace_heads,
+            });
+        }
 
         let provided_old_op_ids: Vec<jj_lib::op_store::OperationId> = old_ids
             .into_iter()
@@ -927,21 +2703,92 @@ impl Server {
         };
 
         if old_op_ids.is_empty() {
-            old_op_ids = provided_old_op_ids;
+            old_op_ids = provided_old_op_ids.clone();
+        }
+
+        let abandoned = self.read_abandoned_operations()?;
+        if let Some((parent, replacement)) = old_op_ids.iter().find_map(|id| {
+            abandoned
+                .operations
+                .get(&id.hex())
+                .map(|replacement| (id.hex(), replacement))
+        }) {
+            bail!(
+                "operation {} was abandoned by an op log rewrite and replaced by {}; \
+                 re-run the command to continue from the rewritten operation log",
+                &parent[..12],
+                &replacement[..replacement.len().min(12)]
+            );
         }
 
         old_op_ids.retain(|id| id != &new_op_id);
+        self.check_protected_paths(&new_op_id, &old_op_ids, workspace_id.as_deref())?;
+
+        // `jj op abandon` replaces the current head with a rewritten one that
+        // does not descend from it; the replaced heads have to go too.
+        let rewrite = self.rewritten_heads(&provided_old_op_ids, &old_op_ids, &new_op_id)?;
+        if let Some(rewrite) = &rewrite {
+            old_op_ids.extend(rewrite.replaced.iter().cloned());
+        }
         pollster::block_on(self.op_heads_store.update_op_heads(&old_op_ids, &new_op_id))
             .map_err(|e| anyhow!("update op heads via jj-lib: {e}"))?;
 
         let new_hex = to_hex(&new_id);
-        let next_workspace_heads =
+        if let Some(rewrite) = &rewrite {
+            let replaced: Vec<_> = rewrite
+                .replaced
+                .iter()
+                .map(|id| (id.clone(), new_hex.clone()))
+                .collect();
+            self.record_abandoned_operations(&replaced)?;
+        }
+        // The workspace that published an operation first owns it: jj also
+        // republishes heads it merely loaded (resolving divergent heads moves
+        // each one through `updateOpHeads`), and that must not reattribute them.
+        if let Some(ws_id) = workspace_id.as_deref().filter(|id| !id.is_empty()) {
+            if let Err(err) =
+                self.op_tags
+                    .insert_if_absent(&new_hex, attribution::WORKSPACE_TAG, ws_id)
+            {
+                tracing::warn!(op_id = %new_hex, error = %err, "failed to record workspace attribution");
+            }
+        }
+        let tags = self.op_tags.get(&new_hex);
+        let mut next_workspace_heads =
             updated_workspace_heads(&metadata.workspace_heads, workspace_id.as_deref(), &new_hex);
+        if let Some(rewrite) = &rewrite {
+            // Workspaces last seen on a rewritten operation would otherwise
+            // merge the abandoned history back in as a second head.
+            for head in next_workspace_heads.values_mut() {
+                if *head != new_hex && !rewrite.ancestry.contains(head.as_str()) {
+                    *head = new_hex.clone();
+                }
+            }
+        }
+        let mut ephemeral_workspaces = metadata.ephemeral_workspaces.clone();
+        if let Some(ws_id) = workspace_id.as_deref().filter(|id| !id.is_empty()) {
+            match ephemeral::classify_update(
+                ephemeral_workspaces.get(ws_id),
+                &tags,
+                now_epoch_secs(),
+            ) {
+                ephemeral::Update::Untracked => {}
+                ephemeral::Update::Renew(entry) => {
+                    ephemeral_workspaces.insert(ws_id.to_string(), entry);
+                }
+                ephemeral::Update::Release => {
+                    ephemeral_workspaces.remove(ws_id);
+                    next_workspace_heads.remove(ws_id);
+                    tracing::info!(workspace_id = %ws_id, "released workspace");
+                }
+            }
+        }
         let (next_heads, _) = self.reconcile_jj_op_heads(&next_workspace_heads)?;
 
         let next_metadata = HeadsMetadata {
             version: metadata.version + 1,
             workspace_heads: next_workspace_heads.clone(),
+            ephemeral_workspaces,
         };
         self.write_heads_metadata(&next_metadata)?;
 
@@ -958,33 +2805,186 @@ impl Server {
             .map(|h| from_hex(h).unwrap_or_default())
             .collect();
 
-        self.notify_watchers(next_metadata.version, &heads_bytes);
+        self.announce_heads(
+            next_metadata.version,
+            &heads_bytes,
+            workspace_id.as_deref(),
+            &tags,
+        );
         if self.integration_enabled {
             self.enqueue_integration_recompute();
         }
 
-        Ok(UpdateResult {
+        let result = UpdateResult {
             ok: true,
             heads: heads_bytes,
             version: next_metadata.version,
             workspace_heads: next_workspace_heads,
-        })
+        };
+        if let Some(key) = idempotency_key {
+            self.applied_updates
+                .insert(key, new_id.clone(), result.clone());
+        }
+        Ok(result)
     }
 
-    fn register_watcher(&self, watcher: head_watcher::Client, after_version: u64) {
+    fn register_watcher(
+        &self,
+        watcher: head_watcher::Client,
+        after_version: u64,
+        summary_path_limit: Option<usize>,
+    ) {
         let mut watchers = self.watchers.lock().unwrap();
         watchers.push(WatcherEntry {
             watcher,
             after_version,
+            summary_path_limit,
         });
         tracing::debug!(
             watchers = watchers.len(),
             after_version,
+            summary = summary_path_limit.is_some(),
             "watcher registered"
         );
     }
 
-    fn notify_watchers(&self, version: u64, heads: &[Vec<u8>]) {
+    /// Summary of the change to `heads`, or `None` (logged) if it cannot be
+    /// computed; the notification then goes out without one.
+    fn heads_summary(
+        &self,
+        heads: &[Vec<u8>],
+        workspace_id: Option<&str>,
+        path_limit: usize,
+    ) -> Option<HeadsSummary> {
+        let op_ids: Vec<OperationId> = heads.iter().cloned().map(OperationId::new).collect();
+        match HeadsSummary::compute(&self.repo_loader, &op_ids, workspace_id, path_limit) {
+            Ok(mut summary) => {
+                if let Some(workspace_id) = &summary.workspace_id {
+                    summary.overlaps = self
+                        .path_overlaps(heads)
+                        .into_iter()
+                        .filter(|overlap| overlap.other(workspace_id).is_some())
+                        .collect();
+                }
+                Some(summary)
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to summarize heads change");
+                None
+            }
+        }
+    }
+
+    /// Path overlaps between workspaces at `heads`, or none (logged) if they
+    /// cannot be computed. Computed once per heads version.
+    fn path_overlaps(&self, heads: &[Vec<u8>]) -> Vec<rpc::PathOverlap> {
+        match self.compute_path_overlaps(heads) {
+            Ok(overlaps) => overlaps,
+            Err(err) => {
+                tracing::warn!(error = %err, "failed to compute path overlaps");
+                Vec::new()
+            }
+        }
+    }
+
+    fn compute_path_overlaps(&self, heads: &[Vec<u8>]) -> Result<Vec<rpc::PathOverlap>> {
+        let metadata = self.read_heads_metadata()?;
+        let mut cached = self
+            .path_overlaps
+            .lock()
+            .map_err(|e| anyhow!("lock: {e}"))?;
+        if let Some((version, overlaps)) = cached.as_ref() {
+            if *version == metadata.version {
+                return Ok(overlaps.clone());
+            }
+        }
+        let Some(head) = heads.first() else {
+            return Ok(Vec::new());
+        };
+        let workspace_commits = self.resolve_workspace_commits(&metadata.workspace_heads)?;
+        let overlaps = path_overlaps::compute(
+            &self.repo_loader,
+            &OperationId::new(head.clone()),
+            &workspace_commits,
+            path_overlaps::PATH_LIMIT,
+        )?;
+        *cached = Some((metadata.version, overlaps.clone()));
+        Ok(overlaps)
+    }
+
+    /// Count a finished `updateOpHeads` toward CAS storm detection and
+    /// return the backoff floor for its response.
+    fn record_cas_outcome(&self, cas_failed: bool) -> u32 {
+        let (min_backoff_ms, change) = self.cas_storm.record(cas_failed);
+        match change {
+            Some(cas_storm::Change::Started {
+                conflicts_per_sec,
+                min_backoff_ms,
+            }) => {
+                tracing::warn!(
+                    conflicts_per_sec,
+                    min_backoff_ms,
+                    "CAS storm: head updates keep losing the race; asking clients to back off"
+                );
+                self.notifications.emit(Event::CasStorm {
+                    conflicts_per_sec,
+                    min_backoff_ms,
+                });
+            }
+            Some(cas_storm::Change::Ended {
+                lasted,
+                peak_per_sec,
+            }) => {
+                tracing::info!(
+                    lasted_ms = lasted.as_millis() as u64,
+                    peak_per_sec,
+                    "CAS storm over"
+                );
+            }
+            None => {}
+        }
+        min_backoff_ms
+    }
+
+    /// Record a heads-version transition in the history journal, then push it
+    /// to watchers. Every version bump goes through here.
+    fn announce_heads(
+        &self,
+        version: u64,
+        heads: &[Vec<u8>],
+        workspace_id: Option<&str>,
+        tags: &BTreeMap<String, String>,
+    ) {
+        let transition = HeadsTransition {
+            version,
+            heads: heads.iter().map(|head| to_hex(head)).collect(),
+            workspace_id: workspace_id.filter(|id| !id.is_empty()).map(str::to_string),
+            timestamp_millis: now_epoch_millis(),
+        };
+        if self.notifications.wants(EventKind::HeadsUpdated) {
+            let operation =
+                self.heads_summary(heads, workspace_id, heads_summary::DEFAULT_PATH_LIMIT);
+            self.notifications.emit(Event::HeadsUpdated {
+                version,
+                heads: transition.heads.clone(),
+                workspace: transition.workspace_id.clone(),
+                tags: tags.clone(),
+                operation,
+            });
+        }
+        if let Err(err) = self.heads_history.record(transition) {
+            tracing::warn!(version, error = %err, "failed to record heads history");
+        }
+        self.notify_watchers(version, heads, workspace_id, tags);
+    }
+
+    fn notify_watchers(
+        &self,
+        version: u64,
+        heads: &[Vec<u8>],
+        workspace_id: Option<&str>,
+        tags: &BTreeMap<String, String>,
+    ) {
         let mut watchers = self.watchers.lock().unwrap();
         tracing::trace!(
             watchers = watchers.len(),
@@ -992,12 +2992,25 @@ impl Server {
             heads = heads.len(),
             "notifying watchers"
         );
+        // One summary, at the largest limit asked for, serves every watcher.
+        let summary_path_limit = watchers
+            .iter()
+            .filter(|entry| entry.after_version < version)
+            .filter_map(|entry| entry.summary_path_limit)
+            .max();
+        let summary =
+            summary_path_limit.and_then(|limit| self.heads_summary(heads, workspace_id, limit));
         for entry in watchers.iter_mut() {
             if entry.after_version >= version {
                 continue;
             }
             let watcher = entry.watcher.clone();
             let heads_clone: Vec<Vec<u8>> = heads.to_vec();
+            let tags_clone = tags.clone();
+            let summary_clone = entry
+                .summary_path_limit
+                .zip(summary.as_ref())
+                .map(|(limit, summary)| summary.limited(limit));
             entry.after_version = version;
 
             tokio::task::spawn_local(async move {
@@ -1005,9 +3018,23 @@ impl Server {
                 {
                     let mut params = req.get();
                     params.set_version(version);
-                    let mut heads_builder = params.init_heads(heads_clone.len() as u32);
-                    for (i, head) in heads_clone.iter().enumerate() {
-                        heads_builder.set(i as u32, head);
+                    {
+                        let mut heads_builder =
+                            params.reborrow().init_heads(heads_clone.len() as u32);
+                        for (i, head) in heads_clone.iter().enumerate() {
+                            heads_builder.set(i as u32, head);
+                        }
+                    }
+                    if !tags_clone.is_empty() {
+                        let mut tags_builder = params.reborrow().init_tags(tags_clone.len() as u32);
+                        for (i, (key, value)) in tags_clone.iter().enumerate() {
+                            let mut tag = tags_builder.reborrow().get(i as u32);
+                            tag.set_key(key);
+                            tag.set_value(value);
+                        }
+                    }
+                    if let Some(summary) = &summary_clone {
+                        summary.write(params.init_summary());
                     }
                 }
                 let _ = req.send().promise.await;
@@ -1015,23 +3042,136 @@ impl Server {
         }
     }
 
+    /// Start refusing new writes and tell every watcher the server is going
+    /// away. Watchers that do not know `shuttingDown` just see the
+    /// connection close.
+    fn begin_shutdown(&self, retry_after: Duration) {
+        self.drain.start(retry_after);
+        let message = drain::describe(retry_after);
+        let watchers = self.watchers.lock().unwrap();
+        tracing::info!(
+            watchers = watchers.len(),
+            retry_after_secs = retry_after.as_secs(),
+            "notifying clients of shutdown"
+        );
+        for entry in watchers.iter() {
+            let mut req = entry.watcher.shutting_down_request();
+            {
+                let mut params = req.get();
+                params.set_retry_after_ms(retry_after.as_millis().min(u32::MAX as u128) as u32);
+                params.set_message(message.as_str());
+            }
+            tokio::task::spawn_local(async move {
+                let _ = req.send().promise.await;
+            });
+        }
+    }
+
     fn read_heads_metadata(&self) -> Result<HeadsMetadata> {
-        let bytes = fs::read(self.tandem_dir.join("heads.json"))?;
-        let metadata = serde_json::from_slice(&bytes)?;
-        Ok(metadata)
+        metadata_schema::read(
+            &metadata_schema::HEADS,
+            &self.tandem_dir.join(metadata_schema::HEADS.file),
+        )
     }
 
     fn write_heads_metadata(&self, metadata: &HeadsMetadata) -> Result<()> {
-        fs::write(
-            self.tandem_dir.join("heads.json"),
-            serde_json::to_vec_pretty(metadata)?,
-        )?;
+        metadata_schema::write(
+            &metadata_schema::HEADS,
+            &self.tandem_dir.join(metadata_schema::HEADS.file),
+            metadata,
+        )
+    }
+}
+
+// ─── Read replica sink ────────────────────────────────────────────────────────
+
+impl ReplicaSink for Server {
+    fn has_operation(&self, id: &[u8]) -> bool {
+        self.operation_file(&to_hex(id))
+            .is_ok_and(|path| path.is_some())
+    }
+
+    fn has_view(&self, id: &[u8]) -> bool {
+        self.op_store_path.join("views").join(to_hex(id)).exists()
+    }
+
+    fn has_object(&self, kind: ObjectKind, id: &[u8]) -> bool {
+        self.get_object_sync(kind.name(), id).is_ok()
+    }
+
+    fn put_operation(&self, data: &[u8], tags: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+        self.put_operation_sync(data, tags)
+    }
+
+    fn put_view(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.put_view_sync(data)
+    }
+
+    fn put_object(&self, kind: ObjectKind, data: &[u8]) -> Result<Vec<u8>> {
+        Ok(self.put_object_sync(kind.name(), data)?.0)
+    }
+
+    fn adopt_heads(&self, primary: &crate::rpc::HeadsState) -> Result<()> {
+        let _guard = self.lock.lock().map_err(|e| anyhow!("lock: {e}"))?;
+        let metadata = self.read_heads_metadata()?;
+        self.replace_heads_locked(metadata, primary)
+    }
+
+    fn heads_held(&self) -> bool {
+        self.snapshots.is_held()
+    }
+}
+
+impl Server {
+    /// Make `primary`'s op heads, version, and workspace attributions ours.
+    /// Callers hold `self.lock` and pass the metadata they read under it.
+    fn replace_heads_locked(
+        &self,
+        mut metadata: HeadsMetadata,
+        primary: &crate::rpc::HeadsState,
+    ) -> Result<()> {
+        let mut primary_heads: Vec<String> = primary.heads.iter().map(|h| to_hex(h)).collect();
+        primary_heads.sort();
+        let workspace_heads: BTreeMap<String, String> = primary
+            .workspace_heads
+            .iter()
+            .map(|(workspace_id, op_id)| (workspace_id.clone(), to_hex(op_id)))
+            .collect();
+        let local_heads = self.read_jj_op_heads()?;
+        if local_heads == primary_heads && metadata.workspace_heads == workspace_heads {
+            return Ok(());
+        }
+
+        let mut stale = local_heads
+            .iter()
+            .filter(|hex| !primary_heads.contains(hex))
+            .map(|hex| from_hex(hex).map(OperationId::new))
+            .collect::<Result<Vec<_>>>()?;
+        for head in &primary.heads {
+            let head = OperationId::new(head.clone());
+            pollster::block_on(self.op_heads_store.update_op_heads(&stale, &head))
+                .map_err(|e| anyhow!("adopt primary op heads: {e}"))?;
+            stale.clear();
+        }
+
+        // Mirror the primary's version so clients can compare the two, but
+        // never move backwards if the primary was reset.
+        metadata.version = primary.version.max(metadata.version + 1);
+        metadata.workspace_heads = workspace_heads;
+        self.write_heads_metadata(&metadata)?;
+
+        let tags = primary_heads
+            .last()
+            .map(|hex| self.op_tags.get(hex))
+            .unwrap_or_default();
+        self.announce_heads(metadata.version, &primary.heads, None, &tags);
         Ok(())
     }
 }
 
 // ─── Data types ───────────────────────────────────────────────────────────────
 
+#[derive(Clone)]
 struct UpdateResult {
     ok: bool,
     heads: Vec<Vec<u8>>,
@@ -1045,6 +3185,24 @@ struct HeadsMetadata {
     version: u64,
     #[serde(default)]
     workspace_heads: BTreeMap<String, String>, // hex-encoded
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    ephemeral_workspaces: BTreeMap<String, EphemeralWorkspace>,
+}
+
+/// Heads replaced by op log rewrites, hex id -> the head that replaced it.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AbandonedOperations {
+    #[serde(default)]
+    operations: BTreeMap<String, String>,
+}
+
+/// Outcome of an update that rewrote the op log.
+struct OpLogRewrite {
+    /// Old heads the new operation replaced instead of descending from.
+    replaced: Vec<OperationId>,
+    /// Hex ids of the new operation's ancestors.
+    ancestry: std::collections::HashSet<String>,
 }
 
 struct HeadsState {
@@ -1069,17 +3227,30 @@ struct IntegrationMetadata {
     updated_at: Option<String>,
     #[serde(default)]
     workspace_commit_count: Option<usize>,
+    #[serde(default)]
+    last_auto_advance: Option<String>,
 }
 
 fn default_integration_status() -> String {
     "idle".to_string()
 }
 
-fn now_epoch_secs_string() -> String {
+fn now_epoch_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn now_epoch_millis() -> u64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs().to_string())
-        .unwrap_or_else(|_| "0".to_string())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn now_epoch_secs_string() -> String {
+    now_epoch_secs().to_string()
 }
 
 fn fingerprint_workspace_commits(workspace_commits: &BTreeMap<String, String>) -> String {
@@ -1136,6 +3307,20 @@ fn test_repo_info_capabilities() -> Vec<crate::tandem_capnp::Capability> {
                 "watchHeads" => crate::tandem_capnp::Capability::WatchHeads,
                 "headsSnapshot" => crate::tandem_capnp::Capability::HeadsSnapshot,
                 "copyTracking" => crate::tandem_capnp::Capability::CopyTracking,
+                "operationTags" => crate::tandem_capnp::Capability::OperationTags,
+                "headsHistory" => crate::tandem_capnp::Capability::HeadsHistory,
+                "consistentSnapshot" => crate::tandem_capnp::Capability::ConsistentSnapshot,
+                "migration" => crate::tandem_capnp::Capability::Migration,
+                "viewDeltas" => crate::tandem_capnp::Capability::ViewDeltas,
+                "headsVersion" => crate::tandem_capnp::Capability::HeadsVersion,
+                "headsSummaries" => crate::tandem_capnp::Capability::HeadsSummaries,
+                "treePages" => crate::tandem_capnp::Capability::TreePages,
+                "commitSearch" => crate::tandem_capnp::Capability::CommitSearch,
+                "changeLocks" => crate::tandem_capnp::Capability::ChangeLocks,
+                "pathOverlaps" => crate::tandem_capnp::Capability::PathOverlaps,
+                "mergePreview" => crate::tandem_capnp::Capability::MergePreview,
+                "commitGraph" => crate::tandem_capnp::Capability::CommitGraph,
+                "idempotentHeadUpdates" => crate::tandem_capnp::Capability::IdempotentHeadUpdates,
                 _ => continue,
             };
             if !caps.contains(&cap) {
@@ -1145,7 +3330,23 @@ fn test_repo_info_capabilities() -> Vec<crate::tandem_capnp::Capability> {
         return caps;
     }
 
-    vec![crate::tandem_capnp::Capability::WatchHeads]
+    vec![
+        crate::tandem_capnp::Capability::WatchHeads,
+        crate::tandem_capnp::Capability::OperationTags,
+        crate::tandem_capnp::Capability::HeadsHistory,
+        crate::tandem_capnp::Capability::ConsistentSnapshot,
+        crate::tandem_capnp::Capability::Migration,
+        crate::tandem_capnp::Capability::ViewDeltas,
+        crate::tandem_capnp::Capability::HeadsVersion,
+        crate::tandem_capnp::Capability::HeadsSummaries,
+        crate::tandem_capnp::Capability::TreePages,
+        crate::tandem_capnp::Capability::CommitSearch,
+        crate::tandem_capnp::Capability::ChangeLocks,
+        crate::tandem_capnp::Capability::PathOverlaps,
+        crate::tandem_capnp::Capability::MergePreview,
+        crate::tandem_capnp::Capability::CommitGraph,
+        crate::tandem_capnp::Capability::IdempotentHeadUpdates,
+    ]
 }
 
 impl store::Server for StoreImpl {
@@ -1165,7 +3366,19 @@ impl store::Server for StoreImpl {
             "TANDEM_TEST_REPO_INFO_PROTOCOL_MINOR",
             1,
         ));
-        info.set_jj_version(env!("CARGO_PKG_VERSION"));
+        let jj_version = test_repo_info_text(
+            "TANDEM_TEST_REPO_INFO_JJ_VERSION",
+            jj_compat::JJ_LIB_VERSION,
+        );
+        info.set_jj_version(&jj_version);
+        let tandem_version = test_repo_info_text(
+            "TANDEM_TEST_REPO_INFO_TANDEM_VERSION",
+            env!("CARGO_PKG_VERSION"),
+        );
+        info.set_tandem_version(&tandem_version);
+        let op_schema = std::env::var("TANDEM_TEST_REPO_INFO_OP_SCHEMA")
+            .unwrap_or_else(|_| jj_compat::op_schema());
+        info.set_op_schema(&op_schema);
         let backend_name = test_repo_info_text("TANDEM_TEST_REPO_INFO_BACKEND_NAME", "tandem");
         let op_store_name =
             test_repo_info_text("TANDEM_TEST_REPO_INFO_OP_STORE_NAME", "tandem_op_store");
@@ -1173,17 +3386,37 @@ impl store::Server for StoreImpl {
         info.set_op_store_name(&op_store_name);
         info.set_commit_id_length(backend.commit_id_length() as u16);
         info.set_change_id_length(backend.change_id_length() as u16);
+        let commit_hash = test_repo_info_text(
+            "TANDEM_TEST_REPO_INFO_COMMIT_HASH",
+            tandem_client::HashAlgorithm::for_git_id_length(backend.commit_id_length())
+                .map_or("unknown", tandem_client::HashAlgorithm::as_str),
+        );
+        let operation_hash = test_repo_info_text(
+            "TANDEM_TEST_REPO_INFO_OPERATION_HASH",
+            tandem_client::HashAlgorithm::Blake2b512.as_str(),
+        );
+        info.set_commit_hash(&commit_hash);
+        info.set_operation_hash(&operation_hash);
         info.set_root_commit_id(backend.root_commit_id().as_bytes());
         info.set_root_change_id(backend.root_change_id().as_bytes());
         info.set_empty_tree_id(backend.empty_tree_id().as_bytes());
         info.set_root_operation_id(&[0u8; 64]);
         let capabilities = test_repo_info_capabilities();
         {
-            let mut caps = info.init_capabilities(capabilities.len() as u32);
+            let mut caps = info.reborrow().init_capabilities(capabilities.len() as u32);
             for (i, cap) in capabilities.iter().enumerate() {
                 caps.set(i as u32, *cap);
             }
         }
+        if let Some(address) = self.server.redirect_address() {
+            info.set_redirect(address.as_str());
+        }
+        if let Some(url) = self.server.git_remote.as_deref() {
+            info.set_git_remote(url);
+        }
+        if let Some(fileset) = self.server.snapshot_auto_track.as_deref() {
+            info.set_snapshot_auto_track(fileset);
+        }
         Promise::ok(())
     }
 
@@ -1205,7 +3438,11 @@ impl store::Server for StoreImpl {
             "rpc request"
         );
 
-        match self.server.get_object_sync(kind_str, id_bytes) {
+        match self
+            .server
+            .get_object_sync(kind_str, id_bytes)
+            .and_then(|data| limits::fit(&format!("{kind_str} object"), data))
+        {
             Ok(data) => {
                 tracing::debug!(
                     conn_id = self.conn_id,
@@ -1237,6 +3474,7 @@ impl store::Server for StoreImpl {
         params: store::PutObjectParams,
         mut results: store::PutObjectResults,
     ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_accepting_writes(self.conn_id));
         let reader = pry!(params.get());
         let kind = pry!(reader.get_kind());
         let data = pry!(reader.get_data()).to_vec();
@@ -1295,7 +3533,11 @@ impl store::Server for StoreImpl {
             "rpc request"
         );
 
-        match self.server.get_operation_sync(id_bytes) {
+        match self
+            .server
+            .get_operation_sync(id_bytes)
+            .and_then(|data| limits::fit("operation", data))
+        {
             Ok(data) => {
                 tracing::debug!(
                     conn_id = self.conn_id,
@@ -1325,17 +3567,20 @@ impl store::Server for StoreImpl {
         params: store::PutOperationParams,
         mut results: store::PutOperationResults,
     ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_accepting_writes(self.conn_id));
         let reader = pry!(params.get());
         let data = pry!(reader.get_data()).to_vec();
+        let tags = pry!(read_operation_tags(pry!(reader.get_tags())));
 
         tracing::info!(
             conn_id = self.conn_id,
             rpc = "putOperation",
             bytes = data.len(),
+            tags = tags.len(),
             "rpc request"
         );
 
-        match self.server.put_operation_sync(&data) {
+        match self.server.put_operation_sync(&data, &tags) {
             Ok(id) => {
                 tracing::info!(
                     conn_id = self.conn_id,
@@ -1375,7 +3620,11 @@ impl store::Server for StoreImpl {
             "rpc request"
         );
 
-        match self.server.get_view_sync(id_bytes) {
+        match self
+            .server
+            .get_view_sync(id_bytes)
+            .and_then(|data| limits::fit("view", data))
+        {
             Ok(data) => {
                 tracing::debug!(
                     conn_id = self.conn_id,
@@ -1405,6 +3654,7 @@ impl store::Server for StoreImpl {
         params: store::PutViewParams,
         mut results: store::PutViewResults,
     ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_accepting_writes(self.conn_id));
         let reader = pry!(params.get());
         let data = pry!(reader.get_data()).to_vec();
 
@@ -1494,10 +3744,16 @@ impl store::Server for StoreImpl {
 
     fn get_heads(
         &mut self,
-        _params: store::GetHeadsParams,
+        params: store::GetHeadsParams,
         mut results: store::GetHeadsResults,
     ) -> Promise<(), capnp::Error> {
-        tracing::debug!(conn_id = self.conn_id, rpc = "getHeads", "rpc request");
+        let with_overlaps = pry!(params.get()).get_with_overlaps();
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "getHeads",
+            with_overlaps,
+            "rpc request"
+        );
         match self.server.get_heads_sync() {
             Ok(state) => {
                 tracing::debug!(
@@ -1523,7 +3779,9 @@ impl store::Server for StoreImpl {
                 }
                 r.set_version(state.version);
                 {
-                    let mut wh = r.init_workspace_heads(state.workspace_heads.len() as u32);
+                    let mut wh = r
+                        .reborrow()
+                        .init_workspace_heads(state.workspace_heads.len() as u32);
                     for (i, (ws_id, commit_hex)) in state.workspace_heads.iter().enumerate() {
                         let mut entry = wh.reborrow().get(i as u32);
                         entry.set_workspace_id(ws_id);
@@ -1532,6 +3790,13 @@ impl store::Server for StoreImpl {
                         }
                     }
                 }
+                if with_overlaps {
+                    let overlaps = self.server.path_overlaps(&head_bytes);
+                    let mut list = r.init_overlaps(overlaps.len() as u32);
+                    for (i, overlap) in overlaps.iter().enumerate() {
+                        overlap.write(list.reborrow().get(i as u32));
+                    }
+                }
                 Promise::ok(())
             }
             Err(e) => {
@@ -1546,94 +3811,334 @@ impl store::Server for StoreImpl {
         }
     }
 
-    fn update_op_heads(
+    fn get_heads_version(
         &mut self,
-        params: store::UpdateOpHeadsParams,
-        mut results: store::UpdateOpHeadsResults,
+        _params: store::GetHeadsVersionParams,
+        mut results: store::GetHeadsVersionResults,
     ) -> Promise<(), capnp::Error> {
-        let reader = pry!(params.get());
-
-        let old_ids_reader = pry!(reader.get_old_ids());
-        let mut old_ids = Vec::new();
-        for i in 0..old_ids_reader.len() {
-            old_ids.push(pry!(old_ids_reader.get(i)).to_vec());
-        }
-
-        let new_id = pry!(reader.get_new_id()).to_vec();
-        let expected_version = reader.get_expected_version();
-        let workspace_id_text = pry!(reader.get_workspace_id());
-        let workspace_id_str = workspace_id_text.to_str().unwrap_or("");
-        let workspace_id = if workspace_id_str.is_empty() {
-            None
-        } else {
-            Some(workspace_id_str.to_string())
-        };
-
-        let request_started = Instant::now();
-        tracing::debug!(
+        tracing::trace!(
             conn_id = self.conn_id,
-            rpc = "updateOpHeads",
-            rpc_method = "updateOpHeads",
-            expected_version,
-            old_ids = old_ids.len(),
-            new_id = %to_hex(&new_id),
-            workspace_id = workspace_id.as_deref().unwrap_or(""),
-            attempt = 1,
-            cas_retries = 0,
-            queue_depth = 0,
+            rpc = "getHeadsVersion",
             "rpc request"
         );
-
+        match self.server.get_heads_version_sync() {
+            Ok(version) => {
+                results.get().set_version(version);
+                Promise::ok(())
+            }
+            Err(e) => {
+                tracing::error!(
+                    conn_id = self.conn_id,
+                    rpc = "getHeadsVersion",
+                    error = %e,
+                    "rpc error"
+                );
+                Promise::err(capnp_err(e))
+            }
+        }
+    }
+
+    fn get_tree_entries(
+        &mut self,
+        params: store::GetTreeEntriesParams,
+        mut results: store::GetTreeEntriesResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = pry!(params.get());
+        let id_bytes = pry!(reader.get_tree_id());
+        let cursor = pry!(pry!(reader.get_cursor()).to_string());
+        let limit = reader.get_limit() as usize;
+
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "getTreeEntries",
+            object_id = %to_hex(id_bytes),
+            cursor = %cursor,
+            "rpc request"
+        );
+
         match self
             .server
-            .update_op_heads_sync(old_ids, new_id, expected_version, workspace_id)
+            .get_tree_entries_sync(id_bytes, &cursor, limit)
+            .and_then(|(data, next)| Ok((limits::fit("tree page", data)?, next)))
         {
-            Ok(result) => {
+            Ok((data, next_cursor)) => {
+                let mut out = results.get();
+                out.set_entries(&data);
+                out.set_next_cursor(next_cursor.as_str());
+                Promise::ok(())
+            }
+            Err(e) => {
+                tracing::error!(
+                    conn_id = self.conn_id,
+                    rpc = "getTreeEntries",
+                    object_id = %to_hex(id_bytes),
+                    error = %e,
+                    "rpc error"
+                );
+                Promise::err(capnp_err(e))
+            }
+        }
+    }
+
+    fn search_commits(
+        &mut self,
+        params: store::SearchCommitsParams,
+        mut results: store::SearchCommitsResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = pry!(params.get());
+        let query = pry!(pry!(reader.get_query()).to_string());
+        let author = pry!(pry!(reader.get_author()).to_string());
+        let limit = search::limit(reader.get_limit());
+
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "searchCommits",
+            query = %query,
+            author = %author,
+            limit,
+            "rpc request"
+        );
+
+        match search::search_commits(&self.server.repo_loader, &query, &author, limit) {
+            Ok((commits, truncated)) => {
+                let mut out = results.get();
+                out.set_truncated(truncated);
+                let mut list = out.init_commits(commits.len() as u32);
+                for (i, commit) in commits.iter().enumerate() {
+                    let mut entry = list.reborrow().get(i as u32);
+                    entry.set_commit_id(&commit.commit_id);
+                    entry.set_change_id(&commit.change_id);
+                    entry.set_description(commit.description.as_str());
+                    entry.set_author_name(commit.author_name.as_str());
+                    entry.set_author_email(commit.author_email.as_str());
+                    entry.set_author_timestamp(commit.author_timestamp_ms);
+                }
+                Promise::ok(())
+            }
+            Err(e) => {
+                tracing::error!(
+                    conn_id = self.conn_id,
+                    rpc = "searchCommits",
+                    error = %e,
+                    "rpc error"
+                );
+                Promise::err(capnp_err(e))
+            }
+        }
+    }
+
+    fn lock_change(
+        &mut self,
+        params: store::LockChangeParams,
+        mut results: store::LockChangeResults,
+    ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_writable().map_err(capnp_err));
+        let reader = pry!(params.get());
+        let change_id = pry!(reader.get_change_id()).to_vec();
+        let workspace_id = pry!(pry!(reader.get_workspace_id()).to_string());
+        if change_id.is_empty() || workspace_id.is_empty() {
+            return Promise::err(capnp::Error::failed(
+                "lockChange needs a change id and a workspace".to_string(),
+            ));
+        }
+        let ttl = change_lock::ttl_from_secs(reader.get_ttl_seconds());
+        let strict = reader.get_strict();
+        let (ok, lock) = self.server.change_locks.lock(
+            &change_id,
+            &workspace_id,
+            ttl,
+            strict,
+            now_epoch_millis(),
+        );
+
+        tracing::info!(
+            conn_id = self.conn_id,
+            rpc = "lockChange",
+            change_id = %to_hex(&change_id),
+            workspace_id = %workspace_id,
+            holder = %lock.workspace_id,
+            ok,
+            ttl_secs = ttl.as_secs(),
+            strict,
+            "change lock requested"
+        );
+
+        let mut out = results.get();
+        out.set_ok(ok);
+        let mut entry = out.init_lock();
+        entry.set_change_id(&lock.change_id);
+        entry.set_workspace_id(lock.workspace_id.as_str());
+        entry.set_expires_at(lock.expires_at_millis);
+        entry.set_strict(lock.strict);
+        Promise::ok(())
+    }
+
+    fn unlock_change(
+        &mut self,
+        params: store::UnlockChangeParams,
+        mut results: store::UnlockChangeResults,
+    ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_writable().map_err(capnp_err));
+        let reader = pry!(params.get());
+        let change_id = pry!(reader.get_change_id()).to_vec();
+        let workspace_id = pry!(pry!(reader.get_workspace_id()).to_string());
+        let force = reader.get_force();
+        let (released, holder) =
+            self.server
+                .change_locks
+                .unlock(&change_id, &workspace_id, force, now_epoch_millis());
+
+        tracing::info!(
+            conn_id = self.conn_id,
+            rpc = "unlockChange",
+            change_id = %to_hex(&change_id),
+            workspace_id = %workspace_id,
+            force,
+            released,
+            "change unlock requested"
+        );
+
+        let mut out = results.get();
+        out.set_released(released);
+        out.set_holder(holder.as_str());
+        Promise::ok(())
+    }
+
+    fn get_change_locks(
+        &mut self,
+        _params: store::GetChangeLocksParams,
+        mut results: store::GetChangeLocksResults,
+    ) -> Promise<(), capnp::Error> {
+        let locks = self.server.change_locks.list(now_epoch_millis());
+
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "getChangeLocks",
+            locks = locks.len(),
+            "rpc request"
+        );
+
+        let mut list = results.get().init_locks(locks.len() as u32);
+        for (i, lock) in locks.iter().enumerate() {
+            let mut entry = list.reborrow().get(i as u32);
+            entry.set_change_id(&lock.change_id);
+            entry.set_workspace_id(lock.workspace_id.as_str());
+            entry.set_expires_at(lock.expires_at_millis);
+            entry.set_strict(lock.strict);
+        }
+        Promise::ok(())
+    }
+
+    fn preview_merge(
+        &mut self,
+        params: store::PreviewMergeParams,
+        mut results: store::PreviewMergeResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = pry!(params.get());
+        let commit_a = pry!(reader.get_commit_a()).to_vec();
+        let commit_b = pry!(reader.get_commit_b()).to_vec();
+        let path_limit = merge_preview::path_limit(reader.get_path_limit());
+
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "previewMerge",
+            commit_a = %to_hex(&commit_a),
+            commit_b = %to_hex(&commit_b),
+            path_limit,
+            "rpc request"
+        );
+
+        match merge_preview::preview_merge(
+            &self.server.repo_loader,
+            &commit_a,
+            &commit_b,
+            path_limit,
+        ) {
+            Ok(preview) => {
+                let mut out = results.get();
+                out.set_clean(preview.clean);
+                out.set_paths_truncated(preview.paths_truncated);
+                let mut list = out.init_conflicted_paths(preview.conflicted_paths.len() as u32);
+                for (i, path) in preview.conflicted_paths.iter().enumerate() {
+                    list.set(i as u32, path.as_str());
+                }
+                Promise::ok(())
+            }
+            Err(e) => {
+                tracing::error!(
+                    conn_id = self.conn_id,
+                    rpc = "previewMerge",
+                    error = %e,
+                    "rpc error"
+                );
+                Promise::err(capnp_err(e))
+            }
+        }
+    }
+
+    fn get_commit_graph(
+        &mut self,
+        params: store::GetCommitGraphParams,
+        mut results: store::GetCommitGraphResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = pry!(params.get());
+        let heads: Vec<Vec<u8>> = pry!(pry!(reader.get_heads())
+            .iter()
+            .map(|id| id.map(<[u8]>::to_vec))
+            .collect::<capnp::Result<_>>());
+        let known: Vec<Vec<u8>> = pry!(pry!(reader.get_known())
+            .iter()
+            .map(|id| id.map(<[u8]>::to_vec))
+            .collect::<capnp::Result<_>>());
+        let cursor = pry!(pry!(reader.get_cursor()).to_string());
+        let limit = commit_graph::limit(reader.get_limit());
+
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "getCommitGraph",
+            heads = heads.len(),
+            known = known.len(),
+            cursor = %cursor,
+            limit,
+            "rpc request"
+        );
+
+        match commit_graph::commit_graph_page(
+            &self.server.repo_loader,
+            &heads,
+            &known,
+            &cursor,
+            limit,
+            |id| self.server.get_object_sync("commit", id.as_bytes()),
+        ) {
+            Ok(page) => {
                 tracing::debug!(
                     conn_id = self.conn_id,
-                    rpc = "updateOpHeads",
-                    rpc_method = "updateOpHeads",
-                    ok = result.ok,
-                    version = result.version,
-                    heads = result.heads.len(),
-                    workspace_heads = result.workspace_heads.len(),
-                    attempt = 1,
-                    cas_retries = 0,
-                    queue_depth = 0,
-                    latency_ms = request_started.elapsed().as_millis() as u64,
+                    rpc = "getCommitGraph",
+                    commits = page.commits.len(),
+                    last_page = page.next_cursor.is_empty(),
                     "rpc response"
                 );
-                let mut r = results.get();
-                r.set_ok(result.ok);
+                let mut out = results.get();
+                out.set_next_cursor(page.next_cursor.as_str());
                 {
-                    let mut heads = r.reborrow().init_heads(result.heads.len() as u32);
-                    for (i, head) in result.heads.iter().enumerate() {
-                        heads.set(i as u32, head);
+                    let mut list = out.reborrow().init_heads(page.heads.len() as u32);
+                    for (i, id) in page.heads.iter().enumerate() {
+                        list.set(i as u32, id);
                     }
                 }
-                r.set_version(result.version);
-                {
-                    let mut wh = r.init_workspace_heads(result.workspace_heads.len() as u32);
-                    for (i, (ws_id, commit_hex)) in result.workspace_heads.iter().enumerate() {
-                        let mut entry = wh.reborrow().get(i as u32);
-                        entry.set_workspace_id(ws_id);
-                        if let Ok(commit_bytes) = from_hex(commit_hex) {
-                            entry.set_commit_id(&commit_bytes);
-                        }
-                    }
+                let mut list = out.init_commits(page.commits.len() as u32);
+                for (i, (id, data)) in page.commits.iter().enumerate() {
+                    let mut entry = list.reborrow().get(i as u32);
+                    entry.set_id(id);
+                    entry.set_data(data);
                 }
                 Promise::ok(())
             }
             Err(e) => {
                 tracing::error!(
                     conn_id = self.conn_id,
-                    rpc = "updateOpHeads",
-                    rpc_method = "updateOpHeads",
-                    expected_version,
-                    attempt = 1,
-                    cas_retries = 0,
-                    queue_depth = 0,
-                    latency_ms = request_started.elapsed().as_millis() as u64,
+                    rpc = "getCommitGraph",
                     error = %e,
                     "rpc error"
                 );
@@ -1642,6 +4147,140 @@ impl store::Server for StoreImpl {
         }
     }
 
+    fn update_op_heads(
+        &mut self,
+        params: store::UpdateOpHeadsParams,
+        mut results: store::UpdateOpHeadsResults,
+    ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_accepting_writes(self.conn_id));
+        let reader = pry!(params.get());
+
+        let old_ids_reader = pry!(reader.get_old_ids());
+        let mut old_ids = Vec::new();
+        for i in 0..old_ids_reader.len() {
+            old_ids.push(pry!(old_ids_reader.get(i)).to_vec());
+        }
+
+        let new_id = pry!(reader.get_new_id()).to_vec();
+        let expected_version = reader.get_expected_version();
+        let workspace_id_text = pry!(reader.get_workspace_id());
+        let workspace_id_str = workspace_id_text.to_str().unwrap_or("");
+        let workspace_id = if workspace_id_str.is_empty() {
+            None
+        } else {
+            Some(workspace_id_str.to_string())
+        };
+        let idempotency_key =
+            Some(pry!(reader.get_idempotency_key()).to_vec()).filter(|key| !key.is_empty());
+
+        let request_started = Instant::now();
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "updateOpHeads",
+            rpc_method = "updateOpHeads",
+            expected_version,
+            old_ids = old_ids.len(),
+            new_id = %to_hex(&new_id),
+            workspace_id = workspace_id.as_deref().unwrap_or(""),
+            attempt = 1,
+            cas_retries = 0,
+            queue_depth = 0,
+            "rpc request"
+        );
+
+        let server = Rc::clone(&self.server);
+        let conn_id = self.conn_id;
+        Promise::from_future(async move {
+            if server.snapshots.is_held() {
+                tracing::info!(
+                    conn_id,
+                    rpc = "updateOpHeads",
+                    "waiting for snapshot hold to be released"
+                );
+                watchdog::parked(server.snapshots.released()).await;
+                // The hold may have been a migration that redirected the repo.
+                server.ensure_accepting_writes(conn_id)?;
+            }
+            let queue_depth = match &server.fair_queue {
+                Some(queue) => watchdog::parked(queue.admit(workspace_id.as_deref())).await,
+                None => 0,
+            };
+            let queued_workspace = workspace_id.clone();
+            let outcome = server.update_op_heads_sync(
+                old_ids,
+                new_id,
+                expected_version,
+                workspace_id,
+                idempotency_key,
+            );
+            let cas_failed = matches!(&outcome, Ok(result) if !result.ok);
+            if let Some(queue) = &server.fair_queue {
+                queue.finish(queued_workspace.as_deref(), cas_failed);
+            }
+            let min_backoff_ms = server.record_cas_outcome(cas_failed);
+            // A lost CAS is retried on the same connection, so the commit
+            // is only over once the heads move.
+            if matches!(&outcome, Ok(result) if result.ok) {
+                server.drain.write_finished(conn_id);
+            }
+            match outcome {
+                Ok(result) => {
+                    tracing::debug!(
+                        conn_id,
+                        rpc = "updateOpHeads",
+                        rpc_method = "updateOpHeads",
+                        ok = result.ok,
+                        version = result.version,
+                        heads = result.heads.len(),
+                        workspace_heads = result.workspace_heads.len(),
+                        attempt = 1,
+                        cas_retries = 0,
+                        queue_depth,
+                        min_backoff_ms,
+                        latency_ms = request_started.elapsed().as_millis() as u64,
+                        "rpc response"
+                    );
+                    let mut r = results.get();
+                    r.set_ok(result.ok);
+                    {
+                        let mut heads = r.reborrow().init_heads(result.heads.len() as u32);
+                        for (i, head) in result.heads.iter().enumerate() {
+                            heads.set(i as u32, head);
+                        }
+                    }
+                    r.set_version(result.version);
+                    r.set_min_backoff_ms(min_backoff_ms);
+                    {
+                        let mut wh = r.init_workspace_heads(result.workspace_heads.len() as u32);
+                        for (i, (ws_id, commit_hex)) in result.workspace_heads.iter().enumerate() {
+                            let mut entry = wh.reborrow().get(i as u32);
+                            entry.set_workspace_id(ws_id);
+                            if let Ok(commit_bytes) = from_hex(commit_hex) {
+                                entry.set_commit_id(&commit_bytes);
+                            }
+                        }
+                    }
+                    Ok(())
+                }
+                Err(e) => {
+                    tracing::error!(
+                        conn_id,
+                        rpc = "updateOpHeads",
+                        rpc_method = "updateOpHeads",
+                        expected_version,
+                        attempt = 1,
+                        cas_retries = 0,
+                        queue_depth,
+                        latency_ms = request_started.elapsed().as_millis() as u64,
+                        error = %e,
+                        "rpc error"
+                    );
+                    Err(capnp_err(e))
+                }
+            }
+        })
+    }
+
     fn watch_heads(
         &mut self,
         params: store::WatchHeadsParams,
@@ -1650,11 +4289,15 @@ impl store::Server for StoreImpl {
         let reader = pry!(params.get());
         let watcher = pry!(reader.get_watcher());
         let after_version = reader.get_after_version();
+        let summary_path_limit = reader
+            .get_with_summary()
+            .then(|| heads_summary::path_limit(reader.get_summary_path_limit()));
 
         tracing::info!(
             conn_id = self.conn_id,
             rpc = "watchHeads",
             after_version,
+            summary = summary_path_limit.is_some(),
             "rpc request"
         );
 
@@ -1686,21 +4329,37 @@ impl store::Server for StoreImpl {
                 .filter_map(|h| from_hex(h).ok())
                 .collect();
             let version = current_state.version;
+            let summary = summary_path_limit.and_then(|limit| {
+                // The journal knows which workspace moved the heads last.
+                let (latest, _) = self.server.heads_history.since(version.saturating_sub(1));
+                let workspace_id = latest
+                    .iter()
+                    .find(|transition| transition.version == version)
+                    .and_then(|transition| transition.workspace_id.clone());
+                self.server
+                    .heads_summary(&heads, workspace_id.as_deref(), limit)
+            });
             tokio::task::spawn_local(async move {
                 let mut req = catch_up_watcher.notify_request();
                 {
                     let mut p = req.get();
                     p.set_version(version);
-                    let mut h = p.init_heads(heads.len() as u32);
-                    for (i, head) in heads.iter().enumerate() {
-                        h.set(i as u32, head);
+                    {
+                        let mut h = p.reborrow().init_heads(heads.len() as u32);
+                        for (i, head) in heads.iter().enumerate() {
+                            h.set(i as u32, head);
+                        }
+                    }
+                    if let Some(summary) = &summary {
+                        summary.write(p.init_summary());
                     }
                 }
                 let _ = req.send().promise.await;
             });
         }
 
-        self.server.register_watcher(watcher, current_state.version);
+        self.server
+            .register_watcher(watcher, current_state.version, summary_path_limit);
         tracing::info!(
             conn_id = self.conn_id,
             rpc = "watchHeads",
@@ -1736,6 +4395,299 @@ impl store::Server for StoreImpl {
             "getRelatedCopies not yet implemented".to_string(),
         ))
     }
+
+    fn get_operation_tags(
+        &mut self,
+        params: store::GetOperationTagsParams,
+        mut results: store::GetOperationTagsResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = pry!(params.get());
+        let filters = pry!(read_operation_tags(pry!(reader.get_filters())));
+
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "getOperationTags",
+            filters = filters.len(),
+            "rpc request"
+        );
+
+        match self.server.get_operation_tags_sync(&filters) {
+            Ok(operations) => {
+                tracing::debug!(
+                    conn_id = self.conn_id,
+                    rpc = "getOperationTags",
+                    operations = operations.len(),
+                    "rpc response"
+                );
+                let mut list = results.get().init_operations(operations.len() as u32);
+                for (i, (op_hex, tags)) in operations.iter().enumerate() {
+                    let mut entry = list.reborrow().get(i as u32);
+                    if let Ok(id) = from_hex(op_hex) {
+                        entry.set_id(&id);
+                    }
+                    let mut tags_builder = entry.init_tags(tags.len() as u32);
+                    for (j, (key, value)) in tags.iter().enumerate() {
+                        let mut tag = tags_builder.reborrow().get(j as u32);
+                        tag.set_key(key);
+                        tag.set_value(value);
+                    }
+                }
+                Promise::ok(())
+            }
+            Err(e) => {
+                tracing::error!(
+                    conn_id = self.conn_id,
+                    rpc = "getOperationTags",
+                    error = %e,
+                    "rpc error"
+                );
+                Promise::err(capnp_err(e))
+            }
+        }
+    }
+
+    fn get_heads_history(
+        &mut self,
+        params: store::GetHeadsHistoryParams,
+        mut results: store::GetHeadsHistoryResults,
+    ) -> Promise<(), capnp::Error> {
+        let after_version = pry!(params.get()).get_after_version();
+        let (transitions, oldest_version) = self.server.heads_history.since(after_version);
+
+        tracing::debug!(
+            conn_id = self.conn_id,
+            rpc = "getHeadsHistory",
+            after_version,
+            transitions = transitions.len(),
+            oldest_version,
+            "rpc response"
+        );
+
+        let mut results = results.get();
+        results.set_oldest_version(oldest_version);
+        let mut list = results.init_transitions(transitions.len() as u32);
+        for (i, transition) in transitions.iter().enumerate() {
+            let mut entry = list.reborrow().get(i as u32);
+            entry.set_version(transition.version);
+            entry.set_timestamp_millis(transition.timestamp_millis);
+            if let Some(workspace_id) = &transition.workspace_id {
+                entry.set_workspace_id(workspace_id);
+            }
+            let heads: Vec<Vec<u8>> = transition
+                .heads
+                .iter()
+                .filter_map(|hex| from_hex(hex).ok())
+                .collect();
+            let mut heads_builder = entry.init_heads(heads.len() as u32);
+            for (j, head) in heads.iter().enumerate() {
+                heads_builder.set(j as u32, head);
+            }
+        }
+        Promise::ok(())
+    }
+
+    fn begin_snapshot(
+        &mut self,
+        params: store::BeginSnapshotParams,
+        mut results: store::BeginSnapshotResults,
+    ) -> Promise<(), capnp::Error> {
+        let lease = snapshot_hold::lease_from_secs(pry!(params.get()).get_lease_seconds());
+        let (snapshot_id, version) =
+            pry!(self.server.begin_snapshot_sync(lease).map_err(capnp_err));
+
+        tracing::info!(
+            conn_id = self.conn_id,
+            rpc = "beginSnapshot",
+            snapshot_id,
+            version,
+            lease_secs = lease.as_secs(),
+            "snapshot hold started"
+        );
+
+        let mut results = results.get();
+        results.set_snapshot_id(snapshot_id);
+        results.set_version(version);
+        results.set_lease_seconds(lease.as_secs() as u32);
+        Promise::ok(())
+    }
+
+    fn end_snapshot(
+        &mut self,
+        params: store::EndSnapshotParams,
+        mut results: store::EndSnapshotResults,
+    ) -> Promise<(), capnp::Error> {
+        let snapshot_id = pry!(params.get()).get_snapshot_id();
+        let released = self.server.snapshots.end(snapshot_id);
+
+        tracing::info!(
+            conn_id = self.conn_id,
+            rpc = "endSnapshot",
+            snapshot_id,
+            released,
+            "snapshot hold ended"
+        );
+
+        results.get().set_released(released);
+        Promise::ok(())
+    }
+
+    fn import_heads(
+        &mut self,
+        params: store::ImportHeadsParams,
+        mut results: store::ImportHeadsResults,
+    ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_accepting_writes(self.conn_id));
+        let reader = pry!(params.get());
+        let heads_reader = pry!(reader.get_heads());
+        let mut heads = Vec::with_capacity(heads_reader.len() as usize);
+        for head in heads_reader.iter() {
+            heads.push(pry!(head).to_vec());
+        }
+        let mut workspace_heads = BTreeMap::new();
+        for entry in pry!(reader.get_workspace_heads()).iter() {
+            let workspace_id = pry!(pry!(entry.get_workspace_id()).to_string());
+            workspace_heads.insert(workspace_id, pry!(entry.get_commit_id()).to_vec());
+        }
+        let state = crate::rpc::HeadsState {
+            heads,
+            version: reader.get_version(),
+            workspace_heads,
+        };
+
+        match self.server.import_heads_sync(&state) {
+            Ok(version) => {
+                self.server.drain.write_finished(self.conn_id);
+                tracing::info!(
+                    conn_id = self.conn_id,
+                    rpc = "importHeads",
+                    heads = state.heads.len(),
+                    workspaces = state.workspace_heads.len(),
+                    version,
+                    "imported heads"
+                );
+                results.get().set_version(version);
+                Promise::ok(())
+            }
+            Err(e) => {
+                tracing::error!(
+                    conn_id = self.conn_id,
+                    rpc = "importHeads",
+                    error = %e,
+                    "rpc error"
+                );
+                Promise::err(capnp_err(e))
+            }
+        }
+    }
+
+    fn set_redirect(
+        &mut self,
+        params: store::SetRedirectParams,
+        mut results: store::SetRedirectResults,
+    ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_accepting_writes(self.conn_id));
+        let reader = pry!(params.get());
+        let address = pry!(pry!(reader.get_address()).to_string());
+        if address.trim().is_empty() {
+            return Promise::err(capnp::Error::failed("redirect address is empty".into()));
+        }
+        let (ok, version) = pry!(self
+            .server
+            .set_redirect_sync(address.trim(), reader.get_expected_version())
+            .map_err(capnp_err));
+        if ok {
+            self.server.drain.write_finished(self.conn_id);
+        }
+
+        tracing::info!(
+            conn_id = self.conn_id,
+            rpc = "setRedirect",
+            address = %address,
+            ok,
+            version,
+            "rpc response"
+        );
+        let mut results = results.get();
+        results.set_ok(ok);
+        results.set_version(version);
+        Promise::ok(())
+    }
+
+    fn get_view_delta(
+        &mut self,
+        params: store::GetViewDeltaParams,
+        mut results: store::GetViewDeltaResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = pry!(params.get());
+        let id = pry!(reader.get_id());
+        let base_id = pry!(reader.get_base_id());
+
+        match pry!(self
+            .server
+            .get_view_delta_sync(id, base_id)
+            .map_err(capnp_err))
+        {
+            crate::rpc::ViewFetch::Delta(delta) => {
+                tracing::debug!(
+                    conn_id = self.conn_id,
+                    rpc = "getViewDelta",
+                    view_id = %to_hex(id),
+                    ops = delta.len(),
+                    bytes = view_delta::encoded_len(&delta),
+                    "rpc response"
+                );
+                let mut results = results.get();
+                results.set_is_delta(true);
+                crate::rpc::write_delta_ops(results.init_delta(delta.len() as u32), &delta);
+            }
+            crate::rpc::ViewFetch::Full(data) => {
+                let data = pry!(limits::fit("view", data).map_err(capnp_err));
+                tracing::debug!(
+                    conn_id = self.conn_id,
+                    rpc = "getViewDelta",
+                    view_id = %to_hex(id),
+                    bytes = data.len(),
+                    "rpc response (full view)"
+                );
+                results.get().set_data(&data);
+            }
+        }
+        Promise::ok(())
+    }
+
+    fn put_view_delta(
+        &mut self,
+        params: store::PutViewDeltaParams,
+        mut results: store::PutViewDeltaResults,
+    ) -> Promise<(), capnp::Error> {
+        pry!(self.server.ensure_accepting_writes(self.conn_id));
+        let reader = pry!(params.get());
+        let base_id = pry!(reader.get_base_id());
+        let delta = pry!(crate::rpc::read_delta_ops(pry!(reader.get_delta())).map_err(capnp_err));
+
+        match self.server.put_view_delta_sync(base_id, &delta) {
+            Ok(id) => {
+                tracing::info!(
+                    conn_id = self.conn_id,
+                    rpc = "putViewDelta",
+                    view_id = %to_hex(&id),
+                    bytes = view_delta::encoded_len(&delta),
+                    "rpc response"
+                );
+                results.get().set_id(&id);
+                Promise::ok(())
+            }
+            Err(e) => {
+                tracing::error!(
+                    conn_id = self.conn_id,
+                    rpc = "putViewDelta",
+                    error = %e,
+                    "rpc error"
+                );
+                Promise::err(capnp_err(e))
+            }
+        }
+    }
 }
 
 // ─── Cancel implementation ────────────────────────────────────────────────────
@@ -1774,6 +4726,30 @@ fn updated_workspace_heads(
     next
 }
 
+fn read_operation_tags(
+    reader: capnp::struct_list::Reader<'_, crate::tandem_capnp::operation_tag::Owned>,
+) -> capnp::Result<BTreeMap<String, String>> {
+    let mut tags = BTreeMap::new();
+    for tag in reader.iter() {
+        let key = tag.get_key()?.to_string()?;
+        if !key.is_empty() {
+            tags.insert(key, tag.get_value()?.to_string()?);
+        }
+    }
+    Ok(tags)
+}
+
+/// Every filter must match; an empty filter value matches any value for that key.
+fn operation_tags_match(
+    tags: &BTreeMap<String, String>,
+    filters: &BTreeMap<String, String>,
+) -> bool {
+    filters.iter().all(|(key, value)| match tags.get(key) {
+        Some(actual) => value.is_empty() || actual == value,
+        None => false,
+    })
+}
+
 fn write_bytes_if_missing(path: &Path, bytes: &[u8]) -> Result<()> {
     if path.exists() {
         return Ok(());
@@ -1784,3 +4760,47 @@ fn write_bytes_if_missing(path: &Path, bytes: &[u8]) -> Result<()> {
     fs::write(path, bytes)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn embedded_server_answers_in_process_clients() {
+        let embedded = EmbeddedServer::start_in_process(None).unwrap();
+        assert!(embedded.address().starts_with("inproc://"));
+        let repo = embedded.repo().to_path_buf();
+        assert!(repo.join(".jj/repo").is_dir());
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let client = tandem_client::TandemClient::connect(embedded.address())
+                .await
+                .unwrap();
+            let id = client
+                .put_object(tandem_client::ObjectKind::File, b"in process\n".to_vec())
+                .await
+                .unwrap();
+            let bytes = client
+                .get_object(tandem_client::ObjectKind::File, &id)
+                .await
+                .unwrap();
+            assert_eq!(bytes, b"in process\n");
+        });
+
+        // The jj store client dials the same address from its own thread.
+        let store_client = rpc::TandemClient::connect(embedded.address()).unwrap();
+        assert_eq!(store_client.get_heads_state().unwrap().heads.len(), 1);
+
+        let address = embedded.address().to_string();
+        drop(embedded);
+        assert!(!repo.exists(), "temporary repo should be removed");
+        let Err(err) = rt.block_on(tandem_client::TandemClient::connect(&address)) else {
+            panic!("stopped server still accepts connections");
+        };
+        assert!(rpc::is_unreachable(&err), "{err:#}");
+    }
+}
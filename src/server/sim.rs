@@ -0,0 +1,391 @@
+//! Deterministic simulation of workspaces contending on `updateOpHeads`.
+//!
+//! Built with `--features sim`. N simulated workspaces commit against an
+//! in-process [`Server`] over a throwaway repo: no processes, sockets, or
+//! sleeps. Time is virtual — every RPC and every CAS backoff takes a
+//! seeded-random number of virtual milliseconds, and whichever workspace is
+//! due first runs next — so a seed fixes the whole interleaving and a failing
+//! seed replays exactly with `tandem sim --seed <n>`.
+//!
+//! Each workspace loops like a jj command does: load the heads, commit on
+//! top of them, then CAS the new operation in with the cached heads version,
+//! retrying with backoff on a version mismatch. After a run the harness
+//! checks that:
+//! - every acknowledged commit is visible at the final heads
+//! - each workspace saw the heads version strictly increase on every ack
+//! - each workspace's recorded head is its last acknowledged operation
+//! - no commit ran out of CAS retries
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use jj_lib::backend::CommitId;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::OperationId;
+use jj_lib::operation::Operation;
+use jj_lib::repo::Repo as _;
+
+use super::{from_hex, AutoAdvancePolicy, Server};
+use crate::protected_paths::ProtectedPaths;
+
+/// Same limits as the client's CAS loop (`TandemOpHeadsStore`).
+const CAS_MAX_ATTEMPTS: usize = 80;
+const CAS_BACKOFF_BASE_MS: u64 = 2;
+const CAS_BACKOFF_MAX_MS: u64 = 256;
+
+#[derive(Debug, Clone)]
+pub struct SimConfig {
+    pub seed: u64,
+    pub workspaces: usize,
+    /// Commits each workspace lands before it stops.
+    pub commits: usize,
+    /// Upper bound on one RPC's virtual latency, in milliseconds.
+    pub max_latency_ms: u64,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            workspaces: 4,
+            commits: 4,
+            max_latency_ms: 20,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SimReport {
+    pub commits: usize,
+    pub cas_conflicts: usize,
+    /// Most attempts any one commit needed.
+    pub max_attempts: usize,
+    pub final_version: u64,
+    pub virtual_ms: u64,
+    /// One line per step, in virtual-time order. Equal for equal seeds.
+    pub schedule: Vec<String>,
+}
+
+/// SplitMix64: small, seedable, and the same on every platform.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..=max`.
+    fn up_to(&mut self, max: u64) -> u64 {
+        self.next() % (max + 1)
+    }
+}
+
+/// Virtual time: workspaces wait in a queue ordered by when they are due,
+/// ties broken by a random key so equal deadlines still interleave by seed.
+#[derive(Default)]
+struct VirtualClock {
+    now_ms: u64,
+    queue: BinaryHeap<Reverse<(u64, u64, usize)>>,
+}
+
+impl VirtualClock {
+    fn wake(&mut self, workspace: usize, after_ms: u64, rng: &mut Rng) {
+        self.queue
+            .push(Reverse((self.now_ms + after_ms, rng.next(), workspace)));
+    }
+
+    fn next_due(&mut self) -> Option<usize> {
+        let Reverse((at, _, workspace)) = self.queue.pop()?;
+        self.now_ms = at;
+        Some(workspace)
+    }
+}
+
+enum Step {
+    Load,
+    Commit { base: Operation },
+    Submit(Pending),
+}
+
+struct Pending {
+    base: OperationId,
+    new: OperationId,
+    commit: CommitId,
+    /// `None` until the heads version has been read for this commit.
+    expected_version: Option<u64>,
+    attempts: usize,
+}
+
+struct SimWorkspace {
+    name: String,
+    step: Option<Step>,
+    committed: usize,
+    /// The client's optimistic version cache; dropped after contention.
+    cached_version: Option<u64>,
+    last_version: u64,
+    acked_commits: Vec<CommitId>,
+    last_acked_op: Option<String>,
+}
+
+/// Run one seeded simulation against a fresh server repo in `repo_dir`.
+pub fn run(repo_dir: &Path, config: &SimConfig) -> Result<SimReport> {
+    let server = Server::new(
+        repo_dir.to_path_buf(),
+        false,
+        AutoAdvancePolicy::default(),
+        ProtectedPaths::default(),
+        None,
+        false,
+    )?;
+    let mut rng = Rng(config.seed);
+    let mut clock = VirtualClock::default();
+    let mut workspaces: Vec<SimWorkspace> = (0..config.workspaces)
+        .map(|i| SimWorkspace {
+            name: format!("sim-{i}"),
+            step: Some(Step::Load),
+            committed: 0,
+            cached_version: None,
+            last_version: 0,
+            acked_commits: Vec::new(),
+            last_acked_op: None,
+        })
+        .collect();
+    for i in 0..workspaces.len() {
+        clock.wake(i, rng.up_to(config.max_latency_ms), &mut rng);
+    }
+
+    let mut report = SimReport {
+        commits: 0,
+        cas_conflicts: 0,
+        max_attempts: 0,
+        final_version: 0,
+        virtual_ms: 0,
+        schedule: Vec::new(),
+    };
+
+    while let Some(i) = clock.next_due() {
+        let now = clock.now_ms;
+        let ws = &mut workspaces[i];
+        let step = ws.step.take().context("woke a finished workspace")?;
+        let (next, delay) = match step {
+            Step::Load => {
+                let heads = server.get_heads_sync()?;
+                report
+                    .schedule
+                    .push(format!("{now}ms {} load v{}", ws.name, heads.version));
+                if heads.version < ws.last_version {
+                    bail!(
+                        "seed {}: {} read heads v{} after seeing v{}",
+                        config.seed,
+                        ws.name,
+                        heads.version,
+                        ws.last_version
+                    );
+                }
+                ws.last_version = heads.version;
+                let base = load_heads(&server, &heads.heads)?;
+                (Step::Commit { base }, 0)
+            }
+            Step::Commit { base } => {
+                let (new, commit) = commit_on(&server, &base, &ws.name, ws.committed)?;
+                report.schedule.push(format!("{now}ms {} commit", ws.name));
+                let pending = Pending {
+                    base: base.id().clone(),
+                    new,
+                    commit,
+                    expected_version: ws.cached_version,
+                    attempts: 0,
+                };
+                (Step::Submit(pending), 0)
+            }
+            Step::Submit(mut pending) => {
+                let Some(expected) = pending.expected_version else {
+                    // No cached version: one more round trip to read it.
+                    let version = server.get_heads_version_sync()?;
+                    report
+                        .schedule
+                        .push(format!("{now}ms {} read v{version}", ws.name));
+                    pending.expected_version = Some(version);
+                    let delay = rng.up_to(config.max_latency_ms);
+                    ws.step = Some(Step::Submit(pending));
+                    clock.wake(i, delay, &mut rng);
+                    continue;
+                };
+                pending.attempts += 1;
+                let result = server.update_op_heads_sync(
+                    vec![pending.base.as_bytes().to_vec()],
+                    pending.new.as_bytes().to_vec(),
+                    expected,
+                    Some(ws.name.clone()),
+                    None,
+                )?;
+                if result.ok {
+                    report.schedule.push(format!(
+                        "{now}ms {} submit v{expected}: ok v{}",
+                        ws.name, result.version
+                    ));
+                    if result.version <= ws.last_version {
+                        bail!(
+                            "seed {}: {} landed at v{} after seeing v{}",
+                            config.seed,
+                            ws.name,
+                            result.version,
+                            ws.last_version
+                        );
+                    }
+                    ws.last_version = result.version;
+                    ws.cached_version = (pending.attempts == 1).then_some(result.version);
+                    ws.acked_commits.push(pending.commit);
+                    ws.last_acked_op = Some(pending.new.hex());
+                    ws.committed += 1;
+                    report.commits += 1;
+                    report.max_attempts = report.max_attempts.max(pending.attempts);
+                    if ws.committed == config.commits {
+                        continue;
+                    }
+                    (Step::Load, rng.up_to(config.max_latency_ms))
+                } else {
+                    report.schedule.push(format!(
+                        "{now}ms {} submit v{expected}: conflict v{}",
+                        ws.name, result.version
+                    ));
+                    report.cas_conflicts += 1;
+                    if pending.attempts == CAS_MAX_ATTEMPTS {
+                        bail!(
+                            "seed {}: {} ran out of CAS retries after {CAS_MAX_ATTEMPTS} attempts",
+                            config.seed,
+                            ws.name
+                        );
+                    }
+                    pending.expected_version = Some(result.version);
+                    ws.last_version = ws.last_version.max(result.version);
+                    let backoff = cas_backoff_ms(pending.attempts, &mut rng);
+                    (Step::Submit(pending), backoff)
+                }
+            }
+        };
+        ws.step = Some(next);
+        let latency = rng.up_to(config.max_latency_ms);
+        clock.wake(i, delay + latency, &mut rng);
+    }
+
+    let heads = server.get_heads_sync()?;
+    let repo = server
+        .repo_loader
+        .load_at(&load_heads(&server, &heads.heads)?)?;
+    for ws in &workspaces {
+        for commit in &ws.acked_commits {
+            if !repo.view().heads().contains(commit) {
+                bail!(
+                    "seed {}: {} commit {} was acknowledged but is not at the final heads",
+                    config.seed,
+                    ws.name,
+                    commit.hex()
+                );
+            }
+        }
+        if heads.workspace_heads.get(&ws.name) != ws.last_acked_op.as_ref() {
+            bail!(
+                "seed {}: {} is recorded at {:?}, not its last acknowledged operation {:?}",
+                config.seed,
+                ws.name,
+                heads.workspace_heads.get(&ws.name),
+                ws.last_acked_op
+            );
+        }
+    }
+    report.final_version = heads.version;
+    report.virtual_ms = clock.now_ms;
+    Ok(report)
+}
+
+/// The operation a client would work on for `heads`: the single head, or a
+/// local merge of several (which, like the client's, is not published).
+fn load_heads(server: &Server, heads: &[String]) -> Result<Operation> {
+    let operations = heads
+        .iter()
+        .map(|hex| {
+            let id = OperationId::new(from_hex(hex)?);
+            Ok(server.repo_loader.load_operation(&id)?)
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(server.repo_loader.merge_operations(operations, None)?)
+}
+
+/// Write one commit and its (unpublished) operation on top of `base`.
+fn commit_on(
+    server: &Server,
+    base: &Operation,
+    workspace: &str,
+    n: usize,
+) -> Result<(OperationId, CommitId)> {
+    let repo = server.repo_loader.load_at(base)?;
+    let mut tx = repo.start_transaction();
+    let root = repo.store().root_commit_id().clone();
+    let tree = repo.store().empty_merged_tree();
+    let commit = tx
+        .repo_mut()
+        .new_commit(vec![root], tree)
+        .set_description(format!("{workspace} commit {n}"))
+        .write()?;
+    let repo = tx
+        .write(format!("{workspace}: commit {n}"))?
+        .leave_unpublished();
+    Ok((repo.op_id().clone(), commit.id().clone()))
+}
+
+/// The client's exponential backoff, with seeded jitter in place of the
+/// operation-id jitter (ids carry wall-clock timestamps).
+fn cas_backoff_ms(attempt: usize, rng: &mut Rng) -> u64 {
+    let shift = (attempt.saturating_sub(1)).min(5) as u32;
+    let base_ms = (CAS_BACKOFF_BASE_MS << shift).min(CAS_BACKOFF_MAX_MS);
+    base_ms + rng.up_to((base_ms / 2).max(1) - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use tempfile::TempDir;
+
+    fn seeds() -> u64 {
+        std::env::var("TANDEM_SIM_SEEDS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(16)
+    }
+
+    #[test]
+    fn same_seed_same_schedule() {
+        let config = SimConfig {
+            seed: 7,
+            ..SimConfig::default()
+        };
+        let first = run(TempDir::new().unwrap().path(), &config).unwrap();
+        let second = run(TempDir::new().unwrap().path(), &config).unwrap();
+        assert_eq!(first.schedule, second.schedule);
+        assert_eq!(first.final_version, second.final_version);
+    }
+
+    #[test]
+    fn contended_commits_all_land() {
+        let mut conflicts = 0;
+        for seed in 0..seeds() {
+            let config = SimConfig {
+                seed,
+                ..SimConfig::default()
+            };
+            let report = run(TempDir::new().unwrap().path(), &config)
+                .unwrap_or_else(|err| panic!("{err:#}"));
+            assert_eq!(report.commits, config.workspaces * config.commits);
+            conflicts += report.cas_conflicts;
+        }
+        assert!(conflicts > 0, "no seed produced any contention");
+    }
+}
@@ -0,0 +1,191 @@
+//! Snapshot holds — freeze the heads version for external backups.
+//!
+//! `beginSnapshot` returns the current heads version and, until the matching
+//! `endSnapshot` or the lease runs out, holds off everything that would move
+//! heads: `updateOpHeads` calls wait, and the integration worker, ephemeral
+//! sweeper, undo-remote, and replica follower pause. Object, operation, and
+//! view writes still land; they are content-addressed and unreachable from the
+//! held heads, so a backup taken during the hold is consistent at that version.
+//!
+//! `tandem snapshot -- <command>` wraps a backup command in a hold and passes
+//! the version to it as `TANDEM_SNAPSHOT_VERSION`.
+
+use std::collections::BTreeMap;
+use std::process::ExitCode;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+
+use crate::rpc::{RepoCapability, TandemClient};
+
+/// Lease used when the client asks for none.
+pub const DEFAULT_LEASE: Duration = Duration::from_secs(10 * 60);
+/// Longest hold a client can request; a crashed backup cannot stall writers
+/// for longer than this.
+pub const MAX_LEASE: Duration = Duration::from_secs(60 * 60);
+/// Environment variable carrying the held version to the backup command.
+pub const VERSION_ENV: &str = "TANDEM_SNAPSHOT_VERSION";
+
+#[derive(Default)]
+pub struct SnapshotHolds {
+    state: Mutex<HoldState>,
+    released: tokio::sync::Notify,
+}
+
+#[derive(Default)]
+struct HoldState {
+    next_id: u64,
+    /// Hold id -> lease deadline.
+    holds: BTreeMap<u64, Instant>,
+}
+
+/// Clamp a requested lease (0 means the default) to `MAX_LEASE`.
+pub fn lease_from_secs(secs: u32) -> Duration {
+    if secs == 0 {
+        DEFAULT_LEASE
+    } else {
+        Duration::from_secs(u64::from(secs)).min(MAX_LEASE)
+    }
+}
+
+impl SnapshotHolds {
+    pub fn begin(&self, lease: Duration) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        state.next_id += 1;
+        let id = state.next_id;
+        state.holds.insert(id, Instant::now() + lease);
+        id
+    }
+
+    /// Release a hold. Returns false if it was unknown or had already expired.
+    pub fn end(&self, id: u64) -> bool {
+        let released = self.state.lock().unwrap().holds.remove(&id).is_some();
+        if released {
+            self.released.notify_waiters();
+        }
+        released
+    }
+
+    pub fn is_held(&self) -> bool {
+        self.latest_deadline().is_some()
+    }
+
+    /// Drop expired holds and return when the last live one expires.
+    fn latest_deadline(&self) -> Option<Instant> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        state.holds.retain(|id, deadline| {
+            let live = *deadline > now;
+            if !live {
+                tracing::warn!(snapshot_id = *id, "snapshot hold lease expired");
+            }
+            live
+        });
+        state.holds.values().max().copied()
+    }
+
+    /// Wait until no hold is active.
+    pub async fn released(&self) {
+        loop {
+            let notified = self.released.notified();
+            let Some(deadline) = self.latest_deadline() else {
+                return;
+            };
+            tokio::select! {
+                _ = notified => {}
+                _ = tokio::time::sleep_until(tokio::time::Instant::from_std(deadline)) => {}
+            }
+        }
+    }
+}
+
+// ─── Public entry point ───────────────────────────────────────────────────────
+
+/// Run `command` while the server holds its heads, then release the hold.
+/// Exits with the command's status.
+pub fn run_snapshot(server_addr: &str, lease: Duration, command: &[String]) -> Result<ExitCode> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("no backup command given (usage: tandem snapshot -- <command> [args...])");
+    };
+
+    let client =
+        TandemClient::connect_with_requirements(server_addr, &[RepoCapability::ConsistentSnapshot])
+            .with_context(|| format!("cannot hold a snapshot on {server_addr}"))?;
+    let lease_secs = u32::try_from(lease.as_secs()).unwrap_or(u32::MAX);
+    let hold = client.begin_snapshot(lease_secs)?;
+    eprintln!(
+        "holding heads at version {} (lease {}s)",
+        hold.version, hold.lease_seconds
+    );
+
+    let status = std::process::Command::new(program)
+        .args(args)
+        .env(VERSION_ENV, hold.version.to_string())
+        .status();
+
+    let released = client.end_snapshot(hold.snapshot_id);
+    let status = status.with_context(|| format!("failed to run {program}"))?;
+    if !released? {
+        eprintln!(
+            "warning: the snapshot lease ran out before {program} finished; heads may have moved past version {}",
+            hold.version
+        );
+        return Ok(ExitCode::FAILURE);
+    }
+    eprintln!("released snapshot at version {}", hold.version);
+
+    Ok(match status.code() {
+        Some(0) => ExitCode::SUCCESS,
+        Some(code) => ExitCode::from(u8::try_from(code).unwrap_or(1)),
+        None => ExitCode::FAILURE,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lease_defaults_and_clamps() {
+        assert_eq!(lease_from_secs(0), DEFAULT_LEASE);
+        assert_eq!(lease_from_secs(30), Duration::from_secs(30));
+        assert_eq!(lease_from_secs(u32::MAX), MAX_LEASE);
+    }
+
+    #[test]
+    fn holds_end_explicitly_or_by_lease() {
+        let holds = SnapshotHolds::default();
+        assert!(!holds.is_held());
+
+        let first = holds.begin(Duration::from_secs(60));
+        let second = holds.begin(Duration::from_millis(1));
+        assert_ne!(first, second);
+        assert!(holds.end(first));
+        assert!(!holds.end(first));
+
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(!holds.is_held());
+        assert!(!holds.end(second));
+    }
+
+    #[test]
+    fn released_wakes_on_end() {
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let holds = std::sync::Arc::new(SnapshotHolds::default());
+            let id = holds.begin(Duration::from_secs(60));
+            let ender = std::sync::Arc::clone(&holds);
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                ender.end(id);
+            });
+            tokio::time::timeout(Duration::from_secs(5), holds.released())
+                .await
+                .expect("released() should return once the hold ends");
+        });
+    }
+}
@@ -0,0 +1,152 @@
+//! Repo-wide snapshot policy — which new files workspaces start tracking.
+//!
+//! `tandem serve --snapshot-exclude <pattern>` (repeatable) and
+//! `--snapshot-auto-track <fileset>` combine into one jj fileset that the
+//! server advertises as `RepoInfo.snapshotAutoTrack`. `tandem init` writes
+//! it to the new workspace's repo config as `snapshot.auto-track`, so build
+//! output and dependency trees never land in an agent's commits and nobody
+//! has to set that up per workspace.
+//!
+//! Exclusion patterns follow `.gitignore` for the common cases: a pattern
+//! with no `/` but a trailing one matches at any depth (`node_modules/`), a
+//! leading or inner `/` anchors it at the repo root (`/target/`,
+//! `docs/build/`), and a trailing `/` means the directory and everything
+//! under it. Globs (`*.log`) work as in jj's `root-glob:`. Negations (`!`)
+//! are not supported.
+//!
+//! Like jj's own `snapshot.auto-track`, the policy only stops untracked
+//! files from being picked up; files a commit already tracks stay tracked.
+//! Workspaces created before the policy keep their config until it is set
+//! there by hand.
+
+use anyhow::{bail, Result};
+
+/// `tandem serve` flags for the policy.
+#[derive(clap::Args, Clone, Debug)]
+pub struct SnapshotPolicyArgs {
+    /// Never snapshot new files matching this .gitignore-style pattern
+    /// (e.g. target/, node_modules/) in workspaces `tandem init` creates.
+    /// Repeatable
+    #[arg(long, value_name = "PATTERN")]
+    pub snapshot_exclude: Vec<String>,
+    /// Fileset of new files workspaces `tandem init` creates snapshot
+    /// (their `snapshot.auto-track`); `--snapshot-exclude` narrows it
+    #[arg(long, value_name = "FILESET")]
+    pub snapshot_auto_track: Option<String>,
+}
+
+impl SnapshotPolicyArgs {
+    pub fn auto_track(&self) -> Result<Option<String>> {
+        auto_track(self.snapshot_auto_track.as_deref(), &self.snapshot_exclude)
+    }
+}
+
+/// The fileset for `--snapshot-auto-track` minus every `--snapshot-exclude`,
+/// or `None` when neither is set.
+pub fn auto_track(base: Option<&str>, excludes: &[String]) -> Result<Option<String>> {
+    let base = base.map(str::trim).filter(|base| !base.is_empty());
+    if base.is_none() && excludes.is_empty() {
+        return Ok(None);
+    }
+    let mut fileset = match base {
+        Some(base) => format!("({base})"),
+        None => "all()".to_string(),
+    };
+    if !excludes.is_empty() {
+        let excluded = excludes
+            .iter()
+            .map(|pattern| exclude_fileset(pattern))
+            .collect::<Result<Vec<_>>>()?;
+        fileset = format!("{fileset} ~ ({})", excluded.join(" | "));
+    }
+    validate(&fileset)?;
+    Ok(Some(fileset))
+}
+
+/// One `--snapshot-exclude` pattern as a fileset.
+fn exclude_fileset(raw: &str) -> Result<String> {
+    let pattern = raw.trim();
+    if pattern.is_empty() || pattern == "/" {
+        bail!("invalid snapshot exclusion {raw:?}: empty pattern");
+    }
+    if pattern.starts_with('!') {
+        bail!("invalid snapshot exclusion {raw:?}: negated patterns are not supported");
+    }
+    if pattern.split('/').any(|part| part == "." || part == "..") {
+        bail!("invalid snapshot exclusion {raw:?}: `.` and `..` are not allowed");
+    }
+    let (pattern, dir_only) = match pattern.strip_suffix('/') {
+        Some(dir) => (dir, true),
+        None => (pattern, false),
+    };
+    let anchored = pattern.contains('/');
+    let pattern = pattern.trim_start_matches('/');
+    let glob = if anchored {
+        pattern.to_string()
+    } else {
+        format!("**/{pattern}")
+    };
+    let quote = |glob: &str| serde_json::to_string(glob).expect("string serializes");
+    Ok(if dir_only {
+        format!("root-glob:{}", quote(&format!("{glob}/**")))
+    } else {
+        // Like .gitignore, a name without a trailing `/` also covers a
+        // directory of that name.
+        format!(
+            "root-glob:{} | root-glob:{}",
+            quote(&glob),
+            quote(&format!("{glob}/**"))
+        )
+    })
+}
+
+/// Check that `fileset` parses the way jj will parse it in a workspace.
+fn validate(fileset: &str) -> Result<()> {
+    let root = std::env::temp_dir();
+    let converter = jj_lib::repo_path::RepoPathUiConverter::Fs {
+        cwd: root.clone(),
+        base: root,
+    };
+    let mut diagnostics = jj_lib::fileset::FilesetDiagnostics::new();
+    if let Err(err) = jj_lib::fileset::parse(&mut diagnostics, fileset, &converter) {
+        bail!("invalid snapshot auto-track fileset {fileset:?}: {err}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn excludes(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn no_policy_without_flags() {
+        assert_eq!(auto_track(None, &[]).unwrap(), None);
+        assert_eq!(auto_track(Some("  "), &[]).unwrap(), None);
+    }
+
+    #[test]
+    fn excludes_follow_gitignore_anchoring() {
+        assert_eq!(
+            auto_track(None, &excludes(&["node_modules/", "/target/"])).unwrap(),
+            Some(r#"all() ~ (root-glob:"**/node_modules/**" | root-glob:"target/**")"#.to_string())
+        );
+        assert_eq!(
+            auto_track(Some("glob:\"src/**\""), &excludes(&["*.log"])).unwrap(),
+            Some(
+                r#"(glob:"src/**") ~ (root-glob:"**/*.log" | root-glob:"**/*.log/**")"#.to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_express() {
+        assert!(auto_track(None, &excludes(&["!keep.txt"])).is_err());
+        assert!(auto_track(None, &excludes(&["../up"])).is_err());
+        assert!(auto_track(None, &excludes(&[""])).is_err());
+        assert!(auto_track(Some("all() ~"), &[]).is_err());
+    }
+}
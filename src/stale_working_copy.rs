@@ -0,0 +1,111 @@
+//! Catch the working copy up to operations built on top of its own.
+//!
+//! Before snapshotting, jj decides whether the working copy's operation is an
+//! ancestor of the operation it loaded with a breadth-first search for the
+//! closest operation both reach. Under contention the server merges other
+//! workspaces' operations on top of ours, and each of them hangs off an
+//! older operation; the search then meets one of those on a shorter side
+//! path before it reaches ours, and jj refuses to run with "the repo was
+//! loaded at operation ..., which seems to be a sibling of the working
+//! copy's operation".
+//!
+//! This dispatch hook walks the whole ancestry instead. When the head does
+//! descend from the working copy's operation and still has this workspace on
+//! the same working-copy commit, it records the head as the working copy's
+//! operation, which is what jj does itself after a snapshot that finds
+//! nothing to commit. Anything else is left for jj to judge.
+
+use std::collections::{HashSet, VecDeque};
+
+use anyhow::{anyhow, Result};
+use jj_cli::cli_util::{CliDispatchFn, CommandHelper};
+use jj_cli::command_error::CommandError;
+use jj_cli::ui::Ui;
+use jj_lib::object_id::ObjectId as _;
+use jj_lib::op_store::OperationId;
+use jj_lib::operation::Operation;
+
+use crate::backend::TandemBackend;
+use crate::integrate::{CLOCK_SKEW_SLACK_MS, OP_WALK_LIMIT};
+
+/// Dispatch hook: advance a working copy whose operation the head has
+/// merged, so jj's stale check does not mistake it for a sibling.
+pub fn catch_up(
+    ui: &mut Ui,
+    command: &CommandHelper,
+    dispatch: CliDispatchFn,
+) -> Result<(), CommandError> {
+    let args = command.global_args();
+    if !args.ignore_working_copy && args.at_operation.is_none() {
+        if let Err(err) = advance_to_head(command) {
+            tracing::debug!(error = %err, "working copy left for jj's stale check");
+        }
+    }
+    dispatch(ui, command)
+}
+
+fn advance_to_head(command: &CommandHelper) -> Result<()> {
+    let mut workspace = command
+        .load_workspace()
+        .map_err(|_| anyhow!("no workspace here"))?;
+    let loader = workspace.repo_loader();
+    if loader.store().backend_impl::<TandemBackend>().is_none() {
+        return Ok(());
+    }
+    let wc_op_id = workspace.working_copy().operation_id().clone();
+    let repo = loader.load_at_head()?;
+    if repo.op_id() == &wc_op_id {
+        return Ok(());
+    }
+    let wc_op = loader.load_operation(&wc_op_id)?;
+    let name = workspace.workspace_name();
+    let wc_commit = wc_op.view()?.get_wc_commit_id(name).cloned();
+    if wc_commit.is_none() || repo.view().get_wc_commit_id(name) != wc_commit.as_ref() {
+        return Ok(());
+    }
+    if !descends_from(repo.operation(), &wc_op)? {
+        return Ok(());
+    }
+
+    let mut locked = workspace.start_working_copy_mutation()?;
+    if locked.locked_wc().old_operation_id() != &wc_op_id {
+        // Another command moved the working copy meanwhile.
+        return Ok(());
+    }
+    tracing::debug!(
+        from = %wc_op_id.hex(),
+        to = %repo.op_id().hex(),
+        "advancing working copy to the operation that merged it"
+    );
+    locked.finish(repo.op_id().clone())?;
+    Ok(())
+}
+
+/// Whether `ancestor` is reachable from `head`. Operations that ended well
+/// before `ancestor` did are not searched.
+fn descends_from(head: &Operation, ancestor: &Operation) -> Result<bool> {
+    let cutoff = ancestor
+        .metadata()
+        .time
+        .end
+        .timestamp
+        .0
+        .saturating_sub(CLOCK_SKEW_SLACK_MS);
+    let mut seen: HashSet<OperationId> = HashSet::new();
+    let mut queue = VecDeque::from([head.clone()]);
+    while let Some(op) = queue.pop_front() {
+        if op.id() == ancestor.id() {
+            return Ok(true);
+        }
+        if !seen.insert(op.id().clone()) || op.metadata().time.end.timestamp.0 < cutoff {
+            continue;
+        }
+        if seen.len() > OP_WALK_LIMIT {
+            return Ok(false);
+        }
+        for parent in op.parents() {
+            queue.push_back(parent?);
+        }
+    }
+    Ok(false)
+}
@@ -0,0 +1,181 @@
+//! Status file — a JSON snapshot of the server, rewritten on a timer.
+//!
+//! `tandem serve --status-file <path>` writes the snapshot every
+//! `--status-interval` (default 30s) for hosts with no metrics scraper: a cron
+//! check or a dashboard reads the file instead of speaking the control socket.
+//! Each write goes to a temporary file that is renamed over `<path>`, so
+//! readers never see a half-written snapshot.
+//!
+//! ```json
+//! {"running":true,"pid":4242,"version":"0.1.0","repo":"/srv/repo",
+//!  "listen":"0.0.0.0:13013","uptimeSecs":3600,"updatedAtMillis":...,
+//!  "intervalSecs":30,"headsVersion":812,"opHeads":1,"workspaces":7,
+//!  "connections":{"active":3,"total":1290},"watchers":2,
+//!  "health":{"healthy":true,"checks":[...]}}
+//! ```
+//!
+//! `updatedAtMillis` and `intervalSecs` let a check notice a server that
+//! stopped updating the file. On a clean shutdown the file is rewritten one
+//! last time with `"running": false`.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+
+use crate::health::HealthResponse;
+use crate::maintenance::MaintenanceNotice;
+use crate::redirect::RedirectTarget;
+use crate::tmp_file;
+
+/// How often the snapshot is rewritten when `--status-interval` is not given.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusSnapshot {
+    pub running: bool,
+    pub pid: u32,
+    pub version: String,
+    pub repo: String,
+    pub listen: String,
+    /// Further `--listen` addresses, after the first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub also_listen: Vec<String>,
+    pub uptime_secs: u64,
+    pub updated_at_millis: u64,
+    pub interval_secs: u64,
+    /// Unset when the heads metadata could not be read.
+    pub heads_version: Option<u64>,
+    pub op_heads: Option<usize>,
+    /// Workspaces with a recorded head.
+    pub workspaces: Option<usize>,
+    pub connections: ConnectionStats,
+    /// Live `watchHeads` subscriptions.
+    pub watchers: usize,
+    pub health: HealthResponse,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub following: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub maintenance: Option<MaintenanceNotice>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect: Option<RedirectTarget>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStats {
+    /// Clients connected right now.
+    pub active: u32,
+    /// Clients accepted since the server started.
+    pub total: u64,
+}
+
+/// Where and how often one server writes its snapshot, and the details of
+/// the snapshot that do not change while it runs.
+pub struct StatusFile {
+    pub path: PathBuf,
+    pub interval: Duration,
+    pub repo: String,
+    pub listen: String,
+    pub also_listen: Vec<String>,
+    pub following: Option<String>,
+    pub started: Instant,
+}
+
+impl StatusFile {
+    /// A snapshot with everything but the server's own counters filled in.
+    pub fn snapshot(&self, running: bool, connections: ConnectionStats) -> StatusSnapshot {
+        StatusSnapshot {
+            running,
+            pid: std::process::id(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            repo: self.repo.clone(),
+            listen: self.listen.clone(),
+            also_listen: self.also_listen.clone(),
+            uptime_secs: self.started.elapsed().as_secs(),
+            updated_at_millis: now_millis(),
+            interval_secs: self.interval.as_secs(),
+            heads_version: None,
+            op_heads: None,
+            workspaces: None,
+            connections,
+            watchers: 0,
+            health: HealthResponse::from_checks(Vec::new()),
+            following: self.following.clone(),
+            maintenance: None,
+            redirect: None,
+        }
+    }
+
+    pub fn write(&self, snapshot: &StatusSnapshot) {
+        if let Err(err) = write_snapshot(&self.path, snapshot) {
+            tracing::warn!(path = %self.path.display(), error = %format!("{err:#}"), "status file write failed");
+        }
+    }
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Replace `path` with `snapshot`.
+fn write_snapshot(path: &Path, snapshot: &StatusSnapshot) -> Result<()> {
+    let tmp = tmp_file::tmp_path(path);
+    let mut contents = serde_json::to_vec_pretty(snapshot)?;
+    contents.push(b'\n');
+    std::fs::write(&tmp, contents).with_context(|| format!("write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("replace {}", path.display()))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot() -> StatusSnapshot {
+        StatusSnapshot {
+            running: true,
+            pid: 1,
+            version: "0.0.0".to_string(),
+            repo: "/srv/repo".to_string(),
+            listen: "127.0.0.1:13013".to_string(),
+            also_listen: Vec::new(),
+            uptime_secs: 5,
+            updated_at_millis: 1_000,
+            interval_secs: 30,
+            heads_version: Some(3),
+            op_heads: Some(1),
+            workspaces: None,
+            connections: ConnectionStats {
+                active: 2,
+                total: 9,
+            },
+            watchers: 0,
+            health: HealthResponse::from_checks(Vec::new()),
+            following: None,
+            maintenance: None,
+            redirect: None,
+        }
+    }
+
+    #[test]
+    fn write_replaces_the_file_without_leaving_temporaries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status.json");
+        std::fs::write(&path, "stale").unwrap();
+
+        write_snapshot(&path, &snapshot()).unwrap();
+        let value: serde_json::Value =
+            serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        assert_eq!(value["headsVersion"], 3);
+        assert_eq!(value["connections"]["total"], 9);
+        assert!(value["workspaces"].is_null());
+        assert!(value.get("redirect").is_none());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
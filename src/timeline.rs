@@ -0,0 +1,269 @@
+//! tandem timeline — time-ordered activity across workspaces.
+//!
+//! Built from the server's heads history (`getHeadsHistory`): each version
+//! bump is one row, attributed to the workspace whose `updateOpHeads` caused
+//! it, or to the server for integration and reconciliation. The operations
+//! that became heads in that step are read for their description and the
+//! number of commits they wrote. A step that leaves several op heads is
+//! flagged as divergent; CAS retries that resolved cleanly leave no trace in
+//! the journal and so do not appear.
+
+use std::collections::HashSet;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result};
+use prost::Message as _;
+
+use crate::proto_convert;
+use crate::ps::format_age;
+use crate::rpc::{HeadsTransition, RepoCapability, TandemClient};
+
+struct TimelineEntry {
+    version: u64,
+    timestamp_millis: u64,
+    workspace: Option<String>,
+    operations: Vec<OperationSummary>,
+    head_count: usize,
+}
+
+struct OperationSummary {
+    id: String,
+    description: String,
+    commits: Option<usize>,
+}
+
+// ─── Public entry point ───────────────────────────────────────────────────────
+
+pub fn run_timeline(
+    server_addr: &str,
+    workspace: Option<&str>,
+    since_secs: Option<u64>,
+    json: bool,
+) -> Result<()> {
+    let since = since_secs.map(Duration::from_secs);
+    let client =
+        TandemClient::connect_with_requirements(server_addr, &[RepoCapability::HeadsHistory])
+            .with_context(|| format!("cannot read activity on {server_addr}"))?;
+
+    let (transitions, oldest_version) = client.get_heads_history(0)?;
+    let cutoff_millis = since.map(|since| {
+        SystemTime::now()
+            .checked_sub(since)
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    });
+
+    let mut entries = Vec::new();
+    let mut previous: HashSet<&[u8]> = HashSet::new();
+    for transition in &transitions {
+        let new_heads: Vec<&Vec<u8>> = transition
+            .heads
+            .iter()
+            .filter(|head| !previous.contains(head.as_slice()))
+            .collect();
+        previous = transition.heads.iter().map(Vec::as_slice).collect();
+        if !selected(transition, workspace, cutoff_millis) {
+            continue;
+        }
+        let operations = new_heads
+            .into_iter()
+            .map(|head| summarize_operation(&client, head))
+            .collect::<Result<_>>()?;
+        entries.push(TimelineEntry {
+            version: transition.version,
+            timestamp_millis: transition.timestamp_millis,
+            workspace: transition.workspace_id.clone(),
+            operations,
+            head_count: transition.heads.len(),
+        });
+    }
+
+    if json {
+        let out: Vec<serde_json::Value> = entries.iter().map(entry_json).collect();
+        println!("{}", serde_json::to_string_pretty(&out)?);
+        return Ok(());
+    }
+    if oldest_version > 1 && since.is_none() {
+        eprintln!(
+            "note: heads history starts at version {oldest_version}; older activity was discarded"
+        );
+    }
+    print!("{}", render(&entries));
+    Ok(())
+}
+
+fn selected(
+    transition: &HeadsTransition,
+    workspace: Option<&str>,
+    cutoff_millis: Option<u64>,
+) -> bool {
+    if let Some(workspace) = workspace {
+        if transition.workspace_id.as_deref() != Some(workspace) {
+            return false;
+        }
+    }
+    cutoff_millis.is_none_or(|cutoff| transition.timestamp_millis >= cutoff)
+}
+
+fn summarize_operation(client: &TandemClient, id: &[u8]) -> Result<OperationSummary> {
+    let id_hex = jj_lib::hex_util::encode_hex(id);
+    if id == client.repo_info().root_operation_id.as_slice() {
+        return Ok(OperationSummary {
+            id: id_hex,
+            description: "root".to_string(),
+            commits: None,
+        });
+    }
+    let data = client
+        .get_operation(id)
+        .with_context(|| format!("reading operation {}", &id_hex[..12]))?;
+    let proto =
+        jj_lib::protos::simple_op_store::Operation::decode(&*data).context("decoding operation")?;
+    let operation = proto_convert::operation_from_proto(proto)?;
+    Ok(OperationSummary {
+        id: id_hex,
+        description: operation
+            .metadata
+            .description
+            .lines()
+            .next()
+            .unwrap_or("")
+            .to_string(),
+        commits: operation.commit_predecessors.map(|map| map.len()),
+    })
+}
+
+// ─── Rendering ────────────────────────────────────────────────────────────────
+
+fn entry_json(entry: &TimelineEntry) -> serde_json::Value {
+    serde_json::json!({
+        "version": entry.version,
+        "timestampMillis": entry.timestamp_millis,
+        "workspace": entry.workspace,
+        "heads": entry.head_count,
+        "operations": entry
+            .operations
+            .iter()
+            .map(|op| serde_json::json!({
+                "id": op.id,
+                "description": op.description,
+                "commits": op.commits,
+            }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn render(entries: &[TimelineEntry]) -> String {
+    if entries.is_empty() {
+        return "no activity recorded\n".to_string();
+    }
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    let who = |entry: &TimelineEntry| {
+        entry
+            .workspace
+            .clone()
+            .unwrap_or_else(|| "(server)".to_string())
+    };
+    let who_width = entries
+        .iter()
+        .map(|entry| who(entry).chars().count())
+        .max()
+        .unwrap_or(0);
+
+    let mut out = String::new();
+    for entry in entries {
+        let age = format_age(now_ms.saturating_sub(entry.timestamp_millis as i64));
+        let mut what: Vec<String> = entry
+            .operations
+            .iter()
+            .map(|op| {
+                let description = if op.description.is_empty() {
+                    format!("operation {}", &op.id[..12])
+                } else {
+                    op.description.clone()
+                };
+                match op.commits {
+                    Some(0) | None => description,
+                    Some(1) => format!("{description} (1 commit)"),
+                    Some(n) => format!("{description} ({n} commits)"),
+                }
+            })
+            .collect();
+        if what.is_empty() {
+            what.push("heads merged".to_string());
+        }
+        let mut line = format!(
+            "{age:>8}  v{:<5} {:<who_width$}  {}",
+            entry.version,
+            who(entry),
+            what.join("; ")
+        );
+        if entry.head_count > 1 {
+            line.push_str(&format!(" [{} heads, divergent]", entry.head_count));
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_by_workspace_and_time() {
+        let transition = HeadsTransition {
+            version: 3,
+            heads: vec![vec![1; 64]],
+            workspace_id: Some("agent-a".to_string()),
+            timestamp_millis: 5_000,
+        };
+        assert!(selected(&transition, None, None));
+        assert!(selected(&transition, Some("agent-a"), Some(5_000)));
+        assert!(!selected(&transition, Some("agent-b"), None));
+        assert!(!selected(&transition, None, Some(5_001)));
+    }
+
+    #[test]
+    fn renders_server_steps_and_divergence() {
+        let entries = [
+            TimelineEntry {
+                version: 4,
+                timestamp_millis: 0,
+                workspace: Some("agent-a".to_string()),
+                operations: vec![OperationSummary {
+                    id: "ab".repeat(32),
+                    description: "new empty commit".to_string(),
+                    commits: Some(1),
+                }],
+                head_count: 2,
+            },
+            TimelineEntry {
+                version: 5,
+                timestamp_millis: 0,
+                workspace: None,
+                operations: vec![OperationSummary {
+                    id: "cd".repeat(32),
+                    description: String::new(),
+                    commits: Some(3),
+                }],
+                head_count: 1,
+            },
+        ];
+        let out = render(&entries);
+        let lines: Vec<&str> = out.lines().collect();
+        assert!(
+            lines[0].ends_with("v4     agent-a   new empty commit (1 commit) [2 heads, divergent]"),
+            "{out}"
+        );
+        assert!(
+            lines[1].ends_with("v5     (server)  operation cdcdcdcdcdcd (3 commits)"),
+            "{out}"
+        );
+    }
+}
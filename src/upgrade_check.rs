@@ -0,0 +1,368 @@
+//! tandem upgrade-check — which side to upgrade, and what it would unlock.
+//!
+//! Compares this tandem with what the server advertises in `getRepoInfo`
+//! (`tandemVersion`, `jjVersion`, `opSchema`, capabilities) and, with
+//! `--index`, with the latest release named in a static JSON file:
+//!
+//! ```json
+//! { "latest": "0.4.0", "url": "https://example.com/tandem/releases/0.4.0" }
+//! ```
+//!
+//! The index is read from a path, a `file://` URL, or fetched with `curl`
+//! for `http(s)://` URLs. The verdict is `none`, `client`, `server`, or
+//! `both`, with a line for each reason and the server features (copy
+//! tracking, head snapshots, ...) the server does not offer.
+
+use std::cmp::Ordering;
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::jj_compat;
+use crate::rpc::{self, RepoCapability, RepoInfoResponse};
+
+/// Where to read the release index when `--index` is not given.
+pub const INDEX_ENV: &str = "TANDEM_UPGRADE_INDEX";
+
+/// The release index file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ReleaseIndex {
+    pub latest: String,
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Upgrade {
+    None,
+    Client,
+    Server,
+    Both,
+}
+
+impl Upgrade {
+    fn with(self, other: Upgrade) -> Upgrade {
+        match (self, other) {
+            (Upgrade::None, other) | (other, Upgrade::None) => other,
+            (a, b) if a == b => a,
+            _ => Upgrade::Both,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Upgrade::None => "none needed",
+            Upgrade::Client => "client",
+            Upgrade::Server => "server",
+            Upgrade::Both => "client and server",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MissingFeature {
+    pub capability: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpgradeReport {
+    pub client_version: String,
+    pub client_jj_version: String,
+    /// `None` for servers that predate version reporting.
+    pub server_version: Option<String>,
+    pub server_jj_version: Option<String>,
+    pub latest_version: Option<String>,
+    pub latest_url: Option<String>,
+    pub upgrade: Upgrade,
+    pub reasons: Vec<String>,
+    /// Features this tandem knows that the server does not offer.
+    pub missing: Vec<MissingFeature>,
+}
+
+/// `major.minor.patch` of a version string, ignoring a leading `v` and any
+/// pre-release or build suffix.
+pub fn parse_version(raw: &str) -> Option<(u64, u64, u64)> {
+    let raw = raw.trim().trim_start_matches('v');
+    let core = raw.split(['-', '+']).next()?;
+    let mut parts = core.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next().unwrap_or(Some(0))?;
+    let patch = parts.next().unwrap_or(Some(0))?;
+    Some((major, minor, patch))
+}
+
+fn compare_versions(a: &str, b: &str) -> Option<Ordering> {
+    Some(parse_version(a)?.cmp(&parse_version(b)?))
+}
+
+/// Decide which side to upgrade for a client at `client_version` talking to
+/// the server behind `info`, given the latest release if known.
+pub fn assess(
+    client_version: &str,
+    info: &RepoInfoResponse,
+    latest: Option<&ReleaseIndex>,
+) -> UpgradeReport {
+    let mut upgrade = Upgrade::None;
+    let mut reasons = Vec::new();
+    let server_version = info.tandem_version.clone();
+
+    match server_version.as_deref() {
+        None => {
+            upgrade = upgrade.with(Upgrade::Server);
+            reasons.push("the server predates version reporting".to_string());
+        }
+        Some(server) => match compare_versions(server, client_version) {
+            Some(Ordering::Less) => {
+                upgrade = upgrade.with(Upgrade::Server);
+                reasons.push(format!(
+                    "the server runs tandem {server}, older than this tandem ({client_version})"
+                ));
+            }
+            Some(Ordering::Greater) => {
+                upgrade = upgrade.with(Upgrade::Client);
+                reasons.push(format!(
+                    "this tandem ({client_version}) is older than the server's ({server})"
+                ));
+            }
+            Some(Ordering::Equal) => {}
+            None => reasons.push(format!("cannot compare server version {server:?}")),
+        },
+    }
+
+    if let Some(problem) = jj_compat::op_schema_problem(
+        info.jj_version.as_deref().unwrap_or("(unknown)"),
+        &info.op_schema,
+    ) {
+        let older_jj = info
+            .jj_version
+            .as_deref()
+            .and_then(|server| compare_versions(server, jj_compat::JJ_LIB_VERSION));
+        upgrade = upgrade.with(match older_jj {
+            Some(Ordering::Less) => Upgrade::Server,
+            Some(Ordering::Greater) => Upgrade::Client,
+            _ => Upgrade::Both,
+        });
+        reasons.push(problem);
+    }
+
+    if let Some(latest) = latest {
+        if compare_versions(client_version, &latest.latest) == Some(Ordering::Less) {
+            upgrade = upgrade.with(Upgrade::Client);
+            reasons.push(format!(
+                "tandem {} is out; this tandem is {client_version}",
+                latest.latest
+            ));
+        }
+        if let Some(server) = server_version.as_deref() {
+            if compare_versions(server, &latest.latest) == Some(Ordering::Less) {
+                upgrade = upgrade.with(Upgrade::Server);
+                reasons.push(format!(
+                    "tandem {} is out; the server runs {server}",
+                    latest.latest
+                ));
+            }
+        }
+    }
+
+    let missing = RepoCapability::ALL
+        .iter()
+        .filter(|capability| !info.capabilities.contains(capability))
+        .map(|capability| MissingFeature {
+            capability: capability.as_str().to_string(),
+            description: capability.describe().to_string(),
+        })
+        .collect();
+
+    UpgradeReport {
+        client_version: client_version.to_string(),
+        client_jj_version: jj_compat::JJ_LIB_VERSION.to_string(),
+        server_version,
+        server_jj_version: info.jj_version.clone(),
+        latest_version: latest.map(|latest| latest.latest.clone()),
+        latest_url: latest.and_then(|latest| latest.url.clone()),
+        upgrade,
+        reasons,
+        missing,
+    }
+}
+
+/// Read the release index at `location`: a path, `file://` URL, or
+/// `http(s)://` URL.
+pub fn read_index(location: &str) -> Result<ReleaseIndex> {
+    let bytes = if location.starts_with("http://") || location.starts_with("https://") {
+        let output = Command::new("curl")
+            .args(["-fsSL", "--max-time", "10", location])
+            .output()
+            .map_err(|err| anyhow!("run curl (needed for http(s) release indexes): {err}"))?;
+        if !output.status.success() {
+            bail!(
+                "curl {location}: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        output.stdout
+    } else {
+        let path = location.strip_prefix("file://").unwrap_or(location);
+        std::fs::read(path).with_context(|| format!("read {path}"))?
+    };
+    let index: ReleaseIndex = serde_json::from_slice(&bytes)
+        .with_context(|| format!("parse release index {location}"))?;
+    if parse_version(&index.latest).is_none() {
+        bail!(
+            "release index {location}: `latest` is not a version: {:?}",
+            index.latest
+        );
+    }
+    Ok(index)
+}
+
+pub fn run_upgrade_check(server_addr: &str, index: Option<&str>, json: bool) -> Result<()> {
+    let info = rpc::probe_repo_info(server_addr)
+        .with_context(|| format!("cannot reach tandem server at {server_addr}"))?;
+    let latest = match index {
+        Some(location) => match read_index(location) {
+            Ok(index) => Some(index),
+            Err(err) => {
+                eprintln!("warning: could not read the release index: {err:#}");
+                None
+            }
+        },
+        None => None,
+    };
+    let report = assess(env!("CARGO_PKG_VERSION"), &info, latest.as_ref());
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!(
+        "client:  tandem {} (jj {})",
+        report.client_version, report.client_jj_version
+    );
+    match (&report.server_version, &report.server_jj_version) {
+        (Some(version), Some(jj)) => {
+            println!("server:  tandem {version} (jj {jj}) at {server_addr}")
+        }
+        (Some(version), None) => println!("server:  tandem {version} at {server_addr}"),
+        _ => println!("server:  tandem (version not reported) at {server_addr}"),
+    }
+    if let Some(latest) = &report.latest_version {
+        match &report.latest_url {
+            Some(url) => println!("latest:  tandem {latest} ({url})"),
+            None => println!("latest:  tandem {latest}"),
+        }
+    }
+    if !report.missing.is_empty() {
+        let features: Vec<String> = report
+            .missing
+            .iter()
+            .map(|feature| format!("{} ({})", feature.description, feature.capability))
+            .collect();
+        println!("not offered by the server: {}", features.join(", "));
+    }
+    println!("upgrade: {}", report.upgrade.describe());
+    for reason in &report.reasons {
+        println!("  - {reason}");
+    }
+    if report.upgrade == Upgrade::Server && !report.missing.is_empty() {
+        println!("  upgrading the server may unlock the features it does not offer");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(tandem_version: Option<&str>, capabilities: &[RepoCapability]) -> RepoInfoResponse {
+        RepoInfoResponse {
+            protocol_major: 0,
+            protocol_minor: 1,
+            jj_version: tandem_version.map(|_| jj_compat::JJ_LIB_VERSION.to_string()),
+            tandem_version: tandem_version.map(str::to_string),
+            backend_name: "tandem".to_string(),
+            op_store_name: "tandem_op_store".to_string(),
+            commit_id_length: 20,
+            change_id_length: 16,
+            commit_hash: "sha1".to_string(),
+            operation_hash: "blake2b-512".to_string(),
+            root_commit_id: vec![0; 20],
+            root_change_id: vec![0; 16],
+            empty_tree_id: vec![0; 20],
+            root_operation_id: vec![0; 64],
+            capabilities: capabilities.iter().copied().collect(),
+            redirect: None,
+            git_remote: None,
+            op_schema: jj_compat::op_schema(),
+            snapshot_auto_track: None,
+        }
+    }
+
+    fn index(latest: &str) -> ReleaseIndex {
+        ReleaseIndex {
+            latest: latest.to_string(),
+            url: None,
+        }
+    }
+
+    #[test]
+    fn parses_versions() {
+        assert_eq!(parse_version("0.3.5"), Some((0, 3, 5)));
+        assert_eq!(parse_version("v1.2"), Some((1, 2, 0)));
+        assert_eq!(parse_version("0.4.0-rc.1"), Some((0, 4, 0)));
+        assert_eq!(parse_version("latest"), None);
+    }
+
+    #[test]
+    fn picks_the_side_to_upgrade() {
+        let all = RepoCapability::ALL;
+        assert_eq!(
+            assess("0.3.5", &info(Some("0.3.5"), all), None).upgrade,
+            Upgrade::None
+        );
+        assert_eq!(
+            assess("0.3.5", &info(Some("0.3.4"), all), None).upgrade,
+            Upgrade::Server
+        );
+        assert_eq!(
+            assess("0.3.5", &info(None, all), None).upgrade,
+            Upgrade::Server
+        );
+        assert_eq!(
+            assess("0.3.5", &info(Some("0.4.0"), all), None).upgrade,
+            Upgrade::Client
+        );
+        assert_eq!(
+            assess("0.3.5", &info(Some("0.3.5"), all), Some(&index("0.4.0"))).upgrade,
+            Upgrade::Both
+        );
+        assert_eq!(
+            assess("0.4.0", &info(Some("0.3.5"), all), Some(&index("0.4.0"))).upgrade,
+            Upgrade::Server
+        );
+    }
+
+    #[test]
+    fn lists_features_the_server_lacks() {
+        let report = assess(
+            "0.3.5",
+            &info(Some("0.3.5"), &[RepoCapability::WatchHeads]),
+            None,
+        );
+        assert!(report
+            .missing
+            .iter()
+            .any(|feature| feature.capability == "copyTracking"
+                && feature.description == "copy tracking"));
+        assert!(!report
+            .missing
+            .iter()
+            .any(|feature| feature.capability == "watchHeads"));
+    }
+}
@@ -2,20 +2,130 @@
 //!
 //! Connects via Cap'n Proto, calls watchHeads with a HeadWatcher callback,
 //! and prints each notification as: version=<N> heads=<hex1>,<hex2>,...
+//! followed by ` tags=<k>=<v>,...` when the new head operation carries tags.
+//! With `--notify`, head changes also raise desktop notifications.
+//!
+//! With `--since <N>`, the transitions recorded after version N are first
+//! replayed from the server's heads history, one line each with the workspace
+//! that caused it (` workspace=<name>`), before live notifications resume.
+//!
+//! With `--summary`, the server attaches a summary of each change, printed as
+//! indented `description:`, `author:`, `workspace:`, `bookmarks:` and
+//! `paths:` lines under the notification, plus an `overlaps <workspace>:` line
+//! for each workspace that has changed some of the same paths.
+//!
+//! When the server shuts down gracefully it says so first; the notice (with
+//! its retry hint) goes to stderr and the watch ends.
+//!
+//! If the connection drops without that notice (the server crashed, the
+//! network went away), the watch reconnects with backoff and re-registers
+//! with the last version it printed. Notifications the server replays for
+//! versions already printed are dropped, and a single `reconnected` line goes
+//! to stderr.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
 
 use anyhow::{Context, Result};
 use capnp::capability::Promise;
 use capnp_rpc::{rpc_twoparty_capnp, twoparty, RpcSystem};
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
-use crate::rpc::{connect_stream, RepoCapability, TandemClient};
+use crate::heads_summary::HeadsSummary;
+use crate::notify;
+use crate::ops::format_tags;
+use crate::rpc::{
+    connect_stream, do_get_heads_history, HeadsTransition, RepoCapability, TandemClient,
+};
 use crate::tandem_capnp::{head_watcher, store};
 
+/// First wait before reconnecting a dropped watch; doubles up to
+/// [`MAX_RECONNECT_DELAY`] while the server stays unreachable.
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
 // ─── HeadWatcher callback implementation ──────────────────────────────────────
 
+/// One head-change notification from `watchHeads`.
+pub struct HeadsEvent {
+    pub version: u64,
+    /// Head operation ids, hex-encoded.
+    pub heads: Vec<String>,
+    /// Tags on the new head operation.
+    pub tags: BTreeMap<String, String>,
+    /// What the change did, when the watch asked for summaries.
+    pub summary: Option<HeadsSummary>,
+}
+
+impl std::fmt::Display for HeadsEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "version={} heads={}", self.version, self.heads.join(","))?;
+        if !self.tags.is_empty() {
+            write!(f, " tags={}", format_tags(&self.tags))?;
+        }
+        if let Some(summary) = &self.summary {
+            write!(f, "\n  description: {}", summary.description.trim_end())?;
+            write!(f, "\n  author: {}", summary.author)?;
+            if let Some(workspace_id) = &summary.workspace_id {
+                write!(f, "\n  workspace: {workspace_id}")?;
+            }
+            if !summary.bookmarks.is_empty() {
+                write!(f, "\n  bookmarks: {}", summary.bookmarks.join(" "))?;
+            }
+            if !summary.paths.is_empty() {
+                write!(f, "\n  paths: {}", summary.paths.join(" "))?;
+                if summary.paths_truncated {
+                    write!(f, " ...")?;
+                }
+            }
+            for overlap in &summary.overlaps {
+                let own = summary.workspace_id.as_deref().unwrap_or_default();
+                let other = overlap.other(own).unwrap_or(&overlap.workspace_b);
+                write!(f, "\n  overlaps {other}: {}", overlap.paths.join(" "))?;
+                if overlap.paths_truncated {
+                    write!(f, " ...")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// What [`follow_heads`] reports, in order: replayed history, then the
+/// moment the watch is registered, then live events, and finally a shutdown
+/// notice if the server sends one.
+pub enum HeadsUpdate {
+    Replayed(HeadsTransition),
+    Subscribed,
+    Live(HeadsEvent),
+    /// The server is going away; reconnecting after `retry_after` should
+    /// reach it again. The watch ends after this.
+    ShuttingDown {
+        retry_after: Duration,
+        message: String,
+    },
+}
+
+/// Where [`follow_heads`] starts.
+#[derive(Debug, Clone, Copy)]
+pub enum WatchFrom {
+    /// The current heads, delivered as the first live event.
+    Now,
+    /// Replay the recorded transitions after this version first.
+    History(u64),
+    /// Only changes after this version, as when resuming a dropped watch.
+    After(u64),
+}
+
+impl WatchFrom {
+    pub fn since(since: Option<u64>) -> Self {
+        since.map_or(Self::Now, Self::History)
+    }
+}
+
 struct WatcherImpl {
-    /// Sender to push notification lines to the main loop for printing.
-    tx: tokio::sync::mpsc::UnboundedSender<String>,
+    /// Sender to push notifications to the main loop.
+    tx: tokio::sync::mpsc::UnboundedSender<HeadsUpdate>,
 }
 
 impl head_watcher::Server for WatcherImpl {
@@ -45,30 +155,206 @@ impl head_watcher::Server for WatcherImpl {
             }
         }
 
-        let line = format!("version={version} heads={}", hex_heads.join(","));
-        let _ = self.tx.send(line);
+        let tags_reader = match reader.get_tags() {
+            Ok(t) => t,
+            Err(e) => return Promise::err(e),
+        };
+        let mut tags = BTreeMap::new();
+        for tag in tags_reader.iter() {
+            match (tag.get_key(), tag.get_value()) {
+                (Ok(key), Ok(value)) => {
+                    tags.insert(
+                        key.to_string().unwrap_or_default(),
+                        value.to_string().unwrap_or_default(),
+                    );
+                }
+                (Err(e), _) | (_, Err(e)) => return Promise::err(e),
+            }
+        }
+
+        let summary = if reader.has_summary() {
+            match reader.get_summary().and_then(HeadsSummary::read) {
+                Ok(summary) => Some(summary),
+                Err(e) => return Promise::err(e),
+            }
+        } else {
+            None
+        };
+
+        let _ = self.tx.send(HeadsUpdate::Live(HeadsEvent {
+            version,
+            heads: hex_heads,
+            tags,
+            summary,
+        }));
+        Promise::ok(())
+    }
+
+    fn shutting_down(
+        &mut self,
+        params: head_watcher::ShuttingDownParams,
+        _results: head_watcher::ShuttingDownResults,
+    ) -> Promise<(), capnp::Error> {
+        let reader = match params.get() {
+            Ok(r) => r,
+            Err(e) => return Promise::err(e),
+        };
+        let message = match reader.get_message() {
+            Ok(text) => text.to_string().unwrap_or_default(),
+            Err(e) => return Promise::err(e),
+        };
+        let _ = self.tx.send(HeadsUpdate::ShuttingDown {
+            retry_after: Duration::from_millis(u64::from(reader.get_retry_after_ms())),
+            message,
+        });
         Promise::ok(())
     }
 }
 
 // ─── Public entry point ───────────────────────────────────────────────────────
 
-pub fn run_watch(server_addr: &str) -> Result<()> {
+fn format_transition(transition: &HeadsTransition) -> String {
+    let heads: Vec<String> = transition
+        .heads
+        .iter()
+        .map(|head| head.iter().map(|b| format!("{b:02x}")).collect())
+        .collect();
+    let mut line = format!("version={} heads={}", transition.version, heads.join(","));
+    if let Some(workspace_id) = &transition.workspace_id {
+        line.push_str(&format!(" workspace={workspace_id}"));
+    }
+    line
+}
+
+pub fn run_watch(
+    server_addr: &str,
+    notify: bool,
+    own_workspace: Option<&str>,
+    since: Option<u64>,
+    summary_paths: Option<u32>,
+) -> Result<()> {
     let rt = tokio::runtime::Builder::new_current_thread()
         .enable_all()
         .build()
         .unwrap();
     let local = tokio::task::LocalSet::new();
 
-    local.block_on(&rt, watch_loop(server_addr))
+    local.block_on(
+        &rt,
+        watch_loop(server_addr, notify, own_workspace, since, summary_paths),
+    )
 }
 
-async fn watch_loop(addr: &str) -> Result<()> {
+async fn watch_loop(
+    addr: &str,
+    notify: bool,
+    own_workspace: Option<&str>,
+    since: Option<u64>,
+    summary_paths: Option<u32>,
+) -> Result<()> {
     // Preflight compatibility + required capability before starting long-lived watch.
-    let preflight = TandemClient::connect_with_requirements(addr, &[RepoCapability::WatchHeads])
+    let mut required = vec![RepoCapability::WatchHeads];
+    if since.is_some() {
+        required.push(RepoCapability::HeadsHistory);
+    }
+    if summary_paths.is_some() {
+        required.push(RepoCapability::HeadsSummaries);
+    }
+    let preflight = TandemClient::connect_with_requirements(addr, &required)
         .with_context(|| format!("watch preflight failed for {addr}"))?;
+    // The notifier reuses the preflight client for its follow-up reads.
+    let notifier =
+        notify.then(|| notify::spawn_notifier(preflight.clone(), own_workspace.map(str::to_owned)));
     drop(preflight);
 
+    let mut from = WatchFrom::since(since);
+    // Highest version printed so far; replays at or below it are dropped.
+    let mut last_version: Option<u64> = None;
+    let mut subscriptions = 0u32;
+    let mut delay = RECONNECT_DELAY;
+    loop {
+        let mut subscribed = false;
+        let mut shutting_down = false;
+        let fresh = |version: u64, last_version: &mut Option<u64>| {
+            if matches!(*last_version, Some(last) if version <= last) {
+                return false;
+            }
+            *last_version = Some(version);
+            true
+        };
+        let result = follow_heads(addr, from, summary_paths, |update| match update {
+            HeadsUpdate::Replayed(transition) => {
+                if fresh(transition.version, &mut last_version) {
+                    println!("{}", format_transition(&transition));
+                }
+            }
+            HeadsUpdate::Subscribed => {
+                subscribed = true;
+                if subscriptions == 0 {
+                    eprintln!("watching heads on {addr}...");
+                } else {
+                    eprintln!("reconnected to {addr}");
+                }
+            }
+            HeadsUpdate::Live(event) => {
+                if fresh(event.version, &mut last_version) {
+                    println!("{event}");
+                    if let Some(notifier) = &notifier {
+                        notifier.heads_changed();
+                    }
+                }
+            }
+            HeadsUpdate::ShuttingDown { message, .. } => {
+                shutting_down = true;
+                eprintln!("{message}");
+            }
+        })
+        .await;
+
+        if shutting_down {
+            return Ok(());
+        }
+        if subscribed {
+            subscriptions += 1;
+            delay = RECONNECT_DELAY;
+        } else if subscriptions == 0 {
+            // Never got going: report it rather than retrying forever.
+            return result;
+        }
+        if let Err(err) = result {
+            tracing::debug!(error = %format!("{err:#}"), "watch connection lost");
+        }
+
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        from = WatchFrom::After(last_version.unwrap_or(0));
+        if let Some(notifier) = &notifier {
+            // The notifier's connection went down with the watch; it keeps
+            // its snapshot and reads through the new one.
+            match TandemClient::connect_with_requirements(addr, &required) {
+                Ok(client) => notifier.reconnected(client),
+                Err(err) => {
+                    tracing::debug!(error = %format!("{err:#}"), "watch reconnect failed");
+                    continue;
+                }
+            }
+        }
+    }
+}
+
+/// Subscribe to head changes on `addr` and hand each update to `on_update`
+/// until the server goes away or says it is shutting down. `from` picks the
+/// first updates: the current heads, a replay of recorded history, or only
+/// what came after a version already seen. With `summary_paths`, live events
+/// carry a summary listing at most that many touched paths (0 for the
+/// server's default). A dropped connection is an error. Must run inside a
+/// `LocalSet`.
+pub async fn follow_heads(
+    addr: &str,
+    from: WatchFrom,
+    summary_paths: Option<u32>,
+    mut on_update: impl FnMut(HeadsUpdate),
+) -> Result<()> {
     // Connect to server using the shared connector abstraction.
     let stream = connect_stream(addr)
         .await
@@ -79,50 +365,73 @@ async fn watch_loop(addr: &str) -> Result<()> {
         reader.compat(),
         writer.compat_write(),
         rpc_twoparty_capnp::Side::Client,
-        Default::default(),
+        crate::limits::reader_options(),
     );
     let mut rpc_system = RpcSystem::new(Box::new(network), None);
     let client: store::Client = rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
     let mut rpc_task = tokio::task::spawn_local(rpc_system);
 
     // Create notification channel
-    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<HeadsUpdate>();
 
     // Create HeadWatcher callback
     let watcher_impl = WatcherImpl { tx };
     let watcher_client: head_watcher::Client = capnp_rpc::new_client(watcher_impl);
 
-    // Call watchHeads(watcher, afterVersion=0) to get all notifications from the start
+    // Replay recorded transitions first, then only ask for what came after.
+    let mut after_version = match from {
+        WatchFrom::After(version) => version,
+        WatchFrom::Now | WatchFrom::History(_) => 0,
+    };
+    if let WatchFrom::History(since) = from {
+        let (transitions, oldest_version) = do_get_heads_history(&client, since)
+            .await
+            .context("read heads history")?;
+        if oldest_version > since.saturating_add(1) {
+            eprintln!(
+                "warning: heads history only reaches back to version {oldest_version}; earlier transitions were discarded"
+            );
+        }
+        after_version = since;
+        for transition in transitions {
+            after_version = transition.version;
+            on_update(HeadsUpdate::Replayed(transition));
+        }
+    }
+
+    // From now, afterVersion=0 delivers the current state right away.
     let mut request = client.watch_heads_request();
     {
         let mut params = request.get();
         params.set_watcher(watcher_client);
-        params.set_after_version(0);
+        params.set_after_version(after_version);
+        if let Some(limit) = summary_paths {
+            params.set_with_summary(true);
+            params.set_summary_path_limit(limit);
+        }
     }
     let _response = request.send().promise.await?;
 
-    eprintln!("watching heads on {addr}...");
+    on_update(HeadsUpdate::Subscribed);
 
-    // Print notifications until channel closes or RPC disconnects
+    // Deliver notifications until channel closes or RPC disconnects
     loop {
         tokio::select! {
-            line = rx.recv() => {
-                match line {
-                    Some(l) => println!("{l}"),
+            update = rx.recv() => {
+                match update {
+                    Some(update @ HeadsUpdate::ShuttingDown { .. }) => {
+                        on_update(update);
+                        break;
+                    }
+                    Some(update) => on_update(update),
                     None => break,
                 }
             }
             result = &mut rpc_task => {
                 match result {
                     Ok(Ok(())) => break,
-                    Ok(Err(e)) => {
-                        eprintln!("rpc error: {e}");
-                        break;
-                    }
-                    Err(e) => {
-                        eprintln!("rpc task panicked: {e}");
-                        break;
-                    }
+                    Ok(Err(e)) => return Err(anyhow::anyhow!("watch connection lost: {e}")),
+                    Err(e) => return Err(anyhow::anyhow!("rpc task panicked: {e}")),
                 }
             }
         }
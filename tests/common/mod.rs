@@ -31,6 +31,8 @@ pub fn isolated_home(tmp: &Path) -> PathBuf {
 pub fn isolate_env(cmd: &mut Command, home: &Path) {
     cmd.env("HOME", home);
     cmd.env("XDG_CONFIG_HOME", home.join(".config"));
+    // Keep default control sockets under TMPDIR, where tests look for them.
+    cmd.env_remove("XDG_RUNTIME_DIR");
     // Write a minimal jj config if not present
     let config_dir = home.join(".config").join("jj");
     if !config_dir.exists() {
@@ -45,20 +47,21 @@ pub fn isolate_env(cmd: &mut Command, home: &Path) {
 }
 
 pub fn spawn_server(repo: &Path, addr: &str) -> Child {
-    Command::new(tandem_bin())
-        .args([
-            "serve",
-            "--listen",
-            addr,
-            "--repo",
-            repo.to_str().unwrap(),
-            "--log-level",
-            "warn",
-        ])
-        .stdout(Stdio::null())
-        .stderr(Stdio::null())
-        .spawn()
-        .expect("spawn tandem serve")
+    let mut cmd = Command::new(tandem_bin());
+    new_process_group(&mut cmd);
+    cmd.args([
+        "serve",
+        "--listen",
+        addr,
+        "--repo",
+        repo.to_str().unwrap(),
+        "--log-level",
+        "warn",
+    ])
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .spawn()
+    .expect("spawn tandem serve")
 }
 
 pub fn wait_for_server(addr: &str, child: &mut Child) {
@@ -141,6 +144,7 @@ pub fn spawn_server_with_args_and_env(
     home: &Path,
 ) -> Child {
     let mut cmd = Command::new(tandem_bin());
+    new_process_group(&mut cmd);
     cmd.args(["serve", "--listen", addr, "--repo", repo.to_str().unwrap()]);
     let has_explicit_log_level = extra_args.iter().copied().any(|arg| arg == "--log-level");
     if !has_explicit_log_level {
@@ -164,14 +168,58 @@ pub fn control_socket_path(tmp: &Path) -> PathBuf {
     tmp.join("control.sock")
 }
 
-/// Wait for a Unix socket to appear on disk.
+/// Connect to a control socket: a Unix socket, or on Windows the named pipe
+/// whose name the socket file holds.
+#[cfg(unix)]
+pub fn connect_control(path: &Path) -> std::io::Result<std::os::unix::net::UnixStream> {
+    std::os::unix::net::UnixStream::connect(path)
+}
+
+#[cfg(windows)]
+pub fn connect_control(path: &Path) -> std::io::Result<std::fs::File> {
+    let pipe_name = std::fs::read_to_string(path)?;
+    std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(pipe_name.trim())
+}
+
+/// Ask a foreground server to shut down gracefully: SIGINT on Unix,
+/// Ctrl+Break on Windows (servers spawned here get their own process group).
 #[cfg(unix)]
+pub fn interrupt_server(server: &Child) {
+    unsafe {
+        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
+    }
+}
+
+#[cfg(windows)]
+pub fn interrupt_server(server: &Child) {
+    use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+    unsafe {
+        GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, server.id());
+    }
+}
+
+/// Give spawned servers their own process group so [`interrupt_server`] can
+/// target one without interrupting the test runner.
+#[cfg(windows)]
+fn new_process_group(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
+    cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(not(windows))]
+fn new_process_group(_cmd: &mut Command) {}
+
+/// Wait for a control socket to appear on disk and accept connections.
 pub fn wait_for_socket(path: &Path, timeout: Duration) {
     let deadline = Instant::now() + timeout;
     loop {
         if path.exists() {
             // Try connecting to verify it's listening
-            if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            if connect_control(path).is_ok() {
                 return;
             }
         }
@@ -201,11 +249,10 @@ pub fn wait_for_addr(addr: &str, timeout: Duration) {
 }
 
 /// Send a JSON request to the control socket and read the response line.
-#[cfg(unix)]
 pub fn control_request(socket_path: &Path, request: &str) -> String {
     use std::io::{BufRead, BufReader, Write};
-    let mut stream =
-        std::os::unix::net::UnixStream::connect(socket_path).expect("connect to control socket");
+    let mut stream = connect_control(socket_path).expect("connect to control socket");
+    #[cfg(unix)]
     stream.set_read_timeout(Some(Duration::from_secs(5))).ok();
     stream.write_all(request.as_bytes()).expect("write request");
     stream.write_all(b"\n").expect("write newline");
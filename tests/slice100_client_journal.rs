@@ -0,0 +1,131 @@
+//! Slice 100: client operation journal
+//!
+//! Acceptance criteria:
+//! - With `tandem.journal = true`, each command appends an entry to
+//!   `.jj/repo/tandem/journal.jsonl`: its arguments, RPCs, heads versions
+//!   seen, CAS retries, and exit code
+//! - A failed RPC is recorded with its error
+//! - `tandem journal show --last N` prints the newest entries; `--json`
+//!   prints them whole
+//! - Nothing is written while the journal is off
+
+mod common;
+
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice100_commands_are_journaled() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--control-socket", sock_str], &home);
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    let journal_file = ws.join(".jj/repo/tandem/journal.jsonl");
+
+    let log = common::run_tandem_in(&ws, &["log"], &home);
+    common::assert_ok(&log, "log with the journal off");
+    assert!(!journal_file.exists(), "journal should be off by default");
+    let show = common::run_tandem_in(&ws, &["journal", "show"], &home);
+    common::assert_ok(&show, "journal show with no entries");
+    assert!(
+        common::stdout_str(&show).contains("tandem.journal true"),
+        "{}",
+        common::stdout_str(&show)
+    );
+
+    let enable = common::run_tandem_in(
+        &ws,
+        &["config", "set", "--repo", "tandem.journal", "true"],
+        &home,
+    );
+    common::assert_ok(&enable, "enable the journal");
+    std::fs::write(ws.join("a.txt"), b"journaled\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "journaled"], &home);
+    common::assert_ok(&commit, "commit");
+
+    let show = |last: &str| -> Vec<serde_json::Value> {
+        let out = common::run_tandem_in(&ws, &["journal", "show", "--last", last, "--json"], &home);
+        common::assert_ok(&out, "journal show --json");
+        common::stdout_str(&out)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    };
+    let entries = show("1");
+    assert_eq!(entries.len(), 1, "{entries:?}");
+    let entry = &entries[0];
+    assert!(
+        entry["command"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|arg| arg == "commit"),
+        "{entry}"
+    );
+    assert_eq!(entry["exitCode"], 0, "{entry}");
+    assert_eq!(entry["server"], addr.as_str(), "{entry}");
+    let methods: Vec<&str> = entry["rpcs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|call| call["method"].as_str().unwrap())
+        .collect();
+    assert!(methods.contains(&"putObject"), "{methods:?}");
+    assert!(methods.contains(&"updateOpHeads"), "{methods:?}");
+    assert!(
+        !entry["versionsSeen"].as_array().unwrap().is_empty(),
+        "{entry}"
+    );
+
+    let faults = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "server",
+            "faults",
+            "--drop-update-op-heads-every",
+            "1",
+            "--control-socket",
+            sock_str,
+        ],
+        &home,
+    );
+    common::assert_ok(&faults, "server faults");
+    let dropped = common::run_tandem_in(&ws, &["describe", "-m", "dropped"], &home);
+    assert!(!dropped.status.success(), "heads update should be dropped");
+
+    let entries = show("10");
+    assert!(entries.len() >= 2, "{entries:?}");
+    let failed = entries.last().unwrap();
+    assert_ne!(failed["exitCode"], 0, "{failed}");
+    let error = failed["rpcs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|call| call["method"] == "updateOpHeads")
+        .and_then(|call| call["error"].as_str())
+        .unwrap_or_default();
+    assert!(error.contains("tandem fault injection"), "{failed}");
+
+    let human = common::run_tandem_in(&ws, &["journal", "show", "--last", "1"], &home);
+    common::assert_ok(&human, "journal show");
+    let out = common::stdout_str(&human);
+    assert!(out.contains("describe -m dropped"), "{out}");
+    assert!(out.contains("Failed:   updateOpHeads"), "{out}");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,67 @@
+//! Slice 101: built-in client profiling
+//!
+//! Acceptance criteria:
+//! - `tandem --tandem-profile <file> <jj command>` writes folded stacks
+//!   (`frame;frame;... <count>` per line) rooted at the command line
+//! - Time waiting on RPCs shows under `rpc wait;<method>`, and the rest of
+//!   the main thread's time under `jj-lib`
+//! - Without the flag no profile is taken
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice101_profile_writes_folded_stacks() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    std::fs::write(ws.join("a.txt"), b"profiled\n").unwrap();
+
+    let profile = tmp.path().join("commit.folded");
+    let commit = common::run_tandem_in(
+        &ws,
+        &[
+            "--tandem-profile",
+            profile.to_str().unwrap(),
+            "commit",
+            "-m",
+            "profiled",
+        ],
+        &home,
+    );
+    common::assert_ok(&commit, "profiled commit");
+    let stderr = common::stderr_str(&commit);
+    assert!(stderr.contains("tandem: profile written to"), "{stderr}");
+
+    let folded = std::fs::read_to_string(&profile).unwrap();
+    let mut stacks = Vec::new();
+    for line in folded.lines() {
+        let (stack, count) = line.rsplit_once(' ').unwrap();
+        assert!(count.parse::<u64>().unwrap() > 0, "{line}");
+        assert!(stack.starts_with("tandem commit -m profiled;"), "{line}");
+        stacks.push(stack.split(';').skip(1).collect::<Vec<_>>().join(";"));
+    }
+    assert!(stacks.iter().any(|s| s == "jj-lib"), "{folded}");
+    assert!(
+        stacks.iter().any(|s| s.starts_with("rpc wait;")),
+        "{folded}"
+    );
+
+    let log = common::run_tandem_in(&ws, &["log"], &home);
+    common::assert_ok(&log, "log without the flag");
+    assert!(!common::stderr_str(&log).contains("profile written"));
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
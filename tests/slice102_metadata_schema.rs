@@ -0,0 +1,84 @@
+//! Slice 102: versioned tandem metadata
+//!
+//! Acceptance criteria:
+//! - The server writes `heads.json` with a `schemaVersion`
+//! - A `heads.json` from before versioning is upgraded on startup, the
+//!   original kept as `heads.json.v0.bak`, and the repo keeps working
+//! - A `heads.json` from a newer tandem makes `tandem serve` refuse to start,
+//!   naming the versions and a fix, without touching the file
+
+mod common;
+
+use tempfile::TempDir;
+
+fn read_heads(path: &std::path::Path) -> serde_json::Value {
+    serde_json::from_slice(&std::fs::read(path).unwrap()).unwrap()
+}
+
+#[test]
+fn slice102_metadata_is_versioned_and_migrated() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+    let heads_path = server_repo.join(".jj/repo/tandem/heads.json");
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    std::fs::write(ws.join("a.txt"), b"before\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "before upgrade"], &home);
+    common::assert_ok(&commit, "commit");
+    common::interrupt_server(&server);
+    let _ = server.wait();
+
+    let mut heads = read_heads(&heads_path);
+    assert_eq!(heads["schemaVersion"], 1, "{heads}");
+    let version = heads["version"].as_u64().unwrap();
+
+    // As written before versioning.
+    heads.as_object_mut().unwrap().remove("schemaVersion");
+    let legacy = serde_json::to_vec_pretty(&heads).unwrap();
+    std::fs::write(&heads_path, &legacy).unwrap();
+
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let upgraded = read_heads(&heads_path);
+    assert_eq!(upgraded["schemaVersion"], 1, "{upgraded}");
+    assert_eq!(upgraded["version"], version, "{upgraded}");
+    assert_eq!(
+        std::fs::read(heads_path.with_file_name("heads.json.v0.bak")).unwrap(),
+        legacy
+    );
+    let log = common::run_tandem_in(&ws, &["log", "--no-graph", "-r", "@-"], &home);
+    common::assert_ok(&log, "log after the upgrade");
+    assert!(common::stdout_str(&log).contains("before upgrade"));
+    common::interrupt_server(&server);
+    let _ = server.wait();
+
+    let mut newer = read_heads(&heads_path);
+    newer["schemaVersion"] = 99.into();
+    let newer = serde_json::to_vec_pretty(&newer).unwrap();
+    std::fs::write(&heads_path, &newer).unwrap();
+    let out = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "serve",
+            "--listen",
+            &addr,
+            "--repo",
+            server_repo.to_str().unwrap(),
+        ],
+        &home,
+    );
+    assert!(!out.status.success(), "serve should refuse a newer format");
+    let stderr = common::stderr_str(&out);
+    assert!(stderr.contains("schema version 99"), "{stderr}");
+    assert!(stderr.contains("newer tandem"), "{stderr}");
+    assert!(stderr.contains("fix:"), "{stderr}");
+    assert_eq!(std::fs::read(&heads_path).unwrap(), newer);
+}
@@ -0,0 +1,133 @@
+//! Slice 103: read-only JSON API for dashboards
+//!
+//! Acceptance criteria:
+//! - `tandem serve --http-listen` serves `/api/workspaces` with each
+//!   workspace's working-copy commit, description, and last published head
+//! - `/api/heads` matches the heads version and workspace attribution
+//! - `/api/stats` counts workspaces and connections
+//! - Anything else is 404, and writes are refused
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+/// Send `method path` and return the status code and body.
+fn http(addr: &str, method: &str, path: &str) -> (u16, Vec<u8>) {
+    let mut stream = None;
+    for _ in 0..50 {
+        match TcpStream::connect(addr) {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(100)),
+        }
+    }
+    let mut stream = stream.expect("connect to api endpoint");
+    write!(stream, "{method} {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .expect("end of headers");
+    let head = String::from_utf8_lossy(&response[..split]).into_owned();
+    assert!(head.contains("Content-Type: application/json"), "{head}");
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .expect("status code");
+    (status, response[split + 4..].to_vec())
+}
+
+fn get_json(addr: &str, path: &str) -> serde_json::Value {
+    let (status, body) = http(addr, "GET", path);
+    assert_eq!(status, 200, "{path}: {}", String::from_utf8_lossy(&body));
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[test]
+fn slice103_api_lists_workspaces_heads_and_stats() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let api_addr = common::free_addr();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--http-listen", &api_addr], &home);
+    common::wait_for_server(&addr, &mut server);
+
+    for name in ["agent-a", "agent-b"] {
+        let workspace = tmp.path().join(name);
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &[
+                "init",
+                "--server",
+                &addr,
+                "--workspace",
+                name,
+                workspace.to_str().unwrap(),
+            ],
+            &home,
+        );
+        common::assert_ok(&init, "init");
+    }
+    let describe = common::run_tandem_in(
+        &tmp.path().join("agent-a"),
+        &["describe", "-m", "fix the parser\n\nlonger body"],
+        &home,
+    );
+    common::assert_ok(&describe, "describe");
+
+    let heads = get_json(&api_addr, "/api/heads");
+    let version = heads["version"].as_u64().unwrap();
+    assert!(version > 0, "{heads}");
+    assert!(!heads["heads"].as_array().unwrap().is_empty(), "{heads}");
+    let agent_a_op = heads["workspaceHeads"]["agent-a"].as_str().unwrap();
+
+    let listing = get_json(&api_addr, "/api/workspaces");
+    assert_eq!(listing["headsVersion"], version, "{listing}");
+    let workspaces = listing["workspaces"].as_array().unwrap();
+    let agent_a = workspaces
+        .iter()
+        .find(|w| w["name"] == "agent-a")
+        .unwrap_or_else(|| panic!("agent-a missing: {listing}"));
+    assert_eq!(agent_a["description"], "fix the parser", "{agent_a}");
+    assert_eq!(agent_a["operation"], agent_a_op, "{agent_a}");
+    assert_eq!(agent_a["commitId"].as_str().unwrap().len(), 40, "{agent_a}");
+    assert!(
+        !agent_a["changeId"].as_str().unwrap().is_empty(),
+        "{agent_a}"
+    );
+    assert!(
+        workspaces.iter().any(|w| w["name"] == "agent-b"),
+        "{listing}"
+    );
+
+    let stats = get_json(&api_addr, "/api/stats");
+    assert_eq!(stats["headsVersion"], version, "{stats}");
+    assert!(stats["workspaces"].as_u64().unwrap() >= 2, "{stats}");
+    assert!(
+        stats["connections"]["total"].as_u64().unwrap() >= 3,
+        "{stats}"
+    );
+    assert_eq!(stats["replica"], false, "{stats}");
+
+    let (status, body) = http(&api_addr, "GET", "/api/objects");
+    assert_eq!(status, 404);
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert!(error["error"].is_string(), "{error}");
+    let (status, _) = http(&api_addr, "POST", "/api/heads");
+    assert_eq!(status, 405);
+
+    common::interrupt_server(&server);
+    let _ = server.wait();
+}
@@ -0,0 +1,181 @@
+//! Slice 104: notification sinks
+//!
+//! Acceptance criteria:
+//! - `tandem serve --notifications <file>` posts events to a webhook sink as
+//!   JSON with the event name, repo, and a summary
+//! - A command sink gets the event on stdin with `TANDEM_EVENT` set
+//! - A sink only receives the events it lists
+//! - A malformed file stops the server from starting, naming the sink
+
+#![cfg(unix)]
+
+mod common;
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+/// Accept POSTs on a local port and pass their bodies on.
+fn webhook_receiver() -> (String, mpsc::Receiver<serde_json::Value>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hooks/tandem", listener.local_addr().unwrap());
+    let (tx, rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            assert!(
+                request_line.starts_with("POST /hooks/tandem "),
+                "{request_line}"
+            );
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some(value) = header.to_ascii_lowercase().strip_prefix("content-length:") {
+                    length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            let _ = tx.send(serde_json::from_slice(&body).unwrap());
+        }
+    });
+    (url, rx)
+}
+
+fn wait_for_file(path: &std::path::Path) -> String {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        if let Ok(text) = std::fs::read_to_string(path) {
+            if text.ends_with('\n') {
+                return text;
+            }
+        }
+        assert!(
+            Instant::now() < deadline,
+            "{} never written",
+            path.display()
+        );
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn slice104_events_reach_the_sinks_that_want_them() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let (url, webhook) = webhook_receiver();
+    let heads_log = tmp.path().join("heads-events");
+    let gc_log = tmp.path().join("gc-events");
+    let append = |path: &std::path::Path| {
+        serde_json::json!([
+            "sh",
+            "-c",
+            format!(
+                "printf '%s ' \"$TANDEM_EVENT\" >> '{}'; cat >> '{}'",
+                path.display(),
+                path.display()
+            ),
+        ])
+    };
+    let config = serde_json::json!({
+        "sinks": [
+            {"type": "webhook", "url": url, "events": ["headsUpdated"]},
+            {"type": "command", "command": append(&heads_log)},
+            {"type": "command", "command": append(&gc_log), "events": ["gc", "gcFailed"]},
+        ]
+    });
+    let config_path = tmp.path().join("notifications.json");
+    std::fs::write(&config_path, config.to_string()).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--notifications", config_path.to_str().unwrap()],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("agent");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent",
+            workspace.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+
+    let event = webhook.recv_timeout(Duration::from_secs(10)).unwrap();
+    assert_eq!(event["event"], "headsUpdated", "{event}");
+    assert_eq!(event["workspace"], "agent", "{event}");
+    assert!(event["version"].as_u64().unwrap() > 0, "{event}");
+    assert!(!event["heads"].as_array().unwrap().is_empty(), "{event}");
+    // The head operation's summary rides along.
+    assert!(event["operation"]["author"].is_string(), "{event}");
+    assert!(
+        event["repo"].as_str().unwrap().ends_with("server-repo"),
+        "{event}"
+    );
+    assert!(
+        event["summary"]
+            .as_str()
+            .unwrap()
+            .contains("agent moved the heads"),
+        "{event}"
+    );
+
+    let logged = wait_for_file(&heads_log);
+    let (name, json) = logged.lines().next().unwrap().split_once(' ').unwrap();
+    assert_eq!(name, "headsUpdated");
+    let json: serde_json::Value = serde_json::from_str(json).unwrap();
+    assert_eq!(json["event"], "headsUpdated", "{json}");
+    assert!(!gc_log.exists(), "the gc sink should get no head updates");
+
+    common::interrupt_server(&server);
+    let _ = server.wait();
+
+    std::fs::write(
+        &config_path,
+        r#"{"sinks": [{"type": "command", "command": []}]}"#,
+    )
+    .unwrap();
+    let out = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "serve",
+            "--listen",
+            &addr,
+            "--repo",
+            server_repo.to_str().unwrap(),
+            "--notifications",
+            config_path.to_str().unwrap(),
+        ],
+        &home,
+    );
+    assert!(!out.status.success());
+    let stderr = common::stderr_str(&out);
+    assert!(stderr.contains("sink 1"), "{stderr}");
+    assert!(stderr.contains("command must name a program"), "{stderr}");
+}
@@ -0,0 +1,146 @@
+//! Slice 105: CAS storm detection and backpressure
+//!
+//! Acceptance criteria:
+//! - Lost head-update races at `--cas-storm-threshold` per second start a
+//!   storm, announced to the notification sinks as `casStorm` with the
+//!   backoff floor handed to clients
+//! - Clients told to back off still land their update
+
+#![cfg(unix)]
+
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+/// `commit` in `ws`. When operations merged under contention leave the
+/// working copy stale, bring it up to date and commit again: staleness is
+/// jj's to report and not what this test is about.
+fn commit(ws: &Path, message: &str, env: &[(&str, &str)], home: &Path) -> Output {
+    let args = ["commit", "-m", message];
+    let output = common::run_tandem_in_with_env(ws, &args, env, home);
+    if output.status.success() || !common::stderr_str(&output).contains("working copy is stale") {
+        return output;
+    }
+    let update = common::run_tandem_in_with_env(ws, &["workspace", "update-stale"], env, home);
+    common::assert_ok(&update, "workspace update-stale");
+    common::run_tandem_in_with_env(ws, &args, env, home)
+}
+
+#[test]
+fn slice105_lost_races_start_a_storm_and_notify() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let events = tmp.path().join("storm-events");
+    let config = serde_json::json!({
+        "sinks": [{
+            "type": "command",
+            "command": ["sh", "-c", format!("cat >> '{}'", events.display())],
+            "events": ["casStorm"],
+        }]
+    });
+    let config_path = tmp.path().join("notifications.json");
+    std::fs::write(&config_path, config.to_string()).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &[
+            "--cas-storm-threshold",
+            "1",
+            "--notifications",
+            config_path.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let names = ["agent-a", "agent-b"];
+    for name in names {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &[
+                "init",
+                "--server",
+                &addr,
+                "--workspace",
+                name,
+                tmp.path().join(name).to_str().unwrap(),
+            ],
+            &home,
+        );
+        common::assert_ok(&init, "init");
+    }
+
+    // agent-b's RPCs are slowed down, so agent-a keeps moving the heads
+    // between agent-b reading them and publishing its commit.
+    let slow = tmp.path().join("agent-b");
+    std::fs::write(slow.join("agent-b.txt"), b"x\n").unwrap();
+    let slow_home = home.clone();
+    let slow_commit = std::thread::spawn(move || {
+        commit(
+            &slow,
+            "agent-b commit",
+            &[("TANDEM_BENCH_INJECT_RTT_MS", "150")],
+            &slow_home,
+        )
+    });
+    let fast = tmp.path().join("agent-a");
+    let mut i = 0;
+    while !slow_commit.is_finished() && i < 50 {
+        std::fs::write(fast.join(format!("agent-a-{i}.txt")), b"x\n").unwrap();
+        common::assert_ok(
+            &commit(&fast, "agent-a commit", &[], &home),
+            "agent-a commit",
+        );
+        i += 1;
+    }
+    common::assert_ok(&slow_commit.join().unwrap(), "agent-b commit");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    let event: serde_json::Value = loop {
+        if let Ok(text) = std::fs::read_to_string(&events) {
+            if let Some(line) = text.lines().next() {
+                break serde_json::from_str(line).unwrap();
+            }
+        }
+        assert!(Instant::now() < deadline, "no casStorm notification");
+        std::thread::sleep(Duration::from_millis(50));
+    };
+    assert_eq!(event["event"], "casStorm", "{event}");
+    assert!(event["conflictsPerSec"].as_u64().unwrap() >= 1, "{event}");
+    assert!(event["minBackoffMs"].as_u64().unwrap() >= 100, "{event}");
+    assert!(
+        event["summary"].as_str().unwrap().contains("CAS storm"),
+        "{event}"
+    );
+
+    let log = common::run_tandem_in(
+        &tmp.path().join("agent-a"),
+        &[
+            "log",
+            "--ignore-working-copy",
+            "--no-graph",
+            "-r",
+            "description(substring:\"commit\")",
+            "-T",
+            "description",
+        ],
+        &home,
+    );
+    common::assert_ok(&log, "log");
+    let stdout = common::stdout_str(&log);
+    for name in names {
+        assert!(stdout.contains(&format!("{name} commit")), "{stdout}");
+    }
+
+    common::interrupt_server(&server);
+    let _ = server.wait();
+}
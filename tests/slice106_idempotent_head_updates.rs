@@ -0,0 +1,114 @@
+//! Slice 106: idempotency keys for head updates
+//!
+//! Acceptance criteria:
+//! - The server advertises `idempotentHeadUpdates`
+//! - A head update whose reply is lost after it was applied is resent under
+//!   the same idempotency key and succeeds
+//! - The resend is not applied a second time: the heads version moves by the
+//!   same amount as for an update whose reply arrived
+
+mod common;
+
+use std::time::Duration;
+
+use tandem_client::{RepoCapability, TandemClient};
+use tempfile::TempDir;
+
+fn heads_version(rt: &tokio::runtime::Runtime, addr: &str) -> u64 {
+    rt.block_on(async {
+        let client = TandemClient::connect(addr).await.expect("connect");
+        client.get_heads().await.expect("get heads").version
+    })
+}
+
+#[test]
+fn slice106_lost_replies_are_resent_without_a_second_bump() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--control-socket", sock_str], &home);
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let client = TandemClient::connect(&addr).await.expect("connect");
+        let info = client.repo_info();
+        assert!(
+            info.capabilities
+                .contains(&RepoCapability::IdempotentHeadUpdates),
+            "{:?}",
+            info.capabilities
+        );
+    });
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+
+    let before = heads_version(&rt, &addr);
+    let describe = common::run_tandem_in(&ws, &["describe", "-m", "first"], &home);
+    common::assert_ok(&describe, "describe without faults");
+    let expected_bump = heads_version(&rt, &addr) - before;
+    assert!(expected_bump > 0);
+
+    let faults = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "server",
+            "faults",
+            "--lose-update-op-heads-reply-every",
+            "1",
+            "--control-socket",
+            sock_str,
+        ],
+        &home,
+    );
+    common::assert_ok(&faults, "server faults");
+    let stdout = common::stdout_str(&faults);
+    assert!(
+        stdout.contains("losing the reply to every 1st updateOpHeads"),
+        "{stdout}"
+    );
+
+    // Every reply is lost, so the client keeps resending until it gives up;
+    // each resend must come back as the update already applied.
+    let before = heads_version(&rt, &addr);
+    let lost = common::run_tandem_in(&ws, &["describe", "-m", "second"], &home);
+    assert!(!lost.status.success(), "every reply was lost");
+    assert_eq!(heads_version(&rt, &addr) - before, expected_bump);
+
+    let faults = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "server",
+            "faults",
+            "--lose-update-op-heads-reply-every",
+            "2",
+            "--control-socket",
+            sock_str,
+        ],
+        &home,
+    );
+    common::assert_ok(&faults, "server faults");
+
+    // With every other reply lost, some of these lose their first reply and
+    // get the stored result back from one resend.
+    let before = heads_version(&rt, &addr);
+    for i in 0..4 {
+        let message = format!("lossy {i}");
+        let describe = common::run_tandem_in(&ws, &["describe", "-m", &message], &home);
+        common::assert_ok(&describe, "describe with lost replies");
+    }
+    assert_eq!(heads_version(&rt, &addr) - before, 4 * expected_bump);
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
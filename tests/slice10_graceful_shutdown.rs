@@ -24,12 +24,7 @@ fn slice10_sigint_clean_exit() {
     common::wait_for_server(&addr, &mut server);
 
     // Send SIGINT
-    #[cfg(unix)]
-    {
-        unsafe {
-            libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-        }
-    }
+    common::interrupt_server(&server);
 
     // Wait for exit (with timeout)
     let start = std::time::Instant::now();
@@ -103,18 +98,10 @@ fn slice10_double_sigint_immediate_exit() {
     let mut server = common::spawn_server_with_args(&server_repo, &addr, &[], &home);
     common::wait_for_server(&addr, &mut server);
 
-    #[cfg(unix)]
-    {
-        let pid = server.id() as libc::pid_t;
-        unsafe {
-            libc::kill(pid, libc::SIGINT);
-        }
-        // Small delay then second signal
-        std::thread::sleep(Duration::from_millis(100));
-        unsafe {
-            libc::kill(pid, libc::SIGINT);
-        }
-    }
+    common::interrupt_server(&server);
+    // Small delay then second signal
+    std::thread::sleep(Duration::from_millis(100));
+    common::interrupt_server(&server);
 
     // Should exit quickly (within 2s)
     let start = std::time::Instant::now();
@@ -146,12 +133,7 @@ fn slice10_log_level_flag_accepted() {
 
     // Server started successfully with --log-level debug
     // Send SIGINT to stop it
-    #[cfg(unix)]
-    {
-        unsafe {
-            libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-        }
-    }
+    common::interrupt_server(&server);
 
     let output = server.wait_with_output().expect("wait_with_output");
     assert!(
@@ -174,12 +156,7 @@ fn slice10_log_format_flag_accepted() {
         common::spawn_server_with_args(&server_repo, &addr, &["--log-format", "json"], &home);
     common::wait_for_server(&addr, &mut server);
 
-    #[cfg(unix)]
-    {
-        unsafe {
-            libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-        }
-    }
+    common::interrupt_server(&server);
 
     let output = server.wait_with_output().expect("wait_with_output");
     assert!(
@@ -52,10 +52,7 @@ fn slice11_status_json_while_running() {
     assert!(parsed["version"].is_string(), "should have version string");
 
     // Cleanup
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 }
 
@@ -91,10 +88,7 @@ fn slice11_status_human_while_running() {
     assert!(out.contains("PID"), "should show PID\noutput: {out}");
 
     // Cleanup
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 }
 
@@ -150,10 +144,7 @@ fn slice11_socket_cleaned_up_on_exit() {
     assert!(sock.exists(), "control socket should exist while running");
 
     // Send SIGINT
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
 
     let _ = server.wait();
 
@@ -199,9 +190,6 @@ fn slice11_status_reports_correct_info() {
     );
 
     // Cleanup
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 }
@@ -100,10 +100,7 @@ fn slice13_logs_json_streams_events() {
     }
 
     // Cleanup
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 }
 
@@ -134,10 +131,7 @@ fn slice13_logs_exits_on_shutdown() {
     std::thread::sleep(Duration::from_millis(500));
 
     // Shut down server
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 
     // logs process should exit within a few seconds
@@ -239,9 +233,6 @@ fn slice13_logs_level_filtering() {
     );
 
     // Cleanup
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 }
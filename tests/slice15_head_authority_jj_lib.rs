@@ -15,12 +15,10 @@ use std::time::{Duration, Instant};
 
 use capnp_rpc::{rpc_twoparty_capnp, twoparty, RpcSystem};
 use jj_lib::object_id::ObjectId as _;
+use tandem_jj_store::tandem_capnp;
 use tempfile::TempDir;
 use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
 
-#[path = "../src/tandem_capnp.rs"]
-mod tandem_capnp;
-
 fn to_hex(bytes: &[u8]) -> String {
     bytes.iter().map(|b| format!("{b:02x}")).collect()
 }
@@ -92,7 +90,9 @@ fn server_jj_op_heads(server_repo: &Path) -> BTreeSet<String> {
     let settings = jj_lib::settings::UserSettings::from_config(config).expect("create jj settings");
     let repo_dir =
         dunce::canonicalize(server_repo.join(".jj/repo")).expect("canonicalize .jj/repo");
-    let factories = jj_lib::repo::StoreFactories::default();
+    // The server's sharded op store is registered with the tandem stores.
+    let mut factories = jj_lib::repo::StoreFactories::default();
+    tandem_jj_store::register(&mut factories);
     let loader = jj_lib::repo::RepoLoader::init_from_file_system(&settings, &repo_dir, &factories)
         .expect("load repo loader");
 
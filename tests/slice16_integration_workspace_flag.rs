@@ -137,10 +137,7 @@ fn slice16_flag_off_no_integration_bookmark() {
         serde_json::from_str(common::stdout_str(&status).trim()).unwrap();
     assert_eq!(parsed["integration"]["enabled"], false);
 
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 }
 
@@ -203,10 +200,7 @@ fn slice16_flag_on_creates_integration_bookmark_and_status() {
         "expected integration.lastStatus in status JSON"
     );
 
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 }
 
@@ -54,6 +54,66 @@ fn slice18_backend_and_op_store_mismatch_fails_fast() {
     );
 }
 
+#[test]
+fn slice18_hash_algorithm_mismatch_fails_fast() {
+    assert_init_fails_with_env(
+        &[("TANDEM_TEST_REPO_INFO_COMMIT_HASH", "sha3-256")],
+        "commit_hash",
+    );
+    assert_init_fails_with_env(
+        &[("TANDEM_TEST_REPO_INFO_OPERATION_HASH", "blake3")],
+        "operation_hash",
+    );
+    // A known hash that disagrees with the advertised id length.
+    assert_init_fails_with_env(
+        &[("TANDEM_TEST_REPO_INFO_COMMIT_HASH", "sha256")],
+        "commit_id_length",
+    );
+}
+
+#[test]
+fn slice18_op_schema_mismatch_names_jj_versions_and_fields() {
+    let schema = "Operation=1,2,3,4,5;OperationMetadata=1,2,3,4,5,6,7;\
+                  View=1,2,3,5,6,7,8,9,11,12,13;RemoteView=1,2,3";
+    assert_init_fails_with_env(
+        &[
+            ("TANDEM_TEST_REPO_INFO_JJ_VERSION", "9.9.9"),
+            ("TANDEM_TEST_REPO_INFO_OP_SCHEMA", schema),
+        ],
+        "server was built against jj 9.9.9",
+    );
+    assert_init_fails_with_env(
+        &[("TANDEM_TEST_REPO_INFO_OP_SCHEMA", schema)],
+        "View field 13 is unknown to this tandem",
+    );
+}
+
+#[test]
+fn slice18_jj_version_drift_alone_is_accepted() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let workspace = tmp.path().join("workspace");
+    std::fs::create_dir_all(&workspace).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args_and_env(
+        &server_repo,
+        &addr,
+        &[],
+        &[("TANDEM_TEST_REPO_INFO_JJ_VERSION", "9.9.9")],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(&workspace, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init against a server on another jj version");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
 #[test]
 fn slice18_missing_watch_capability_is_gated() {
     let tmp = TempDir::new().unwrap();
@@ -373,10 +373,7 @@ fn slice21_log_stream_contains_contention_observability_fields() {
     let _ = logs_child.kill();
     let _ = logs_child.wait();
 
-    #[cfg(unix)]
-    unsafe {
-        libc::kill(server.id() as libc::pid_t, libc::SIGINT);
-    }
+    common::interrupt_server(&server);
     let _ = server.wait();
 
     let stdout = std::fs::read_to_string(&logs_path).expect("read logs output");
@@ -0,0 +1,100 @@
+//! Slice 22: Operation tags
+//!
+//! Acceptance criteria:
+//! - Operations written with `TANDEM_OP_TAGS` are tagged on the server
+//! - `tandem ops --tag key=value` lists only matching operations
+//! - `tandem ops --tag key` matches any value for that key
+//! - `tandem ops --json` emits id + tags objects
+//! - Operations written without tags are not listed
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice22_ops_lists_tagged_operations() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let workspace = tmp.path().join("workspace");
+    std::fs::create_dir_all(&workspace).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(&workspace, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "tandem init");
+
+    let untagged = common::run_tandem_in(&workspace, &["ops", "--server", &addr], &home);
+    common::assert_ok(&untagged, "tandem ops before tagging");
+    // Every operation carries its workspace attribution, but no user tags yet.
+    let untagged_out = common::stdout_str(&untagged);
+    assert!(
+        untagged_out
+            .lines()
+            .all(|line| !line.contains("ci=") && !line.contains("agent=")),
+        "no operations should carry user tags yet:\n{untagged_out}"
+    );
+
+    std::fs::write(workspace.join("ci.txt"), b"from ci\n").unwrap();
+    let tagged = common::run_tandem_in_with_env(
+        &workspace,
+        &["new", "-m", "tagged change"],
+        &[("TANDEM_OP_TAGS", "ci=1234,agent=alice")],
+        &home,
+    );
+    common::assert_ok(&tagged, "tandem new with TANDEM_OP_TAGS");
+
+    let by_value = common::run_tandem_in(
+        &workspace,
+        &["ops", "--server", &addr, "--tag", "ci=1234"],
+        &home,
+    );
+    common::assert_ok(&by_value, "tandem ops --tag ci=1234");
+    let stdout = common::stdout_str(&by_value);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(!lines.is_empty(), "expected tagged operations");
+    for line in &lines {
+        assert!(
+            line.contains("agent=alice") && line.contains("ci=1234"),
+            "tagged operation should list all tags: {line}"
+        );
+    }
+
+    let by_key = common::run_tandem_in(
+        &workspace,
+        &["ops", "--server", &addr, "--tag", "agent"],
+        &home,
+    );
+    common::assert_ok(&by_key, "tandem ops --tag agent");
+    assert_eq!(common::stdout_str(&by_key), stdout);
+
+    let mismatch = common::run_tandem_in(
+        &workspace,
+        &["ops", "--server", &addr, "--tag", "ci=9999"],
+        &home,
+    );
+    common::assert_ok(&mismatch, "tandem ops --tag ci=9999");
+    assert!(common::stdout_str(&mismatch).trim().is_empty());
+
+    let json = common::run_tandem_in(
+        &workspace,
+        &["ops", "--server", &addr, "--tag", "ci=1234", "--json"],
+        &home,
+    );
+    common::assert_ok(&json, "tandem ops --json");
+    let parsed: serde_json::Value =
+        serde_json::from_str(&common::stdout_str(&json)).expect("ops --json output is JSON");
+    let entries = parsed.as_array().expect("ops --json returns an array");
+    assert_eq!(entries.len(), lines.len());
+    for entry in entries {
+        assert_eq!(entry["tags"]["ci"], "1234");
+        assert_eq!(entry["tags"]["agent"], "alice");
+        assert_eq!(entry["id"].as_str().unwrap().len(), 128);
+    }
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
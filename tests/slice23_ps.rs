@@ -0,0 +1,87 @@
+//! Slice 23: tandem ps
+//!
+//! Acceptance criteria:
+//! - `tandem ps` before any workspace publishes reports no workspaces
+//! - After two workspaces describe changes, `tandem ps` lists one row per
+//!   workspace with its current change description
+//! - Without an integration bookmark, ahead/behind columns show `-`
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice23_ps_lists_workspace_activity() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws_a_dir = tmp.path().join("agent-a");
+    std::fs::create_dir_all(&ws_a_dir).unwrap();
+    let ws_b_dir = tmp.path().join("agent-b");
+    std::fs::create_dir_all(&ws_b_dir).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let empty = common::run_tandem_in(tmp.path(), &["ps", "--server", &addr], &home);
+    common::assert_ok(&empty, "tandem ps with no workspaces");
+    assert!(
+        common::stdout_str(&empty).contains("no workspaces"),
+        "expected empty-state message:\n{}",
+        common::stdout_str(&empty)
+    );
+
+    let init_a = common::run_tandem_in(
+        &ws_a_dir,
+        &["init", "--server", &addr, "--workspace", "agent-a", "."],
+        &home,
+    );
+    common::assert_ok(&init_a, "workspace A init");
+    std::fs::write(ws_a_dir.join("auth.rs"), b"pub fn login() {}\n").unwrap();
+    let describe_a = common::run_tandem_in(&ws_a_dir, &["describe", "-m", "A adds auth"], &home);
+    common::assert_ok(&describe_a, "workspace A describe");
+
+    let init_b = common::run_tandem_in(
+        &ws_b_dir,
+        &["init", "--server", &addr, "--workspace", "agent-b", "."],
+        &home,
+    );
+    common::assert_ok(&init_b, "workspace B init");
+    std::fs::write(ws_b_dir.join("docs.md"), b"# docs\n").unwrap();
+    let describe_b = common::run_tandem_in(&ws_b_dir, &["describe", "-m", "B writes docs"], &home);
+    common::assert_ok(&describe_b, "workspace B describe");
+
+    let ps = common::run_tandem_in(tmp.path(), &["ps", "--server", &addr], &home);
+    common::assert_ok(&ps, "tandem ps");
+    let stdout = common::stdout_str(&ps);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(
+        lines.first().is_some_and(|l| l.starts_with("WORKSPACE")),
+        "expected table header:\n{stdout}"
+    );
+
+    let row_a = lines
+        .iter()
+        .find(|l| l.starts_with("agent-a"))
+        .unwrap_or_else(|| panic!("missing agent-a row:\n{stdout}"));
+    assert!(row_a.contains("A adds auth"), "agent-a row: {row_a}");
+    assert!(
+        row_a.contains("ago"),
+        "agent-a row should show age: {row_a}"
+    );
+    assert!(
+        row_a.contains(" - "),
+        "agent-a ahead/behind should be '-': {row_a}"
+    );
+
+    let row_b = lines
+        .iter()
+        .find(|l| l.starts_with("agent-b"))
+        .unwrap_or_else(|| panic!("missing agent-b row:\n{stdout}"));
+    assert!(row_b.contains("B writes docs"), "agent-b row: {row_b}");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
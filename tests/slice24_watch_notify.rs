@@ -0,0 +1,125 @@
+//! Slice 24: tandem watch --notify
+//!
+//! Acceptance criteria:
+//! - Changes landed by another workspace raise a notification naming it
+//! - Moving a bookmark raises a notification naming the bookmark
+//! - Changes from the watcher's own workspace (`--workspace`) are skipped
+//! - History replayed by the catch-up notification is not re-announced
+//! - A landed change is announced once, not once per operation
+
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn wait_for_notification(log: &Path, needle: &str) -> String {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let text = std::fs::read_to_string(log).unwrap_or_default();
+        if text.contains(needle) {
+            return text;
+        }
+        if Instant::now() > deadline {
+            panic!("timed out waiting for notification {needle:?}; log:\n{text}");
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn slice24_watch_notify_reports_other_workspaces_and_bookmarks() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws_a_dir = tmp.path().join("agent-a");
+    std::fs::create_dir_all(&ws_a_dir).unwrap();
+    let ws_b_dir = tmp.path().join("agent-b");
+    std::fs::create_dir_all(&ws_b_dir).unwrap();
+    let notify_log = tmp.path().join("notifications.log");
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init_a = common::run_tandem_in(
+        &ws_a_dir,
+        &["init", "--server", &addr, "--workspace", "agent-a", "."],
+        &home,
+    );
+    common::assert_ok(&init_a, "workspace A init");
+    std::fs::write(ws_a_dir.join("before.txt"), b"before watch\n").unwrap();
+    let before = common::run_tandem_in(&ws_a_dir, &["new", "-m", "before watch"], &home);
+    common::assert_ok(&before, "workspace A commit before watch");
+
+    let init_b = common::run_tandem_in(
+        &ws_b_dir,
+        &["init", "--server", &addr, "--workspace", "agent-b", "."],
+        &home,
+    );
+    common::assert_ok(&init_b, "workspace B init");
+
+    let mut watch_proc = Command::new(common::tandem_bin())
+        .args([
+            "watch",
+            "--server",
+            &addr,
+            "--notify",
+            "--workspace",
+            "agent-b",
+        ])
+        .env("HOME", &home)
+        .env("TANDEM_TEST_NOTIFY_LOG", &notify_log)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn tandem watch --notify");
+
+    std::thread::sleep(Duration::from_millis(1000));
+
+    // Own workspace: must not notify.
+    std::fs::write(ws_b_dir.join("own.txt"), b"own change\n").unwrap();
+    let own = common::run_tandem_in(&ws_b_dir, &["new", "-m", "B own change"], &home);
+    common::assert_ok(&own, "workspace B commit");
+
+    // Other workspace: must notify with the landed description.
+    std::fs::write(ws_a_dir.join("auth.rs"), b"pub fn login() {}\n").unwrap();
+    let landed = common::run_tandem_in(&ws_a_dir, &["new", "-m", "A adds auth"], &home);
+    common::assert_ok(&landed, "workspace A commit");
+    let log = wait_for_notification(&notify_log, "A adds auth");
+    assert!(
+        log.contains("agent-a landed a change"),
+        "expected workspace name in notification:\n{log}"
+    );
+
+    let bookmark = common::run_tandem_in(
+        &ws_a_dir,
+        &["bookmark", "create", "feature-auth", "-r", "@-"],
+        &home,
+    );
+    common::assert_ok(&bookmark, "bookmark create");
+    let log = wait_for_notification(&notify_log, "bookmark feature-auth moved");
+
+    let _ = watch_proc.kill();
+    let _ = watch_proc.wait();
+
+    assert!(
+        !log.contains("agent-b"),
+        "own workspace changes must not notify:\n{log}"
+    );
+    assert!(
+        !log.contains("before watch"),
+        "catch-up history must not notify:\n{log}"
+    );
+    assert_eq!(
+        log.matches("agent-a landed a change").count(),
+        1,
+        "each landed change is announced once:\n{log}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
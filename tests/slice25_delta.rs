@@ -0,0 +1,78 @@
+//! Slice 25: tandem delta
+//!
+//! Acceptance criteria:
+//! - `tandem delta <workspace>` diffs @ against that workspace's working copy
+//! - `--summary` lists added and deleted paths relative to the other workspace
+//! - `tandem delta` without an integration bookmark fails with a hint
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice25_delta_against_workspace_and_integration() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws_a_dir = tmp.path().join("agent-a");
+    std::fs::create_dir_all(&ws_a_dir).unwrap();
+    let ws_b_dir = tmp.path().join("agent-b");
+    std::fs::create_dir_all(&ws_b_dir).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init_a = common::run_tandem_in(
+        &ws_a_dir,
+        &["init", "--server", &addr, "--workspace", "agent-a", "."],
+        &home,
+    );
+    common::assert_ok(&init_a, "workspace A init");
+    std::fs::write(ws_a_dir.join("auth.rs"), b"pub fn login() {}\n").unwrap();
+    let describe_a = common::run_tandem_in(&ws_a_dir, &["describe", "-m", "A adds auth"], &home);
+    common::assert_ok(&describe_a, "workspace A describe");
+
+    let init_b = common::run_tandem_in(
+        &ws_b_dir,
+        &["init", "--server", &addr, "--workspace", "agent-b", "."],
+        &home,
+    );
+    common::assert_ok(&init_b, "workspace B init");
+    std::fs::write(ws_b_dir.join("docs.md"), b"# docs\n").unwrap();
+
+    let summary = common::run_tandem_in(&ws_b_dir, &["delta", "agent-a", "--summary"], &home);
+    common::assert_ok(&summary, "tandem delta agent-a --summary");
+    let stdout = common::stdout_str(&summary);
+    assert!(
+        stdout.lines().any(|l| l.trim() == "A docs.md"),
+        "B's new file should show as added:\n{stdout}"
+    );
+    assert!(
+        stdout.lines().any(|l| l.trim() == "D auth.rs"),
+        "A's file should show as missing from B:\n{stdout}"
+    );
+
+    let full = common::run_tandem_in(&ws_b_dir, &["delta", "agent-a"], &home);
+    common::assert_ok(&full, "tandem delta agent-a");
+    assert!(
+        common::stdout_str(&full).contains("# docs"),
+        "full diff should include file bytes:\n{}",
+        common::stdout_str(&full)
+    );
+
+    let integration = common::run_tandem_in(&ws_b_dir, &["delta"], &home);
+    assert!(
+        !integration.status.success(),
+        "delta without integration bookmark should fail"
+    );
+    assert!(
+        common::stderr_str(&integration).contains("--enable-integration-workspace"),
+        "expected integration hint:\n{}",
+        common::stderr_str(&integration)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,180 @@
+//! Slice 26: bookmark auto-advance policies
+//!
+//! Acceptance criteria:
+//! - `--auto-advance main` moves `main` to each clean integration commit
+//! - A failing `--auto-advance-check` leaves `main` untouched and is reported
+//!   in `tandem server status`
+//! - `--auto-advance` without `--enable-integration-workspace` is rejected
+
+mod common;
+
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn resolve_commit(workspace_dir: &Path, rev: &str, home: &Path) -> Option<String> {
+    let out = common::run_tandem_in(
+        workspace_dir,
+        &["log", "-r", rev, "--no-graph", "-T", "commit_id ++ \"\\n\""],
+        home,
+    );
+    if !out.status.success() {
+        return None;
+    }
+    let commit = common::stdout_str(&out).trim().to_string();
+    (!commit.is_empty()).then_some(commit)
+}
+
+fn write_single_commit(workspace_dir: &Path, home: &Path) {
+    std::fs::write(workspace_dir.join("advance.txt"), b"auto-advance test\n").unwrap();
+    let describe = common::run_tandem_in(
+        workspace_dir,
+        &["describe", "-m", "auto-advance commit"],
+        home,
+    );
+    common::assert_ok(&describe, "describe for auto-advance test");
+    let new_out = common::run_tandem_in(workspace_dir, &["new"], home);
+    common::assert_ok(&new_out, "new for auto-advance test");
+}
+
+fn server_status(tmp: &Path, sock: &str, home: &Path) -> serde_json::Value {
+    let status = common::run_tandem_in(
+        tmp,
+        &["server", "status", "--json", "--control-socket", sock],
+        home,
+    );
+    common::assert_ok(&status, "server status --json");
+    serde_json::from_str(common::stdout_str(&status).trim()).unwrap()
+}
+
+#[test]
+fn slice26_main_follows_clean_integration() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--enable-integration-workspace", "--auto-advance", "main"],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    write_single_commit(&ws, &home);
+
+    let deadline = Instant::now() + Duration::from_secs(15);
+    loop {
+        let integration = resolve_commit(&ws, "integration", &home);
+        let main = resolve_commit(&ws, "main", &home);
+        if integration.is_some() && integration == main {
+            break;
+        }
+        if Instant::now() > deadline {
+            panic!("main did not follow integration: integration={integration:?} main={main:?}");
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+
+    let show = common::run_tandem_in(&ws, &["file", "show", "-r", "main", "advance.txt"], &home);
+    common::assert_ok(&show, "file show at main");
+    assert_eq!(show.stdout, b"auto-advance test\n");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice26_failing_check_blocks_advance() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &[
+            "--control-socket",
+            sock_str,
+            "--enable-integration-workspace",
+            "--auto-advance",
+            "main",
+            "--auto-advance-check",
+            "exit 3",
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    write_single_commit(&ws, &home);
+
+    let deadline = Instant::now() + Duration::from_secs(15);
+    let outcome = loop {
+        let status = server_status(tmp.path(), sock_str, &home);
+        if let Some(outcome) = status["integration"]["lastAutoAdvance"].as_str() {
+            break outcome.to_string();
+        }
+        if Instant::now() > deadline {
+            panic!("auto-advance outcome never recorded: {status}");
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+    assert!(
+        outcome.starts_with("check failed"),
+        "unexpected outcome: {outcome}"
+    );
+    assert!(
+        resolve_commit(&ws, "main", &home).is_none(),
+        "main must not exist when the check fails"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice26_auto_advance_requires_integration_mode() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let output = Command::new(common::tandem_bin())
+        .args([
+            "serve",
+            "--listen",
+            &addr,
+            "--repo",
+            server_repo.to_str().unwrap(),
+            "--auto-advance",
+            "main",
+        ])
+        .env("HOME", &home)
+        .output()
+        .expect("run tandem serve");
+    assert!(!output.status.success());
+    assert!(
+        common::stderr_str(&output).contains("--enable-integration-workspace"),
+        "stderr:\n{}",
+        common::stderr_str(&output)
+    );
+}
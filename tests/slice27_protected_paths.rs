@@ -0,0 +1,92 @@
+//! Slice 27: protected-path ownership rules
+//!
+//! Acceptance criteria:
+//! - A workspace outside the owning group cannot publish changes to a
+//!   protected path; the error lists the offending path
+//! - The same workspace can still change unprotected paths
+//! - A workspace in the owning group can change the protected path, and the
+//!   bytes round-trip
+
+mod common;
+
+use tempfile::TempDir;
+
+const RULES: &str = "\
+@infra = ops-agent
+infra/ @infra
+";
+
+#[test]
+fn slice27_protected_paths_reject_non_owners() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let rules_path = tmp.path().join("protected-paths");
+    std::fs::write(&rules_path, RULES).unwrap();
+    let agent_dir = tmp.path().join("agent-a");
+    std::fs::create_dir_all(&agent_dir).unwrap();
+    let ops_dir = tmp.path().join("ops-agent");
+    std::fs::create_dir_all(&ops_dir).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--protected-paths", rules_path.to_str().unwrap()],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let init_agent = common::run_tandem_in(
+        &agent_dir,
+        &["init", "--server", &addr, "--workspace", "agent-a", "."],
+        &home,
+    );
+    common::assert_ok(&init_agent, "agent-a init");
+    let init_ops = common::run_tandem_in(
+        &ops_dir,
+        &["init", "--server", &addr, "--workspace", "ops-agent", "."],
+        &home,
+    );
+    common::assert_ok(&init_ops, "ops-agent init");
+
+    // agent-a may not touch infra/.
+    std::fs::create_dir_all(agent_dir.join("infra")).unwrap();
+    std::fs::write(agent_dir.join("infra/main.tf"), b"resource \"x\" {}\n").unwrap();
+    let rejected = common::run_tandem_in(&agent_dir, &["new", "-m", "touch infra"], &home);
+    assert!(
+        !rejected.status.success(),
+        "agent-a infra change should be rejected\nstdout:\n{}\nstderr:\n{}",
+        common::stdout_str(&rejected),
+        common::stderr_str(&rejected)
+    );
+    let stderr = common::stderr_str(&rejected);
+    assert!(
+        stderr.contains("infra/main.tf") && stderr.contains("ops-agent"),
+        "error should list path and owners:\n{stderr}"
+    );
+    std::fs::remove_dir_all(agent_dir.join("infra")).unwrap();
+
+    // agent-a can still change unprotected paths.
+    std::fs::write(agent_dir.join("README.md"), b"# agent a\n").unwrap();
+    let allowed = common::run_tandem_in(&agent_dir, &["new", "-m", "docs"], &home);
+    common::assert_ok(&allowed, "agent-a unprotected change");
+
+    // ops-agent owns infra/.
+    std::fs::create_dir_all(ops_dir.join("infra")).unwrap();
+    std::fs::write(ops_dir.join("infra/main.tf"), b"resource \"ok\" {}\n").unwrap();
+    let owned = common::run_tandem_in(&ops_dir, &["new", "-m", "ops infra"], &home);
+    common::assert_ok(&owned, "ops-agent protected change");
+
+    let show = common::run_tandem_in(
+        &ops_dir,
+        &["file", "show", "-r", "@-", "infra/main.tf"],
+        &home,
+    );
+    common::assert_ok(&show, "file show infra/main.tf");
+    assert_eq!(show.stdout, b"resource \"ok\" {}\n");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,131 @@
+//! Slice 28: tandem undo-remote
+//!
+//! Acceptance criteria:
+//! - `tandem undo-remote <op>` restores the server's repo state to that
+//!   operation, hiding commits pushed after it
+//! - The rollback is recorded as a new operation in the op log
+//! - An unknown operation id is rejected with an error
+
+mod common;
+
+use std::path::Path;
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+fn latest_op(workspace_dir: &Path, template: &str, home: &Path) -> String {
+    let out = common::run_tandem_in(
+        workspace_dir,
+        &[
+            "op",
+            "log",
+            "--no-graph",
+            "--limit",
+            "1",
+            "--ignore-working-copy",
+            "-T",
+            template,
+        ],
+        home,
+    );
+    common::assert_ok(&out, "op log");
+    common::stdout_str(&out).trim().to_string()
+}
+
+#[test]
+fn slice28_undo_remote_restores_prior_operation() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--control-socket", sock_str], &home);
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+
+    std::fs::write(ws.join("good.txt"), b"good\n").unwrap();
+    let good = common::run_tandem_in(&ws, &["new", "-m", "good change"], &home);
+    common::assert_ok(&good, "good commit");
+    let good_op = latest_op(&ws, "id.short(16) ++ \"\\n\"", &home);
+
+    std::fs::write(ws.join("bad.txt"), b"catastrophe\n").unwrap();
+    let bad = common::run_tandem_in(&ws, &["new", "-m", "bad change"], &home);
+    common::assert_ok(&bad, "bad commit");
+
+    let undo = common::run_tandem_in(
+        tmp.path(),
+        &["undo-remote", &good_op, "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&undo, "undo-remote");
+    assert!(
+        common::stdout_str(&undo).contains("restored operation"),
+        "stdout:\n{}",
+        common::stdout_str(&undo)
+    );
+
+    let bad_log = common::run_tandem_in(
+        &ws,
+        &[
+            "log",
+            "-r",
+            "description(substring:\"bad change\")",
+            "--no-graph",
+            "--ignore-working-copy",
+            "-T",
+            "commit_id ++ \"\\n\"",
+        ],
+        &home,
+    );
+    common::assert_ok(&bad_log, "log for bad change");
+    assert!(
+        common::stdout_str(&bad_log).trim().is_empty(),
+        "bad change should be gone after undo-remote:\n{}",
+        common::stdout_str(&bad_log)
+    );
+
+    let good_show = common::run_tandem_in(
+        &ws,
+        &[
+            "file",
+            "show",
+            "-r",
+            "@-",
+            "good.txt",
+            "--ignore-working-copy",
+        ],
+        &home,
+    );
+    common::assert_ok(&good_show, "good change survives");
+    assert_eq!(good_show.stdout, b"good\n");
+
+    let description = latest_op(&ws, "description ++ \"\\n\"", &home);
+    assert!(
+        description.contains("undo-remote"),
+        "rollback should be recorded as an operation: {description}"
+    );
+
+    let unknown = common::run_tandem_in(
+        tmp.path(),
+        &["undo-remote", "ffffffffffff", "--control-socket", sock_str],
+        &home,
+    );
+    assert!(!unknown.status.success(), "unknown operation should fail");
+    assert!(
+        common::stderr_str(&unknown).contains("no operation matches"),
+        "stderr:\n{}",
+        common::stderr_str(&unknown)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,124 @@
+//! Slice 29: read replicas
+//!
+//! Acceptance criteria:
+//! - `tandem serve --follow <primary>` mirrors operations and objects written
+//!   to the primary
+//! - A workspace pointed at the replica (TANDEM_SERVER) can read log and file
+//!   contents of those commits
+//! - Writes against the replica are rejected as read-only
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+#[test]
+fn slice29_replica_mirrors_primary_and_rejects_writes() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let primary_repo = tmp.path().join("primary-repo");
+    std::fs::create_dir_all(&primary_repo).unwrap();
+    let replica_repo = tmp.path().join("replica-repo");
+    std::fs::create_dir_all(&replica_repo).unwrap();
+    let ws = tmp.path().join("agent-a");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let primary_addr = common::free_addr();
+    let mut primary = common::spawn_server(&primary_repo, &primary_addr);
+    common::wait_for_server(&primary_addr, &mut primary);
+
+    let replica_addr = common::free_addr();
+    let mut replica = common::spawn_server_with_args(
+        &replica_repo,
+        &replica_addr,
+        &["--follow", &primary_addr],
+        &home,
+    );
+    common::wait_for_server(&replica_addr, &mut replica);
+
+    let init = common::run_tandem_in(
+        &ws,
+        &[
+            "init",
+            "--server",
+            &primary_addr,
+            "--workspace",
+            "agent-a",
+            ".",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init against primary");
+    std::fs::write(ws.join("replicated.txt"), b"from the primary\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "replicated change"], &home);
+    common::assert_ok(&commit, "commit on primary");
+
+    let via_replica = [("TANDEM_SERVER", replica_addr.as_str())];
+    let deadline = Instant::now() + Duration::from_secs(15);
+    let commit_id = loop {
+        let log = common::run_tandem_in_with_env(
+            &ws,
+            &[
+                "log",
+                "-r",
+                "description(substring:\"replicated change\")",
+                "--no-graph",
+                "--ignore-working-copy",
+                "-T",
+                "commit_id ++ \"\\n\"",
+            ],
+            &via_replica,
+            &home,
+        );
+        let stdout = common::stdout_str(&log);
+        if log.status.success() && !stdout.trim().is_empty() {
+            break stdout.trim().to_string();
+        }
+        if Instant::now() > deadline {
+            panic!(
+                "replica never caught up\nstdout:\n{stdout}\nstderr:\n{}",
+                common::stderr_str(&log)
+            );
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    };
+
+    let show = common::run_tandem_in_with_env(
+        &ws,
+        &[
+            "file",
+            "show",
+            "-r",
+            &commit_id,
+            "replicated.txt",
+            "--ignore-working-copy",
+        ],
+        &via_replica,
+        &home,
+    );
+    common::assert_ok(&show, "file show via replica");
+    assert_eq!(show.stdout, b"from the primary\n");
+
+    let reader_dir = tmp.path().join("reader");
+    std::fs::create_dir_all(&reader_dir).unwrap();
+    let write = common::run_tandem_in(
+        &reader_dir,
+        &["init", "--server", &replica_addr, "."],
+        &home,
+    );
+    assert!(
+        !write.status.success(),
+        "init against a replica should fail"
+    );
+    assert!(
+        common::stderr_str(&write).contains("read-only replica"),
+        "stderr:\n{}",
+        common::stderr_str(&write)
+    );
+
+    let _ = replica.kill();
+    let _ = replica.wait();
+    let _ = primary.kill();
+    let _ = primary.wait();
+}
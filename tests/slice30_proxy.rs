@@ -0,0 +1,103 @@
+//! Slice 30: multiplexing proxy
+//!
+//! Acceptance criteria:
+//! - `tandem proxy --listen <addr> --upstream <server>` accepts agent
+//!   connections exactly like `tandem serve`
+//! - Workspaces initialized through the proxy can write, and other
+//!   workspaces behind the same proxy see those writes
+
+mod common;
+
+use std::process::{Command, Stdio};
+
+use tempfile::TempDir;
+
+#[test]
+fn slice30_proxy_forwards_reads_and_writes() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws_a = tmp.path().join("agent-a");
+    std::fs::create_dir_all(&ws_a).unwrap();
+    let ws_b = tmp.path().join("agent-b");
+    std::fs::create_dir_all(&ws_b).unwrap();
+
+    let server_addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &server_addr);
+    common::wait_for_server(&server_addr, &mut server);
+
+    let proxy_addr = common::free_addr();
+    let mut cmd = Command::new(common::tandem_bin());
+    cmd.args([
+        "proxy",
+        "--listen",
+        &proxy_addr,
+        "--upstream",
+        &server_addr,
+        "--upstream-connections",
+        "2",
+        "--log-level",
+        "warn",
+    ]);
+    common::isolate_env(&mut cmd, &home);
+    let mut proxy = cmd
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn tandem proxy");
+    common::wait_for_server(&proxy_addr, &mut proxy);
+
+    let init_a = common::run_tandem_in(
+        &ws_a,
+        &[
+            "init",
+            "--server",
+            &proxy_addr,
+            "--workspace",
+            "agent-a",
+            ".",
+        ],
+        &home,
+    );
+    common::assert_ok(&init_a, "init agent-a via proxy");
+    std::fs::write(ws_a.join("shared.txt"), b"through the proxy\n").unwrap();
+    let commit = common::run_tandem_in(&ws_a, &["new", "-m", "via proxy"], &home);
+    common::assert_ok(&commit, "commit via proxy");
+
+    let init_b = common::run_tandem_in(
+        &ws_b,
+        &[
+            "init",
+            "--server",
+            &proxy_addr,
+            "--workspace",
+            "agent-b",
+            ".",
+        ],
+        &home,
+    );
+    common::assert_ok(&init_b, "init agent-b via proxy");
+
+    // Read twice so the second read is answered from the proxy cache.
+    for attempt in 0..2 {
+        let show = common::run_tandem_in(
+            &ws_b,
+            &[
+                "file",
+                "show",
+                "-r",
+                "description(substring:\"via proxy\")",
+                "shared.txt",
+            ],
+            &home,
+        );
+        common::assert_ok(&show, &format!("file show via proxy (attempt {attempt})"));
+        assert_eq!(show.stdout, b"through the proxy\n");
+    }
+
+    let _ = proxy.kill();
+    let _ = proxy.wait();
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,99 @@
+//! Slice 31: ephemeral workspaces
+//!
+//! Acceptance criteria:
+//! - `tandem init --ephemeral --ttl <ttl>` registers a workspace the server
+//!   forgets once the TTL passes without activity
+//! - `tandem workspace release` forgets the current workspace immediately
+//! - Regular workspaces are never expired
+
+mod common;
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn workspace_names(dir: &Path, home: &Path) -> Vec<String> {
+    let out = common::run_tandem_in(dir, &["workspace", "list", "--ignore-working-copy"], home);
+    common::assert_ok(&out, "workspace list");
+    common::stdout_str(&out)
+        .lines()
+        .filter_map(|line| {
+            line.split_once(':')
+                .map(|(name, _)| name.trim().to_string())
+        })
+        .collect()
+}
+
+fn init_workspace(dir: &Path, addr: &str, name: &str, extra: &[&str], home: &Path) {
+    std::fs::create_dir_all(dir).unwrap();
+    let mut args = vec!["init", "--server", addr, "--workspace", name];
+    args.extend_from_slice(extra);
+    args.push(".");
+    let out = common::run_tandem_in(dir, &args, home);
+    common::assert_ok(&out, &format!("init {name}"));
+}
+
+#[test]
+fn slice31_ephemeral_workspaces_expire_or_release() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let keeper = tmp.path().join("keeper");
+    init_workspace(&keeper, &addr, "keeper", &[], &home);
+    let released = tmp.path().join("ci-released");
+    init_workspace(
+        &released,
+        &addr,
+        "ci-released",
+        &["--ephemeral", "--ttl", "1h"],
+        &home,
+    );
+    let expiring = tmp.path().join("ci-expiring");
+    init_workspace(
+        &expiring,
+        &addr,
+        "ci-expiring",
+        &["--ephemeral", "--ttl", "2s"],
+        &home,
+    );
+
+    let names = workspace_names(&keeper, &home);
+    assert!(
+        names.contains(&"ci-released".to_string()),
+        "workspaces: {names:?}"
+    );
+
+    let release = common::run_tandem_in(&released, &["workspace", "release"], &home);
+    common::assert_ok(&release, "workspace release");
+    let names = workspace_names(&keeper, &home);
+    assert!(
+        !names.contains(&"ci-released".to_string()),
+        "released workspace should be gone: {names:?}"
+    );
+
+    let deadline = Instant::now() + Duration::from_secs(15);
+    loop {
+        let names = workspace_names(&keeper, &home);
+        if !names.contains(&"ci-expiring".to_string()) {
+            assert!(
+                names.contains(&"keeper".to_string()),
+                "regular workspace must survive: {names:?}"
+            );
+            break;
+        }
+        if Instant::now() > deadline {
+            panic!("ephemeral workspace never expired: {names:?}");
+        }
+        std::thread::sleep(Duration::from_millis(250));
+    }
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
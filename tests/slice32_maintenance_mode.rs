@@ -0,0 +1,110 @@
+//! Slice 32: maintenance mode
+//!
+//! Acceptance criteria:
+//! - `tandem server maintenance --for <d> --message <m>` makes new writes fail
+//!   with "tandem server is in maintenance until <ts>: <m>", shown verbatim
+//! - Reads keep working during maintenance
+//! - `tandem server maintenance --off` re-enables writes
+
+mod common;
+
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice32_maintenance_rejects_writes_with_notice() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--control-socket", sock_str], &home);
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    std::fs::write(ws.join("before.txt"), b"before\n").unwrap();
+    let before = common::run_tandem_in(&ws, &["new", "-m", "before maintenance"], &home);
+    common::assert_ok(&before, "commit before maintenance");
+
+    let enter = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "server",
+            "maintenance",
+            "--for",
+            "10m",
+            "--message",
+            "disk migration in progress",
+            "--control-socket",
+            sock_str,
+        ],
+        &home,
+    );
+    common::assert_ok(&enter, "enter maintenance");
+
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "server status");
+    assert!(
+        common::stdout_str(&status).contains("disk migration in progress"),
+        "status should show the notice:\n{}",
+        common::stdout_str(&status)
+    );
+
+    std::fs::write(ws.join("during.txt"), b"during\n").unwrap();
+    let during = common::run_tandem_in(&ws, &["new", "-m", "during maintenance"], &home);
+    assert!(!during.status.success(), "write should fail in maintenance");
+    let stderr = common::stderr_str(&during);
+    assert!(
+        stderr.contains("tandem server is in maintenance until")
+            && stderr.contains("disk migration in progress"),
+        "stderr should carry the notice verbatim:\n{stderr}"
+    );
+
+    let read = common::run_tandem_in(
+        &ws,
+        &[
+            "log",
+            "-r",
+            "description(substring:\"before maintenance\")",
+            "--no-graph",
+            "--ignore-working-copy",
+            "-T",
+            "description",
+        ],
+        &home,
+    );
+    common::assert_ok(&read, "read during maintenance");
+    assert!(common::stdout_str(&read).contains("before maintenance"));
+
+    let exit = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "server",
+            "maintenance",
+            "--off",
+            "--control-socket",
+            sock_str,
+        ],
+        &home,
+    );
+    common::assert_ok(&exit, "leave maintenance");
+
+    let after = common::run_tandem_in(&ws, &["new", "-m", "after maintenance"], &home);
+    common::assert_ok(&after, "commit after maintenance");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
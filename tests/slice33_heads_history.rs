@@ -0,0 +1,123 @@
+//! Slice 33: heads history journal
+//!
+//! Acceptance criteria:
+//! - Every heads-version bump is journaled with its op heads, the workspace
+//!   that caused it, and a timestamp
+//! - `tandem watch --since <N>` replays each transition after N, in order,
+//!   tagged with ` workspace=<name>`, before following live changes
+//! - The journal survives a server restart
+
+mod common;
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+/// Run `tandem watch --since <since>` briefly and return its `version=` lines.
+fn replay_since(addr: &str, since: u64) -> Vec<String> {
+    let mut watch_proc = Command::new(common::tandem_bin())
+        .args(["watch", "--server", addr, "--since", &since.to_string()])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn tandem watch");
+    std::thread::sleep(Duration::from_millis(1000));
+    let _ = watch_proc.kill();
+    let output = watch_proc
+        .wait_with_output()
+        .expect("wait for watch process");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    eprintln!(
+        "watch --since {since} stdout:\n{stdout}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    stdout
+        .lines()
+        .filter(|l| l.starts_with("version="))
+        .map(str::to_string)
+        .collect()
+}
+
+fn version_of(line: &str) -> u64 {
+    line.strip_prefix("version=")
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| panic!("no version in {line}"))
+}
+
+fn commit(dir: &Path, file: &str, message: &str, home: &Path) {
+    std::fs::write(dir.join(file), format!("{message}\n")).unwrap();
+    let out = common::run_tandem_in(dir, &["new", "-m", message], home);
+    common::assert_ok(&out, message);
+}
+
+#[test]
+fn slice33_watch_since_replays_heads_history() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    for name in ["agent-a", "agent-b"] {
+        let dir = tmp.path().join(name);
+        std::fs::create_dir_all(&dir).unwrap();
+        let init = common::run_tandem_in(
+            &dir,
+            &["init", "--server", &addr, "--workspace", name, "."],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+    commit(&tmp.path().join("agent-a"), "a.txt", "from agent a", &home);
+    commit(&tmp.path().join("agent-b"), "b.txt", "from agent b", &home);
+
+    let lines = replay_since(&addr, 0);
+    assert!(lines.len() >= 2, "expected replayed transitions: {lines:?}");
+    let versions: Vec<u64> = lines.iter().map(|l| version_of(l)).collect();
+    for window in versions.windows(2) {
+        assert!(
+            window[1] > window[0],
+            "replay should be in version order: {versions:?}"
+        );
+    }
+    for name in ["agent-a", "agent-b"] {
+        assert!(
+            lines
+                .iter()
+                .any(|l| l.ends_with(&format!(" workspace={name}"))),
+            "history should attribute a transition to {name}: {lines:?}"
+        );
+    }
+
+    // Only transitions after the requested version come back.
+    let last = *versions.last().unwrap();
+    let tail = replay_since(&addr, last - 1);
+    assert_eq!(
+        tail.first().map(|l| version_of(l)),
+        Some(last),
+        "replay after {} should start at {last}: {tail:?}",
+        last - 1
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let after_restart = replay_since(&addr, 0);
+    assert!(
+        after_restart
+            .iter()
+            .any(|l| l.ends_with(" workspace=agent-b")),
+        "history should survive a restart: {after_restart:?}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
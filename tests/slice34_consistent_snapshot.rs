@@ -0,0 +1,102 @@
+//! Slice 34: consistent snapshots for backups
+//!
+//! Acceptance criteria:
+//! - `tandem snapshot -- <cmd>` holds the server's heads while `<cmd>` runs and
+//!   passes the held version as `TANDEM_SNAPSHOT_VERSION`
+//! - Head updates issued during the hold wait and land after it is released
+//! - The backup command's exit status is passed through
+
+mod common;
+
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+#[test]
+fn slice34_snapshot_holds_head_updates_until_released() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+
+    let held = tmp.path().join("held-version");
+    let script = format!(
+        "echo \"$TANDEM_SNAPSHOT_VERSION\" > '{}'; sleep 3",
+        held.display()
+    );
+    let mut snapshot_cmd = Command::new(common::tandem_bin());
+    snapshot_cmd
+        .args([
+            "snapshot", "--server", &addr, "--lease", "1m", "--", "sh", "-c",
+        ])
+        .arg(&script)
+        .current_dir(tmp.path())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    common::isolate_env(&mut snapshot_cmd, &home);
+    let snapshot = snapshot_cmd.spawn().expect("spawn tandem snapshot");
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !held.exists() {
+        assert!(Instant::now() < deadline, "backup command never started");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    std::thread::sleep(Duration::from_millis(100));
+    let version = std::fs::read_to_string(&held).unwrap().trim().to_string();
+    assert!(
+        version.parse::<u64>().is_ok(),
+        "TANDEM_SNAPSHOT_VERSION should be a version: {version:?}"
+    );
+
+    std::fs::write(ws.join("during.txt"), b"during\n").unwrap();
+    let mut commit_cmd = Command::new(common::tandem_bin());
+    commit_cmd
+        .args(["new", "-m", "during snapshot"])
+        .current_dir(&ws)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    common::isolate_env(&mut commit_cmd, &home);
+    let mut commit = commit_cmd.spawn().expect("spawn tandem new");
+
+    std::thread::sleep(Duration::from_secs(1));
+    assert!(
+        commit.try_wait().unwrap().is_none(),
+        "head update should wait for the snapshot hold"
+    );
+
+    let snapshot = snapshot.wait_with_output().expect("wait for snapshot");
+    common::assert_ok(&snapshot, "tandem snapshot");
+    assert!(
+        common::stderr_str(&snapshot).contains(&format!("holding heads at version {version}")),
+        "snapshot should report the held version:\n{}",
+        common::stderr_str(&snapshot)
+    );
+
+    let commit = commit.wait_with_output().expect("wait for tandem new");
+    common::assert_ok(&commit, "commit after snapshot release");
+
+    let failing = common::run_tandem_in(
+        tmp.path(),
+        &["snapshot", "--server", &addr, "--", "sh", "-c", "exit 3"],
+        &home,
+    );
+    assert_eq!(
+        failing.status.code(),
+        Some(3),
+        "backup exit status should pass through:\n{}",
+        common::stderr_str(&failing)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
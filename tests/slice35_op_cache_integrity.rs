@@ -0,0 +1,95 @@
+//! Slice 35: verified op store cache
+//!
+//! Acceptance criteria:
+//! - Operations fetched from the server are cached under
+//!   `.jj/repo/op_store/cache/operations/<id>`
+//! - A cached entry whose content hash does not match its id is evicted and
+//!   re-fetched instead of being trusted
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice35_tampered_cache_entries_are_refetched() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    std::fs::write(ws.join("a.txt"), b"a\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "cached"], &home);
+    common::assert_ok(&commit, "commit");
+    let op_log = common::run_tandem_in(
+        &ws,
+        &[
+            "op",
+            "log",
+            "--no-graph",
+            "--ignore-working-copy",
+            "-T",
+            "id ++ \"\\n\"",
+        ],
+        &home,
+    );
+    common::assert_ok(&op_log, "op log (populate cache)");
+
+    // Every cached operation is one the op log reads back: init's own
+    // workspace-add operation is never published, so it is evicted too.
+    let logged = common::stdout_str(&op_log);
+    let cache_dir = ws.join(".jj/repo/op_store/cache/operations");
+    let cached: Vec<_> = std::fs::read_dir(&cache_dir)
+        .unwrap_or_else(|e| panic!("read {}: {e}", cache_dir.display()))
+        .map(|entry| entry.unwrap().path())
+        .collect();
+    assert!(!cached.is_empty(), "operations should be cached");
+    for path in &cached {
+        let id = path.file_name().unwrap().to_str().unwrap();
+        assert!(
+            logged.lines().any(|line| line.trim() == id),
+            "cached operation {id} is not in the op log:\n{logged}"
+        );
+    }
+    for path in &cached {
+        std::fs::write(path, b"tampered").unwrap();
+    }
+
+    let op_log_again = common::run_tandem_in(
+        &ws,
+        &[
+            "op",
+            "log",
+            "--no-graph",
+            "--ignore-working-copy",
+            "-T",
+            "id ++ \"\\n\"",
+        ],
+        &home,
+    );
+    common::assert_ok(&op_log_again, "op log with tampered cache");
+    assert_eq!(
+        common::stdout_str(&op_log_again),
+        common::stdout_str(&op_log),
+        "op log should be unaffected by tampered cache entries"
+    );
+    for path in &cached {
+        let contents = std::fs::read(path).unwrap_or_default();
+        assert_ne!(
+            contents,
+            b"tampered",
+            "{} should have been evicted and re-fetched",
+            path.display()
+        );
+    }
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
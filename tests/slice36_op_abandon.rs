@@ -0,0 +1,97 @@
+//! Slice 36: `jj op abandon` across workspaces
+//!
+//! Acceptance criteria:
+//! - `tandem op abandon <op>` in one workspace removes the operation from the
+//!   op log every other workspace sees
+//! - The rewritten head replaces the old one instead of becoming a second head
+//! - Other workspaces' recorded heads do not bring the abandoned history back
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn op_ids(ws: &Path, home: &Path) -> Vec<String> {
+    let out = common::run_tandem_in(
+        ws,
+        &[
+            "op",
+            "log",
+            "--no-graph",
+            "--ignore-working-copy",
+            "-T",
+            "id ++ \"\\n\"",
+        ],
+        home,
+    );
+    common::assert_ok(&out, "op log");
+    common::stdout_str(&out)
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn slice36_op_abandon_is_visible_to_other_workspaces() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws_a = tmp.path().join("ws-a");
+    let ws_b = tmp.path().join("ws-b");
+    std::fs::create_dir_all(&ws_a).unwrap();
+    std::fs::create_dir_all(&ws_b).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init_a = common::run_tandem_in(
+        &ws_a,
+        &["init", "--server", &addr, "--workspace", "agent-a", "."],
+        &home,
+    );
+    common::assert_ok(&init_a, "init agent-a");
+    let init_b = common::run_tandem_in(
+        &ws_b,
+        &["init", "--server", &addr, "--workspace", "agent-b", "."],
+        &home,
+    );
+    common::assert_ok(&init_b, "init agent-b");
+
+    std::fs::write(ws_a.join("a1.txt"), b"a1\n").unwrap();
+    let a1 = common::run_tandem_in(&ws_a, &["new", "-m", "a1"], &home);
+    common::assert_ok(&a1, "agent-a first commit");
+    let abandoned = op_ids(&ws_a, &home)[0].clone();
+
+    // agent-b records a head that descends from the operation to be abandoned.
+    std::fs::write(ws_b.join("b1.txt"), b"b1\n").unwrap();
+    let b1 = common::run_tandem_in(&ws_b, &["new", "-m", "b1"], &home);
+    common::assert_ok(&b1, "agent-b commit");
+
+    std::fs::write(ws_a.join("a2.txt"), b"a2\n").unwrap();
+    let a2 = common::run_tandem_in(&ws_a, &["new", "-m", "a2"], &home);
+    common::assert_ok(&a2, "agent-a second commit");
+
+    let abandon = common::run_tandem_in(&ws_a, &["op", "abandon", &abandoned], &home);
+    common::assert_ok(&abandon, "op abandon");
+
+    let seen_by_a = op_ids(&ws_a, &home);
+    let seen_by_b = op_ids(&ws_b, &home);
+    assert!(
+        !seen_by_a.contains(&abandoned),
+        "agent-a should no longer see {abandoned}: {seen_by_a:?}"
+    );
+    assert!(
+        !seen_by_b.contains(&abandoned),
+        "agent-b should no longer see {abandoned}: {seen_by_b:?}"
+    );
+    assert_eq!(
+        seen_by_a[0], seen_by_b[0],
+        "both workspaces should load the rewritten head"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,100 @@
+//! Slice 37: TANDEM_SERVER overrides are checked against the workspace's repo
+//!
+//! Acceptance criteria:
+//! - A workspace remembers which repo it belongs to on first load
+//! - `TANDEM_SERVER` pointing at a server for a different repo is refused
+//! - `--allow-server-override` lets the override through
+//! - `TANDEM_SERVER` naming the workspace's own server keeps working
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice37_mismatched_server_override_is_refused() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let repo_a = tmp.path().join("repo-a");
+    let repo_b = tmp.path().join("repo-b");
+    std::fs::create_dir_all(&repo_a).unwrap();
+    std::fs::create_dir_all(&repo_b).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr_a = common::free_addr();
+    let mut server_a = common::spawn_server(&repo_a, &addr_a);
+    common::wait_for_server(&addr_a, &mut server_a);
+    let addr_b = common::free_addr();
+    let mut server_b = common::spawn_server(&repo_b, &addr_b);
+    common::wait_for_server(&addr_b, &mut server_b);
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr_a, "."], &home);
+    common::assert_ok(&init, "init workspace against server a");
+    let log = common::run_tandem_in(&ws, &["log", "-r", "@"], &home);
+    common::assert_ok(&log, "log against recorded server");
+    assert!(
+        ws.join(".jj/repo/op_heads/server_anchor.json").exists(),
+        "first load should record the repo anchor"
+    );
+    // Every jj repo has the same root ids; only its operations identify it.
+    let anchor: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(ws.join(".jj/repo/op_heads/server_anchor.json")).unwrap(),
+    )
+    .unwrap();
+    assert!(
+        anchor["operations"]
+            .as_array()
+            .is_some_and(|ops| !ops.is_empty())
+            && anchor.get("rootCommitId").is_none(),
+        "the anchor should hold the workspace's operations:\n{anchor}"
+    );
+
+    let same = common::run_tandem_in_with_env(
+        &ws,
+        &["log", "-r", "@"],
+        &[("TANDEM_SERVER", &addr_a)],
+        &home,
+    );
+    common::assert_ok(&same, "TANDEM_SERVER naming the recorded server");
+
+    let refused = common::run_tandem_in_with_env(
+        &ws,
+        &["log", "-r", "@"],
+        &[("TANDEM_SERVER", &addr_b)],
+        &home,
+    );
+    assert!(
+        !refused.status.success(),
+        "override to another repo's server should be refused"
+    );
+    let stderr = common::stderr_str(&refused);
+    assert!(
+        stderr.contains("does not match this workspace's server")
+            && stderr.contains("does not know operation")
+            && stderr.contains("--allow-server-override"),
+        "refusal should explain the mismatch and the escape hatch:\n{stderr}"
+    );
+
+    let allowed = common::run_tandem_in_with_env(
+        &ws,
+        &[
+            "--allow-server-override",
+            "op",
+            "log",
+            "--ignore-working-copy",
+        ],
+        &[("TANDEM_SERVER", &addr_b)],
+        &home,
+    );
+    common::assert_ok(&allowed, "override with --allow-server-override");
+    assert!(
+        common::stderr_str(&allowed).contains("overrides this workspace's server"),
+        "an allowed override should still warn:\n{}",
+        common::stderr_str(&allowed)
+    );
+
+    let _ = server_a.kill();
+    let _ = server_a.wait();
+    let _ = server_b.kill();
+    let _ = server_b.wait();
+}
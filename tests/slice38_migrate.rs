@@ -0,0 +1,80 @@
+//! Slice 38: moving a repo between servers
+//!
+//! Acceptance criteria:
+//! - `tandem migrate --from <old> --to <new>` copies history and heads to a
+//!   fresh server
+//! - Existing workspaces follow the old server's redirect and rewrite their
+//!   stored server address
+//! - After the move, workspaces keep working with the old server gone
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice38_workspaces_follow_a_migrated_repo() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let old_repo = tmp.path().join("old-repo");
+    let new_repo = tmp.path().join("new-repo");
+    std::fs::create_dir_all(&old_repo).unwrap();
+    std::fs::create_dir_all(&new_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let old_addr = common::free_addr();
+    let mut old_server = common::spawn_server(&old_repo, &old_addr);
+    common::wait_for_server(&old_addr, &mut old_server);
+    let new_addr = common::free_addr();
+    let mut new_server = common::spawn_server(&new_repo, &new_addr);
+    common::wait_for_server(&new_addr, &mut new_server);
+
+    let init = common::run_tandem_in(
+        &ws,
+        &["init", "--server", &old_addr, "--workspace", "agent-a", "."],
+        &home,
+    );
+    common::assert_ok(&init, "init workspace on old server");
+    std::fs::write(ws.join("before.txt"), b"before migration\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "before"], &home);
+    common::assert_ok(&commit, "commit before migration");
+
+    let migrate = common::run_tandem_in(
+        tmp.path(),
+        &["migrate", "--from", &old_addr, "--to", &new_addr],
+        &home,
+    );
+    common::assert_ok(&migrate, "tandem migrate");
+
+    let log = common::run_tandem_in(&ws, &["log", "-r", "@", "-T", "description"], &home);
+    common::assert_ok(&log, "log after migration");
+    assert!(
+        common::stdout_str(&log).contains("before"),
+        "history should survive the move:\n{}",
+        common::stdout_str(&log)
+    );
+    assert!(
+        common::stderr_str(&log).contains(&format!("moved from {old_addr} to {new_addr}")),
+        "the workspace should report following the redirect:\n{}",
+        common::stderr_str(&log)
+    );
+    let recorded = std::fs::read_to_string(ws.join(".jj/repo/store/server_address")).unwrap();
+    assert_eq!(
+        recorded.trim(),
+        new_addr,
+        "server address should be rewritten"
+    );
+
+    let _ = old_server.kill();
+    let _ = old_server.wait();
+
+    std::fs::write(ws.join("after.txt"), b"after migration\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "after"], &home);
+    common::assert_ok(&commit, "commit against the new server");
+    let show = common::run_tandem_in(&ws, &["file", "show", "-r", "@-", "before.txt"], &home);
+    common::assert_ok(&show, "read migrated file content");
+    assert_eq!(common::stdout_str(&show), "before migration\n");
+
+    let _ = new_server.kill();
+    let _ = new_server.wait();
+}
@@ -0,0 +1,133 @@
+//! Slice 39: operator-set endpoint redirects
+//!
+//! Acceptance criteria:
+//! - `tandem server redirect <addr>` on a server whose repo was moved by hand
+//!   sends workspaces to the new address, and they rewrite `server_address`
+//! - `tandem server status` shows the redirect
+//! - A redirect loop fails with the chain instead of hanging
+//! - `tandem server redirect --off` lets the server serve again
+
+mod common;
+
+use std::path::Path;
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+fn copy_dir(from: &Path, to: &Path) {
+    std::fs::create_dir_all(to).unwrap();
+    for entry in std::fs::read_dir(from).unwrap() {
+        let entry = entry.unwrap();
+        let target = to.join(entry.file_name());
+        if entry.file_type().unwrap().is_dir() {
+            copy_dir(&entry.path(), &target);
+        } else {
+            std::fs::copy(entry.path(), target).unwrap();
+        }
+    }
+}
+
+fn redirect(tmp: &Path, sock: &str, args: &[&str], home: &Path) {
+    let mut full = vec!["server", "redirect"];
+    full.extend_from_slice(args);
+    full.extend_from_slice(&["--control-socket", sock]);
+    let out = common::run_tandem_in(tmp, &full, home);
+    common::assert_ok(&out, "server redirect");
+}
+
+#[test]
+fn slice39_workspaces_follow_operator_redirects() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let old_repo = tmp.path().join("old-repo");
+    let new_repo = tmp.path().join("new-repo");
+    std::fs::create_dir_all(&old_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let old_addr = common::free_addr();
+    let mut old_server = common::spawn_server(&old_repo, &old_addr);
+    common::wait_for_server(&old_addr, &mut old_server);
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &old_addr, "."], &home);
+    common::assert_ok(&init, "init workspace on old server");
+    std::fs::write(ws.join("before.txt"), b"before move\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "before"], &home);
+    common::assert_ok(&commit, "commit before move");
+
+    // Move the repo by hand: stop, copy the directory, serve both copies.
+    let _ = old_server.kill();
+    let _ = old_server.wait();
+    copy_dir(&old_repo, &new_repo);
+
+    let old_sock = tmp.path().join("old.sock");
+    let old_sock_str = old_sock.to_str().unwrap();
+    let mut old_server = common::spawn_server_with_args(
+        &old_repo,
+        &old_addr,
+        &["--control-socket", old_sock_str],
+        &home,
+    );
+    common::wait_for_server(&old_addr, &mut old_server);
+    common::wait_for_socket(&old_sock, Duration::from_secs(5));
+
+    let new_addr = common::free_addr();
+    let new_sock = tmp.path().join("new.sock");
+    let new_sock_str = new_sock.to_str().unwrap();
+    let mut new_server = common::spawn_server_with_args(
+        &new_repo,
+        &new_addr,
+        &["--control-socket", new_sock_str],
+        &home,
+    );
+    common::wait_for_server(&new_addr, &mut new_server);
+    common::wait_for_socket(&new_sock, Duration::from_secs(5));
+
+    redirect(tmp.path(), old_sock_str, &[&new_addr], &home);
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--control-socket", old_sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "server status");
+    assert!(
+        common::stdout_str(&status).contains(&format!("Redirect: {new_addr}")),
+        "status should show the redirect:\n{}",
+        common::stdout_str(&status)
+    );
+
+    // `new -m` describes the new working-copy commit.
+    let log = common::run_tandem_in(&ws, &["log", "-r", "@", "-T", "description"], &home);
+    common::assert_ok(&log, "log after redirect");
+    assert!(
+        common::stdout_str(&log).contains("before"),
+        "history should be served by the new server:\n{}",
+        common::stdout_str(&log)
+    );
+    let recorded = std::fs::read_to_string(ws.join(".jj/repo/store/server_address")).unwrap();
+    assert_eq!(
+        recorded.trim(),
+        new_addr,
+        "server address should be rewritten"
+    );
+
+    // new -> old -> new is a loop; the client names it instead of spinning.
+    redirect(tmp.path(), new_sock_str, &[&old_addr], &home);
+    let looped = common::run_tandem_in(&ws, &["log", "-r", "@-"], &home);
+    assert!(!looped.status.success(), "a redirect loop should fail");
+    let stderr = common::stderr_str(&looped);
+    assert!(
+        stderr.contains("redirect loop") && stderr.contains(&old_addr),
+        "the error should show the chain:\n{stderr}"
+    );
+
+    redirect(tmp.path(), new_sock_str, &["--off"], &home);
+    std::fs::write(ws.join("after.txt"), b"after move\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "after"], &home);
+    common::assert_ok(&commit, "commit once the redirect is off");
+
+    let _ = old_server.kill();
+    let _ = old_server.wait();
+    let _ = new_server.kill();
+    let _ = new_server.wait();
+}
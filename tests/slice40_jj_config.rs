@@ -0,0 +1,93 @@
+//! Slice 40: tandem settings in jj config
+//!
+//! Acceptance criteria:
+//! - `tandem.timing = true` prints each RPC's latency to stderr
+//! - `tandem.cache-dir` moves the operation/view cache out of the store
+//! - `tandem.endpoint` overrides the workspace's server, with the same repo
+//!   identity check as `TANDEM_SERVER`
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice40_tandem_table_in_jj_config() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    let other_repo = tmp.path().join("other-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    std::fs::create_dir_all(&other_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let other_addr = common::free_addr();
+    let mut other_server = common::spawn_server(&other_repo, &other_addr);
+    common::wait_for_server(&other_addr, &mut other_server);
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    std::fs::write(ws.join("a.txt"), b"a\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "configured"], &home);
+    common::assert_ok(&commit, "commit");
+
+    let set = common::run_tandem_in(
+        &ws,
+        &["config", "set", "--repo", "tandem.timing", "true"],
+        &home,
+    );
+    common::assert_ok(&set, "config set tandem.timing");
+    let log = common::run_tandem_in(&ws, &["log", "-r", "@-", "-T", "description"], &home);
+    common::assert_ok(&log, "log with timing");
+    let stderr = common::stderr_str(&log);
+    assert!(
+        stderr.contains("tandem: getHeads") && stderr.contains("ms"),
+        "timing should report RPCs:\n{stderr}"
+    );
+    let unset = common::run_tandem_in(&ws, &["config", "unset", "--repo", "tandem.timing"], &home);
+    common::assert_ok(&unset, "config unset tandem.timing");
+
+    let cache_dir = tmp.path().join("shared-cache");
+    let set = common::run_tandem_in(
+        &ws,
+        &[
+            "config",
+            "set",
+            "--repo",
+            "tandem.cache-dir",
+            cache_dir.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&set, "config set tandem.cache-dir");
+    let op_log = common::run_tandem_in(&ws, &["op", "log", "--no-graph"], &home);
+    common::assert_ok(&op_log, "op log with cache-dir");
+    let cached = std::fs::read_dir(cache_dir.join("operations"))
+        .map(|entries| entries.count())
+        .unwrap_or(0);
+    assert!(cached > 0, "operations should be cached in {cache_dir:?}");
+
+    let config_arg = format!("tandem.endpoint={other_addr}");
+    let refused = common::run_tandem_in(&ws, &["--config", &config_arg, "log"], &home);
+    assert!(
+        !refused.status.success(),
+        "an endpoint serving another repo should be refused"
+    );
+    let stderr = common::stderr_str(&refused);
+    assert!(
+        stderr.contains(&format!("tandem.endpoint={other_addr} does not match")),
+        "the refusal should name the config key:\n{stderr}"
+    );
+
+    let config_arg = format!("tandem.endpoint={addr}");
+    let same = common::run_tandem_in(&ws, &["--config", &config_arg, "log"], &home);
+    common::assert_ok(&same, "endpoint naming the workspace's own server");
+
+    let _ = server.kill();
+    let _ = server.wait();
+    let _ = other_server.kill();
+    let _ = other_server.wait();
+}
@@ -0,0 +1,82 @@
+//! Slice 41: view deltas
+//!
+//! Acceptance criteria:
+//! - With several workspaces, a new view is written as a delta (`putViewDelta`)
+//! - Another workspace fetches it as a delta (`getViewDelta`) and sees the
+//!   same history as the writer
+
+mod common;
+
+use tempfile::TempDir;
+
+const TIMING: &[&str] = &["--config", "tandem.timing=true"];
+
+#[test]
+fn slice41_views_travel_as_deltas() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    // Enough workspaces that most of each view is unchanged by one commit.
+    let mut workspaces = Vec::new();
+    for name in ["agent-a", "agent-b", "agent-c", "agent-d"] {
+        let ws = tmp.path().join(name);
+        std::fs::create_dir_all(&ws).unwrap();
+        let init = common::run_tandem_in(
+            &ws,
+            &["init", "--server", &addr, "--workspace", name, "."],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+        workspaces.push(ws);
+    }
+    let (ws_a, ws_b) = (&workspaces[0], &workspaces[1]);
+    let log_b = common::run_tandem_in(ws_b, &["log", "-r", "@"], &home);
+    common::assert_ok(&log_b, "agent-b loads the repo");
+
+    std::fs::write(ws_a.join("a.txt"), b"a\n").unwrap();
+    let args: Vec<&str> = TIMING
+        .iter()
+        .copied()
+        .chain(["new", "-m", "delta-commit"])
+        .collect();
+    let commit = common::run_tandem_in(ws_a, &args, &home);
+    common::assert_ok(&commit, "agent-a commit");
+    let stderr = common::stderr_str(&commit);
+    assert!(
+        stderr.contains("tandem: putViewDelta"),
+        "the new view should be sent as a delta:\n{stderr}"
+    );
+
+    let args: Vec<&str> = TIMING
+        .iter()
+        .copied()
+        .chain([
+            "log",
+            "-r",
+            "description(substring:delta-commit)",
+            "-T",
+            "description",
+        ])
+        .collect();
+    let log = common::run_tandem_in(ws_b, &args, &home);
+    common::assert_ok(&log, "agent-b log");
+    assert!(
+        common::stdout_str(&log).contains("delta-commit"),
+        "agent-b should see agent-a's commit:\n{}",
+        common::stdout_str(&log)
+    );
+    let stderr = common::stderr_str(&log);
+    assert!(
+        stderr.contains("tandem: getViewDelta"),
+        "the new view should be fetched as a delta:\n{stderr}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,83 @@
+//! Slice 42: workspace attribution in templates
+//!
+//! Acceptance criteria:
+//! - The server tags each published operation with `tandem.workspace`
+//! - `tandem_workspace` in a commit template names the workspace whose
+//!   operation created the commit, from any workspace
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice42_commits_show_the_workspace_that_made_them() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws_a = tmp.path().join("ws-a");
+    let ws_b = tmp.path().join("ws-b");
+    std::fs::create_dir_all(&ws_a).unwrap();
+    std::fs::create_dir_all(&ws_b).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    for (ws, name) in [(&ws_a, "agent-a"), (&ws_b, "agent-b")] {
+        let init = common::run_tandem_in(
+            ws,
+            &["init", "--server", &addr, "--workspace", name, "."],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+
+    std::fs::write(ws_a.join("a.txt"), b"a\n").unwrap();
+    let a = common::run_tandem_in(&ws_a, &["describe", "-m", "from-a"], &home);
+    common::assert_ok(&a, "agent-a describe");
+    std::fs::write(ws_b.join("b.txt"), b"b\n").unwrap();
+    let b = common::run_tandem_in(&ws_b, &["describe", "-m", "from-b"], &home);
+    common::assert_ok(&b, "agent-b describe");
+
+    let log = common::run_tandem_in(
+        &ws_a,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "description(substring:\"from-a\") | description(substring:\"from-b\")",
+            "-T",
+            "description.first_line() ++ \"=\" ++ tandem_workspace ++ \"\\n\"",
+        ],
+        &home,
+    );
+    common::assert_ok(&log, "log with tandem_workspace");
+    let stdout = common::stdout_str(&log);
+    assert!(
+        stdout.contains("from-a=agent-a") && stdout.contains("from-b=agent-b"),
+        "each commit should name its workspace:\n{stdout}"
+    );
+
+    let ops = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "ops",
+            "--server",
+            &addr,
+            "--tag",
+            "tandem.workspace=agent-b",
+            "--json",
+        ],
+        &home,
+    );
+    common::assert_ok(&ops, "tandem ops by workspace");
+    assert!(
+        common::stdout_str(&ops).contains("agent-b"),
+        "agent-b's operations should be tagged:\n{}",
+        common::stdout_str(&ops)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,224 @@
+//! Slice 43: offline mode
+//!
+//! Acceptance criteria:
+//! - With the server down, reads of history this workspace has already seen
+//!   still work, with a banner saying the workspace is offline
+//! - Commands that write succeed, queueing their writes locally, and later
+//!   commands see them; `tandem status` reports the queue
+//! - Once the server is back the queue is replayed under the same ids and
+//!   merged with operations other workspaces published meanwhile
+//! - `--tandem-offline` reads the same saved state without dialing
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice43_reads_survive_an_unreachable_server() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    std::fs::write(ws.join("notes.txt"), b"written online\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "online-commit"], &home);
+    common::assert_ok(&commit, "commit while online");
+    let warm = common::run_tandem_in(&ws, &["diff", "-r", "@-"], &home);
+    common::assert_ok(&warm, "diff while online");
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    let log = common::run_tandem_in(
+        &ws,
+        &[
+            "log",
+            "-r",
+            "description(substring:online-commit)",
+            "-T",
+            "description",
+        ],
+        &home,
+    );
+    common::assert_ok(&log, "log with the server down");
+    assert!(
+        common::stdout_str(&log).contains("online-commit"),
+        "cached history should be readable:\n{}",
+        common::stdout_str(&log)
+    );
+    let stderr = common::stderr_str(&log);
+    assert!(
+        stderr.contains("tandem: offline"),
+        "reads should say they are offline:\n{stderr}"
+    );
+
+    let diff = common::run_tandem_in(&ws, &["diff", "-r", "@-"], &home);
+    common::assert_ok(&diff, "diff with the server down");
+    assert!(
+        common::stdout_str(&diff).contains("written online"),
+        "cached file contents should be readable:\n{}",
+        common::stdout_str(&diff)
+    );
+
+    std::fs::write(ws.join("notes.txt"), b"written offline\n").unwrap();
+    let write = common::run_tandem_in(&ws, &["new", "-m", "offline-commit"], &home);
+    common::assert_ok(&write, "commit with the server down");
+    let queue = std::fs::read_to_string(ws.join(".jj/repo/tandem/offline/queue.jsonl"))
+        .expect("offline writes should be queued");
+    assert!(
+        queue.contains("\"write\":\"heads\""),
+        "the queue should hold the head update:\n{queue}"
+    );
+
+    let after = common::run_tandem_in(
+        &ws,
+        &["log", "-r", "@ | @-", "-T", "description ++ \"|\""],
+        &home,
+    );
+    common::assert_ok(&after, "log after an offline write");
+    assert!(
+        common::stdout_str(&after).contains("offline-commit"),
+        "the offline commit should be visible:\n{}",
+        common::stdout_str(&after)
+    );
+    let diff = common::run_tandem_in(&ws, &["diff", "--git", "-r", "@-"], &home);
+    common::assert_ok(&diff, "diff of an offline commit");
+    assert!(
+        common::stdout_str(&diff).contains("+written offline"),
+        "offline file contents should be readable:\n{}",
+        common::stdout_str(&diff)
+    );
+
+    let forced = common::run_tandem_in(
+        &ws,
+        &[
+            "--tandem-offline",
+            "--ignore-working-copy",
+            "log",
+            "-r",
+            "@-",
+            "-T",
+            "description",
+        ],
+        &home,
+    );
+    common::assert_ok(&forced, "log with --tandem-offline");
+    assert!(
+        common::stderr_str(&forced).contains("tandem: offline mode"),
+        "--tandem-offline should say so:\n{}",
+        common::stderr_str(&forced)
+    );
+}
+
+#[test]
+fn slice43_offline_writes_sync_when_the_server_is_back() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws_a = tmp.path().join("ws-a");
+    let ws_b = tmp.path().join("ws-b");
+    std::fs::create_dir_all(&ws_a).unwrap();
+    std::fs::create_dir_all(&ws_b).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init_a = common::run_tandem_in(&ws_a, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init_a, "init workspace a");
+    let init_b = common::run_tandem_in(
+        &ws_b,
+        &["init", "--server", &addr, "--workspace", "b", "."],
+        &home,
+    );
+    common::assert_ok(&init_b, "init workspace b");
+    let warm = common::run_tandem_in(&ws_a, &["log"], &home);
+    common::assert_ok(&warm, "log while online");
+
+    // Workspace a works offline while b keeps publishing.
+    std::fs::write(ws_a.join("notes.txt"), b"written offline\n").unwrap();
+    let offline = common::run_tandem_in(
+        &ws_a,
+        &["--tandem-offline", "new", "-m", "offline-a"],
+        &home,
+    );
+    common::assert_ok(&offline, "offline commit");
+    let status = common::run_tandem_in(&ws_a, &["--tandem-offline", "status"], &home);
+    common::assert_ok(&status, "status while offline");
+    assert!(
+        common::stderr_str(&status).contains("not yet sent to the tandem server"),
+        "status should report the queue:\n{}",
+        common::stderr_str(&status)
+    );
+
+    std::fs::write(ws_b.join("other.txt"), b"from b\n").unwrap();
+    let from_b = common::run_tandem_in(&ws_b, &["new", "-m", "from-b"], &home);
+    common::assert_ok(&from_b, "commit in workspace b");
+
+    // Back online: the queue is replayed and merged with b's operation.
+    let log = common::run_tandem_in(
+        &ws_a,
+        &[
+            "log",
+            "-r",
+            "description(substring:offline-a) | description(substring:from-b)",
+            "-T",
+            "description ++ \"|\"",
+        ],
+        &home,
+    );
+    common::assert_ok(&log, "log after reconnecting");
+    let stderr = common::stderr_str(&log);
+    assert!(
+        stderr.contains("synced") && stderr.contains("merged with operations published meanwhile"),
+        "the replay should be announced:\n{stderr}"
+    );
+    let stdout = common::stdout_str(&log);
+    assert!(
+        stdout.contains("offline-a") && stdout.contains("from-b"),
+        "both workspaces' commits should be visible:\n{stdout}"
+    );
+    assert!(
+        !ws_a.join(".jj/repo/tandem/offline/queue.jsonl").exists(),
+        "a replayed queue should be dropped"
+    );
+
+    // The server has the offline commit under the id it was given offline.
+    let seen_by_b = common::run_tandem_in(
+        &ws_b,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "description(substring:offline-a)",
+            "-T",
+            "description",
+        ],
+        &home,
+    );
+    common::assert_ok(&seen_by_b, "log in workspace b");
+    assert!(
+        common::stdout_str(&seen_by_b).contains("offline-a"),
+        "workspace b should see the offline commit:\n{}",
+        common::stdout_str(&seen_by_b)
+    );
+    let status = common::run_tandem_in(&ws_a, &["status"], &home);
+    common::assert_ok(&status, "status after syncing");
+    assert!(
+        !common::stderr_str(&status).contains("not yet sent"),
+        "nothing should be queued:\n{}",
+        common::stderr_str(&status)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
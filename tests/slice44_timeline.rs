@@ -0,0 +1,85 @@
+//! Slice 44: workspace activity timeline
+//!
+//! Acceptance criteria:
+//! - `tandem timeline` lists each heads step with the workspace that took it
+//!   and the operation's description
+//! - `--workspace` keeps only that workspace's steps
+//! - `--json` emits one object per step, oldest first
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice44_timeline_merges_workspace_activity() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    for name in ["agent-a", "agent-b"] {
+        let ws = tmp.path().join(name);
+        std::fs::create_dir_all(&ws).unwrap();
+        let init = common::run_tandem_in(
+            &ws,
+            &["init", "--server", &addr, "--workspace", name, "."],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+        std::fs::write(ws.join(format!("{name}.txt")), name).unwrap();
+        let commit = common::run_tandem_in(&ws, &["new", "-m", &format!("{name} work")], &home);
+        common::assert_ok(&commit, &format!("{name} commit"));
+    }
+
+    let timeline = common::run_tandem_in(
+        tmp.path(),
+        &["timeline", "--server", &addr, "--since", "1h"],
+        &home,
+    );
+    common::assert_ok(&timeline, "timeline");
+    let stdout = common::stdout_str(&timeline);
+    let a = stdout.find("agent-a").expect("agent-a in timeline");
+    let b = stdout.find("agent-b").expect("agent-b in timeline");
+    assert!(a < b, "steps should be oldest first:\n{stdout}");
+    assert!(
+        stdout.contains("new empty commit"),
+        "operation descriptions should be shown:\n{stdout}"
+    );
+
+    let only_b = common::run_tandem_in(
+        tmp.path(),
+        &["timeline", "--server", &addr, "--workspace", "agent-b"],
+        &home,
+    );
+    common::assert_ok(&only_b, "timeline --workspace");
+    let stdout = common::stdout_str(&only_b);
+    assert!(stdout.contains("agent-b"), "{stdout}");
+    assert!(!stdout.contains("agent-a"), "{stdout}");
+
+    let json = common::run_tandem_in(
+        tmp.path(),
+        &["timeline", "--server", &addr, "--json"],
+        &home,
+    );
+    common::assert_ok(&json, "timeline --json");
+    let entries: Vec<serde_json::Value> =
+        serde_json::from_str(&common::stdout_str(&json)).expect("timeline JSON");
+    let versions: Vec<u64> = entries
+        .iter()
+        .map(|entry| entry["version"].as_u64().unwrap())
+        .collect();
+    assert!(
+        versions.windows(2).all(|pair| pair[0] < pair[1]),
+        "{versions:?}"
+    );
+    assert!(entries
+        .iter()
+        .any(|entry| entry["workspace"] == "agent-a" && !entry["operations"][0]["id"].is_null()));
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
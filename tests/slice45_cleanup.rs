@@ -0,0 +1,50 @@
+//! Slice 45: tandem cleanup
+//!
+//! Acceptance criteria:
+//! - `tandem cleanup --dry-run` lists up-state entries for repos that no
+//!   longer exist and dead control sockets, without removing them
+//! - `tandem cleanup` removes them and keeps entries for live repos
+
+mod common;
+
+use tempfile::TempDir;
+
+#[cfg(unix)]
+#[test]
+fn slice45_cleanup_removes_state_for_missing_repos() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let tmpdir = tmp.path().join("tmp");
+    let state_dir = tmpdir.join("tandem").join("up-state");
+    std::fs::create_dir_all(&state_dir).unwrap();
+
+    let live_repo = tmp.path().join("live-repo");
+    std::fs::create_dir_all(live_repo.join(".jj")).unwrap();
+    let live = state_dir.join("last-listen-live.txt");
+    std::fs::write(&live, format!("0.0.0.0:13013\n{}\n", live_repo.display())).unwrap();
+    let gone = state_dir.join("last-listen-gone.txt");
+    std::fs::write(
+        &gone,
+        format!("0.0.0.0:13014\n{}\n", tmp.path().join("deleted").display()),
+    )
+    .unwrap();
+    let socket = tmpdir.join("tandem").join("crashed.sock");
+    drop(std::os::unix::net::UnixListener::bind(&socket).unwrap());
+
+    let env = [("TMPDIR", tmpdir.to_str().unwrap())];
+    let dry = common::run_tandem_in_with_env(tmp.path(), &["cleanup", "--dry-run"], &env, &home);
+    common::assert_ok(&dry, "cleanup --dry-run");
+    let stdout = common::stdout_str(&dry);
+    assert!(stdout.contains("would remove") && stdout.contains("no longer exists"));
+    assert!(stdout.contains("crashed.sock"), "{stdout}");
+    assert!(gone.exists() && socket.exists());
+
+    let run = common::run_tandem_in_with_env(tmp.path(), &["cleanup"], &env, &home);
+    common::assert_ok(&run, "cleanup");
+    assert!(!gone.exists() && !socket.exists());
+    assert!(live.exists(), "entries for existing repos stay");
+
+    let again = common::run_tandem_in_with_env(tmp.path(), &["cleanup"], &env, &home);
+    common::assert_ok(&again, "second cleanup");
+    assert!(common::stdout_str(&again).contains("nothing to clean up"));
+}
@@ -0,0 +1,72 @@
+//! Slice 46: port conflict diagnostics for tandem up
+//!
+//! Acceptance criteria:
+//! - When a repo's last listen address is taken by another tandem daemon,
+//!   `tandem up` names that daemon (PID, repo, control socket) on stderr and
+//!   falls back to auto port selection
+
+mod common;
+
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[cfg(unix)]
+#[test]
+fn slice46_up_names_daemon_holding_last_listen_port() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let tmpdir = tmp.path().join("tmp");
+    let socket_dir = tmpdir.join("tandem");
+    std::fs::create_dir_all(&socket_dir).unwrap();
+    let env = [("TMPDIR", tmpdir.to_str().unwrap())];
+
+    let repo_a = tmp.path().join("repo-a");
+    let repo_b = tmp.path().join("repo-b");
+    std::fs::create_dir_all(&repo_a).unwrap();
+    std::fs::create_dir_all(&repo_b).unwrap();
+    let sock_a = socket_dir.join("a.sock");
+    let sock_b = socket_dir.join("b.sock");
+    let addr = common::free_addr();
+
+    let up = |repo: &std::path::Path, sock: &std::path::Path, listen: Option<&str>| {
+        let mut args = vec![
+            "up",
+            "--repo",
+            repo.to_str().unwrap(),
+            "--control-socket",
+            sock.to_str().unwrap(),
+        ];
+        if let Some(listen) = listen {
+            args.extend(["--listen", listen]);
+        }
+        common::run_tandem_in_with_env(tmp.path(), &args, &env, &home)
+    };
+    let down = |sock: &std::path::Path| {
+        let out = common::run_tandem_in_with_env(
+            tmp.path(),
+            &["down", "--control-socket", sock.to_str().unwrap()],
+            &env,
+            &home,
+        );
+        common::assert_ok(&out, "tandem down");
+    };
+
+    // Remember `addr` as repo B's last listen address, then hand it to repo A.
+    common::assert_ok(&up(&repo_b, &sock_b, Some(&addr)), "up repo B");
+    down(&sock_b);
+    std::thread::sleep(Duration::from_millis(500));
+    common::assert_ok(&up(&repo_a, &sock_a, Some(&addr)), "up repo A");
+    common::wait_for_addr(&addr, Duration::from_secs(10));
+
+    let again = up(&repo_b, &sock_b, None);
+    common::assert_ok(&again, "up repo B without --listen");
+    let stderr = common::stderr_str(&again);
+    assert!(stderr.contains(&format!(
+        "{addr}, this repo's last listen address, is taken"
+    )));
+    assert!(stderr.contains("by tandem PID"), "{stderr}");
+    assert!(stderr.contains("a.sock"), "{stderr}");
+
+    down(&sock_b);
+    down(&sock_a);
+}
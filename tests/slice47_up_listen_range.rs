@@ -0,0 +1,107 @@
+//! Slice 47: configurable auto-listen host and port range for tandem up
+//!
+//! Acceptance criteria:
+//! - `TANDEM_UP_HOST` / `TANDEM_UP_PORT_RANGE` (or `--listen-host` /
+//!   `--port-range`) steer auto port selection
+//! - A range with no free port fails and suggests widening it
+//! - Malformed ranges are rejected
+
+mod common;
+
+use tempfile::TempDir;
+
+fn free_port() -> u16 {
+    let addr = common::free_addr();
+    addr.rsplit_once(':').unwrap().1.parse().unwrap()
+}
+
+#[test]
+fn slice47_up_binds_configured_host_and_range() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let repo = tmp.path().join("repo");
+    std::fs::create_dir_all(&repo).unwrap();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let port = free_port();
+    let range = format!("{port}-{port}");
+
+    let up = common::run_tandem_in_with_env(
+        tmp.path(),
+        &[
+            "up",
+            "--repo",
+            repo.to_str().unwrap(),
+            "--control-socket",
+            sock_str,
+        ],
+        &[
+            ("TANDEM_UP_HOST", "127.0.0.1"),
+            ("TANDEM_UP_PORT_RANGE", &range),
+        ],
+        &home,
+    );
+    common::assert_ok(&up, "tandem up with configured range");
+
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--json", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "status");
+    let parsed: serde_json::Value =
+        serde_json::from_str(common::stdout_str(&status).trim()).unwrap();
+    assert_eq!(parsed["listen"], format!("127.0.0.1:{port}"));
+
+    let down = common::run_tandem_in(tmp.path(), &["down", "--control-socket", sock_str], &home);
+    common::assert_ok(&down, "tandem down");
+}
+
+#[test]
+fn slice47_up_reports_exhausted_range() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let repo = tmp.path().join("repo");
+    std::fs::create_dir_all(&repo).unwrap();
+    let sock = common::control_socket_path(tmp.path());
+    let held = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = held.local_addr().unwrap().port();
+
+    let out = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "up",
+            "--repo",
+            repo.to_str().unwrap(),
+            "--control-socket",
+            sock.to_str().unwrap(),
+            "--listen-host",
+            "127.0.0.1",
+            "--port-range",
+            &port.to_string(),
+        ],
+        &home,
+    );
+    assert!(!out.status.success(), "up should fail with no free port");
+    let stderr = common::stderr_str(&out);
+    assert!(
+        stderr.contains(&format!(
+            "could not find a free listen address in 127.0.0.1:{port}-{port}"
+        )),
+        "{stderr}"
+    );
+    assert!(stderr.contains("--port-range"), "{stderr}");
+}
+
+#[test]
+fn slice47_up_rejects_malformed_range() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let out = common::run_tandem_in(
+        tmp.path(),
+        &["up", "--repo", ".", "--port-range", "13063-13013"],
+        &home,
+    );
+    assert!(!out.status.success());
+    assert!(common::stderr_str(&out).contains("ends before it starts"));
+}
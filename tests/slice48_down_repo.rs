@@ -0,0 +1,100 @@
+//! Slice 48: tandem down --repo
+//!
+//! Acceptance criteria:
+//! - `tandem down --repo <path>` stops the daemon `tandem up` started for that
+//!   repo, using the control socket recorded in the up-state directory
+//! - Other daemons keep running
+//! - A repo with no recorded daemon fails with a hint
+
+mod common;
+
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[cfg(unix)]
+#[test]
+fn slice48_down_by_repo_stops_only_that_daemon() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let tmpdir = tmp.path().join("tmp");
+    std::fs::create_dir_all(&tmpdir).unwrap();
+    let env = [("TMPDIR", tmpdir.to_str().unwrap())];
+
+    let repo_a = tmp.path().join("repo-a");
+    let repo_b = tmp.path().join("repo-b");
+    std::fs::create_dir_all(&repo_a).unwrap();
+    std::fs::create_dir_all(&repo_b).unwrap();
+    let sock_a = tmp.path().join("a.sock");
+    let sock_b = tmp.path().join("b.sock");
+
+    for (repo, sock) in [(&repo_a, &sock_a), (&repo_b, &sock_b)] {
+        let addr = common::free_addr();
+        let up = common::run_tandem_in_with_env(
+            tmp.path(),
+            &[
+                "up",
+                "--repo",
+                repo.to_str().unwrap(),
+                "--listen",
+                &addr,
+                "--control-socket",
+                sock.to_str().unwrap(),
+            ],
+            &env,
+            &home,
+        );
+        common::assert_ok(&up, "tandem up");
+    }
+
+    let down = common::run_tandem_in_with_env(
+        tmp.path(),
+        &["down", "--repo", repo_a.to_str().unwrap()],
+        &env,
+        &home,
+    );
+    common::assert_ok(&down, "tandem down --repo");
+    assert!(common::stdout_str(&down).contains("tandem stopped"));
+    std::thread::sleep(Duration::from_millis(500));
+    assert!(!sock_a.exists(), "repo A's daemon should be gone");
+
+    let status_b = common::run_tandem_in_with_env(
+        tmp.path(),
+        &[
+            "server",
+            "status",
+            "--control-socket",
+            sock_b.to_str().unwrap(),
+        ],
+        &env,
+        &home,
+    );
+    common::assert_ok(&status_b, "repo B's daemon keeps running");
+
+    let down_b = common::run_tandem_in_with_env(
+        tmp.path(),
+        &["down", "--repo", repo_b.to_str().unwrap()],
+        &env,
+        &home,
+    );
+    common::assert_ok(&down_b, "tandem down --repo B");
+}
+
+#[test]
+fn slice48_down_by_unknown_repo_fails() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let tmpdir = tmp.path().join("tmp");
+    std::fs::create_dir_all(&tmpdir).unwrap();
+    let repo = tmp.path().join("never-up");
+    std::fs::create_dir_all(&repo).unwrap();
+
+    let out = common::run_tandem_in_with_env(
+        tmp.path(),
+        &["down", "--repo", repo.to_str().unwrap()],
+        &[("TMPDIR", tmpdir.to_str().unwrap())],
+        &home,
+    );
+    assert!(!out.status.success());
+    let stderr = common::stderr_str(&out);
+    assert!(stderr.contains("no daemon recorded for repo"), "{stderr}");
+}
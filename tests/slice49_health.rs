@@ -0,0 +1,98 @@
+//! Slice 49: health checks for the serve process
+//!
+//! Acceptance criteria:
+//! - `tandem server health` reports op-heads, repo, and git checks and exits 0
+//!   while all pass
+//! - `tandem serve --health-listen` answers `GET /healthz` with the same report
+//!   (200 when healthy, 503 otherwise) and 404 for anything else
+//! - Losing the op heads turns both unhealthy
+
+mod common;
+
+use std::io::{Read, Write};
+use std::time::Duration;
+use tempfile::TempDir;
+
+fn http_get(addr: &str, path: &str) -> (u16, String) {
+    let mut stream = std::net::TcpStream::connect(addr).expect("connect health endpoint");
+    stream.set_read_timeout(Some(Duration::from_secs(15))).ok();
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .unwrap_or_else(|| panic!("malformed response: {response}"));
+    let body = response
+        .split_once("\r\n\r\n")
+        .map(|(_, body)| body.to_string())
+        .unwrap_or_default();
+    (status, body)
+}
+
+#[test]
+fn slice49_health_reports_checks_over_socket_and_http() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let health_addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &[
+            "--control-socket",
+            sock_str,
+            "--health-listen",
+            &health_addr,
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let out = common::run_tandem_in(
+        tmp.path(),
+        &["server", "health", "--json", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&out, "tandem server health");
+    let report: serde_json::Value = serde_json::from_str(common::stdout_str(&out).trim()).unwrap();
+    assert_eq!(report["healthy"], true, "{report}");
+    let names: Vec<&str> = report["checks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|check| check["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["op-heads", "repo", "git"]);
+
+    let (status, body) = http_get(&health_addr, "/healthz");
+    assert_eq!(status, 200, "{body}");
+    assert!(body.contains("\"healthy\":true"), "{body}");
+    assert_eq!(http_get(&health_addr, "/other").0, 404);
+
+    // Without op heads the repo cannot load at head.
+    let heads_dir = server_repo.join(".jj/repo/op_heads/heads");
+    for entry in std::fs::read_dir(&heads_dir).unwrap() {
+        std::fs::remove_file(entry.unwrap().path()).unwrap();
+    }
+    let out = common::run_tandem_in(
+        tmp.path(),
+        &["server", "health", "--control-socket", sock_str],
+        &home,
+    );
+    assert!(!out.status.success(), "health should fail without op heads");
+    assert!(common::stdout_str(&out).contains("unhealthy"));
+    let (status, body) = http_get(&health_addr, "/healthz");
+    assert_eq!(status, 503, "{body}");
+
+    common::interrupt_server(&server);
+    let _ = server.wait();
+}
@@ -144,6 +144,7 @@ fn slice4_ten_files_rapid_fire_round_trip() {
 /// Verify that pipelining handles larger files efficiently.
 /// Writes files with substantial content to exercise blob transfer pipelining.
 #[test]
+#[allow(clippy::needless_range_loop)]
 fn slice4_large_files_pipelining() {
     let tmp = TempDir::new().unwrap();
     let home = common::isolated_home(tmp.path());
@@ -214,6 +215,7 @@ fn slice4_large_files_pipelining() {
 /// Verify that files accumulate correctly across multiple pipelined commits.
 /// Each commit adds a new file while keeping all previous files in the tree.
 #[test]
+#[allow(clippy::needless_range_loop)]
 fn slice4_cumulative_tree_growth() {
     let file_count = 5;
     let tmp = TempDir::new().unwrap();
@@ -0,0 +1,73 @@
+//! Slice 50: startup repo validation
+//!
+//! Acceptance criteria:
+//! - `tandem serve` on a half-initialized `.jj` refuses to start with a
+//!   plain-language problem, a fix, and a pointer to `--repair`
+//! - `tandem serve --repair` removes the interrupted init and starts
+//! - Pointing the server at a tandem client workspace says so
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice50_interrupted_init_is_explained_and_repairable() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(server_repo.join(".jj/repo/store")).unwrap();
+
+    let addr = common::free_addr();
+    let out = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "serve",
+            "--listen",
+            &addr,
+            "--repo",
+            server_repo.to_str().unwrap(),
+        ],
+        &home,
+    );
+    assert!(!out.status.success(), "serve should refuse a broken repo");
+    let stderr = common::stderr_str(&out);
+    assert!(stderr.contains("interrupted `jj init`"), "{stderr}");
+    assert!(stderr.contains("--repair"), "{stderr}");
+
+    let mut server = common::spawn_server_with_args(&server_repo, &addr, &["--repair"], &home);
+    common::wait_for_server(&addr, &mut server);
+    assert!(server_repo.join(".jj/repo/store/type").exists());
+
+    common::interrupt_server(&server);
+    let _ = server.wait();
+}
+
+#[test]
+fn slice50_client_workspace_is_named() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let workspace = tmp.path().join("client");
+    let repo_dir = workspace.join(".jj/repo");
+    for dir in ["store", "op_store", "op_heads/heads"] {
+        std::fs::create_dir_all(repo_dir.join(dir)).unwrap();
+    }
+    std::fs::write(repo_dir.join("store/type"), "tandem").unwrap();
+    std::fs::write(repo_dir.join("op_heads/type"), "tandem_op_heads").unwrap();
+    std::fs::write(repo_dir.join("op_heads/heads/00"), "").unwrap();
+
+    let addr = common::free_addr();
+    let out = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "serve",
+            "--listen",
+            &addr,
+            "--repo",
+            workspace.to_str().unwrap(),
+        ],
+        &home,
+    );
+    assert!(!out.status.success());
+    let stderr = common::stderr_str(&out);
+    assert!(stderr.contains("tandem client workspace"), "{stderr}");
+}
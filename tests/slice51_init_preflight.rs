@@ -0,0 +1,65 @@
+//! Slice 51: init-time server compatibility preflight
+//!
+//! Acceptance criteria:
+//! - `tandem init` against an incompatible server explains each problem in
+//!   plain language and creates no local state
+//! - `tandem init` against an unreachable address creates no local state
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice51_incompatible_server_leaves_nothing_behind() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args_and_env(
+        &server_repo,
+        &addr,
+        &[],
+        &[
+            ("TANDEM_TEST_REPO_INFO_PROTOCOL_MAJOR", "9"),
+            ("TANDEM_TEST_REPO_INFO_CAPABILITIES", ""),
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("workspace");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--server", &addr, workspace.to_str().unwrap()],
+        &home,
+    );
+    assert!(!init.status.success());
+    let stderr = common::stderr_str(&init);
+    assert!(stderr.contains("speaks tandem protocol 9."), "{stderr}");
+    assert!(stderr.contains("live head notifications"), "{stderr}");
+    assert!(stderr.contains("nothing was created"), "{stderr}");
+    assert!(!workspace.exists(), "init must not create the workspace");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice51_unreachable_server_leaves_nothing_behind() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let addr = common::free_addr();
+
+    let workspace = tmp.path().join("workspace");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--server", &addr, workspace.to_str().unwrap()],
+        &home,
+    );
+    assert!(!init.status.success());
+    let stderr = common::stderr_str(&init);
+    assert!(stderr.contains("cannot reach a tandem server"), "{stderr}");
+    assert!(!workspace.exists(), "init must not create the workspace");
+}
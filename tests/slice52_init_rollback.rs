@@ -0,0 +1,101 @@
+//! Slice 52: roll back partially created workspaces when init fails
+//!
+//! Acceptance criteria:
+//! - When `tandem init` fails after creating `.jj`, it removes what it created:
+//!   the whole directory if init made it, otherwise only `.jj`
+//! - `--keep-partial` leaves the half-initialized state in place
+//! - A later init into the same path succeeds
+
+mod common;
+
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice52_failed_init_removes_partial_state() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--control-socket", sock_str], &home);
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    // Maintenance passes the preflight (repo info is a read) but rejects the
+    // operation init writes, so init fails after `.jj` exists.
+    let enter = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "server",
+            "maintenance",
+            "--for",
+            "10m",
+            "--control-socket",
+            sock_str,
+        ],
+        &home,
+    );
+    common::assert_ok(&enter, "enter maintenance");
+
+    let fresh = tmp.path().join("fresh");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--server", &addr, fresh.to_str().unwrap()],
+        &home,
+    );
+    assert!(!init.status.success());
+    let stderr = common::stderr_str(&init);
+    assert!(stderr.contains("removed partially initialized"), "{stderr}");
+    assert!(!fresh.exists(), "init created the directory, so it must go");
+
+    let existing = tmp.path().join("existing");
+    std::fs::create_dir_all(&existing).unwrap();
+    std::fs::write(existing.join("notes.txt"), b"keep me\n").unwrap();
+    let init = common::run_tandem_in(&existing, &["init", "--server", &addr, "."], &home);
+    assert!(!init.status.success());
+    assert!(!existing.join(".jj").exists());
+    assert!(
+        existing.join("notes.txt").exists(),
+        "user files must survive"
+    );
+
+    let kept = tmp.path().join("kept");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--keep-partial",
+            kept.to_str().unwrap(),
+        ],
+        &home,
+    );
+    assert!(!init.status.success());
+    assert!(common::stderr_str(&init).contains("--keep-partial"));
+    assert!(kept.join(".jj").exists(), "--keep-partial keeps .jj");
+
+    let leave = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "server",
+            "maintenance",
+            "--off",
+            "--control-socket",
+            sock_str,
+        ],
+        &home,
+    );
+    common::assert_ok(&leave, "leave maintenance");
+    let init = common::run_tandem_in(&existing, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init after rollback");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,108 @@
+//! Slice 53: tandem init --colocate
+//!
+//! Acceptance criteria:
+//! - `tandem serve --git-remote <url>` advertises a git mirror in repo info
+//! - `tandem init --colocate` creates `.git` with a `tandem` remote at that
+//!   URL and fetches the commits jj shows
+//! - Without a mirror (advertised or `--git-remote`), `--colocate` fails
+//!   before creating anything
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice53_colocate_fetches_server_mirror() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let mirror = server_repo.join(".git");
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--git-remote", mirror.to_str().unwrap()],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let agent_a = tmp.path().join("agent-a");
+    std::fs::create_dir_all(&agent_a).unwrap();
+    common::assert_ok(
+        &common::run_tandem_in(&agent_a, &["init", "--server", &addr, "."], &home),
+        "init agent-a",
+    );
+    std::fs::write(agent_a.join("hello.txt"), b"hello\n").unwrap();
+    common::assert_ok(
+        &common::run_tandem_in(&agent_a, &["new", "-m", "add hello"], &home),
+        "commit in agent-a",
+    );
+    let log = common::run_tandem_in(
+        &agent_a,
+        &["log", "--no-graph", "-r", "@-", "-T", "commit_id"],
+        &home,
+    );
+    common::assert_ok(&log, "log agent-a");
+    let commit_id = common::stdout_str(&log).trim().to_string();
+
+    let agent_b = tmp.path().join("agent-b");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--colocate",
+            agent_b.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init --colocate");
+    assert!(agent_b.join(".jj").is_dir());
+    assert!(agent_b.join(".git").is_dir());
+
+    let url = common::run_git_in(&agent_b, &["remote", "get-url", "tandem"]);
+    common::assert_ok(&url, "git remote get-url");
+    assert_eq!(common::stdout_str(&url).trim(), mirror.to_str().unwrap());
+
+    let has_commit = common::run_git_in(&agent_b, &["cat-file", "-e", &commit_id]);
+    common::assert_ok(&has_commit, "agent-a's commit is in the colocated .git");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice53_colocate_without_mirror_creates_nothing() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("workspace");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--colocate",
+            workspace.to_str().unwrap(),
+        ],
+        &home,
+    );
+    assert!(!init.status.success());
+    let stderr = common::stderr_str(&init);
+    assert!(stderr.contains("does not advertise one"), "{stderr}");
+    assert!(stderr.contains("--git-remote"), "{stderr}");
+    assert!(!workspace.exists(), "init must not create the workspace");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
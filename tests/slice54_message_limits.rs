@@ -0,0 +1,70 @@
+//! Slice 54: graceful handling of oversized Cap'n Proto messages
+//!
+//! Acceptance criteria:
+//! - A file larger than the message limit fails with a structured
+//!   "tandem message too large" error naming the object and the limit,
+//!   instead of capnp's traversal-limit error
+//! - The error says how to raise the limit (`TANDEM_MAX_MESSAGE_MB`)
+//! - The connection survives: the same workspace works once the limit fits
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice54_oversized_file_reports_too_large() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(&server_repo, &addr, &[], &home);
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("agent");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--server", &addr, workspace.to_str().unwrap()],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    // jj leaves new files over 1 MiB untracked by default.
+    let config = common::run_tandem_in(
+        &workspace,
+        &[
+            "config",
+            "set",
+            "--repo",
+            "snapshot.max-new-file-size",
+            "10MiB",
+        ],
+        &home,
+    );
+    common::assert_ok(&config, "raise snapshot.max-new-file-size");
+
+    std::fs::write(workspace.join("big.bin"), vec![0x5a; 2 * 1024 * 1024]).unwrap();
+
+    let limited = common::run_tandem_in_with_env(
+        &workspace,
+        &["describe", "-m", "big file"],
+        &[("TANDEM_MAX_MESSAGE_MB", "1")],
+        &home,
+    );
+    assert!(!limited.status.success());
+    let stderr = common::stderr_str(&limited);
+    assert!(stderr.contains("tandem message too large"), "{stderr}");
+    assert!(stderr.contains("file object is 2.0 MiB"), "{stderr}");
+    assert!(stderr.contains("TANDEM_MAX_MESSAGE_MB"), "{stderr}");
+    assert!(!stderr.contains("ReaderOptions"), "{stderr}");
+
+    let describe = common::run_tandem_in(&workspace, &["describe", "-m", "big file"], &home);
+    common::assert_ok(&describe, "describe under the default limit");
+
+    let show = common::run_tandem_in(&workspace, &["file", "show", "-r", "@", "big.bin"], &home);
+    common::assert_ok(&show, "file show");
+    assert_eq!(show.stdout.len(), 2 * 1024 * 1024);
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
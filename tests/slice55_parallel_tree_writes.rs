@@ -0,0 +1,92 @@
+//! Slice 55: parallel tree writes during snapshot
+//!
+//! Acceptance criteria:
+//! - A snapshot with many new directories keeps several tree writes in
+//!   flight, bounded by the in-flight limit, and every tree still lands on
+//!   the server under the id jj recorded
+//! - Another workspace sees the whole tree
+//! - `TANDEM_RPC_MAX_INFLIGHT=1` writes trees one at a time and yields the
+//!   same commit content
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn write_nested_tree(root: &Path) {
+    for a in 0..6 {
+        for b in 0..4 {
+            let dir = root.join(format!("pkg{a}")).join(format!("mod{b}"));
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("lib.rs"), format!("// {a}/{b}\n")).unwrap();
+        }
+        // A file sorting next to its directory in git order ("pkg0.md" < "pkg0/").
+        std::fs::write(root.join(format!("pkg{a}.md")), b"notes\n").unwrap();
+    }
+}
+
+fn file_list(dir: &Path, rev: &str, home: &Path) -> String {
+    let out = common::run_tandem_in(dir, &["file", "list", "-r", rev], home);
+    common::assert_ok(&out, "file list");
+    common::stdout_str(&out)
+}
+
+#[test]
+fn slice55_nested_snapshot_round_trips() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(&server_repo, &addr, &[], &home);
+    common::wait_for_server(&addr, &mut server);
+
+    let mut lists = Vec::new();
+    for (name, inflight) in [("parallel", "4"), ("serial", "1")] {
+        let workspace = tmp.path().join(name);
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &[
+                "init",
+                "--server",
+                &addr,
+                "--workspace",
+                name,
+                workspace.to_str().unwrap(),
+            ],
+            &home,
+        );
+        common::assert_ok(&init, "init");
+        write_nested_tree(&workspace);
+
+        let commit = common::run_tandem_in_with_env(
+            &workspace,
+            &["commit", "-m", &format!("nested tree ({name})")],
+            &[("TANDEM_RPC_MAX_INFLIGHT", inflight)],
+            &home,
+        );
+        common::assert_ok(&commit, "commit nested tree");
+        lists.push(file_list(&workspace, "@-", &home));
+    }
+
+    assert_eq!(lists[0], lists[1]);
+    assert_eq!(lists[0].lines().count(), 6 * 4 + 6, "{}", lists[0]);
+    assert!(lists[0].contains("pkg5/mod3/lib.rs"), "{}", lists[0]);
+
+    // The serial workspace reads the parallel one's trees from the server.
+    let serial = tmp.path().join("serial");
+    let rev = "description(substring:\"(parallel)\")";
+    assert_eq!(file_list(&serial, rev, &home), lists[0]);
+    let show = common::run_tandem_in(
+        &serial,
+        &["file", "show", "-r", rev, "pkg2/mod1/lib.rs"],
+        &home,
+    );
+    common::assert_ok(&show, "file show");
+    assert_eq!(common::stdout_str(&show), "// 2/1\n");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,68 @@
+//! Slice 56: getHeadsVersion for polling clients
+//!
+//! Acceptance criteria:
+//! - Servers advertise `headsVersion` and answer `getHeadsVersion` with just
+//!   the version
+//! - Clients that only need the version (the op-heads CAS path) use it
+//!   instead of a full `getHeads`
+//! - Against a server without the capability, clients fall back to `getHeads`
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn commit_with_timing(tmp: &Path, server_env: &[(&str, &str)], name: &str) -> String {
+    let home = common::isolated_home(&tmp.join(name));
+    let server_repo = tmp.join(name).join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server =
+        common::spawn_server_with_args_and_env(&server_repo, &addr, &[], server_env, &home);
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.join(name).join("agent");
+    let init = common::run_tandem_in(
+        tmp,
+        &["init", "--server", &addr, workspace.to_str().unwrap()],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+
+    std::fs::write(workspace.join("a.txt"), b"a\n").unwrap();
+    let commit = common::run_tandem_in_with_env(
+        &workspace,
+        &["--config", "tandem.timing=true", "commit", "-m", "a"],
+        &[("TANDEM_BENCH_DISABLE_OPTIMISTIC_OP_HEAD_VERSION_CACHE", "1")],
+        &home,
+    );
+    common::assert_ok(&commit, "commit");
+
+    let _ = server.kill();
+    let _ = server.wait();
+    common::stderr_str(&commit)
+}
+
+#[test]
+fn slice56_cas_path_uses_heads_version() {
+    let tmp = TempDir::new().unwrap();
+    let stderr = commit_with_timing(tmp.path(), &[], "current");
+    assert!(stderr.contains("tandem: getHeadsVersion"), "{stderr}");
+}
+
+#[test]
+fn slice56_falls_back_without_capability() {
+    let tmp = TempDir::new().unwrap();
+    let stderr = commit_with_timing(
+        tmp.path(),
+        &[(
+            "TANDEM_TEST_REPO_INFO_CAPABILITIES",
+            "watchHeads,operationTags,headsHistory",
+        )],
+        "old",
+    );
+    assert!(!stderr.contains("tandem: getHeadsVersion"), "{stderr}");
+    assert!(stderr.contains("tandem: getHeads "), "{stderr}");
+}
@@ -0,0 +1,151 @@
+//! Slice 57: tandem ide-server (JSON-RPC over stdio)
+//!
+//! Acceptance criteria:
+//! - `heads` returns the heads version, head operation ids, and each
+//!   workspace's head operation
+//! - `presence` returns one entry per workspace, like `tandem ps`
+//! - `whoTouched` names the other workspaces whose working-copy change
+//!   modifies a path, and leaves out `--workspace`
+//! - `subscribe` streams `headsChanged` notifications on the same stdout
+//! - Unknown methods get a JSON-RPC error; `shutdown` ends the process
+
+mod common;
+
+use std::io::{BufRead, BufReader, Write};
+use std::process::{ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+use tempfile::TempDir;
+
+struct Ide {
+    stdin: ChildStdin,
+    lines: Receiver<Value>,
+}
+
+impl Ide {
+    fn call(&mut self, id: u64, method: &str, params: Value) -> Value {
+        let request = json!({"jsonrpc": "2.0", "id": id, "method": method, "params": params});
+        writeln!(self.stdin, "{request}").unwrap();
+        self.stdin.flush().unwrap();
+        loop {
+            let message = self.next();
+            if message["id"] == json!(id) {
+                return message;
+            }
+        }
+    }
+
+    fn next(&self) -> Value {
+        self.lines
+            .recv_timeout(Duration::from_secs(10))
+            .expect("ide-server message")
+    }
+}
+
+#[test]
+fn slice57_ide_server_answers_editor_queries() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(&server_repo, &addr, &[], &home);
+    common::wait_for_server(&addr, &mut server);
+
+    for name in ["agent-a", "agent-b"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &[
+                "init",
+                "--server",
+                &addr,
+                "--workspace",
+                name,
+                tmp.path().join(name).to_str().unwrap(),
+            ],
+            &home,
+        );
+        common::assert_ok(&init, "init");
+    }
+    let agent_a = tmp.path().join("agent-a");
+    let agent_b = tmp.path().join("agent-b");
+    std::fs::write(agent_b.join("shared.txt"), b"from b\n").unwrap();
+    let describe = common::run_tandem_in(&agent_b, &["describe", "-m", "b edits shared"], &home);
+    common::assert_ok(&describe, "describe in agent-b");
+    std::fs::write(agent_a.join("shared.txt"), b"from a\n").unwrap();
+    let describe = common::run_tandem_in(&agent_a, &["describe", "-m", "a edits shared"], &home);
+    common::assert_ok(&describe, "describe in agent-a");
+
+    let mut child = Command::new(common::tandem_bin())
+        .args(["ide-server", "--server", &addr, "--workspace", "agent-a"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn tandem ide-server");
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = channel();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            let Ok(line) = line else { break };
+            let _ = tx.send(serde_json::from_str::<Value>(&line).expect("JSON line"));
+        }
+    });
+    let mut ide = Ide {
+        stdin: child.stdin.take().unwrap(),
+        lines: rx,
+    };
+
+    let heads = ide.call(1, "heads", Value::Null);
+    let result = &heads["result"];
+    assert!(result["version"].as_u64().unwrap() > 0, "{heads}");
+    assert!(!result["heads"].as_array().unwrap().is_empty(), "{heads}");
+    assert!(result["workspaces"]["agent-a"].is_string(), "{heads}");
+    assert!(result["workspaces"]["agent-b"].is_string(), "{heads}");
+
+    let presence = ide.call(2, "presence", Value::Null);
+    let rows = presence["result"].as_array().expect("presence rows");
+    let b_row = rows
+        .iter()
+        .find(|row| row["workspace"] == "agent-b")
+        .expect("agent-b row");
+    assert_eq!(b_row["description"], "b edits shared", "{presence}");
+
+    let touched = ide.call(3, "whoTouched", json!({"path": "shared.txt"}));
+    let touched = touched["result"].as_array().expect("whoTouched result");
+    assert_eq!(touched.len(), 1, "{touched:?}");
+    assert_eq!(touched[0]["workspace"], "agent-b");
+    assert_eq!(touched[0]["description"], "b edits shared");
+
+    let untouched = ide.call(4, "whoTouched", json!({"path": "missing/file.txt"}));
+    assert_eq!(untouched["result"], json!([]), "{untouched}");
+
+    let unknown = ide.call(5, "frobnicate", Value::Null);
+    assert_eq!(unknown["error"]["code"], -32601, "{unknown}");
+
+    let subscribe = ide.call(6, "subscribe", Value::Null);
+    assert_eq!(subscribe["result"]["subscribed"], true, "{subscribe}");
+    let current = ide.next();
+    assert_eq!(current["method"], "headsChanged", "{current}");
+    let before = current["params"]["version"].as_u64().unwrap();
+
+    let new = common::run_tandem_in(&agent_b, &["new", "-m", "next"], &home);
+    common::assert_ok(&new, "new in agent-b");
+    let changed = ide.next();
+    assert_eq!(changed["method"], "headsChanged", "{changed}");
+    assert!(
+        changed["params"]["version"].as_u64().unwrap() > before,
+        "{changed}"
+    );
+
+    let shutdown = ide.call(7, "shutdown", Value::Null);
+    assert_eq!(shutdown["result"], Value::Null, "{shutdown}");
+    let status = child.wait().unwrap();
+    assert!(status.success());
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
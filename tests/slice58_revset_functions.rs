@@ -0,0 +1,104 @@
+//! Slice 58: tandem revset functions
+//!
+//! Acceptance criteria:
+//! - `tandem_workspace(name)` resolves to that workspace's working-copy
+//!   commit, quoted or not, and composes with other revsets
+//! - `tandem_integration()` resolves to the integration commit when the
+//!   server runs the integration workspace
+
+mod common;
+
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn log(dir: &Path, revset: &str, template: &str, home: &Path) -> std::process::Output {
+    common::run_tandem_in(
+        dir,
+        &["log", "--no-graph", "-r", revset, "-T", template],
+        home,
+    )
+}
+
+#[test]
+fn slice58_revset_functions_resolve_workspaces() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--enable-integration-workspace"],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    for name in ["agent-a", "agent-b"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &[
+                "init",
+                "--server",
+                &addr,
+                "--workspace",
+                name,
+                tmp.path().join(name).to_str().unwrap(),
+            ],
+            &home,
+        );
+        common::assert_ok(&init, "init");
+    }
+    let agent_a = tmp.path().join("agent-a");
+    let agent_b = tmp.path().join("agent-b");
+
+    std::fs::write(agent_b.join("b.txt"), b"b\n").unwrap();
+    let commit = common::run_tandem_in(&agent_b, &["commit", "-m", "b first"], &home);
+    common::assert_ok(&commit, "commit in agent-b");
+    let describe = common::run_tandem_in(&agent_b, &["describe", "-m", "b second"], &home);
+    common::assert_ok(&describe, "describe in agent-b");
+
+    for revset in ["tandem_workspace(agent-b)", "tandem_workspace(\"agent-b\")"] {
+        let out = log(&agent_a, revset, "description", &home);
+        common::assert_ok(&out, revset);
+        assert_eq!(common::stdout_str(&out).trim(), "b second", "{revset}");
+    }
+
+    let range = log(
+        &agent_a,
+        "tandem_workspace(agent-a)..tandem_workspace(agent-b)",
+        "description.first_line() ++ \"\\n\"",
+        &home,
+    );
+    common::assert_ok(&range, "range from agent-a to agent-b");
+    assert_eq!(common::stdout_str(&range), "b second\nb first\n");
+
+    let unknown = log(&agent_a, "tandem_workspace(agent-z)", "description", &home);
+    assert!(!unknown.status.success());
+
+    let deadline = Instant::now() + Duration::from_secs(15);
+    loop {
+        let integration = log(&agent_a, "tandem_integration()", "commit_id", &home);
+        let bookmark = log(&agent_a, "integration", "commit_id", &home);
+        if integration.status.success() {
+            assert_eq!(
+                common::stdout_str(&integration),
+                common::stdout_str(&bookmark)
+            );
+            break;
+        }
+        assert!(
+            Instant::now() < deadline,
+            "tandem_integration() never resolved: {}",
+            common::stderr_str(&integration)
+        );
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
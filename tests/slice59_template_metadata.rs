@@ -0,0 +1,92 @@
+//! Slice 59: tandem_version and tandem_presence template keywords
+//!
+//! Acceptance criteria:
+//! - `tandem_version` renders the heads version at which a commit's operation
+//!   became a head, and later commits land at later versions
+//! - `tandem_presence` is true for commits from a workspace that published
+//!   recently
+//! - Commits with no tandem attribution render "" and false
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn render(dir: &Path, revset: &str, home: &Path) -> String {
+    let out = common::run_tandem_in(
+        dir,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            revset,
+            "-T",
+            "tandem_workspace ++ \"|\" ++ tandem_version ++ \"|\" ++ \
+             if(tandem_presence, \"online\", \"away\") ++ \"\\n\"",
+        ],
+        home,
+    );
+    common::assert_ok(&out, &format!("log -r {revset}"));
+    common::stdout_str(&out)
+}
+
+#[test]
+fn slice59_commits_show_landing_version_and_presence() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    for name in ["agent-a", "agent-b"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &[
+                "init",
+                "--server",
+                &addr,
+                "--workspace",
+                name,
+                tmp.path().join(name).to_str().unwrap(),
+            ],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+    let agent_a = tmp.path().join("agent-a");
+    let agent_b = tmp.path().join("agent-b");
+
+    std::fs::write(agent_b.join("b.txt"), b"b\n").unwrap();
+    let commit = common::run_tandem_in(&agent_b, &["commit", "-m", "from-b"], &home);
+    common::assert_ok(&commit, "agent-b commit");
+    std::fs::write(agent_a.join("a.txt"), b"a\n").unwrap();
+    let commit = common::run_tandem_in(&agent_a, &["commit", "-m", "from-a"], &home);
+    common::assert_ok(&commit, "agent-a commit");
+
+    let parse = |line: &str| -> (String, u64, String) {
+        let fields: Vec<&str> = line.trim().split('|').collect();
+        assert_eq!(fields.len(), 3, "{line}");
+        let version = fields[1]
+            .parse()
+            .unwrap_or_else(|_| panic!("version in {line:?}"));
+        (fields[0].to_string(), version, fields[2].to_string())
+    };
+    let (b_workspace, b_version, b_presence) =
+        parse(&render(&agent_a, "description(substring:from-b)", &home));
+    let (a_workspace, a_version, a_presence) =
+        parse(&render(&agent_a, "description(substring:from-a)", &home));
+    assert_eq!(b_workspace, "agent-b");
+    assert_eq!(a_workspace, "agent-a");
+    assert!(a_version > b_version, "{a_version} <= {b_version}");
+    assert_eq!(b_presence, "online");
+    assert_eq!(a_presence, "online");
+
+    assert_eq!(render(&agent_a, "root()", &home), "||away\n");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,81 @@
+//! Slice 60: tandem op integrate-all
+//!
+//! Acceptance criteria:
+//! - A workspace whose operation is reachable from the server's heads has
+//!   nothing to integrate
+//! - A working copy left on an operation the heads no longer reach (the
+//!   state `jj op integrate <id>` hints about) is found and integrated,
+//!   printing the operation
+//! - `--dry-run` reports the same plan without changing anything
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice60_integrates_the_working_copy_operation() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(&server_repo, &addr, &[], &home);
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("agent");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--server", &addr, workspace.to_str().unwrap()],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    for message in ["one", "two"] {
+        let describe = common::run_tandem_in(&workspace, &["describe", "-m", message], &home);
+        common::assert_ok(&describe, "describe");
+    }
+
+    let clean = common::run_tandem_in(&workspace, &["op", "integrate-all"], &home);
+    common::assert_ok(&clean, "integrate-all on a clean workspace");
+    assert_eq!(common::stdout_str(&clean).trim(), "nothing to integrate");
+
+    // Abandoning the previous operation rewrites the head; putting the old
+    // checkout back leaves the working copy on an operation no head reaches.
+    let checkout_path = workspace.join(".jj/working_copy/checkout");
+    let checkout = std::fs::read(&checkout_path).unwrap();
+    let abandon = common::run_tandem_in(&workspace, &["op", "abandon", "@-"], &home);
+    common::assert_ok(&abandon, "op abandon");
+    std::fs::write(&checkout_path, &checkout).unwrap();
+
+    let dry_run = common::run_tandem_in(&workspace, &["op", "integrate-all", "--dry-run"], &home);
+    common::assert_ok(&dry_run, "integrate-all --dry-run");
+    let plan = common::stdout_str(&dry_run);
+    assert!(
+        plan.contains("integrating working-copy operation"),
+        "{plan}"
+    );
+    assert!(plan.contains("dry run"), "{plan}");
+
+    let integrate = common::run_tandem_in(&workspace, &["operation", "integrate-all"], &home);
+    common::assert_ok(&integrate, "integrate-all");
+    let done = common::stdout_str(&integrate);
+    assert!(
+        done.contains("integrating working-copy operation"),
+        "{done}"
+    );
+
+    let again = common::run_tandem_in(&workspace, &["op", "integrate-all"], &home);
+    common::assert_ok(&again, "integrate-all after integrating");
+    assert_eq!(common::stdout_str(&again).trim(), "nothing to integrate");
+
+    let log = common::run_tandem_in(
+        &workspace,
+        &["log", "--no-graph", "-r", "@", "-T", "description"],
+        &home,
+    );
+    common::assert_ok(&log, "log after integrating");
+    assert_eq!(common::stdout_str(&log).trim(), "two");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
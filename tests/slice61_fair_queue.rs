@@ -0,0 +1,122 @@
+//! Slice 61: fair queuing of head updates
+//!
+//! Acceptance criteria:
+//! - `tandem serve --fair-queue` accepts concurrent commits from several
+//!   workspaces, and every one of them lands
+//! - `--fair-queue-wait` requires `--fair-queue`
+
+mod common;
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use tempfile::TempDir;
+
+const COMMITS_PER_WORKSPACE: usize = 4;
+
+#[test]
+fn slice61_contending_workspaces_all_land() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--fair-queue", "--fair-queue-wait", "5s"],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let names = ["agent-a", "agent-b", "agent-c"];
+    let workspaces: Vec<PathBuf> = names.iter().map(|name| tmp.path().join(name)).collect();
+    for (name, workspace) in names.iter().zip(&workspaces) {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &[
+                "init",
+                "--server",
+                &addr,
+                "--workspace",
+                name,
+                workspace.to_str().unwrap(),
+            ],
+            &home,
+        );
+        common::assert_ok(&init, "init");
+    }
+
+    let writers: Vec<_> = names
+        .iter()
+        .zip(workspaces.clone())
+        .map(|(name, workspace)| {
+            let home = home.clone();
+            let name = name.to_string();
+            std::thread::spawn(move || {
+                for i in 0..COMMITS_PER_WORKSPACE {
+                    std::fs::write(workspace.join(format!("{name}-{i}.txt")), b"x\n").unwrap();
+                    let commit = common::run_tandem_in(
+                        &workspace,
+                        &["commit", "-m", &format!("{name} commit {i}")],
+                        &home,
+                    );
+                    common::assert_ok(&commit, &format!("{name} commit {i}"));
+                }
+            })
+        })
+        .collect();
+    for writer in writers {
+        writer.join().expect("writer thread");
+    }
+
+    let log = common::run_tandem_in(
+        &workspaces[0],
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "description(substring:\"commit\")",
+            "-T",
+            "description",
+        ],
+        &home,
+    );
+    common::assert_ok(&log, "log");
+    let stdout = common::stdout_str(&log);
+    for name in names {
+        for i in 0..COMMITS_PER_WORKSPACE {
+            assert!(
+                stdout.contains(&format!("{name} commit {i}")),
+                "missing {name} commit {i}:\n{stdout}"
+            );
+        }
+    }
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice61_wait_requires_fair_queue() {
+    let tmp = TempDir::new().unwrap();
+    let out = Command::new(common::tandem_bin())
+        .args([
+            "serve",
+            "--listen",
+            &common::free_addr(),
+            "--repo",
+            tmp.path().to_str().unwrap(),
+            "--fair-queue-wait",
+            "5s",
+        ])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(
+        common::stderr_str(&out).contains("--fair-queue"),
+        "{}",
+        common::stderr_str(&out)
+    );
+}
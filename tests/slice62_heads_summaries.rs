@@ -0,0 +1,131 @@
+//! Slice 62: heads change summaries for watchers
+//!
+//! Acceptance criteria:
+//! - `tandem watch --summary` prints each change's description, author and
+//!   workspace, the bookmarks it moved, and the paths its commits touched
+//! - `--summary-paths` caps the touched paths, marking the list as cut short
+//! - Watchers that do not ask for summaries get the plain one-line events
+//! - `--summary-paths` requires `--summary`
+
+mod common;
+
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+fn spawn_watch(addr: &str, extra: &[&str]) -> Child {
+    Command::new(common::tandem_bin())
+        .args(["watch", "--server", addr])
+        .args(extra)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn tandem watch")
+}
+
+fn stop_watch(mut watch_proc: Child) -> String {
+    let _ = watch_proc.kill();
+    let output = watch_proc
+        .wait_with_output()
+        .expect("wait for watch process");
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    eprintln!(
+        "watch stdout:\n{stdout}\nstderr:\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    stdout
+}
+
+#[test]
+fn slice62_watch_summary_describes_each_change() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("agent-a");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            workspace.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+
+    let summarized = spawn_watch(&addr, &["--summary", "--summary-paths", "1"]);
+    let plain = spawn_watch(&addr, &[]);
+    std::thread::sleep(Duration::from_millis(1000));
+
+    std::fs::write(workspace.join("a.txt"), b"a\n").unwrap();
+    std::fs::write(workspace.join("b.txt"), b"b\n").unwrap();
+    let commit = common::run_tandem_in(&workspace, &["commit", "-m", "two files"], &home);
+    common::assert_ok(&commit, "commit");
+    let bookmark = common::run_tandem_in(
+        &workspace,
+        &["bookmark", "create", "feature", "-r", "@-"],
+        &home,
+    );
+    common::assert_ok(&bookmark, "bookmark create");
+    std::thread::sleep(Duration::from_millis(1000));
+
+    let stdout = stop_watch(summarized);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert!(
+        lines.iter().any(|l| l.starts_with("  description: ")),
+        "{stdout}"
+    );
+    assert!(
+        lines.iter().any(|l| l.starts_with("  author: ")),
+        "{stdout}"
+    );
+    assert!(lines.contains(&"  workspace: agent-a"), "{stdout}");
+    assert!(lines.contains(&"  bookmarks: feature"), "{stdout}");
+    assert!(
+        lines.contains(&"  paths: a.txt ..."),
+        "one path, marked as cut short: {stdout}"
+    );
+
+    let stdout = stop_watch(plain);
+    assert!(
+        stdout.lines().any(|l| l.starts_with("version=")),
+        "{stdout}"
+    );
+    assert!(
+        stdout.lines().all(|l| l.starts_with("version=")),
+        "plain watchers get no summaries: {stdout}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice62_summary_paths_requires_summary() {
+    let out = Command::new(common::tandem_bin())
+        .args([
+            "watch",
+            "--server",
+            &common::free_addr(),
+            "--summary-paths",
+            "5",
+        ])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(
+        common::stderr_str(&out).contains("--summary"),
+        "{}",
+        common::stderr_str(&out)
+    );
+}
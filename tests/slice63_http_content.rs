@@ -0,0 +1,124 @@
+//! Slice 63: read-only file content over plain HTTP
+//!
+//! Acceptance criteria:
+//! - `tandem serve --http-listen` serves `/raw/<rev>/<path>` by commit id and
+//!   by bookmark name, with the file's exact bytes
+//! - Missing paths, directories and unknown revisions are 404
+//! - `/archive/<rev>.tar` holds the tree's files; `.tar.gz` is gzip-framed
+//! - Nothing else is served
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+/// GET `path` and return the status code and body.
+fn http_get(addr: &str, path: &str) -> (u16, Vec<u8>) {
+    let mut stream = None;
+    for _ in 0..50 {
+        match TcpStream::connect(addr) {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(_) => std::thread::sleep(Duration::from_millis(100)),
+        }
+    }
+    let mut stream = stream.expect("connect to http endpoint");
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .expect("end of headers");
+    let head = String::from_utf8_lossy(&response[..split]).into_owned();
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .expect("status code");
+    (status, response[split + 4..].to_vec())
+}
+
+#[test]
+fn slice63_serves_raw_files_and_archives() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let http_addr = common::free_addr();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--http-listen", &http_addr], &home);
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("agent");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--server", &addr, workspace.to_str().unwrap()],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    std::fs::create_dir_all(workspace.join("src")).unwrap();
+    std::fs::write(
+        workspace.join("src/lib.rs"),
+        b"pub fn answer() -> u32 { 42 }\n",
+    )
+    .unwrap();
+    std::fs::write(workspace.join("README.md"), b"# demo\n").unwrap();
+    let commit = common::run_tandem_in(&workspace, &["commit", "-m", "add files"], &home);
+    common::assert_ok(&commit, "commit");
+    let bookmark = common::run_tandem_in(
+        &workspace,
+        &["bookmark", "create", "main", "-r", "@-"],
+        &home,
+    );
+    common::assert_ok(&bookmark, "bookmark create");
+    let log = common::run_tandem_in(
+        &workspace,
+        &["log", "--no-graph", "-r", "@-", "-T", "commit_id"],
+        &home,
+    );
+    common::assert_ok(&log, "log");
+    let commit_id = common::stdout_str(&log).trim().to_string();
+
+    let (status, body) = http_get(&http_addr, &format!("/raw/{commit_id}/src/lib.rs"));
+    assert_eq!(status, 200);
+    assert_eq!(body, b"pub fn answer() -> u32 { 42 }\n");
+    let (status, body) = http_get(&http_addr, "/raw/main/README.md");
+    assert_eq!(status, 200, "{}", String::from_utf8_lossy(&body));
+    assert_eq!(body, b"# demo\n");
+
+    for missing in [
+        "/raw/main/nope.txt".to_string(),
+        "/raw/main/src".to_string(),
+        "/raw/no-such-bookmark/README.md".to_string(),
+        "/archive/no-such-bookmark.tar".to_string(),
+        "/healthz".to_string(),
+    ] {
+        let (status, _) = http_get(&http_addr, &missing);
+        assert_eq!(status, 404, "{missing}");
+    }
+
+    let (status, tar) = http_get(&http_addr, &format!("/archive/{commit_id}.tar"));
+    assert_eq!(status, 200);
+    assert_eq!(tar.len() % 512, 0);
+    let find = |needle: &[u8]| tar.windows(needle.len()).any(|w| w == needle);
+    assert!(find(b"src/lib.rs\0"));
+    assert!(find(b"pub fn answer() -> u32 { 42 }\n"));
+    assert!(find(b"README.md\0"));
+
+    let (status, gz) = http_get(&http_addr, "/archive/main.tar.gz");
+    assert_eq!(status, 200);
+    assert_eq!(&gz[..2], &[0x1f, 0x8b]);
+    let size = u32::from_le_bytes(gz[gz.len() - 4..].try_into().unwrap());
+    assert_eq!(size as usize, tar.len(), "same tree as the plain archive");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,152 @@
+//! Slice 64: tandem archive
+//!
+//! Acceptance criteria:
+//! - `tandem archive -r <bookmark> -o out.tar` writes the revision's files
+//!   without a workspace, and `.tar.gz` output is gzip-framed
+//! - `-r <workspace>@` exports that workspace's working-copy commit
+//! - Unknown revisions and output names without a known extension fail
+
+mod common;
+
+use std::process::Command;
+
+use tempfile::TempDir;
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|w| w == needle)
+}
+
+#[test]
+fn slice64_archives_a_revision_without_a_workspace() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("agent-a");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            workspace.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    std::fs::create_dir_all(workspace.join("src")).unwrap();
+    std::fs::write(workspace.join("src/main.rs"), b"fn main() {}\n").unwrap();
+    let commit = common::run_tandem_in(&workspace, &["commit", "-m", "add main"], &home);
+    common::assert_ok(&commit, "commit");
+    let bookmark = common::run_tandem_in(
+        &workspace,
+        &["bookmark", "create", "release", "-r", "@-"],
+        &home,
+    );
+    common::assert_ok(&bookmark, "bookmark create");
+    std::fs::write(workspace.join("wip.txt"), b"not committed yet\n").unwrap();
+    let snapshot = common::run_tandem_in(&workspace, &["status"], &home);
+    common::assert_ok(&snapshot, "status");
+
+    let out_dir = tmp.path().join("ci");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let archive = common::run_tandem_in(
+        &out_dir,
+        &[
+            "archive",
+            "--server",
+            &addr,
+            "-r",
+            "release",
+            "-o",
+            "release.tar",
+        ],
+        &home,
+    );
+    common::assert_ok(&archive, "archive release");
+    assert!(
+        common::stdout_str(&archive).contains("1 file(s)"),
+        "{}",
+        common::stdout_str(&archive)
+    );
+    let tar = std::fs::read(out_dir.join("release.tar")).unwrap();
+    assert_eq!(tar.len() % 512, 0);
+    assert!(contains(&tar, b"src/main.rs\0"));
+    assert!(contains(&tar, b"fn main() {}\n"));
+    assert!(!contains(&tar, b"wip.txt\0"));
+
+    let archive = common::run_tandem_in(
+        &out_dir,
+        &[
+            "archive",
+            "--server",
+            &addr,
+            "-r",
+            "release",
+            "-o",
+            "release.tar.gz",
+        ],
+        &home,
+    );
+    common::assert_ok(&archive, "archive release as tar.gz");
+    let gz = std::fs::read(out_dir.join("release.tar.gz")).unwrap();
+    assert_eq!(&gz[..2], &[0x1f, 0x8b]);
+    let size = u32::from_le_bytes(gz[gz.len() - 4..].try_into().unwrap());
+    assert_eq!(size as usize, tar.len(), "same tree as the plain archive");
+
+    let archive = common::run_tandem_in(
+        &out_dir,
+        &[
+            "archive", "--server", &addr, "-r", "agent-a@", "-o", "wc.tar",
+        ],
+        &home,
+    );
+    common::assert_ok(&archive, "archive working copy");
+    let tar = std::fs::read(out_dir.join("wc.tar")).unwrap();
+    assert!(contains(&tar, b"wip.txt\0"));
+    assert!(contains(&tar, b"not committed yet\n"));
+
+    let missing = common::run_tandem_in(
+        &out_dir,
+        &["archive", "--server", &addr, "-r", "nope", "-o", "x.tar"],
+        &home,
+    );
+    assert!(!missing.status.success());
+    assert!(
+        common::stderr_str(&missing).contains("no commit or bookmark nope"),
+        "{}",
+        common::stderr_str(&missing)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice64_rejects_unknown_output_format() {
+    let out = Command::new(common::tandem_bin())
+        .args([
+            "archive",
+            "--server",
+            &common::free_addr(),
+            "-r",
+            "main",
+            "-o",
+            "out.zip",
+        ])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(
+        common::stderr_str(&out).contains(".tar.gz"),
+        "{}",
+        common::stderr_str(&out)
+    );
+}
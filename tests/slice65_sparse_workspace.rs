@@ -0,0 +1,132 @@
+//! Slice 65: sparse workspaces
+//!
+//! Acceptance criteria:
+//! - `tandem init --sparse <path>` sets jj's sparse patterns, so checking out
+//!   a revision writes only the files under those paths
+//! - The paths are saved as `tandem.prefetch-paths` in the repo config
+//! - Commits from the sparse workspace keep the files it does not check out
+//! - `--sparse` rejects paths that are not plain repo paths
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice65_sparse_init_checks_out_only_matching_paths() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let full = tmp.path().join("full");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "full",
+            full.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init full");
+    for (path, content) in [
+        ("services/api/main.rs", "fn main() {}\n"),
+        ("services/web/index.js", "console.log('hi')\n"),
+        ("README.md", "# monorepo\n"),
+    ] {
+        let path = full.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+    let commit = common::run_tandem_in(&full, &["commit", "-m", "monorepo"], &home);
+    common::assert_ok(&commit, "commit");
+    let bookmark = common::run_tandem_in(&full, &["bookmark", "create", "main", "-r", "@-"], &home);
+    common::assert_ok(&bookmark, "bookmark create");
+
+    let sparse = tmp.path().join("api-agent");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "api-agent",
+            "--sparse",
+            "./services/api/",
+            sparse.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init sparse");
+
+    let list = common::run_tandem_in(&sparse, &["sparse", "list"], &home);
+    common::assert_ok(&list, "sparse list");
+    assert_eq!(common::stdout_str(&list).trim(), "services/api");
+    let config = common::run_tandem_in(&sparse, &["config", "get", "tandem.prefetch-paths"], &home);
+    common::assert_ok(&config, "config get");
+    assert!(
+        common::stdout_str(&config).contains("services/api"),
+        "{}",
+        common::stdout_str(&config)
+    );
+
+    let new = common::run_tandem_in(&sparse, &["new", "main"], &home);
+    common::assert_ok(&new, "new main");
+    assert_eq!(
+        std::fs::read_to_string(sparse.join("services/api/main.rs")).unwrap(),
+        "fn main() {}\n"
+    );
+    assert!(!sparse.join("services/web").exists());
+    assert!(!sparse.join("README.md").exists());
+
+    std::fs::write(
+        sparse.join("services/api/main.rs"),
+        "fn main() { serve() }\n",
+    )
+    .unwrap();
+    let commit = common::run_tandem_in(&sparse, &["commit", "-m", "api change"], &home);
+    common::assert_ok(&commit, "commit in sparse workspace");
+    let files = common::run_tandem_in(&sparse, &["file", "list", "-r", "@-"], &home);
+    common::assert_ok(&files, "file list");
+    let files = common::stdout_str(&files);
+    for path in ["services/api/main.rs", "services/web/index.js", "README.md"] {
+        assert!(files.contains(path), "{path} missing from:\n{files}");
+    }
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice65_sparse_rejects_parent_paths() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let target = tmp.path().join("ws");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &common::free_addr(),
+            "--sparse",
+            "../outside",
+            target.to_str().unwrap(),
+        ],
+        &home,
+    );
+    assert!(!init.status.success());
+    assert!(
+        common::stderr_str(&init).contains("not a plain repo path"),
+        "{}",
+        common::stderr_str(&init)
+    );
+    assert!(!target.exists(), "nothing is created for a bad --sparse");
+}
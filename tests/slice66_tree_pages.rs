@@ -0,0 +1,87 @@
+//! Slice 66: paged tree reads
+//!
+//! Acceptance criteria:
+//! - A directory with more entries than one `getTreeEntries` page is written
+//!   from one workspace and checked out whole in another
+//! - Listing the revision's files sees every entry, in order
+
+mod common;
+
+use tempfile::TempDir;
+
+/// More than the server's default page of 2000 entries.
+const FILES: usize = 2_500;
+
+#[test]
+fn slice66_huge_directory_round_trips_in_pages() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let writer = tmp.path().join("writer");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "writer",
+            writer.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init writer");
+    let vendor = writer.join("vendor");
+    std::fs::create_dir_all(&vendor).unwrap();
+    for i in 0..FILES {
+        std::fs::write(vendor.join(format!("dep-{i:05}.txt")), format!("{i}\n")).unwrap();
+    }
+    let commit = common::run_tandem_in(&writer, &["commit", "-m", "vendor"], &home);
+    common::assert_ok(&commit, "commit");
+    let bookmark =
+        common::run_tandem_in(&writer, &["bookmark", "create", "main", "-r", "@-"], &home);
+    common::assert_ok(&bookmark, "bookmark create");
+
+    let reader = tmp.path().join("reader");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "reader",
+            reader.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init reader");
+    let new = common::run_tandem_in(&reader, &["new", "main"], &home);
+    common::assert_ok(&new, "new main");
+    let checked_out = std::fs::read_dir(reader.join("vendor")).unwrap().count();
+    assert_eq!(checked_out, FILES);
+    assert_eq!(
+        std::fs::read_to_string(reader.join("vendor/dep-02499.txt")).unwrap(),
+        "2499\n"
+    );
+
+    let files = common::run_tandem_in(&reader, &["file", "list", "-r", "main"], &home);
+    common::assert_ok(&files, "file list");
+    let listed: Vec<String> = common::stdout_str(&files)
+        .lines()
+        .map(str::to_string)
+        .collect();
+    let expected: Vec<String> = (0..FILES)
+        .map(|i| format!("vendor/dep-{i:05}.txt"))
+        .collect();
+    assert_eq!(listed, expected);
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
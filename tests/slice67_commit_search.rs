@@ -0,0 +1,116 @@
+//! Slice 67: server-side commit search
+//!
+//! Acceptance criteria:
+//! - `tandem search <query>` lists visible commits whose description contains
+//!   the query, ignoring case, newest first
+//! - `--author` narrows to commits whose author name or email matches
+//! - `--limit` caps the hits and `--json` reports when more matched
+//! - A search needs a query or `--author`
+
+mod common;
+
+use std::process::Command;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice67_search_finds_commits_by_description_and_author() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let workspace = tmp.path().join("agent-a");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            workspace.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    for (i, message) in ["Fix flaky test in CI", "add parser", "fix flaky test again"]
+        .iter()
+        .enumerate()
+    {
+        std::fs::write(workspace.join(format!("f{i}.txt")), message).unwrap();
+        let commit = common::run_tandem_in(&workspace, &["commit", "-m", message], &home);
+        common::assert_ok(&commit, "commit");
+    }
+    let describe = common::run_tandem_in(
+        &workspace,
+        &[
+            "describe",
+            "-m",
+            "fix flaky test by bob",
+            "--author",
+            "Bob <bob@example.com>",
+        ],
+        &home,
+    );
+    common::assert_ok(&describe, "describe as bob");
+
+    let search = common::run_tandem_in(
+        tmp.path(),
+        &["search", "--server", &addr, "FIX FLAKY TEST"],
+        &home,
+    );
+    common::assert_ok(&search, "search");
+    let stdout = common::stdout_str(&search);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines[0].ends_with(" fix flaky test by bob"), "{stdout}");
+    assert!(lines[1].ends_with(" fix flaky test again"), "{stdout}");
+    assert!(lines[2].ends_with(" Fix flaky test in CI"), "{stdout}");
+
+    let search = common::run_tandem_in(
+        tmp.path(),
+        &["search", "--server", &addr, "--author", "bob@example"],
+        &home,
+    );
+    common::assert_ok(&search, "search by author");
+    let stdout = common::stdout_str(&search);
+    assert_eq!(stdout.lines().count(), 1, "{stdout}");
+    assert!(stdout.contains("bob@example.com"), "{stdout}");
+
+    let search = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "search", "--server", &addr, "flaky", "--limit", "1", "--json",
+        ],
+        &home,
+    );
+    common::assert_ok(&search, "search --json");
+    let json: serde_json::Value = serde_json::from_str(&common::stdout_str(&search)).unwrap();
+    assert_eq!(json["truncated"], true);
+    let commits = json["commits"].as_array().unwrap();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0]["description"], "fix flaky test by bob\n");
+    assert_eq!(commits[0]["author"]["name"], "Bob");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice67_search_needs_query_or_author() {
+    let out = Command::new(common::tandem_bin())
+        .args(["search", "--server", &common::free_addr()])
+        .output()
+        .unwrap();
+    assert!(!out.status.success());
+    assert!(
+        common::stderr_str(&out).contains("nothing to search for"),
+        "{}",
+        common::stderr_str(&out)
+    );
+}
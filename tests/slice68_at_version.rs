@@ -0,0 +1,100 @@
+//! Slice 68: time travel with --at-version
+//!
+//! Acceptance criteria:
+//! - `tandem --at-version N <jj command>` shows the repo as it was at heads
+//!   version N from the heads-history journal
+//! - `TANDEM_AT_VERSION=N` does the same
+//! - Commands that would write are refused while pinned
+//! - Versions the server never recorded are reported, not approximated
+
+mod common;
+
+use tempfile::TempDir;
+
+fn latest_version(tmp: &std::path::Path, addr: &str, home: &std::path::Path) -> u64 {
+    let json = common::run_tandem_in(tmp, &["timeline", "--server", addr, "--json"], home);
+    common::assert_ok(&json, "timeline --json");
+    let entries: Vec<serde_json::Value> =
+        serde_json::from_str(&common::stdout_str(&json)).expect("timeline JSON");
+    entries.last().expect("timeline entries")["version"]
+        .as_u64()
+        .unwrap()
+}
+
+#[test]
+fn slice68_at_version_shows_earlier_heads() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+    let init = common::run_tandem_in(
+        &ws,
+        &["init", "--server", &addr, "--workspace", "ws", "."],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+
+    std::fs::write(ws.join("a.txt"), "first\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "first change"], &home);
+    common::assert_ok(&commit, "first commit");
+    let before = latest_version(tmp.path(), &addr, &home);
+
+    std::fs::write(ws.join("b.txt"), "second\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "second change"], &home);
+    common::assert_ok(&commit, "second commit");
+
+    let log_args = ["log", "-r", "all()", "--no-graph", "-T", "description"];
+    let now = common::run_tandem_in(&ws, &log_args, &home);
+    common::assert_ok(&now, "log");
+    assert!(common::stdout_str(&now).contains("second change"));
+
+    let version = before.to_string();
+    let mut args = vec!["--at-version", version.as_str()];
+    args.extend(log_args);
+    let then = common::run_tandem_in(&ws, &args, &home);
+    common::assert_ok(&then, "log --at-version");
+    let stdout = common::stdout_str(&then);
+    assert!(stdout.contains("first change"), "{stdout}");
+    assert!(!stdout.contains("second change"), "{stdout}");
+
+    let then_env = common::run_tandem_in_with_env(
+        &ws,
+        &log_args,
+        &[("TANDEM_AT_VERSION", version.as_str())],
+        &home,
+    );
+    common::assert_ok(&then_env, "log with TANDEM_AT_VERSION");
+    assert!(!common::stdout_str(&then_env).contains("second change"));
+
+    let write = common::run_tandem_in(
+        &ws,
+        &["--at-version", &version, "new", "-m", "from the past"],
+        &home,
+    );
+    assert!(!write.status.success());
+    assert!(
+        common::stderr_str(&write).contains("read-only"),
+        "{}",
+        common::stderr_str(&write)
+    );
+    let now = common::run_tandem_in(&ws, &log_args, &home);
+    assert!(!common::stdout_str(&now).contains("from the past"));
+
+    let missing = common::run_tandem_in(&ws, &["--at-version", "999999", "log"], &home);
+    assert!(!missing.status.success());
+    assert!(
+        common::stderr_str(&missing).contains("no version 999999"),
+        "{}",
+        common::stderr_str(&missing)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
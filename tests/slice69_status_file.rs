@@ -0,0 +1,134 @@
+//! Slice 69: status file
+//!
+//! Acceptance criteria:
+//! - `tandem serve --status-file <path>` writes a JSON snapshot of the server
+//!   and rewrites it every `--status-interval`
+//! - The snapshot tracks heads, connections, and health
+//! - A clean shutdown leaves a final snapshot with `running: false`
+//! - `--status-interval` requires `--status-file`
+
+mod common;
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn read_status(path: &Path) -> Option<serde_json::Value> {
+    serde_json::from_slice(&std::fs::read(path).ok()?).ok()
+}
+
+fn wait_for_status(
+    path: &Path,
+    what: &str,
+    ready: impl Fn(&serde_json::Value) -> bool,
+) -> serde_json::Value {
+    let deadline = Instant::now() + Duration::from_secs(15);
+    loop {
+        if let Some(status) = read_status(path) {
+            if ready(&status) {
+                return status;
+            }
+        }
+        assert!(
+            Instant::now() < deadline,
+            "status file never showed {what}: {:?}",
+            read_status(path)
+        );
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn slice69_status_file_tracks_the_server() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let status_path = tmp.path().join("status.json");
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &[
+            "--status-file",
+            status_path.to_str().unwrap(),
+            "--status-interval",
+            "1s",
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let first = wait_for_status(&status_path, "a snapshot", |_| true);
+    assert_eq!(first["running"], true);
+    assert_eq!(first["pid"], server.id());
+    assert_eq!(first["intervalSecs"], 1);
+    assert_eq!(first["health"]["healthy"], true);
+
+    let ws = tmp.path().join("ws");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "ws",
+            ws.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "work"], &home);
+    common::assert_ok(&commit, "new");
+
+    let later = wait_for_status(&status_path, "the workspace", |status| {
+        status["workspaces"].as_u64() >= Some(1)
+            && status["updatedAtMillis"].as_u64() > first["updatedAtMillis"].as_u64()
+    });
+    assert!(
+        later["headsVersion"].as_u64() > first["headsVersion"].as_u64(),
+        "{later}"
+    );
+    assert!(later["connections"]["total"].as_u64() >= Some(2), "{later}");
+    assert_eq!(later["opHeads"], 1);
+
+    common::interrupt_server(&server);
+    let _ = server.wait();
+    let last = read_status(&status_path).expect("final status");
+    assert_eq!(last["running"], false);
+    assert_eq!(last["connections"]["active"], 0);
+    let leftovers: Vec<_> = std::fs::read_dir(tmp.path())
+        .unwrap()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().contains(".tmp-"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+}
+
+#[test]
+fn slice69_status_interval_requires_status_file() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let output = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "serve",
+            "--listen",
+            &common::free_addr(),
+            "--repo",
+            tmp.path().to_str().unwrap(),
+            "--status-interval",
+            "5s",
+        ],
+        &home,
+    );
+    assert!(!output.status.success());
+    assert!(
+        common::stderr_str(&output).contains("--status-file"),
+        "{}",
+        common::stderr_str(&output)
+    );
+}
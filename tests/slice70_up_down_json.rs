@@ -0,0 +1,63 @@
+//! Slice 70: machine-readable tandem up / down
+//!
+//! Acceptance criteria:
+//! - `tandem up --json` prints one JSON object with the daemon's pid, listen
+//!   address, control socket, log file, and repo
+//! - `tandem down --json` prints the stopped daemon's pid and addresses
+
+mod common;
+
+use std::time::Duration;
+use tempfile::TempDir;
+
+#[test]
+fn slice70_up_and_down_print_json() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let log_file = tmp.path().join("daemon.log");
+
+    let up = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "up",
+            "--repo",
+            server_repo.to_str().unwrap(),
+            "--listen",
+            &addr,
+            "--control-socket",
+            sock_str,
+            "--log-file",
+            log_file.to_str().unwrap(),
+            "--json",
+        ],
+        &home,
+    );
+    common::assert_ok(&up, "tandem up --json");
+    let up: serde_json::Value = serde_json::from_str(common::stdout_str(&up).trim())
+        .unwrap_or_else(|e| panic!("invalid JSON: {e}\nraw: {}", common::stdout_str(&up)));
+    assert_eq!(up["listen"], addr.as_str());
+    assert_eq!(up["controlSocket"], sock_str);
+    assert_eq!(up["logFile"], log_file.to_str().unwrap());
+    assert_eq!(up["repo"], server_repo.to_str().unwrap());
+    let pid = up["pid"].as_u64().expect("pid");
+    common::wait_for_addr(&addr, Duration::from_secs(10));
+
+    let down = common::run_tandem_in(
+        tmp.path(),
+        &["down", "--control-socket", sock_str, "--json"],
+        &home,
+    );
+    common::assert_ok(&down, "tandem down --json");
+    let down: serde_json::Value = serde_json::from_str(common::stdout_str(&down).trim())
+        .unwrap_or_else(|e| panic!("invalid JSON: {e}\nraw: {}", common::stdout_str(&down)));
+    assert_eq!(down["stopped"], true);
+    assert_eq!(down["pid"], pid);
+    assert_eq!(down["listen"], addr.as_str());
+    assert_eq!(down["controlSocket"], sock_str);
+}
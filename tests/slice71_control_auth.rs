@@ -0,0 +1,137 @@
+//! Slice 71: control socket access
+//!
+//! Acceptance criteria:
+//! - The control socket is created 0600 in a 0700 directory
+//! - With a control token, shutdown and log streaming are refused without it;
+//!   status stays open
+//! - Clients send the token from `TANDEM_CONTROL_TOKEN`
+//! - The default socket lives under `$XDG_RUNTIME_DIR/tandem` when it is set
+
+mod common;
+
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+#[cfg(unix)]
+fn mode(path: &std::path::Path) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).unwrap().permissions().mode() & 0o777
+}
+
+#[cfg(unix)]
+#[test]
+fn slice71_control_token_guards_shutdown_and_logs() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let sock = tmp.path().join("run").join("control.sock");
+    let sock_str = sock.to_str().unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args_and_env(
+        &server_repo,
+        &addr,
+        &["--control-socket", sock_str],
+        &[("TANDEM_CONTROL_TOKEN", "s3cret")],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(10));
+
+    assert_eq!(mode(&sock), 0o600);
+    assert_eq!(mode(sock.parent().unwrap()), 0o700);
+
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--json", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "status without token");
+
+    let logs = common::run_tandem_in(
+        tmp.path(),
+        &["server", "logs", "--control-socket", sock_str],
+        &home,
+    );
+    assert!(!logs.status.success());
+    assert!(
+        common::stderr_str(&logs).contains("control token"),
+        "{}",
+        common::stderr_str(&logs)
+    );
+
+    for token in [None, Some("wrong")] {
+        let env: Vec<(&str, &str)> = token
+            .map(|token| ("TANDEM_CONTROL_TOKEN", token))
+            .into_iter()
+            .collect();
+        let down = common::run_tandem_in_with_env(
+            tmp.path(),
+            &["down", "--control-socket", sock_str],
+            &env,
+            &home,
+        );
+        assert!(!down.status.success(), "down with token {token:?}");
+        assert!(
+            common::stderr_str(&down).contains("control token"),
+            "{}",
+            common::stderr_str(&down)
+        );
+    }
+    assert!(server.try_wait().unwrap().is_none(), "server still running");
+
+    let down = common::run_tandem_in_with_env(
+        tmp.path(),
+        &["down", "--control-socket", sock_str],
+        &[("TANDEM_CONTROL_TOKEN", "s3cret")],
+        &home,
+    );
+    common::assert_ok(&down, "down with token");
+    let _ = server.wait();
+}
+
+#[cfg(unix)]
+#[test]
+fn slice71_default_socket_uses_xdg_runtime_dir() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let runtime_dir = tmp.path().join("xdg-runtime");
+    std::fs::create_dir_all(&runtime_dir).unwrap();
+    let env = [
+        ("XDG_RUNTIME_DIR", runtime_dir.to_str().unwrap()),
+        ("TMPDIR", tmp.path().to_str().unwrap()),
+    ];
+
+    let addr = common::free_addr();
+    let up = common::run_tandem_in_with_env(
+        tmp.path(),
+        &[
+            "up",
+            "--repo",
+            server_repo.to_str().unwrap(),
+            "--listen",
+            &addr,
+            "--json",
+        ],
+        &env,
+        &home,
+    );
+    common::assert_ok(&up, "tandem up");
+    let up: serde_json::Value = serde_json::from_str(common::stdout_str(&up).trim()).unwrap();
+    let sock = std::path::PathBuf::from(up["controlSocket"].as_str().unwrap());
+    assert_eq!(sock.parent().unwrap(), runtime_dir.join("tandem"));
+    assert_eq!(mode(&sock), 0o600);
+    assert_eq!(mode(sock.parent().unwrap()), 0o700);
+
+    let down = common::run_tandem_in_with_env(
+        tmp.path(),
+        &["down", "--repo", server_repo.to_str().unwrap()],
+        &env,
+        &home,
+    );
+    common::assert_ok(&down, "tandem down");
+}
@@ -0,0 +1,85 @@
+//! Slice 72: per-repo default control sockets
+//!
+//! Acceptance criteria:
+//! - `tandem up` without `--control-socket` picks a socket named after the
+//!   repo, so daemons for two repos start side by side
+//! - `tandem server status --repo <path>` finds that repo's daemon
+//! - Inside the served repo, daemon commands find its daemon on their own
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice72_up_for_two_repos_uses_separate_sockets() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let tmpdir = tmp.path().join("tmp");
+    std::fs::create_dir_all(&tmpdir).unwrap();
+    let env = [("TMPDIR", tmpdir.to_str().unwrap())];
+
+    let mut repos = Vec::new();
+    for name in ["repo-a", "repo-b"] {
+        let repo = tmp.path().join(name);
+        std::fs::create_dir_all(&repo).unwrap();
+        let addr = common::free_addr();
+        let up = common::run_tandem_in_with_env(
+            tmp.path(),
+            &[
+                "up",
+                "--repo",
+                repo.to_str().unwrap(),
+                "--listen",
+                &addr,
+                "--json",
+            ],
+            &env,
+            &home,
+        );
+        common::assert_ok(&up, &format!("tandem up {name}"));
+        let up: serde_json::Value = serde_json::from_str(common::stdout_str(&up).trim()).unwrap();
+        repos.push((
+            repo,
+            addr,
+            up["controlSocket"].as_str().unwrap().to_string(),
+        ));
+    }
+    assert_ne!(repos[0].2, repos[1].2, "each repo gets its own socket");
+
+    let (repo_a, addr_a, _) = &repos[0];
+    let status = common::run_tandem_in_with_env(
+        tmp.path(),
+        &[
+            "server",
+            "status",
+            "--json",
+            "--repo",
+            repo_a.to_str().unwrap(),
+        ],
+        &env,
+        &home,
+    );
+    common::assert_ok(&status, "status --repo");
+    let status: serde_json::Value =
+        serde_json::from_str(common::stdout_str(&status).trim()).unwrap();
+    assert_eq!(status["listen"], addr_a.as_str());
+
+    // `tandem up` initialised repo B; from inside it no flags are needed.
+    let (repo_b, addr_b, _) = &repos[1];
+    let status =
+        common::run_tandem_in_with_env(repo_b, &["server", "status", "--json"], &env, &home);
+    common::assert_ok(&status, "status inside repo B");
+    let status: serde_json::Value =
+        serde_json::from_str(common::stdout_str(&status).trim()).unwrap();
+    assert_eq!(status["listen"], addr_b.as_str());
+
+    let down = common::run_tandem_in_with_env(repo_b, &["down"], &env, &home);
+    common::assert_ok(&down, "down inside repo B");
+    let down = common::run_tandem_in_with_env(
+        tmp.path(),
+        &["down", "--repo", repo_a.to_str().unwrap()],
+        &env,
+        &home,
+    );
+    common::assert_ok(&down, "down --repo A");
+}
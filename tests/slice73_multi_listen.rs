@@ -0,0 +1,119 @@
+//! Slice 73: several listen addresses at once
+//!
+//! Acceptance criteria:
+//! - `tandem serve --listen` can be repeated; the server accepts clients on
+//!   every address
+//! - `unix:///path` listens on a Unix socket, and clients reach it with
+//!   `tandem init --server unix:///path`
+//! - Workspaces on different listeners share one repo
+//! - `tandem server status` reports every address; the Unix socket file is
+//!   removed on shutdown
+
+mod common;
+
+use tempfile::TempDir;
+
+#[cfg(unix)]
+#[test]
+fn slice73_serve_accepts_on_every_listener() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let control = common::control_socket_path(tmp.path());
+    let rpc_socket = tmp.path().join("rpc.sock");
+    let unix_addr = format!("unix://{}", rpc_socket.display());
+
+    let addr = common::free_addr();
+    let second_addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &[
+            "--listen",
+            &second_addr,
+            "--listen",
+            &unix_addr,
+            "--control-socket",
+            control.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_addr(&second_addr, std::time::Duration::from_secs(10));
+    common::wait_for_socket(&control, std::time::Duration::from_secs(10));
+
+    let status: serde_json::Value =
+        serde_json::from_str(&common::control_request(&control, r#"{"type":"status"}"#)).unwrap();
+    assert_eq!(status["listen"], addr.as_str());
+    assert_eq!(
+        status["also_listen"],
+        serde_json::json!([second_addr, unix_addr]),
+        "{status}"
+    );
+
+    let mut workspaces = Vec::new();
+    for (name, server_addr) in [("ws-tcp", &second_addr), ("ws-unix", &unix_addr)] {
+        let ws = tmp.path().join(name);
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &[
+                "init",
+                "--server",
+                server_addr,
+                "--workspace",
+                name,
+                ws.to_str().unwrap(),
+            ],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+        workspaces.push(ws);
+    }
+
+    let commit = common::run_tandem_in(
+        &workspaces[1],
+        &["new", "-m", "over the unix socket"],
+        &home,
+    );
+    common::assert_ok(&commit, "new over unix socket");
+    let log = common::run_tandem_in(
+        &workspaces[0],
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "all()",
+            "-T",
+            "description ++ \"\\n\"",
+        ],
+        &home,
+    );
+    common::assert_ok(&log, "log over tcp");
+    assert!(
+        common::stdout_str(&log).contains("over the unix socket"),
+        "{}",
+        common::stdout_str(&log)
+    );
+
+    common::interrupt_server(&server);
+    let _ = server.wait();
+    assert!(!rpc_socket.exists(), "unix listen socket left behind");
+}
+
+#[test]
+fn slice73_serve_requires_a_listen_address() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let output = common::run_tandem_in(
+        tmp.path(),
+        &["serve", "--repo", tmp.path().to_str().unwrap()],
+        &home,
+    );
+    assert!(!output.status.success());
+    assert!(
+        common::stderr_str(&output).contains("--listen"),
+        "{}",
+        common::stderr_str(&output)
+    );
+}
@@ -0,0 +1,83 @@
+//! Slice 74: graceful drain on shutdown
+//!
+//! Acceptance criteria:
+//! - On shutdown the server tells watchers it is going away, with a retry
+//!   hint taken from `--drain-timeout`
+//! - `tandem watch` prints the notice to stderr and exits cleanly
+//! - Once its clients have gone, the server stops without waiting out the
+//!   drain window
+
+mod common;
+
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn wait_with_deadline(child: &mut Child, timeout: Duration, what: &str) {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if child.try_wait().unwrap().is_some() {
+            return;
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            panic!("{what} did not exit within {timeout:?}");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+#[test]
+fn slice74_shutdown_notifies_watchers_before_closing() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let control = common::control_socket_path(tmp.path());
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &[
+            "--control-socket",
+            control.to_str().unwrap(),
+            "--drain-timeout",
+            "12s",
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&control, Duration::from_secs(10));
+
+    let mut watch = Command::new(common::tandem_bin())
+        .args(["watch", "--server", &addr])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn tandem watch");
+    std::thread::sleep(Duration::from_millis(1000));
+
+    let response: serde_json::Value =
+        serde_json::from_str(&common::control_request(&control, r#"{"type":"shutdown"}"#)).unwrap();
+    assert_eq!(response["ok"], true, "{response}");
+
+    wait_with_deadline(&mut watch, Duration::from_secs(10), "tandem watch");
+    let output = watch.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{stderr}");
+    assert!(
+        stderr.contains("tandem server is shutting down; retry in 12s"),
+        "{stderr}"
+    );
+
+    // With the watcher gone nothing is left to drain.
+    let stopping = Instant::now();
+    wait_with_deadline(&mut server, Duration::from_secs(10), "tandem serve");
+    assert!(
+        stopping.elapsed() < Duration::from_secs(5),
+        "server waited {:?} with no clients left",
+        stopping.elapsed()
+    );
+}
@@ -0,0 +1,112 @@
+//! Slice 75: zero-downtime restart via socket handover
+//!
+//! Acceptance criteria:
+//! - `tandem server restart` starts a new server process that takes over the
+//!   listening socket and the control socket, and reports both PIDs
+//! - The old process drains and exits; the new one answers on the same
+//!   address and control socket path
+//! - Workspaces keep working across the restart without re-initializing
+//!
+//! Socket handover needs Unix.
+#![cfg(unix)]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+#[test]
+fn slice75_restart_hands_the_listener_to_a_new_process() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let control = common::control_socket_path(tmp.path());
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--control-socket", control.to_str().unwrap()],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&control, Duration::from_secs(10));
+
+    let ws = tmp.path().join("ws");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            ws.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    let before = common::run_tandem_in(&ws, &["new", "-m", "before restart"], &home);
+    common::assert_ok(&before, "new before restart");
+
+    let restart = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "server",
+            "restart",
+            "--control-socket",
+            control.to_str().unwrap(),
+            "--json",
+        ],
+        &home,
+    );
+    common::assert_ok(&restart, "server restart");
+    let restart: serde_json::Value = serde_json::from_str(&common::stdout_str(&restart)).unwrap();
+    assert_eq!(restart["previousPid"], server.id(), "{restart}");
+    let new_pid = restart["pid"].as_u64().unwrap();
+    assert_ne!(new_pid, server.id() as u64);
+
+    // The old process has nothing left to drain and exits on its own.
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while server.try_wait().unwrap().is_none() {
+        if Instant::now() > deadline {
+            let _ = server.kill();
+            panic!("old server did not exit after handing over");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    let status: serde_json::Value =
+        serde_json::from_str(&common::control_request(&control, r#"{"type":"status"}"#)).unwrap();
+    assert_eq!(status["pid"], new_pid, "{status}");
+    assert_eq!(status["listen"], addr.as_str());
+
+    let after = common::run_tandem_in(&ws, &["new", "-m", "after restart"], &home);
+    common::assert_ok(&after, "new after restart");
+    let log = common::run_tandem_in(
+        &ws,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "all()",
+            "-T",
+            "description ++ \"\\n\"",
+        ],
+        &home,
+    );
+    common::assert_ok(&log, "log after restart");
+    let log = common::stdout_str(&log);
+    assert!(log.contains("before restart"), "{log}");
+    assert!(log.contains("after restart"), "{log}");
+
+    // The new server is not our child; stop it over the control socket.
+    common::control_request(&control, r#"{"type":"shutdown"}"#);
+    let deadline = Instant::now() + Duration::from_secs(15);
+    while control.exists() {
+        assert!(Instant::now() < deadline, "new server did not shut down");
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
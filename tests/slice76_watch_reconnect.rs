@@ -0,0 +1,103 @@
+//! Slice 76: `tandem watch` survives a dropped connection
+//!
+//! Acceptance criteria:
+//! - When the server dies without a shutdown notice, watch keeps running and
+//!   reconnects once the server is back
+//! - It prints a single `reconnected` line to stderr
+//! - Changes after the reconnect are printed; versions printed before it are
+//!   not printed again
+
+mod common;
+
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+use tempfile::TempDir;
+
+fn version_of(line: &str) -> Option<u64> {
+    line.strip_prefix("version=")?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+#[test]
+fn slice76_watch_reconnects_after_the_server_dies() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init");
+
+    let mut watch = Command::new(common::tandem_bin())
+        .args(["watch", "--server", &addr])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn tandem watch");
+    let (lines_tx, lines_rx) = mpsc::channel::<String>();
+    let stdout = watch.stdout.take().unwrap();
+    std::thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            let _ = lines_tx.send(line);
+        }
+    });
+    let mut printed = Vec::new();
+    let wait_for_version = |min: u64, printed: &mut Vec<u64>| {
+        let deadline = Instant::now() + Duration::from_secs(20);
+        while printed.last().copied().unwrap_or(0) < min {
+            let left = deadline.saturating_duration_since(Instant::now());
+            let line = lines_rx
+                .recv_timeout(left)
+                .unwrap_or_else(|_| panic!("no version >= {min}; printed {printed:?}"));
+            if let Some(version) = version_of(&line) {
+                printed.push(version);
+            }
+        }
+    };
+
+    // The current state arrives first.
+    wait_for_version(1, &mut printed);
+    let before = *printed.last().unwrap();
+
+    // The server dies without a notice and comes back on the same address.
+    let _ = server.kill();
+    let _ = server.wait();
+    std::thread::sleep(Duration::from_millis(500));
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let commit = common::run_tandem_in(&ws, &["new", "-m", "after the crash"], &home);
+    common::assert_ok(&commit, "new after restart");
+    wait_for_version(before + 1, &mut printed);
+    std::thread::sleep(Duration::from_millis(500));
+    while let Ok(line) = lines_rx.try_recv() {
+        printed.extend(version_of(&line));
+    }
+
+    let _ = watch.kill();
+    let output = watch.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let _ = server.kill();
+    let _ = server.wait();
+
+    assert_eq!(
+        stderr.matches("reconnected to").count(),
+        1,
+        "stderr:\n{stderr}"
+    );
+    assert!(
+        printed.windows(2).all(|pair| pair[1] > pair[0]),
+        "a version was printed twice: {printed:?}"
+    );
+}
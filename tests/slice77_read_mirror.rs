@@ -0,0 +1,139 @@
+//! Slice 77: read mirror
+//!
+//! Acceptance criteria:
+//! - `tandem init --read-mirror <addr>` records the mirror as
+//!   `tandem.read-mirror` in the workspace's repo config
+//! - With a replica as the mirror, writes still land on the primary and
+//!   reads of just-written objects succeed (the primary answers what the
+//!   replica has not copied yet)
+//! - A mirror hosting a different repo is skipped with a warning
+//! - An unreachable mirror fails init before anything is created
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice77_reads_go_to_the_mirror_and_writes_to_the_primary() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let primary_repo = tmp.path().join("primary-repo");
+    std::fs::create_dir_all(&primary_repo).unwrap();
+    let replica_repo = tmp.path().join("replica-repo");
+    std::fs::create_dir_all(&replica_repo).unwrap();
+    let other_repo = tmp.path().join("other-repo");
+    std::fs::create_dir_all(&other_repo).unwrap();
+
+    let primary_addr = common::free_addr();
+    let mut primary = common::spawn_server(&primary_repo, &primary_addr);
+    common::wait_for_server(&primary_addr, &mut primary);
+    let replica_addr = common::free_addr();
+    let mut replica = common::spawn_server_with_args(
+        &replica_repo,
+        &replica_addr,
+        &["--follow", &primary_addr],
+        &home,
+    );
+    common::wait_for_server(&replica_addr, &mut replica);
+
+    let ws = tmp.path().join("ws");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &primary_addr,
+            "--read-mirror",
+            &replica_addr,
+            ws.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init with a read mirror");
+    assert!(
+        common::stderr_str(&init).contains(&format!("Reading objects from mirror {replica_addr}")),
+        "stderr:\n{}",
+        common::stderr_str(&init)
+    );
+    let config = common::run_tandem_in(&ws, &["config", "get", "tandem.read-mirror"], &home);
+    common::assert_ok(&config, "config get");
+    assert_eq!(common::stdout_str(&config).trim(), replica_addr);
+
+    std::fs::write(ws.join("fresh.txt"), b"written a moment ago\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "fresh change"], &home);
+    common::assert_ok(&commit, "commit");
+    let show = common::run_tandem_in(&ws, &["file", "show", "-r", "@-", "fresh.txt"], &home);
+    common::assert_ok(&show, "file show right after the write");
+    assert_eq!(show.stdout, b"written a moment ago\n");
+    assert!(
+        !common::stderr_str(&show).contains("not using read mirror"),
+        "stderr:\n{}",
+        common::stderr_str(&show)
+    );
+
+    // The write went to the primary: a workspace without the mirror sees it.
+    let log = common::run_tandem_in_with_env(
+        &ws,
+        &[
+            "log",
+            "--no-graph",
+            "--ignore-working-copy",
+            "-r",
+            "description(substring:\"fresh change\")",
+            "-T",
+            "description",
+        ],
+        &[("TANDEM_READ_MIRROR", primary_addr.as_str())],
+        &home,
+    );
+    common::assert_ok(&log, "log via the primary alone");
+    assert!(common::stdout_str(&log).contains("fresh change"));
+
+    // A server hosting some other repo is not used as the mirror.
+    let other_addr = common::free_addr();
+    let mut other = common::spawn_server(&other_repo, &other_addr);
+    common::wait_for_server(&other_addr, &mut other);
+    let log = common::run_tandem_in_with_env(
+        &ws,
+        &["log", "--no-graph", "-r", "@-", "-T", "description"],
+        &[("TANDEM_READ_MIRROR", other_addr.as_str())],
+        &home,
+    );
+    common::assert_ok(&log, "log with an unrelated mirror");
+    assert!(common::stdout_str(&log).contains("fresh change"));
+    let stderr = common::stderr_str(&log);
+    assert_eq!(
+        stderr
+            .matches(&format!("not using read mirror {other_addr}"))
+            .count(),
+        1,
+        "stderr:\n{stderr}"
+    );
+
+    // An unreachable mirror is caught before init creates anything.
+    let missing = tmp.path().join("missing");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &primary_addr,
+            "--read-mirror",
+            &common::free_addr(),
+            missing.to_str().unwrap(),
+        ],
+        &home,
+    );
+    assert!(!init.status.success(), "init with a dead mirror succeeded");
+    assert!(
+        common::stderr_str(&init).contains("cannot use read mirror"),
+        "stderr:\n{}",
+        common::stderr_str(&init)
+    );
+    assert!(!missing.exists());
+
+    for server in [&mut other, &mut replica, &mut primary] {
+        let _ = server.kill();
+        let _ = server.wait();
+    }
+}
@@ -0,0 +1,71 @@
+//! Slice 78: tandem stores as a library
+//!
+//! Acceptance criteria:
+//! - A jj-lib program that calls `tandem_jj_store::register` can load a
+//!   tandem workspace's repo in-process and read its commits from the server
+//! - Without the tandem factories, jj-lib refuses the `tandem` store type
+
+mod common;
+
+use jj_lib::repo::Repo as _;
+use tempfile::TempDir;
+
+fn user_settings() -> jj_lib::settings::UserSettings {
+    let config = jj_lib::config::StackedConfig::with_defaults();
+    jj_lib::settings::UserSettings::from_config(config).expect("create jj settings")
+}
+
+#[test]
+fn slice78_register_lets_jj_lib_open_a_tandem_repo() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init");
+    let describe = common::run_tandem_in(&ws, &["describe", "-m", "written by tandem"], &home);
+    common::assert_ok(&describe, "describe");
+
+    let settings = user_settings();
+    let repo_dir = dunce::canonicalize(ws.join(".jj/repo")).unwrap();
+
+    let stock = jj_lib::repo::StoreFactories::default();
+    assert!(
+        jj_lib::repo::RepoLoader::init_from_file_system(&settings, &repo_dir, &stock).is_err(),
+        "jj-lib opened a tandem repo without the tandem factories"
+    );
+
+    let mut factories = jj_lib::repo::StoreFactories::default();
+    tandem_jj_store::register(&mut factories);
+    let loader = jj_lib::repo::RepoLoader::init_from_file_system(&settings, &repo_dir, &factories)
+        .expect("load tandem repo");
+    let repo = loader.load_at_head().expect("load repo at head");
+    let descriptions: Vec<String> = repo
+        .view()
+        .heads()
+        .iter()
+        .map(|id| {
+            repo.store()
+                .get_commit(id)
+                .expect("read commit")
+                .description()
+                .to_string()
+        })
+        .collect();
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    assert!(
+        descriptions
+            .iter()
+            .any(|description| description.contains("written by tandem")),
+        "heads: {descriptions:?}"
+    );
+}
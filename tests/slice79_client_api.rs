@@ -0,0 +1,76 @@
+//! Slice 79: async client API
+//!
+//! Acceptance criteria:
+//! - `tandem_client::TandemClient` connects and reports the server's repo info
+//! - Objects stored through it read back byte for byte
+//! - `get_heads` follows commits made by a workspace
+//! - A watch delivers the current heads, then each change a workspace makes
+
+mod common;
+
+use std::time::Duration;
+
+use tandem_client::{ObjectKind, RepoCapability, TandemClient, WatchEvent};
+use tempfile::TempDir;
+
+async fn next_change(watch: &mut tandem_client::HeadsWatch) -> tandem_client::HeadsChange {
+    match tokio::time::timeout(Duration::from_secs(10), watch.next()).await {
+        Ok(Some(WatchEvent::Changed(change))) => change,
+        Ok(other) => panic!("expected a heads change, got {other:?}"),
+        Err(_) => panic!("timed out waiting for a heads change"),
+    }
+}
+
+#[test]
+fn slice79_client_reads_writes_and_watches() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init");
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let client = TandemClient::connect(&addr).await.expect("connect");
+        assert_eq!(client.repo_info().backend_name, "tandem");
+        assert!(client.supports_capability(RepoCapability::WatchHeads));
+
+        let id = client
+            .put_object(ObjectKind::File, b"from the client api\n".to_vec())
+            .await
+            .expect("put file");
+        let data = client
+            .get_object(ObjectKind::File, &id)
+            .await
+            .expect("get file");
+        assert_eq!(data, b"from the client api\n");
+        assert!(client.get_object(ObjectKind::File, &[0; 20]).await.is_err());
+
+        let before = client.get_heads().await.expect("get heads");
+        assert!(!before.heads.is_empty());
+
+        let mut watch = client.watch(0).await.expect("watch");
+        let current = next_change(&mut watch).await;
+        assert_eq!(current.version, before.version);
+        assert_eq!(current.heads, before.heads);
+
+        let describe = common::run_tandem_in(&ws, &["describe", "-m", "seen by a bot"], &home);
+        common::assert_ok(&describe, "describe");
+
+        let changed = next_change(&mut watch).await;
+        assert!(changed.version > before.version, "{changed:?}");
+        let after = client.get_heads().await.expect("get heads");
+        assert!(after.version >= changed.version);
+        assert_ne!(after.heads, before.heads);
+    });
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
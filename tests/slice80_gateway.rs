@@ -0,0 +1,138 @@
+//! Slice 80: JSON-over-HTTP gateway
+//!
+//! Acceptance criteria:
+//! - `tandem gateway` serves the server's repo info and heads as JSON
+//! - `/v1/objects/<kind>/<id>` returns an object's exact bytes; unknown ids
+//!   are 404, malformed paths 400
+//! - `/v1/heads?after=N` waits for the heads to move past version N, and
+//!   answers with the unchanged heads once `wait` runs out
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use tandem_client::{ObjectKind, TandemClient};
+use tempfile::TempDir;
+
+/// GET `path` and return the status code and body.
+fn http_get(addr: &str, path: &str) -> (u16, Vec<u8>) {
+    let mut stream = TcpStream::connect(addr).expect("connect to gateway");
+    stream.set_read_timeout(Some(Duration::from_secs(30))).ok();
+    write!(stream, "GET {path} HTTP/1.1\r\nHost: {addr}\r\n\r\n").unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    let split = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .expect("end of headers");
+    let head = String::from_utf8_lossy(&response[..split]).into_owned();
+    let status = head
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .expect("status code");
+    (status, response[split + 4..].to_vec())
+}
+
+fn get_json(addr: &str, path: &str) -> serde_json::Value {
+    let (status, body) = http_get(addr, path);
+    assert_eq!(status, 200, "{path}: {}", String::from_utf8_lossy(&body));
+    serde_json::from_slice(&body).expect("json body")
+}
+
+#[test]
+fn slice80_gateway_serves_heads_and_objects() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let server_addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &server_addr);
+    common::wait_for_server(&server_addr, &mut server);
+    let init = common::run_tandem_in(&ws, &["init", "--server", &server_addr, "."], &home);
+    common::assert_ok(&init, "init");
+
+    let gateway_addr = common::free_addr();
+    let mut cmd = Command::new(common::tandem_bin());
+    cmd.args([
+        "gateway",
+        "--listen",
+        &gateway_addr,
+        "--upstream",
+        &server_addr,
+        "--log-level",
+        "warn",
+    ]);
+    common::isolate_env(&mut cmd, &home);
+    let mut gateway = cmd
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn tandem gateway");
+    common::wait_for_server(&gateway_addr, &mut gateway);
+
+    let repo = get_json(&gateway_addr, "/v1/repo");
+    assert_eq!(repo["backendName"], "tandem");
+    assert!(repo["capabilities"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|c| c == "watchHeads"));
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let file_id = rt.block_on(async {
+        let client = TandemClient::connect(&server_addr).await.expect("connect");
+        client
+            .put_object(ObjectKind::File, b"read through the gateway\n".to_vec())
+            .await
+            .expect("put file")
+    });
+    let file_hex: String = file_id.iter().map(|b| format!("{b:02x}")).collect();
+    let (status, body) = http_get(&gateway_addr, &format!("/v1/objects/file/{file_hex}"));
+    assert_eq!(status, 200);
+    assert_eq!(body, b"read through the gateway\n");
+    let unknown = "00".repeat(file_id.len());
+    assert_eq!(
+        http_get(&gateway_addr, &format!("/v1/objects/file/{unknown}")).0,
+        404
+    );
+    assert_eq!(http_get(&gateway_addr, "/v1/objects/blob/00").0, 400);
+    assert_eq!(http_get(&gateway_addr, "/v1/objects/file/xyz").0, 400);
+    assert_eq!(http_get(&gateway_addr, "/v1/nothing").0, 404);
+
+    let before = get_json(&gateway_addr, "/v1/heads");
+    let version = before["version"].as_u64().unwrap();
+    assert!(!before["heads"].as_array().unwrap().is_empty());
+    assert!(
+        !before["workspaceHeads"].as_object().unwrap().is_empty(),
+        "{before}"
+    );
+
+    // Nothing changes: the long poll gives up after `wait`.
+    let started = Instant::now();
+    let unchanged = get_json(&gateway_addr, &format!("/v1/heads?after={version}&wait=1"));
+    assert!(started.elapsed() >= Duration::from_secs(1));
+    assert_eq!(unchanged["version"], before["version"]);
+
+    let poll_addr = gateway_addr.clone();
+    let poll = std::thread::spawn(move || {
+        get_json(&poll_addr, &format!("/v1/heads?after={version}&wait=30"))
+    });
+    std::thread::sleep(Duration::from_millis(300));
+    let describe = common::run_tandem_in(&ws, &["describe", "-m", "seen over http"], &home);
+    common::assert_ok(&describe, "describe");
+    let changed = poll.join().unwrap();
+    assert!(changed["version"].as_u64().unwrap() > version, "{changed}");
+    assert_ne!(changed["heads"], before["heads"]);
+
+    let _ = gateway.kill();
+    let _ = gateway.wait();
+    let _ = server.kill();
+    let _ = server.wait();
+}
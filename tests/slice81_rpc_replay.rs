@@ -0,0 +1,68 @@
+//! Slice 81: record/replay of store RPCs
+//!
+//! Acceptance criteria:
+//! - `TANDEM_RPC_RECORD=<dir>` logs every store call a command makes
+//! - With the server gone, `replay://<dir>` answers the same command's calls
+//!   from the log and the command prints the same output
+//! - A library client connected to `replay://<dir>` gets recorded replies, and
+//!   an unrecorded call fails instead of reaching a server
+
+mod common;
+
+use tandem_jj_store::rpc::TandemClient;
+use tempfile::TempDir;
+
+#[test]
+fn slice81_replays_a_recorded_command_without_a_server() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+    let recording = tmp.path().join("recording");
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init");
+    std::fs::write(ws.join("notes.txt"), "recorded\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "before recording"], &home);
+    common::assert_ok(&commit, "commit");
+
+    let log_args = ["log", "--ignore-working-copy", "-r", "::@", "--no-graph"];
+    let recorded = common::run_tandem_in_with_env(
+        &ws,
+        &log_args,
+        &[("TANDEM_RPC_RECORD", recording.to_str().unwrap())],
+        &home,
+    );
+    common::assert_ok(&recorded, "log while recording");
+    assert!(common::stdout_str(&recorded).contains("before recording"));
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    // The recording does not hold the identity check's calls, so the
+    // override has to be allowed.
+    let replay_addr = format!("replay://{}", recording.display());
+    let mut replay_args = log_args.to_vec();
+    replay_args.push("--allow-server-override");
+    let replayed = common::run_tandem_in_with_env(
+        &ws,
+        &replay_args,
+        &[("TANDEM_SERVER", replay_addr.as_str())],
+        &home,
+    );
+    common::assert_ok(&replayed, "log from replay");
+    assert_eq!(common::stdout_str(&replayed), common::stdout_str(&recorded));
+
+    let client = TandemClient::connect(&replay_addr).expect("connect to replay");
+    let heads = client.get_heads_state().expect("recorded getHeads");
+    assert!(!heads.heads.is_empty());
+    let err = client
+        .get_object(2, &[0; 20])
+        .expect_err("unrecorded call fails");
+    assert!(format!("{err:#}").contains("no recorded reply"), "{err:#}");
+}
@@ -0,0 +1,104 @@
+//! Slice 82: server-side fault injection
+//!
+//! Acceptance criteria:
+//! - `tandem server faults --drop-update-op-heads-every N` makes every Nth
+//!   heads update fail without being applied
+//! - `--get-object-delay <ms>` holds getObject replies back
+//! - `--error-every N --error-method <m>` fails calls as transient errors
+//! - Active faults show up in `tandem server status`; `--off` clears them
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use tandem_client::{ObjectKind, TandemClient};
+use tempfile::TempDir;
+
+#[test]
+fn slice82_faults_are_injected_and_cleared() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--control-socket", sock_str], &home);
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+
+    let faults = |args: &[&str]| {
+        let mut full = vec!["server", "faults"];
+        full.extend_from_slice(args);
+        full.extend_from_slice(&["--control-socket", sock_str]);
+        let out = common::run_tandem_in(tmp.path(), &full, &home);
+        common::assert_ok(&out, "server faults");
+        common::stdout_str(&out)
+    };
+    assert!(faults(&[]).contains("no faults injected"));
+
+    let set = faults(&["--drop-update-op-heads-every", "1"]);
+    assert!(set.contains("dropping every 1st updateOpHeads"), "{set}");
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "server status");
+    assert!(
+        common::stdout_str(&status).contains("Faults:   dropping every 1st updateOpHeads"),
+        "status should show the faults:\n{}",
+        common::stdout_str(&status)
+    );
+
+    let dropped = common::run_tandem_in(&ws, &["describe", "-m", "dropped"], &home);
+    assert!(!dropped.status.success(), "heads update should be dropped");
+    let stderr = common::stderr_str(&dropped);
+    assert!(stderr.contains("tandem fault injection"), "{stderr}");
+
+    faults(&[
+        "--get-object-delay",
+        "400",
+        "--error-every",
+        "2",
+        "--error-method",
+        "putObject",
+    ]);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let client = TandemClient::connect(&addr).await.expect("connect");
+        let id = client
+            .put_object(ObjectKind::File, b"first\n".to_vec())
+            .await
+            .expect("first put passes");
+        let err = client
+            .put_object(ObjectKind::File, b"second\n".to_vec())
+            .await
+            .expect_err("second put fails");
+        assert!(
+            format!("{err:#}").contains("transient error in putObject"),
+            "{err:#}"
+        );
+
+        let started = Instant::now();
+        client
+            .get_object(ObjectKind::File, &id)
+            .await
+            .expect("delayed get");
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    });
+
+    assert!(faults(&["--off"]).contains("no faults injected"));
+    let describe = common::run_tandem_in(&ws, &["describe", "-m", "after faults"], &home);
+    common::assert_ok(&describe, "describe after faults are cleared");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
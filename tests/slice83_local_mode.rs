@@ -0,0 +1,65 @@
+//! Slice 83: local single-user mode
+//!
+//! Acceptance criteria:
+//! - `tandem init --local` creates a workspace without any server running
+//! - Its repo lives in `.jj/repo/tandem-local`, and the stores point at it
+//!   with a `local://` address
+//! - Commits and file reads work across separate tandem invocations
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice83_local_workspace_needs_no_server() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let ws = tmp.path().join("solo");
+
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--local", "--workspace", "solo", "solo"],
+        &home,
+    );
+    common::assert_ok(&init, "init --local");
+    assert!(common::stderr_str(&init).contains("Initialized local tandem workspace 'solo'"));
+
+    let server_repo = ws.join(".jj/repo/tandem-local");
+    assert!(server_repo.join(".jj/repo/tandem/heads.json").is_file());
+    let address = std::fs::read_to_string(ws.join(".jj/repo/store/server_address")).unwrap();
+    assert!(address.starts_with("local://"), "{address}");
+    assert!(address.ends_with("tandem-local"), "{address}");
+
+    std::fs::write(ws.join("notes.txt"), b"kept locally\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "solo work"], &home);
+    common::assert_ok(&commit, "commit");
+
+    let show = common::run_tandem_in(&ws, &["file", "show", "-r", "@-", "notes.txt"], &home);
+    common::assert_ok(&show, "file show");
+    assert_eq!(show.stdout, b"kept locally\n");
+    let log = common::run_tandem_in(&ws, &["log", "--no-graph", "-r", "@-"], &home);
+    common::assert_ok(&log, "log");
+    assert!(common::stdout_str(&log).contains("solo work"));
+
+    let heads: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(server_repo.join(".jj/repo/tandem/heads.json")).unwrap(),
+    )
+    .unwrap();
+    assert!(
+        heads["workspaceHeads"]["solo"].is_string(),
+        "the local server tracks the workspace like a real one:\n{heads}"
+    );
+}
+
+#[test]
+fn slice83_local_conflicts_with_server_only_options() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--local", "--colocate", "solo"],
+        &home,
+    );
+    assert!(!init.status.success());
+    assert!(!tmp.path().join("solo").exists());
+}
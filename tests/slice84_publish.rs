@@ -0,0 +1,103 @@
+//! Slice 84: publishing a local-mode repo
+//!
+//! Acceptance criteria:
+//! - `tandem publish --server <addr>` copies a local-mode workspace's history,
+//!   heads, and workspace attributions to a fresh server
+//! - The workspace's stores then point at the server, and keep working with
+//!   the local repo out of the picture
+//! - A server that already has workspaces is refused and the workspace stays
+//!   local; a workspace that already uses a server is pointed at `migrate`
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn recorded_address(ws: &Path, store: &str) -> String {
+    std::fs::read_to_string(ws.join(".jj/repo").join(store).join("server_address"))
+        .unwrap()
+        .trim()
+        .to_string()
+}
+
+#[test]
+fn slice84_publish_moves_a_local_workspace_onto_a_server() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let ws = tmp.path().join("solo");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &["init", "--local", "--workspace", "solo", "solo"],
+        &home,
+    );
+    common::assert_ok(&init, "init --local");
+    std::fs::write(ws.join("notes.txt"), b"written locally\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "local work"], &home);
+    common::assert_ok(&commit, "commit");
+
+    // A server somebody already works against is not overwritten.
+    let busy_repo = tmp.path().join("busy-repo");
+    std::fs::create_dir_all(&busy_repo).unwrap();
+    let busy_addr = common::free_addr();
+    let mut busy_server = common::spawn_server(&busy_repo, &busy_addr);
+    common::wait_for_server(&busy_addr, &mut busy_server);
+    let other = tmp.path().join("other");
+    std::fs::create_dir_all(&other).unwrap();
+    let init = common::run_tandem_in(&other, &["init", "--server", &busy_addr, "."], &home);
+    common::assert_ok(&init, "init on the busy server");
+    let refused = common::run_tandem_in(&ws, &["publish", "--server", &busy_addr], &home);
+    assert!(!refused.status.success());
+    assert!(
+        common::stderr_str(&refused).contains("already has workspaces"),
+        "{}",
+        common::stderr_str(&refused)
+    );
+    assert!(recorded_address(&ws, "store").starts_with("local://"));
+    let migrate_hint = common::run_tandem_in(&other, &["publish", "--server", &busy_addr], &home);
+    assert!(!migrate_hint.status.success());
+    assert!(common::stderr_str(&migrate_hint).contains("tandem migrate"));
+    let _ = busy_server.kill();
+    let _ = busy_server.wait();
+
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let publish = common::run_tandem_in(&ws, &["publish", "--server", &addr], &home);
+    common::assert_ok(&publish, "publish");
+    assert!(common::stderr_str(&publish).contains("this workspace now uses"));
+    for store in ["store", "op_store", "op_heads"] {
+        assert_eq!(recorded_address(&ws, store), addr, "{store}");
+    }
+
+    // Nothing reads the local repo any more.
+    std::fs::remove_dir_all(ws.join(".jj/repo/tandem-local")).unwrap();
+    let show = common::run_tandem_in(&ws, &["file", "show", "-r", "@-", "notes.txt"], &home);
+    common::assert_ok(&show, "file show after publish");
+    assert_eq!(show.stdout, b"written locally\n");
+    std::fs::write(ws.join("more.txt"), b"written to the server\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "served work"], &home);
+    common::assert_ok(&commit, "commit after publish");
+
+    let heads: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(server_repo.join(".jj/repo/tandem/heads.json")).unwrap(),
+    )
+    .unwrap();
+    assert!(
+        heads["workspaceHeads"]["solo"].is_string(),
+        "the server knows the published workspace:\n{heads}"
+    );
+    let log = common::run_tandem_in(&ws, &["log", "--no-graph", "-r", "::@-"], &home);
+    common::assert_ok(&log, "log after publish");
+    let log = common::stdout_str(&log);
+    assert!(
+        log.contains("local work") && log.contains("served work"),
+        "{log}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,118 @@
+//! Slice 85: cloning a workspace's position
+//!
+//! Acceptance criteria:
+//! - `tandem init --from-workspace agent-a` creates a workspace whose `@-` is
+//!   agent-a's current working-copy commit, including its unfinished changes
+//! - Naming a workspace the server does not know fails before anything is
+//!   created
+
+mod common;
+
+use tempfile::TempDir;
+
+fn commit_id(ws: &std::path::Path, revision: &str, home: &std::path::Path) -> String {
+    let out = common::run_tandem_in(
+        ws,
+        &[
+            "log",
+            "-r",
+            revision,
+            "--no-graph",
+            "-T",
+            "commit_id ++ \"\\n\"",
+        ],
+        home,
+    );
+    common::assert_ok(&out, &format!("read {revision}"));
+    common::stdout_str(&out).trim().to_string()
+}
+
+#[test]
+fn slice85_init_starts_where_another_workspace_left_off() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let agent = tmp.path().join("agent-a");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            "agent-a",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init agent-a");
+    std::fs::write(agent.join("done.txt"), b"finished\n").unwrap();
+    let commit = common::run_tandem_in(&agent, &["commit", "-m", "finished part"], &home);
+    common::assert_ok(&commit, "commit in agent-a");
+    std::fs::write(agent.join("wip.txt"), b"half done\n").unwrap();
+    let describe = common::run_tandem_in(&agent, &["describe", "-m", "in progress"], &home);
+    common::assert_ok(&describe, "describe agent-a @");
+    let agent_head = commit_id(&agent, "@", &home);
+
+    let review = tmp.path().join("review");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "review",
+            "--from-workspace",
+            "agent-a",
+            "review",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init --from-workspace");
+    assert!(
+        common::stderr_str(&init).contains("starts on top of workspace 'agent-a'"),
+        "{}",
+        common::stderr_str(&init)
+    );
+
+    assert_eq!(commit_id(&review, "@-", &home), agent_head);
+    assert_eq!(
+        std::fs::read(review.join("wip.txt")).unwrap(),
+        b"half done\n"
+    );
+    assert_eq!(
+        std::fs::read(review.join("done.txt")).unwrap(),
+        b"finished\n"
+    );
+
+    let missing = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "late",
+            "--from-workspace",
+            "agent-z",
+            "late",
+        ],
+        &home,
+    );
+    assert!(!missing.status.success());
+    let stderr = common::stderr_str(&missing);
+    assert!(
+        stderr.contains("no workspace named 'agent-z'") && stderr.contains("agent-a"),
+        "{stderr}"
+    );
+    assert!(!tmp.path().join("late").exists());
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
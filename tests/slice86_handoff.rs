@@ -0,0 +1,110 @@
+//! Slice 86: handing a change from one workspace to another
+//!
+//! Acceptance criteria:
+//! - `tandem handoff <workspace>` makes the target workspace edit the change
+//!   the current workspace had checked out, unsnapshotted edits included
+//! - The current workspace moves to a fresh empty change on the same parents
+//! - Both moves land in one operation, and the target's empty working-copy
+//!   commit is abandoned
+//! - Handing off to an unknown workspace fails and changes nothing
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn template(ws: &Path, revision: &str, template: &str, home: &Path) -> String {
+    let out = common::run_tandem_in(
+        ws,
+        &["log", "-r", revision, "--no-graph", "-T", template],
+        home,
+    );
+    common::assert_ok(&out, &format!("log {revision}"));
+    common::stdout_str(&out).trim().to_string()
+}
+
+#[test]
+fn slice86_handoff_moves_the_change_to_another_workspace() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let agent = tmp.path().join("agent");
+    let human = tmp.path().join("human");
+    for name in ["agent", "human"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &["init", "--server", &addr, "--workspace", name, name],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+    let human_before = template(&human, "@", "change_id", &home);
+
+    std::fs::write(agent.join("draft.txt"), b"first draft\n").unwrap();
+    let describe = common::run_tandem_in(&agent, &["describe", "-m", "agent draft"], &home);
+    common::assert_ok(&describe, "describe");
+    // Not snapshotted yet: handoff has to pick it up.
+    std::fs::write(agent.join("late.txt"), b"last edit\n").unwrap();
+    let draft = template(&agent, "@", "change_id", &home);
+
+    let missing = common::run_tandem_in(&agent, &["handoff", "nobody"], &home);
+    assert!(!missing.status.success());
+    assert!(common::stderr_str(&missing).contains("no workspace named 'nobody'"));
+    assert_eq!(template(&agent, "@", "change_id", &home), draft);
+
+    let handoff = common::run_tandem_in(&agent, &["handoff", "human"], &home);
+    common::assert_ok(&handoff, "handoff");
+    assert!(
+        common::stderr_str(&handoff).contains("to workspace 'human'"),
+        "{}",
+        common::stderr_str(&handoff)
+    );
+
+    assert_eq!(template(&agent, "human@", "change_id", &home), draft);
+    assert_ne!(template(&agent, "@", "change_id", &home), draft);
+    assert_eq!(
+        template(&agent, "@", "if(empty, \"empty\", \"changed\")", &home),
+        "empty"
+    );
+    assert_eq!(template(&agent, "@-", "change_id", &home), "z".repeat(32));
+    assert!(!agent.join("draft.txt").exists());
+    assert!(!agent.join("late.txt").exists());
+    assert_eq!(
+        template(
+            &agent,
+            &format!("present({human_before})"),
+            "change_id",
+            &home
+        ),
+        "",
+        "the target's empty working-copy commit should be abandoned"
+    );
+    let op = common::run_tandem_in(
+        &agent,
+        &["op", "log", "-n", "1", "--no-graph", "-T", "description"],
+        &home,
+    );
+    common::assert_ok(&op, "op log");
+    assert!(common::stdout_str(&op).contains("hand off working-copy change"));
+
+    let update = common::run_tandem_in(&human, &["workspace", "update-stale"], &home);
+    common::assert_ok(&update, "update-stale in human");
+    assert_eq!(template(&human, "@", "change_id", &home), draft);
+    assert_eq!(
+        std::fs::read(human.join("draft.txt")).unwrap(),
+        b"first draft\n"
+    );
+    assert_eq!(
+        std::fs::read(human.join("late.txt")).unwrap(),
+        b"last edit\n"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,123 @@
+//! Slice 87: advisory per-change locks
+//!
+//! Acceptance criteria:
+//! - `tandem lock` claims the working-copy change for this workspace;
+//!   `tandem lock --list` shows who holds what
+//! - `describe` and `new` on a change another workspace locked print a
+//!   warning, and fail when the lock was taken with `--strict`
+//! - The holder's own commands are never checked
+//! - Only the holder releases a lock, unless `tandem unlock --force`
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn change_id(ws: &Path, revision: &str, home: &Path) -> String {
+    let out = common::run_tandem_in(
+        ws,
+        &["log", "-r", revision, "--no-graph", "-T", "change_id"],
+        home,
+    );
+    common::assert_ok(&out, &format!("log {revision}"));
+    common::stdout_str(&out).trim().to_string()
+}
+
+#[test]
+fn slice87_locked_changes_warn_or_refuse_other_workspaces() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let agent_a = tmp.path().join("agent-a");
+    let agent_b = tmp.path().join("agent-b");
+    for name in ["agent-a", "agent-b"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &["init", "--server", &addr, "--workspace", name, name],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+    let describe = common::run_tandem_in(&agent_a, &["describe", "-m", "auth rework"], &home);
+    common::assert_ok(&describe, "describe in agent-a");
+    let change = change_id(&agent_a, "@", &home);
+
+    let lock = common::run_tandem_in(&agent_a, &["lock", "--ttl", "10m"], &home);
+    common::assert_ok(&lock, "lock");
+    assert!(
+        common::stderr_str(&lock).contains("Locked"),
+        "{}",
+        common::stderr_str(&lock)
+    );
+    let list = common::run_tandem_in(&agent_b, &["lock", "--list"], &home);
+    common::assert_ok(&list, "lock --list");
+    let listed = common::stdout_str(&list);
+    assert!(
+        listed.contains(&change[..12]) && listed.contains("agent-a"),
+        "{listed}"
+    );
+
+    // Soft lock: agent-b is warned but goes ahead.
+    let soft = common::run_tandem_in(
+        &agent_b,
+        &["describe", "-r", "agent-a@", "-m", "auth rework (b)"],
+        &home,
+    );
+    common::assert_ok(&soft, "describe under a soft lock");
+    assert!(
+        common::stderr_str(&soft).contains("is locked by workspace 'agent-a'"),
+        "{}",
+        common::stderr_str(&soft)
+    );
+
+    // The holder renews it as strict; its own describe is not checked.
+    let strict = common::run_tandem_in(&agent_a, &["lock", "--strict"], &home);
+    common::assert_ok(&strict, "lock --strict");
+    let own = common::run_tandem_in(&agent_a, &["describe", "-m", "auth rework"], &home);
+    common::assert_ok(&own, "describe by the holder");
+    assert!(!common::stderr_str(&own).contains("is locked"));
+
+    let refused = common::run_tandem_in(
+        &agent_b,
+        &["describe", "-r", "agent-a@", "-m", "mine now"],
+        &home,
+    );
+    assert!(!refused.status.success());
+    assert!(
+        common::stderr_str(&refused).contains("is locked by workspace 'agent-a'"),
+        "{}",
+        common::stderr_str(&refused)
+    );
+    let refused = common::run_tandem_in(&agent_b, &["new", "agent-a@"], &home);
+    assert!(!refused.status.success());
+    assert_eq!(change_id(&agent_a, "agent-a@", &home), change);
+    let description = common::run_tandem_in(
+        &agent_a,
+        &["log", "-r", "@", "--no-graph", "-T", "description"],
+        &home,
+    );
+    assert_eq!(common::stdout_str(&description).trim(), "auth rework");
+
+    let steal = common::run_tandem_in(&agent_b, &["unlock", "agent-a@"], &home);
+    assert!(!steal.status.success());
+    assert!(common::stderr_str(&steal).contains("--force"));
+
+    let unlock = common::run_tandem_in(&agent_a, &["unlock"], &home);
+    common::assert_ok(&unlock, "unlock");
+    let free = common::run_tandem_in(
+        &agent_b,
+        &["describe", "-r", "agent-a@", "-m", "taken over"],
+        &home,
+    );
+    common::assert_ok(&free, "describe after unlock");
+    assert!(!common::stderr_str(&free).contains("is locked"));
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,90 @@
+//! Slice 88: warnings when two workspaces change the same paths
+//!
+//! Acceptance criteria:
+//! - After `commit` (and `new`, `describe`, `squash`), tandem warns about each other workspace that has
+//!   changed some of the same paths since their working copies diverged,
+//!   naming the workspace and the paths
+//! - Paths only one workspace changed are not mentioned
+//! - Read-only commands print no overlap warning
+//! - The command itself goes through either way
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice88_commit_warns_about_overlapping_paths() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let agent_a = tmp.path().join("agent-a");
+    let agent_b = tmp.path().join("agent-b");
+    for name in ["agent-a", "agent-b"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &["init", "--server", &addr, "--workspace", name, name],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+
+    std::fs::create_dir_all(agent_a.join("src")).unwrap();
+    std::fs::write(agent_a.join("src/auth.rs"), b"fn login() {}\n").unwrap();
+    // Snapshot and publish agent-a's working copy.
+    let status = common::run_tandem_in(&agent_a, &["status"], &home);
+    common::assert_ok(&status, "status in agent-a");
+
+    std::fs::create_dir_all(agent_b.join("src")).unwrap();
+    std::fs::write(agent_b.join("src/auth.rs"), b"fn logout() {}\n").unwrap();
+    std::fs::write(agent_b.join("src/parse.rs"), b"fn parse() {}\n").unwrap();
+
+    // Read-only commands stay quiet.
+    let status = common::run_tandem_in(&agent_b, &["status"], &home);
+    common::assert_ok(&status, "status in agent-b");
+    assert!(
+        !common::stderr_str(&status).contains("also modified"),
+        "{}",
+        common::stderr_str(&status)
+    );
+
+    let describe = common::run_tandem_in(&agent_b, &["describe", "-m", "auth work"], &home);
+    common::assert_ok(&describe, "describe in agent-b");
+    let stderr = common::stderr_str(&describe);
+    assert!(
+        stderr.contains("agent-a also modified src/auth.rs"),
+        "{stderr}"
+    );
+
+    let commit = common::run_tandem_in(&agent_b, &["commit", "-m", "auth work"], &home);
+    common::assert_ok(&commit, "commit in agent-b");
+    let stderr = common::stderr_str(&commit);
+    assert!(
+        stderr.contains("agent-a also modified src/auth.rs"),
+        "{stderr}"
+    );
+    assert!(!stderr.contains("src/parse.rs"), "{stderr}");
+
+    let log = common::run_tandem_in(
+        &agent_b,
+        &["log", "-r", "@-", "--no-graph", "-T", "description"],
+        &home,
+    );
+    common::assert_ok(&log, "log in agent-b");
+    assert!(common::stdout_str(&log).contains("auth work"));
+
+    let commit = common::run_tandem_in(&agent_a, &["commit", "-m", "login"], &home);
+    common::assert_ok(&commit, "commit in agent-a");
+    let stderr = common::stderr_str(&commit);
+    assert!(
+        stderr.contains("agent-b also modified src/auth.rs"),
+        "{stderr}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
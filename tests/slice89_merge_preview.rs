@@ -0,0 +1,81 @@
+//! Slice 89: server-computed merge previews
+//!
+//! Acceptance criteria:
+//! - `tandem preview-merge <a> <b>` says whether the two revisions merge
+//!   cleanly, listing the conflicted paths when they do not
+//! - The second revision defaults to `@`
+//! - Previewing writes nothing: the operation log does not move
+//! - The overlap warning after `commit` says whether the merge would conflict
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn latest_op(ws: &Path, home: &Path) -> String {
+    let out = common::run_tandem_in(
+        ws,
+        &["op", "log", "-n", "1", "--no-graph", "-T", "id"],
+        home,
+    );
+    common::assert_ok(&out, "op log");
+    common::stdout_str(&out).trim().to_string()
+}
+
+#[test]
+fn slice89_preview_merge_reports_conflicted_paths() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let agent_a = tmp.path().join("agent-a");
+    let agent_b = tmp.path().join("agent-b");
+    for name in ["agent-a", "agent-b"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &["init", "--server", &addr, "--workspace", name, name],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+
+    std::fs::write(agent_a.join("notes.txt"), b"from a\n").unwrap();
+    std::fs::write(agent_a.join("a-only.txt"), b"a\n").unwrap();
+    let status = common::run_tandem_in(&agent_a, &["status"], &home);
+    common::assert_ok(&status, "status in agent-a");
+
+    std::fs::write(agent_b.join("b-only.txt"), b"b\n").unwrap();
+    let clean = common::run_tandem_in(&agent_b, &["preview-merge", "agent-a@"], &home);
+    common::assert_ok(&clean, "preview-merge (clean)");
+    assert_eq!(common::stdout_str(&clean).trim(), "The merge is clean");
+
+    std::fs::write(agent_b.join("notes.txt"), b"from b\n").unwrap();
+    let before = latest_op(&agent_b, &home);
+    let conflicted =
+        common::run_tandem_in(&agent_b, &["preview-merge", "agent-a@", "agent-b@"], &home);
+    common::assert_ok(&conflicted, "preview-merge (conflicted)");
+    let stdout = common::stdout_str(&conflicted);
+    assert!(
+        stdout.contains("The merge would conflict in 1 path:"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("  notes.txt"), "{stdout}");
+    assert!(!stdout.contains("only.txt"), "{stdout}");
+    assert_eq!(latest_op(&agent_b, &home), before);
+
+    let commit = common::run_tandem_in(&agent_b, &["commit", "-m", "notes"], &home);
+    common::assert_ok(&commit, "commit in agent-b");
+    let stderr = common::stderr_str(&commit);
+    assert!(
+        stderr.contains("agent-a also modified notes.txt (the merge would conflict)"),
+        "{stderr}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
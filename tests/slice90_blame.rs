@@ -0,0 +1,74 @@
+//! Slice 90: blame across workspaces
+//!
+//! Acceptance criteria:
+//! - `tandem blame <path>` prints each line of the file with the workspace
+//!   whose commit introduced it
+//! - Lines written in different workspaces are attributed to each of them
+//! - `--summary` prints how many lines each workspace wrote
+//! - Blaming a path that does not exist is an error
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice90_blame_names_the_workspace_of_each_line() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let agent_a = tmp.path().join("agent-a");
+    let agent_b = tmp.path().join("agent-b");
+    for name in ["agent-a", "agent-b"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &["init", "--server", &addr, "--workspace", name, name],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+
+    std::fs::write(agent_a.join("plan.md"), b"step one\nstep two\n").unwrap();
+    let commit = common::run_tandem_in(&agent_a, &["commit", "-m", "plan"], &home);
+    common::assert_ok(&commit, "commit in agent-a");
+
+    let new = common::run_tandem_in(&agent_b, &["new", "agent-a@-"], &home);
+    common::assert_ok(&new, "new in agent-b");
+    std::fs::write(agent_b.join("plan.md"), b"step one\nstep two\nstep three\n").unwrap();
+
+    let blame = common::run_tandem_in(&agent_b, &["blame", "plan.md"], &home);
+    common::assert_ok(&blame, "blame");
+    let stdout = common::stdout_str(&blame);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3, "{stdout}");
+    assert!(lines[0].starts_with("agent-a "), "{stdout}");
+    assert!(lines[0].ends_with("1: step one"), "{stdout}");
+    assert!(lines[1].starts_with("agent-a "), "{stdout}");
+    assert!(lines[2].starts_with("agent-b "), "{stdout}");
+    assert!(lines[2].ends_with("3: step three"), "{stdout}");
+
+    let summary = common::run_tandem_in(&agent_b, &["blame", "--summary", "plan.md"], &home);
+    common::assert_ok(&summary, "blame --summary");
+    let stdout = common::stdout_str(&summary);
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 2, "{stdout}");
+    assert!(lines[0].starts_with("agent-a"), "{stdout}");
+    assert!(lines[0].contains(" 2 "), "{stdout}");
+    assert!(lines[1].starts_with("agent-b"), "{stdout}");
+    assert!(lines[1].contains(" 1 "), "{stdout}");
+
+    let missing = common::run_tandem_in(&agent_b, &["blame", "nope.md"], &home);
+    assert!(!missing.status.success());
+    assert!(
+        common::stderr_str(&missing).contains("No such path"),
+        "{}",
+        common::stderr_str(&missing)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,163 @@
+//! Slice 91: op retention
+//!
+//! Acceptance criteria:
+//! - `tandem serve --op-retention <count>` prunes the op log down to the
+//!   newest operations the policy keeps
+//! - The pruned operations' files are deleted from the server's op store
+//! - Commits and bookmarks reachable from the current heads are unaffected
+//! - Workspaces keep working after `tandem workspace update-stale`
+//! - An invalid policy is rejected
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+/// The server's own op log, from its op heads.
+fn op_ids(server_repo: &Path, home: &Path) -> Vec<String> {
+    let out = common::run_tandem_in(
+        server_repo,
+        &[
+            "op",
+            "log",
+            "--no-graph",
+            "--ignore-working-copy",
+            "-T",
+            "id ++ \"\\n\"",
+        ],
+        home,
+    );
+    common::assert_ok(&out, "op log");
+    common::stdout_str(&out)
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Operation files under the server's op store, across its id-prefix
+/// shards.
+fn stored_operations(server_repo: &Path) -> usize {
+    std::fs::read_dir(server_repo.join(".jj/repo/op_store/operations"))
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                std::fs::read_dir(path).unwrap().count()
+            } else {
+                1
+            }
+        })
+        .sum()
+}
+
+#[test]
+fn slice91_op_retention_prunes_old_operations() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let agent_a = tmp.path().join("agent-a");
+    let agent_b = tmp.path().join("agent-b");
+    for name in ["agent-a", "agent-b"] {
+        let init = common::run_tandem_in(
+            tmp.path(),
+            &["init", "--server", &addr, "--workspace", name, name],
+            &home,
+        );
+        common::assert_ok(&init, &format!("init {name}"));
+    }
+    for i in 0..8 {
+        std::fs::write(agent_a.join(format!("file-{i}.txt")), format!("{i}\n")).unwrap();
+        let commit =
+            common::run_tandem_in(&agent_a, &["commit", "-m", &format!("change {i}")], &home);
+        common::assert_ok(&commit, &format!("commit {i}"));
+    }
+    let bookmark =
+        common::run_tandem_in(&agent_a, &["bookmark", "create", "main", "-r", "@-"], &home);
+    common::assert_ok(&bookmark, "bookmark create");
+
+    let before = op_ids(&server_repo, &home);
+    assert!(before.len() > 10, "{before:?}");
+    let stored_before = stored_operations(&server_repo);
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    let bad = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "serve",
+            "--listen",
+            &addr,
+            "--repo",
+            server_repo.to_str().unwrap(),
+            "--op-retention",
+            "3w",
+        ],
+        &home,
+    );
+    assert!(!bad.status.success());
+    assert!(
+        common::stderr_str(&bad).contains("invalid op retention"),
+        "{}",
+        common::stderr_str(&bad)
+    );
+
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--op-retention", "5"], &home);
+    common::wait_for_server(&addr, &mut server);
+
+    let after = op_ids(&server_repo, &home);
+    // The five kept operations, then the root operation.
+    assert_eq!(after.len(), 6, "{after:?}");
+    assert_eq!(after.last(), before.last());
+    assert!(stored_operations(&server_repo) < stored_before);
+    // The head was rewritten too, and its old id points at the new one.
+    assert_ne!(after.first(), before.first());
+    let abandoned: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(server_repo.join(".jj/repo/tandem/abandoned-ops.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        abandoned["operations"][before[0].as_str()].as_str(),
+        Some(after[0].as_str()),
+        "{abandoned}"
+    );
+
+    for ws in [&agent_a, &agent_b] {
+        let update = common::run_tandem_in(ws, &["workspace", "update-stale"], &home);
+        common::assert_ok(&update, "workspace update-stale");
+    }
+    let log = common::run_tandem_in(
+        &agent_b,
+        &["log", "-r", "::main", "--no-graph", "-T", "description"],
+        &home,
+    );
+    common::assert_ok(&log, "log in agent-b");
+    let descriptions = common::stdout_str(&log);
+    for i in 0..8 {
+        assert!(
+            descriptions.contains(&format!("change {i}")),
+            "{descriptions}"
+        );
+    }
+
+    std::fs::write(agent_b.join("after.txt"), b"after\n").unwrap();
+    let commit = common::run_tandem_in(&agent_b, &["commit", "-m", "after pruning"], &home);
+    common::assert_ok(&commit, "commit after pruning");
+    let show = common::run_tandem_in(
+        &agent_a,
+        &["log", "-r", "agent-b@-", "--no-graph", "-T", "description"],
+        &home,
+    );
+    common::assert_ok(&show, "log in agent-a");
+    assert!(common::stdout_str(&show).contains("after pruning"));
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,175 @@
+//! Slice 92: sharded operations directory
+//!
+//! Acceptance criteria:
+//! - With `--shard-operations` the server stores operations under
+//!   `operations/<2 hex>/<rest>`
+//! - A repo with the flat layout (jj's `simple_op_store`) is switched over
+//!   when the server starts with the flag, without moving anything up front
+//! - Operations still in the flat layout resolve by full id and by prefix,
+//!   and move into their shard when read
+//! - Without the flag the server moves every operation back to the flat
+//!   layout and `simple_op_store`
+//! - Workspaces keep committing across both switches
+
+mod common;
+
+use std::path::Path;
+
+use tempfile::TempDir;
+
+fn op_ids(server_repo: &Path, home: &Path) -> Vec<String> {
+    let out = common::run_tandem_in(
+        server_repo,
+        &[
+            "op",
+            "log",
+            "--no-graph",
+            "--ignore-working-copy",
+            "-T",
+            "id ++ \"\\n\"",
+        ],
+        home,
+    );
+    common::assert_ok(&out, "op log");
+    common::stdout_str(&out)
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Move every operation back to the flat layout, as an older server left it.
+fn flatten(op_dir: &Path) {
+    for entry in std::fs::read_dir(op_dir).unwrap() {
+        let shard = entry.unwrap().path();
+        if !shard.is_dir() {
+            continue;
+        }
+        let prefix = shard.file_name().unwrap().to_str().unwrap().to_string();
+        for op in std::fs::read_dir(&shard).unwrap() {
+            let op = op.unwrap();
+            let rest = op.file_name().into_string().unwrap();
+            std::fs::rename(op.path(), op_dir.join(format!("{prefix}{rest}"))).unwrap();
+        }
+        std::fs::remove_dir(&shard).unwrap();
+    }
+}
+
+#[test]
+fn slice92_operations_are_sharded_and_flat_layouts_migrate_lazily() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let op_store = server_repo.join(".jj/repo/op_store");
+    let op_dir = op_store.join("operations");
+    let addr = common::free_addr();
+    let sharded_server =
+        || common::spawn_server_with_args(&server_repo, &addr, &["--shard-operations"], &home);
+    let mut server = sharded_server();
+    common::wait_for_server(&addr, &mut server);
+
+    let ws = tmp.path().join("agent-a");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            "agent-a",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    for i in 0..3 {
+        std::fs::write(ws.join(format!("file-{i}.txt")), format!("{i}\n")).unwrap();
+        let commit = common::run_tandem_in(&ws, &["commit", "-m", &format!("change {i}")], &home);
+        common::assert_ok(&commit, &format!("commit {i}"));
+    }
+
+    assert_eq!(
+        std::fs::read_to_string(op_store.join("type")).unwrap(),
+        "tandem_sharded_op_store"
+    );
+    for entry in std::fs::read_dir(&op_dir).unwrap() {
+        let entry = entry.unwrap();
+        assert!(entry.path().is_dir(), "{:?} is not a shard", entry.path());
+        assert_eq!(entry.file_name().len(), 2, "{:?}", entry.path());
+    }
+    let before = op_ids(&server_repo, &home);
+    assert!(before.len() > 4, "{before:?}");
+
+    let _ = server.kill();
+    let _ = server.wait();
+    flatten(&op_dir);
+    std::fs::write(op_store.join("type"), "simple_op_store").unwrap();
+
+    let mut server = sharded_server();
+    common::wait_for_server(&addr, &mut server);
+    assert_eq!(
+        std::fs::read_to_string(op_store.join("type")).unwrap(),
+        "tandem_sharded_op_store"
+    );
+
+    // The oldest operation after the root is not read at startup.
+    let oldest = &before[before.len() - 2];
+    assert!(op_dir.join(oldest).is_file(), "migrated before it was read");
+    let show = common::run_tandem_in(
+        &server_repo,
+        &[
+            "op",
+            "show",
+            "--ignore-working-copy",
+            "--no-op-diff",
+            "-T",
+            "id",
+            &oldest[..12],
+        ],
+        &home,
+    );
+    common::assert_ok(&show, "op show by prefix");
+    assert!(common::stdout_str(&show).contains(oldest.as_str()));
+    assert!(!op_dir.join(oldest).exists());
+    assert!(op_dir.join(&oldest[..2]).join(&oldest[2..]).is_file());
+
+    assert_eq!(op_ids(&server_repo, &home), before);
+    std::fs::write(ws.join("after.txt"), b"after\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "after the switch"], &home);
+    common::assert_ok(&commit, "commit after the switch");
+    let after = op_ids(&server_repo, &home);
+    assert!(after.len() > before.len(), "{after:?}");
+    assert!(op_dir.join(&after[0][..2]).join(&after[0][2..]).is_file());
+    for id in &after[..after.len() - 1] {
+        assert!(
+            op_dir.join(&id[..2]).join(&id[2..]).is_file(),
+            "op log read {id}, so it is in its shard"
+        );
+    }
+
+    // Serving without the flag goes back to the layout stock jj reads.
+    let _ = server.kill();
+    let _ = server.wait();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    assert_eq!(
+        std::fs::read_to_string(op_store.join("type")).unwrap(),
+        "simple_op_store"
+    );
+    for entry in std::fs::read_dir(&op_dir).unwrap() {
+        let entry = entry.unwrap();
+        assert!(entry.path().is_file(), "{:?} is a shard", entry.path());
+    }
+    assert!(op_dir.join(&after[0]).is_file());
+    assert_eq!(op_ids(&server_repo, &home), after);
+
+    std::fs::write(ws.join("flat.txt"), b"flat\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "back to flat"], &home);
+    common::assert_ok(&commit, "commit after going back");
+    let flat = op_ids(&server_repo, &home);
+    assert!(flat.len() > after.len(), "{flat:?}");
+    assert!(op_dir.join(&flat[0]).is_file());
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
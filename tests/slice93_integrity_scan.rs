@@ -0,0 +1,150 @@
+//! Slice 93: startup integrity scan
+//!
+//! Acceptance criteria:
+//! - `tandem serve --integrity-scan all` moves operations and views that do
+//!   not decode or hash to their id into `op_store/corrupt/`
+//! - Intact operations and views stay where they are
+//! - `tandem server status` reports the scan and what it quarantined
+//! - Workspaces keep working after the scan
+//! - An invalid scan scope is rejected
+
+mod common;
+
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice93_integrity_scan_quarantines_corrupt_files() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let op_store = server_repo.join(".jj/repo/op_store");
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let ws = tmp.path().join("agent-a");
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            "agent-a",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    std::fs::write(ws.join("a.txt"), b"a\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "before the scan"], &home);
+    common::assert_ok(&commit, "commit before the scan");
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    // A torn view write and an operation whose content belongs to another id.
+    let torn_view = "c".repeat(128);
+    std::fs::write(op_store.join("views").join(&torn_view), b"\x0a\xff").unwrap();
+    // Operations are stored flat unless the server shards them.
+    let intact = std::fs::read_dir(op_store.join("operations"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.is_file())
+        .unwrap();
+    let misplaced = "d".repeat(128);
+    let misplaced_path = op_store.join("operations").join(&misplaced);
+    std::fs::copy(&intact, &misplaced_path).unwrap();
+
+    let bad = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "serve",
+            "--listen",
+            &addr,
+            "--repo",
+            server_repo.to_str().unwrap(),
+            "--integrity-scan",
+            "some",
+        ],
+        &home,
+    );
+    assert!(!bad.status.success());
+    assert!(
+        common::stderr_str(&bad).contains("invalid integrity scan"),
+        "{}",
+        common::stderr_str(&bad)
+    );
+
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--integrity-scan", "all", "--control-socket", sock_str],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(10));
+
+    assert!(!op_store.join("views").join(&torn_view).exists());
+    assert!(op_store.join("corrupt/views").join(&torn_view).is_file());
+    assert!(!misplaced_path.exists());
+    assert!(op_store
+        .join("corrupt/operations")
+        .join(&misplaced)
+        .is_file());
+    assert!(intact.is_file());
+
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--json", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "server status --json");
+    let status: serde_json::Value = serde_json::from_str(&common::stdout_str(&status)).unwrap();
+    let integrity = &status["integrity"];
+    assert!(integrity["checked"].as_u64().unwrap() > 2, "{integrity}");
+    assert_eq!(integrity["checked"], integrity["stored"]);
+    let mut quarantined: Vec<(String, String)> = integrity["quarantined"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|q| {
+            (
+                q["kind"].as_str().unwrap().to_string(),
+                q["id"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    quarantined.sort();
+    assert_eq!(
+        quarantined,
+        [
+            ("operation".to_string(), misplaced.clone()),
+            ("view".to_string(), torn_view.clone()),
+        ]
+    );
+
+    let text = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&text, "server status");
+    assert!(
+        common::stdout_str(&text).contains("2 quarantined"),
+        "{}",
+        common::stdout_str(&text)
+    );
+
+    std::fs::write(ws.join("b.txt"), b"b\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "after the scan"], &home);
+    common::assert_ok(&commit, "commit after the scan");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
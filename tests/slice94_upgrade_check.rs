@@ -0,0 +1,107 @@
+//! Slice 94: tandem upgrade-check
+//!
+//! Acceptance criteria:
+//! - Against a server on the same version, no upgrade is needed, and the
+//!   features the server does not offer are listed
+//! - An older server, or a release index naming a newer version, says which
+//!   side to upgrade and why
+//! - `--json` reports versions, the verdict, and missing capabilities
+//! - An unreadable index is a warning, not a failure
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice94_upgrade_check_says_which_side_to_upgrade() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let check = common::run_tandem_in(tmp.path(), &["upgrade-check", "--server", &addr], &home);
+    common::assert_ok(&check, "upgrade-check");
+    let out = common::stdout_str(&check);
+    assert!(out.contains("upgrade: none needed"), "{out}");
+    assert!(out.contains("copy tracking (copyTracking)"), "{out}");
+
+    let missing_index = tmp.path().join("missing.json");
+    let check = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "upgrade-check",
+            "--server",
+            &addr,
+            "--index",
+            missing_index.to_str().unwrap(),
+        ],
+        &home,
+    );
+    common::assert_ok(&check, "upgrade-check with a missing index");
+    assert!(
+        common::stderr_str(&check).contains("could not read the release index"),
+        "{}",
+        common::stderr_str(&check)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    let mut server = common::spawn_server_with_args_and_env(
+        &server_repo,
+        &addr,
+        &[],
+        &[
+            ("TANDEM_TEST_REPO_INFO_TANDEM_VERSION", "0.0.1"),
+            ("TANDEM_TEST_REPO_INFO_CAPABILITIES", "watchHeads"),
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let check = common::run_tandem_in(tmp.path(), &["upgrade-check", "--server", &addr], &home);
+    common::assert_ok(&check, "upgrade-check against an older server");
+    let out = common::stdout_str(&check);
+    assert!(out.contains("upgrade: server"), "{out}");
+    assert!(out.contains("the server runs tandem 0.0.1"), "{out}");
+
+    let index = tmp.path().join("index.json");
+    std::fs::write(
+        &index,
+        r#"{"latest": "99.0.0", "url": "https://example.com/tandem/99.0.0"}"#,
+    )
+    .unwrap();
+    let check = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "upgrade-check",
+            "--server",
+            &addr,
+            "--index",
+            index.to_str().unwrap(),
+            "--json",
+        ],
+        &home,
+    );
+    common::assert_ok(&check, "upgrade-check --json");
+    let report: serde_json::Value = serde_json::from_str(&common::stdout_str(&check)).unwrap();
+    assert_eq!(report["upgrade"], "both", "{report}");
+    assert_eq!(report["serverVersion"], "0.0.1");
+    assert_eq!(report["latestVersion"], "99.0.0");
+    assert_eq!(report["latestUrl"], "https://example.com/tandem/99.0.0");
+    let missing: Vec<&str> = report["missing"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|feature| feature["capability"].as_str().unwrap())
+        .collect();
+    assert!(missing.contains(&"headsHistory"), "{missing:?}");
+    assert!(!missing.contains(&"watchHeads"), "{missing:?}");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
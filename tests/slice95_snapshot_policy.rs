@@ -0,0 +1,139 @@
+//! Slice 95: snapshot exclusions distributed by the server
+//!
+//! Acceptance criteria:
+//! - `tandem serve --snapshot-exclude <pattern>` makes `tandem init` write
+//!   the matching `snapshot.auto-track` into the new workspace's repo config
+//! - New files under excluded directories are not snapshotted; others are
+//! - A workspace created against a server without a policy tracks everything
+//! - A pattern tandem cannot express stops `tandem serve` at startup
+
+mod common;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice95_init_applies_the_servers_snapshot_exclusions() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &[
+            "--snapshot-exclude",
+            "target/",
+            "--snapshot-exclude",
+            "node_modules/",
+        ],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            "agent-a",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init");
+    assert!(
+        common::stderr_str(&init).contains("Snapshot policy from the server"),
+        "{}",
+        common::stderr_str(&init)
+    );
+    let workspace = tmp.path().join("agent-a");
+
+    let config =
+        common::run_tandem_in(&workspace, &["config", "get", "snapshot.auto-track"], &home);
+    common::assert_ok(&config, "config get snapshot.auto-track");
+    assert_eq!(
+        common::stdout_str(&config).trim(),
+        r#"all() ~ (root-glob:"**/target/**" | root-glob:"**/node_modules/**")"#
+    );
+
+    std::fs::create_dir_all(workspace.join("target/debug")).unwrap();
+    std::fs::write(workspace.join("target/debug/app"), b"binary").unwrap();
+    std::fs::create_dir_all(workspace.join("web/node_modules/left-pad")).unwrap();
+    std::fs::write(workspace.join("web/node_modules/left-pad/index.js"), b"pad").unwrap();
+    std::fs::create_dir_all(workspace.join("src")).unwrap();
+    std::fs::write(workspace.join("src/main.rs"), b"fn main() {}\n").unwrap();
+
+    let commit = common::run_tandem_in(&workspace, &["commit", "-m", "build"], &home);
+    common::assert_ok(&commit, "commit");
+    let files = common::run_tandem_in(&workspace, &["file", "list", "-r", "@-"], &home);
+    common::assert_ok(&files, "file list");
+    let files = common::stdout_str(&files);
+    assert!(files.contains("src/main.rs"), "{files}");
+    assert!(!files.contains("target"), "{files}");
+    assert!(!files.contains("node_modules"), "{files}");
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    // Without a policy, init leaves jj's default alone.
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-b",
+            "agent-b",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init without a policy");
+    let workspace = tmp.path().join("agent-b");
+    std::fs::create_dir_all(workspace.join("target")).unwrap();
+    std::fs::write(workspace.join("target/out"), b"built").unwrap();
+    let files = common::run_tandem_in(&workspace, &["file", "list"], &home);
+    common::assert_ok(&files, "file list without a policy");
+    assert!(
+        common::stdout_str(&files).contains("target/out"),
+        "{}",
+        common::stdout_str(&files)
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
+
+#[test]
+fn slice95_serve_rejects_patterns_it_cannot_express() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+
+    let serve = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "serve",
+            "--listen",
+            &addr,
+            "--repo",
+            server_repo.to_str().unwrap(),
+            "--snapshot-exclude",
+            "!keep.txt",
+        ],
+        &home,
+    );
+    assert!(!serve.status.success(), "serve should refuse the pattern");
+    assert!(
+        common::stderr_str(&serve).contains("negated patterns are not supported"),
+        "{}",
+        common::stderr_str(&serve)
+    );
+}
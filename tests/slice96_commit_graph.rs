@@ -0,0 +1,132 @@
+//! Slice 96: bulk commit reads for a new workspace's index
+//!
+//! Acceptance criteria:
+//! - `tandem init` fetches the visible commits in pages (`getCommitGraph`)
+//!   before jj indexes the repo, instead of one `getObject` per commit
+//! - The new workspace sees the whole history
+//! - With a shared `tandem.cache-dir`, the next init only fetches commits the
+//!   previous prefetch did not cover
+
+mod common;
+
+use tempfile::TempDir;
+
+const COMMITS: usize = 12;
+
+fn count(haystack: &str, needle: &str) -> usize {
+    haystack.matches(needle).count()
+}
+
+/// The count init reports prefetching, 0 when it says nothing.
+fn fetched(stderr: &str) -> usize {
+    stderr
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("Fetched ")?
+                .strip_suffix(" commits for the index")?
+                .parse()
+                .ok()
+        })
+        .unwrap_or(0)
+}
+
+#[test]
+fn slice96_init_fetches_the_commit_graph_in_bulk() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let addr = common::free_addr();
+    let mut server = common::spawn_server(&server_repo, &addr);
+    common::wait_for_server(&addr, &mut server);
+
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-a",
+            "agent-a",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init agent-a");
+    let agent_a = tmp.path().join("agent-a");
+    for i in 0..COMMITS {
+        std::fs::write(agent_a.join("history.txt"), format!("line {i}\n")).unwrap();
+        let commit = common::run_tandem_in(&agent_a, &["commit", "-m", &format!("c-{i}")], &home);
+        common::assert_ok(&commit, &format!("commit c-{i}"));
+    }
+
+    // Later inits report their RPCs and share one object cache.
+    let config = home.join(".config/jj/config.toml");
+    let mut toml = std::fs::read_to_string(&config).unwrap();
+    toml.push_str(&format!(
+        "[tandem]\ntiming = true\ncache-dir = {:?}\n",
+        tmp.path().join("shared-cache").to_str().unwrap()
+    ));
+    std::fs::write(&config, toml).unwrap();
+
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-b",
+            "agent-b",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init agent-b");
+    let stderr = common::stderr_str(&init);
+    assert!(stderr.contains("tandem: getCommitGraph"), "{stderr}");
+    // Hidden commits (each `commit` rewrites the working copy) count too.
+    assert!(fetched(&stderr) > COMMITS, "{stderr}");
+    assert!(
+        count(&stderr, "tandem: getObject") < COMMITS,
+        "commits should not be read one at a time:\n{stderr}"
+    );
+
+    let agent_b = tmp.path().join("agent-b");
+    let log = common::run_tandem_in(
+        &agent_b,
+        &["log", "-r", "all()", "-T", "description"],
+        &home,
+    );
+    common::assert_ok(&log, "log in agent-b");
+    assert!(
+        common::stdout_str(&log).contains("c-0"),
+        "{}",
+        common::stdout_str(&log)
+    );
+
+    std::fs::write(agent_a.join("history.txt"), b"one more\n").unwrap();
+    let commit = common::run_tandem_in(&agent_a, &["commit", "-m", "c-last"], &home);
+    common::assert_ok(&commit, "commit c-last");
+
+    let init = common::run_tandem_in(
+        tmp.path(),
+        &[
+            "init",
+            "--server",
+            &addr,
+            "--workspace",
+            "agent-c",
+            "agent-c",
+        ],
+        &home,
+    );
+    common::assert_ok(&init, "init agent-c");
+    let stderr = common::stderr_str(&init);
+    assert!(
+        (1..COMMITS).contains(&fetched(&stderr)),
+        "only commits since agent-b's init should be fetched:\n{stderr}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
@@ -0,0 +1,73 @@
+//! Slice 97: resource usage in server status
+//!
+//! Acceptance criteria:
+//! - `tandem server status --json` reports the server's RSS (on Linux), open
+//!   file descriptors, and repo disk usage split into git objects, op store,
+//!   tandem metadata, and the commit index cache
+//! - The human-readable status shows the same figures
+
+mod common;
+
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice97_status_reports_memory_descriptors_and_disk() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+    let ws = tmp.path().join("ws");
+    std::fs::create_dir_all(&ws).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server =
+        common::spawn_server_with_args(&server_repo, &addr, &["--control-socket", sock_str], &home);
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let init = common::run_tandem_in(&ws, &["init", "--server", &addr, "."], &home);
+    common::assert_ok(&init, "init workspace");
+    std::fs::write(ws.join("a.txt"), b"some content\n").unwrap();
+    let commit = common::run_tandem_in(&ws, &["commit", "-m", "add a"], &home);
+    common::assert_ok(&commit, "commit");
+
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--json", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "server status --json");
+    let report: serde_json::Value = serde_json::from_str(&common::stdout_str(&status)).unwrap();
+    let resources = &report["resources"];
+    assert!(resources["openFds"].as_u64().unwrap() > 0, "{resources}");
+    if cfg!(target_os = "linux") {
+        assert!(resources["rssBytes"].as_u64().unwrap() > 0, "{resources}");
+    }
+    let disk = &resources["disk"];
+    for part in ["gitObjectsBytes", "opStoreBytes", "tandemBytes"] {
+        assert!(disk[part].as_u64().unwrap() > 0, "{part}: {disk}");
+    }
+    assert!(disk["indexCacheBytes"].is_u64(), "{disk}");
+    assert_eq!(disk["measuredSecsAgo"], 0, "{disk}");
+
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "server status");
+    let out = common::stdout_str(&status);
+    assert!(out.contains("open files"), "{out}");
+    assert!(
+        out.contains("Disk:") && out.contains("git objects"),
+        "{out}"
+    );
+    assert!(out.contains("Caches:   commit index"), "{out}");
+
+    let _ = server.kill();
+    let _ = server.wait();
+}
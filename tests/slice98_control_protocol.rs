@@ -0,0 +1,66 @@
+//! Slice 98: versioned control protocol
+//!
+//! Acceptance criteria:
+//! - Every control response line carries `v` and `type`
+//! - Requests without `v` are served as version 1; other versions get an
+//!   `unsupportedVersion` error instead of a guess
+//! - Unknown request types and malformed lines get a `badRequest` error
+//!   rather than a dropped connection
+//! - `tandem server schema` prints the JSON Schema for the protocol
+
+mod common;
+
+use std::time::Duration;
+
+use tempfile::TempDir;
+
+#[test]
+fn slice98_control_lines_are_versioned() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--control-socket", sock.to_str().unwrap()],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let request = |line: &str| -> serde_json::Value {
+        serde_json::from_str(&common::control_request(&sock, line)).unwrap()
+    };
+
+    let status = request(r#"{"type":"status"}"#);
+    assert_eq!(status["v"], 1, "{status}");
+    assert_eq!(status["type"], "status", "{status}");
+    assert_eq!(status["running"], true, "{status}");
+
+    let status = request(r#"{"v":1,"type":"status"}"#);
+    assert_eq!(status["type"], "status", "{status}");
+
+    let refused = request(r#"{"v":2,"type":"status"}"#);
+    assert_eq!(refused["v"], 1, "{refused}");
+    assert_eq!(refused["type"], "error", "{refused}");
+    assert_eq!(refused["code"], "unsupportedVersion", "{refused}");
+
+    let unknown = request(r#"{"type":"reboot"}"#);
+    assert_eq!(unknown["code"], "badRequest", "{unknown}");
+    assert_eq!(unknown["msg"], "unknown request type: reboot", "{unknown}");
+
+    let malformed = request("status please");
+    assert_eq!(malformed["code"], "badRequest", "{malformed}");
+
+    let _ = server.kill();
+    let _ = server.wait();
+
+    let schema = common::run_tandem_in(tmp.path(), &["server", "schema"], &home);
+    common::assert_ok(&schema, "server schema");
+    let schema: serde_json::Value = serde_json::from_str(&common::stdout_str(&schema)).unwrap();
+    assert_eq!(schema["$defs"]["version"]["const"], 1, "{schema}");
+}
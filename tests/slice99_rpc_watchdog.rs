@@ -0,0 +1,102 @@
+//! Slice 99: RPC watchdog
+//!
+//! Acceptance criteria:
+//! - `tandem serve --rpc-timeout <duration>` fails a store call still running
+//!   at the limit with a `tandem rpc timed out` error naming the method
+//! - The connection stays usable: the next call on it is served
+//! - `tandem server status` reports the limit and counts timeouts by method
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use tandem_client::{ObjectKind, TandemClient};
+use tempfile::TempDir;
+
+#[test]
+fn slice99_stuck_calls_time_out_and_are_counted() {
+    let tmp = TempDir::new().unwrap();
+    let home = common::isolated_home(tmp.path());
+    let server_repo = tmp.path().join("server-repo");
+    std::fs::create_dir_all(&server_repo).unwrap();
+
+    let addr = common::free_addr();
+    let sock = common::control_socket_path(tmp.path());
+    let sock_str = sock.to_str().unwrap();
+    let mut server = common::spawn_server_with_args(
+        &server_repo,
+        &addr,
+        &["--control-socket", sock_str, "--rpc-timeout", "1s"],
+        &home,
+    );
+    common::wait_for_server(&addr, &mut server);
+    common::wait_for_socket(&sock, Duration::from_secs(5));
+
+    let faults = |args: &[&str]| {
+        let mut full = vec!["server", "faults"];
+        full.extend_from_slice(args);
+        full.extend_from_slice(&["--control-socket", sock_str]);
+        let out = common::run_tandem_in(tmp.path(), &full, &home);
+        common::assert_ok(&out, "server faults");
+    };
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(async {
+        let client = TandemClient::connect(&addr).await.expect("connect");
+        let id = client
+            .put_object(ObjectKind::File, b"stuck\n".to_vec())
+            .await
+            .expect("put");
+
+        // A getObject held back well past the limit stands in for a hung
+        // filesystem.
+        faults(&["--get-object-delay", "5000"]);
+        let started = Instant::now();
+        let err = client
+            .get_object(ObjectKind::File, &id)
+            .await
+            .expect_err("stuck getObject should time out");
+        assert!(
+            format!("{err:#}").contains("tandem rpc timed out: getObject ran for 1s"),
+            "{err:#}"
+        );
+        assert!(
+            started.elapsed() < Duration::from_secs(4),
+            "{:?}",
+            started.elapsed()
+        );
+
+        faults(&["--off"]);
+        let content = client
+            .get_object(ObjectKind::File, &id)
+            .await
+            .expect("same connection serves the next call");
+        assert_eq!(content, b"stuck\n");
+    });
+
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--json", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "server status --json");
+    let report: serde_json::Value = serde_json::from_str(&common::stdout_str(&status)).unwrap();
+    let watchdog = &report["watchdog"];
+    assert_eq!(watchdog["timeoutSecs"], 1, "{watchdog}");
+    assert_eq!(watchdog["timedOut"]["getObject"], 1, "{watchdog}");
+
+    let status = common::run_tandem_in(
+        tmp.path(),
+        &["server", "status", "--control-socket", sock_str],
+        &home,
+    );
+    common::assert_ok(&status, "server status");
+    let out = common::stdout_str(&status);
+    assert!(
+        out.contains("Watchdog: 1s limit; 1 timed out (getObject 1)"),
+        "{out}"
+    );
+
+    let _ = server.kill();
+    let _ = server.wait();
+}